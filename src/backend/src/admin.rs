@@ -1,26 +1,157 @@
-use crate::memory::{MEM_ADMINS, MM};
+use crate::capsule_store::types::{Page, PaginationOrder as Order};
+use crate::memory::{MEM_ADMINS, MEM_ADMIN_AUDIT, MEM_ADMIN_GRANTS, MM};
 use crate::types::Error;
-use candid::Principal;
+use crate::utils::pagination::paginate_sorted;
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
 use ic_cdk::api::msg_caller;
 use ic_stable_structures::memory_manager::VirtualMemory;
-use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap};
+use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableBTreeMap, Storable};
+use serde::Serialize;
+use std::borrow::Cow;
 use std::cell::RefCell;
 
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 
+/// Default page size for admin listings
+const ADMIN_PAGE_DEFAULT: u32 = 50;
+/// Maximum page size for admin listings
+const ADMIN_PAGE_MAX: u32 = 100;
+/// Oldest audit entries are dropped once the log holds this many
+const ADMIN_AUDIT_MAX: u64 = 10_000;
+
 thread_local! {
     static STABLE_ADMINS: RefCell<StableBTreeMap<Principal, (), Memory>> = RefCell::new(
         StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_ADMINS)))
     );
+
+    // Grant metadata lives in its own region so the MEM_ADMINS layout stays unchanged.
+    // Admins added before grant tracking have no entry here.
+    static STABLE_ADMIN_GRANTS: RefCell<StableBTreeMap<Principal, AdminGrant, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_ADMIN_GRANTS)))
+    );
+
+    // Append-only log of privileged actions, keyed by sequence number
+    static STABLE_ADMIN_AUDIT: RefCell<StableBTreeMap<u64, AdminAuditEntry, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_ADMIN_AUDIT)))
+    );
+}
+
+// ============================================================================
+// ADMIN TYPES
+// ============================================================================
+
+/// Role of an entry in the admin listings
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum AdminRole {
+    SuperAdmin,
+    Admin,
+}
+
+/// Admin listing entry with grant metadata (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct AdminEntry {
+    pub principal: Principal,
+    pub granted_at: u64, // 0 when unknown (hardcoded or pre-tracking admins)
+    pub granted_by: Option<Principal>, // None for bootstrap, hardcoded or pre-tracking admins
+    pub role: AdminRole,
+}
+
+/// Grant metadata captured when an admin is added (stable storage)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct AdminGrant {
+    pub granted_at: u64,
+    pub granted_by: Option<Principal>,
+}
+
+impl Storable for AdminGrant {
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 128,
+        is_fixed_size: false,
+    };
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode AdminGrant"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, grant): (u16, AdminGrant) =
+            Decode!(bytes.as_ref(), (u16, AdminGrant)).expect("Failed to decode AdminGrant");
+        assert_eq!(version, 1, "Unsupported AdminGrant version");
+        grant
+    }
+}
+
+/// Audit log entry for a privileged admin action
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct AdminAuditEntry {
+    pub seq: u64,
+    pub at: u64,
+    pub actor: Principal,
+    pub action: String, // e.g. "abort_session"
+    pub detail: String, // human-readable target description
+}
+
+impl Storable for AdminAuditEntry {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode AdminAuditEntry"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, entry): (u16, AdminAuditEntry) =
+            Decode!(bytes.as_ref(), (u16, AdminAuditEntry))
+                .expect("Failed to decode AdminAuditEntry");
+        assert_eq!(version, 1, "Unsupported AdminAuditEntry version");
+        entry
+    }
+}
+
+/// Build an admin entry from its (optional) grant record
+fn admin_entry(principal: Principal, grant: Option<AdminGrant>) -> AdminEntry {
+    let grant = grant.unwrap_or(AdminGrant {
+        granted_at: 0,
+        granted_by: None,
+    });
+    AdminEntry {
+        principal,
+        granted_at: grant.granted_at,
+        granted_by: grant.granted_by,
+        role: AdminRole::Admin,
+    }
+}
+
+/// Paginate admin entries sorted by principal
+///
+/// Cursor is the text form of the last principal of the previous page (exclusive).
+fn paginate_admin_entries(
+    entries: Vec<AdminEntry>,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<AdminEntry>, Error> {
+    let limit = limit.unwrap_or(ADMIN_PAGE_DEFAULT).clamp(1, ADMIN_PAGE_MAX);
+
+    let after = match cursor {
+        Some(text) => Some(
+            Principal::from_text(&text)
+                .map_err(|_| Error::InvalidArgument(format!("invalid cursor: {}", text)))?,
+        ),
+        None => None,
+    };
+
+    Ok(paginate_sorted(
+        entries,
+        |entry| entry.principal,
+        after,
+        limit,
+        Order::Asc,
+    ))
 }
 
 /// Admin store for managing admin principals
 ///
-/// TODO (Post-MVP): Consider creating an Admin struct instead of just storing principals
-/// - Current: StableBTreeMap<Principal, (), Memory> (just principals)
-/// - Future: StableBTreeMap<Principal, Admin, Memory> (with metadata)
-/// - Benefits: Audit trail, permissions, creation tracking, admin history
-/// - Use cases: When we need admin metadata, permission levels, or audit requirements
+/// Admin principals live in MEM_ADMINS; grant metadata (who/when) lives in a
+/// separate MEM_ADMIN_GRANTS map keyed by the same principal.
 ///
 /// TODO (Post-MVP): Implement decoupled architecture for admin system
 /// - Replace direct stable memory access with AdminStore trait and with_admin_store pattern
@@ -31,8 +162,8 @@ thread_local! {
 pub struct AdminStore;
 
 impl AdminStore {
-    /// Check if principal is an admin (auto-bootstrap first caller)
-    pub fn is_admin(principal: &Principal) -> bool {
+    /// Check if principal is an admin (auto-bootstrap first caller, granted at `now`)
+    pub fn is_admin(principal: &Principal, now: u64) -> bool {
         // Superadmins are always considered admins
         if is_superadmin(principal) {
             return true;
@@ -42,6 +173,15 @@ impl AdminStore {
             // If no admins exist, the first caller becomes admin
             if admins.is_empty() {
                 admins.insert(*principal, ());
+                Self::with_grants_mut(|grants| {
+                    grants.insert(
+                        *principal,
+                        AdminGrant {
+                            granted_at: now,
+                            granted_by: None, // bootstrap
+                        },
+                    );
+                });
                 return true;
             }
 
@@ -78,6 +218,17 @@ impl AdminStore {
             admins.insert(new_admin_principal, ());
         });
 
+        // Capture grant metadata for auditing
+        Self::with_grants_mut(|grants| {
+            grants.insert(
+                new_admin_principal,
+                AdminGrant {
+                    granted_at: ic_cdk::api::time(),
+                    granted_by: Some(caller),
+                },
+            );
+        });
+
         Ok(())
     }
 
@@ -99,6 +250,7 @@ impl AdminStore {
 
         Self::with_admins_mut(|admins| {
             if admins.remove(&admin_principal).is_some() {
+                Self::with_grants_mut(|grants| grants.remove(&admin_principal));
                 Ok(())
             } else {
                 Err(crate::types::Error::NotFound)
@@ -106,17 +258,40 @@ impl AdminStore {
         })
     }
 
-    /// List all admins
-    pub fn list_admins() -> Vec<Principal> {
+    /// List all admin principals (legacy, principals only)
+    pub fn list_admin_principals() -> Vec<Principal> {
         let caller = msg_caller();
 
-        if !Self::is_admin(&caller) {
+        if !Self::is_admin(&caller, ic_cdk::api::time()) {
             return Vec::new();
         }
 
         Self::with_admins(|admins| admins.iter().map(|(k, _)| k).collect())
     }
 
+    /// List admins with grant metadata (paginated by principal)
+    pub fn list_admins(
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> std::result::Result<Page<AdminEntry>, Error> {
+        let caller = msg_caller();
+
+        if !Self::is_admin(&caller, ic_cdk::api::time()) {
+            return Err(Error::Unauthorized);
+        }
+
+        let entries: Vec<AdminEntry> = Self::with_admins(|admins| {
+            Self::with_grants(|grants| {
+                admins
+                    .iter()
+                    .map(|(principal, _)| admin_entry(principal, grants.get(&principal)))
+                    .collect()
+            })
+        });
+
+        paginate_admin_entries(entries, cursor, limit)
+    }
+
     /// Export all admins for upgrade persistence
     pub fn export_admins_for_upgrade() -> Vec<Principal> {
         Self::with_admins(|admins| admins.iter().map(|(k, _)| k).collect())
@@ -154,6 +329,22 @@ impl AdminStore {
     {
         STABLE_ADMINS.with(|admins| f(&mut admins.borrow_mut()))
     }
+
+    /// Direct access to admin grant metadata (read-only)
+    pub fn with_grants<F, R>(f: F) -> R
+    where
+        F: FnOnce(&StableBTreeMap<Principal, AdminGrant, Memory>) -> R,
+    {
+        STABLE_ADMIN_GRANTS.with(|grants| f(&grants.borrow()))
+    }
+
+    /// Direct mutable access to admin grant metadata
+    pub fn with_grants_mut<F, R>(f: F) -> R
+    where
+        F: FnOnce(&mut StableBTreeMap<Principal, AdminGrant, Memory>) -> R,
+    {
+        STABLE_ADMIN_GRANTS.with(|grants| f(&mut grants.borrow_mut()))
+    }
 }
 
 // Hardcoded superadmin principals
@@ -169,9 +360,9 @@ pub fn is_superadmin(principal: &Principal) -> bool {
 }
 
 // Convenience functions that use AdminStore for backward compatibility
-/// Check if principal is an admin (auto-bootstrap first caller)
-pub fn is_admin(principal: &Principal) -> bool {
-    AdminStore::is_admin(principal)
+/// Check if principal is an admin (auto-bootstrap first caller, granted at `now`)
+pub fn is_admin(principal: &Principal, now: u64) -> bool {
+    AdminStore::is_admin(principal, now)
 }
 
 /// Add new admin (only superadmins can add admins)
@@ -184,14 +375,53 @@ pub fn remove_admin(admin_principal: Principal) -> std::result::Result<(), Error
     AdminStore::remove_admin(admin_principal)
 }
 
-/// List all admins
-pub fn list_admins() -> Vec<Principal> {
-    AdminStore::list_admins()
+/// List admins with grant metadata (paginated)
+pub fn list_admins(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<AdminEntry>, Error> {
+    AdminStore::list_admins(cursor, limit)
+}
+
+/// List all admin principals (legacy, principals only)
+pub fn list_admin_principals() -> Vec<Principal> {
+    AdminStore::list_admin_principals()
 }
 
-/// List all superadmins (hardcoded)
+/// Number of stored admins (superadmins are hardcoded and not counted)
+pub fn admin_count() -> u64 {
+    AdminStore::with_admins(|admins| admins.len())
+}
+
+/// List all superadmins (hardcoded, paginated)
+/// Only superadmins can call this function
+pub fn list_superadmins(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<AdminEntry>, Error> {
+    let caller = msg_caller();
+
+    // Only superadmins can list superadmins
+    if !is_superadmin(&caller) {
+        return Err(Error::Unauthorized);
+    }
+
+    let entries = superadmin_principals()
+        .into_iter()
+        .map(|principal| AdminEntry {
+            principal,
+            granted_at: 0,    // hardcoded at build time
+            granted_by: None, // hardcoded at build time
+            role: AdminRole::SuperAdmin,
+        })
+        .collect();
+
+    paginate_admin_entries(entries, cursor, limit)
+}
+
+/// List all superadmin principals (legacy, principals only)
 /// Only superadmins can call this function
-pub fn list_superadmins() -> Vec<Principal> {
+pub fn list_superadmin_principals() -> Vec<Principal> {
     let caller = msg_caller();
 
     // Only superadmins can list superadmins
@@ -199,6 +429,11 @@ pub fn list_superadmins() -> Vec<Principal> {
         return Vec::new();
     }
 
+    superadmin_principals()
+}
+
+/// Parse the hardcoded superadmin principals
+fn superadmin_principals() -> Vec<Principal> {
     SUPERADMIN_PRINCIPALS
         .iter()
         .map(|s| Principal::from_text(s).unwrap_or_else(|_| Principal::anonymous()))
@@ -210,7 +445,174 @@ pub fn export_admins_for_upgrade() -> Vec<Principal> {
     AdminStore::export_admins_for_upgrade()
 }
 
+// ============================================================================
+// AUDIT LOG
+// ============================================================================
+
+/// Append a privileged action to the audit log and return its sequence number
+pub fn record_admin_action(actor: Principal, at: u64, action: &str, detail: String) -> u64 {
+    STABLE_ADMIN_AUDIT.with(|log| {
+        let mut log = log.borrow_mut();
+        let seq = log.last_key_value().map(|(seq, _)| seq + 1).unwrap_or(0);
+        log.insert(
+            seq,
+            AdminAuditEntry {
+                seq,
+                at,
+                actor,
+                action: action.to_string(),
+                detail,
+            },
+        );
+        while log.len() > ADMIN_AUDIT_MAX {
+            log.pop_first();
+        }
+        seq
+    })
+}
+
+/// Page through the audit log, oldest first
+///
+/// Cursor is the sequence number of the last entry of the previous page (exclusive).
+fn audit_page(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<AdminAuditEntry>, Error> {
+    let limit = limit.unwrap_or(ADMIN_PAGE_DEFAULT).clamp(1, ADMIN_PAGE_MAX) as usize;
+    let start = match cursor {
+        Some(text) => text
+            .parse::<u64>()
+            .map_err(|_| Error::InvalidArgument(format!("invalid cursor: {}", text)))?
+            .saturating_add(1),
+        None => 0,
+    };
+
+    STABLE_ADMIN_AUDIT.with(|log| {
+        let log = log.borrow();
+        let mut entries = log.range(start..).map(|(_, entry)| entry);
+        let items: Vec<AdminAuditEntry> = entries.by_ref().take(limit).collect();
+        let next_cursor = match entries.next() {
+            Some(_) => items.last().map(|entry| entry.seq.to_string()),
+            None => None,
+        };
+        Ok(Page { items, next_cursor })
+    })
+}
+
+/// List the admin audit log (admins only, paginated by sequence)
+pub fn list_admin_audit(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<AdminAuditEntry>, Error> {
+    let caller = msg_caller();
+
+    if !is_admin(&caller, ic_cdk::api::time()) {
+        return Err(Error::Unauthorized);
+    }
+
+    audit_page(cursor, limit)
+}
+
 /// Import admins from stable storage after canister upgrade
 pub fn import_admins_from_upgrade(admin_data: Vec<Principal>) {
     AdminStore::import_admins_from_upgrade(admin_data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal(n: u8) -> Principal {
+        Principal::from_slice(&[n; 10])
+    }
+
+    fn entries(count: u8) -> Vec<AdminEntry> {
+        (1..=count)
+            .map(|n| AdminEntry {
+                principal: principal(n),
+                granted_at: n as u64,
+                granted_by: Some(principal(0)),
+                role: AdminRole::Admin,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_admin_grant_storable_roundtrip() {
+        let grant = AdminGrant {
+            granted_at: 1234567890,
+            granted_by: Some(principal(7)),
+        };
+        let decoded = AdminGrant::from_bytes(grant.to_bytes());
+        assert_eq!(decoded, grant);
+    }
+
+    #[test]
+    fn test_admin_entry_without_grant_defaults() {
+        let entry = admin_entry(principal(1), None);
+        assert_eq!(entry.granted_at, 0);
+        assert_eq!(entry.granted_by, None);
+        assert_eq!(entry.role, AdminRole::Admin);
+    }
+
+    #[test]
+    fn test_paginate_admin_entries_walks_all_pages() {
+        let all = entries(5);
+        let mut seen = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let page = paginate_admin_entries(all.clone(), cursor, Some(2)).unwrap();
+            assert!(page.items.len() <= 2);
+            seen.extend(page.items.into_iter().map(|e| e.principal));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        let mut expected: Vec<Principal> = all.iter().map(|e| e.principal).collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_paginate_admin_entries_limit_clamped() {
+        let page = paginate_admin_entries(entries(3), None, Some(0)).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert!(page.next_cursor.is_some());
+    }
+
+    #[test]
+    fn test_admin_audit_log_records_in_order() {
+        let first = record_admin_action(principal(1), 10, "abort_session", "session 1".into());
+        let second = record_admin_action(principal(2), 20, "abort_session", "session 2".into());
+        assert_eq!(second, first + 1);
+
+        let page = audit_page(None, Some(1)).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].actor, principal(1));
+        assert_eq!(page.items[0].action, "abort_session");
+
+        let page = audit_page(page.next_cursor, Some(1)).unwrap();
+        assert_eq!(page.items[0].detail, "session 2");
+        assert_eq!(page.items[0].at, 20);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_bootstrap_admin_grant_uses_given_time() {
+        assert!(AdminStore::is_admin(&principal(3), 42));
+        assert!(!AdminStore::is_admin(&principal(4), 43));
+
+        let grant = AdminStore::with_grants(|grants| grants.get(&principal(3))).unwrap();
+        assert_eq!(grant.granted_at, 42);
+        assert_eq!(grant.granted_by, None);
+    }
+
+    #[test]
+    fn test_paginate_admin_entries_invalid_cursor() {
+        let result = paginate_admin_entries(entries(3), Some("not-a-principal".into()), None);
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+    }
+}
@@ -23,8 +23,9 @@ pub use types::*;
 
 // Re-export only the functions that are actually used
 pub use orchestrator::{
-    create_personal_canister, get_creation_status, get_my_personal_canister_id,
-    get_personal_canister_id,
+    admin_cleanup_orphaned_canister, cancel_personal_canister_creation, create_personal_canister,
+    estimate_migration, get_creation_status, get_my_personal_canister_id, get_personal_canister_id,
+    list_orphaned_canisters, resume_personal_canister_creation, sync_to_personal_canister,
 };
 
 // use crate::types as crate_types; // Will be used when implementing actual functions
@@ -58,14 +59,23 @@ pub fn get_api_version() -> String {
     API_VERSION.to_string()
 }
 
+/// Detailed creation status of the calling user
 pub fn get_detailed_creation_status() -> Option<DetailedCreationStatus> {
-    None
+    let caller = ic_cdk::api::msg_caller();
+    if caller == Principal::anonymous() {
+        return None;
+    }
+    crate::memory::with_migration_state(|state| orchestrator::user_detailed_status(state, caller))
 }
 
+/// Detailed creation status of `user` (admin only)
 pub fn get_user_creation_status(
-    _user: Principal,
+    user: Principal,
 ) -> std::result::Result<Option<DetailedCreationStatus>, Error> {
-    Ok(None)
+    auth::validate_admin_caller().map_err(|_| Error::Unauthorized)?;
+    Ok(crate::memory::with_migration_state(|state| {
+        orchestrator::user_detailed_status(state, user)
+    }))
 }
 
 // Legacy function for backward compatibility
@@ -75,9 +85,13 @@ pub fn get_user_migration_status(
     get_user_creation_status(user)
 }
 
+/// Detailed status of every user's creation (admin only)
 pub fn list_all_creation_states(
 ) -> std::result::Result<Vec<(Principal, DetailedCreationStatus)>, Error> {
-    Ok(vec![])
+    auth::validate_admin_caller().map_err(|_| Error::Unauthorized)?;
+    Ok(crate::memory::with_migration_state(|state| {
+        orchestrator::detailed_statuses(state, None)
+    }))
 }
 
 // Legacy function for backward compatibility
@@ -86,10 +100,14 @@ pub fn list_all_migration_states(
     list_all_creation_states()
 }
 
+/// Detailed status of the creations currently in `status` (admin only)
 pub fn get_creation_states_by_status(
-    _status: CreationStatus,
+    status: CreationStatus,
 ) -> std::result::Result<Vec<(Principal, DetailedCreationStatus)>, Error> {
-    Ok(vec![])
+    auth::validate_admin_caller().map_err(|_| Error::Unauthorized)?;
+    Ok(crate::memory::with_migration_state(|state| {
+        orchestrator::detailed_statuses(state, Some(&status))
+    }))
 }
 
 // Legacy function for backward compatibility
@@ -108,17 +126,42 @@ pub fn clear_migration_state(user: Principal) -> std::result::Result<bool, Error
     clear_creation_state(user)
 }
 
-pub fn set_personal_canister_creation_enabled(_enabled: bool) -> std::result::Result<(), Error> {
+/// Turn personal canister creation on or off (admin only)
+///
+/// Disabling is a hard off: new creations are refused and in-flight ones stop
+/// at their next stage. Use `set_migration_paused` to only hold back new ones.
+pub fn set_personal_canister_creation_enabled(enabled: bool) -> std::result::Result<(), Error> {
+    auth::validate_admin_caller().map_err(|_| Error::Unauthorized)?;
+    crate::memory::with_migration_state_mut(|state| state.creation_config.enabled = enabled);
+    Ok(())
+}
+
+/// Pause or unpause new personal canister creations (admin only)
+///
+/// While paused, new creations are refused with a retry-later message and
+/// in-flight ones keep going.
+pub fn set_migration_paused(paused: bool) -> std::result::Result<(), Error> {
+    auth::validate_admin_caller().map_err(|_| Error::Unauthorized)?;
+    crate::memory::with_migration_state_mut(|state| state.creation_config.paused = Some(paused));
     Ok(())
 }
 
+/// Creation stats, including whether creation is paused (admin only)
 pub fn get_personal_canister_creation_stats(
 ) -> std::result::Result<PersonalCanisterCreationStats, Error> {
-    Ok(PersonalCanisterCreationStats::default())
+    auth::validate_admin_caller().map_err(|_| Error::Unauthorized)?;
+    Ok(crate::memory::with_migration_state(|state| {
+        PersonalCanisterCreationStats {
+            paused: Some(state.creation_config.paused.unwrap_or(false)),
+            ..state.creation_stats.clone()
+        }
+    }))
 }
 
 pub fn is_personal_canister_creation_enabled() -> std::result::Result<bool, Error> {
-    Ok(false)
+    Ok(crate::memory::with_migration_state(|state| {
+        state.creation_config.enabled
+    }))
 }
 
 // Legacy function for backward compatibility
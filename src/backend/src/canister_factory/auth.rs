@@ -29,8 +29,8 @@ pub fn ensure_owner(caller: Principal) -> Result<(), String> {
 
 /// Ensure the caller is an admin (can perform admin-only operations)
 /// This function checks if the caller is either a superadmin or a regular admin
-pub fn ensure_admin(caller: Principal) -> Result<(), String> {
-    if crate::admin::is_admin(&caller) {
+pub fn ensure_admin(caller: Principal, now: u64) -> Result<(), String> {
+    if crate::admin::is_admin(&caller, now) {
         Ok(())
     } else {
         Err(format!("Access denied: Principal {caller} is not an admin"))
@@ -64,7 +64,7 @@ pub fn validate_admin_caller() -> Result<Principal, String> {
     }
 
     // Ensure the caller is an admin
-    ensure_admin(caller)?;
+    ensure_admin(caller, ic_cdk::api::time())?;
 
     Ok(caller)
 }
@@ -180,6 +180,14 @@ mod tests {
             bound_to_neon: false,
             inline_bytes_used: 0,
             hosting_preferences: HostingPreferences::default(),
+            created_by: None,
+            role_templates: None,
+            blob_bytes_quota: None,
+            blob_bytes_used: None,
+            tags: None,
+            access_entries: None,
+            default_memory_access: None,
+            public_memory_default_allowed: None,
         }
     }
 
@@ -311,7 +319,7 @@ mod tests {
         setup_test_admins();
 
         let admin1 = test_principal(10);
-        let result = ensure_admin(admin1);
+        let result = ensure_admin(admin1, 1_000);
 
         assert!(result.is_ok(), "Valid admin should pass admin check");
 
@@ -324,7 +332,7 @@ mod tests {
         setup_test_admins();
 
         let non_admin = test_principal(50);
-        let result = ensure_admin(non_admin);
+        let result = ensure_admin(non_admin, 1_000);
 
         assert!(result.is_err(), "Non-admin should fail admin check");
         assert!(result.unwrap_err().contains("is not an admin"));
@@ -340,7 +348,7 @@ mod tests {
         let superadmin =
             Principal::from_text("otzfv-jscof-niinw-gtloq-25uz3-pglpg-u3kug-besf3-rzlbd-ylrmp-5ae")
                 .unwrap();
-        let result = ensure_admin(superadmin);
+        let result = ensure_admin(superadmin, 1_000);
 
         assert!(result.is_ok(), "Superadmin should pass admin check");
 
@@ -353,7 +361,7 @@ mod tests {
         setup_test_admins();
 
         let anonymous = Principal::anonymous();
-        let result = ensure_admin(anonymous);
+        let result = ensure_admin(anonymous, 1_000);
 
         assert!(result.is_err(), "Anonymous caller should fail admin check");
         assert!(result.unwrap_err().contains("is not an admin"));
@@ -594,21 +602,21 @@ mod tests {
 
         // Admin checks
         assert!(
-            ensure_admin(admin1).is_ok(),
+            ensure_admin(admin1, 1_000).is_ok(),
             "Admin should pass admin check"
         );
         assert!(
-            ensure_admin(non_admin).is_err(),
+            ensure_admin(non_admin, 1_000).is_err(),
             "Non-admin should fail admin check"
         );
         assert!(
-            ensure_admin(anonymous).is_err(),
+            ensure_admin(anonymous, 1_000).is_err(),
             "Anonymous should fail admin check"
         );
 
         // Cross-validation: admin who is not owner
         assert!(
-            ensure_admin(admin1).is_ok(),
+            ensure_admin(admin1, 1_000).is_ok(),
             "Admin should pass admin check"
         );
         assert!(
@@ -629,7 +637,7 @@ mod tests {
 
         // First caller becomes admin due to auto-bootstrap
         assert!(
-            ensure_admin(user).is_ok(),
+            ensure_admin(user, 1_000).is_ok(),
             "First caller should become admin"
         );
 
@@ -1,6 +1,12 @@
 use crate::canister_factory::types::*;
 use candid::Principal;
 
+/// Estimated cycles to push one byte of capsule data into a personal canister
+pub const IMPORT_CYCLES_PER_BYTE: u128 = 2_000;
+
+/// Estimated fixed cost of one import chunk call
+pub const IMPORT_CYCLES_PER_CHUNK: u128 = 5_000_000;
+
 /// Check if factory has sufficient cycles in reserve for the required amount
 /// This is a preflight check that should be called before attempting operations
 pub fn preflight_cycles_reserve(required_cycles: u128) -> Result<(), String> {
@@ -256,3 +262,11 @@ pub fn get_default_canister_cycles() -> u128 {
     // This should be sufficient for initial setup and some operations
     2_000_000_000_000
 }
+
+/// Estimated cycles for a full migration of `total_bytes` in `chunk_count` chunks:
+/// the funding sent with create + install plus the cost of the import calls
+pub fn estimate_migration_cycles(total_bytes: u64, chunk_count: u64) -> u128 {
+    get_default_canister_cycles()
+        + IMPORT_CYCLES_PER_BYTE * total_bytes as u128
+        + IMPORT_CYCLES_PER_CHUNK * chunk_count as u128
+}
@@ -89,6 +89,38 @@ pub fn export_user_capsule_data(user: Principal) -> Result<ExportData, String> {
     Ok(export_data)
 }
 
+/// Fixed time budget for the create, install and verify stages, in seconds
+const MIGRATION_BASE_SECONDS: u64 = 30;
+
+/// Estimated time to transfer and commit one import chunk, in seconds
+const MIGRATION_SECONDS_PER_CHUNK: u64 = 2;
+
+/// Estimate the size, cycles and time of migrating `capsule` (dry run)
+pub fn estimate_capsule_migration(capsule: &Capsule, max_chunk_size: u64) -> MigrationEstimate {
+    let capsule_bytes = crate::capsule::util::calculate_capsule_size(capsule);
+    let blob_bytes: u64 = capsule
+        .memories
+        .values()
+        .flat_map(|memory| &memory.blob_internal_assets)
+        .map(|asset| asset.blob_ref.len)
+        .sum();
+
+    let total_bytes = capsule_bytes + blob_bytes;
+    let chunk_count = total_bytes.div_ceil(max_chunk_size.max(1));
+
+    MigrationEstimate {
+        memory_count: capsule.memories.len() as u64,
+        capsule_bytes,
+        blob_bytes,
+        chunk_count,
+        estimated_cycles: crate::canister_factory::cycles::estimate_migration_cycles(
+            total_bytes,
+            chunk_count,
+        ),
+        estimated_seconds: MIGRATION_BASE_SECONDS + chunk_count * MIGRATION_SECONDS_PER_CHUNK,
+    }
+}
+
 /// Calculate the approximate size of exported data in bytes
 /// This provides an estimate for monitoring and validation purposes
 fn calculate_export_data_size(
@@ -561,6 +593,14 @@ mod tests {
             bound_to_neon: false, // Default to not bound to Neon
             inline_bytes_used: 0,
             hosting_preferences: HostingPreferences::default(),
+            created_by: None,
+            role_templates: None,
+            blob_bytes_quota: None,
+            blob_bytes_used: None,
+            tags: None,
+            access_entries: None,
+            default_memory_access: None,
+            public_memory_default_allowed: None,
         }
     }
 
@@ -638,8 +678,12 @@ mod tests {
                     language: None,
                     word_count: None,
                 }),
+                order: None,
             }],
             blob_external_assets: vec![],
+            locked: None,
+            pending: None,
+            expires_at: None,
         }
     }
 
@@ -823,6 +867,30 @@ mod tests {
         assert!(size >= 2700, "Size should include memory data: {}", size);
     }
 
+    #[test]
+    fn test_estimate_capsule_migration_scales_with_size() {
+        let (_, small) = setup_test_capsule_with_data();
+        let mut large = small.clone();
+        for i in 0..10 {
+            let id = format!("extra{}", i);
+            let memory =
+                create_test_memory(&id, "extra.jpg", MemoryType::Image, "image/jpeg", 4096);
+            large.memories.insert(id, memory);
+        }
+
+        let small_estimate = estimate_capsule_migration(&small, 1024);
+        let large_estimate = estimate_capsule_migration(&large, 1024);
+
+        assert_eq!(small_estimate.memory_count, 2);
+        assert_eq!(small_estimate.blob_bytes, 200);
+        assert_eq!(large_estimate.memory_count, 12);
+        assert_eq!(large_estimate.blob_bytes, 1200);
+        assert!(large_estimate.capsule_bytes > small_estimate.capsule_bytes);
+        assert!(large_estimate.chunk_count > small_estimate.chunk_count);
+        assert!(large_estimate.estimated_cycles > small_estimate.estimated_cycles);
+        assert!(large_estimate.estimated_seconds > small_estimate.estimated_seconds);
+    }
+
     #[test]
     fn test_generate_export_manifest() {
         let (_user, capsule) = setup_test_capsule_with_data();
@@ -18,41 +18,38 @@ pub fn begin_import() -> Result<ImportSessionResponse, String> {
         });
     }
 
-    // Generate unique session ID
-    let session_id = generate_session_id(caller);
     let now = ic_cdk::api::time();
 
-    // Create new import session
-    let session = ImportSession {
-        session_id: session_id.clone(),
-        user: caller,
-        created_at: now,
-        last_activity_at: now,
-        total_expected_size: 0,
-        total_received_size: 0,
-        memories_in_progress: HashMap::new(),
-        completed_memories: HashMap::new(),
-        import_manifest: None,
-        status: ImportSessionStatus::Active,
-    };
-
     // Store the session
-    crate::memory::with_migration_state_mut(|state| {
+    let session_id = crate::memory::with_migration_state_mut(|state| {
         // Clean up expired sessions before creating new one
         cleanup_expired_sessions_internal(state);
 
-        // Check if user already has an active session
-        let existing_active = state
-            .import_sessions
-            .values()
-            .any(|s| s.user == caller && s.status == ImportSessionStatus::Active);
-
-        if existing_active {
-            return Err("User already has an active import session".to_string());
-        }
+        // Check the user's active sessions against the configured limit
+        check_session_limit(state, caller)?;
+
+        // Generate unique session ID; parallel sessions can start in the same round
+        let session_id = (0u32..)
+            .map(|attempt| generate_session_id(caller, now, attempt))
+            .find(|id| !state.import_sessions.contains_key(id))
+            .expect("session id candidates are unbounded");
+
+        // Create new import session
+        let session = ImportSession {
+            session_id: session_id.clone(),
+            user: caller,
+            created_at: now,
+            last_activity_at: now,
+            total_expected_size: 0,
+            total_received_size: 0,
+            memories_in_progress: HashMap::new(),
+            completed_memories: HashMap::new(),
+            import_manifest: None,
+            status: ImportSessionStatus::Active,
+        };
 
         state.import_sessions.insert(session_id.clone(), session);
-        Ok(())
+        Ok::<String, String>(session_id)
     })?;
 
     ic_cdk::println!("Created import session {} for user {}", session_id, caller);
@@ -86,6 +83,9 @@ pub fn put_memory_chunk(
     }
 
     crate::memory::with_migration_state_mut(|state| {
+        // Size limit applies to all of the caller's active sessions combined
+        let size_check = check_combined_import_size(state, caller, bytes.len() as u64);
+
         // Get import configuration
         let config = &state.import_config;
 
@@ -138,8 +138,7 @@ pub fn put_memory_chunk(
 
         // Check session timeout
         let now = ic_cdk::api::time();
-        let session_age = (now - session.last_activity_at) / 1_000_000_000; // Convert to seconds
-        if session_age > config.session_timeout_seconds {
+        if session_timed_out(session, config, now) {
             session.status = ImportSessionStatus::Expired;
             return Ok(ChunkUploadResponse {
                 success: false,
@@ -150,14 +149,10 @@ pub fn put_memory_chunk(
         }
 
         // Validate total import size
-        let new_total_size = session.total_received_size + bytes.len() as u64;
-        if new_total_size > config.max_total_import_size {
+        if let Err(message) = size_check {
             return Ok(ChunkUploadResponse {
                 success: false,
-                message: format!(
-                    "Total import size would exceed maximum allowed size {}",
-                    config.max_total_import_size
-                ),
+                message,
                 received_size: session.total_received_size,
                 total_expected_size: session.total_expected_size,
             });
@@ -248,6 +243,16 @@ pub fn commit_memory(
         });
     }
 
+    // Every chunk must have a checksum, so a truncated manifest can't pass partially verified
+    if let Err(message) = validate_memory_manifest(&manifest) {
+        return Ok(MemoryCommitResponse {
+            success: false,
+            message,
+            memory_id: manifest.memory_id.clone(),
+            assembled_size: 0,
+        });
+    }
+
     crate::memory::with_migration_state_mut(|state| {
         // Get and validate session
         let session = match state.import_sessions.get_mut(&session_id) {
@@ -295,6 +300,18 @@ pub fn commit_memory(
             }
         };
 
+        // The manifest must agree with the size the memory's metadata declares
+        if let Err(message) =
+            check_manifest_against_metadata(&manifest, memory_state.memory_metadata.as_ref())
+        {
+            return Ok(MemoryCommitResponse {
+                success: false,
+                message,
+                memory_id: manifest.memory_id.clone(),
+                assembled_size: 0,
+            });
+        }
+
         // Validate chunk count
         if memory_state.received_chunks.len() as u32 != manifest.total_chunks {
             return Ok(MemoryCommitResponse {
@@ -328,19 +345,17 @@ pub fn commit_memory(
             match memory_state.received_chunks.get(&chunk_index) {
                 Some(chunk) => {
                     // Validate chunk checksum against manifest
-                    if chunk_index < manifest.chunk_checksums.len() as u32 {
-                        let expected_checksum = &manifest.chunk_checksums[chunk_index as usize];
-                        if chunk.sha256 != *expected_checksum {
-                            return Ok(MemoryCommitResponse {
-                                success: false,
-                                message: format!(
-                                    "Chunk {} checksum mismatch for memory {}",
-                                    chunk_index, manifest.memory_id
-                                ),
-                                memory_id: manifest.memory_id.clone(),
-                                assembled_size: 0,
-                            });
-                        }
+                    let expected_checksum = &manifest.chunk_checksums[chunk_index as usize];
+                    if chunk.sha256 != *expected_checksum {
+                        return Ok(MemoryCommitResponse {
+                            success: false,
+                            message: format!(
+                                "Chunk {} checksum mismatch for memory {}",
+                                chunk_index, manifest.memory_id
+                            ),
+                            memory_id: manifest.memory_id.clone(),
+                            assembled_size: 0,
+                        });
                     }
                     assembled_data.extend_from_slice(&chunk.data);
                 }
@@ -405,120 +420,335 @@ pub fn commit_memory(
     })
 }
 
+/// Reject a memory manifest whose checksum list doesn't cover exactly `total_chunks` chunks
+pub fn validate_memory_manifest(manifest: &MemoryManifest) -> Result<(), String> {
+    if manifest.chunk_checksums.len() != manifest.total_chunks as usize {
+        return Err(format!(
+            "Manifest for memory {} lists {} chunk checksums for {} chunks",
+            manifest.memory_id,
+            manifest.chunk_checksums.len(),
+            manifest.total_chunks
+        ));
+    }
+    Ok(())
+}
+
+/// Reject a manifest whose `total_size` disagrees with the size declared in the memory's metadata
+///
+/// A declared size of 0 means the client did not fill it in and is not checked.
+pub fn check_manifest_against_metadata(
+    manifest: &MemoryManifest,
+    metadata: Option<&types::Memory>,
+) -> Result<(), String> {
+    let declared = match metadata {
+        Some(memory) if memory.metadata.total_size > 0 => memory.metadata.total_size,
+        _ => return Ok(()),
+    };
+    if declared != manifest.total_size {
+        return Err(format!(
+            "Manifest for memory {} declares {} bytes but its metadata declares {}",
+            manifest.memory_id, manifest.total_size, declared
+        ));
+    }
+    Ok(())
+}
+
 /// Finalize the import session after all memories have been committed
-/// This function completes the import process and makes the data available
-pub fn finalize_import(session_id: String) -> Result<ImportFinalizationResponse, String> {
+/// This function completes the import process and makes the data available.
+/// With `allow_partial`, memories still in progress are dropped and reported as
+/// skipped instead of failing the finalize.
+pub fn finalize_import(
+    session_id: String,
+    allow_partial: bool,
+) -> Result<ImportFinalizationResponse, String> {
     let caller = ic_cdk::api::msg_caller();
 
     // Reject anonymous callers
     if caller == Principal::anonymous() {
-        return Ok(ImportFinalizationResponse {
-            success: false,
-            message: "Anonymous callers cannot finalize imports".to_string(),
-            total_memories_imported: 0,
-            total_size_imported: 0,
-        });
+        return Ok(finalization_failure(
+            "Anonymous callers cannot finalize imports".to_string(),
+        ));
     }
 
+    let now = ic_cdk::api::time();
     crate::memory::with_migration_state_mut(|state| {
-        // Get and validate session
-        let session = match state.import_sessions.get_mut(&session_id) {
-            Some(s) => s,
-            None => {
-                return Ok(ImportFinalizationResponse {
-                    success: false,
-                    message: "Import session not found".to_string(),
-                    total_memories_imported: 0,
-                    total_size_imported: 0,
-                })
-            }
-        };
+        Ok(finalize_session(
+            state,
+            caller,
+            &session_id,
+            allow_partial,
+            now,
+        ))
+    })
+}
 
-        // Validate session ownership
-        if session.user != caller {
-            return Ok(ImportFinalizationResponse {
-                success: false,
-                message: "Access denied: session belongs to different user".to_string(),
-                total_memories_imported: 0,
-                total_size_imported: 0,
-            });
-        }
+/// Finalize `session_id` on behalf of `caller`
+pub fn finalize_session(
+    state: &mut PersonalCanisterCreationStateData,
+    caller: Principal,
+    session_id: &str,
+    allow_partial: bool,
+    now: u64,
+) -> ImportFinalizationResponse {
+    // Get and validate session
+    let session = match state.import_sessions.get_mut(session_id) {
+        Some(s) => s,
+        None => return finalization_failure("Import session not found".to_string()),
+    };
 
-        // Check session status
-        if session.status != ImportSessionStatus::Active {
-            return Ok(ImportFinalizationResponse {
-                success: false,
-                message: format!("Session is not active (status: {:?})", session.status),
-                total_memories_imported: 0,
-                total_size_imported: 0,
-            });
-        }
+    // Validate session ownership
+    if session.user != caller {
+        return finalization_failure(
+            "Access denied: session belongs to different user".to_string(),
+        );
+    }
 
-        // Check that all memories in progress have been committed
-        if !session.memories_in_progress.is_empty() {
-            return Ok(ImportFinalizationResponse {
-                success: false,
-                message: format!(
-                    "Cannot finalize: {} memories still in progress",
-                    session.memories_in_progress.len()
-                ),
-                total_memories_imported: 0,
-                total_size_imported: 0,
-            });
+    // Check session status
+    if session.status != ImportSessionStatus::Active {
+        return finalization_failure(format!(
+            "Session is not active (status: {:?})",
+            session.status
+        ));
+    }
+
+    // Memories still in progress are only dropped when the caller asked for a partial import
+    let mut pending: Vec<String> = session.memories_in_progress.keys().cloned().collect();
+    pending.sort();
+    if !pending.is_empty() && !allow_partial {
+        return finalization_failure(format!(
+            "Cannot finalize: {} memories still in progress: {}",
+            pending.len(),
+            pending.join(", ")
+        ));
+    }
+    let skipped_bytes: u64 = session
+        .memories_in_progress
+        .drain()
+        .map(|(_, memory_state)| memory_state.received_size)
+        .sum();
+    session.total_received_size = session.total_received_size.saturating_sub(skipped_bytes);
+
+    // Update session status
+    session.status = ImportSessionStatus::Finalizing;
+    session.last_activity_at = now;
+
+    let total_memories = session.completed_memories.len() as u32;
+    let total_size = session.total_received_size;
+
+    // Perform final validation if manifest was provided
+    if let Some(ref manifest) = session.import_manifest {
+        if let Err(e) = validate_import_against_manifest(session, manifest, allow_partial) {
+            session.status = ImportSessionStatus::Failed;
+            return finalization_failure(format!("Import validation failed: {e}"));
         }
+    }
 
-        // Update session status
-        session.status = ImportSessionStatus::Finalizing;
-        session.last_activity_at = ic_cdk::api::time();
+    // Mark session as completed
+    session.status = ImportSessionStatus::Completed;
 
-        let total_memories = session.completed_memories.len() as u32;
-        let total_size = session.total_received_size;
+    ic_cdk::println!(
+        "Successfully finalized import session {} for user {}: {} memories, {} bytes, {} skipped",
+        session_id,
+        caller,
+        total_memories,
+        total_size,
+        pending.len()
+    );
 
-        // Perform final validation if manifest was provided
-        if let Some(ref manifest) = session.import_manifest {
-            if let Err(e) = validate_import_against_manifest(session, manifest) {
-                session.status = ImportSessionStatus::Failed;
-                return Ok(ImportFinalizationResponse {
-                    success: false,
-                    message: format!("Import validation failed: {e}"),
-                    total_memories_imported: 0,
-                    total_size_imported: 0,
-                });
-            }
-        }
+    ImportFinalizationResponse {
+        success: true,
+        message: if pending.is_empty() {
+            format!("Import finalized successfully: {total_memories} memories imported")
+        } else {
+            format!(
+                "Import finalized partially: {total_memories} memories imported, {} skipped",
+                pending.len()
+            )
+        },
+        total_memories_imported: total_memories,
+        total_size_imported: total_size,
+        skipped_memories: pending,
+    }
+}
 
-        // Mark session as completed
-        session.status = ImportSessionStatus::Completed;
+fn finalization_failure(message: String) -> ImportFinalizationResponse {
+    ImportFinalizationResponse {
+        success: false,
+        message,
+        total_memories_imported: 0,
+        total_size_imported: 0,
+        skipped_memories: Vec::new(),
+    }
+}
 
-        ic_cdk::println!(
-            "Successfully finalized import session {} for user {}: {} memories, {} bytes",
-            session_id,
-            caller,
-            total_memories,
-            total_size
-        );
+/// Keep the caller's import session alive without uploading a chunk
+pub fn import_heartbeat(session_id: String) -> Result<(), types::Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if caller == Principal::anonymous() {
+        return Err(types::Error::Unauthorized);
+    }
 
-        Ok(ImportFinalizationResponse {
-            success: true,
-            message: format!("Import finalized successfully: {total_memories} memories imported"),
-            total_memories_imported: total_memories,
-            total_size_imported: total_size,
-        })
+    let now = ic_cdk::api::time();
+    crate::memory::with_migration_state_mut(|state| {
+        heartbeat_session(state, caller, &session_id, now)
     })
 }
 
+/// Refresh `last_activity_at` of an active session owned by `caller`
+pub fn heartbeat_session(
+    state: &mut PersonalCanisterCreationStateData,
+    caller: Principal,
+    session_id: &str,
+    now: u64,
+) -> Result<(), types::Error> {
+    let config = &state.import_config;
+    let session = state
+        .import_sessions
+        .get_mut(session_id)
+        .ok_or(types::Error::NotFound)?;
+
+    if session.user != caller {
+        return Err(types::Error::Unauthorized);
+    }
+    if session.status != ImportSessionStatus::Active {
+        return Err(types::Error::Conflict(format!(
+            "Session is not active (status: {:?})",
+            session.status
+        )));
+    }
+    // A heartbeat can't revive a session that already timed out
+    if session_timed_out(session, config, now) {
+        session.status = ImportSessionStatus::Expired;
+        return Err(types::Error::Conflict("Session has expired".to_string()));
+    }
+
+    session.last_activity_at = now;
+    Ok(())
+}
+
+/// Whether `session` has been idle for longer than the session timeout
+pub fn session_timed_out(session: &ImportSession, config: &ImportConfig, now: u64) -> bool {
+    let idle_seconds = now.saturating_sub(session.last_activity_at) / 1_000_000_000;
+    idle_seconds > config.session_timeout_seconds
+}
+
 /// Clean up expired import sessions
 pub fn cleanup_expired_sessions() -> u32 {
     crate::memory::with_migration_state_mut(cleanup_expired_sessions_internal)
 }
 
+/// IDs of `user`'s active import sessions, sorted
+pub fn active_session_ids(
+    state: &PersonalCanisterCreationStateData,
+    user: Principal,
+) -> Vec<String> {
+    let mut ids: Vec<String> = state
+        .import_sessions
+        .values()
+        .filter(|s| s.user == user && s.status == ImportSessionStatus::Active)
+        .map(|s| s.session_id.clone())
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Reject a new session once `user` holds the configured number of active ones
+pub fn check_session_limit(
+    state: &PersonalCanisterCreationStateData,
+    user: Principal,
+) -> Result<(), String> {
+    let limit = state.import_config.active_session_limit();
+    let active = active_session_ids(state, user).len() as u32;
+    if active >= limit {
+        return Err(format!(
+            "User already has an active import session ({active} active, max {limit})"
+        ));
+    }
+    Ok(())
+}
+
+/// Bytes received so far across all of `user`'s active sessions
+pub fn combined_received_size(state: &PersonalCanisterCreationStateData, user: Principal) -> u64 {
+    state
+        .import_sessions
+        .values()
+        .filter(|s| s.user == user && s.status == ImportSessionStatus::Active)
+        .map(|s| s.total_received_size)
+        .sum()
+}
+
+/// Reject `incoming` bytes that would take `user`'s active sessions past the total size limit
+pub fn check_combined_import_size(
+    state: &PersonalCanisterCreationStateData,
+    user: Principal,
+    incoming: u64,
+) -> Result<(), String> {
+    let max = state.import_config.max_total_import_size;
+    if combined_received_size(state, user).saturating_add(incoming) > max {
+        return Err(format!(
+            "Total import size across active sessions would exceed maximum allowed size {max}"
+        ));
+    }
+    Ok(())
+}
+
+/// Active import session IDs of the caller
+pub fn my_import_sessions() -> Vec<String> {
+    let caller = ic_cdk::api::msg_caller();
+    if caller == Principal::anonymous() {
+        return Vec::new();
+    }
+    crate::memory::with_migration_state(|state| active_session_ids(state, caller))
+}
+
+/// Progress of one of the caller's import sessions
+pub fn import_progress(session_id: String) -> Result<ImportSessionProgress, types::Error> {
+    let caller = ic_cdk::api::msg_caller();
+    crate::memory::with_migration_state(|state| session_progress(state, caller, &session_id))
+}
+
+/// Progress of `session_id`, which must belong to `caller`
+pub fn session_progress(
+    state: &PersonalCanisterCreationStateData,
+    caller: Principal,
+    session_id: &str,
+) -> Result<ImportSessionProgress, types::Error> {
+    let session = state
+        .import_sessions
+        .get(session_id)
+        .ok_or(types::Error::NotFound)?;
+    if session.user != caller {
+        return Err(types::Error::Unauthorized);
+    }
+
+    let mut in_progress_memories: Vec<MemoryChunkProgress> = session
+        .memories_in_progress
+        .values()
+        .map(|memory| MemoryChunkProgress {
+            memory_id: memory.memory_id.clone(),
+            received_chunks: memory.received_chunks.len() as u32,
+            expected_chunks: memory.expected_chunks,
+            received_size: memory.received_size,
+        })
+        .collect();
+    in_progress_memories.sort_by(|a, b| a.memory_id.cmp(&b.memory_id));
+
+    Ok(ImportSessionProgress {
+        session_id: session.session_id.clone(),
+        status: session.status.clone(),
+        completed_memories: session.completed_memories.len() as u32,
+        in_progress_memories,
+        total_received_size: session.total_received_size,
+        total_expected_size: session.total_expected_size,
+    })
+}
+
 // Helper functions for import system
 
 /// Generate a unique session ID for import operations
-fn generate_session_id(user: Principal) -> String {
-    let timestamp = ic_cdk::api::time();
+fn generate_session_id(user: Principal, timestamp: u64, attempt: u32) -> String {
     let user_text = user.to_text();
-    let session_data = format!("{user_text}:{timestamp}");
+    let session_data = format!("{user_text}:{timestamp}:{attempt}");
     format!("import_{}", simple_hash(&session_data))
 }
 
@@ -600,6 +830,7 @@ fn create_memory_from_assembled_data(
                     language: None,
                     word_count: None,
                 }),
+                order: None,
             });
         }
         memory
@@ -678,9 +909,13 @@ fn create_memory_from_assembled_data(
                     language: None,
                     word_count: None,
                 }),
+                order: None,
             }],
             blob_internal_assets: vec![],
             blob_external_assets: vec![],
+            locked: None,
+            pending: None,
+            expires_at: None,
             // access: types::MemoryAccess::Private {
             //     owner_secure_code: format!(
             //         "import_mem_{}_{:x}",
@@ -727,9 +962,16 @@ fn cleanup_expired_sessions_internal(state: &mut PersonalCanisterCreationStateDa
 fn validate_import_against_manifest(
     session: &ImportSession,
     manifest: &DataManifest,
+    allow_partial: bool,
 ) -> Result<(), String> {
-    // Check memory count
-    if session.completed_memories.len() as u32 != manifest.memory_count {
+    // Check memory count; a partial import may hold fewer than the manifest lists
+    let imported = session.completed_memories.len() as u32;
+    let count_ok = if allow_partial {
+        imported <= manifest.memory_count
+    } else {
+        imported == manifest.memory_count
+    };
+    if !count_ok {
         return Err(format!(
             "Memory count mismatch: imported {}, manifest expects {}",
             session.completed_memories.len(),
@@ -1,4 +1,9 @@
 use super::test_utils::*;
+use crate::canister_factory::import::{
+    active_session_ids, check_combined_import_size, check_manifest_against_metadata,
+    check_session_limit, finalize_session, heartbeat_session, session_progress, session_timed_out,
+    validate_memory_manifest,
+};
 use crate::canister_factory::types::*;
 use candid::Principal;
 
@@ -9,6 +14,7 @@ fn setup_import_test_state() {
                 max_chunk_size: 1_000_000,          // 1MB max chunk size
                 max_total_import_size: 100_000_000, // 100MB max total import size
                 session_timeout_seconds: 3600,      // 1 hour session timeout
+                max_active_sessions_per_user: None,
             },
             import_sessions: std::collections::HashMap::new(),
             ..Default::default()
@@ -17,7 +23,6 @@ fn setup_import_test_state() {
 }
 
 fn mock_begin_import(user: Principal) -> Result<ImportSessionResponse, String> {
-    let session_id = format!("import_{}", simple_hash(&user.to_text()));
     let now = mock_time();
 
     with_mock_creation_state_mut(|state| {
@@ -37,20 +42,20 @@ fn mock_begin_import(user: Principal) -> Result<ImportSessionResponse, String> {
             state.import_sessions.remove(&session_id);
         }
 
-        // Check if user already has an active session
-        let existing_active = state
-            .import_sessions
-            .values()
-            .any(|s| s.user == user && s.status == ImportSessionStatus::Active);
-
-        if existing_active {
+        // Check the user's active sessions against the configured limit
+        if let Err(message) = check_session_limit(state, user) {
             return Ok(ImportSessionResponse {
                 success: false,
                 session_id: None,
-                message: "User already has an active import session".to_string(),
+                message,
             });
         }
 
+        let session_id = (0u32..)
+            .map(|attempt| format!("import_{}", simple_hash(&format!("{}:{}", user, attempt))))
+            .find(|id| !state.import_sessions.contains_key(id))
+            .unwrap();
+
         // Create new import session
         let session = ImportSession {
             session_id: session_id.clone(),
@@ -78,6 +83,7 @@ fn mock_begin_import(user: Principal) -> Result<ImportSessionResponse, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
 
     #[test]
     fn test_import_session_creation() {
@@ -126,4 +132,247 @@ mod tests {
         assert!(result1.unwrap().success);
         assert!(result2.unwrap().success);
     }
+
+    #[test]
+    fn test_import_session_configured_parallel_limit() {
+        setup_import_test_state();
+        with_mock_creation_state_mut(|state| {
+            state.import_config.max_active_sessions_per_user = Some(2);
+        });
+        let user = create_test_principal(1);
+
+        let first = mock_begin_import(user).unwrap();
+        let second = mock_begin_import(user).unwrap();
+        assert!(first.success);
+        assert!(second.success);
+        assert_ne!(first.session_id, second.session_id);
+
+        let third = mock_begin_import(user).unwrap();
+        assert!(!third.success);
+        assert!(third.message.contains("max 2"));
+
+        let active = with_mock_creation_state(|state| active_session_ids(state, user));
+        assert_eq!(active.len(), 2);
+        assert!(active.contains(&first.session_id.unwrap()));
+        assert!(active.contains(&second.session_id.unwrap()));
+    }
+
+    #[test]
+    fn test_import_size_limit_applies_across_sessions() {
+        setup_import_test_state();
+        with_mock_creation_state_mut(|state| {
+            state.import_config.max_active_sessions_per_user = Some(2);
+            state.import_config.max_total_import_size = 100;
+        });
+        let user = create_test_principal(1);
+        let other = create_test_principal(2);
+        let first = mock_begin_import(user).unwrap().session_id.unwrap();
+        mock_begin_import(user).unwrap();
+        mock_begin_import(other).unwrap();
+
+        with_mock_creation_state_mut(|state| {
+            state
+                .import_sessions
+                .get_mut(&first)
+                .unwrap()
+                .total_received_size = 60;
+        });
+
+        with_mock_creation_state(|state| {
+            // 60 + 40 fits; 60 + 50 does not, even though the second session is empty
+            assert!(check_combined_import_size(state, user, 40).is_ok());
+            assert!(check_combined_import_size(state, user, 50).is_err());
+            // Other users have their own budget
+            assert!(check_combined_import_size(state, other, 100).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_commit_rejects_manifest_missing_a_chunk_checksum() {
+        let mut manifest = MemoryManifest {
+            memory_id: "mem1".to_string(),
+            total_chunks: 3,
+            total_size: 300,
+            chunk_checksums: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            final_checksum: "abc".to_string(),
+        };
+        assert!(validate_memory_manifest(&manifest).is_ok());
+
+        manifest.chunk_checksums.pop();
+        let err = validate_memory_manifest(&manifest).unwrap_err();
+        assert!(err.contains("2 chunk checksums for 3 chunks"));
+    }
+
+    #[test]
+    fn test_heartbeat_keeps_idle_session_alive_past_timeout() {
+        setup_import_test_state();
+        let user = create_test_principal(1);
+        let session_id = mock_begin_import(user).unwrap().session_id.unwrap();
+        let timeout_ns = 3600 * 1_000_000_000;
+        let start = mock_time();
+
+        with_mock_creation_state_mut(|state| {
+            // Another user can't keep the session alive
+            assert_eq!(
+                heartbeat_session(state, create_test_principal(2), &session_id, start + 1),
+                Err(Error::Unauthorized)
+            );
+
+            // No chunks, only heartbeats during a long client-side pause
+            heartbeat_session(state, user, &session_id, start + timeout_ns / 2).unwrap();
+            heartbeat_session(state, user, &session_id, start + timeout_ns).unwrap();
+
+            // Past the original timeout, a chunk upload still sees a live session
+            let later = start + timeout_ns * 3 / 2;
+            let session = &state.import_sessions[&session_id];
+            assert!(!session_timed_out(session, &state.import_config, later));
+            assert!(session_timed_out(
+                session,
+                &state.import_config,
+                later + timeout_ns
+            ));
+        });
+    }
+
+    /// Session with one committed memory and one still waiting for chunks
+    fn session_with_pending_memory(user: Principal) -> String {
+        let session_id = mock_begin_import(user).unwrap().session_id.unwrap();
+        with_mock_creation_state_mut(|state| {
+            let session = state.import_sessions.get_mut(&session_id).unwrap();
+            session.completed_memories.insert(
+                "mem_done".to_string(),
+                crate::test_utils::create_test_memory_at("mem_done", "capsule_1", mock_time()),
+            );
+            session.memories_in_progress.insert(
+                "mem_pending".to_string(),
+                MemoryImportState {
+                    memory_id: "mem_pending".to_string(),
+                    expected_chunks: 2,
+                    received_chunks: std::collections::HashMap::new(),
+                    total_size: 200,
+                    received_size: 100,
+                    memory_metadata: None,
+                    is_complete: false,
+                },
+            );
+            session.total_received_size = 300;
+        });
+        session_id
+    }
+
+    #[test]
+    fn test_finalize_without_allow_partial_rejects_pending_memory() {
+        setup_import_test_state();
+        let user = create_test_principal(1);
+        let session_id = session_with_pending_memory(user);
+
+        let response = with_mock_creation_state_mut(|state| {
+            finalize_session(state, user, &session_id, false, mock_time())
+        });
+        assert!(!response.success);
+        assert!(response.message.contains("mem_pending"));
+
+        // Nothing was dropped; the session stays open for the missing chunks
+        with_mock_creation_state(|state| {
+            let session = &state.import_sessions[&session_id];
+            assert_eq!(session.status, ImportSessionStatus::Active);
+            assert!(session.memories_in_progress.contains_key("mem_pending"));
+        });
+    }
+
+    #[test]
+    fn test_finalize_with_allow_partial_reports_skipped_memory() {
+        setup_import_test_state();
+        let user = create_test_principal(1);
+        let session_id = session_with_pending_memory(user);
+
+        let response = with_mock_creation_state_mut(|state| {
+            finalize_session(state, user, &session_id, true, mock_time())
+        });
+        assert!(response.success);
+        assert_eq!(response.total_memories_imported, 1);
+        assert_eq!(response.total_size_imported, 200);
+        assert_eq!(response.skipped_memories, vec!["mem_pending".to_string()]);
+
+        with_mock_creation_state(|state| {
+            let session = &state.import_sessions[&session_id];
+            assert_eq!(session.status, ImportSessionStatus::Completed);
+            assert!(session.memories_in_progress.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_commit_rejects_manifest_size_disagreeing_with_metadata() {
+        let mut metadata =
+            crate::test_utils::create_test_memory_at("mem1", "capsule_1", mock_time());
+        metadata.metadata.total_size = 300;
+        let mut manifest = MemoryManifest {
+            memory_id: "mem1".to_string(),
+            total_chunks: 1,
+            total_size: 300,
+            chunk_checksums: vec!["a".to_string()],
+            final_checksum: "a".to_string(),
+        };
+        assert!(check_manifest_against_metadata(&manifest, Some(&metadata)).is_ok());
+        assert!(check_manifest_against_metadata(&manifest, None).is_ok());
+
+        manifest.total_size = 299;
+        let err = check_manifest_against_metadata(&manifest, Some(&metadata)).unwrap_err();
+        assert!(err.contains("declares 299 bytes"));
+    }
+
+    #[test]
+    fn test_import_progress_reflects_uncommitted_chunk() {
+        setup_import_test_state();
+        let user = create_test_principal(1);
+        let session_id = mock_begin_import(user).unwrap().session_id.unwrap();
+
+        with_mock_creation_state_mut(|state| {
+            let session = state.import_sessions.get_mut(&session_id).unwrap();
+            let mut received_chunks = std::collections::HashMap::new();
+            received_chunks.insert(
+                0,
+                ChunkData {
+                    chunk_index: 0,
+                    data: vec![0; 100],
+                    sha256: "a".to_string(),
+                    received_at: mock_time(),
+                },
+            );
+            session.memories_in_progress.insert(
+                "mem1".to_string(),
+                MemoryImportState {
+                    memory_id: "mem1".to_string(),
+                    expected_chunks: 3,
+                    received_chunks,
+                    total_size: 300,
+                    received_size: 100,
+                    memory_metadata: None,
+                    is_complete: false,
+                },
+            );
+            session.total_received_size = 100;
+        });
+
+        with_mock_creation_state(|state| {
+            let progress = session_progress(state, user, &session_id).unwrap();
+            assert_eq!(progress.completed_memories, 0);
+            assert_eq!(progress.total_received_size, 100);
+            assert_eq!(
+                progress.in_progress_memories,
+                vec![MemoryChunkProgress {
+                    memory_id: "mem1".to_string(),
+                    received_chunks: 1,
+                    expected_chunks: 3,
+                    received_size: 100,
+                }]
+            );
+
+            // Only the owner may read it
+            assert!(matches!(
+                session_progress(state, create_test_principal(2), &session_id),
+                Err(Error::Unauthorized)
+            ));
+        });
+    }
 }
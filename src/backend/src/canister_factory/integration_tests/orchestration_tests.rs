@@ -1,6 +1,64 @@
 #[cfg(test)]
 mod tests {
-    // Tests removed due to IC system call dependencies
-    // These functions require canister context and cannot be unit tested
-    // Integration tests should be handled via bash scripts as per task 18
+    // Most orchestration paths need IC system calls and are covered by the bash
+    // scripts as per task 18; the resume decision itself is pure.
+    use super::super::test_utils::*;
+    use crate::canister_factory::orchestrator::resume_stage;
+    use crate::canister_factory::types::*;
+    use crate::types::Error;
+
+    fn persisted(status: CreationStatus) -> PersonalCanisterCreationState {
+        PersonalCanisterCreationState {
+            user: create_test_principal(1),
+            status,
+            created_at: mock_time(),
+            completed_at: None,
+            personal_canister_id: Some(create_test_principal(10)),
+            cycles_consumed: 1_000_000_000_000,
+            error_message: None,
+            verification: None,
+            last_synced_at: None,
+        }
+    }
+
+    #[test]
+    fn test_resume_after_upgrade_continues_from_importing() {
+        setup_test_state();
+        let user = create_test_principal(1);
+        with_mock_creation_state_mut(|state| {
+            state
+                .creation_states
+                .insert(user, persisted(CreationStatus::Importing));
+        });
+
+        // After an upgrade nothing is running: pick up at the persisted stage
+        let stored = with_mock_creation_state(|state| state.creation_states[&user].clone());
+        assert_eq!(
+            resume_stage(&stored, false),
+            Ok(Some(CreationStatus::Importing))
+        );
+
+        // Without an upgrade the original run still owns the creation
+        assert!(matches!(
+            resume_stage(&stored, true),
+            Err(Error::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_resume_of_terminal_or_early_stages() {
+        assert_eq!(
+            resume_stage(&persisted(CreationStatus::Completed), false),
+            Ok(None)
+        );
+        assert_eq!(
+            resume_stage(&persisted(CreationStatus::Failed), true),
+            Ok(None)
+        );
+        // Nothing recorded before Installing, so the creation starts over
+        assert_eq!(
+            resume_stage(&persisted(CreationStatus::Creating), false),
+            Ok(Some(CreationStatus::NotStarted))
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use super::test_utils::*;
+use crate::canister_factory::registry::{ensure_orphaned, orphaned_canisters};
 use crate::canister_factory::types::*;
 
 // Mock registry functions
@@ -178,4 +179,30 @@ mod tests {
 
         assert_eq!(total_registry_cycles, 5_000_000_000_000); // 2T + 3T
     }
+
+    #[test]
+    fn test_orphaned_canister_listed_and_cleaned_up() {
+        setup_test_state();
+        let user = create_test_principal(1);
+        let orphan = create_test_principal(10);
+        let handed_off = create_test_principal(11);
+        mock_create_registry_entry(orphan, user, CreationStatus::Failed, 1_000).unwrap();
+        mock_create_registry_entry(handed_off, user, CreationStatus::Completed, 1_000).unwrap();
+
+        let listed = with_mock_creation_state(orphaned_canisters);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].canister_id, orphan);
+
+        with_mock_creation_state(|state| {
+            assert!(ensure_orphaned(state, orphan).is_ok());
+            // Live canisters and unknown ids are refused
+            assert!(ensure_orphaned(state, handed_off).is_err());
+            assert!(ensure_orphaned(state, create_test_principal(12)).is_err());
+        });
+
+        // Deleting the canister drops its registry entry
+        with_mock_creation_state_mut(|state| state.personal_canisters.remove(&orphan));
+        assert!(with_mock_creation_state(orphaned_canisters).is_empty());
+        assert!(with_mock_creation_state(|state| ensure_orphaned(state, orphan)).is_err());
+    }
 }
@@ -46,6 +46,7 @@ pub fn setup_test_state() {
                 cycles_reserve: 10_000_000_000_000, // 10T cycles
                 min_cycles_threshold: 2_000_000_000_000, // 2T cycles
                 admin_principals: std::collections::BTreeSet::new(),
+                paused: None,
             },
             creation_stats: PersonalCanisterCreationStats {
                 total_cycles_consumed: 1_000_000_000_000, // 1T cycles consumed
@@ -65,6 +66,7 @@ pub fn setup_low_reserve_state() {
                 cycles_reserve: 1_000_000_000_000, // 1T cycles (below threshold)
                 min_cycles_threshold: 2_000_000_000_000, // 2T cycles
                 admin_principals: std::collections::BTreeSet::new(),
+                paused: None,
             },
             creation_stats: PersonalCanisterCreationStats {
                 total_cycles_consumed: 5_000_000_000_000, // 5T cycles consumed
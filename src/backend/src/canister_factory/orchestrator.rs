@@ -1,6 +1,37 @@
 use crate::canister_factory::types::*;
 use crate::canister_factory::{auth::*, cycles::*, export::*, factory::*, registry::*, verify::*};
 use candid::Principal;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+thread_local! {
+    // Users whose creation is running in this instance. Heap-only on purpose: an
+    // upgrade clears it, which is what marks a persisted in-progress stage as interrupted.
+    static RUNNING: RefCell<BTreeSet<Principal>> = const { RefCell::new(BTreeSet::new()) };
+}
+
+fn is_running(user: Principal) -> bool {
+    RUNNING.with(|running| running.borrow().contains(&user))
+}
+
+/// Marks `user`'s creation as running in this instance until dropped, so the
+/// mark clears on completion, failure and early returns alike
+struct RunningGuard(Principal);
+
+impl RunningGuard {
+    /// `None` if a run for `user` is already in flight
+    fn acquire(user: Principal) -> Option<Self> {
+        RUNNING
+            .with(|running| running.borrow_mut().insert(user))
+            .then_some(RunningGuard(user))
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING.with(|running| running.borrow_mut().remove(&self.0));
+    }
+}
 
 /// Main personal canister creation function that orchestrates the complete capsule migration process
 /// This function implements the state machine: NotStarted → Exporting → Creating → Installing → Importing → Verifying → Completed/Failed
@@ -10,17 +41,26 @@ pub async fn create_personal_canister() -> Result<PersonalCanisterCreationRespon
 
     ic_cdk::println!("Starting migration for user {}", user);
 
-    // Check if migration is enabled
-    let migration_enabled =
-        crate::memory::with_migration_state(|state| state.creation_config.enabled);
-    if !migration_enabled {
+    // Check if new creations are accepted (not disabled or paused)
+    if let Err(message) =
+        crate::memory::with_migration_state(|state| admission(&state.creation_config))
+    {
         return Ok(PersonalCanisterCreationResponse {
             success: false,
             canister_id: None,
-            message: "Personal canister creation is currently disabled".to_string(),
+            message,
         });
     }
 
+    // Claim the user before any await so an overlapping call cannot start a second canister
+    let Some(_running) = RunningGuard::acquire(user) else {
+        return Ok(PersonalCanisterCreationResponse {
+            success: false,
+            canister_id: None,
+            message: "Personal canister creation already in progress".to_string(),
+        });
+    };
+
     // Get or create migration state for this user
     let existing_state =
         crate::memory::with_migration_state(|state| state.creation_states.get(&user).cloned());
@@ -66,6 +106,8 @@ pub async fn create_personal_canister() -> Result<PersonalCanisterCreationRespon
         personal_canister_id: None,
         cycles_consumed: 0,
         error_message: None,
+        verification: None,
+        last_synced_at: None,
     };
 
     // Update creation stats
@@ -77,24 +119,134 @@ pub async fn create_personal_canister() -> Result<PersonalCanisterCreationRespon
     // Execute creation state machine
     let result = execute_creation_state_machine(&mut creation_state).await;
 
-    // Update final creation state
-    crate::memory::with_migration_state_mut(|state| {
-        state.creation_states.insert(user, creation_state.clone());
+    record_outcome(&creation_state, &result);
+    result
+}
 
-        // Update stats based on result
-        match &result {
-            Ok(_) => {
-                if creation_state.status == CreationStatus::Completed {
-                    state.creation_stats.total_successes += 1;
-                }
-            }
-            Err(_) => {
-                state.creation_stats.total_failures += 1;
-            }
+/// Store the final state of a run and count it in the stats
+///
+/// A cancel recorded while the run was in flight wins and is left untouched.
+fn record_outcome(
+    creation_state: &PersonalCanisterCreationState,
+    result: &Result<PersonalCanisterCreationResponse, String>,
+) {
+    crate::memory::with_migration_state_mut(|state| apply_outcome(state, creation_state, result));
+}
+
+/// Store a finished run and update the stats; a failure is counted against the
+/// last stage the run checkpointed before it failed
+pub fn apply_outcome(
+    state: &mut PersonalCanisterCreationStateData,
+    creation_state: &PersonalCanisterCreationState,
+    result: &Result<PersonalCanisterCreationResponse, String>,
+) {
+    if state
+        .creation_states
+        .get(&creation_state.user)
+        .is_some_and(is_cancelled)
+    {
+        return;
+    }
+    let last_stage = state
+        .creation_states
+        .insert(creation_state.user, creation_state.clone())
+        .map(|previous| previous.status);
+
+    // Update stats based on result
+    if result.is_ok() && creation_state.status == CreationStatus::Completed {
+        state.creation_stats.total_successes += 1;
+    } else if result.is_err() || creation_state.status == CreationStatus::Failed {
+        state.creation_stats.total_failures += 1;
+        if let Some(stage) = last_stage {
+            state
+                .creation_stats
+                .failures_by_stage
+                .get_or_insert_with(FailuresByStage::default)
+                .record(&stage);
         }
-    });
+    }
+}
 
-    result
+/// Where a resumed creation picks up, or `None` when it already reached a terminal state
+///
+/// Stages from `Installing` on continue where they stopped. Earlier stages haven't
+/// recorded a canister yet, so they start over (`NotStarted`). A creation still
+/// running in this instance (not interrupted by an upgrade) can't be resumed.
+pub fn resume_stage(
+    creation_state: &PersonalCanisterCreationState,
+    running: bool,
+) -> Result<Option<CreationStatus>, crate::types::Error> {
+    match creation_state.status {
+        CreationStatus::Completed | CreationStatus::Failed => Ok(None),
+        _ if running => Err(crate::types::Error::Conflict(
+            "personal canister creation is still running".to_string(),
+        )),
+        CreationStatus::Installing | CreationStatus::Importing | CreationStatus::Verifying => {
+            Ok(Some(creation_state.status.clone()))
+        }
+        CreationStatus::NotStarted | CreationStatus::Exporting | CreationStatus::Creating => {
+            Ok(Some(CreationStatus::NotStarted))
+        }
+    }
+}
+
+/// Continue the caller's creation after an upgrade interrupted it
+pub async fn resume_personal_canister_creation(
+) -> Result<CreationStatusResponse, crate::types::Error> {
+    use crate::types::Error;
+
+    let user = validate_migration_caller().map_err(|_| Error::Unauthorized)?;
+    let mut creation_state =
+        crate::memory::with_migration_state(|state| state.creation_states.get(&user).cloned())
+            .ok_or(Error::NotFound)?;
+
+    let from = match resume_stage(&creation_state, is_running(user))? {
+        Some(from) => from,
+        None => return Ok(status_response(&creation_state)),
+    };
+    ic_cdk::println!(
+        "Resuming personal canister creation for user {} from {:?}",
+        user,
+        from
+    );
+
+    let Some(_running) = RunningGuard::acquire(user) else {
+        return Ok(status_response(&creation_state));
+    };
+    let result = if from == CreationStatus::NotStarted {
+        execute_creation_state_machine(&mut creation_state).await
+    } else {
+        resume_from(&mut creation_state, from).await
+    };
+
+    record_outcome(&creation_state, &result);
+    Ok(status_response(&creation_state))
+}
+
+/// Re-export the capsule and run the remaining stages from `from`
+async fn resume_from(
+    creation_state: &mut PersonalCanisterCreationState,
+    from: CreationStatus,
+) -> Result<PersonalCanisterCreationResponse, String> {
+    let canister_id = creation_state
+        .personal_canister_id
+        .ok_or("No personal canister recorded for an in-progress creation")?;
+
+    // Export data isn't persisted; the capsule is exported again
+    let export_data = match export_user_capsule_data(creation_state.user) {
+        Ok(data) => data,
+        Err(e) => {
+            creation_state.status = CreationStatus::Failed;
+            creation_state.error_message = Some(format!("Export failed: {e}"));
+            return Ok(PersonalCanisterCreationResponse {
+                success: false,
+                canister_id: Some(canister_id),
+                message: format!("Failed to export capsule data: {e}"),
+            });
+        }
+    };
+
+    run_remaining_stages(creation_state, canister_id, &export_data, from).await
 }
 
 /// Execute the personal canister creation state machine with comprehensive error handling
@@ -137,6 +289,9 @@ async fn execute_creation_state_machine(
 
     // State: Exporting → Creating
     creation_state.status = CreationStatus::Creating;
+    if let Some(stopped) = checkpoint(creation_state).await {
+        return stopped;
+    }
     ic_cdk::println!(
         "Personal canister creation state: Creating personal canister for user {}",
         user
@@ -163,65 +318,99 @@ async fn execute_creation_state_machine(
         }
     };
 
-    // State: Creating → Installing
-    creation_state.status = CreationStatus::Installing;
-    ic_cdk::println!(
-        "Personal canister creation state: Installing WASM for canister {}",
-        canister_id
-    );
+    run_remaining_stages(
+        creation_state,
+        canister_id,
+        &export_data,
+        CreationStatus::Installing,
+    )
+    .await
+}
 
-    // Install WASM module
-    if let Err(e) = complete_wasm_installation(canister_id, user, &export_data).await {
-        creation_state.status = CreationStatus::Failed;
-        creation_state.error_message = Some(format!("WASM installation failed: {e}"));
+/// Run the stages that follow canister creation, starting at `from`
+///
+/// A fresh creation starts at `Installing`; a resumed one at the stage it was
+/// interrupted in (`Installing`, `Importing` or `Verifying`).
+async fn run_remaining_stages(
+    creation_state: &mut PersonalCanisterCreationState,
+    canister_id: Principal,
+    export_data: &ExportData,
+    from: CreationStatus,
+) -> Result<PersonalCanisterCreationResponse, String> {
+    let user = creation_state.user;
 
-        // Cleanup failed canister
-        if let Err(cleanup_err) = cleanup_failed_canister_creation(canister_id, user).await {
-            ic_cdk::println!("Warning: Cleanup failed: {}", cleanup_err);
+    if from == CreationStatus::Installing {
+        // State: Creating → Installing
+        creation_state.status = CreationStatus::Installing;
+        if let Some(stopped) = checkpoint(creation_state).await {
+            return stopped;
         }
+        ic_cdk::println!(
+            "Personal canister creation state: Installing WASM for canister {}",
+            canister_id
+        );
 
-        return Ok(PersonalCanisterCreationResponse {
-            success: false,
-            canister_id: Some(canister_id),
-            message: format!("Failed to install WASM: {e}"),
-        });
-    }
+        // Install WASM module
+        if let Err(e) = complete_wasm_installation(canister_id, user, export_data).await {
+            creation_state.status = CreationStatus::Failed;
+            creation_state.error_message = Some(format!("WASM installation failed: {e}"));
 
-    // State: Installing → Importing
-    creation_state.status = CreationStatus::Importing;
-    ic_cdk::println!(
-        "Personal canister creation state: Importing data to canister {}",
-        canister_id
-    );
+            // Cleanup failed canister
+            if let Err(cleanup_err) = cleanup_failed_canister_creation(canister_id, user).await {
+                ic_cdk::println!("Warning: Cleanup failed: {}", cleanup_err);
+            }
 
-    // For MVP, we'll simulate the import process since the actual chunked import
-    // would require the personal canister to be fully implemented
-    // In production, this would use the chunked import API
-    if let Err(e) = simulate_data_import(canister_id, &export_data).await {
-        creation_state.status = CreationStatus::Failed;
-        creation_state.error_message = Some(format!("Data import failed: {e}"));
+            return Ok(PersonalCanisterCreationResponse {
+                success: false,
+                canister_id: Some(canister_id),
+                message: format!("Failed to install WASM: {e}"),
+            });
+        }
+    }
 
-        // Cleanup failed canister
-        if let Err(cleanup_err) = cleanup_failed_canister_creation(canister_id, user).await {
-            ic_cdk::println!("Warning: Cleanup failed: {}", cleanup_err);
+    if from != CreationStatus::Verifying {
+        // State: Installing → Importing
+        creation_state.status = CreationStatus::Importing;
+        if let Some(stopped) = checkpoint(creation_state).await {
+            return stopped;
         }
+        ic_cdk::println!(
+            "Personal canister creation state: Importing data to canister {}",
+            canister_id
+        );
 
-        return Ok(PersonalCanisterCreationResponse {
-            success: false,
-            canister_id: Some(canister_id),
-            message: format!("Failed to import data: {e}"),
-        });
+        // For MVP, we'll simulate the import process since the actual chunked import
+        // would require the personal canister to be fully implemented
+        // In production, this would use the chunked import API
+        if let Err(e) = simulate_data_import(canister_id, export_data).await {
+            creation_state.status = CreationStatus::Failed;
+            creation_state.error_message = Some(format!("Data import failed: {e}"));
+
+            // Cleanup failed canister
+            if let Err(cleanup_err) = cleanup_failed_canister_creation(canister_id, user).await {
+                ic_cdk::println!("Warning: Cleanup failed: {}", cleanup_err);
+            }
+
+            return Ok(PersonalCanisterCreationResponse {
+                success: false,
+                canister_id: Some(canister_id),
+                message: format!("Failed to import data: {e}"),
+            });
+        }
     }
 
     // State: Importing → Verifying
     creation_state.status = CreationStatus::Verifying;
+    if let Some(stopped) = checkpoint(creation_state).await {
+        return stopped;
+    }
     ic_cdk::println!(
         "Personal canister creation state: Verifying data for canister {}",
         canister_id
     );
 
     // Verify data integrity
-    if let Err(e) = verify_migration_data(canister_id, &export_data).await {
+    if let Err(e) = verify_migration_data(canister_id, export_data).await {
         creation_state.status = CreationStatus::Failed;
         creation_state.error_message = Some(format!("Data verification failed: {e}"));
 
@@ -232,6 +421,32 @@ async fn execute_creation_state_machine(
         });
     }
 
+    // Compare what the personal canister holds against the source capsule
+    let verification = match verify_import_counts(canister_id, export_data).await {
+        Ok(verification) => verification,
+        Err(e) => {
+            creation_state.status = CreationStatus::Failed;
+            creation_state.error_message = Some(format!("Data verification failed: {e}"));
+
+            return Ok(PersonalCanisterCreationResponse {
+                success: false,
+                canister_id: Some(canister_id),
+                message: format!("Data verification failed: {e}"),
+            });
+        }
+    };
+    creation_state.verification = Some(verification.clone());
+    if let Err(mismatch) = verification.check() {
+        creation_state.status = CreationStatus::Failed;
+        creation_state.error_message = Some(mismatch.to_string());
+
+        return Ok(PersonalCanisterCreationResponse {
+            success: false,
+            canister_id: Some(canister_id),
+            message: mismatch.to_string(),
+        });
+    }
+
     // State: Verifying → Handoff Controllers
     ic_cdk::println!(
         "Personal canister creation state: Handing off controllers for canister {}",
@@ -280,6 +495,239 @@ async fn execute_creation_state_machine(
     })
 }
 
+/// Error message recorded on a creation the user cancelled
+pub const CANCELLED_MESSAGE: &str = "Cancelled by user";
+
+fn is_cancelled(creation_state: &PersonalCanisterCreationState) -> bool {
+    creation_state.status == CreationStatus::Failed
+        && creation_state.error_message.as_deref() == Some(CANCELLED_MESSAGE)
+}
+
+/// Message for creations refused or stopped because creation is disabled
+pub const DISABLED_MESSAGE: &str = "Personal canister creation is currently disabled";
+/// Message for new creations refused while creation is paused
+pub const PAUSED_MESSAGE: &str = "Personal canister creation is temporarily paused, retry later";
+
+/// Whether a new creation may start under `config`
+///
+/// Disabled is a hard off; paused only holds back new creations (see `stage_gate`).
+pub fn admission(config: &PersonalCanisterCreationConfig) -> Result<(), String> {
+    if !config.enabled {
+        return Err(DISABLED_MESSAGE.to_string());
+    }
+    if config.paused == Some(true) {
+        return Err(PAUSED_MESSAGE.to_string());
+    }
+    Ok(())
+}
+
+/// What an in-flight creation may do when it enters its next stage
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StageGate {
+    Continue,
+    Cancelled,
+    Disabled,
+}
+
+/// Gate for `user`'s in-flight creation; pausing doesn't stop it, disabling does
+pub fn stage_gate(state: &PersonalCanisterCreationStateData, user: Principal) -> StageGate {
+    if state.creation_states.get(&user).is_some_and(is_cancelled) {
+        StageGate::Cancelled
+    } else if !state.creation_config.enabled {
+        StageGate::Disabled
+    } else {
+        StageGate::Continue
+    }
+}
+
+/// Persist in-flight progress so status queries and cancellation see it
+///
+/// Only writes when the gate lets the creation continue.
+fn save_progress(creation_state: &PersonalCanisterCreationState) -> StageGate {
+    crate::memory::with_migration_state_mut(|state| {
+        let gate = stage_gate(state, creation_state.user);
+        if gate == StageGate::Continue {
+            state
+                .creation_states
+                .insert(creation_state.user, creation_state.clone());
+        }
+        gate
+    })
+}
+
+/// Save progress at a stage boundary; returns the response to stop with, if the run must stop
+async fn checkpoint(
+    creation_state: &mut PersonalCanisterCreationState,
+) -> Option<Result<PersonalCanisterCreationResponse, String>> {
+    match save_progress(creation_state) {
+        StageGate::Continue => None,
+        StageGate::Cancelled => Some(abandon_cancelled_creation(creation_state).await),
+        StageGate::Disabled => {
+            creation_state.status = CreationStatus::Failed;
+            creation_state.error_message = Some(DISABLED_MESSAGE.to_string());
+
+            if let Some(canister_id) = creation_state.personal_canister_id {
+                if let Err(cleanup_err) =
+                    cleanup_failed_canister_creation(canister_id, creation_state.user).await
+                {
+                    ic_cdk::println!("Warning: Cleanup failed: {}", cleanup_err);
+                }
+            }
+
+            Some(Ok(PersonalCanisterCreationResponse {
+                success: false,
+                canister_id: creation_state.personal_canister_id,
+                message: DISABLED_MESSAGE.to_string(),
+            }))
+        }
+    }
+}
+
+/// Stop the state machine after a cancel, rolling back a canister the cancel didn't know about
+async fn abandon_cancelled_creation(
+    creation_state: &PersonalCanisterCreationState,
+) -> Result<PersonalCanisterCreationResponse, String> {
+    let recorded_canister = crate::memory::with_migration_state(|state| {
+        state
+            .creation_states
+            .get(&creation_state.user)
+            .and_then(|recorded| recorded.personal_canister_id)
+    });
+
+    // Created after the cancel was recorded: the cancel couldn't clean it up
+    if let (Some(canister_id), None) = (creation_state.personal_canister_id, recorded_canister) {
+        crate::memory::with_migration_state_mut(|state| {
+            state.creation_config.cycles_reserve = state
+                .creation_config
+                .cycles_reserve
+                .saturating_add(creation_state.cycles_consumed);
+            if let Some(record) = state.personal_canisters.get_mut(&canister_id) {
+                record.status = CreationStatus::Failed;
+            }
+        });
+        if let Err(e) = delete_created_canister(canister_id).await {
+            ic_cdk::println!(
+                "Warning: Rollback of canister {} failed: {}",
+                canister_id,
+                e
+            );
+        }
+    }
+
+    Ok(PersonalCanisterCreationResponse {
+        success: false,
+        canister_id: None,
+        message: CANCELLED_MESSAGE.to_string(),
+    })
+}
+
+/// Mark the in-progress creation of `user` as cancelled
+///
+/// Refunds the cycles the creation took from the factory reserve and marks its
+/// registry entry failed. Returns the canister to stop and delete, if one was
+/// created. Completed and already-failed creations can't be cancelled.
+pub fn cancel_creation(
+    state: &mut PersonalCanisterCreationStateData,
+    user: Principal,
+    now: u64,
+) -> Result<Option<Principal>, crate::types::Error> {
+    use crate::types::Error;
+
+    let creation_state = state
+        .creation_states
+        .get_mut(&user)
+        .ok_or(Error::NotFound)?;
+    match creation_state.status {
+        CreationStatus::Completed => {
+            return Err(Error::Conflict(
+                "personal canister creation already completed".to_string(),
+            ))
+        }
+        CreationStatus::Failed => {
+            return Err(Error::Conflict(
+                "personal canister creation is not in progress".to_string(),
+            ))
+        }
+        _ => {}
+    }
+
+    creation_state.status = CreationStatus::Failed;
+    creation_state.error_message = Some(CANCELLED_MESSAGE.to_string());
+    creation_state.completed_at = Some(now);
+    let canister_id = creation_state.personal_canister_id;
+    let refund = creation_state.cycles_consumed;
+
+    state.creation_stats.total_failures += 1;
+    if let Some(canister_id) = canister_id {
+        state.creation_config.cycles_reserve =
+            state.creation_config.cycles_reserve.saturating_add(refund);
+        if let Some(record) = state.personal_canisters.get_mut(&canister_id) {
+            record.status = CreationStatus::Failed;
+        }
+    }
+    Ok(canister_id)
+}
+
+/// Cancel the caller's in-progress personal canister creation and delete its canister
+pub async fn cancel_personal_canister_creation() -> Result<(), crate::types::Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if caller == Principal::anonymous() {
+        return Err(crate::types::Error::Unauthorized);
+    }
+
+    let now = ic_cdk::api::time();
+    let canister_id =
+        crate::memory::with_migration_state_mut(|state| cancel_creation(state, caller, now))?;
+
+    if let Some(canister_id) = canister_id {
+        delete_created_canister(canister_id)
+            .await
+            .map_err(crate::types::Error::Internal)?;
+    }
+    Ok(())
+}
+
+/// Stop and delete a canister the factory created, dropping its registry entry
+async fn delete_created_canister(canister_id: Principal) -> Result<(), String> {
+    use ic_cdk::management_canister::{
+        delete_canister, stop_canister, DeleteCanisterArgs, StopCanisterArgs,
+    };
+
+    stop_canister(&StopCanisterArgs { canister_id })
+        .await
+        .map_err(|e| format!("Failed to stop canister {canister_id}: {e:?}"))?;
+    delete_canister(&DeleteCanisterArgs { canister_id })
+        .await
+        .map_err(|e| format!("Failed to delete canister {canister_id}: {e:?}"))?;
+
+    crate::memory::with_migration_state_mut(|state| state.personal_canisters.remove(&canister_id));
+    ic_cdk::println!("Deleted personal canister {}", canister_id);
+    Ok(())
+}
+
+/// Canisters left behind by failed creations (admin only)
+pub fn list_orphaned_canisters() -> Result<Vec<PersonalCanisterRecord>, String> {
+    validate_admin_caller()?;
+    Ok(crate::memory::with_migration_state(orphaned_canisters))
+}
+
+/// Stop and delete an orphaned canister and remove it from the registry (admin only)
+///
+/// This stops the orphan from drawing cycles for storage; whatever balance it
+/// still holds is burned with it.
+pub async fn admin_cleanup_orphaned_canister(canister_id: Principal) -> Result<(), String> {
+    let admin = validate_admin_caller()?;
+    crate::memory::with_migration_state(|state| ensure_orphaned(state, canister_id))?;
+
+    delete_created_canister(canister_id).await?;
+    ic_cdk::println!(
+        "Admin {} cleaned up orphaned canister {}",
+        admin,
+        canister_id
+    );
+    Ok(())
+}
+
 /// Complete WASM installation process with error handling and validation
 /// This function orchestrates the complete WASM installation process
 pub async fn complete_wasm_installation(
@@ -459,32 +907,253 @@ pub fn get_creation_status() -> Option<CreationStatusResponse> {
     }
 
     crate::memory::with_migration_state(|state| {
-        state.creation_states.get(&caller).map(|creation_state| {
-            let message = match creation_state.status {
-                CreationStatus::NotStarted => "Personal canister creation not started".to_string(),
-                CreationStatus::Exporting => "Exporting capsule data...".to_string(),
-                CreationStatus::Creating => "Creating personal canister...".to_string(),
-                CreationStatus::Installing => "Installing WASM module...".to_string(),
-                CreationStatus::Importing => "Importing data to personal canister...".to_string(),
-                CreationStatus::Verifying => "Verifying data integrity...".to_string(),
-                CreationStatus::Completed => {
-                    "Personal canister creation completed successfully".to_string()
+        state.creation_states.get(&caller).map(status_response)
+    })
+}
+
+fn status_response(creation_state: &PersonalCanisterCreationState) -> CreationStatusResponse {
+    CreationStatusResponse {
+        status: creation_state.status.clone(),
+        canister_id: creation_state.personal_canister_id,
+        message: Some(progress_message(creation_state)),
+    }
+}
+
+/// Human-readable description of the stage a creation is in
+fn progress_message(creation_state: &PersonalCanisterCreationState) -> String {
+    match creation_state.status {
+        CreationStatus::NotStarted => "Personal canister creation not started".to_string(),
+        CreationStatus::Exporting => "Exporting capsule data...".to_string(),
+        CreationStatus::Creating => "Creating personal canister...".to_string(),
+        CreationStatus::Installing => "Installing WASM module...".to_string(),
+        CreationStatus::Importing => "Importing data to personal canister...".to_string(),
+        CreationStatus::Verifying => "Verifying data integrity...".to_string(),
+        CreationStatus::Completed => {
+            "Personal canister creation completed successfully".to_string()
+        }
+        CreationStatus::Failed => creation_state
+            .error_message
+            .clone()
+            .unwrap_or_else(|| "Personal canister creation failed".to_string()),
+    }
+}
+
+/// Progress recorded by an import session
+pub fn import_progress(session: &ImportSession) -> ImportProgress {
+    let memories_imported = session.completed_memories.len() as u32;
+    let total_memories = session
+        .import_manifest
+        .as_ref()
+        .map(|manifest| manifest.memory_count)
+        .unwrap_or(memories_imported + session.memories_in_progress.len() as u32);
+    ImportProgress {
+        memories_imported,
+        total_memories,
+        bytes_transferred: session.total_received_size,
+        total_bytes: session.total_expected_size,
+    }
+}
+
+/// Percent complete across all stages; `Importing` advances with the import itself
+pub fn percent_complete(status: &CreationStatus, import: Option<&ImportProgress>) -> u8 {
+    const IMPORT_START: u64 = 40;
+    const IMPORT_END: u64 = 85;
+
+    match status {
+        CreationStatus::NotStarted | CreationStatus::Failed => 0,
+        CreationStatus::Exporting => 5,
+        CreationStatus::Creating => 15,
+        CreationStatus::Installing => 30,
+        CreationStatus::Importing => {
+            let (done, total) = match import {
+                Some(p) if p.total_memories > 0 => {
+                    (p.memories_imported as u64, p.total_memories as u64)
                 }
-                CreationStatus::Failed => creation_state
-                    .error_message
-                    .clone()
-                    .unwrap_or_else(|| "Personal canister creation failed".to_string()),
+                Some(p) if p.total_bytes > 0 => (p.bytes_transferred, p.total_bytes),
+                _ => (0, 1),
             };
+            let span = (IMPORT_END - IMPORT_START) * done.min(total) / total;
+            (IMPORT_START + span) as u8
+        }
+        CreationStatus::Verifying => 90,
+        CreationStatus::Completed => 100,
+    }
+}
 
-            CreationStatusResponse {
-                status: creation_state.status.clone(),
-                canister_id: creation_state.personal_canister_id,
-                message: Some(message),
-            }
+/// Full status of one creation, including its stage, cycles, error and progress
+pub fn detailed_status(
+    creation_state: &PersonalCanisterCreationState,
+    import_session: Option<&ImportSession>,
+) -> DetailedCreationStatus {
+    let progress = import_session.map(import_progress);
+    DetailedCreationStatus {
+        status: creation_state.status.clone(),
+        canister_id: creation_state.personal_canister_id,
+        created_at: creation_state.created_at,
+        completed_at: creation_state.completed_at,
+        cycles_consumed: creation_state.cycles_consumed,
+        error_message: creation_state.error_message.clone(),
+        progress_message: progress_message(creation_state),
+        percent_complete: percent_complete(&creation_state.status, progress.as_ref()),
+        import_progress: progress,
+        verification: creation_state.verification.clone(),
+    }
+}
+
+/// Most recent import session of `user`
+fn latest_import_session(
+    state: &PersonalCanisterCreationStateData,
+    user: Principal,
+) -> Option<&ImportSession> {
+    state
+        .import_sessions
+        .values()
+        .filter(|session| session.user == user)
+        .max_by_key(|session| session.created_at)
+}
+
+/// Detailed status of `user`'s creation, if one was started
+pub fn user_detailed_status(
+    state: &PersonalCanisterCreationStateData,
+    user: Principal,
+) -> Option<DetailedCreationStatus> {
+    state
+        .creation_states
+        .get(&user)
+        .map(|creation_state| detailed_status(creation_state, latest_import_session(state, user)))
+}
+
+/// Detailed status of every creation, optionally only those in `status`
+pub fn detailed_statuses(
+    state: &PersonalCanisterCreationStateData,
+    status: Option<&CreationStatus>,
+) -> Vec<(Principal, DetailedCreationStatus)> {
+    state
+        .creation_states
+        .iter()
+        .filter(|(_, creation_state)| status.is_none_or(|s| creation_state.status == *s))
+        .map(|(user, creation_state)| {
+            let session = latest_import_session(state, *user);
+            (*user, detailed_status(creation_state, session))
         })
+        .collect()
+}
+
+/// Estimate size, cycles and time of migrating the caller's capsule without starting it
+pub fn estimate_migration() -> Result<MigrationEstimate, crate::types::Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if caller == Principal::anonymous() {
+        return Err(crate::types::Error::Unauthorized);
+    }
+
+    let capsule =
+        crate::capsule::util::find_self_capsule(&crate::types::PersonRef::Principal(caller))
+            .ok_or(crate::types::Error::NotFound)?;
+    let max_chunk_size =
+        crate::memory::with_migration_state(|state| state.import_config.max_chunk_size);
+
+    Ok(estimate_capsule_migration(&capsule, max_chunk_size))
+}
+
+/// Re-sync the caller's memories created since the last sync into their personal canister
+pub async fn sync_to_personal_canister() -> Result<SyncReport, crate::types::Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if caller == Principal::anonymous() {
+        return Err(crate::types::Error::Unauthorized);
+    }
+
+    let now = ic_cdk::api::time();
+    let (canister_id, since) =
+        crate::memory::with_migration_state(|state| sync_target(state, caller))?;
+    let capsule =
+        crate::capsule::util::find_self_capsule(&crate::types::PersonRef::Principal(caller))
+            .ok_or(crate::types::Error::NotFound)?;
+
+    let memories = memories_created_since(&capsule, since);
+    if !memories.is_empty() {
+        transfer_memories(canister_id, &memories)
+            .await
+            .map_err(crate::types::Error::Internal)?;
+    }
+    crate::memory::with_migration_state_mut(|state| record_sync(state, caller, now));
+
+    Ok(SyncReport {
+        canister_id,
+        memories_synced: memories.len() as u32,
+        since,
+        synced_at: now,
     })
 }
 
+/// Personal canister `user` syncs into and the time the previous transfer covered
+///
+/// Before the first re-sync that is the start of the original creation: the
+/// export ran after it, so nothing older can be missing.
+pub fn sync_target(
+    state: &PersonalCanisterCreationStateData,
+    user: Principal,
+) -> Result<(Principal, u64), crate::types::Error> {
+    let creation_state = state
+        .creation_states
+        .get(&user)
+        .ok_or(crate::types::Error::NotFound)?;
+    if creation_state.status != CreationStatus::Completed {
+        return Err(crate::types::Error::Conflict(
+            "Personal canister creation has not completed".to_string(),
+        ));
+    }
+    let canister_id = creation_state
+        .personal_canister_id
+        .ok_or(crate::types::Error::NotFound)?;
+
+    Ok((
+        canister_id,
+        creation_state
+            .last_synced_at
+            .unwrap_or(creation_state.created_at),
+    ))
+}
+
+/// Memories of `capsule` created after `since`, oldest first
+pub fn memories_created_since(
+    capsule: &crate::capsule::domain::Capsule,
+    since: u64,
+) -> Vec<(String, crate::types::Memory)> {
+    let mut memories: Vec<_> = capsule
+        .memories
+        .iter()
+        .filter(|(_, memory)| memory.metadata.created_at > since)
+        .map(|(id, memory)| (id.clone(), memory.clone()))
+        .collect();
+    memories.sort_by_key(|(_, memory)| memory.metadata.created_at);
+    memories
+}
+
+/// Record that `user`'s memories up to `synced_at` are in their personal canister
+pub fn record_sync(state: &mut PersonalCanisterCreationStateData, user: Principal, synced_at: u64) {
+    if let Some(creation_state) = state.creation_states.get_mut(&user) {
+        creation_state.last_synced_at = Some(synced_at);
+    }
+}
+
+/// Send `memories` to an existing personal canister (placeholder for the chunked import)
+async fn transfer_memories(
+    canister_id: Principal,
+    memories: &[(String, crate::types::Memory)],
+) -> Result<(), String> {
+    ic_cdk::println!(
+        "Syncing {} memories to personal canister {}",
+        memories.len(),
+        canister_id
+    );
+
+    // For MVP, we simulate the transfer like the initial import
+    // In production, this would run begin_import / put_memory_chunk / commit_memory /
+    // finalize_import on the personal canister for just these memories
+
+    Ok(())
+}
+
 /// Get personal canister ID for a user (convenience function)
 pub fn get_personal_canister_id(user: Principal) -> Option<Principal> {
     crate::memory::with_migration_state(|state| {
@@ -509,9 +1178,274 @@ pub fn get_my_personal_canister_id() -> Option<Principal> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::types::Error;
 
     #[test]
     fn test_simple_orchestrator() {
         assert_eq!(1 + 1, 2);
     }
+
+    fn in_flight(
+        user: Principal,
+        status: CreationStatus,
+        canister_id: Principal,
+    ) -> PersonalCanisterCreationStateData {
+        let mut state = PersonalCanisterCreationStateData::default();
+        state.creation_config.cycles_reserve = 500;
+        state.creation_states.insert(
+            user,
+            PersonalCanisterCreationState {
+                user,
+                status: status.clone(),
+                created_at: 1,
+                completed_at: None,
+                personal_canister_id: Some(canister_id),
+                cycles_consumed: 200,
+                error_message: None,
+                verification: None,
+                last_synced_at: None,
+            },
+        );
+        state.personal_canisters.insert(
+            canister_id,
+            PersonalCanisterRecord {
+                canister_id,
+                created_by: user,
+                created_at: 1,
+                status,
+                cycles_consumed: 200,
+            },
+        );
+        state
+    }
+
+    #[test]
+    fn test_failures_are_bucketed_by_stage() {
+        let canister_id = Principal::from_slice(&[10, 20, 30]);
+        let failed = Ok(PersonalCanisterCreationResponse {
+            success: false,
+            canister_id: Some(canister_id),
+            message: "boom".to_string(),
+        });
+
+        let mut stats = PersonalCanisterCreationStats::default();
+        for (i, stage) in [
+            CreationStatus::Creating,
+            CreationStatus::Importing,
+            CreationStatus::Importing,
+            CreationStatus::Verifying,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let user = Principal::from_slice(&[i as u8 + 1]);
+            let mut state = in_flight(user, stage, canister_id);
+            state.creation_stats = stats;
+
+            let mut creation_state = state.creation_states[&user].clone();
+            creation_state.status = CreationStatus::Failed;
+            creation_state.error_message = Some("boom".to_string());
+            apply_outcome(&mut state, &creation_state, &failed);
+
+            assert_eq!(state.creation_states[&user].status, CreationStatus::Failed);
+            stats = state.creation_stats;
+        }
+
+        assert_eq!(stats.total_failures, 4);
+        assert_eq!(
+            stats.failures_by_stage,
+            Some(FailuresByStage {
+                creating: 1,
+                importing: 2,
+                verifying: 1,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_overlapping_creations_only_one_proceeds() {
+        let user = Principal::from_slice(&[7, 7, 7]);
+
+        let first = RunningGuard::acquire(user);
+        assert!(first.is_some());
+        assert!(is_running(user));
+
+        // A second call arriving while the first is suspended at an await
+        assert!(RunningGuard::acquire(user).is_none());
+        // Other users are unaffected
+        assert!(RunningGuard::acquire(Principal::from_slice(&[8, 8, 8])).is_some());
+
+        // Finishing (or failing) the first run lets a later call proceed
+        drop(first);
+        assert!(!is_running(user));
+        assert!(RunningGuard::acquire(user).is_some());
+    }
+
+    #[test]
+    fn test_resync_sends_only_memories_created_since_last_sync() {
+        use crate::test_utils::{create_test_capsule_at, create_test_memory_at};
+
+        let user = Principal::from_slice(&[1, 2, 3]);
+        let canister_id = Principal::from_slice(&[10, 20, 30]);
+        let mut state = in_flight(user, CreationStatus::Completed, canister_id);
+        let mut capsule = create_test_capsule_at("c1", crate::types::PersonRef::Principal(user), 1);
+        capsule
+            .memories
+            .insert("m1".to_string(), create_test_memory_at("m1", "c1", 5));
+
+        // First sync: everything created after the original creation started
+        assert_eq!(sync_target(&state, user), Ok((canister_id, 1)));
+        let first = memories_created_since(&capsule, 1);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].0, "m1");
+        record_sync(&mut state, user, 10);
+
+        // A new memory after the sync is the only one sent next time
+        capsule
+            .memories
+            .insert("m2".to_string(), create_test_memory_at("m2", "c1", 12));
+        let (_, since) = sync_target(&state, user).unwrap();
+        assert_eq!(since, 10);
+        let second = memories_created_since(&capsule, since);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].0, "m2");
+    }
+
+    #[test]
+    fn test_resync_requires_completed_creation() {
+        let user = Principal::from_slice(&[1, 2, 3]);
+        let canister_id = Principal::from_slice(&[10, 20, 30]);
+        let state = in_flight(user, CreationStatus::Importing, canister_id);
+
+        assert!(matches!(sync_target(&state, user), Err(Error::Conflict(_))));
+        assert_eq!(
+            sync_target(&state, Principal::anonymous()),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_cancel_mid_install_rolls_back() {
+        let user = Principal::from_slice(&[1, 2, 3]);
+        let canister_id = Principal::from_slice(&[10, 20, 30]);
+        let mut state = in_flight(user, CreationStatus::Installing, canister_id);
+
+        assert_eq!(cancel_creation(&mut state, user, 9), Ok(Some(canister_id)));
+
+        let cancelled = &state.creation_states[&user];
+        assert!(is_cancelled(cancelled));
+        assert_eq!(cancelled.completed_at, Some(9));
+        assert_eq!(state.creation_config.cycles_reserve, 700);
+        assert_eq!(
+            state.personal_canisters[&canister_id].status,
+            CreationStatus::Failed
+        );
+        assert_eq!(state.creation_stats.total_failures, 1);
+
+        // Cancelling twice is rejected
+        assert!(matches!(
+            cancel_creation(&mut state, user, 10),
+            Err(Error::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_detailed_statuses_reflect_stored_state() {
+        let user = Principal::from_slice(&[1, 2, 3]);
+        let canister_id = Principal::from_slice(&[10, 20, 30]);
+        let mut state = in_flight(user, CreationStatus::Installing, canister_id);
+        let other = Principal::from_slice(&[4, 5, 6]);
+        let mut failed = state.creation_states[&user].clone();
+        failed.user = other;
+        failed.status = CreationStatus::Failed;
+        failed.personal_canister_id = None;
+        failed.error_message = Some("Export failed: no capsule".to_string());
+        state.creation_states.insert(other, failed);
+
+        let detail = user_detailed_status(&state, user).unwrap();
+        assert_eq!(detail.status, CreationStatus::Installing);
+        assert_eq!(detail.canister_id, Some(canister_id));
+        assert_eq!(detail.cycles_consumed, 200);
+        assert_eq!(detail.progress_message, "Installing WASM module...");
+
+        assert_eq!(detailed_statuses(&state, None).len(), 2);
+        let failures = detailed_statuses(&state, Some(&CreationStatus::Failed));
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, other);
+        assert_eq!(
+            failures[0].1.error_message.as_deref(),
+            Some("Export failed: no capsule")
+        );
+        assert!(detailed_statuses(&state, Some(&CreationStatus::Completed)).is_empty());
+    }
+
+    #[test]
+    fn test_percent_complete_increases_across_stages() {
+        let import = |done, total| ImportProgress {
+            memories_imported: done,
+            total_memories: total,
+            bytes_transferred: 0,
+            total_bytes: 0,
+        };
+        let percents = [
+            percent_complete(&CreationStatus::NotStarted, None),
+            percent_complete(&CreationStatus::Exporting, None),
+            percent_complete(&CreationStatus::Creating, None),
+            percent_complete(&CreationStatus::Installing, None),
+            percent_complete(&CreationStatus::Importing, Some(&import(0, 4))),
+            percent_complete(&CreationStatus::Importing, Some(&import(2, 4))),
+            percent_complete(&CreationStatus::Importing, Some(&import(4, 4))),
+            percent_complete(&CreationStatus::Verifying, None),
+            percent_complete(&CreationStatus::Completed, None),
+        ];
+
+        assert!(
+            percents.windows(2).all(|pair| pair[0] < pair[1]),
+            "{percents:?}"
+        );
+        assert_eq!(percents[8], 100);
+    }
+
+    #[test]
+    fn test_paused_blocks_new_creations_but_not_in_flight_ones() {
+        let user = Principal::from_slice(&[1, 2, 3]);
+        let canister_id = Principal::from_slice(&[10, 20, 30]);
+        let mut state = in_flight(user, CreationStatus::Importing, canister_id);
+        state.creation_config.enabled = true;
+        assert_eq!(admission(&state.creation_config), Ok(()));
+
+        state.creation_config.paused = Some(true);
+        assert_eq!(
+            admission(&state.creation_config),
+            Err(PAUSED_MESSAGE.to_string())
+        );
+        assert_eq!(stage_gate(&state, user), StageGate::Continue);
+
+        // Disabled is a hard off, whether or not paused
+        state.creation_config.enabled = false;
+        assert_eq!(
+            admission(&state.creation_config),
+            Err(DISABLED_MESSAGE.to_string())
+        );
+        assert_eq!(stage_gate(&state, user), StageGate::Disabled);
+    }
+
+    #[test]
+    fn test_cancel_rejects_completed_creation() {
+        let user = Principal::from_slice(&[1, 2, 3]);
+        let canister_id = Principal::from_slice(&[10, 20, 30]);
+        let mut state = in_flight(user, CreationStatus::Completed, canister_id);
+
+        assert!(matches!(
+            cancel_creation(&mut state, user, 9),
+            Err(Error::Conflict(_))
+        ));
+        assert_eq!(state.creation_config.cycles_reserve, 500);
+        assert_eq!(
+            cancel_creation(&mut state, Principal::anonymous(), 9),
+            Err(Error::NotFound)
+        );
+    }
 }
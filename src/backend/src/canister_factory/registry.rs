@@ -119,6 +119,35 @@ pub fn remove_registry_entry(canister_id: Principal) -> Result<(), String> {
     })
 }
 
+/// Canisters left behind by failed creations: still recorded, never handed off
+pub fn orphaned_canisters(
+    state: &PersonalCanisterCreationStateData,
+) -> Vec<PersonalCanisterRecord> {
+    state
+        .personal_canisters
+        .values()
+        .filter(|record| record.status == CreationStatus::Failed)
+        .cloned()
+        .collect()
+}
+
+/// Ensure `canister_id` is a recorded orphan before it is deleted
+pub fn ensure_orphaned(
+    state: &PersonalCanisterCreationStateData,
+    canister_id: Principal,
+) -> Result<(), String> {
+    match state.personal_canisters.get(&canister_id) {
+        None => Err(format!(
+            "Registry entry not found for canister {canister_id}"
+        )),
+        Some(record) if record.status != CreationStatus::Failed => Err(format!(
+            "Canister {} is not orphaned (status: {:?})",
+            canister_id, record.status
+        )),
+        Some(_) => Ok(()),
+    }
+}
+
 /// Finalize registry after successful personal canister creation
 /// This function updates the registry with final status and cycles consumed
 pub fn finalize_registry_after_creation(
@@ -46,6 +46,66 @@ pub struct DetailedCreationStatus {
     pub cycles_consumed: u128,
     pub error_message: Option<String>,
     pub progress_message: String,
+    pub percent_complete: u8, // estimate across all stages, 0 once failed
+    pub import_progress: Option<ImportProgress>, // from the user's latest import session
+    pub verification: Option<ImportVerification>, // set once the verify step has run
+}
+
+/// Progress of the import sub-phase
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportProgress {
+    pub memories_imported: u32,
+    pub total_memories: u32,
+    pub bytes_transferred: u64,
+    pub total_bytes: u64,
+}
+
+/// Memory count and payload bytes of a capsule (inline plus ICP blob assets)
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportCounts {
+    pub memory_count: u64,
+    pub total_bytes: u64,
+}
+
+/// Source counts against what the personal canister reports after the import
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportVerification {
+    pub expected: ImportCounts,
+    pub actual: ImportCounts,
+}
+
+/// The personal canister does not hold what was exported from the source capsule
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VerificationMismatch {
+    pub expected: ImportCounts,
+    pub actual: ImportCounts,
+}
+
+impl std::fmt::Display for VerificationMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Verification mismatch: expected {} memories ({} bytes), found {} memories ({} bytes)",
+            self.expected.memory_count,
+            self.expected.total_bytes,
+            self.actual.memory_count,
+            self.actual.total_bytes
+        )
+    }
+}
+
+impl ImportVerification {
+    /// `Err` unless both the memory count and the byte total match
+    pub fn check(&self) -> Result<(), VerificationMismatch> {
+        if self.expected == self.actual {
+            Ok(())
+        } else {
+            Err(VerificationMismatch {
+                expected: self.expected,
+                actual: self.actual,
+            })
+        }
+    }
 }
 
 /// Exported capsule data for migration
@@ -78,6 +138,26 @@ pub struct DataManifest {
     pub manifest_version: String,
 }
 
+/// Dry-run estimate of a migration, computed without starting one
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MigrationEstimate {
+    pub memory_count: u64,
+    pub capsule_bytes: u64, // serialized capsule: metadata, memories, inline assets, galleries
+    pub blob_bytes: u64,    // ICP blob assets referenced by the capsule's memories
+    pub chunk_count: u64,   // import chunks at the configured max chunk size
+    pub estimated_cycles: u128, // create + install + import
+    pub estimated_seconds: u64,
+}
+
+/// Result of re-syncing new memories into an existing personal canister
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SyncReport {
+    pub canister_id: Principal,
+    pub memories_synced: u32,
+    pub since: u64, // memories created after this time were sent
+    pub synced_at: u64,
+}
+
 /// Personal canister creation state for tracking individual user creations
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct PersonalCanisterCreationState {
@@ -88,6 +168,8 @@ pub struct PersonalCanisterCreationState {
     pub personal_canister_id: Option<Principal>,
     pub cycles_consumed: u128,
     pub error_message: Option<String>,
+    pub verification: Option<ImportVerification>, // count comparison from the verify step
+    pub last_synced_at: Option<u64>, // last re-sync; memories created after it are sent next
 }
 
 /// Configuration for personal canister creation system
@@ -97,6 +179,7 @@ pub struct PersonalCanisterCreationConfig {
     pub cycles_reserve: u128,
     pub min_cycles_threshold: u128,
     pub admin_principals: BTreeSet<Principal>,
+    pub paused: Option<bool>, // no new creations while paused; in-flight ones continue
 }
 
 impl Default for PersonalCanisterCreationConfig {
@@ -106,6 +189,7 @@ impl Default for PersonalCanisterCreationConfig {
             cycles_reserve: 0,
             min_cycles_threshold: 1_000_000_000_000, // 1T cycles default threshold
             admin_principals: BTreeSet::new(),
+            paused: None,
         }
     }
 }
@@ -127,6 +211,34 @@ pub struct PersonalCanisterCreationStats {
     pub total_successes: u64,
     pub total_failures: u64,
     pub total_cycles_consumed: u128,
+    pub paused: Option<bool>, // filled in from the config when stats are read
+    pub failures_by_stage: Option<FailuresByStage>, // None until the first failed run
+}
+
+/// Failed creation runs, by the stage they failed in
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FailuresByStage {
+    pub exporting: u64,
+    pub creating: u64,
+    pub installing: u64,
+    pub importing: u64,
+    pub verifying: u64, // includes controller handoff, which runs after verification
+}
+
+impl FailuresByStage {
+    /// Count a failure in `stage`; terminal states are not stages and are ignored
+    pub fn record(&mut self, stage: &CreationStatus) {
+        let bucket = match stage {
+            // Exporting is not checkpointed, so the last saved stage is still NotStarted
+            CreationStatus::NotStarted | CreationStatus::Exporting => &mut self.exporting,
+            CreationStatus::Creating => &mut self.creating,
+            CreationStatus::Installing => &mut self.installing,
+            CreationStatus::Importing => &mut self.importing,
+            CreationStatus::Verifying => &mut self.verifying,
+            CreationStatus::Completed | CreationStatus::Failed => return,
+        };
+        *bucket += 1;
+    }
 }
 
 /// Minimal configuration for creating personal canisters
@@ -142,18 +254,31 @@ pub struct ImportConfig {
     pub max_chunk_size: u64,
     pub max_total_import_size: u64,
     pub session_timeout_seconds: u64,
+    pub max_active_sessions_per_user: Option<u32>, // None = DEFAULT_MAX_ACTIVE_IMPORT_SESSIONS
 }
 
+/// Active import sessions a user may hold at once unless configured otherwise
+pub const DEFAULT_MAX_ACTIVE_IMPORT_SESSIONS: u32 = 1;
+
 impl Default for ImportConfig {
     fn default() -> Self {
         Self {
             max_chunk_size: 2_000_000,          // 2MB max chunk size
             max_total_import_size: 100_000_000, // 100MB max total import size
             session_timeout_seconds: 3600,      // 1 hour session timeout
+            max_active_sessions_per_user: None,
         }
     }
 }
 
+impl ImportConfig {
+    /// Active import sessions a single user may hold at once
+    pub fn active_session_limit(&self) -> u32 {
+        self.max_active_sessions_per_user
+            .unwrap_or(DEFAULT_MAX_ACTIVE_IMPORT_SESSIONS)
+    }
+}
+
 /// Import session state for tracking chunked data transfers
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct ImportSession {
@@ -191,6 +316,26 @@ pub struct MemoryImportState {
     pub is_complete: bool,
 }
 
+/// Progress of one import session, for the caller's migration UI
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportSessionProgress {
+    pub session_id: String,
+    pub status: ImportSessionStatus,
+    pub completed_memories: u32,
+    pub in_progress_memories: Vec<MemoryChunkProgress>, // sorted by memory_id
+    pub total_received_size: u64,
+    pub total_expected_size: u64,
+}
+
+/// Chunks received so far for a memory that has not been committed yet
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MemoryChunkProgress {
+    pub memory_id: String,
+    pub received_chunks: u32,
+    pub expected_chunks: u32,
+    pub received_size: u64,
+}
+
 /// Individual chunk data
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct ChunkData {
@@ -243,6 +388,7 @@ pub struct ImportFinalizationResponse {
     pub message: String,
     pub total_memories_imported: u32,
     pub total_size_imported: u64,
+    pub skipped_memories: Vec<String>, // in-progress memories dropped by a partial finalize
 }
 
 /// Cycles reserve status including threshold information (admin function)
@@ -108,6 +108,67 @@ pub async fn verify_migration_data(
     Ok(())
 }
 
+/// Memory count and payload bytes (inline plus ICP blob assets) of `memories`
+pub fn memory_counts<'a>(
+    memories: impl IntoIterator<Item = &'a crate::types::Memory>,
+) -> ImportCounts {
+    memories
+        .into_iter()
+        .fold(ImportCounts::default(), |mut counts, memory| {
+            counts.memory_count += 1;
+            counts.total_bytes += memory
+                .inline_assets
+                .iter()
+                .map(|asset| asset.bytes.len() as u64)
+                .sum::<u64>();
+            counts.total_bytes += memory
+                .blob_internal_assets
+                .iter()
+                .map(|asset| asset.blob_ref.len)
+                .sum::<u64>();
+            counts
+        })
+}
+
+/// Ask the personal canister how many memories and bytes it holds after the import
+pub async fn fetch_imported_counts(
+    canister_id: Principal,
+    export_data: &ExportData,
+) -> Result<ImportCounts, String> {
+    ic_cdk::println!("Fetching imported counts from canister {}", canister_id);
+
+    // TODO: Replace with the actual call once the import writes to the personal canister
+    // let counts: ImportCounts = ic_cdk::call::Call::bounded_wait(canister_id, "memories_count")
+    //     .await
+    //     .map_err(|e| format!("memories_count call failed: {:?}", e))?
+    //     .candid::<Result<ImportCounts, crate::types::Error>>()
+    //     .map_err(|e| format!("Failed to decode memories_count reply: {:?}", e))?
+    //     .map_err(|e| format!("memories_count rejected: {:?}", e))?;
+
+    // For MVP, the simulated import transfers every exported memory
+    let counts = memory_counts(export_data.memories.iter().map(|(_, memory)| memory));
+
+    Ok(counts)
+}
+
+/// Compare the source capsule's counts with what the personal canister reports
+pub async fn verify_import_counts(
+    canister_id: Principal,
+    export_data: &ExportData,
+) -> Result<ImportVerification, String> {
+    let expected = memory_counts(export_data.memories.iter().map(|(_, memory)| memory));
+    let actual = fetch_imported_counts(canister_id, export_data).await?;
+
+    ic_cdk::println!(
+        "Import counts for canister {}: expected {:?}, actual {:?}",
+        canister_id,
+        expected,
+        actual
+    );
+
+    Ok(ImportVerification { expected, actual })
+}
+
 /// Check API version compatibility between factory and personal canister
 pub async fn check_api_version_compatibility(canister_id: Principal) -> Result<(), String> {
     ic_cdk::println!(
@@ -270,6 +331,14 @@ mod tests {
             bound_to_neon: false, // Default to not bound to Neon
             inline_bytes_used: 0,
             hosting_preferences: HostingPreferences::default(),
+            created_by: None,
+            role_templates: None,
+            blob_bytes_quota: None,
+            blob_bytes_used: None,
+            tags: None,
+            access_entries: None,
+            default_memory_access: None,
+            public_memory_default_allowed: None,
         }
     }
 
@@ -348,8 +417,12 @@ mod tests {
                     language: None,
                     format: Some("text".to_string()),
                 }),
+                order: None,
             }],
             blob_external_assets: vec![],
+            locked: None,
+            pending: None,
+            expires_at: None,
         }
     }
 
@@ -449,6 +522,40 @@ mod tests {
         assert!(result.unwrap_err().contains("Unsupported manifest version"));
     }
 
+    #[test]
+    fn test_import_counts_fail_when_a_memory_is_dropped() {
+        let export_data = create_test_export_data();
+        let expected = memory_counts(export_data.memories.iter().map(|(_, memory)| memory));
+        assert_eq!(
+            expected,
+            ImportCounts {
+                memory_count: 2,
+                total_bytes: 200,
+            }
+        );
+
+        // The import lost the last memory
+        let imported = &export_data.memories[..export_data.memories.len() - 1];
+        let actual = memory_counts(imported.iter().map(|(_, memory)| memory));
+        let verification = ImportVerification { expected, actual };
+
+        assert_eq!(
+            verification.check(),
+            Err(VerificationMismatch {
+                expected,
+                actual: ImportCounts {
+                    memory_count: 1,
+                    total_bytes: 100,
+                },
+            })
+        );
+        let complete = ImportVerification {
+            expected,
+            actual: expected,
+        };
+        assert!(complete.check().is_ok());
+    }
+
     // Test API compatibility checks
     #[tokio::test]
     async fn test_check_api_version_compatibility_success() {
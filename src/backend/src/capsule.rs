@@ -1,4 +1,5 @@
 // Declare submodules
+pub mod access;
 pub mod api_types;
 pub mod commands;
 pub mod domain;
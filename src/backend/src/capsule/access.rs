@@ -0,0 +1,1162 @@
+//! Access grants on capsule resources
+//!
+//! Grant/revoke individual `AccessEntry`s on memories, galleries, folders and
+//! the capsule itself.
+//! Every change is recorded in the capsule event feed as `AccessChanged` so
+//! affected principals can invalidate cached permissions, and mirrored into the
+//! reverse grant index behind `capsules_shared_with_me`.
+
+use crate::capsule::api_types::SharingGrantee;
+use crate::capsule::domain::{
+    effective_perm_mask, AccessCondition, AccessEntry, Capsule, GrantSource, Perm, PersonRef,
+    PrincipalContext, ResourceRole, ResourceType, RoleTemplate,
+};
+use crate::capsule_store::{types::PaginationOrder as Order, CapsuleStore};
+use crate::events::{self, AccessChange, CapsuleEventKind};
+use crate::grant_index;
+use crate::memory::{with_capsule_store, with_capsule_store_mut};
+use crate::types::{BulkFailure, BulkResult, Error};
+use crate::utils::uuid_v7;
+
+/// Mutable access entries of a resource inside `capsule`
+fn access_entries_mut<'a>(
+    capsule: &'a mut Capsule,
+    resource_type: &ResourceType,
+    resource_id: &str,
+) -> std::result::Result<&'a mut Vec<AccessEntry>, Error> {
+    let entries = match resource_type {
+        ResourceType::Memory => capsule
+            .memories
+            .get_mut(resource_id)
+            .map(|memory| &mut memory.access_entries),
+        ResourceType::Gallery => capsule
+            .galleries
+            .get_mut(resource_id)
+            .map(|gallery| &mut gallery.access_entries),
+        ResourceType::Folder => capsule
+            .folders
+            .get_mut(resource_id)
+            .map(|folder| &mut folder.access_entries),
+        ResourceType::Capsule => {
+            (capsule.id == resource_id).then(|| capsule.access_entries.get_or_insert_with(Vec::new))
+        }
+    };
+    entries.ok_or(Error::NotFound)
+}
+
+/// Whether `capsule` holds the given resource
+fn contains_resource(capsule: &Capsule, resource_type: &ResourceType, resource_id: &str) -> bool {
+    match resource_type {
+        ResourceType::Memory => capsule.memories.contains_key(resource_id),
+        ResourceType::Gallery => capsule.galleries.contains_key(resource_id),
+        ResourceType::Folder => capsule.folders.contains_key(resource_id),
+        ResourceType::Capsule => capsule.id == resource_id,
+    }
+}
+
+/// Record an `AccessChanged` event for `person` on a resource
+fn emit_access_changed(
+    capsule_id: &str,
+    resource_type: &ResourceType,
+    resource_id: &str,
+    person: &PersonRef,
+    change: AccessChange,
+    now: u64,
+) -> u64 {
+    events::emit(
+        capsule_id,
+        now,
+        CapsuleEventKind::AccessChanged {
+            resource_type: resource_type.clone(),
+            resource_id: resource_id.to_string(),
+            affected_principal: person.clone(),
+            change,
+        },
+    )
+}
+
+/// Grant `person` access to a resource in `capsule`
+///
+/// Replaces any existing individual grant for `person` (owner entries are kept).
+#[allow(clippy::too_many_arguments)]
+pub fn grant_access(
+    capsule: &mut Capsule,
+    caller: &PersonRef,
+    resource_type: &ResourceType,
+    resource_id: &str,
+    person: &PersonRef,
+    role: ResourceRole,
+    perm_mask: u32,
+    now: u64,
+) -> std::result::Result<AccessEntry, Error> {
+    if !capsule.has_write_access(caller) {
+        return Err(Error::Unauthorized);
+    }
+
+    let entries = access_entries_mut(capsule, resource_type, resource_id)?;
+    entries.retain(|entry| {
+        entry.is_public
+            || entry.role == ResourceRole::Owner
+            || entry.person_ref.as_ref() != Some(person)
+    });
+
+    let entry = AccessEntry {
+        id: uuid_v7::uuid_v7_weak(),
+        person_ref: Some(person.clone()),
+        is_public: false,
+        grant_source: GrantSource::User,
+        source_id: None,
+        role,
+        perm_mask,
+        invited_by_person_ref: Some(caller.clone()),
+        created_at: now,
+        updated_at: now,
+        condition: AccessCondition::Immediate,
+    };
+    entries.push(entry.clone());
+    capsule.updated_at = now;
+    grant_index::sync(person, &capsule.id, true);
+
+    emit_access_changed(
+        &capsule.id,
+        resource_type,
+        resource_id,
+        person,
+        AccessChange::Granted,
+        now,
+    );
+    Ok(entry)
+}
+
+/// `ResourceRole` recorded on entries granted from the named template
+fn role_for_template(name: &str) -> ResourceRole {
+    match name {
+        "owner" => ResourceRole::Owner,
+        "admin" => ResourceRole::Admin,
+        "guest" => ResourceRole::Guest,
+        _ => ResourceRole::Member,
+    }
+}
+
+/// Reject a mask carrying bits `caller` doesn't hold in `capsule`
+fn ensure_grantable(
+    capsule: &Capsule,
+    caller: &PersonRef,
+    perm_mask: u32,
+) -> std::result::Result<(), Error> {
+    if perm_mask & !capsule.held_perm_mask(caller) != 0 {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Grant `person` access to a resource using a named role template
+///
+/// Resolves the name against the capsule's custom templates, then the built-ins,
+/// expands it to its `perm_mask` and delegates to `grant_access`. Unknown role
+/// names are rejected with `InvalidArgument`; templates carrying permissions the
+/// caller doesn't hold with `Unauthorized`.
+pub fn grant_role(
+    capsule: &mut Capsule,
+    caller: &PersonRef,
+    resource_type: &ResourceType,
+    resource_id: &str,
+    person: &PersonRef,
+    role_name: &str,
+    now: u64,
+) -> std::result::Result<AccessEntry, Error> {
+    let template = capsule
+        .resolve_role_template(role_name)
+        .ok_or_else(|| Error::InvalidArgument(format!("unknown role: {}", role_name)))?;
+    ensure_grantable(capsule, caller, template.perm_mask)?;
+
+    grant_access(
+        capsule,
+        caller,
+        resource_type,
+        resource_id,
+        person,
+        role_for_template(&template.name),
+        template.perm_mask,
+        now,
+    )
+}
+
+/// Most memories and galleries `grant_role_capsule_wide` updates per call
+pub const CAPSULE_GRANT_BATCH: usize = 200;
+
+/// Whether `entry` is an individual grant to `grantee` (owner and public entries never are)
+fn is_grant_to(entry: &AccessEntry, grantee: &SharingGrantee) -> bool {
+    if entry.is_public || entry.role == ResourceRole::Owner {
+        return false;
+    }
+    match grantee {
+        SharingGrantee::Person(person) => entry.person_ref.as_ref() == Some(person),
+        SharingGrantee::Group(group_id) => {
+            entry.grant_source == GrantSource::Group && entry.source_id.as_ref() == Some(group_id)
+        }
+    }
+}
+
+/// Grant `grantee` a named role on `capsule` and, with `cascade`, on its memories and galleries
+///
+/// The role is resolved and checked as in `grant_role`; ownership can't be
+/// granted this way, and a group must be one of the capsule's connection
+/// groups. The capsule-level entry is written on every call. With `cascade`, at
+/// most `CAPSULE_GRANT_BATCH` memories and galleries not yet carrying the same
+/// grant are updated (memories first, by id); those already carrying it are
+/// skipped, so calling again resumes, and a call updating fewer than a full
+/// batch has reached the end. Returns the ids of the resources updated.
+pub fn grant_role_capsule_wide(
+    capsule: &mut Capsule,
+    caller: &PersonRef,
+    grantee: &SharingGrantee,
+    role_name: &str,
+    cascade: bool,
+    now: u64,
+) -> std::result::Result<BulkResult<String>, Error> {
+    if !capsule.has_write_access(caller) {
+        return Err(Error::Unauthorized);
+    }
+    let template = capsule
+        .resolve_role_template(role_name)
+        .ok_or_else(|| Error::InvalidArgument(format!("unknown role: {}", role_name)))?;
+    ensure_grantable(capsule, caller, template.perm_mask)?;
+    let role = role_for_template(&template.name);
+    if role == ResourceRole::Owner {
+        return Err(Error::InvalidArgument(
+            "ownership is shared through capsule owners, not grants".to_string(),
+        ));
+    }
+    let affected = match grantee {
+        SharingGrantee::Person(person) => vec![person.clone()],
+        SharingGrantee::Group(group_id) => capsule
+            .connection_groups
+            .get(group_id)
+            .ok_or(Error::NotFound)?
+            .members
+            .clone(),
+    };
+
+    let mut targets = vec![(ResourceType::Capsule, capsule.id.clone())];
+    if cascade {
+        let pending = |entries: &[AccessEntry]| {
+            !entries.iter().any(|entry| {
+                is_grant_to(entry, grantee)
+                    && entry.role == role
+                    && entry.perm_mask == template.perm_mask
+            })
+        };
+        let mut memory_ids: Vec<String> = capsule
+            .memories
+            .values()
+            .filter(|memory| pending(&memory.access_entries))
+            .map(|memory| memory.id.clone())
+            .collect();
+        memory_ids.sort();
+        let mut gallery_ids: Vec<String> = capsule
+            .galleries
+            .values()
+            .filter(|gallery| pending(&gallery.access_entries))
+            .map(|gallery| gallery.id.clone())
+            .collect();
+        gallery_ids.sort();
+        targets.extend(
+            memory_ids
+                .into_iter()
+                .map(|id| (ResourceType::Memory, id))
+                .chain(
+                    gallery_ids
+                        .into_iter()
+                        .map(|id| (ResourceType::Gallery, id)),
+                )
+                .take(CAPSULE_GRANT_BATCH),
+        );
+    }
+
+    let (person_ref, grant_source, source_id) = match grantee {
+        SharingGrantee::Person(person) => (Some(person.clone()), GrantSource::User, None),
+        SharingGrantee::Group(group_id) => (None, GrantSource::Group, Some(group_id.clone())),
+    };
+    let mut result = BulkResult {
+        ok: Vec::new(),
+        failed: Vec::new(),
+    };
+    for (resource_type, resource_id) in targets {
+        let entries = match access_entries_mut(capsule, &resource_type, &resource_id) {
+            Ok(entries) => entries,
+            Err(err) => {
+                result.failed.push(BulkFailure {
+                    id: resource_id,
+                    err,
+                });
+                continue;
+            }
+        };
+        entries.retain(|entry| !is_grant_to(entry, grantee));
+        entries.push(AccessEntry {
+            id: uuid_v7::uuid_v7_weak(),
+            person_ref: person_ref.clone(),
+            is_public: false,
+            grant_source: grant_source.clone(),
+            source_id: source_id.clone(),
+            role: role.clone(),
+            perm_mask: template.perm_mask,
+            invited_by_person_ref: Some(caller.clone()),
+            created_at: now,
+            updated_at: now,
+            condition: AccessCondition::Immediate,
+        });
+        result.ok.push(resource_id);
+    }
+    capsule.updated_at = now;
+
+    // Group grants aren't indexed: `has_grant_for` only counts individual grants
+    if let Some(person) = &person_ref {
+        grant_index::sync(person, &capsule.id, true);
+    }
+    for person in &affected {
+        emit_access_changed(
+            &capsule.id,
+            &ResourceType::Capsule,
+            &capsule.id,
+            person,
+            AccessChange::Granted,
+            now,
+        );
+    }
+    Ok(result)
+}
+
+/// Add (or replace) a custom role template on `capsule`
+///
+/// The mask must be non-empty, use only defined `Perm` bits and stay within what
+/// the caller holds, so a controller can't mint an "owner-plus" template.
+pub fn add_role_template(
+    capsule: &mut Capsule,
+    caller: &PersonRef,
+    template: RoleTemplate,
+    now: u64,
+) -> std::result::Result<RoleTemplate, Error> {
+    if !capsule.has_write_access(caller) {
+        return Err(Error::Unauthorized);
+    }
+    if template.name.trim().is_empty() {
+        return Err(Error::InvalidArgument(
+            "role template name must not be empty".to_string(),
+        ));
+    }
+    if template.perm_mask == 0 || Perm::from_bits(template.perm_mask).is_none() {
+        return Err(Error::InvalidArgument(format!(
+            "invalid perm_mask: {:#b}",
+            template.perm_mask
+        )));
+    }
+    ensure_grantable(capsule, caller, template.perm_mask)?;
+
+    let templates = capsule.role_templates.get_or_insert_with(Vec::new);
+    templates.retain(|existing| existing.name != template.name);
+    templates.push(template.clone());
+    capsule.updated_at = now;
+    Ok(template)
+}
+
+/// Remove a custom role template from `capsule`
+///
+/// Existing grants made from it keep their mask; built-ins can't be removed.
+pub fn remove_role_template(
+    capsule: &mut Capsule,
+    caller: &PersonRef,
+    name: &str,
+    now: u64,
+) -> std::result::Result<RoleTemplate, Error> {
+    if !capsule.has_write_access(caller) {
+        return Err(Error::Unauthorized);
+    }
+
+    let templates = capsule.role_templates.as_mut().ok_or(Error::NotFound)?;
+    let index = templates
+        .iter()
+        .position(|template| template.name == name)
+        .ok_or(Error::NotFound)?;
+    let removed = templates.remove(index);
+    capsule.updated_at = now;
+    Ok(removed)
+}
+
+/// Revoke the individual grants of `person` on a resource in `capsule`
+///
+/// Always emits an `AccessChanged` event - even when nothing was removed - so
+/// clients holding stale permissions can reconcile. Returns the number of
+/// entries removed; owner entries are never revoked here.
+pub fn revoke_access(
+    capsule: &mut Capsule,
+    caller: &PersonRef,
+    resource_type: &ResourceType,
+    resource_id: &str,
+    person: &PersonRef,
+    now: u64,
+) -> std::result::Result<u32, Error> {
+    if !capsule.has_write_access(caller) {
+        return Err(Error::Unauthorized);
+    }
+
+    let entries = access_entries_mut(capsule, resource_type, resource_id)?;
+    let before = entries.len();
+    entries.retain(|entry| {
+        entry.is_public
+            || entry.role == ResourceRole::Owner
+            || entry.person_ref.as_ref() != Some(person)
+    });
+    let removed = (before - entries.len()) as u32;
+    if removed > 0 {
+        capsule.updated_at = now;
+        grant_index::sync(person, &capsule.id, capsule.has_grant_for(person));
+    }
+
+    emit_access_changed(
+        &capsule.id,
+        resource_type,
+        resource_id,
+        person,
+        AccessChange::Revoked,
+        now,
+    );
+    Ok(removed)
+}
+
+/// Find the capsule holding a resource the caller can manage
+fn find_capsule_for_resource<S: CapsuleStore>(
+    store: &S,
+    caller: &PersonRef,
+    resource_type: &ResourceType,
+    resource_id: &str,
+) -> std::result::Result<Capsule, Error> {
+    store
+        .paginate(None, u32::MAX, Order::Asc)
+        .items
+        .into_iter()
+        .find(|capsule| {
+            contains_resource(capsule, resource_type, resource_id)
+                && capsule.has_read_access(caller)
+        })
+        .ok_or(Error::NotFound)
+}
+
+/// Grant `person` access to a resource (endpoint entry point)
+pub fn resources_grant_access(
+    resource_type: ResourceType,
+    resource_id: String,
+    person: PersonRef,
+    role: ResourceRole,
+    perm_mask: u32,
+) -> std::result::Result<AccessEntry, Error> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store_mut(|store| {
+        let capsule = find_capsule_for_resource(&*store, &caller, &resource_type, &resource_id)?;
+        store.update_with(&capsule.id, |capsule| {
+            grant_access(
+                capsule,
+                &caller,
+                &resource_type,
+                &resource_id,
+                &person,
+                role,
+                perm_mask,
+                now,
+            )
+        })
+    })
+}
+
+/// Grant `person` a named role on a resource (endpoint entry point)
+pub fn resources_grant_role(
+    resource_type: ResourceType,
+    resource_id: String,
+    person: PersonRef,
+    role_name: String,
+) -> std::result::Result<AccessEntry, Error> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store_mut(|store| {
+        let capsule = find_capsule_for_resource(&*store, &caller, &resource_type, &resource_id)?;
+        store.update_with(&capsule.id, |capsule| {
+            grant_role(
+                capsule,
+                &caller,
+                &resource_type,
+                &resource_id,
+                &person,
+                &role_name,
+                now,
+            )
+        })
+    })
+}
+
+/// Grant a named role across a capsule (endpoint entry point)
+pub fn capsules_grant_role(
+    capsule_id: String,
+    grantee: SharingGrantee,
+    role_name: String,
+    cascade: bool,
+) -> std::result::Result<BulkResult<String>, Error> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store_mut(|store| {
+        store.update_with(&capsule_id, |capsule| {
+            grant_role_capsule_wide(capsule, &caller, &grantee, &role_name, cascade, now)
+        })
+    })
+}
+
+/// Add a custom role template to a capsule (endpoint entry point)
+pub fn capsule_add_role_template(
+    capsule_id: String,
+    template: RoleTemplate,
+) -> std::result::Result<RoleTemplate, Error> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store_mut(|store| {
+        store.update_with(&capsule_id, |capsule| {
+            add_role_template(capsule, &caller, template, now)
+        })
+    })
+}
+
+/// Remove a custom role template from a capsule (endpoint entry point)
+pub fn capsule_remove_role_template(
+    capsule_id: String,
+    name: String,
+) -> std::result::Result<RoleTemplate, Error> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store_mut(|store| {
+        store.update_with(&capsule_id, |capsule| {
+            remove_role_template(capsule, &caller, &name, now)
+        })
+    })
+}
+
+/// Built-in plus custom role templates of a capsule (caller needs read access)
+pub fn capsule_list_role_templates(
+    capsule_id: String,
+) -> std::result::Result<Vec<RoleTemplate>, Error> {
+    let caller = PersonRef::from_caller();
+
+    with_capsule_store(|store| {
+        let capsule = store.get(&capsule_id).ok_or(Error::NotFound)?;
+        if !capsule.has_read_access(&caller) {
+            return Err(Error::Unauthorized);
+        }
+        Ok(capsule.list_role_templates())
+    })
+}
+
+/// Revoke `person`'s access to a resource (endpoint entry point)
+pub fn resources_revoke_access(
+    resource_type: ResourceType,
+    resource_id: String,
+    person: PersonRef,
+) -> std::result::Result<(), Error> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store_mut(|store| {
+        let capsule = find_capsule_for_resource(&*store, &caller, &resource_type, &resource_id)?;
+        store.update_with(&capsule.id, |capsule| {
+            revoke_access(capsule, &caller, &resource_type, &resource_id, &person, now).map(|_| ())
+        })
+    })
+}
+
+/// Permission bits `ctx` holds on a resource of `capsule`, or None if the capsule doesn't hold it
+///
+/// Capsule-wide bits (owners, controllers) apply to every resource inside it;
+/// the capsule, its memories, galleries and folders add their own access entries.
+pub fn resource_perm_mask(
+    capsule: &Capsule,
+    resource_type: &ResourceType,
+    resource_id: &str,
+    ctx: &PrincipalContext,
+) -> Option<u32> {
+    let entries_mask = match resource_type {
+        ResourceType::Memory => capsule
+            .memories
+            .get(resource_id)
+            .map(|memory| effective_perm_mask(memory, ctx)),
+        ResourceType::Gallery => capsule
+            .galleries
+            .get(resource_id)
+            .map(|gallery| effective_perm_mask(gallery, ctx)),
+        ResourceType::Folder => capsule
+            .folders
+            .get(resource_id)
+            .map(|folder| effective_perm_mask(folder, ctx)),
+        ResourceType::Capsule => {
+            (capsule.id == resource_id).then(|| effective_perm_mask(capsule, ctx))
+        }
+    }?;
+    Some(entries_mask | capsule.held_perm_mask(&PersonRef::Principal(ctx.principal)))
+}
+
+/// Check many `(resource, perm_mask)` queries at once, in input order
+///
+/// A query passes when every requested bit is held; missing resources and
+/// empty masks give `false`.
+pub fn can_access_bulk(
+    capsules: &[Capsule],
+    ctx: &PrincipalContext,
+    queries: &[(ResourceType, String, u32)],
+) -> Vec<bool> {
+    queries
+        .iter()
+        .map(|(resource_type, resource_id, want)| {
+            capsules
+                .iter()
+                .find_map(|capsule| resource_perm_mask(capsule, resource_type, resource_id, ctx))
+                .is_some_and(|held| *want != 0 && held & want == *want)
+        })
+        .collect()
+}
+
+/// Batch permission check for the caller (endpoint entry point)
+pub fn resources_can_access_bulk(queries: Vec<(ResourceType, String, u32)>) -> Vec<bool> {
+    let ctx = PrincipalContext::new(ic_cdk::api::msg_caller(), vec![], None, ic_cdk::api::time());
+    let capsules = with_capsule_store(|store| store.paginate(None, u32::MAX, Order::Asc).items);
+    can_access_bulk(&capsules, &ctx, &queries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capsule::domain::ControllerState;
+    use crate::events::{list_access_changes_for, CapsuleEvent};
+    use crate::test_utils::{create_test_capsule_at, create_test_memory_at};
+    use candid::Principal;
+
+    fn owner() -> PersonRef {
+        PersonRef::Principal(Principal::anonymous())
+    }
+
+    fn friend() -> PersonRef {
+        PersonRef::Principal(Principal::from_slice(&[7; 29]))
+    }
+
+    fn capsule_with_memory() -> Capsule {
+        let mut capsule = create_test_capsule_at("capsule_1", owner(), 1);
+        capsule.memories.insert(
+            "mem_1".to_string(),
+            create_test_memory_at("mem_1", "capsule_1", 1),
+        );
+        capsule
+    }
+
+    fn change_of(event: &CapsuleEvent) -> &AccessChange {
+        match &event.kind {
+            CapsuleEventKind::AccessChanged { change, .. } => change,
+        }
+    }
+
+    #[test]
+    fn test_grant_access_adds_entry_and_emits() {
+        let mut capsule = capsule_with_memory();
+
+        let entry = grant_access(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            ResourceRole::Member,
+            (Perm::VIEW | Perm::DOWNLOAD).bits(),
+            5,
+        )
+        .unwrap();
+
+        assert_eq!(entry.person_ref, Some(friend()));
+        assert!(capsule.memories["mem_1"]
+            .access_entries
+            .iter()
+            .any(|e| e.id == entry.id));
+        assert_eq!(capsule.updated_at, 5);
+
+        let feed = list_access_changes_for(&friend(), None, None).unwrap();
+        assert_eq!(feed.items.len(), 1);
+        assert_eq!(change_of(&feed.items[0]), &AccessChange::Granted);
+    }
+
+    #[test]
+    fn test_revoke_access_emits_event() {
+        let mut capsule = capsule_with_memory();
+        grant_access(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            ResourceRole::Guest,
+            Perm::VIEW.bits(),
+            5,
+        )
+        .unwrap();
+
+        let removed = revoke_access(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            6,
+        )
+        .unwrap();
+
+        assert_eq!(removed, 1);
+        assert!(!capsule.memories["mem_1"]
+            .access_entries
+            .iter()
+            .any(|e| e.person_ref == Some(friend())));
+
+        let feed = list_access_changes_for(&friend(), None, None).unwrap();
+        let last = feed.items.last().unwrap();
+        assert_eq!(last.capsule_id, "capsule_1");
+        assert_eq!(last.at, 6);
+        assert_eq!(change_of(last), &AccessChange::Revoked);
+    }
+
+    #[test]
+    fn test_revoke_without_prior_grant_still_emits() {
+        let mut capsule = capsule_with_memory();
+
+        let removed = revoke_access(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            6,
+        )
+        .unwrap();
+
+        assert_eq!(removed, 0);
+        assert_eq!(capsule.updated_at, 1);
+        let feed = list_access_changes_for(&friend(), None, None).unwrap();
+        assert_eq!(feed.items.len(), 1);
+        assert_eq!(change_of(&feed.items[0]), &AccessChange::Revoked);
+    }
+
+    #[test]
+    fn test_revoke_requires_write_access() {
+        let mut capsule = capsule_with_memory();
+
+        let result = revoke_access(
+            &mut capsule,
+            &friend(),
+            &ResourceType::Memory,
+            "mem_1",
+            &owner(),
+            6,
+        );
+
+        assert_eq!(result, Err(Error::Unauthorized));
+        assert!(list_access_changes_for(&owner(), None, None)
+            .unwrap()
+            .items
+            .is_empty());
+    }
+
+    fn effective_mask_for(capsule: &Capsule, person: Principal) -> u32 {
+        let ctx = PrincipalContext::new(person, vec![], None, 10);
+        effective_perm_mask(&capsule.memories["mem_1"], &ctx)
+    }
+
+    #[test]
+    fn test_can_access_bulk_mixes_grants_and_bits() {
+        let mut capsule = capsule_with_memory();
+        capsule.memories.insert(
+            "mem_2".to_string(),
+            create_test_memory_at("mem_2", "capsule_1", 1),
+        );
+        let granted = grant_access(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            ResourceRole::Member,
+            (Perm::VIEW | Perm::DOWNLOAD).bits(),
+            5,
+        )
+        .unwrap();
+        // mem_2 is public, view only
+        let public = AccessEntry {
+            person_ref: None,
+            is_public: true,
+            perm_mask: Perm::VIEW.bits(),
+            ..granted
+        };
+        capsule
+            .memories
+            .get_mut("mem_2")
+            .unwrap()
+            .access_entries
+            .push(public);
+
+        let query =
+            |resource_type, id: &str, perm: Perm| (resource_type, id.to_string(), perm.bits());
+        let queries = vec![
+            query(ResourceType::Memory, "mem_1", Perm::VIEW | Perm::DOWNLOAD),
+            query(ResourceType::Memory, "mem_1", Perm::VIEW | Perm::SHARE),
+            query(ResourceType::Memory, "mem_2", Perm::VIEW),
+            query(ResourceType::Memory, "mem_2", Perm::DOWNLOAD),
+            query(ResourceType::Memory, "missing", Perm::VIEW),
+            query(ResourceType::Capsule, "capsule_1", Perm::VIEW),
+            query(ResourceType::Capsule, "capsule_1", Perm::MANAGE),
+        ];
+        let capsules = [capsule];
+
+        let friend_ctx = PrincipalContext::new(Principal::from_slice(&[7; 29]), vec![], None, 10);
+        assert_eq!(
+            can_access_bulk(&capsules, &friend_ctx, &queries),
+            vec![true, false, true, false, false, false, false]
+        );
+
+        // Owners hold every bit on everything in the capsule
+        let owner_ctx = PrincipalContext::new(Principal::anonymous(), vec![], None, 10);
+        assert_eq!(
+            can_access_bulk(&capsules, &owner_ctx, &queries),
+            vec![true, true, true, true, false, true, true]
+        );
+    }
+
+    #[test]
+    fn test_grant_role_guest_expands_to_view() {
+        let mut capsule = capsule_with_memory();
+
+        let entry = grant_role(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            "guest",
+            5,
+        )
+        .unwrap();
+
+        assert_eq!(entry.role, ResourceRole::Guest);
+        assert_eq!(
+            effective_mask_for(&capsule, Principal::from_slice(&[7; 29])),
+            Perm::VIEW.bits()
+        );
+    }
+
+    #[test]
+    fn test_grant_role_admin_replaces_previous_grant() {
+        let mut capsule = capsule_with_memory();
+        grant_role(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            "guest",
+            5,
+        )
+        .unwrap();
+
+        let entry = grant_role(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            "admin",
+            6,
+        )
+        .unwrap();
+
+        assert_eq!(entry.role, ResourceRole::Admin);
+        assert_eq!(
+            effective_mask_for(&capsule, Principal::from_slice(&[7; 29])),
+            (Perm::VIEW | Perm::DOWNLOAD | Perm::SHARE | Perm::MANAGE).bits()
+        );
+    }
+
+    #[test]
+    fn test_grant_role_rejects_unknown_role() {
+        let mut capsule = capsule_with_memory();
+
+        let result = grant_role(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            "superuser",
+            5,
+        );
+
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+    }
+
+    fn editor_template() -> RoleTemplate {
+        RoleTemplate {
+            name: "editor".to_string(),
+            perm_mask: (Perm::VIEW | Perm::DOWNLOAD | Perm::MANAGE).bits(),
+            description: "Edit without sharing".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_grant_role_uses_custom_template() {
+        let mut capsule = capsule_with_memory();
+        add_role_template(&mut capsule, &owner(), editor_template(), 4).unwrap();
+        assert!(capsule
+            .list_role_templates()
+            .iter()
+            .any(|template| template.name == "editor"));
+
+        let entry = grant_role(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            "editor",
+            5,
+        )
+        .unwrap();
+
+        assert_eq!(entry.role, ResourceRole::Member);
+        assert_eq!(
+            effective_mask_for(&capsule, Principal::from_slice(&[7; 29])),
+            editor_template().perm_mask
+        );
+
+        remove_role_template(&mut capsule, &owner(), "editor", 6).unwrap();
+        assert!(matches!(
+            grant_role(
+                &mut capsule,
+                &owner(),
+                &ResourceType::Memory,
+                "mem_1",
+                &friend(),
+                "editor",
+                7,
+            ),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_controller_cannot_escalate_beyond_held_perms() {
+        let mut capsule = capsule_with_memory();
+        capsule.controllers.insert(
+            friend(),
+            ControllerState {
+                granted_at: 1,
+                granted_by: owner(),
+            },
+        );
+
+        let owner_plus = RoleTemplate {
+            name: "co-owner".to_string(),
+            perm_mask: Perm::all().bits(),
+            description: "Everything".to_string(),
+        };
+        assert_eq!(
+            add_role_template(&mut capsule, &friend(), owner_plus, 4),
+            Err(Error::Unauthorized)
+        );
+        assert!(add_role_template(&mut capsule, &friend(), editor_template(), 4).is_ok());
+
+        // Built-in "owner" carries OWN, which a controller doesn't hold
+        let stranger = PersonRef::Principal(Principal::from_slice(&[9; 29]));
+        assert_eq!(
+            grant_role(
+                &mut capsule,
+                &friend(),
+                &ResourceType::Memory,
+                "mem_1",
+                &stranger,
+                "owner",
+                5,
+            ),
+            Err(Error::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn test_shared_with_me_follows_grant_and_revoke() {
+        use crate::capsule::query::shared_capsules;
+        use crate::capsule_store::Store;
+
+        let mut store = Store::new_stable_test();
+        let mut capsule = capsule_with_memory();
+        grant_access(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            ResourceRole::Guest,
+            Perm::VIEW.bits(),
+            5,
+        )
+        .unwrap();
+        store.upsert(capsule.id.clone(), capsule.clone());
+
+        let shared = shared_capsules(&store, &friend(), 6);
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].id, "capsule_1");
+        // The owner manages the capsule, so it isn't "shared" with them
+        assert!(shared_capsules(&store, &owner(), 6).is_empty());
+
+        revoke_access(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            7,
+        )
+        .unwrap();
+        store.upsert(capsule.id.clone(), capsule);
+
+        assert!(shared_capsules(&store, &friend(), 8).is_empty());
+        assert!(crate::grant_index::capsules_for(&friend()).is_empty());
+    }
+
+    #[test]
+    fn test_grant_index_backfill_covers_grants_made_before_the_index() {
+        use crate::capsule::query::shared_capsules;
+        use crate::capsule_store::Store;
+
+        let mut store = Store::new_stable_test();
+        let mut capsule = capsule_with_memory();
+        grant_access(
+            &mut capsule,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            ResourceRole::Guest,
+            Perm::VIEW.bits(),
+            5,
+        )
+        .unwrap();
+        store.upsert(capsule.id.clone(), capsule.clone());
+        // A grant stored before the index existed has no index entry
+        crate::grant_index::sync(&friend(), &capsule.id, false);
+        assert!(shared_capsules(&store, &friend(), 6).is_empty());
+
+        assert!(!crate::grant_index::is_backfilled());
+        assert_eq!(crate::grant_index::backfill(&[capsule]), 1);
+
+        assert!(crate::grant_index::is_backfilled());
+        let shared = shared_capsules(&store, &friend(), 6);
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].id, "capsule_1");
+    }
+
+    #[test]
+    fn test_capsule_headers_hides_inaccessible_capsules() {
+        use crate::capsule::query::capsule_headers;
+        use crate::capsule_store::Store;
+
+        let mut store = Store::new_stable_test();
+        let mut shared = capsule_with_memory();
+        grant_access(
+            &mut shared,
+            &owner(),
+            &ResourceType::Memory,
+            "mem_1",
+            &friend(),
+            ResourceRole::Guest,
+            Perm::VIEW.bits(),
+            5,
+        )
+        .unwrap();
+        store.upsert(shared.id.clone(), shared);
+        let private = create_test_capsule_at("capsule_2", owner(), 1);
+        store.upsert(private.id.clone(), private);
+
+        let ids = vec![
+            "capsule_2".to_string(),
+            "capsule_1".to_string(),
+            "missing".to_string(),
+        ];
+        let visible = |person: &PersonRef| -> Vec<(String, bool)> {
+            capsule_headers(&store, person, ids.clone(), 6)
+                .into_iter()
+                .map(|(id, header)| (id, header.is_some()))
+                .collect()
+        };
+
+        assert_eq!(
+            visible(&friend()),
+            vec![
+                ("capsule_2".to_string(), false),
+                ("capsule_1".to_string(), true),
+                ("missing".to_string(), false),
+            ]
+        );
+        assert_eq!(
+            visible(&owner()),
+            vec![
+                ("capsule_2".to_string(), true),
+                ("capsule_1".to_string(), true),
+                ("missing".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grant_member_across_capsule_with_memories() {
+        let mut capsule = create_test_capsule_at("capsule_1", owner(), 1);
+        for id in ["mem_1", "mem_2", "mem_3"] {
+            capsule
+                .memories
+                .insert(id.to_string(), create_test_memory_at(id, "capsule_1", 1));
+        }
+        let grantee = SharingGrantee::Person(friend());
+
+        // Without write access nothing can be granted
+        assert_eq!(
+            grant_role_capsule_wide(&mut capsule, &friend(), &grantee, "member", true, 5),
+            Err(Error::Unauthorized)
+        );
+
+        let result =
+            grant_role_capsule_wide(&mut capsule, &owner(), &grantee, "member", true, 5).unwrap();
+        assert_eq!(result.ok, vec!["capsule_1", "mem_1", "mem_2", "mem_3"]);
+        assert!(result.failed.is_empty());
+
+        let member = (Perm::VIEW | Perm::DOWNLOAD).bits();
+        let ctx = PrincipalContext::new(Principal::from_slice(&[7; 29]), vec![], None, 10);
+        for (resource_type, id) in [
+            (ResourceType::Capsule, "capsule_1"),
+            (ResourceType::Memory, "mem_1"),
+            (ResourceType::Memory, "mem_3"),
+        ] {
+            assert_eq!(
+                resource_perm_mask(&capsule, &resource_type, id, &ctx),
+                Some(member)
+            );
+        }
+        assert!(capsule.has_grant_for(&friend()));
+
+        // Memories already carrying the grant are skipped on the next call
+        let again =
+            grant_role_capsule_wide(&mut capsule, &owner(), &grantee, "member", true, 6).unwrap();
+        assert_eq!(again.ok, vec!["capsule_1"]);
+        assert_eq!(capsule.memories["mem_1"].access_entries.len(), 2); // owner + grant
+    }
+}
@@ -1,7 +1,7 @@
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
 
-use crate::capsule::domain::PersonRef;
+use crate::capsule::domain::{DefaultMemoryAccess, PersonRef, ResourceType};
 use crate::types::HostingPreferences;
 
 // ============================================================================
@@ -24,6 +24,102 @@ pub struct CapsuleInfo {
     pub memory_count: u64,     // Number of memories in this capsule
     pub gallery_count: u64,    // Number of galleries in this capsule
     pub connection_count: u64, // Number of connections to other people
+
+    pub created_by: Option<PersonRef>, // Who created the capsule (None if unknown)
+}
+
+/// Who the caller is, in one bootstrap query (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct CallerIdentity {
+    pub principal: Principal,
+    pub is_anonymous: bool, // true for the anonymous principal (not logged in)
+    pub has_capsule: bool,  // true if a capsule exists with the caller as subject
+}
+
+/// A resource whose stored `capsule_id` disagrees with the capsule containing it (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct CapsuleIdMismatch {
+    pub capsule_id: String, // capsule the resource is actually stored in
+    pub resource_type: ResourceType,
+    pub resource_id: String,
+    pub claimed_capsule_id: String, // value found on the resource
+}
+
+/// A derived counter that disagreed with the collection it is computed from (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct DerivedCounterFix {
+    pub resource_type: ResourceType,
+    pub resource_id: String,
+    pub field: String,
+    pub before: u64,
+    pub after: u64,
+}
+
+/// Counters corrected by `capsules_recompute_derived` (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct DerivedRecomputeReport {
+    pub capsule_id: String,
+    pub corrections: Vec<DerivedCounterFix>, // empty when nothing had drifted
+}
+
+/// Capsule and optional first memory set up by `onboard` (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct OnboardResult {
+    pub capsule_id: String,
+    pub capsule_created: bool, // false when the caller's existing capsule was reused
+    pub memory_id: Option<String>,
+}
+
+/// Who a `SharingSummary` entry is about
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SharingGrantee {
+    Person(PersonRef),
+    Group(String), // group id of a group grant
+}
+
+/// Active grants one grantee holds across a capsule's resources
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SharingGrant {
+    pub grantee: SharingGrantee,
+    pub perm_mask: u32, // union of the grantee's active grants (the most it can do anywhere)
+    pub resource_count: u32,
+}
+
+/// Who has access to what in a capsule (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SharingSummary {
+    pub capsule_id: String,
+    pub grants: Vec<SharingGrant>, // sorted by grantee; owner entries are not grants
+    pub public_memories: u32,
+    pub public_galleries: u32,
+    pub public_folders: u32,
+}
+
+/// Internal blobs freed by `capsules_delete` with `delete_assets` (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Default)]
+pub struct CapsuleDeleteReport {
+    pub blobs_freed: u32,
+    pub bytes_reclaimed: u64,
+    pub blobs_kept_shared: u32, // still referenced by another capsule's memories
+}
+
+/// Internal blobs freed from trashed memories by `capsules_reclaim_trash_blobs` (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Default)]
+pub struct ReclaimReport {
+    pub memories_reclaimed: u32, // trashed memories whose internal assets were dropped
+    pub blobs_freed: u32,
+    pub bytes_reclaimed: u64,
+    pub blobs_kept_shared: u32, // still referenced by another memory
+}
+
+/// One internal blob of a capsule, for storage audits (API response)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BlobSummary {
+    pub locator: String,
+    pub size: u64,
+    pub chunk_count: u32,
+    pub content_type: String, // mime type of the first referencing asset
+    pub referenced_by: Vec<String>, // memory ids, sorted
 }
 
 /// Capsule header for listing (API response)
@@ -36,6 +132,8 @@ pub struct CapsuleHeader {
     pub memory_count: u64,
     pub created_at: u64,
     pub updated_at: u64,
+    pub created_by: Option<PersonRef>, // None for capsules created before tracking
+    pub tags: Vec<String>,
 }
 
 /// Capsule update data for partial updates (API request)
@@ -50,6 +148,8 @@ pub struct CapsuleUpdateData {
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
 pub struct UserSettingsUpdateData {
     pub has_advanced_settings: Option<bool>,
+    pub default_memory_access: Option<DefaultMemoryAccess>, // Public needs the owner opt-in
+    pub allow_public_memory_default: Option<bool>,          // owners only
 }
 
 /// User settings response for reading capsule settings (API response)
@@ -57,4 +157,6 @@ pub struct UserSettingsUpdateData {
 pub struct UserSettingsResponse {
     pub has_advanced_settings: bool,
     pub hosting_preferences: HostingPreferences,
+    pub default_memory_access: DefaultMemoryAccess,
+    pub allow_public_memory_default: bool,
 }
@@ -1,17 +1,63 @@
-use crate::capsule::domain::Capsule;
-use crate::capsule::util::calculate_capsule_size;
+use crate::capsule::api_types::{
+    CapsuleDeleteReport, DerivedRecomputeReport, OnboardResult, ReclaimReport,
+};
+use crate::capsule::domain::{Capsule, DefaultMemoryAccess};
+use crate::capsule::util::{
+    calculate_capsule_size, free_capsule_blobs, reclaim_trashed_blobs, recompute_derived,
+};
 use crate::capsule_store::{types::PaginationOrder as Order, CapsuleStore};
+use crate::memories::core::{memories_create_core, Env};
+use crate::memories::StoreAdapter;
 use crate::memory::{with_capsule_store, with_capsule_store_mut};
 use crate::state::add_canister_size;
 use crate::types::*;
 
 use ic_cdk::api::time;
 
+/// Recompute a capsule's derived counters and persist any corrections (owners and controllers only)
+pub fn capsules_recompute_derived(
+    capsule_id: String,
+) -> std::result::Result<DerivedRecomputeReport, Error> {
+    let caller = PersonRef::from_caller();
+
+    with_capsule_store_mut(|store| {
+        let mut capsule = store.get(&capsule_id).ok_or(Error::NotFound)?;
+        if !capsule.has_write_access(&caller) {
+            return Err(Error::Unauthorized);
+        }
+
+        let corrections = recompute_derived(&mut capsule);
+        if !corrections.is_empty() {
+            store.upsert(capsule_id.clone(), capsule);
+        }
+        Ok(DerivedRecomputeReport {
+            capsule_id,
+            corrections,
+        })
+    })
+}
+
 /// Create a new capsule with optional subject
 /// If subject is None, creates a self-capsule (subject = caller)
 /// If subject is provided, creates a capsule for that subject
-pub fn capsules_create(subject: Option<PersonRef>) -> std::result::Result<Capsule, Error> {
-    let caller = PersonRef::from_caller();
+pub fn capsules_create(
+    subject: Option<PersonRef>,
+    delegation: Option<String>,
+) -> std::result::Result<Capsule, Error> {
+    capsules_create_with(&crate::memories::CanisterEnv, subject, delegation)
+}
+
+/// `capsules_create` with the caller and clock taken from `env`
+pub fn capsules_create_with<E: Env>(
+    env: &E,
+    subject: Option<PersonRef>,
+    delegation: Option<String>,
+) -> std::result::Result<Capsule, Error> {
+    let caller = env.caller();
+    let token = match &subject {
+        Some(subject) => authorize_subject(&caller, subject, delegation.as_deref(), env.now())?,
+        None => None,
+    };
 
     // Check if caller already has a self-capsule when creating self-capsule
     let is_self_capsule = subject.is_none();
@@ -30,7 +76,7 @@ pub fn capsules_create(subject: Option<PersonRef>) -> std::result::Result<Capsul
             let capsule_id = capsule.id.clone();
             let update_result = with_capsule_store_mut(|store| {
                 store.update(&capsule_id, |capsule| {
-                    let now = time();
+                    let now = env.now();
                     capsule.updated_at = now;
 
                     if let Some(owner_state) = capsule.owners.get_mut(&caller) {
@@ -52,7 +98,7 @@ pub fn capsules_create(subject: Option<PersonRef>) -> std::result::Result<Capsul
 
     // MIGRATED: Create new capsule
     let actual_subject = subject.unwrap_or_else(|| caller.clone());
-    let capsule = Capsule::new(actual_subject, caller);
+    let capsule = Capsule::new(actual_subject, caller.clone(), caller.clone(), env.now());
     let capsule_id = capsule.id.clone();
 
     // Track size before creating capsule
@@ -66,9 +112,103 @@ pub fn capsules_create(subject: Option<PersonRef>) -> std::result::Result<Capsul
         store.upsert(capsule_id.clone(), capsule.clone());
     });
 
+    // Use up the delegation only now that the capsule exists, so a failed create can be retried
+    if let Some(token) = token {
+        crate::creation_delegations::consume(token, &capsule.subject, &caller, env.now())?;
+    }
+
     Ok(capsule)
 }
 
+/// Check that `caller` may create a capsule about `subject`
+///
+/// Callers may create capsules about themselves and about opaque (non-principal)
+/// subjects. Another principal must have delegated creation to the caller; the
+/// `delegation` token is only checked here and returned, for the caller to
+/// consume once the capsule is stored, so it authorizes a single capsule.
+pub fn authorize_subject<'a>(
+    caller: &PersonRef,
+    subject: &PersonRef,
+    delegation: Option<&'a str>,
+    now: u64,
+) -> std::result::Result<Option<&'a str>, Error> {
+    match subject {
+        PersonRef::Opaque(_) => Ok(None),
+        PersonRef::Principal(_) if subject == caller => Ok(None),
+        PersonRef::Principal(_) => {
+            let token = delegation.ok_or(Error::Unauthorized)?;
+            crate::creation_delegations::check(token, subject, caller, now)?;
+            Ok(Some(token))
+        }
+    }
+}
+
+/// Create the caller's capsule (unless one exists) and optionally its first memory
+///
+/// An existing capsule owned by the caller with the same subject (the caller
+/// when `subject` is None) is reused. If the first memory can't be created, a
+/// capsule created by this call is removed again so a retry starts clean; the
+/// memory uses a per-capsule idempotency key, so retries don't duplicate it.
+/// A principal subject other than the caller is rejected: delegated creation
+/// goes through `capsules_create`.
+pub fn onboard_with<E: Env>(
+    env: &E,
+    subject: Option<PersonRef>,
+    first_memory: Option<MemoryCreateInput>,
+) -> std::result::Result<OnboardResult, Error> {
+    let caller = env.caller();
+    let subject = subject.unwrap_or_else(|| caller.clone());
+    authorize_subject(&caller, &subject, None, env.now())?;
+
+    let existing = with_capsule_store(|store| {
+        store
+            .get_many(&store.list_by_owner(&caller))
+            .into_iter()
+            .find(|capsule| capsule.subject == subject)
+    });
+    let (capsule_id, capsule_created) = match existing {
+        Some(capsule) => (capsule.id, false),
+        None => {
+            let capsule = Capsule::new(subject, caller.clone(), caller, env.now());
+            if add_canister_size(calculate_capsule_size(&capsule)).is_err() {
+                return Err(Error::ResourceExhausted);
+            }
+            let capsule_id = capsule.id.clone();
+            with_capsule_store_mut(|store| store.upsert(capsule_id.clone(), capsule));
+            (capsule_id, true)
+        }
+    };
+
+    let memory_id = match first_memory {
+        None => None,
+        Some(item) => {
+            let created = memories_create_core(
+                env,
+                &mut StoreAdapter,
+                capsule_id.clone(),
+                item,
+                format!("onboard:{}", capsule_id),
+                Default::default(),
+            );
+            match created {
+                Ok(memory_id) => Some(memory_id),
+                Err(err) => {
+                    if capsule_created {
+                        with_capsule_store_mut(|store| store.remove(&capsule_id));
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    };
+
+    Ok(OnboardResult {
+        capsule_id,
+        capsule_created,
+        memory_id,
+    })
+}
+
 /// Update a capsule with the provided data
 /// Only allows updates to mutable fields (binding status, timestamps)
 pub fn capsules_update(
@@ -109,7 +249,13 @@ pub fn capsules_update(
 
 /// Delete a capsule (permanent deletion)
 /// Only allows deletion by capsule owners
-pub fn capsules_delete(capsule_id: String) -> std::result::Result<(), Error> {
+///
+/// With `delete_assets`, internal blobs referenced only by this capsule's memories
+/// are freed as well; blobs another capsule still references are kept.
+pub fn capsules_delete(
+    capsule_id: String,
+    delete_assets: bool,
+) -> std::result::Result<CapsuleDeleteReport, Error> {
     let caller = PersonRef::from_caller();
 
     // First, get the capsule to check ownership and calculate size for tracking
@@ -130,7 +276,55 @@ pub fn capsules_delete(capsule_id: String) -> std::result::Result<(), Error> {
     // For now, we'll just log this - in a full implementation, we'd subtract from total size
     // TODO: Implement remove_canister_size in state.rs
 
-    Ok(())
+    if !delete_assets {
+        return Ok(CapsuleDeleteReport::default());
+    }
+
+    // Free blobs only after the capsule is gone, so nothing can point at a freed blob
+    with_capsule_store_mut(|store| {
+        // The capsule is gone, so the index only counts references held elsewhere
+        let (report, freed) = free_capsule_blobs(
+            &crate::upload::blob_store::StableBlobStore,
+            &capsule,
+            |blob_id| store.blob_ref_count(blob_id),
+        );
+        // Duplicated blobs may be charged to another capsule's quota
+        for meta in &freed {
+            crate::upload::service::release_blob_charge(store, meta);
+        }
+        Ok(report)
+    })
+}
+
+/// Free the internal blobs of memories trashed at least `older_than` ns ago (owners only)
+///
+/// The trashed memory records are kept, so the deletion history survives.
+pub fn capsules_reclaim_trash_blobs(
+    capsule_id: String,
+    older_than: u64,
+) -> std::result::Result<ReclaimReport, Error> {
+    let caller = PersonRef::from_caller();
+    let cutoff = time().saturating_sub(older_than);
+
+    with_capsule_store_mut(|store| {
+        let mut capsule = store.get(&capsule_id).ok_or(Error::NotFound)?;
+        if !capsule.is_owner(&caller) {
+            return Err(Error::Unauthorized);
+        }
+        let (report, freed) = reclaim_trashed_blobs(
+            &crate::upload::blob_store::StableBlobStore,
+            &mut capsule,
+            |blob_id| store.blob_ref_count(blob_id),
+            cutoff,
+        );
+        if report.memories_reclaimed > 0 {
+            store.upsert(capsule_id, capsule);
+        }
+        for meta in &freed {
+            crate::upload::service::release_blob_charge(store, meta);
+        }
+        Ok(report)
+    })
 }
 
 /// Flexible resource binding function for Neon database
@@ -255,6 +449,40 @@ pub fn resources_bind_neon(
     }
 }
 
+/// Apply the default-memory-access part of a settings update
+///
+/// Only owners can opt in to (or out of) a public default, and a Public default
+/// is rejected until they have. Opting out puts the default back to Private.
+/// Memories that already exist keep their access.
+pub fn apply_memory_access_settings(
+    capsule: &mut Capsule,
+    caller: &PersonRef,
+    updates: &UserSettingsUpdateData,
+) -> std::result::Result<(), Error> {
+    if let Some(allowed) = updates.allow_public_memory_default {
+        if !capsule.is_owner(caller) {
+            return Err(Error::Unauthorized);
+        }
+        capsule.public_memory_default_allowed = Some(allowed);
+        if !allowed {
+            capsule.default_memory_access = None;
+        }
+    }
+
+    if let Some(access) = updates.default_memory_access {
+        if access == DefaultMemoryAccess::Public
+            && capsule.public_memory_default_allowed != Some(true)
+        {
+            return Err(Error::InvalidArgument(
+                "a public memory default needs the owner's opt-in (allow_public_memory_default)"
+                    .to_string(),
+            ));
+        }
+        capsule.default_memory_access = Some(access);
+    }
+    Ok(())
+}
+
 /// Update user settings for the caller's capsule
 pub fn update_user_settings(
     updates: crate::types::UserSettingsUpdateData,
@@ -278,6 +506,7 @@ pub fn update_user_settings(
             }
 
             // Update settings if provided
+            apply_memory_access_settings(capsule, &caller, &updates)?;
             if let Some(has_advanced_settings) = updates.has_advanced_settings {
                 capsule.has_advanced_settings = has_advanced_settings;
             }
@@ -301,5 +530,257 @@ pub fn update_user_settings(
     Ok(crate::types::UserSettingsResponse {
         has_advanced_settings: updated_capsule.has_advanced_settings,
         hosting_preferences: updated_capsule.hosting_preferences.clone(),
+        default_memory_access: updated_capsule.memory_access_default(),
+        allow_public_memory_default: updated_capsule
+            .public_memory_default_allowed
+            .unwrap_or(false),
+    })
+}
+
+/// Change the tags of a capsule `person` owns; returns its tags afterwards
+pub fn edit_capsule_tags<S: CapsuleStore>(
+    store: &mut S,
+    person: &PersonRef,
+    capsule_id: &str,
+    now: u64,
+    edit: impl FnOnce(&mut Capsule) -> std::result::Result<(), Error>,
+) -> std::result::Result<Vec<String>, Error> {
+    store.update_with(&capsule_id.to_string(), |capsule| {
+        if !capsule.is_owner(person) {
+            return Err(Error::Unauthorized);
+        }
+        edit(capsule)?;
+        capsule.updated_at = now;
+        Ok(capsule.tags().to_vec())
+    })
+}
+
+/// Tag a capsule the caller owns
+pub fn capsules_add_tags(
+    capsule_id: String,
+    tags: Vec<String>,
+) -> std::result::Result<Vec<String>, Error> {
+    let caller = PersonRef::from_caller();
+    with_capsule_store_mut(|store| {
+        edit_capsule_tags(store, &caller, &capsule_id, time(), |capsule| {
+            capsule.add_tags(&tags)
+        })
+    })
+}
+
+/// Untag a capsule the caller owns
+pub fn capsules_remove_tags(
+    capsule_id: String,
+    tags: Vec<String>,
+) -> std::result::Result<Vec<String>, Error> {
+    let caller = PersonRef::from_caller();
+    with_capsule_store_mut(|store| {
+        edit_capsule_tags(store, &caller, &capsule_id, time(), |capsule| {
+            capsule.remove_tags(&tags);
+            Ok(())
+        })
     })
 }
+
+/// Purge galleries and folders that sat in a capsule trash longer than `TRASH_RETENTION_NS`
+///
+/// Called from the maintenance timer; returns how many containers were purged.
+pub fn purge_container_trash(now: u64) -> u32 {
+    let cutoff = now.saturating_sub(crate::capsule::domain::TRASH_RETENTION_NS);
+    with_capsule_store_mut(|store| {
+        let mut purged = 0;
+        for mut capsule in store.paginate(None, u32::MAX, Order::Asc).items {
+            let count = capsule.purge_trashed_containers(cutoff);
+            if count > 0 {
+                purged += count;
+                store.upsert(capsule.id.clone(), capsule);
+            }
+        }
+        purged
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+
+    struct TestEnv {
+        caller: PersonRef,
+        now: u64,
+    }
+
+    impl Env for TestEnv {
+        fn caller(&self) -> PersonRef {
+            self.caller.clone()
+        }
+
+        fn now(&self) -> u64 {
+            self.now
+        }
+    }
+
+    fn note_item(declared_bytes: u64) -> MemoryCreateInput {
+        MemoryCreateInput {
+            bytes: Some(b"note".to_vec()),
+            blob_ref: None,
+            external_location: None,
+            external_storage_key: None,
+            external_url: None,
+            external_size: None,
+            external_hash: None,
+            asset_metadata: AssetMetadata::Note(NoteAssetMetadata {
+                base: AssetMetadataBase {
+                    name: "note.txt".to_string(),
+                    description: None,
+                    tags: vec![],
+                    asset_type: AssetType::Original,
+                    bytes: declared_bytes,
+                    mime_type: "text/plain".to_string(),
+                    sha256: None,
+                    width: None,
+                    height: None,
+                    url: None,
+                    storage_key: None,
+                    bucket: None,
+                    asset_location: None,
+                    processing_status: None,
+                    processing_error: None,
+                    created_at: 0,
+                    updated_at: 0,
+                    deleted_at: None,
+                },
+                word_count: None,
+                language: None,
+                format: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_onboard_new_caller_then_reuses_capsule() {
+        let env = TestEnv {
+            caller: PersonRef::Principal(Principal::from_slice(&[4, 4, 4])),
+            now: 1_000,
+        };
+
+        let first = onboard_with(&env, None, Some(note_item(4))).unwrap();
+        assert!(first.capsule_created);
+        let memory_id = first.memory_id.clone().unwrap();
+        let capsule = with_capsule_store(|store| store.get(&first.capsule_id)).unwrap();
+        assert_eq!(capsule.subject, env.caller);
+        assert!(capsule.memories.contains_key(&memory_id));
+
+        // A caller who already has a capsule gets it back, with no memory when none is asked for
+        let later = TestEnv {
+            caller: env.caller.clone(),
+            now: 2_000,
+        };
+        let again = onboard_with(&later, None, None).unwrap();
+        assert_eq!(again.capsule_id, first.capsule_id);
+        assert!(!again.capsule_created);
+        assert_eq!(again.memory_id, None);
+    }
+
+    #[test]
+    fn test_onboard_removes_new_capsule_when_first_memory_fails() {
+        let env = TestEnv {
+            caller: PersonRef::Principal(Principal::from_slice(&[5, 5, 5])),
+            now: 3_000,
+        };
+
+        // Declared size disagrees with the inline bytes
+        assert!(matches!(
+            onboard_with(&env, None, Some(note_item(99))),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(with_capsule_store(|store| store.list_by_owner(&env.caller)).is_empty());
+    }
+
+    #[test]
+    fn test_onboard_opaque_subject_records_creator() {
+        let env = TestEnv {
+            caller: PersonRef::Principal(Principal::from_slice(&[6, 6, 6])),
+            now: 4_000,
+        };
+        let subject = PersonRef::Opaque("grandma".to_string());
+
+        let result = onboard_with(&env, Some(subject.clone()), None).unwrap();
+        assert!(result.capsule_created);
+        let capsule = with_capsule_store(|store| store.get(&result.capsule_id)).unwrap();
+        assert_eq!(capsule.subject, subject);
+        assert_eq!(capsule.created_by, Some(env.caller));
+    }
+
+    #[test]
+    fn test_capsules_create_for_another_subject_records_creator() {
+        let executor = PersonRef::Principal(Principal::from_slice(&[10, 10, 10]));
+        let subject = PersonRef::Principal(Principal::from_slice(&[11, 11, 11]));
+        let env = TestEnv {
+            caller: executor.clone(),
+            now: 6_000,
+        };
+        let token = crate::creation_delegations::delegate(
+            subject.clone(),
+            executor.clone(),
+            [4; 32],
+            6_000,
+        )
+        .unwrap();
+
+        let capsule = capsules_create_with(&env, Some(subject.clone()), Some(token)).unwrap();
+        assert_eq!(capsule.subject, subject);
+        assert_eq!(capsule.created_by, Some(executor.clone()));
+        assert!(capsule.owners.contains_key(&executor));
+        let stored = with_capsule_store(|store| store.get(&capsule.id)).unwrap();
+        assert_eq!(stored.created_by, Some(executor));
+    }
+
+    #[test]
+    fn test_principal_subject_requires_delegation() {
+        let executor = PersonRef::Principal(Principal::from_slice(&[7, 7, 7]));
+        let subject = PersonRef::Principal(Principal::from_slice(&[8, 8, 8]));
+        let env = TestEnv {
+            caller: executor.clone(),
+            now: 5_000,
+        };
+
+        assert_eq!(
+            onboard_with(&env, Some(subject.clone()), None),
+            Err(Error::Unauthorized)
+        );
+        assert!(with_capsule_store(|store| store.list_by_owner(&executor)).is_empty());
+        assert_eq!(
+            authorize_subject(&executor, &subject, Some("forged"), 5_000),
+            Err(Error::Unauthorized)
+        );
+
+        // A token minted by the subject works once, and only for the named creator
+        let token = crate::creation_delegations::delegate(
+            subject.clone(),
+            executor.clone(),
+            [3; 32],
+            5_000,
+        )
+        .unwrap();
+        let stranger = PersonRef::Principal(Principal::from_slice(&[9, 9, 9]));
+        assert_eq!(
+            authorize_subject(&stranger, &subject, Some(&token), 5_001),
+            Err(Error::Unauthorized)
+        );
+        // Checking alone doesn't use the token up; creating the capsule does
+        assert_eq!(
+            authorize_subject(&executor, &subject, Some(&token), 5_001),
+            Ok(Some(token.as_str()))
+        );
+        assert!(capsules_create_with(&env, Some(subject.clone()), Some(token.clone())).is_ok());
+        assert_eq!(
+            authorize_subject(&executor, &subject, Some(&token), 5_002),
+            Err(Error::Unauthorized)
+        );
+        assert_eq!(
+            capsules_create_with(&env, Some(subject.clone()), Some(token)),
+            Err(Error::Unauthorized)
+        );
+    }
+}
@@ -1,8 +1,11 @@
-use ic_cdk::api::time;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
+use crate::memory::{MEM_ADVANCED_SETTINGS_DEFAULT, MM};
 use crate::types::*; // Memory, MemoryAccess, CapsuleHeader, HostingPreferences, Error, etc.
 use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{DefaultMemoryImpl, StableCell};
 use serde::Serialize;
 
 // ============================================================================
@@ -39,7 +42,6 @@ impl Default for RoleTemplate {
     }
 }
 
-#[allow(dead_code)]
 pub fn get_default_role_templates() -> Vec<RoleTemplate> {
     vec![
         RoleTemplate {
@@ -66,6 +68,13 @@ pub fn get_default_role_templates() -> Vec<RoleTemplate> {
     ]
 }
 
+/// Look up a default role template by name
+pub fn find_role_template(name: &str) -> Option<RoleTemplate> {
+    get_default_role_templates()
+        .into_iter()
+        .find(|template| template.name == name)
+}
+
 // ============================================================================
 // UNIVERSAL ACCESS SYSTEM TYPES
 // ============================================================================
@@ -136,6 +145,14 @@ pub enum SharingStatus {
 
 // ❌ REMOVED: PublicMode enum - now handled by is_public flag in AccessEntry
 
+/// Access a new memory starts with, unless its creator picks another
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq, Default)]
+pub enum DefaultMemoryAccess {
+    #[default]
+    Private, // owner entry only
+    Public, // plus a public VIEW | DOWNLOAD entry
+}
+
 /// Events that can trigger access changes
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
 pub enum AccessEvent {
@@ -254,11 +271,82 @@ pub struct Capsule {
     pub inline_bytes_used: u64,      // Track inline storage consumption
     pub has_advanced_settings: bool, // Controls whether user sees advanced settings panels
     pub hosting_preferences: HostingPreferences, // User's preferred hosting providers
+    pub created_by: Option<PersonRef>, // who invoked creation (None if created before tracking)
+    pub role_templates: Option<Vec<RoleTemplate>>, // custom role templates (None until one is added)
+    pub blob_bytes_quota: Option<u64>,             // admin override of CAPSULE_BLOB_QUOTA_DEFAULT
+    pub blob_bytes_used: Option<u64>, // internal blob bytes charged (None before tracking)
+    pub tags: Option<Vec<String>>,    // normalized and sorted (None until first tagged)
+    pub access_entries: Option<Vec<AccessEntry>>, // capsule-wide grants (None until the first one)
+    pub default_memory_access: Option<DefaultMemoryAccess>, // for new memories (None = Private)
+    pub public_memory_default_allowed: Option<bool>, // owner opt-in required for a Public default
+}
+
+type Mem = VirtualMemory<DefaultMemoryImpl>;
+
+thread_local! {
+    // Admin-set default, kept across upgrades
+    static ADVANCED_SETTINGS_DEFAULT: RefCell<StableCell<bool, Mem>> = RefCell::new(
+        StableCell::init(MM.with(|m| m.borrow().get(MEM_ADVANCED_SETTINGS_DEFAULT)), true)
+            .expect("Failed to init advanced settings default")
+    );
+}
+
+/// Whether new capsules start with advanced settings shown
+pub fn advanced_settings_default() -> bool {
+    ADVANCED_SETTINGS_DEFAULT.with(|default| *default.borrow().get())
+}
+
+/// Change the advanced-settings default for new capsules (admin); existing ones keep theirs
+pub fn set_advanced_settings_default(enabled: bool) {
+    ADVANCED_SETTINGS_DEFAULT.with(|default| {
+        default
+            .borrow_mut()
+            .set(enabled)
+            .expect("Failed to store advanced settings default");
+    });
+}
+
+/// How long a deleted gallery or folder stays restorable before the sweep purges it
+pub const TRASH_RETENTION_NS: u64 = 30 * crate::capsule::time::DAY_NS;
+
+/// Tags a capsule may carry
+pub const MAX_CAPSULE_TAGS: usize = 32;
+/// Longest capsule tag in bytes, after normalization
+pub const MAX_CAPSULE_TAG_LEN: usize = 64;
+
+/// Canonical form of a capsule tag: trimmed, lowercase, inner whitespace as `-`
+///
+/// So "Family Archive" and " family-archive" are the same tag.
+pub fn normalize_capsule_tag(tag: &str) -> std::result::Result<String, Error> {
+    let tag = tag
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    if tag.is_empty() {
+        return Err(Error::InvalidArgument("tag is empty".to_string()));
+    }
+    if tag.len() > MAX_CAPSULE_TAG_LEN {
+        return Err(Error::InvalidArgument(format!(
+            "tag longer than {} bytes",
+            MAX_CAPSULE_TAG_LEN
+        )));
+    }
+    Ok(tag)
 }
 
 impl Capsule {
-    pub fn new(subject: PersonRef, initial_owner: PersonRef) -> Self {
-        let now = time();
+    /// Create a capsule about `subject`, owned by `initial_owner`, created by `created_by`
+    ///
+    /// The three usually coincide, but differ e.g. when someone sets up a memorial capsule
+    /// for a deceased subject on behalf of the family. `now` is supplied by the endpoint
+    /// layer (`ic_cdk::api::time()`), which keeps the domain free of IC calls.
+    pub fn new(
+        subject: PersonRef,
+        initial_owner: PersonRef,
+        created_by: PersonRef,
+        now: u64,
+    ) -> Self {
         let capsule_id = format!("capsule_{now}");
 
         let mut owners = HashMap::new();
@@ -282,13 +370,26 @@ impl Capsule {
             folders: HashMap::new(),
             created_at: now,
             updated_at: now,
-            bound_to_neon: false,        // Initially not bound to Neon
-            inline_bytes_used: 0,        // Start with zero inline consumption
-            has_advanced_settings: true, // Default to advanced settings for Web3 users
+            bound_to_neon: false, // Initially not bound to Neon
+            inline_bytes_used: 0, // Start with zero inline consumption
+            has_advanced_settings: advanced_settings_default(), // Deployment-level default
             hosting_preferences: HostingPreferences::default(), // Default to ICP hosting
+            created_by: Some(created_by),
+            role_templates: None,
+            blob_bytes_quota: None,
+            blob_bytes_used: None,
+            tags: None,
+            access_entries: None,
+            default_memory_access: None,
+            public_memory_default_allowed: None,
         }
     }
 
+    /// Access new memories get when their creator doesn't choose one
+    pub fn memory_access_default(&self) -> DefaultMemoryAccess {
+        self.default_memory_access.unwrap_or_default()
+    }
+
     /// Check if a PersonRef is an owner
     pub fn is_owner(&self, person: &PersonRef) -> bool {
         self.owners.contains_key(person)
@@ -304,6 +405,168 @@ impl Capsule {
         self.is_owner(person) || self.is_controller(person)
     }
 
+    /// Access entries of the capsule itself and every memory, gallery and folder in it
+    fn resource_access_entries(&self) -> impl Iterator<Item = &AccessEntry> {
+        let capsule = self.access_entries.iter().flatten();
+        let memories = self.memories.values().flat_map(|m| m.access_entries.iter());
+        let galleries = self
+            .galleries
+            .values()
+            .flat_map(|g| g.access_entries.iter());
+        let folders = self.folders.values().flat_map(|f| f.access_entries.iter());
+        capsule.chain(memories).chain(galleries).chain(folders)
+    }
+
+    /// Whether `person` holds any individual (non-owner) grant in this capsule
+    pub fn has_grant_for(&self, person: &PersonRef) -> bool {
+        self.resource_access_entries().any(|entry| {
+            !entry.is_public
+                && entry.role != ResourceRole::Owner
+                && entry.person_ref.as_ref() == Some(person)
+        })
+    }
+
+    /// People holding an individual (non-owner) grant in this capsule
+    pub fn grantees(&self) -> std::collections::BTreeSet<PersonRef> {
+        self.resource_access_entries()
+            .filter(|entry| !entry.is_public && entry.role != ResourceRole::Owner)
+            .filter_map(|entry| entry.person_ref.clone())
+            .collect()
+    }
+
+    /// Whether this capsule is shared with `person` without them managing it
+    ///
+    /// True for an accepted connection, or an active grant carrying `VIEW`.
+    pub fn is_shared_with(&self, person: &PersonRef, now: u64) -> bool {
+        if self.has_write_access(person) {
+            return false;
+        }
+        let connected = self
+            .connections
+            .get(person)
+            .is_some_and(|conn| conn.status == ConnectionStatus::Accepted);
+        connected
+            || self.resource_access_entries().any(|entry| {
+                !entry.is_public
+                    && entry.person_ref.as_ref() == Some(person)
+                    && entry.perm_mask & Perm::VIEW.bits() != 0
+                    && is_access_active(&entry.condition, now)
+            })
+    }
+
+    /// Internal blob bytes charged to this capsule
+    pub fn blob_bytes_used(&self) -> u64 {
+        self.blob_bytes_used.unwrap_or(0)
+    }
+
+    /// Internal blob byte quota (admin override or the default)
+    pub fn blob_bytes_quota(&self) -> u64 {
+        self.blob_bytes_quota
+            .unwrap_or(crate::upload::types::CAPSULE_BLOB_QUOTA_DEFAULT)
+    }
+
+    /// Reject a blob of `bytes` that wouldn't fit in the remaining quota
+    pub fn check_blob_quota(&self, bytes: u64) -> std::result::Result<(), Error> {
+        let remaining_bytes = self
+            .blob_bytes_quota()
+            .saturating_sub(self.blob_bytes_used());
+        if bytes > remaining_bytes {
+            return Err(Error::QuotaExceeded {
+                requested_bytes: bytes,
+                remaining_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Normalized tags, sorted
+    pub fn tags(&self) -> &[String] {
+        self.tags.as_deref().unwrap_or_default()
+    }
+
+    /// Whether the capsule carries `tag` (compared in normalized form)
+    pub fn has_tag(&self, tag: &str) -> bool {
+        normalize_capsule_tag(tag).is_ok_and(|tag| self.tags().contains(&tag))
+    }
+
+    /// Add `tags`, normalized; tags already present are skipped
+    ///
+    /// Nothing changes if any tag is invalid or the result would exceed `MAX_CAPSULE_TAGS`.
+    pub fn add_tags(&mut self, tags: &[String]) -> std::result::Result<(), Error> {
+        let mut merged = self.tags().to_vec();
+        for tag in tags {
+            merged.push(normalize_capsule_tag(tag)?);
+        }
+        merged.sort();
+        merged.dedup();
+        if merged.len() > MAX_CAPSULE_TAGS {
+            return Err(Error::LimitReached {
+                current: self.tags().len() as u32,
+                max: MAX_CAPSULE_TAGS as u32,
+            });
+        }
+        self.tags = Some(merged);
+        Ok(())
+    }
+
+    /// Remove `tags` (in any spelling that normalizes to them); unknown tags are ignored
+    pub fn remove_tags(&mut self, tags: &[String]) {
+        let doomed: Vec<String> = tags
+            .iter()
+            .filter_map(|tag| normalize_capsule_tag(tag).ok())
+            .collect();
+        if let Some(current) = &mut self.tags {
+            current.retain(|tag| !doomed.contains(tag));
+        }
+    }
+
+    /// Charge a committed blob to this capsule
+    pub fn charge_blob_bytes(&mut self, bytes: u64) {
+        self.blob_bytes_used = Some(self.blob_bytes_used().saturating_add(bytes));
+    }
+
+    /// Give back the bytes of a deleted blob
+    pub fn release_blob_bytes(&mut self, bytes: u64) {
+        self.blob_bytes_used = Some(self.blob_bytes_used().saturating_sub(bytes));
+    }
+
+    /// Permission bits `person` holds capsule-wide (and may therefore hand out)
+    ///
+    /// Owners hold everything; controllers everything except `OWN`.
+    pub fn held_perm_mask(&self, person: &PersonRef) -> u32 {
+        if self.is_owner(person) {
+            Perm::all().bits()
+        } else if self.is_controller(person) {
+            (Perm::all() - Perm::OWN).bits()
+        } else {
+            0
+        }
+    }
+
+    /// Custom role templates of this capsule
+    pub fn custom_role_templates(&self) -> &[RoleTemplate] {
+        self.role_templates.as_deref().unwrap_or(&[])
+    }
+
+    /// Resolve a role template by name: custom templates first, then built-ins
+    pub fn resolve_role_template(&self, name: &str) -> Option<RoleTemplate> {
+        self.custom_role_templates()
+            .iter()
+            .find(|template| template.name == name)
+            .cloned()
+            .or_else(|| find_role_template(name))
+    }
+
+    /// Built-in templates (unless shadowed by a custom one) followed by custom templates
+    pub fn list_role_templates(&self) -> Vec<RoleTemplate> {
+        let custom = self.custom_role_templates();
+        get_default_role_templates()
+            .into_iter()
+            .filter(|builtin| !custom.iter().any(|template| template.name == builtin.name))
+            .chain(custom.iter().cloned())
+            .collect()
+    }
+
     /// Check if a PersonRef has read access to this capsule
     ///
     /// TODO: Implement proper read access logic based on capsule access model.
@@ -329,8 +592,7 @@ impl Capsule {
 
     /// Update the last activity timestamp for a person
     #[allow(dead_code)]
-    pub fn touch_activity(&mut self, person: &PersonRef) {
-        let now = time();
+    pub fn touch_activity(&mut self, person: &PersonRef, now: u64) {
         self.updated_at = now;
 
         // Update owner activity if they are an owner
@@ -344,8 +606,21 @@ impl Capsule {
 
     /// Update the capsule's updated_at timestamp
     #[allow(dead_code)]
-    pub fn touch(&mut self) {
-        self.updated_at = time();
+    pub fn touch(&mut self, now: u64) {
+        self.updated_at = now;
+    }
+
+    /// Drop galleries and folders that were trashed at or before `cutoff`
+    ///
+    /// Returns how many containers were purged; memories they referenced stay untouched.
+    pub fn purge_trashed_containers(&mut self, cutoff: u64) -> u32 {
+        let trashed_before = |deleted_at: Option<u64>| deleted_at.is_some_and(|at| at <= cutoff);
+        let before = self.galleries.len() + self.folders.len();
+        self.galleries
+            .retain(|_, gallery| !trashed_before(gallery.deleted_at));
+        self.folders
+            .retain(|_, folder| !trashed_before(folder.deleted_at));
+        (before - self.galleries.len() - self.folders.len()) as u32
     }
 
     /// Convert capsule to header for listing
@@ -358,6 +633,8 @@ impl Capsule {
             memory_count: self.memories.len() as u64,
             created_at: self.created_at,
             updated_at: self.updated_at,
+            created_by: self.created_by.clone(),
+            tags: self.tags().to_vec(),
         }
     }
 }
@@ -373,6 +650,12 @@ pub trait AccessControlled {
     // ❌ REMOVED: fn public_policy(&self) -> Option<&PublicPolicy>; // Now unified in AccessEntry
 }
 
+impl AccessControlled for Capsule {
+    fn access_entries(&self) -> &[AccessEntry] {
+        self.access_entries.as_deref().unwrap_or(&[])
+    }
+}
+
 /// Context for permission evaluation
 #[derive(Clone, Debug, PartialEq)]
 pub struct PrincipalContext {
@@ -384,12 +667,17 @@ pub struct PrincipalContext {
 
 impl PrincipalContext {
     #[allow(dead_code)]
-    pub fn new(principal: Principal, groups: Vec<String>, link: Option<String>) -> Self {
+    pub fn new(
+        principal: Principal,
+        groups: Vec<String>,
+        link: Option<String>,
+        now_ns: u64,
+    ) -> Self {
         Self {
             principal,
             groups,
             link,
-            now_ns: time(),
+            now_ns,
         }
     }
 }
@@ -440,8 +728,7 @@ pub fn effective_perm_mask<T: AccessControlled>(resource: &T, ctx: &PrincipalCon
 }
 
 /// Check if an access condition is currently active
-#[allow(dead_code)]
-fn is_access_active(condition: &AccessCondition, now_ns: u64) -> bool {
+pub(crate) fn is_access_active(condition: &AccessCondition, now_ns: u64) -> bool {
     match condition {
         AccessCondition::Immediate => true,
         AccessCondition::Scheduled { accessible_after } => now_ns >= *accessible_after,
@@ -493,3 +780,127 @@ fn sum_user_and_groups(entries: &[AccessEntry], ctx: &PrincipalContext) -> u32 {
     }
     mask
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person(byte: u8) -> PersonRef {
+        PersonRef::Principal(Principal::from_slice(&[byte; 29]))
+    }
+
+    #[test]
+    fn test_capsule_new_at_fixed_timestamp() {
+        let capsule = Capsule::new(person(1), person(2), person(3), 1_700_000_000);
+
+        assert_eq!(capsule.id, "capsule_1700000000");
+        assert_eq!(capsule.created_at, 1_700_000_000);
+        assert_eq!(capsule.updated_at, 1_700_000_000);
+        assert_eq!(capsule.subject, person(1));
+        assert_eq!(capsule.created_by, Some(person(3)));
+        let owner = capsule.owners.get(&person(2)).expect("initial owner");
+        assert_eq!(owner.since, 1_700_000_000);
+        assert_eq!(owner.last_activity_at, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_blob_quota_rejects_then_frees() {
+        let mut capsule = Capsule::new(person(1), person(1), person(1), 42);
+        assert_eq!(
+            capsule.blob_bytes_quota(),
+            crate::upload::types::CAPSULE_BLOB_QUOTA_DEFAULT
+        );
+
+        capsule.blob_bytes_quota = Some(100);
+        capsule.charge_blob_bytes(70);
+        assert_eq!(
+            capsule.check_blob_quota(40),
+            Err(Error::QuotaExceeded {
+                requested_bytes: 40,
+                remaining_bytes: 30,
+            })
+        );
+
+        capsule.release_blob_bytes(70);
+        assert_eq!(capsule.blob_bytes_used(), 0);
+        assert!(capsule.check_blob_quota(40).is_ok());
+    }
+
+    #[test]
+    fn test_capsule_tags_are_normalized() {
+        let mut capsule = Capsule::new(person(1), person(1), person(1), 1);
+        assert!(capsule.tags().is_empty());
+
+        capsule
+            .add_tags(&["Work".to_string(), " Family   Archive ".to_string()])
+            .unwrap();
+        capsule.add_tags(&["family-archive".to_string()]).unwrap();
+        assert_eq!(capsule.tags(), ["family-archive", "work"]);
+        assert!(capsule.has_tag("WORK"));
+
+        // An invalid tag rejects the whole batch
+        assert!(matches!(
+            capsule.add_tags(&["trips".to_string(), "  ".to_string()]),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(!capsule.has_tag("trips"));
+
+        let too_many: Vec<String> = (0..MAX_CAPSULE_TAGS).map(|i| format!("t{i}")).collect();
+        assert_eq!(
+            capsule.add_tags(&too_many),
+            Err(Error::LimitReached {
+                current: 2,
+                max: MAX_CAPSULE_TAGS as u32
+            })
+        );
+
+        capsule.remove_tags(&["Family Archive".to_string(), "unknown".to_string()]);
+        assert_eq!(capsule.tags(), ["work"]);
+        assert_eq!(capsule.to_header().tags, vec!["work".to_string()]);
+    }
+
+    #[test]
+    fn test_capsule_new_is_deterministic() {
+        let a = Capsule::new(person(1), person(1), person(1), 42);
+        let b = Capsule::new(person(1), person(1), person(1), 42);
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.created_at, b.created_at);
+    }
+
+    #[test]
+    fn test_touch_activity_updates_owner_only() {
+        let mut capsule = Capsule::new(person(1), person(1), person(1), 100);
+
+        capsule.touch_activity(&person(1), 200);
+        assert_eq!(capsule.updated_at, 200);
+        assert_eq!(capsule.owners[&person(1)].last_activity_at, 200);
+
+        // Non-owners bump the capsule but have no owner state to update
+        capsule.touch_activity(&person(9), 300);
+        assert_eq!(capsule.updated_at, 300);
+        assert_eq!(capsule.owners[&person(1)].last_activity_at, 200);
+
+        capsule.touch(400);
+        assert_eq!(capsule.updated_at, 400);
+    }
+
+    #[test]
+    fn test_capsule_new_follows_advanced_settings_default() {
+        assert!(Capsule::new(person(1), person(1), person(1), 1).has_advanced_settings);
+
+        set_advanced_settings_default(false);
+        let simple = Capsule::new(person(1), person(1), person(1), 2);
+        assert!(!simple.has_advanced_settings);
+
+        set_advanced_settings_default(true);
+        assert!(Capsule::new(person(1), person(1), person(1), 3).has_advanced_settings);
+        // Capsules created earlier keep what they were created with
+        assert!(!simple.has_advanced_settings);
+    }
+
+    #[test]
+    fn test_principal_context_uses_supplied_time() {
+        let ctx = PrincipalContext::new(Principal::anonymous(), vec![], None, 7);
+        assert_eq!(ctx.now_ns, 7);
+    }
+}
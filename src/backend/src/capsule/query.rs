@@ -1,7 +1,12 @@
+use crate::capsule::api_types::{BlobSummary, SharingSummary};
 use crate::capsule::domain::Capsule;
-use crate::capsule_store::{types::PaginationOrder as Order, CapsuleStore};
+use crate::capsule::util::{capsule_blob_summaries, sharing_summary};
+use crate::capsule_store::types::{Page, PaginationOrder as Order};
+use crate::capsule_store::CapsuleStore;
 use crate::memory::with_capsule_store;
 use crate::types::*;
+use crate::upload::blob_store::StableBlobStore;
+use crate::utils::pagination::paginate_sorted;
 
 /// Get capsule by ID (with read access check)
 
@@ -41,6 +46,8 @@ pub fn capsules_read_basic(capsule_id: String) -> std::result::Result<CapsuleInf
                 memory_count: capsule.memories.len() as u64,
                 gallery_count: capsule.galleries.len() as u64,
                 connection_count: capsule.connections.len() as u64,
+
+                created_by: capsule.created_by.clone(),
             })
             .ok_or(Error::NotFound)
     })
@@ -87,6 +94,8 @@ pub fn capsule_read_self_basic() -> std::result::Result<CapsuleInfo, Error> {
                 memory_count: capsule.memories.len() as u64,
                 gallery_count: capsule.galleries.len() as u64,
                 connection_count: capsule.connections.len() as u64,
+
+                created_by: capsule.created_by.clone(),
             })
             .ok_or(Error::NotFound)
     })
@@ -108,6 +117,133 @@ pub fn capsules_list() -> Vec<CapsuleHeader> {
     })
 }
 
+/// Capsules `person` owns or controls that carry `tag` (in any spelling)
+pub fn capsules_with_tag<S: CapsuleStore>(
+    store: &S,
+    person: &PersonRef,
+    tag: &str,
+) -> Vec<CapsuleHeader> {
+    store
+        .paginate(None, u32::MAX, Order::Asc)
+        .items
+        .into_iter()
+        .filter(|capsule| capsule.has_write_access(person) && capsule.has_tag(tag))
+        .map(|capsule| capsule.to_header())
+        .collect()
+}
+
+/// List capsules owned or controlled by caller that carry `tag`
+pub fn capsules_list_by_tag(tag: String) -> Vec<CapsuleHeader> {
+    let caller = PersonRef::from_caller();
+
+    with_capsule_store(|store| capsules_with_tag(store, &caller, &tag))
+}
+
+/// Internal blobs referenced by a capsule's memories (owners and controllers only)
+///
+/// Keyset-paginated by locator (cursor = last locator of the previous page).
+pub fn capsules_list_blobs(
+    capsule_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<BlobSummary>, Error> {
+    let caller = PersonRef::from_caller();
+    let limit = limit.unwrap_or(50).clamp(1, 100);
+
+    let capsule = with_capsule_store(|store| store.get(&capsule_id)).ok_or(Error::NotFound)?;
+    if !capsule.has_write_access(&caller) {
+        return Err(Error::Unauthorized);
+    }
+
+    let summaries = capsule_blob_summaries(&StableBlobStore, &capsule);
+    Ok(paginate_sorted(
+        summaries,
+        |summary| summary.locator.clone(),
+        cursor,
+        limit,
+        Order::Asc,
+    ))
+}
+
+/// Capsules shared with `person` (they can view something but don't manage it)
+///
+/// Candidates come from the reverse grant index; each is re-checked against the
+/// stored capsule, so revoked, expired or deleted grants drop out. Connections
+/// are only honoured on indexed capsules, as nothing mutates them in place yet.
+pub fn shared_capsules<S: CapsuleStore>(
+    store: &S,
+    person: &PersonRef,
+    now: u64,
+) -> Vec<CapsuleHeader> {
+    crate::grant_index::capsules_for(person)
+        .into_iter()
+        .filter_map(|capsule_id| store.get(&capsule_id))
+        .filter(|capsule| capsule.is_shared_with(person, now))
+        .map(|capsule| capsule.to_header())
+        .collect()
+}
+
+/// Headers for `ids`, in input order
+///
+/// `None` where the capsule is missing or `person` neither manages it nor
+/// can view it through a share (see `Capsule::is_shared_with`).
+pub fn capsule_headers<S: CapsuleStore>(
+    store: &S,
+    person: &PersonRef,
+    ids: Vec<String>,
+    now: u64,
+) -> Vec<(String, Option<CapsuleHeader>)> {
+    ids.into_iter()
+        .map(|id| {
+            let header = store
+                .get(&id)
+                .filter(|capsule| {
+                    capsule.has_write_access(person) || capsule.is_shared_with(person, now)
+                })
+                .map(|capsule| capsule.to_header());
+            (id, header)
+        })
+        .collect()
+}
+
+/// Headers of the given capsules the caller can view
+pub fn capsules_headers(ids: Vec<String>) -> Vec<(String, Option<CapsuleHeader>)> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store(|store| capsule_headers(store, &caller, ids, now))
+}
+
+/// Sharing summary of a capsule `person` manages (owners and controllers only)
+pub fn capsule_sharing_summary<S: CapsuleStore>(
+    store: &S,
+    person: &PersonRef,
+    capsule_id: &str,
+    now: u64,
+) -> std::result::Result<SharingSummary, Error> {
+    let capsule = store.get(&capsule_id.to_string()).ok_or(Error::NotFound)?;
+    if !capsule.has_write_access(person) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(sharing_summary(&capsule, now))
+}
+
+/// Who has access to what in a capsule the caller manages
+pub fn capsules_sharing_summary(capsule_id: String) -> std::result::Result<SharingSummary, Error> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store(|store| capsule_sharing_summary(store, &caller, &capsule_id, now))
+}
+
+/// List capsules shared with the caller through grants
+pub fn capsules_shared_with_me() -> Vec<CapsuleHeader> {
+    let caller = PersonRef::from_caller();
+    let now = ic_cdk::api::time();
+
+    with_capsule_store(|store| shared_capsules(store, &caller, now))
+}
+
 /// Get user settings for the caller's capsule
 pub fn get_user_settings() -> std::result::Result<crate::types::UserSettingsResponse, Error> {
     let caller = PersonRef::from_caller();
@@ -125,5 +261,99 @@ pub fn get_user_settings() -> std::result::Result<crate::types::UserSettingsResp
     Ok(crate::types::UserSettingsResponse {
         has_advanced_settings: capsule.has_advanced_settings,
         hosting_preferences: capsule.hosting_preferences.clone(),
+        default_memory_access: capsule.memory_access_default(),
+        allow_public_memory_default: capsule.public_memory_default_allowed.unwrap_or(false),
     })
 }
+
+/// Describe `principal` for client bootstrap: anonymous or not, and whether it has a capsule
+pub fn caller_identity(principal: candid::Principal) -> CallerIdentity {
+    let has_capsule = with_capsule_store(|store| {
+        store
+            .find_by_subject(&PersonRef::Principal(principal))
+            .is_some()
+    });
+
+    CallerIdentity {
+        principal,
+        is_anonymous: principal == candid::Principal::anonymous(),
+        has_capsule,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caller_identity_anonymous_without_capsule() {
+        let identity = caller_identity(candid::Principal::anonymous());
+        assert!(identity.is_anonymous);
+        assert!(!identity.has_capsule);
+    }
+
+    #[test]
+    fn test_list_by_tag_covers_managed_capsules_only() {
+        use crate::capsule::commands::edit_capsule_tags;
+        use crate::capsule_store::Store;
+
+        let person = |byte: u8| PersonRef::Principal(candid::Principal::from_slice(&[byte; 29]));
+        let mut store = Store::new_stable_test();
+        for (id, owner) in [
+            ("capsule_a", person(1)),
+            ("capsule_b", person(1)),
+            ("capsule_c", person(2)),
+        ] {
+            let capsule = crate::test_utils::create_test_capsule_at(id, owner, 1);
+            store.upsert(capsule.id.clone(), capsule);
+        }
+
+        let tag = |store: &mut Store, who: &PersonRef, id: &str, tags: &[&str]| {
+            let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+            edit_capsule_tags(store, who, id, 5, |capsule| capsule.add_tags(&tags))
+        };
+        assert_eq!(
+            tag(&mut store, &person(1), "capsule_a", &["Work", "family"]),
+            Ok(vec!["family".to_string(), "work".to_string()])
+        );
+        tag(&mut store, &person(1), "capsule_b", &["family"]).unwrap();
+        tag(&mut store, &person(2), "capsule_c", &["work"]).unwrap();
+        // Only owners change tags
+        assert_eq!(
+            tag(&mut store, &person(2), "capsule_a", &["stolen"]),
+            Err(Error::Unauthorized)
+        );
+
+        let ids = |headers: Vec<CapsuleHeader>| -> Vec<String> {
+            headers.into_iter().map(|header| header.id).collect()
+        };
+        assert_eq!(
+            ids(capsules_with_tag(&store, &person(1), "WORK")),
+            vec!["capsule_a"]
+        );
+        assert_eq!(
+            ids(capsules_with_tag(&store, &person(1), "family")),
+            vec!["capsule_a", "capsule_b"]
+        );
+        assert!(capsules_with_tag(&store, &person(1), "stolen").is_empty());
+        assert_eq!(store.get(&"capsule_a".to_string()).unwrap().updated_at, 5);
+    }
+
+    #[test]
+    fn test_caller_identity_with_capsule() {
+        let principal = candid::Principal::from_slice(&[4; 29]);
+        let capsule = crate::test_utils::create_test_capsule_at(
+            "capsule_identity",
+            PersonRef::Principal(principal),
+            1,
+        );
+        crate::memory::with_capsule_store_mut(|store| {
+            store.upsert(capsule.id.clone(), capsule);
+        });
+
+        let identity = caller_identity(principal);
+        assert_eq!(identity.principal, principal);
+        assert!(!identity.is_anonymous);
+        assert!(identity.has_capsule);
+    }
+}
@@ -1,9 +1,19 @@
-use crate::capsule::domain::{Capsule, PersonRef};
+use crate::capsule::api_types::{
+    BlobSummary, CapsuleDeleteReport, CapsuleIdMismatch, DerivedCounterFix, ReclaimReport,
+    SharingGrant, SharingGrantee, SharingSummary,
+};
+use crate::capsule::domain::{
+    is_access_active, AccessEntry, Capsule, GrantSource, PersonRef, ResourceRole, ResourceType,
+};
 use crate::capsule_store::{types::PaginationOrder as Order, CapsuleStore};
 use crate::memory::{with_capsule_store, with_capsule_store_mut};
-use crate::types::Error;
+use crate::types::{Error, MemoryAssetBlobInternal};
+use crate::upload::blob_store::BlobStore;
+use crate::upload::types::{BlobId, BlobMeta, CHUNK_SIZE};
+use crate::utils::blob_id::parse_blob_id;
 use ic_cdk::api::time;
 use ic_stable_structures::Storable;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Calculate the serialized size of a capsule
 pub fn calculate_capsule_size(capsule: &Capsule) -> u64 {
@@ -38,6 +48,338 @@ pub fn update_capsule_activity(
     .map_err(|_| crate::types::Error::Internal("Failed to update capsule activity".to_string()))
 }
 
+/// Find memories, galleries and folders whose `capsule_id` differs from their container
+///
+/// Sorted by resource type, then id, so repeated checks are comparable.
+pub fn capsule_id_mismatches(capsule: &Capsule) -> Vec<CapsuleIdMismatch> {
+    let claims = capsule
+        .memories
+        .values()
+        .map(|m| (ResourceType::Memory, &m.id, &m.capsule_id))
+        .chain(
+            capsule
+                .galleries
+                .values()
+                .map(|g| (ResourceType::Gallery, &g.id, &g.capsule_id)),
+        )
+        .chain(
+            capsule
+                .folders
+                .values()
+                .map(|f| (ResourceType::Folder, &f.id, &f.capsule_id)),
+        );
+
+    let mut mismatches: Vec<CapsuleIdMismatch> = claims
+        .filter(|(_, _, claimed)| **claimed != capsule.id)
+        .map(|(resource_type, id, claimed)| CapsuleIdMismatch {
+            capsule_id: capsule.id.clone(),
+            resource_type,
+            resource_id: id.clone(),
+            claimed_capsule_id: claimed.clone(),
+        })
+        .collect();
+
+    mismatches.sort_by(|a, b| {
+        (&a.resource_type, &a.resource_id).cmp(&(&b.resource_type, &b.resource_id))
+    });
+    mismatches
+}
+
+/// Point every mismatched `capsule_id` back at the containing capsule
+///
+/// Returns what was fixed (empty when the capsule was already consistent).
+pub fn repair_capsule_ids(capsule: &mut Capsule) -> Vec<CapsuleIdMismatch> {
+    let mismatches = capsule_id_mismatches(capsule);
+    for mismatch in &mismatches {
+        let owner = capsule.id.clone();
+        match mismatch.resource_type {
+            ResourceType::Memory => {
+                if let Some(memory) = capsule.memories.get_mut(&mismatch.resource_id) {
+                    memory.capsule_id = owner;
+                }
+            }
+            ResourceType::Gallery => {
+                if let Some(gallery) = capsule.galleries.get_mut(&mismatch.resource_id) {
+                    gallery.capsule_id = owner;
+                }
+            }
+            ResourceType::Folder => {
+                if let Some(folder) = capsule.folders.get_mut(&mismatch.resource_id) {
+                    folder.capsule_id = owner;
+                }
+            }
+            ResourceType::Capsule => {}
+        }
+    }
+    mismatches
+}
+
+/// Check (and optionally repair) `capsule_id` consistency across all capsules
+pub fn check_capsule_ids(apply: bool) -> Vec<CapsuleIdMismatch> {
+    let all_capsules = with_capsule_store(|store| store.paginate(None, u32::MAX, Order::Asc));
+
+    let mut found = Vec::new();
+    for mut capsule in all_capsules.items {
+        if !apply {
+            found.extend(capsule_id_mismatches(&capsule));
+            continue;
+        }
+
+        let fixed = repair_capsule_ids(&mut capsule);
+        if !fixed.is_empty() {
+            with_capsule_store_mut(|store| store.upsert(capsule.id.clone(), capsule));
+            found.extend(fixed);
+        }
+    }
+    found
+}
+
+/// Recompute stored counters from the collections they summarize
+///
+/// Covers the capsule's `inline_bytes_used` and each gallery's and folder's
+/// `total_memories`; header counts are derived on read and can't drift.
+/// Returns what was corrected, sorted by resource type, then id.
+pub fn recompute_derived(capsule: &mut Capsule) -> Vec<DerivedCounterFix> {
+    let mut fixes = Vec::new();
+    let mut check = |resource_type: ResourceType, id: &str, field: &str, stored: u64, actual| {
+        if stored != actual {
+            fixes.push(DerivedCounterFix {
+                resource_type,
+                resource_id: id.to_string(),
+                field: field.to_string(),
+                before: stored,
+                after: actual,
+            });
+        }
+    };
+
+    let inline_bytes: u64 = capsule
+        .memories
+        .values()
+        .flat_map(|memory| memory.inline_assets.iter())
+        .map(|asset| asset.bytes.len() as u64)
+        .sum();
+    check(
+        ResourceType::Capsule,
+        &capsule.id,
+        "inline_bytes_used",
+        capsule.inline_bytes_used,
+        inline_bytes,
+    );
+    capsule.inline_bytes_used = inline_bytes;
+
+    for gallery in capsule.galleries.values_mut() {
+        let actual = gallery.items.len() as u32;
+        check(
+            ResourceType::Gallery,
+            &gallery.id,
+            "total_memories",
+            gallery.metadata.total_memories as u64,
+            actual as u64,
+        );
+        gallery.metadata.total_memories = actual;
+    }
+
+    let mut per_folder: BTreeMap<&str, u32> = BTreeMap::new();
+    for memory in capsule.memories.values() {
+        if let Some(folder_id) = &memory.metadata.parent_folder_id {
+            *per_folder.entry(folder_id.as_str()).or_default() += 1;
+        }
+    }
+    for folder in capsule.folders.values_mut() {
+        let actual = per_folder.get(folder.id.as_str()).copied().unwrap_or(0);
+        check(
+            ResourceType::Folder,
+            &folder.id,
+            "total_memories",
+            folder.metadata.total_memories as u64,
+            actual as u64,
+        );
+        folder.metadata.total_memories = actual;
+    }
+
+    fixes.sort_by(|a, b| {
+        (&a.resource_type, &a.resource_id).cmp(&(&b.resource_type, &b.resource_id))
+    });
+    fixes
+}
+
+/// Internal blob IDs referenced by the memories of `capsule`
+pub fn internal_blob_ids(capsule: &Capsule) -> BTreeSet<u64> {
+    blob_references(capsule).into_keys().collect()
+}
+
+/// Blob ID behind an internal asset's locator (which may carry a `canister_id:` prefix)
+pub fn internal_blob_id(asset: &MemoryAssetBlobInternal) -> Option<u64> {
+    asset
+        .blob_ref
+        .locator
+        .rsplit(':')
+        .next()
+        .and_then(|locator| parse_blob_id(locator).ok())
+}
+
+/// Reverse index of `capsule`: internal blob ID -> assets referencing it
+///
+/// Built from the capsule itself, so it can't drift from its memories. Locators
+/// may carry a `canister_id:` prefix; unparseable ones are skipped.
+fn blob_references(capsule: &Capsule) -> BTreeMap<u64, Vec<(&str, &MemoryAssetBlobInternal)>> {
+    let mut references: BTreeMap<u64, Vec<_>> = BTreeMap::new();
+    for memory in capsule.memories.values() {
+        for asset in &memory.blob_internal_assets {
+            let Some(blob_id) = internal_blob_id(asset) else {
+                continue;
+            };
+            references
+                .entry(blob_id)
+                .or_default()
+                .push((memory.id.as_str(), asset));
+        }
+    }
+    references
+}
+
+/// Number of internal assets of `capsule` referencing each blob ID
+///
+/// Feeds the capsule store's blob refcount index, which the store adjusts on
+/// every capsule write.
+pub fn blob_ref_counts(capsule: &Capsule) -> BTreeMap<u64, u32> {
+    blob_references(capsule)
+        .into_iter()
+        .map(|(blob_id, assets)| (blob_id, assets.len() as u32))
+        .collect()
+}
+
+/// Summaries of the internal blobs referenced by `capsule`, ordered by locator
+///
+/// Size comes from the blob store when the blob is still there, otherwise from
+/// the referencing asset.
+pub fn capsule_blob_summaries<B: BlobStore>(blob_store: &B, capsule: &Capsule) -> Vec<BlobSummary> {
+    blob_references(capsule)
+        .into_iter()
+        .map(|(blob_id, refs)| {
+            let (_, first) = refs[0];
+            let size = blob_store
+                .get_meta(blob_id)
+                .map_or(first.blob_ref.len, |meta| meta.size);
+            let mut referenced_by: Vec<String> = refs
+                .iter()
+                .map(|(memory_id, _)| memory_id.to_string())
+                .collect();
+            referenced_by.sort();
+            referenced_by.dedup();
+
+            BlobSummary {
+                locator: format!("blob_{blob_id}"),
+                size,
+                chunk_count: size.div_ceil(CHUNK_SIZE as u64) as u32,
+                content_type: first.metadata.get_base().mime_type.clone(),
+                referenced_by,
+            }
+        })
+        .collect()
+}
+
+/// Delete the internal blobs of `capsule` that no other capsule references
+///
+/// `refs_elsewhere` gives the number of assets outside `capsule` referencing a
+/// blob (from the capsule store's refcount index); shared blobs are kept and
+/// counted. Returns the metadata of freed blobs too, so callers can release
+/// quota charged elsewhere.
+pub fn free_capsule_blobs<B: BlobStore>(
+    blob_store: &B,
+    capsule: &Capsule,
+    refs_elsewhere: impl Fn(u64) -> u32,
+) -> (CapsuleDeleteReport, Vec<BlobMeta>) {
+    let mut report = CapsuleDeleteReport::default();
+    let mut freed = Vec::new();
+    for blob_id in internal_blob_ids(capsule) {
+        if refs_elsewhere(blob_id) > 0 {
+            report.blobs_kept_shared += 1;
+            continue;
+        }
+        let Some(meta) = blob_store.get_meta(blob_id) else {
+            continue; // already gone
+        };
+        if blob_store.delete_blob(&BlobId(blob_id)).is_ok() {
+            report.blobs_freed += 1;
+            report.bytes_reclaimed += meta.size;
+            freed.push(meta);
+        }
+    }
+    (report, freed)
+}
+
+/// Free the internal blobs of memories in `capsule` trashed at or before `cutoff`
+///
+/// A memory is trashed when its `metadata.deleted_at` is set. Its record stays
+/// (with inline and external assets) but its internal assets are dropped, so
+/// nothing points at freed bytes. `ref_count` gives the number of assets
+/// referencing a blob before the reclaim, `capsule`'s own included (from the
+/// capsule store's refcount index); a blob still referenced once the trashed
+/// assets are dropped is kept and counted. Returns the freed blobs' metadata
+/// too, so callers can release their quota charges.
+pub fn reclaim_trashed_blobs<B: BlobStore>(
+    blob_store: &B,
+    capsule: &mut Capsule,
+    ref_count: impl Fn(u64) -> u32,
+    cutoff: u64,
+) -> (ReclaimReport, Vec<BlobMeta>) {
+    let mut report = ReclaimReport::default();
+    let mut dropped: BTreeMap<u64, u32> = BTreeMap::new();
+    for memory in capsule.memories.values_mut() {
+        let trashed = memory
+            .metadata
+            .deleted_at
+            .is_some_and(|deleted_at| deleted_at <= cutoff);
+        if !trashed || memory.blob_internal_assets.is_empty() {
+            continue;
+        }
+        for blob_id in std::mem::take(&mut memory.blob_internal_assets)
+            .iter()
+            .filter_map(internal_blob_id)
+        {
+            *dropped.entry(blob_id).or_insert(0) += 1;
+        }
+        memory.update_dashboard_fields();
+        report.memories_reclaimed += 1;
+    }
+
+    let mut freed = Vec::new();
+    for (blob_id, refs) in dropped {
+        if ref_count(blob_id) > refs {
+            report.blobs_kept_shared += 1;
+            continue;
+        }
+        let Some(meta) = blob_store.get_meta(blob_id) else {
+            continue; // already gone
+        };
+        if blob_store.delete_blob(&BlobId(blob_id)).is_ok() {
+            report.blobs_freed += 1;
+            report.bytes_reclaimed += meta.size;
+            freed.push(meta);
+        }
+    }
+    (report, freed)
+}
+
+/// Delete internal blob `blob_id` unless `ref_count` assets still reference it
+///
+/// `ref_count` comes from the capsule store's blob refcount index. Returns the
+/// freed blob's metadata so the caller can release its quota charge.
+pub fn free_blob_if_unreferenced<B: BlobStore>(
+    blob_store: &B,
+    blob_id: u64,
+    ref_count: u32,
+) -> Option<BlobMeta> {
+    if ref_count > 0 {
+        return None;
+    }
+    let meta = blob_store.get_meta(blob_id)?;
+    blob_store.delete_blob(&BlobId(blob_id)).ok()?;
+    Some(meta)
+}
+
 /// Export all capsules for upgrade persistence
 #[allow(dead_code)]
 pub fn export_capsules_for_upgrade() -> Vec<(String, Capsule)> {
@@ -61,3 +403,471 @@ pub fn import_capsules_from_upgrade(capsule_data: Vec<(String, Capsule)>) {
         }
     })
 }
+
+/// Aggregate the active grants and public resources of a capsule
+///
+/// Expired or not-yet-active grants are left out, as the permission evaluator
+/// would ignore them. A group grant is reported under its group id.
+pub fn sharing_summary(capsule: &Capsule, now: u64) -> SharingSummary {
+    let mut grants: BTreeMap<SharingGrantee, (u32, u32)> = BTreeMap::new();
+    // Tally one resource's grants; true when it is public
+    let mut tally = |entries: &[AccessEntry]| {
+        let mut public = false;
+        let mut seen = BTreeSet::new();
+        for entry in entries {
+            if !is_access_active(&entry.condition, now) {
+                continue;
+            }
+            if entry.is_public {
+                public = true;
+                continue;
+            }
+            if entry.role == ResourceRole::Owner {
+                continue;
+            }
+            let grantee = match (&entry.grant_source, &entry.source_id, &entry.person_ref) {
+                (GrantSource::Group, Some(group_id), _) => SharingGrantee::Group(group_id.clone()),
+                (_, _, Some(person)) => SharingGrantee::Person(person.clone()),
+                _ => continue,
+            };
+            let (mask, count) = grants.entry(grantee.clone()).or_default();
+            *mask |= entry.perm_mask;
+            if seen.insert(grantee) {
+                *count += 1;
+            }
+        }
+        public
+    };
+
+    let public_memories = capsule
+        .memories
+        .values()
+        .filter(|m| tally(&m.access_entries))
+        .count() as u32;
+    let public_galleries = capsule
+        .galleries
+        .values()
+        .filter(|g| tally(&g.access_entries))
+        .count() as u32;
+    let public_folders = capsule
+        .folders
+        .values()
+        .filter(|f| tally(&f.access_entries))
+        .count() as u32;
+
+    SharingSummary {
+        capsule_id: capsule.id.clone(),
+        grants: grants
+            .into_iter()
+            .map(|(grantee, (perm_mask, resource_count))| SharingGrant {
+                grantee,
+                perm_mask,
+                resource_count,
+            })
+            .collect(),
+        public_memories,
+        public_galleries,
+        public_folders,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_test_capsule_at, create_test_memory_at};
+    use candid::Principal;
+
+    #[test]
+    fn test_capsule_id_mismatches_flags_and_repairs() {
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_a", owner, 1);
+        capsule
+            .memories
+            .insert("m_ok".into(), create_test_memory_at("m_ok", "capsule_a", 1));
+        capsule.memories.insert(
+            "m_bad".into(),
+            create_test_memory_at("m_bad", "capsule_b", 1),
+        );
+
+        let mismatches = capsule_id_mismatches(&capsule);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].resource_type, ResourceType::Memory);
+        assert_eq!(mismatches[0].resource_id, "m_bad");
+        assert_eq!(mismatches[0].claimed_capsule_id, "capsule_b");
+
+        let fixed = repair_capsule_ids(&mut capsule);
+        assert_eq!(fixed, mismatches);
+        assert_eq!(capsule.memories["m_bad"].capsule_id, "capsule_a");
+        assert!(capsule_id_mismatches(&capsule).is_empty());
+    }
+
+    fn blob_memory(id: &str, capsule_id: &str, locator: &str) -> crate::types::Memory {
+        use crate::memories::types::{AssetMetadataBase, AssetType, NoteAssetMetadata};
+        let mut memory = create_test_memory_at(id, capsule_id, 1);
+        memory
+            .blob_internal_assets
+            .push(crate::types::MemoryAssetBlobInternal {
+                asset_id: format!("asset_{id}"),
+                blob_ref: crate::types::BlobRef {
+                    locator: locator.to_string(),
+                    hash: None,
+                    len: 5,
+                },
+                metadata: crate::types::AssetMetadata::Note(NoteAssetMetadata {
+                    base: AssetMetadataBase {
+                        name: "note.txt".to_string(),
+                        description: None,
+                        tags: vec![],
+                        asset_type: AssetType::Original,
+                        bytes: 5,
+                        mime_type: "text/plain".to_string(),
+                        sha256: None,
+                        width: None,
+                        height: None,
+                        url: None,
+                        storage_key: None,
+                        bucket: None,
+                        asset_location: None,
+                        processing_status: None,
+                        processing_error: None,
+                        created_at: 1,
+                        updated_at: 1,
+                        deleted_at: None,
+                    },
+                    word_count: None,
+                    language: None,
+                    format: None,
+                }),
+                order: None,
+            });
+        memory
+    }
+
+    #[test]
+    fn test_recompute_derived_fixes_corrupted_counters() {
+        use crate::capsule::domain::SharingStatus;
+        use crate::folder::domain::{Folder, FolderMetadata};
+
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_a", owner, 1);
+        let mut memory = blob_memory("m1", "capsule_a", "blob_1");
+        memory.inline_assets.push(crate::types::MemoryAssetInline {
+            asset_id: "inline_1".to_string(),
+            bytes: vec![0; 7],
+            metadata: memory.blob_internal_assets[0].metadata.clone(),
+            order: None,
+        });
+        memory.metadata.parent_folder_id = Some("f1".to_string());
+        capsule.memories.insert("m1".into(), memory);
+        capsule.folders.insert(
+            "f1".into(),
+            Folder {
+                id: "f1".to_string(),
+                capsule_id: "capsule_a".to_string(),
+                metadata: FolderMetadata {
+                    title: None,
+                    name: "f1".to_string(),
+                    description: None,
+                    shared_count: 0,
+                    sharing_status: SharingStatus::Private,
+                    total_memories: 1,
+                    storage_location: vec![],
+                },
+                access_entries: vec![],
+                created_at: 1,
+                updated_at: 1,
+                deleted_at: None,
+            },
+        );
+        capsule.inline_bytes_used = 7;
+        assert!(recompute_derived(&mut capsule).is_empty());
+
+        // Corrupt both counters, as a partial failure would
+        capsule.inline_bytes_used = 100;
+        capsule
+            .folders
+            .get_mut("f1")
+            .unwrap()
+            .metadata
+            .total_memories = 3;
+
+        let fixes = recompute_derived(&mut capsule);
+        let summary: Vec<_> = fixes
+            .iter()
+            .map(|f| (f.resource_id.as_str(), f.before, f.after))
+            .collect();
+        assert_eq!(summary, vec![("f1", 3, 1), ("capsule_a", 100, 7)]);
+        assert_eq!(capsule.inline_bytes_used, 7);
+        assert_eq!(capsule.folders["f1"].metadata.total_memories, 1);
+        assert!(recompute_derived(&mut capsule).is_empty());
+    }
+
+    #[test]
+    fn test_sharing_summary_totals_grants_and_public_resources() {
+        use crate::capsule::domain::{AccessCondition, Perm, SharingStatus};
+        use crate::folder::domain::{Folder, FolderMetadata};
+
+        let entry = |person: Option<PersonRef>,
+                     source: GrantSource,
+                     source_id: Option<&str>,
+                     perm: Perm,
+                     condition: AccessCondition| AccessEntry {
+            id: "grant".to_string(),
+            is_public: person.is_none() && source_id.is_none(),
+            person_ref: person,
+            grant_source: source,
+            source_id: source_id.map(str::to_string),
+            role: ResourceRole::Guest,
+            perm_mask: perm.bits(),
+            invited_by_person_ref: None,
+            created_at: 1,
+            updated_at: 1,
+            condition,
+        };
+        let alice = PersonRef::Principal(Principal::from_slice(&[1; 29]));
+        let bob = PersonRef::Principal(Principal::from_slice(&[2; 29]));
+
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_a", owner, 1);
+        let private = create_test_memory_at("m_private", "capsule_a", 1);
+        let mut shared = create_test_memory_at("m_shared", "capsule_a", 1);
+        shared.access_entries.extend([
+            entry(
+                Some(alice.clone()),
+                GrantSource::User,
+                None,
+                Perm::VIEW,
+                AccessCondition::Immediate,
+            ),
+            entry(
+                None,
+                GrantSource::Group,
+                Some("family"),
+                Perm::VIEW | Perm::DOWNLOAD,
+                AccessCondition::Immediate,
+            ),
+            // Expired: not counted
+            entry(
+                Some(bob),
+                GrantSource::User,
+                None,
+                Perm::MANAGE,
+                AccessCondition::ExpiresAt { expires: 5 },
+            ),
+        ]);
+        let mut public = create_test_memory_at("m_public", "capsule_a", 1);
+        public.access_entries.push(entry(
+            None,
+            GrantSource::System,
+            None,
+            Perm::VIEW,
+            AccessCondition::Immediate,
+        ));
+        for memory in [private, shared, public] {
+            capsule.memories.insert(memory.id.clone(), memory);
+        }
+        capsule.folders.insert(
+            "f1".into(),
+            Folder {
+                id: "f1".to_string(),
+                capsule_id: "capsule_a".to_string(),
+                metadata: FolderMetadata {
+                    title: None,
+                    name: "f1".to_string(),
+                    description: None,
+                    shared_count: 1,
+                    sharing_status: SharingStatus::Shared,
+                    total_memories: 0,
+                    storage_location: vec![],
+                },
+                access_entries: vec![entry(
+                    Some(alice.clone()),
+                    GrantSource::User,
+                    None,
+                    Perm::SHARE,
+                    AccessCondition::Immediate,
+                )],
+                created_at: 1,
+                updated_at: 1,
+                deleted_at: None,
+            },
+        );
+
+        let summary = sharing_summary(&capsule, 10);
+        assert_eq!(
+            summary.grants,
+            vec![
+                SharingGrant {
+                    grantee: SharingGrantee::Person(alice),
+                    perm_mask: (Perm::VIEW | Perm::SHARE).bits(),
+                    resource_count: 2,
+                },
+                SharingGrant {
+                    grantee: SharingGrantee::Group("family".to_string()),
+                    perm_mask: (Perm::VIEW | Perm::DOWNLOAD).bits(),
+                    resource_count: 1,
+                },
+            ]
+        );
+        assert_eq!(summary.public_memories, 1);
+        assert_eq!(summary.public_galleries, 0);
+        assert_eq!(summary.public_folders, 0);
+    }
+
+    #[test]
+    fn test_free_capsule_blobs_skips_shared_blobs() {
+        use crate::upload::blob_store::InMemoryBlobStore;
+
+        let blob_store = InMemoryBlobStore::new(1);
+        for blob_id in [1u64, 2] {
+            let pmid_hash = [blob_id as u8; 32];
+            blob_store.put_page(pmid_hash, 0, b"hello".to_vec());
+            blob_store.put_meta(
+                blob_id,
+                BlobMeta {
+                    size: 5,
+                    checksum: [0; 32],
+                    created_at: 1,
+                    pmid_hash,
+                    capsule_id: Some("capsule_a".to_string()),
+                },
+            );
+        }
+
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut doomed = create_test_capsule_at("capsule_a", owner.clone(), 1);
+        doomed
+            .memories
+            .insert("m1".into(), blob_memory("m1", "capsule_a", "blob_1"));
+        doomed.memories.insert(
+            "m2".into(),
+            blob_memory("m2", "capsule_a", "aaaaa-aa:blob_2"),
+        );
+        // A duplicate in another capsule still points at blob 2
+        let mut other = create_test_capsule_at("capsule_b", owner, 1);
+        other
+            .memories
+            .insert("m3".into(), blob_memory("m3", "capsule_b", "blob_2"));
+
+        let other_refs = blob_ref_counts(&other);
+        let (report, freed) = free_capsule_blobs(&blob_store, &doomed, |blob_id| {
+            other_refs.get(&blob_id).copied().unwrap_or(0)
+        });
+
+        assert_eq!(
+            report,
+            CapsuleDeleteReport {
+                blobs_freed: 1,
+                bytes_reclaimed: 5,
+                blobs_kept_shared: 1,
+            }
+        );
+        assert_eq!(freed.len(), 1);
+        assert!(blob_store.get_meta(1).is_none());
+        assert!(blob_store.get_page(&[1; 32], 0).is_none());
+        assert!(blob_store.get_meta(2).is_some());
+    }
+
+    #[test]
+    fn test_reclaim_frees_trashed_blob_and_keeps_record() {
+        use crate::upload::blob_store::InMemoryBlobStore;
+
+        let blob_store = InMemoryBlobStore::new(1);
+        for blob_id in [1u64, 2] {
+            let pmid_hash = [blob_id as u8; 32];
+            blob_store.put_page(pmid_hash, 0, b"hello".to_vec());
+            blob_store.put_meta(
+                blob_id,
+                BlobMeta {
+                    size: 5,
+                    checksum: [0; 32],
+                    created_at: 1,
+                    pmid_hash,
+                    capsule_id: Some("capsule_a".to_string()),
+                },
+            );
+        }
+
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_a", owner, 1);
+        let mut trashed = blob_memory("m1", "capsule_a", "blob_1");
+        trashed.metadata.deleted_at = Some(10);
+        capsule.memories.insert("m1".into(), trashed);
+        capsule
+            .memories
+            .insert("m2".into(), blob_memory("m2", "capsule_a", "blob_2"));
+
+        // Trashed after the cutoff: nothing to reclaim yet
+        let refs = blob_ref_counts(&capsule);
+        let ref_count = |blob_id| refs.get(&blob_id).copied().unwrap_or(0);
+        let (report, _) = reclaim_trashed_blobs(&blob_store, &mut capsule, ref_count, 5);
+        assert_eq!(report, ReclaimReport::default());
+
+        let (report, freed) = reclaim_trashed_blobs(&blob_store, &mut capsule, ref_count, 20);
+        assert_eq!(
+            report,
+            ReclaimReport {
+                memories_reclaimed: 1,
+                blobs_freed: 1,
+                bytes_reclaimed: 5,
+                blobs_kept_shared: 0,
+            }
+        );
+        assert_eq!(freed.len(), 1);
+        assert!(blob_store.get_meta(1).is_none());
+        assert!(blob_store.get_page(&[1; 32], 0).is_none());
+
+        // The trashed record stays, without its internal asset; live memories are untouched
+        let record = &capsule.memories["m1"];
+        assert_eq!(record.metadata.deleted_at, Some(10));
+        assert!(record.blob_internal_assets.is_empty());
+        assert_eq!(capsule.memories["m2"].blob_internal_assets.len(), 1);
+        assert!(blob_store.get_meta(2).is_some());
+    }
+
+    #[test]
+    fn test_capsule_blob_summaries_lists_referencing_memories() {
+        use crate::upload::blob_store::InMemoryBlobStore;
+
+        let blob_store = InMemoryBlobStore::new(1);
+        blob_store.put_meta(
+            7,
+            BlobMeta {
+                size: 5,
+                checksum: [0; 32],
+                created_at: 1,
+                pmid_hash: [7; 32],
+                capsule_id: Some("capsule_a".to_string()),
+            },
+        );
+
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_a", owner, 1);
+        for (memory_id, locator) in [
+            ("m2", "blob_7"),
+            ("m1", "aaaaa-aa:blob_7"),
+            ("m3", "blob_9"),
+        ] {
+            capsule.memories.insert(
+                memory_id.into(),
+                blob_memory(memory_id, "capsule_a", locator),
+            );
+        }
+        // An inline-only memory references no blob
+        capsule
+            .memories
+            .insert("m4".into(), create_test_memory_at("m4", "capsule_a", 1));
+
+        let summaries = capsule_blob_summaries(&blob_store, &capsule);
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].locator, "blob_7");
+        assert_eq!(summaries[0].size, 5);
+        assert_eq!(summaries[0].chunk_count, 1);
+        assert_eq!(summaries[0].content_type, "text/plain");
+        assert_eq!(summaries[0].referenced_by, vec!["m1", "m2"]);
+        // Blob 9 is gone from the store: size falls back to the asset's blob_ref
+        assert_eq!(summaries[1].locator, "blob_9");
+        assert_eq!(summaries[1].referenced_by, vec!["m3"]);
+    }
+}
@@ -3,6 +3,7 @@
 //! This module provides a single source of truth for capsule access control,
 //! eliminating inconsistencies between different memory operations.
 
+use crate::capsule::domain::DefaultMemoryAccess;
 use crate::types::{ControllerState, OwnerState, PersonRef};
 use std::collections::HashMap;
 
@@ -33,6 +34,7 @@ pub struct CapsuleAccess {
     pub subject: PersonRef,
     pub owners: HashMap<PersonRef, OwnerState>,
     pub controllers: HashMap<PersonRef, ControllerState>,
+    pub default_memory_access: DefaultMemoryAccess, // what new memories get unless overridden
 }
 
 impl CapsuleAccess {
@@ -45,8 +47,15 @@ impl CapsuleAccess {
             subject,
             owners,
             controllers,
+            default_memory_access: DefaultMemoryAccess::Private,
         }
     }
+
+    /// Carry the capsule's default access for new memories
+    pub fn with_default_memory_access(mut self, access: DefaultMemoryAccess) -> Self {
+        self.default_memory_access = access;
+        self
+    }
 }
 
 impl CapsuleAcl for CapsuleAccess {
@@ -89,6 +89,22 @@ pub trait CapsuleStore {
     /// Get storage statistics
     /// Returns (capsules_count, subject_index_count, owner_index_count)
     fn stats(&self) -> (u64, u64, u64);
+
+    /// Number of memory assets, across all capsules, referencing internal blob `blob_id`
+    fn blob_ref_count(&self, blob_id: u64) -> u32;
+
+    /// Recount blob references from every stored capsule if the refcount index is empty
+    ///
+    /// Fills the index for capsules written before it existed. Returns the number of
+    /// blobs indexed (0 when the index was already populated).
+    fn backfill_blob_refs(&mut self) -> u64;
+
+    /// Remove every capsule and every index entry (test isolation)
+    ///
+    /// Backends must clear any secondary index they maintain, so a cleared
+    /// store is indistinguishable from a fresh one.
+    #[cfg(test)]
+    fn clear(&mut self);
 }
 
 // Include the backend implementations
@@ -307,6 +307,14 @@ fn create_test_capsule_with_principal(
         bound_to_neon: false,
         inline_bytes_used: 0,
         hosting_preferences: HostingPreferences::default(),
+        created_by: None,
+        role_templates: None,
+        blob_bytes_quota: None,
+        blob_bytes_used: None,
+        tags: None,
+        access_entries: None,
+        default_memory_access: None,
+        public_memory_default_allowed: None,
     }
 }
 
@@ -337,5 +345,13 @@ fn create_test_capsule(id: CapsuleId) -> Capsule {
         bound_to_neon: false,
         inline_bytes_used: 0,
         hosting_preferences: HostingPreferences::default(),
+        created_by: None,
+        role_templates: None,
+        blob_bytes_quota: None,
+        blob_bytes_used: None,
+        tags: None,
+        access_entries: None,
+        default_memory_access: None,
+        public_memory_default_allowed: None,
     }
 }
@@ -12,7 +12,10 @@
 
 use super::{CapsuleId, CapsuleStore, Order, Page};
 use crate::capsule::domain::Capsule;
-use crate::memory::{MEM_CAPSULES, MEM_CAPSULES_IDX_OWNER, MEM_CAPSULES_IDX_SUBJECT, MM};
+use crate::capsule::util::blob_ref_counts;
+use crate::memory::{
+    MEM_BLOB_REFS, MEM_CAPSULES, MEM_CAPSULES_IDX_OWNER, MEM_CAPSULES_IDX_SUBJECT, MM,
+};
 use crate::state::track_size_change;
 use crate::types::Error;
 #[allow(unused_imports)]
@@ -20,6 +23,7 @@ use candid::{Decode, Encode, Principal};
 use ic_stable_structures::memory_manager::VirtualMemory;
 use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableBTreeMap, Storable};
 use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Owner index key for sparse multimap: (owner_principal_bytes, capsule_id)
 ///
@@ -90,6 +94,8 @@ pub struct StableStore {
     /// Owner → CapsuleId sparse multimap (1:N relationship)
     /// Key: OwnerIndexKey(owner_principal_bytes, capsule_id), Value: ()
     owner_index: StableBTreeMap<OwnerIndexKey, (), VirtualMemory<DefaultMemoryImpl>>,
+    /// Internal blob ID → number of memory assets referencing it, across all capsules
+    blob_refs: StableBTreeMap<u64, u32, VirtualMemory<DefaultMemoryImpl>>,
 }
 
 impl Default for StableStore {
@@ -112,6 +118,7 @@ impl StableStore {
                 capsules: StableBTreeMap::init(mm.get(MEM_CAPSULES)),
                 subject_index: StableBTreeMap::init(mm.get(MEM_CAPSULES_IDX_SUBJECT)),
                 owner_index: StableBTreeMap::init(mm.get(MEM_CAPSULES_IDX_OWNER)),
+                blob_refs: StableBTreeMap::init(mm.get(MEM_BLOB_REFS)),
             }
         })
     }
@@ -126,6 +133,7 @@ impl StableStore {
             capsules: StableBTreeMap::init(memory_manager.get(MEM_CAPSULES)),
             subject_index: StableBTreeMap::init(memory_manager.get(MEM_CAPSULES_IDX_SUBJECT)),
             owner_index: StableBTreeMap::init(memory_manager.get(MEM_CAPSULES_IDX_OWNER)),
+            blob_refs: StableBTreeMap::init(memory_manager.get(MEM_BLOB_REFS)),
         }
     }
 
@@ -164,6 +172,25 @@ impl StableStore {
             }
         }
     }
+
+    /// Internal helper: Move blob refcounts from a capsule's `old` references to its `new` ones
+    fn adjust_blob_refs(&mut self, old: &BTreeMap<u64, u32>, new: &BTreeMap<u64, u32>) {
+        let blob_ids: BTreeSet<u64> = old.keys().chain(new.keys()).copied().collect();
+        for blob_id in blob_ids {
+            let before = old.get(&blob_id).copied().unwrap_or(0);
+            let after = new.get(&blob_id).copied().unwrap_or(0);
+            if before == after {
+                continue;
+            }
+            let current = self.blob_refs.get(&blob_id).unwrap_or(0);
+            let count = (current + after).saturating_sub(before);
+            if count == 0 {
+                self.blob_refs.remove(&blob_id);
+            } else {
+                self.blob_refs.insert(blob_id, count);
+            }
+        }
+    }
 }
 
 impl StableStore {
@@ -229,6 +256,8 @@ impl CapsuleStore for StableStore {
 
         let prev = self.capsules.insert(id.clone(), capsule.clone());
         self.update_indexes(&id, &capsule);
+        let old_refs = prev.as_ref().map(blob_ref_counts).unwrap_or_default();
+        self.adjust_blob_refs(&old_refs, &blob_ref_counts(&capsule));
         prev
     }
 
@@ -246,6 +275,7 @@ impl CapsuleStore for StableStore {
         }
         self.capsules.insert(id.clone(), capsule.clone());
         self.update_indexes(&id, &capsule);
+        self.adjust_blob_refs(&BTreeMap::new(), &blob_ref_counts(&capsule));
         Ok(())
     }
 
@@ -264,6 +294,8 @@ impl CapsuleStore for StableStore {
                 })
                 .collect();
 
+            let old_refs = blob_ref_counts(&capsule);
+
             // Calculate old size before update
             let old_size = capsule.to_bytes().len() as u64;
 
@@ -316,6 +348,8 @@ impl CapsuleStore for StableStore {
                 }
             }
 
+            self.adjust_blob_refs(&old_refs, &blob_ref_counts(&capsule));
+
             // Save the updated capsule
             self.capsules.insert(id.clone(), capsule);
             Ok(())
@@ -339,6 +373,8 @@ impl CapsuleStore for StableStore {
                 })
                 .collect();
 
+            let old_refs = blob_ref_counts(&capsule);
+
             let result = f(&mut capsule)?;
 
             // Update indexes if subject or owners changed
@@ -382,6 +418,8 @@ impl CapsuleStore for StableStore {
                 }
             }
 
+            self.adjust_blob_refs(&old_refs, &blob_ref_counts(&capsule));
+
             // Save the updated capsule
             self.capsules.insert(id.clone(), capsule);
             Ok(result)
@@ -393,6 +431,7 @@ impl CapsuleStore for StableStore {
     fn remove(&mut self, id: &CapsuleId) -> Option<Capsule> {
         if let Some(capsule) = self.capsules.remove(id) {
             self.remove_from_indexes(id, &capsule);
+            self.adjust_blob_refs(&blob_ref_counts(&capsule), &BTreeMap::new());
             Some(capsule)
         } else {
             None
@@ -493,6 +532,34 @@ impl CapsuleStore for StableStore {
         // This is more efficient and reliable than iteration
         self.capsules.len()
     }
+
+    fn blob_ref_count(&self, blob_id: u64) -> u32 {
+        self.blob_refs.get(&blob_id).unwrap_or(0)
+    }
+
+    fn backfill_blob_refs(&mut self) -> u64 {
+        if !self.blob_refs.is_empty() {
+            return 0;
+        }
+        let mut counts: BTreeMap<u64, u32> = BTreeMap::new();
+        for (_, capsule) in self.capsules.iter() {
+            for (blob_id, refs) in blob_ref_counts(&capsule) {
+                *counts.entry(blob_id).or_insert(0) += refs;
+            }
+        }
+        for (blob_id, refs) in &counts {
+            self.blob_refs.insert(*blob_id, *refs);
+        }
+        counts.len() as u64
+    }
+
+    #[cfg(test)]
+    fn clear(&mut self) {
+        self.capsules.clear_new();
+        self.subject_index.clear_new();
+        self.owner_index.clear_new();
+        self.blob_refs.clear_new();
+    }
 }
 
 /// Storable implementation for Capsule
@@ -579,6 +646,14 @@ mod tests {
             bound_to_neon: false,
             inline_bytes_used: 0,
             hosting_preferences: HostingPreferences::default(),
+            created_by: None,
+            role_templates: None,
+            blob_bytes_quota: None,
+            blob_bytes_used: None,
+            tags: None,
+            access_entries: None,
+            default_memory_access: None,
+            public_memory_default_allowed: None,
         }
     }
 
@@ -600,6 +675,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_capsule_created_by_differs_from_subject() {
+        let mut capsule = create_test_capsule("memorial-capsule".to_string());
+        let creator = PersonRef::Principal(candid::Principal::from_slice(&[9; 29]));
+        assert_ne!(capsule.subject, creator);
+        capsule.created_by = Some(creator.clone());
+
+        // Survives stable encoding and is exposed in the listing header
+        let decoded = Capsule::from_bytes(capsule.to_bytes());
+        assert_eq!(decoded.created_by, Some(creator.clone()));
+        let header = decoded.to_header();
+        assert_eq!(header.created_by, Some(creator));
+        assert_eq!(header.subject, capsule.subject);
+    }
+
     #[test]
     fn test_capsule_size_within_bound() {
         let id = "test-capsule".to_string();
@@ -716,6 +806,25 @@ mod tests {
         assert_eq!(store2.count(), 0);
     }
 
+    #[test]
+    fn test_clear_empties_capsules_and_indexes() {
+        let mut store = StableStore::new_test();
+        for id in ["cap1", "cap2"] {
+            store.upsert(id.to_string(), create_test_capsule(id.to_string()));
+        }
+        assert_eq!(store.count(), 2);
+
+        store.clear();
+
+        assert_eq!(store.count(), 0);
+        assert_eq!(store.debug_lens(), (0, 0, 0));
+        assert!(!store.exists(&"cap1".to_string()));
+
+        // A cleared store accepts the same ids again
+        store.upsert("cap1".to_string(), create_test_capsule("cap1".to_string()));
+        assert_eq!(store.count(), 1);
+    }
+
     /// 🔧 GUARDRAIL TEST: Index consistency validation
     /// Comprehensive test to validate subject and owner index consistency
     #[test]
@@ -819,4 +928,79 @@ mod tests {
         assert_eq!(debug_lens.1, 3, "Subject index should have 3 entries");
         assert_eq!(debug_lens.2, 3, "Owner index should have 3 entries");
     }
+
+    fn add_blob_memory(capsule: &mut Capsule, memory_id: &str, locator: &str) {
+        let memory = crate::memories::core::model_helpers::create_blob_memory(
+            memory_id,
+            &capsule.id,
+            crate::types::BlobRef {
+                locator: locator.to_string(),
+                hash: None,
+                len: 5,
+            },
+            crate::test_utils::create_test_asset_metadata(),
+            1,
+            &PersonRef::Principal(candid::Principal::anonymous()),
+        );
+        capsule.memories.insert(memory_id.to_string(), memory);
+    }
+
+    #[test]
+    fn test_blob_refs_follow_capsule_writes() {
+        let mut store = StableStore::new_test();
+
+        let mut a = create_test_capsule("capsule_a".to_string());
+        add_blob_memory(&mut a, "m1", "blob_1");
+        add_blob_memory(&mut a, "m2", "aaaaa-aa:blob_1");
+        store.put_if_absent(a.id.clone(), a).unwrap();
+        let mut b = create_test_capsule("capsule_b".to_string());
+        add_blob_memory(&mut b, "m3", "blob_1");
+        add_blob_memory(&mut b, "m4", "blob_2");
+        store.upsert(b.id.clone(), b);
+        assert_eq!(store.blob_ref_count(1), 3);
+        assert_eq!(store.blob_ref_count(2), 1);
+
+        store
+            .update(&"capsule_a".to_string(), |capsule| {
+                capsule.memories.remove("m1");
+            })
+            .unwrap();
+        assert_eq!(store.blob_ref_count(1), 2);
+
+        store
+            .update_with(&"capsule_b".to_string(), |capsule| {
+                add_blob_memory(capsule, "m5", "blob_2");
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(store.blob_ref_count(2), 2);
+
+        store.remove(&"capsule_b".to_string());
+        assert_eq!(store.blob_ref_count(1), 1);
+        assert_eq!(store.blob_ref_count(2), 0);
+    }
+
+    #[test]
+    fn test_backfill_blob_refs_counts_capsules_stored_before_the_index() {
+        let mut store = StableStore::new_test();
+        let mut a = create_test_capsule("capsule_a".to_string());
+        add_blob_memory(&mut a, "m1", "blob_1");
+        add_blob_memory(&mut a, "m2", "blob_2");
+        let mut b = create_test_capsule("capsule_b".to_string());
+        add_blob_memory(&mut b, "m3", "blob_1");
+        store.upsert(a.id.clone(), a);
+        store.upsert(b.id.clone(), b);
+
+        // Simulate capsules written before the refcount index existed
+        store.blob_refs.clear_new();
+        assert_eq!(store.blob_ref_count(1), 0);
+
+        assert_eq!(store.backfill_blob_refs(), 2);
+        assert_eq!(store.blob_ref_count(1), 2);
+        assert_eq!(store.blob_ref_count(2), 1);
+
+        // A populated index is left alone
+        assert_eq!(store.backfill_blob_refs(), 0);
+        assert_eq!(store.blob_ref_count(1), 2);
+    }
 }
@@ -113,6 +113,25 @@ impl CapsuleStore for Store {
             Store::Stable(store) => store.stats(),
         }
     }
+
+    fn blob_ref_count(&self, blob_id: u64) -> u32 {
+        match self {
+            Store::Stable(store) => store.blob_ref_count(blob_id),
+        }
+    }
+
+    fn backfill_blob_refs(&mut self) -> u64 {
+        match self {
+            Store::Stable(store) => store.backfill_blob_refs(),
+        }
+    }
+
+    #[cfg(test)]
+    fn clear(&mut self) {
+        match self {
+            Store::Stable(store) => store.clear(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -241,6 +260,14 @@ mod tests {
             bound_to_neon: false,
             inline_bytes_used: 0,
             hosting_preferences: HostingPreferences::default(),
+            created_by: None,
+            role_templates: None,
+            blob_bytes_quota: None,
+            blob_bytes_used: None,
+            tags: None,
+            access_entries: None,
+            default_memory_access: None,
+            public_memory_default_allowed: None,
         }
     }
 }
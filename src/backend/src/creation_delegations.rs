@@ -0,0 +1,127 @@
+//! Delegations to create a capsule about someone else
+//!
+//! A principal can authorize one creator (e.g. an executor) to create a capsule
+//! with the principal as its subject. The subject mints a token naming the
+//! creator; the creator passes it to `capsules_create`, which checks it up front
+//! and consumes it once the capsule is stored. Like
+//! share links, only the sha256 of each token is stored, and a token expires
+//! after `CREATION_DELEGATION_TTL_NS` if unused.
+
+use crate::capsule::time::DAY_NS;
+use crate::memory::{MEM_CREATION_DELEGATIONS, MM};
+use crate::types::{Error, PersonRef};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// How long an unused delegation stays valid
+pub const CREATION_DELEGATION_TTL_NS: u64 = 7 * DAY_NS;
+
+thread_local! {
+    static STABLE_DELEGATIONS: RefCell<StableBTreeMap<String, CreationDelegation, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_CREATION_DELEGATIONS)))
+    );
+}
+
+/// A subject's authorization for `creator`, keyed by the sha256 of its token
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct CreationDelegation {
+    pub subject: PersonRef,
+    pub creator: PersonRef,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+impl Storable for CreationDelegation {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode CreationDelegation"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, delegation): (u16, CreationDelegation) =
+            Decode!(bytes.as_ref(), (u16, CreationDelegation))
+                .expect("Failed to decode CreationDelegation");
+        assert_eq!(version, 1, "Unsupported CreationDelegation version");
+        delegation
+    }
+}
+
+fn token_key(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Authorize `creator` to create one capsule about `subject`, from 32 bytes of `entropy`
+///
+/// Returns the token to hand to the creator; it is not stored and can't be
+/// recovered later.
+pub fn delegate(
+    subject: PersonRef,
+    creator: PersonRef,
+    entropy: [u8; 32],
+    now: u64,
+) -> Result<String, Error> {
+    if subject == creator {
+        return Err(Error::InvalidArgument(
+            "a capsule about yourself needs no delegation".to_string(),
+        ));
+    }
+
+    let token = hex::encode(entropy);
+    STABLE_DELEGATIONS.with(|delegations| {
+        delegations.borrow_mut().insert(
+            token_key(&token),
+            CreationDelegation {
+                subject,
+                creator,
+                created_at: now,
+                expires_at: now.saturating_add(CREATION_DELEGATION_TTL_NS),
+            },
+        )
+    });
+    Ok(token)
+}
+
+/// Check that `token` lets `creator` create a capsule about `subject`, without using it up
+///
+/// Unknown tokens, and tokens issued by another subject or to another creator,
+/// are `Unauthorized` and left in place; an expired token is dropped and
+/// reported as `Expired`.
+pub fn check(token: &str, subject: &PersonRef, creator: &PersonRef, now: u64) -> Result<(), Error> {
+    let key = token_key(token);
+    STABLE_DELEGATIONS.with(|delegations| {
+        let mut delegations = delegations.borrow_mut();
+        let delegation = delegations.get(&key).ok_or(Error::Unauthorized)?;
+        if &delegation.subject != subject || &delegation.creator != creator {
+            return Err(Error::Unauthorized);
+        }
+        if delegation.expires_at <= now {
+            delegations.remove(&key);
+            return Err(Error::Expired {
+                expired_at: delegation.expires_at,
+            });
+        }
+        Ok(())
+    })
+}
+
+/// Use up `token` for `creator` creating a capsule about `subject`
+///
+/// Fails like `check`; a valid token is removed so it authorizes a single capsule.
+pub fn consume(
+    token: &str,
+    subject: &PersonRef,
+    creator: &PersonRef,
+    now: u64,
+) -> Result<(), Error> {
+    check(token, subject, creator, now)?;
+    STABLE_DELEGATIONS.with(|delegations| delegations.borrow_mut().remove(&token_key(token)));
+    Ok(())
+}
@@ -0,0 +1,255 @@
+//! Capsule event feed
+//!
+//! Append-only log of capsule-level change events, keyed by a global sequence
+//! number. Clients poll it with the last sequence they saw (the page cursor)
+//! to invalidate cached state without re-reading whole capsules.
+
+use crate::capsule::domain::{PersonRef, ResourceType};
+use crate::capsule_store::types::Page;
+use crate::memory::{MEM_EVENTS, MM};
+use crate::types::{CapsuleId, Error};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableBTreeMap, Storable};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Oldest events are dropped once the log holds this many entries
+const EVENT_LOG_MAX: u64 = 10_000;
+/// Default page size for event listings
+const EVENT_PAGE_DEFAULT: u32 = 50;
+/// Maximum page size for event listings
+const EVENT_PAGE_MAX: u32 = 100;
+
+thread_local! {
+    static STABLE_EVENTS: RefCell<StableBTreeMap<u64, CapsuleEvent, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_EVENTS)))
+    );
+}
+
+// ============================================================================
+// EVENT TYPES
+// ============================================================================
+
+/// Direction of an access change
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum AccessChange {
+    Granted,
+    Revoked,
+}
+
+/// What happened in a capsule
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum CapsuleEventKind {
+    /// An access entry for `affected_principal` on a resource was granted or revoked
+    AccessChanged {
+        resource_type: ResourceType,
+        resource_id: String,
+        affected_principal: PersonRef,
+        change: AccessChange,
+    },
+}
+
+/// Entry in the capsule event feed
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct CapsuleEvent {
+    pub seq: u64,
+    pub capsule_id: CapsuleId,
+    pub at: u64,
+    pub kind: CapsuleEventKind,
+}
+
+impl CapsuleEvent {
+    /// Whether this event changes what `person` can see
+    pub fn affects(&self, person: &PersonRef) -> bool {
+        match &self.kind {
+            CapsuleEventKind::AccessChanged {
+                affected_principal, ..
+            } => affected_principal == person,
+        }
+    }
+}
+
+impl Storable for CapsuleEvent {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode CapsuleEvent"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, event): (u16, CapsuleEvent) =
+            Decode!(bytes.as_ref(), (u16, CapsuleEvent)).expect("Failed to decode CapsuleEvent");
+        assert_eq!(version, 1, "Unsupported CapsuleEvent version");
+        event
+    }
+}
+
+// ============================================================================
+// FEED OPERATIONS
+// ============================================================================
+
+/// Append an event to the feed and return its sequence number
+pub fn emit(capsule_id: &str, at: u64, kind: CapsuleEventKind) -> u64 {
+    STABLE_EVENTS.with(|events| {
+        let mut events = events.borrow_mut();
+        let seq = events.last_key_value().map(|(seq, _)| seq + 1).unwrap_or(0);
+        events.insert(
+            seq,
+            CapsuleEvent {
+                seq,
+                capsule_id: capsule_id.to_string(),
+                at,
+                kind,
+            },
+        );
+
+        // Bounded log: clients that fall further behind re-read full state
+        while events.len() > EVENT_LOG_MAX {
+            events.pop_first();
+        }
+
+        seq
+    })
+}
+
+/// List events matching `filter`, oldest first
+///
+/// Cursor is the sequence number of the last event the client has seen (exclusive).
+pub fn list_events<F>(
+    filter: F,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<CapsuleEvent>, Error>
+where
+    F: Fn(&CapsuleEvent) -> bool,
+{
+    let limit = limit.unwrap_or(EVENT_PAGE_DEFAULT).clamp(1, EVENT_PAGE_MAX) as usize;
+    let start = match cursor {
+        Some(text) => text
+            .parse::<u64>()
+            .map_err(|_| Error::InvalidArgument(format!("invalid cursor: {}", text)))?
+            .saturating_add(1),
+        None => 0,
+    };
+
+    STABLE_EVENTS.with(|events| {
+        let events = events.borrow();
+        let mut matching = events
+            .range(start..)
+            .map(|(_, event)| event)
+            .filter(|event| filter(event));
+
+        let items: Vec<CapsuleEvent> = matching.by_ref().take(limit).collect();
+        let next_cursor = match matching.next() {
+            Some(_) => items.last().map(|event| event.seq.to_string()),
+            None => None,
+        };
+
+        Ok(Page { items, next_cursor })
+    })
+}
+
+/// Access changes affecting `person`, across all capsules
+pub fn list_access_changes_for(
+    person: &PersonRef,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<CapsuleEvent>, Error> {
+    list_events(|event| event.affects(person), cursor, limit)
+}
+
+/// All events of one capsule
+pub fn list_capsule_events(
+    capsule_id: &str,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<CapsuleEvent>, Error> {
+    list_events(|event| event.capsule_id == capsule_id, cursor, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+
+    fn person(byte: u8) -> PersonRef {
+        PersonRef::Principal(Principal::from_slice(&[byte; 29]))
+    }
+
+    fn access_changed(resource_id: &str, who: PersonRef) -> CapsuleEventKind {
+        CapsuleEventKind::AccessChanged {
+            resource_type: ResourceType::Memory,
+            resource_id: resource_id.to_string(),
+            affected_principal: who,
+            change: AccessChange::Granted,
+        }
+    }
+
+    #[test]
+    fn test_capsule_event_storable_roundtrip() {
+        let event = CapsuleEvent {
+            seq: 7,
+            capsule_id: "capsule_1".to_string(),
+            at: 42,
+            kind: access_changed("mem_1", person(1)),
+        };
+        assert_eq!(CapsuleEvent::from_bytes(event.to_bytes()), event);
+    }
+
+    #[test]
+    fn test_emit_assigns_increasing_sequence() {
+        let first = emit("capsule_1", 1, access_changed("mem_1", person(1)));
+        let second = emit("capsule_1", 2, access_changed("mem_2", person(1)));
+        assert_eq!(second, first + 1);
+    }
+
+    #[test]
+    fn test_list_access_changes_filters_by_person() {
+        emit("capsule_1", 1, access_changed("mem_1", person(1)));
+        emit("capsule_1", 2, access_changed("mem_2", person(2)));
+        emit("capsule_2", 3, access_changed("mem_3", person(1)));
+
+        let page = list_access_changes_for(&person(1), None, None).unwrap();
+        let ids: Vec<&str> = page
+            .items
+            .iter()
+            .map(|event| match &event.kind {
+                CapsuleEventKind::AccessChanged { resource_id, .. } => resource_id.as_str(),
+            })
+            .collect();
+        assert_eq!(ids, vec!["mem_1", "mem_3"]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_list_events_cursor_walk() {
+        for i in 0..5 {
+            emit(
+                "capsule_1",
+                i,
+                access_changed(&format!("mem_{i}"), person(1)),
+            );
+        }
+
+        let first = list_capsule_events("capsule_1", None, Some(2)).unwrap();
+        assert_eq!(first.items.len(), 2);
+        let cursor = first.next_cursor.clone().unwrap();
+
+        let second = list_capsule_events("capsule_1", Some(cursor), Some(10)).unwrap();
+        assert_eq!(second.items.len(), 3);
+        assert_eq!(second.items[0].seq, first.items[1].seq + 1);
+        assert_eq!(second.next_cursor, None);
+    }
+
+    #[test]
+    fn test_list_events_rejects_invalid_cursor() {
+        assert!(matches!(
+            list_capsule_events("capsule_1", Some("abc".to_string()), None),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
@@ -1,11 +1,13 @@
 // Folder Commands Module
 // Write operations for folders
 
+use crate::capsule::domain::Capsule;
 use crate::capsule_store::{types::PaginationOrder as Order, CapsuleStore};
 use crate::folder::api_types::{FolderData, FolderUpdateData};
 use crate::folder::domain::Folder;
+use crate::memories::core::update::check_unlocked;
 use crate::memory::{with_capsule_store, with_capsule_store_mut};
-use crate::types::{Error, PersonRef};
+use crate::types::{BulkFailure, BulkResult, Error, PersonRef};
 
 /// Create a folder in the caller's capsule
 pub fn folders_create(folder_data: FolderData) -> std::result::Result<Folder, Error> {
@@ -25,7 +27,7 @@ pub fn folders_create(folder_data: FolderData) -> std::result::Result<Folder, Er
         Some(capsule) => capsule,
         None => {
             // Create a new capsule for the caller
-            crate::capsule::commands::capsules_create(Some(caller.clone()))?;
+            crate::capsule::commands::capsules_create(Some(caller.clone()), None)?;
             with_capsule_store(|store| {
                 let all_capsules = store.paginate(None, u32::MAX, Order::Asc);
                 all_capsules
@@ -44,22 +46,29 @@ pub fn folders_create(folder_data: FolderData) -> std::result::Result<Folder, Er
         return Ok(existing_folder.clone());
     }
 
-    // Create folder from data
-    let mut folder = folder_data.folder;
-    folder.created_at = ic_cdk::api::time();
-    folder.updated_at = ic_cdk::api::time();
-
-    // Store folder in capsule
-    let folder_clone = folder.clone();
+    // Create folder from data and store it in the capsule
+    let folder = folder_data.folder;
     with_capsule_store_mut(|store| {
         let mut capsule = store.get(&capsule.id).ok_or(Error::NotFound)?;
-        capsule.folders.insert(folder_id.clone(), folder);
-        capsule.updated_at = ic_cdk::api::time();
+        let folder = insert_folder(&mut capsule, folder, ic_cdk::api::time());
         store.upsert(capsule.id.clone(), capsule);
-        Ok(())
-    })?;
+        Ok(folder)
+    })
+}
 
-    Ok(folder_clone)
+/// Insert a new folder into `capsule`, owned by that capsule
+///
+/// `capsule_id` always comes from the containing capsule; any client-supplied
+/// value is ignored.
+pub fn insert_folder(capsule: &mut Capsule, mut folder: Folder, now: u64) -> Folder {
+    folder.capsule_id = capsule.id.clone();
+    folder.created_at = now;
+    folder.updated_at = now;
+    folder.deleted_at = None;
+
+    capsule.folders.insert(folder.id.clone(), folder.clone());
+    capsule.updated_at = now;
+    folder
 }
 
 /// Update a folder in the caller's capsule
@@ -105,7 +114,10 @@ pub fn folders_update(
     })
 }
 
-/// Delete a folder from the caller's capsule
+/// Move a folder of the caller's capsule to the trash
+///
+/// It stays restorable with `folders_restore` until the maintenance sweep purges it
+/// (`TRASH_RETENTION_NS` after deletion).
 pub fn folders_delete(folder_id: String) -> std::result::Result<(), Error> {
     let caller = PersonRef::from_caller();
 
@@ -119,18 +131,284 @@ pub fn folders_delete(folder_id: String) -> std::result::Result<(), Error> {
 
         match self_capsule {
             Some(mut capsule) => {
-                if capsule.folders.remove(&folder_id).is_some() {
-                    capsule.updated_at = ic_cdk::api::time();
+                trash_folder(&mut capsule, &folder_id, ic_cdk::api::time())?;
 
-                    // Save updated capsule
-                    let capsule_id = capsule.id.clone();
-                    store.upsert(capsule_id, capsule);
-                    Ok(())
-                } else {
-                    Err(Error::NotFound)
-                }
+                // Save updated capsule
+                let capsule_id = capsule.id.clone();
+                store.upsert(capsule_id, capsule);
+                Ok(())
+            }
+            None => Err(Error::NotFound),
+        }
+    })
+}
+
+/// Restore a trashed folder of the caller's capsule
+pub fn folders_restore(folder_id: String) -> std::result::Result<Folder, Error> {
+    let caller = PersonRef::from_caller();
+
+    with_capsule_store_mut(|store| {
+        let all_capsules = store.paginate(None, u32::MAX, Order::Asc);
+        let self_capsule = all_capsules
+            .items
+            .into_iter()
+            .find(|capsule| capsule.subject == caller && capsule.owners.contains_key(&caller));
+
+        match self_capsule {
+            Some(mut capsule) => {
+                let folder = restore_folder(&mut capsule, &folder_id, ic_cdk::api::time())?;
+
+                let capsule_id = capsule.id.clone();
+                store.upsert(capsule_id, capsule);
+                Ok(folder)
             }
             None => Err(Error::NotFound),
         }
     })
 }
+
+/// Mark a folder of `capsule` as trashed at `now`
+///
+/// An already-trashed folder is reported as `NotFound`, like one that never existed.
+pub fn trash_folder(
+    capsule: &mut Capsule,
+    folder_id: &str,
+    now: u64,
+) -> std::result::Result<(), Error> {
+    match capsule.folders.get_mut(folder_id) {
+        Some(folder) if folder.deleted_at.is_none() => {
+            folder.deleted_at = Some(now);
+            capsule.updated_at = now;
+            Ok(())
+        }
+        _ => Err(Error::NotFound),
+    }
+}
+
+/// Take a folder of `capsule` back out of the trash
+pub fn restore_folder(
+    capsule: &mut Capsule,
+    folder_id: &str,
+    now: u64,
+) -> std::result::Result<Folder, Error> {
+    let folder = capsule.folders.get_mut(folder_id).ok_or(Error::NotFound)?;
+    if folder.deleted_at.is_none() {
+        return Err(Error::InvalidArgument(format!(
+            "folder {} is not in the trash",
+            folder_id
+        )));
+    }
+    folder.deleted_at = None;
+    folder.updated_at = now;
+    capsule.updated_at = now;
+    Ok(folder.clone())
+}
+
+/// Move memories from one folder to another in the capsule holding the source folder
+pub fn folders_move_memories(
+    source_folder_id: String,
+    target_folder_id: String,
+    memory_ids: Vec<String>,
+) -> std::result::Result<BulkResult<String>, Error> {
+    let caller = PersonRef::from_caller();
+
+    with_capsule_store_mut(|store| {
+        let mut capsule = store
+            .paginate(None, u32::MAX, Order::Asc)
+            .items
+            .into_iter()
+            .find(|capsule| {
+                capsule.folders.contains_key(&source_folder_id) && capsule.has_read_access(&caller)
+            })
+            .ok_or(Error::NotFound)?;
+
+        let result = move_folder_memories(
+            &mut capsule,
+            &caller,
+            &source_folder_id,
+            &target_folder_id,
+            memory_ids,
+            ic_cdk::api::time(),
+        )?;
+
+        if !result.ok.is_empty() {
+            store.upsert(capsule.id.clone(), capsule);
+        }
+        Ok(result)
+    })
+}
+
+/// Move `memory_ids` of `capsule` from `source_folder_id` into `target_folder_id`
+///
+/// Needs write access, and both folders must be live folders of the capsule. Each
+/// memory is moved on its own: one that isn't in the source folder (or is
+/// locked) is reported as failed without affecting the others. Both folders'
+/// `total_memories` follow every move.
+pub fn move_folder_memories(
+    capsule: &mut Capsule,
+    caller: &PersonRef,
+    source_folder_id: &str,
+    target_folder_id: &str,
+    memory_ids: Vec<String>,
+    now: u64,
+) -> std::result::Result<BulkResult<String>, Error> {
+    if !capsule.has_write_access(caller) {
+        return Err(Error::Unauthorized);
+    }
+    if source_folder_id == target_folder_id {
+        return Err(Error::InvalidArgument(
+            "source and target folder are the same".to_string(),
+        ));
+    }
+    for folder_id in [source_folder_id, target_folder_id] {
+        match capsule.folders.get(folder_id) {
+            Some(folder) if folder.deleted_at.is_none() => {}
+            _ => return Err(Error::NotFound),
+        }
+    }
+
+    let mut result = BulkResult {
+        ok: Vec::new(),
+        failed: Vec::new(),
+    };
+    for memory_id in memory_ids {
+        let moved = match capsule.memories.get_mut(&memory_id) {
+            Some(memory)
+                if memory.metadata.parent_folder_id.as_deref() == Some(source_folder_id) =>
+            {
+                check_unlocked(memory).map(|()| {
+                    memory.metadata.parent_folder_id = Some(target_folder_id.to_string());
+                    memory.metadata.updated_at = now;
+                })
+            }
+            Some(_) => Err(Error::InvalidArgument(format!(
+                "memory {} is not in folder {}",
+                memory_id, source_folder_id
+            ))),
+            None => Err(Error::NotFound),
+        };
+        match moved {
+            Ok(()) => result.ok.push(memory_id),
+            Err(err) => result.failed.push(BulkFailure { id: memory_id, err }),
+        }
+    }
+
+    if !result.ok.is_empty() {
+        let count = result.ok.len() as u32;
+        for (folder_id, moved_in) in [(source_folder_id, false), (target_folder_id, true)] {
+            if let Some(folder) = capsule.folders.get_mut(folder_id) {
+                let total = &mut folder.metadata.total_memories;
+                *total = if moved_in {
+                    total.saturating_add(count)
+                } else {
+                    total.saturating_sub(count)
+                };
+                folder.updated_at = now;
+            }
+        }
+        capsule.updated_at = now;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_test_capsule_at, create_test_folder};
+    use candid::Principal;
+
+    #[test]
+    fn test_insert_folder_uses_containing_capsule_id() {
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_real", owner, 1);
+
+        let stored = insert_folder(
+            &mut capsule,
+            create_test_folder("f1", "capsule_spoofed"),
+            70,
+        );
+        assert_eq!(stored.capsule_id, "capsule_real");
+        assert_eq!(capsule.folders["f1"].capsule_id, capsule.id);
+        assert_eq!(capsule.updated_at, 70);
+    }
+
+    #[test]
+    fn test_move_memories_between_folders_updates_both() {
+        use crate::test_utils::create_test_memory_at;
+
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_real", owner.clone(), 1);
+        insert_folder(
+            &mut capsule,
+            create_test_folder("f_src", "capsule_spoofed"),
+            10,
+        );
+        insert_folder(
+            &mut capsule,
+            create_test_folder("f_dst", "capsule_spoofed"),
+            10,
+        );
+        for (memory_id, folder_id) in [
+            ("m1", "f_src"),
+            ("m2", "f_src"),
+            ("m3", "f_src"),
+            ("m4", "f_dst"),
+        ] {
+            let mut memory = create_test_memory_at(memory_id, "capsule_real", 1);
+            memory.metadata.parent_folder_id = Some(folder_id.to_string());
+            capsule.memories.insert(memory_id.to_string(), memory);
+        }
+        for (folder_id, total) in [("f_src", 3), ("f_dst", 1)] {
+            capsule
+                .folders
+                .get_mut(folder_id)
+                .unwrap()
+                .metadata
+                .total_memories = total;
+        }
+
+        let ids = ["m1", "m3", "m4", "missing"].map(String::from).to_vec();
+        let result = move_folder_memories(&mut capsule, &owner, "f_src", "f_dst", ids, 20).unwrap();
+
+        assert_eq!(result.ok, vec!["m1", "m3"]);
+        let failed: Vec<_> = result.failed.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(failed, vec!["m4", "missing"]);
+        assert!(matches!(result.failed[0].err, Error::InvalidArgument(_)));
+        assert_eq!(result.failed[1].err, Error::NotFound);
+
+        assert_eq!(capsule.folders["f_src"].metadata.total_memories, 1);
+        assert_eq!(capsule.folders["f_dst"].metadata.total_memories, 3);
+        let folder_of = |id: &str| capsule.memories[id].metadata.parent_folder_id.clone();
+        assert_eq!(folder_of("m1").as_deref(), Some("f_dst"));
+        assert_eq!(folder_of("m2").as_deref(), Some("f_src"));
+        assert_eq!(folder_of("m3").as_deref(), Some("f_dst"));
+
+        let stranger = PersonRef::Principal(Principal::from_slice(&[7; 29]));
+        assert_eq!(
+            move_folder_memories(&mut capsule, &stranger, "f_src", "f_dst", vec![], 30),
+            Err(Error::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn test_trashed_folder_restores_until_purged() {
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_real", owner, 1);
+        insert_folder(
+            &mut capsule,
+            create_test_folder("f1", "capsule_spoofed"),
+            10,
+        );
+
+        trash_folder(&mut capsule, "f1", 20).unwrap();
+        assert_eq!(capsule.folders["f1"].deleted_at, Some(20));
+        assert_eq!(
+            restore_folder(&mut capsule, "f1", 30).unwrap().deleted_at,
+            None
+        );
+
+        trash_folder(&mut capsule, "f1", 40).unwrap();
+        assert_eq!(capsule.purge_trashed_containers(40), 1);
+        assert_eq!(restore_folder(&mut capsule, "f1", 50), Err(Error::NotFound));
+    }
+}
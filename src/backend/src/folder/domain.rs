@@ -29,6 +29,8 @@ pub struct Folder {
     pub access_entries: Vec<AccessEntry>,
     pub created_at: u64,
     pub updated_at: u64,
+    /// Set while the folder sits in the capsule trash (purged after `TRASH_RETENTION_NS`)
+    pub deleted_at: Option<u64>,
 }
 
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
@@ -43,6 +45,7 @@ pub struct FolderHeader {
     pub sharing_status: SharingStatus,
     pub total_memories: u32,
     pub storage_location: Vec<BlobHosting>,
+    pub deleted_at: Option<u64>,
 }
 
 // Folder implementations
@@ -72,6 +75,7 @@ impl Folder {
             sharing_status: self.metadata.sharing_status.clone(),
             total_memories: self.metadata.total_memories,
             storage_location: self.metadata.storage_location.clone(),
+            deleted_at: self.deleted_at,
         }
     }
 
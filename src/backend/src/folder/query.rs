@@ -7,7 +7,10 @@ use crate::memory::with_capsule_store;
 use crate::types::PersonRef;
 
 /// Get all folders for the caller
-pub fn folders_list() -> Vec<FolderHeader> {
+///
+/// Trashed folders are left out unless `include_deleted` is set.
+pub fn folders_list(include_deleted: Option<bool>) -> Vec<FolderHeader> {
+    let include_deleted = include_deleted.unwrap_or(false);
     let caller = PersonRef::from_caller();
 
     // List all folders from caller's self-capsule
@@ -23,6 +26,7 @@ pub fn folders_list() -> Vec<FolderHeader> {
                 let mut folder_headers: Vec<FolderHeader> = capsule
                     .folders
                     .values()
+                    .filter(|folder| include_deleted || folder.deleted_at.is_none())
                     .map(|folder| folder.to_header())
                     .collect();
 
@@ -5,8 +5,8 @@ use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
 
 // Re-export domain types
-use crate::gallery::domain::Gallery;
-use crate::memories::types::GalleryMemoryEntry;
+use crate::gallery::domain::{Gallery, GalleryMetadata};
+use crate::memories::types::{AssetStorageKind, GalleryMemoryEntry, MemoryType};
 
 // ============================================================================
 // GALLERY API REQUEST/RESPONSE TYPES
@@ -35,3 +35,63 @@ pub struct GalleryUpdateData {
     pub is_public: Option<bool>,
     pub memory_entries: Option<Vec<GalleryMemoryEntry>>,
 }
+
+/// Gallery metadata without its items - API response DTO
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct GalleryMeta {
+    pub id: String,
+    pub capsule_id: String,
+    pub metadata: GalleryMetadata,
+    pub cover_memory_id: Option<String>,
+    pub item_count: u64, // page through items with galleries_read_items
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Outcome of merging one gallery into another - API response DTO
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct GalleryMergeResult {
+    pub items_merged: u32,
+    pub duplicates_skipped: u32, // source items whose memory was already in the target
+}
+
+/// One asset of an exported gallery item, with what it takes to fetch it
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ManifestAsset {
+    pub asset_id: String,
+    pub kind: AssetStorageKind,
+    pub locator: Option<String>, // blob locator or external URL; None for inline bytes
+    pub size: u64,
+    pub sha256: Option<[u8; 32]>,
+    pub content_type: String,
+}
+
+/// One gallery item the caller can view, with its memory's assets
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ManifestItem {
+    pub memory_id: String,
+    pub memory_type: MemoryType,
+    pub position: u32,
+    pub caption: Option<String>,
+    pub title: Option<String>,
+    pub assets: Vec<ManifestAsset>, // display order
+}
+
+/// A gallery with the fetch details of its members - API response DTO
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct GalleryManifest {
+    pub id: String,
+    pub capsule_id: String,
+    pub metadata: GalleryMetadata,
+    pub cover_memory_id: Option<String>,
+    pub items: Vec<ManifestItem>, // ordered by position then memory_id
+    pub omitted_items: u32,       // members the caller can't view (or that no longer exist)
+    pub exported_at: u64,
+}
+
+/// Gallery a finished upload should land in - API request DTO
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum GalleryTarget {
+    Existing { gallery_id: String },
+    New { title: String }, // created on first use; later uploads pass its id
+}
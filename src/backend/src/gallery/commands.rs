@@ -1,9 +1,67 @@
 use crate::capsule::commands::capsules_create;
+use crate::capsule::domain::{Capsule, SharingStatus};
+use crate::capsule::util::internal_blob_id;
 use crate::capsule_store::{types::PaginationOrder as Order, CapsuleStore};
-use crate::gallery::api_types::{GalleryData, GalleryUpdateData};
-use crate::gallery::domain::Gallery;
-use crate::memory::{with_capsule_store, with_capsule_store_mut};
-use crate::types::{Error, PersonRef};
+use crate::gallery::api_types::{
+    GalleryData, GalleryMergeResult, GalleryTarget, GalleryUpdateData,
+};
+use crate::gallery::domain::{Gallery, GalleryMetadata};
+use crate::memories::core::model_helpers::generate_deterministic_uuid_from_idem;
+use crate::memories::types::MemoryType;
+use crate::memory::{with_capsule_store, with_capsule_store_mut, MEM_MAX_GALLERIES, MM};
+use crate::types::{BlobHosting, Error, PersonRef};
+use crate::upload::blob_store::BlobStore;
+use crate::upload::types::BlobMeta;
+use crate::utils::{title_to_name, unique_name};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{DefaultMemoryImpl, StableCell};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Galleries a capsule may hold unless an admin changes the cap
+pub const MAX_GALLERIES_PER_CAPSULE_DEFAULT: u32 = 500;
+
+thread_local! {
+    // Admin-set cap, kept across upgrades
+    static MAX_GALLERIES_PER_CAPSULE: RefCell<StableCell<u32, Memory>> = RefCell::new(
+        StableCell::init(
+            MM.with(|m| m.borrow().get(MEM_MAX_GALLERIES)),
+            MAX_GALLERIES_PER_CAPSULE_DEFAULT,
+        )
+        .expect("Failed to init gallery cap")
+    );
+}
+
+/// Current cap on galleries per capsule
+pub fn max_galleries_per_capsule() -> u32 {
+    MAX_GALLERIES_PER_CAPSULE.with(|max| *max.borrow().get())
+}
+
+/// Change the gallery cap (admin); capsules already above it keep their galleries
+pub fn set_max_galleries_per_capsule(max: u32) {
+    MAX_GALLERIES_PER_CAPSULE.with(|cell| {
+        cell.borrow_mut()
+            .set(max)
+            .expect("Failed to store gallery cap");
+    });
+}
+
+/// Reject creating another gallery in a capsule already at `max`
+///
+/// Trashed galleries don't count, so deleting one frees a slot.
+pub fn check_gallery_limit(capsule: &Capsule, max: u32) -> std::result::Result<(), Error> {
+    let current = capsule
+        .galleries
+        .values()
+        .filter(|gallery| gallery.deleted_at.is_none())
+        .count() as u32;
+    if current >= max {
+        return Err(Error::LimitReached { current, max });
+    }
+    Ok(())
+}
 
 /// Create a gallery in the caller's capsule (replaces store_gallery_forever)
 pub fn galleries_create(gallery_data: GalleryData) -> std::result::Result<Gallery, Error> {
@@ -23,7 +81,7 @@ pub fn galleries_create(gallery_data: GalleryData) -> std::result::Result<Galler
         Some(capsule) => Some(capsule),
         None => {
             // No capsule found - create one automatically for first-time users
-            match capsules_create(None) {
+            match capsules_create(None, None) {
                 Ok(capsule) => Some(capsule),
                 Err(e) => {
                     return Err(Error::Internal(format!("Failed to create capsule: {e}")));
@@ -38,17 +96,12 @@ pub fn galleries_create(gallery_data: GalleryData) -> std::result::Result<Galler
             if let Some(existing_gallery) = capsule.galleries.get(&gallery_id) {
                 return Ok(existing_gallery.clone());
             }
+            check_gallery_limit(&capsule, max_galleries_per_capsule())?;
 
             // Create gallery from data (don't overwrite gallery.id - it's already set by Web2)
-            let mut gallery = gallery_data.gallery;
-            gallery.created_at = ic_cdk::api::time();
-            gallery.updated_at = ic_cdk::api::time();
             // Note: owner_principal and storage_location are now handled through access_entries and metadata
-
-            // Store gallery in capsule
-            let gallery_clone = gallery.clone();
-            capsule.galleries.insert(gallery_id.clone(), gallery);
-            capsule.updated_at = ic_cdk::api::time(); // Update capsule timestamp
+            let gallery_clone =
+                insert_gallery(&mut capsule, gallery_data.gallery, ic_cdk::api::time());
 
             // MIGRATED: Save updated capsule
             let capsule_id = capsule.id.clone();
@@ -82,7 +135,7 @@ pub fn galleries_create_with_memories(
         Some(capsule) => Some(capsule),
         None => {
             // No capsule found - create one automatically for first-time users
-            match capsules_create(None) {
+            match capsules_create(None, None) {
                 Ok(capsule) => Some(capsule),
                 Err(e) => {
                     return Err(Error::Internal(format!("Failed to create capsule: {e}")));
@@ -97,17 +150,12 @@ pub fn galleries_create_with_memories(
             if let Some(existing_gallery) = capsule.galleries.get(&gallery_id) {
                 return Ok(existing_gallery.clone());
             }
+            check_gallery_limit(&capsule, max_galleries_per_capsule())?;
 
             // Create gallery from data (don't overwrite gallery.id - it's already set by Web2)
-            let mut gallery = gallery_data.gallery;
-            gallery.created_at = ic_cdk::api::time();
-            gallery.updated_at = ic_cdk::api::time();
             // Note: owner_principal and storage_location are now handled through access_entries and metadata
-
-            // Store gallery in capsule
-            let gallery_clone = gallery.clone();
-            capsule.galleries.insert(gallery_id.clone(), gallery);
-            capsule.updated_at = ic_cdk::api::time(); // Update capsule timestamp
+            let gallery_clone =
+                insert_gallery(&mut capsule, gallery_data.gallery, ic_cdk::api::time());
 
             // MIGRATED: Save updated capsule
             with_capsule_store_mut(|store| {
@@ -120,6 +168,37 @@ pub fn galleries_create_with_memories(
     }
 }
 
+/// Slug for a gallery titled `title`, unique among the other galleries of `capsule`
+pub fn unique_gallery_name(capsule: &Capsule, gallery_id: &str, title: Option<&str>) -> String {
+    let base = title_to_name(title.unwrap_or(""));
+    unique_name(&base, |candidate| {
+        capsule
+            .galleries
+            .values()
+            .any(|other| other.id != gallery_id && other.metadata.name == candidate)
+    })
+}
+
+/// Insert a new gallery into `capsule`, owned by that capsule
+///
+/// `capsule_id` always comes from the containing capsule; any client-supplied
+/// value is ignored so a gallery can't list under one capsule but claim another.
+/// Likewise `metadata.name` is derived from the title and made unique in the capsule.
+pub fn insert_gallery(capsule: &mut Capsule, mut gallery: Gallery, now: u64) -> Gallery {
+    gallery.capsule_id = capsule.id.clone();
+    gallery.metadata.name =
+        unique_gallery_name(capsule, &gallery.id, gallery.metadata.title.as_deref());
+    gallery.created_at = now;
+    gallery.updated_at = now;
+    gallery.deleted_at = None;
+
+    capsule
+        .galleries
+        .insert(gallery.id.clone(), gallery.clone());
+    capsule.updated_at = now; // Update capsule timestamp
+    gallery
+}
+
 /// Update gallery storage location (replaces update_gallery_storage_location)
 pub fn update_gallery_storage_location(
     gallery_id: String,
@@ -172,11 +251,18 @@ pub fn galleries_update(
 
         match self_capsule {
             Some(mut capsule) => {
+                let renamed = update_data
+                    .title
+                    .as_deref()
+                    .map(|title| unique_gallery_name(&capsule, &gallery_id, Some(title)));
                 if let Some(gallery) = capsule.galleries.get_mut(&gallery_id) {
                     // Update gallery fields
                     if let Some(title) = update_data.title {
                         gallery.metadata.title = Some(title);
                     }
+                    if let Some(name) = renamed {
+                        gallery.metadata.name = name;
+                    }
                     if let Some(description) = update_data.description {
                         gallery.metadata.description = Some(description);
                     }
@@ -202,7 +288,10 @@ pub fn galleries_update(
     })
 }
 
-/// Delete a gallery from the caller's capsule (replaces delete_gallery_forever)
+/// Move a gallery of the caller's capsule to the trash (replaces delete_gallery_forever)
+///
+/// It stays restorable with `galleries_restore` until the maintenance sweep purges it
+/// (`TRASH_RETENTION_NS` after deletion).
 pub fn galleries_delete(gallery_id: String) -> std::result::Result<(), Error> {
     let caller = PersonRef::from_caller();
 
@@ -216,18 +305,1056 @@ pub fn galleries_delete(gallery_id: String) -> std::result::Result<(), Error> {
 
         match self_capsule {
             Some(mut capsule) => {
-                if capsule.galleries.remove(&gallery_id).is_some() {
-                    capsule.updated_at = ic_cdk::api::time();
+                trash_gallery(&mut capsule, &gallery_id, ic_cdk::api::time())?;
 
-                    // Save updated capsule
-                    let capsule_id = capsule.id.clone();
-                    store.upsert(capsule_id, capsule);
-                    Ok(())
-                } else {
-                    Err(Error::NotFound)
-                }
+                // Save updated capsule
+                let capsule_id = capsule.id.clone();
+                store.upsert(capsule_id, capsule);
+                Ok(())
             }
             None => Err(Error::NotFound),
         }
     })
 }
+
+/// Restore a trashed gallery of the caller's capsule
+pub fn galleries_restore(gallery_id: String) -> std::result::Result<Gallery, Error> {
+    let caller = PersonRef::from_caller();
+
+    with_capsule_store_mut(|store| {
+        let all_capsules = store.paginate(None, u32::MAX, Order::Asc);
+        let self_capsule = all_capsules
+            .items
+            .into_iter()
+            .find(|capsule| capsule.subject == caller && capsule.owners.contains_key(&caller));
+
+        match self_capsule {
+            Some(mut capsule) => {
+                let gallery = restore_gallery(&mut capsule, &gallery_id, ic_cdk::api::time())?;
+
+                let capsule_id = capsule.id.clone();
+                store.upsert(capsule_id, capsule);
+                Ok(gallery)
+            }
+            None => Err(Error::NotFound),
+        }
+    })
+}
+
+/// Mark a gallery of `capsule` as trashed at `now`
+///
+/// An already-trashed gallery is reported as `NotFound`, like one that never existed.
+pub fn trash_gallery(
+    capsule: &mut Capsule,
+    gallery_id: &str,
+    now: u64,
+) -> std::result::Result<(), Error> {
+    match capsule.galleries.get_mut(gallery_id) {
+        Some(gallery) if gallery.deleted_at.is_none() => {
+            gallery.deleted_at = Some(now);
+            capsule.updated_at = now;
+            Ok(())
+        }
+        _ => Err(Error::NotFound),
+    }
+}
+
+/// Take a gallery of `capsule` back out of the trash
+pub fn restore_gallery(
+    capsule: &mut Capsule,
+    gallery_id: &str,
+    now: u64,
+) -> std::result::Result<Gallery, Error> {
+    let gallery = capsule
+        .galleries
+        .get_mut(gallery_id)
+        .ok_or(Error::NotFound)?;
+    if gallery.deleted_at.is_none() {
+        return Err(Error::InvalidArgument(format!(
+            "gallery {} is not in the trash",
+            gallery_id
+        )));
+    }
+    gallery.deleted_at = None;
+    gallery.updated_at = now;
+    capsule.updated_at = now;
+    Ok(gallery.clone())
+}
+
+/// Move a gallery from the capsule that holds it to `target_capsule_id`
+///
+/// The caller needs write access to both capsules. Member memories that don't
+/// already exist in the target are moved along when `move_memories` is set;
+/// otherwise the call fails with the list of missing memory ids.
+pub fn galleries_move(
+    gallery_id: String,
+    target_capsule_id: String,
+    move_memories: bool,
+) -> std::result::Result<(), Error> {
+    let caller = PersonRef::from_caller();
+
+    with_capsule_store_mut(|store| {
+        let mut source = store
+            .paginate(None, u32::MAX, Order::Asc)
+            .items
+            .into_iter()
+            .find(|capsule| {
+                capsule.galleries.contains_key(&gallery_id) && capsule.has_read_access(&caller)
+            })
+            .ok_or(Error::NotFound)?;
+
+        if source.id == target_capsule_id {
+            // Already there - nothing to move
+            return Ok(());
+        }
+
+        let mut target = store.get(&target_capsule_id).ok_or(Error::NotFound)?;
+
+        let moved = move_gallery(
+            &crate::upload::blob_store::StableBlobStore,
+            &mut source,
+            &mut target,
+            &caller,
+            &gallery_id,
+            move_memories,
+            ic_cdk::api::time(),
+        )?;
+
+        // Share links of moved memories keep working from their new capsule
+        for memory_id in &moved {
+            crate::share_links::rescope(memory_id, &source.id, &target.id);
+        }
+        store.upsert(source.id.clone(), source);
+        store.upsert(target.id.clone(), target);
+        Ok(())
+    })
+}
+
+/// Move `gallery_id` (and optionally its missing member memories) from `source` to `target`
+///
+/// Capsule-to-capsule transfer; both capsules (and the blob store) are left
+/// untouched on error. A moved memory is detached from the source galleries
+/// that still used it and leaves its source folder, so nothing left behind
+/// points at a missing memory. Internal blobs charged to the source move their
+/// charge (and checksum index entry) to the target, whose quota must fit them;
+/// a blob also used by a memory staying behind can't move. Returns the ids of
+/// the memories moved along.
+pub fn move_gallery<B: BlobStore>(
+    blob_store: &B,
+    source: &mut Capsule,
+    target: &mut Capsule,
+    caller: &PersonRef,
+    gallery_id: &str,
+    move_memories: bool,
+    now: u64,
+) -> std::result::Result<Vec<String>, Error> {
+    if !source.has_write_access(caller) || !target.has_write_access(caller) {
+        return Err(Error::Unauthorized);
+    }
+    if !source.galleries.contains_key(gallery_id) {
+        return Err(Error::NotFound);
+    }
+    if target.galleries.contains_key(gallery_id) {
+        return Err(Error::Conflict(format!(
+            "gallery {gallery_id} already exists in capsule {}",
+            target.id
+        )));
+    }
+
+    // Collect member memories (items + cover) the target doesn't have yet
+    let gallery = &source.galleries[gallery_id];
+    let mut missing: Vec<String> = Vec::new();
+    for memory_id in gallery
+        .items
+        .iter()
+        .map(|item| &item.memory_id)
+        .chain(gallery.cover_memory_id.iter())
+    {
+        if !target.memories.contains_key(memory_id) && !missing.contains(memory_id) {
+            missing.push(memory_id.clone());
+        }
+    }
+
+    if !missing.is_empty() {
+        if !move_memories {
+            return Err(Error::InvalidArgument(format!(
+                "missing_memories: {}",
+                missing.join(", ")
+            )));
+        }
+        if let Some(absent) = missing
+            .iter()
+            .find(|memory_id| !source.memories.contains_key(*memory_id))
+        {
+            return Err(Error::InvalidArgument(format!(
+                "memory {absent} not found in source capsule"
+            )));
+        }
+    }
+
+    let blob_charges = moved_blob_charges(blob_store, source, &missing)?;
+    let charged_bytes: u64 = blob_charges.iter().map(|(_, meta)| meta.size).sum();
+    target.check_blob_quota(charged_bytes)?;
+
+    if let Some(mut gallery) = source.galleries.remove(gallery_id) {
+        gallery.capsule_id = target.id.clone();
+        gallery.metadata.name =
+            unique_gallery_name(target, gallery_id, gallery.metadata.title.as_deref());
+        gallery.updated_at = now;
+        target.galleries.insert(gallery_id.to_string(), gallery);
+    }
+
+    for memory_id in &missing {
+        if let Some(mut memory) = source.memories.remove(memory_id) {
+            memory.capsule_id = target.id.clone();
+            if let Some(folder_id) = &memory.metadata.parent_folder_id {
+                if let Some(folder) = source.folders.get_mut(folder_id) {
+                    folder.metadata.total_memories =
+                        folder.metadata.total_memories.saturating_sub(1);
+                    folder.updated_at = now;
+                }
+                match target.folders.get_mut(folder_id) {
+                    Some(folder) => {
+                        folder.metadata.total_memories =
+                            folder.metadata.total_memories.saturating_add(1);
+                        folder.updated_at = now;
+                    }
+                    None => memory.metadata.parent_folder_id = None,
+                }
+            }
+            target.memories.insert(memory_id.clone(), memory);
+            detach_memory_from_galleries(source, memory_id, now);
+        }
+    }
+
+    for (blob_id, mut meta) in blob_charges {
+        source.release_blob_bytes(meta.size);
+        target.charge_blob_bytes(meta.size);
+        // Re-key the checksum index: dedup looks blobs up per capsule
+        blob_store.remove_meta(blob_id);
+        meta.capsule_id = Some(target.id.clone());
+        blob_store.put_meta(blob_id, meta);
+    }
+
+    source.updated_at = now;
+    target.updated_at = now;
+    Ok(missing)
+}
+
+/// Internal blobs of the `moving` memories of `source` that are charged to `source`
+///
+/// Fails if a memory staying in `source` references one of them, since a blob is
+/// charged to a single capsule.
+fn moved_blob_charges<B: BlobStore>(
+    blob_store: &B,
+    source: &Capsule,
+    moving: &[String],
+) -> std::result::Result<Vec<(u64, BlobMeta)>, Error> {
+    let blob_ids = |memory: &crate::types::Memory| -> Vec<u64> {
+        memory
+            .blob_internal_assets
+            .iter()
+            .filter_map(internal_blob_id)
+            .collect()
+    };
+    let moving_blobs: BTreeSet<u64> = moving
+        .iter()
+        .filter_map(|memory_id| source.memories.get(memory_id))
+        .flat_map(blob_ids)
+        .collect();
+    if let Some(shared) = source
+        .memories
+        .values()
+        .filter(|memory| !moving.contains(&memory.id))
+        .flat_map(blob_ids)
+        .find(|blob_id| moving_blobs.contains(blob_id))
+    {
+        return Err(Error::InvalidArgument(format!(
+            "blob_{shared} is shared with memories staying in capsule {}",
+            source.id
+        )));
+    }
+
+    Ok(moving_blobs
+        .into_iter()
+        .filter_map(|blob_id| blob_store.get_meta(blob_id).map(|meta| (blob_id, meta)))
+        .filter(|(_, meta)| meta.capsule_id.as_deref() == Some(source.id.as_str()))
+        .collect())
+}
+
+/// Merge `source_gallery_id` into `target_gallery_id`, then delete the source
+///
+/// Both galleries must be in capsules the caller can write; they may live in
+/// different capsules.
+pub fn galleries_merge(
+    source_gallery_id: String,
+    target_gallery_id: String,
+) -> std::result::Result<GalleryMergeResult, Error> {
+    let caller = PersonRef::from_caller();
+
+    with_capsule_store_mut(|store| {
+        let capsules = store.paginate(None, u32::MAX, Order::Asc).items;
+        let holder = |gallery_id: &str| {
+            capsules
+                .iter()
+                .find(|capsule| {
+                    capsule.galleries.contains_key(gallery_id) && capsule.has_read_access(&caller)
+                })
+                .cloned()
+                .ok_or(Error::NotFound)
+        };
+        let mut source = holder(&source_gallery_id)?;
+        let mut target = holder(&target_gallery_id)?;
+        let now = ic_cdk::api::time();
+
+        if source.id == target.id {
+            let result = merge_galleries(
+                &mut source,
+                None,
+                &caller,
+                &source_gallery_id,
+                &target_gallery_id,
+                now,
+            )?;
+            store.upsert(source.id.clone(), source);
+            return Ok(result);
+        }
+
+        let result = merge_galleries(
+            &mut source,
+            Some(&mut target),
+            &caller,
+            &source_gallery_id,
+            &target_gallery_id,
+            now,
+        )?;
+        store.upsert(source.id.clone(), source);
+        store.upsert(target.id.clone(), target);
+        Ok(result)
+    })
+}
+
+/// Append the source gallery's items to the target gallery and remove the source
+///
+/// `target` is `None` when both galleries live in `source`. Items whose memory is
+/// already in the target are skipped; positions are renumbered in order (target
+/// items first) and the source cover is kept if the target has none. Across
+/// capsules every merged memory must already exist in the target capsule.
+/// Both capsules are left untouched on error.
+pub fn merge_galleries(
+    source: &mut Capsule,
+    target: Option<&mut Capsule>,
+    caller: &PersonRef,
+    source_gallery_id: &str,
+    target_gallery_id: &str,
+    now: u64,
+) -> std::result::Result<GalleryMergeResult, Error> {
+    if source_gallery_id == target_gallery_id {
+        return Err(Error::InvalidArgument(
+            "cannot merge a gallery into itself".to_string(),
+        ));
+    }
+    let target_capsule: &Capsule = target.as_deref().unwrap_or(&*source);
+    if !source.has_write_access(caller) || !target_capsule.has_write_access(caller) {
+        return Err(Error::Unauthorized);
+    }
+    let source_gallery = source
+        .galleries
+        .get(source_gallery_id)
+        .ok_or(Error::NotFound)?;
+    let target_gallery = target_capsule
+        .galleries
+        .get(target_gallery_id)
+        .ok_or(Error::NotFound)?;
+
+    let mut incoming = source_gallery.items.clone();
+    incoming.sort_by_key(|item| item.position);
+    let (duplicates, incoming): (Vec<_>, Vec<_>) = incoming.into_iter().partition(|item| {
+        target_gallery
+            .items
+            .iter()
+            .any(|existing| existing.memory_id == item.memory_id)
+    });
+
+    let missing: Vec<&str> = incoming
+        .iter()
+        .map(|item| item.memory_id.as_str())
+        .filter(|memory_id| !target_capsule.memories.contains_key(*memory_id))
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::InvalidArgument(format!(
+            "missing_memories: {}",
+            missing.join(", ")
+        )));
+    }
+
+    let source_cover = source_gallery.cover_memory_id.clone();
+    let result = GalleryMergeResult {
+        items_merged: incoming.len() as u32,
+        duplicates_skipped: duplicates.len() as u32,
+    };
+
+    source.galleries.remove(source_gallery_id);
+    source.updated_at = now;
+
+    let target_capsule = match target {
+        Some(target) => target,
+        None => source,
+    };
+    if let Some(gallery) = target_capsule.galleries.get_mut(target_gallery_id) {
+        gallery.items.sort_by_key(|item| item.position);
+        gallery.items.extend(incoming);
+        for (position, item) in gallery.items.iter_mut().enumerate() {
+            item.position = position as u32;
+        }
+        gallery.metadata.total_memories = gallery.items.len() as u32;
+        if gallery.cover_memory_id.is_none() {
+            gallery.cover_memory_id = source_cover;
+        }
+        gallery.updated_at = now;
+    }
+    target_capsule.updated_at = now;
+
+    Ok(result)
+}
+
+/// Check that an upload can land in `target` without changing `capsule`
+///
+/// An existing gallery must be live; a new one needs a title and a free slot.
+pub fn check_gallery_target(
+    capsule: &Capsule,
+    target: &GalleryTarget,
+    max: u32,
+) -> std::result::Result<(), Error> {
+    match target {
+        GalleryTarget::Existing { gallery_id } => match capsule.galleries.get(gallery_id) {
+            Some(gallery) if gallery.deleted_at.is_none() => Ok(()),
+            _ => Err(Error::NotFound),
+        },
+        GalleryTarget::New { title } => {
+            if title.trim().is_empty() {
+                return Err(Error::InvalidArgument(
+                    "a new gallery needs a title".to_string(),
+                ));
+            }
+            check_gallery_limit(capsule, max)
+        }
+    }
+}
+
+/// Append `memory_id` to the gallery `target` names, creating it for `GalleryTarget::New`
+///
+/// A new gallery's id is derived from `idem`. Returns the gallery id.
+pub fn add_memory_to_gallery(
+    capsule: &mut Capsule,
+    target: &GalleryTarget,
+    memory_id: &str,
+    memory_type: MemoryType,
+    idem: &str,
+    now: u64,
+    max: u32,
+) -> std::result::Result<String, Error> {
+    check_gallery_target(capsule, target, max)?;
+
+    let gallery_id = match target {
+        GalleryTarget::Existing { gallery_id } => gallery_id.clone(),
+        GalleryTarget::New { title } => {
+            let gallery = Gallery {
+                id: generate_deterministic_uuid_from_idem(&format!("gallery#{idem}")),
+                capsule_id: capsule.id.clone(),
+                metadata: GalleryMetadata {
+                    title: Some(title.trim().to_string()),
+                    name: String::new(), // derived by insert_gallery
+                    description: None,
+                    shared_count: 0,
+                    sharing_status: SharingStatus::Private,
+                    total_memories: 0,
+                    storage_location: vec![BlobHosting::Icp],
+                },
+                items: Vec::new(),
+                cover_memory_id: None,
+                access_entries: Vec::new(),
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+            };
+            insert_gallery(capsule, gallery, now).id
+        }
+    };
+
+    let gallery = capsule
+        .galleries
+        .get_mut(&gallery_id)
+        .ok_or(Error::NotFound)?;
+    if !gallery.items.iter().any(|item| item.memory_id == memory_id) {
+        let position = gallery.items.len() as u32;
+        gallery.add_item(memory_id.to_string(), memory_type, position);
+    }
+    gallery.updated_at = now;
+    capsule.updated_at = now;
+    Ok(gallery_id)
+}
+
+/// Galleries of `capsule` using `memory_id` as an item or as their cover, sorted by id
+pub fn galleries_referencing(capsule: &Capsule, memory_id: &str) -> Vec<String> {
+    let mut ids: Vec<String> = capsule
+        .galleries
+        .values()
+        .filter(|gallery| {
+            gallery.cover_memory_id.as_deref() == Some(memory_id)
+                || gallery.items.iter().any(|item| item.memory_id == memory_id)
+        })
+        .map(|gallery| gallery.id.clone())
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Drop `memory_id` from every gallery's items and cover; returns the galleries changed
+pub fn detach_memory_from_galleries(
+    capsule: &mut Capsule,
+    memory_id: &str,
+    now: u64,
+) -> Vec<String> {
+    let affected = galleries_referencing(capsule, memory_id);
+    for gallery_id in &affected {
+        if let Some(gallery) = capsule.galleries.get_mut(gallery_id) {
+            gallery.remove_memory(memory_id);
+            gallery.updated_at = now;
+        }
+    }
+    affected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        create_test_asset_metadata, create_test_capsule_at, create_test_folder,
+        create_test_gallery, create_test_memory_at,
+    };
+    use crate::upload::blob_store::{checksum_key, InMemoryBlobStore};
+    use candid::Principal;
+
+    fn owner() -> PersonRef {
+        PersonRef::Principal(Principal::anonymous())
+    }
+
+    #[test]
+    fn test_insert_gallery_uses_containing_capsule_id() {
+        let mut capsule = create_test_capsule_at("capsule_real", owner(), 1);
+        let gallery = create_test_gallery("g1", "capsule_spoofed", &[]);
+
+        let stored = insert_gallery(&mut capsule, gallery, 50);
+        assert_eq!(stored.capsule_id, "capsule_real");
+        assert_eq!(capsule.galleries["g1"].capsule_id, capsule.id);
+        assert_eq!(capsule.galleries["g1"].created_at, 50);
+        assert_eq!(capsule.updated_at, 50);
+    }
+
+    #[test]
+    fn test_insert_gallery_dedupes_names() {
+        let mut capsule = create_test_capsule_at("capsule_real", owner(), 1);
+
+        let first = insert_gallery(
+            &mut capsule,
+            create_test_gallery("g1", "capsule_real", &[]),
+            50,
+        );
+        let second = insert_gallery(
+            &mut capsule,
+            create_test_gallery("g2", "capsule_real", &[]),
+            51,
+        );
+        assert_eq!(first.metadata.name, "trip");
+        assert_eq!(second.metadata.name, "trip-2");
+
+        let mut untitled = create_test_gallery("g3", "capsule_real", &[]);
+        untitled.metadata.title = Some("???".to_string());
+        let third = insert_gallery(&mut capsule, untitled, 52);
+        assert_eq!(third.metadata.name, "untitled-1");
+
+        // Re-deriving a gallery's own name doesn't collide with itself
+        assert_eq!(unique_gallery_name(&capsule, "g2", Some("Trip")), "trip-2");
+    }
+
+    #[test]
+    fn test_gallery_limit_frees_a_slot_after_delete() {
+        let mut capsule = create_test_capsule_at("capsule_real", owner(), 1);
+        let max = 3;
+        for id in ["g1", "g2", "g3"] {
+            check_gallery_limit(&capsule, max).unwrap();
+            insert_gallery(
+                &mut capsule,
+                create_test_gallery(id, "capsule_real", &[]),
+                10,
+            );
+        }
+
+        assert_eq!(
+            check_gallery_limit(&capsule, max),
+            Err(Error::LimitReached { current: 3, max: 3 })
+        );
+
+        // A trashed gallery no longer counts
+        trash_gallery(&mut capsule, "g2", 20).unwrap();
+        check_gallery_limit(&capsule, max).unwrap();
+        insert_gallery(
+            &mut capsule,
+            create_test_gallery("g4", "capsule_real", &[]),
+            30,
+        );
+        assert!(check_gallery_limit(&capsule, max).is_err());
+    }
+
+    #[test]
+    fn test_trashed_gallery_can_be_restored() {
+        let mut capsule = create_test_capsule_at("capsule_real", owner(), 1);
+        insert_gallery(
+            &mut capsule,
+            create_test_gallery("g1", "capsule_real", &[]),
+            10,
+        );
+
+        trash_gallery(&mut capsule, "g1", 20).unwrap();
+        assert_eq!(capsule.galleries["g1"].deleted_at, Some(20));
+        assert_eq!(trash_gallery(&mut capsule, "g1", 21), Err(Error::NotFound));
+
+        let restored = restore_gallery(&mut capsule, "g1", 30).unwrap();
+        assert_eq!(restored.deleted_at, None);
+        assert_eq!(restored.updated_at, 30);
+        assert!(matches!(
+            restore_gallery(&mut capsule, "g1", 31),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_trashed_gallery_is_purged_after_cutoff() {
+        let mut capsule = create_test_capsule_at("capsule_real", owner(), 1);
+        insert_gallery(
+            &mut capsule,
+            create_test_gallery("g1", "capsule_real", &[]),
+            10,
+        );
+        insert_gallery(
+            &mut capsule,
+            create_test_gallery("g2", "capsule_real", &[]),
+            10,
+        );
+        trash_gallery(&mut capsule, "g1", 100).unwrap();
+
+        // Trashed after the cutoff: still restorable
+        assert_eq!(capsule.purge_trashed_containers(99), 0);
+        assert!(capsule.galleries.contains_key("g1"));
+
+        assert_eq!(capsule.purge_trashed_containers(100), 1);
+        assert!(!capsule.galleries.contains_key("g1"));
+        assert!(capsule.galleries.contains_key("g2"));
+        assert_eq!(
+            restore_gallery(&mut capsule, "g1", 200),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_two_uploads_land_in_new_gallery() {
+        let mut capsule = create_test_capsule_at("capsule_real", owner(), 1);
+        for memory_id in ["m1", "m2"] {
+            capsule.memories.insert(
+                memory_id.to_string(),
+                create_test_memory_at(memory_id, "capsule_real", 1),
+            );
+        }
+        let new = GalleryTarget::New {
+            title: "Wedding".to_string(),
+        };
+
+        let gallery_id =
+            add_memory_to_gallery(&mut capsule, &new, "m1", MemoryType::Image, "up-1", 10, 5)
+                .unwrap();
+        let existing = GalleryTarget::Existing {
+            gallery_id: gallery_id.clone(),
+        };
+        assert_eq!(
+            add_memory_to_gallery(
+                &mut capsule,
+                &existing,
+                "m2",
+                MemoryType::Image,
+                "up-2",
+                20,
+                5
+            ),
+            Ok(gallery_id.clone())
+        );
+
+        assert_eq!(capsule.galleries.len(), 1);
+        let gallery = &capsule.galleries[&gallery_id];
+        assert_eq!(gallery.capsule_id, "capsule_real");
+        assert_eq!(gallery.metadata.title.as_deref(), Some("Wedding"));
+        assert_eq!(gallery.metadata.name, "wedding");
+        let items: Vec<_> = gallery
+            .items
+            .iter()
+            .map(|item| (item.memory_id.as_str(), item.position))
+            .collect();
+        assert_eq!(items, vec![("m1", 0), ("m2", 1)]);
+        assert_eq!(gallery.metadata.total_memories, 2);
+        assert_eq!(gallery.updated_at, 20);
+
+        // Targets are checked before anything changes
+        let missing = GalleryTarget::Existing {
+            gallery_id: "nope".to_string(),
+        };
+        assert_eq!(
+            check_gallery_target(&capsule, &missing, 5),
+            Err(Error::NotFound)
+        );
+        let untitled = GalleryTarget::New {
+            title: " ".to_string(),
+        };
+        assert!(matches!(
+            check_gallery_target(&capsule, &untitled, 5),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(check_gallery_target(&capsule, &new, 1).is_err());
+    }
+
+    #[test]
+    fn test_deleting_cover_memory_clears_gallery_cover() {
+        let (mut capsule, _) = capsules_with_gallery(&["m1", "m2"]);
+        capsule.galleries.get_mut("g1").unwrap().cover_memory_id = Some("m1".to_string());
+        let mut other = create_test_gallery("g2", "source", &["m2"]);
+        other.cover_memory_id = Some("m2".to_string());
+        capsule.galleries.insert("g2".to_string(), other);
+
+        assert_eq!(
+            galleries_referencing(&capsule, "m1"),
+            vec!["g1".to_string()]
+        );
+
+        capsule.memories.remove("m1");
+        let affected = detach_memory_from_galleries(&mut capsule, "m1", 9);
+
+        assert_eq!(affected, vec!["g1".to_string()]);
+        let g1 = &capsule.galleries["g1"];
+        assert_eq!(g1.cover_memory_id, None);
+        assert_eq!(g1.items.len(), 1);
+        assert_eq!(g1.metadata.total_memories, 1);
+        assert_eq!(g1.updated_at, 9);
+        // Galleries that never used the memory are untouched
+        assert_eq!(
+            capsule.galleries["g2"].cover_memory_id.as_deref(),
+            Some("m2")
+        );
+        assert_eq!(capsule.galleries["g2"].updated_at, 1);
+        assert!(galleries_referencing(&capsule, "m1").is_empty());
+    }
+
+    fn capsules_with_gallery(memory_ids: &[&str]) -> (Capsule, Capsule) {
+        let mut source = create_test_capsule_at("source", owner(), 1);
+        for memory_id in memory_ids {
+            source.memories.insert(
+                memory_id.to_string(),
+                create_test_memory_at(memory_id, "source", 1),
+            );
+        }
+        source.galleries.insert(
+            "g1".to_string(),
+            create_test_gallery("g1", "source", memory_ids),
+        );
+        let target = create_test_capsule_at("target", owner(), 1);
+        (source, target)
+    }
+
+    /// Move g1 from `source` to `target` with an empty blob store
+    fn move_g1(
+        source: &mut Capsule,
+        target: &mut Capsule,
+        move_memories: bool,
+    ) -> std::result::Result<Vec<String>, Error> {
+        let blob_store = InMemoryBlobStore::new(1);
+        move_gallery(
+            &blob_store,
+            source,
+            target,
+            &owner(),
+            "g1",
+            move_memories,
+            42,
+        )
+    }
+
+    #[test]
+    fn test_move_gallery_with_memories() {
+        let (mut source, mut target) = capsules_with_gallery(&["m1", "m2"]);
+        move_g1(&mut source, &mut target, true).unwrap();
+
+        assert!(!source.galleries.contains_key("g1"));
+        let moved = &target.galleries["g1"];
+        assert_eq!(moved.capsule_id, "target");
+        assert_eq!(moved.updated_at, 42);
+        assert!(source.memories.is_empty());
+        assert_eq!(target.memories["m1"].capsule_id, "target");
+        assert_eq!(target.memories["m2"].capsule_id, "target");
+        assert_eq!(source.updated_at, 42);
+        assert_eq!(target.updated_at, 42);
+    }
+
+    #[test]
+    fn test_move_gallery_detaches_moved_memories_from_source() {
+        let (mut source, mut target) = capsules_with_gallery(&["m1", "m2"]);
+        let mut other = create_test_gallery("g2", "source", &["m1"]);
+        other.cover_memory_id = Some("m2".to_string());
+        source.galleries.insert("g2".to_string(), other);
+        source
+            .memories
+            .get_mut("m1")
+            .unwrap()
+            .metadata
+            .parent_folder_id = Some("f1".to_string());
+        move_g1(&mut source, &mut target, true).unwrap();
+
+        // The gallery left behind no longer points at memories that moved
+        let other = &source.galleries["g2"];
+        assert!(other.items.is_empty());
+        assert_eq!(other.cover_memory_id, None);
+        assert_eq!(other.updated_at, 42);
+        assert_eq!(target.memories["m1"].metadata.parent_folder_id, None);
+        assert_eq!(target.galleries["g1"].items.len(), 2);
+    }
+
+    /// `source` with gallery g1 over m1 and m2, each backed by a 5-byte blob charged to `source`
+    fn capsules_with_blob_gallery(blob_store: &InMemoryBlobStore) -> (Capsule, Capsule) {
+        let (mut source, target) = capsules_with_gallery(&["m1", "m2"]);
+        for (memory_id, blob_id) in [("m1", 1u64), ("m2", 2)] {
+            let memory = crate::memories::core::model_helpers::create_blob_memory(
+                memory_id,
+                &source.id,
+                crate::types::BlobRef {
+                    locator: format!("blob_{blob_id}"),
+                    hash: None,
+                    len: 5,
+                },
+                create_test_asset_metadata(),
+                1,
+                &owner(),
+            );
+            source.memories.insert(memory_id.to_string(), memory);
+            blob_store.put_meta(
+                blob_id,
+                BlobMeta {
+                    size: 5,
+                    checksum: [blob_id as u8; 32],
+                    created_at: 1,
+                    pmid_hash: [blob_id as u8; 32],
+                    capsule_id: Some(source.id.clone()),
+                },
+            );
+            source.charge_blob_bytes(5);
+        }
+        (source, target)
+    }
+
+    #[test]
+    fn test_move_gallery_moves_blob_charges_to_target() {
+        let blob_store = InMemoryBlobStore::new(1);
+        let (mut source, mut target) = capsules_with_blob_gallery(&blob_store);
+
+        let moved = move_gallery(
+            &blob_store,
+            &mut source,
+            &mut target,
+            &owner(),
+            "g1",
+            true,
+            42,
+        )
+        .unwrap();
+
+        assert_eq!(moved, vec!["m1".to_string(), "m2".to_string()]);
+        assert_eq!(source.blob_bytes_used(), 0);
+        assert_eq!(target.blob_bytes_used(), 10);
+        // Dedup finds the blobs under the target capsule only
+        let meta = blob_store.get_meta(1).unwrap();
+        assert_eq!(meta.capsule_id.as_deref(), Some("target"));
+        assert_eq!(
+            blob_store.blob_by_checksum(&checksum_key("target", &[1; 32])),
+            Some(1)
+        );
+        assert_eq!(
+            blob_store.blob_by_checksum(&checksum_key("source", &[1; 32])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_move_gallery_checks_target_blob_quota_first() {
+        let blob_store = InMemoryBlobStore::new(1);
+        let (mut source, mut target) = capsules_with_blob_gallery(&blob_store);
+        target.blob_bytes_quota = Some(8);
+
+        let err = move_gallery(
+            &blob_store,
+            &mut source,
+            &mut target,
+            &owner(),
+            "g1",
+            true,
+            42,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            Error::QuotaExceeded {
+                requested_bytes: 10,
+                remaining_bytes: 8,
+            }
+        );
+        assert!(source.galleries.contains_key("g1"));
+        assert_eq!(source.memories.len(), 2);
+        assert_eq!(source.blob_bytes_used(), 10);
+        assert_eq!(target.blob_bytes_used(), 0);
+        assert_eq!(
+            blob_store.get_meta(1).unwrap().capsule_id.as_deref(),
+            Some("source")
+        );
+    }
+
+    #[test]
+    fn test_move_gallery_refuses_blobs_shared_with_memories_staying_behind() {
+        let blob_store = InMemoryBlobStore::new(1);
+        let (mut source, mut target) = capsules_with_blob_gallery(&blob_store);
+        let mut copy = source.memories["m1"].clone();
+        copy.id = "m3".to_string();
+        source.memories.insert("m3".to_string(), copy);
+
+        let err = move_gallery(
+            &blob_store,
+            &mut source,
+            &mut target,
+            &owner(),
+            "g1",
+            true,
+            42,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, Error::InvalidArgument(_)));
+        assert!(source.galleries.contains_key("g1"));
+        assert_eq!(source.blob_bytes_used(), 10);
+    }
+
+    #[test]
+    fn test_move_gallery_updates_folder_counts() {
+        let (mut source, mut target) = capsules_with_gallery(&["m1", "m2"]);
+        let mut folder = create_test_folder("f1", "source");
+        folder.metadata.total_memories = 2;
+        source.folders.insert("f1".to_string(), folder);
+        for memory_id in ["m1", "m2"] {
+            source
+                .memories
+                .get_mut(memory_id)
+                .unwrap()
+                .metadata
+                .parent_folder_id = Some("f1".to_string());
+        }
+        move_g1(&mut source, &mut target, true).unwrap();
+
+        assert_eq!(source.folders["f1"].metadata.total_memories, 0);
+        assert_eq!(source.folders["f1"].updated_at, 42);
+        assert_eq!(target.memories["m1"].metadata.parent_folder_id, None);
+    }
+
+    #[test]
+    fn test_move_gallery_when_members_already_in_target() {
+        let (mut source, mut target) = capsules_with_gallery(&["m1"]);
+        target
+            .memories
+            .insert("m1".to_string(), create_test_memory_at("m1", "target", 1));
+        move_g1(&mut source, &mut target, false).unwrap();
+
+        assert_eq!(target.galleries["g1"].capsule_id, "target");
+        // Source copy of the memory stays where it was
+        assert!(source.memories.contains_key("m1"));
+    }
+
+    #[test]
+    fn test_move_gallery_reports_missing_members() {
+        let (mut source, mut target) = capsules_with_gallery(&["m1", "m2"]);
+        target
+            .memories
+            .insert("m1".to_string(), create_test_memory_at("m1", "target", 1));
+        let err = move_g1(&mut source, &mut target, false).unwrap_err();
+
+        assert_eq!(
+            err,
+            Error::InvalidArgument("missing_memories: m2".to_string())
+        );
+        assert!(source.galleries.contains_key("g1"));
+        assert!(!target.galleries.contains_key("g1"));
+        assert_eq!(source.updated_at, 1);
+        assert_eq!(target.updated_at, 1);
+    }
+
+    #[test]
+    fn test_move_gallery_requires_write_access_to_target() {
+        let (mut source, _) = capsules_with_gallery(&[]);
+        let stranger = PersonRef::Principal(Principal::management_canister());
+        let mut target = create_test_capsule_at("target", stranger, 1);
+        let err = move_g1(&mut source, &mut target, true).unwrap_err();
+
+        assert_eq!(err, Error::Unauthorized);
+        assert!(source.galleries.contains_key("g1"));
+    }
+
+    #[test]
+    fn test_merge_overlapping_galleries() {
+        let (mut capsule, _) = capsules_with_gallery(&["m1", "m2", "m3"]);
+        let mut target = create_test_gallery("g2", "source", &["m2", "m1"]);
+        target.items[0].position = 5; // positions need not be dense
+        capsule.galleries.insert("g2".to_string(), target);
+        capsule.galleries.get_mut("g1").unwrap().cover_memory_id = Some("m3".to_string());
+
+        let result = merge_galleries(&mut capsule, None, &owner(), "g1", "g2", 42).unwrap();
+
+        assert_eq!(
+            result,
+            GalleryMergeResult {
+                items_merged: 1,
+                duplicates_skipped: 2,
+            }
+        );
+        assert!(!capsule.galleries.contains_key("g1"));
+        let merged = &capsule.galleries["g2"];
+        let order: Vec<(&str, u32)> = merged
+            .items
+            .iter()
+            .map(|item| (item.memory_id.as_str(), item.position))
+            .collect();
+        assert_eq!(order, vec![("m1", 0), ("m2", 1), ("m3", 2)]);
+        assert_eq!(merged.metadata.total_memories, 3);
+        assert_eq!(merged.cover_memory_id.as_deref(), Some("m3"));
+        assert_eq!(merged.updated_at, 42);
+    }
+
+    #[test]
+    fn test_merge_galleries_across_capsules_needs_memories_in_target() {
+        let (mut source, mut target) = capsules_with_gallery(&["m1"]);
+        target
+            .galleries
+            .insert("g2".to_string(), create_test_gallery("g2", "target", &[]));
+
+        let err =
+            merge_galleries(&mut source, Some(&mut target), &owner(), "g1", "g2", 42).unwrap_err();
+
+        assert_eq!(
+            err,
+            Error::InvalidArgument("missing_memories: m1".to_string())
+        );
+        assert!(source.galleries.contains_key("g1"));
+        assert!(target.galleries["g2"].items.is_empty());
+    }
+}
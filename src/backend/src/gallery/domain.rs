@@ -41,6 +41,8 @@ pub struct Gallery {
     pub access_entries: Vec<AccessEntry>,
     pub created_at: u64,
     pub updated_at: u64,
+    /// Set while the gallery sits in the capsule trash (purged after `TRASH_RETENTION_NS`)
+    pub deleted_at: Option<u64>,
 }
 
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
@@ -55,6 +57,7 @@ pub struct GalleryHeader {
     pub sharing_status: SharingStatus,
     pub total_memories: u32,
     pub storage_location: Vec<BlobHosting>,
+    pub deleted_at: Option<u64>,
 }
 
 // Gallery implementations
@@ -69,9 +72,14 @@ impl Gallery {
     /// Convert Gallery to GalleryHeader for listing operations
     pub fn to_header(&self) -> GalleryHeader {
         let title = self.metadata.title.clone();
-        let name = title.as_ref()
-            .map(|t| crate::utils::title_to_name(t))
-            .unwrap_or_else(|| "untitled".to_string());
+        // Stored name is deduplicated within the capsule; derive one for legacy galleries
+        let name = if self.metadata.name.is_empty() {
+            title.as_ref()
+                .map(|t| crate::utils::title_to_name(t))
+                .unwrap_or_else(|| "untitled".to_string())
+        } else {
+            self.metadata.name.clone()
+        };
 
         GalleryHeader {
             id: self.id.clone(),
@@ -84,6 +92,7 @@ impl Gallery {
             sharing_status: self.metadata.sharing_status.clone(),
             total_memories: self.metadata.total_memories,
             storage_location: self.metadata.storage_location.clone(),
+            deleted_at: self.deleted_at,
         }
     }
 
@@ -94,7 +103,6 @@ impl Gallery {
         self.metadata.storage_location.clone()
     }
 
-    #[allow(dead_code)]
     pub fn add_item(&mut self, memory_id: String, memory_type: MemoryType, position: u32) {
         let item = GalleryItem {
             memory_id: memory_id.clone(),
@@ -108,7 +116,6 @@ impl Gallery {
         // Note: updated_at should be set by the caller using ic_cdk::api::time()
     }
 
-    #[allow(dead_code)]
     pub fn remove_memory(&mut self, memory_id: &str) {
         // If removing the cover memory, clear the cover reference
         if self.cover_memory_id.as_ref() == Some(&memory_id.to_string()) {
@@ -1,10 +1,23 @@
-use crate::capsule_store::{types::PaginationOrder as Order, CapsuleStore};
+use crate::capsule::access::resource_perm_mask;
+use crate::capsule::domain::{Capsule, Perm, PrincipalContext, ResourceType};
+use crate::capsule_store::{types::Page, types::PaginationOrder as Order, CapsuleStore};
 use crate::memory::with_capsule_store;
-use crate::types::PersonRef;
-use crate::gallery::domain::GalleryHeader;
+use crate::memories::types::AssetStorageKind;
+use crate::types::{Error, Memory, PersonRef};
+use crate::utils::pagination::paginate_sorted;
+use crate::gallery::api_types::{GalleryManifest, GalleryMeta, ManifestAsset, ManifestItem};
+use crate::gallery::domain::{Gallery, GalleryHeader, GalleryItem};
+
+/// Default page size for gallery item listings
+const ITEMS_PAGE_DEFAULT: u32 = 50;
+/// Maximum page size for gallery item listings
+const ITEMS_PAGE_MAX: u32 = 100;
 
 /// Get all galleries for the caller (replaces get_user_galleries)
-pub fn galleries_list() -> Vec<GalleryHeader> {
+///
+/// Trashed galleries are left out unless `include_deleted` is set.
+pub fn galleries_list(include_deleted: Option<bool>) -> Vec<GalleryHeader> {
+    let include_deleted = include_deleted.unwrap_or(false);
     let caller = PersonRef::from_caller();
 
     // MIGRATED: List all galleries from caller's self-capsule
@@ -20,6 +33,7 @@ pub fn galleries_list() -> Vec<GalleryHeader> {
                 let mut gallery_headers: Vec<GalleryHeader> = capsule
                     .galleries
                     .values()
+                    .filter(|gallery| include_deleted || gallery.deleted_at.is_none())
                     .map(|gallery| gallery.to_header())
                     .collect();
 
@@ -32,3 +46,397 @@ pub fn galleries_list() -> Vec<GalleryHeader> {
         }
     })
 }
+
+/// Find a gallery in any capsule the caller can read
+fn find_readable_gallery(caller: &PersonRef, gallery_id: &str) -> std::result::Result<Gallery, Error> {
+    with_capsule_store(|store| {
+        let all_capsules = store.paginate(None, u32::MAX, Order::Asc);
+        all_capsules
+            .items
+            .into_iter()
+            .find_map(|capsule| {
+                capsule
+                    .galleries
+                    .get(gallery_id)
+                    .map(|gallery| (capsule.has_read_access(caller), gallery.clone()))
+            })
+            .ok_or(Error::NotFound)
+            .and_then(|(readable, gallery)| {
+                if readable {
+                    Ok(gallery)
+                } else {
+                    Err(Error::Unauthorized)
+                }
+            })
+    })
+}
+
+/// Read gallery metadata and cover without its items
+pub fn galleries_read_meta(gallery_id: String) -> std::result::Result<GalleryMeta, Error> {
+    let caller = PersonRef::from_caller();
+    let gallery = find_readable_gallery(&caller, &gallery_id)?;
+
+    Ok(GalleryMeta {
+        id: gallery.id,
+        capsule_id: gallery.capsule_id,
+        metadata: gallery.metadata,
+        cover_memory_id: gallery.cover_memory_id,
+        item_count: gallery.items.len() as u64,
+        created_at: gallery.created_at,
+        updated_at: gallery.updated_at,
+    })
+}
+
+/// Read one page of a gallery's items, ordered by position then memory_id
+pub fn galleries_read_items(
+    gallery_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<GalleryItem>, Error> {
+    let caller = PersonRef::from_caller();
+    let gallery = find_readable_gallery(&caller, &gallery_id)?;
+    paginate_gallery_items(gallery.items, cursor, limit)
+}
+
+/// Fetch details of a memory's assets, in display order
+fn manifest_assets(memory: &Memory) -> Vec<ManifestAsset> {
+    memory
+        .ordered_asset_ids()
+        .into_iter()
+        .filter_map(|asset_id| {
+            if let Some(a) = memory.inline_assets.iter().find(|a| a.asset_id == asset_id) {
+                let base = a.metadata.get_base();
+                return Some(ManifestAsset {
+                    asset_id,
+                    kind: AssetStorageKind::Inline,
+                    locator: None,
+                    size: a.bytes.len() as u64,
+                    sha256: base.sha256,
+                    content_type: base.mime_type.clone(),
+                });
+            }
+            if let Some(a) = memory
+                .blob_internal_assets
+                .iter()
+                .find(|a| a.asset_id == asset_id)
+            {
+                let base = a.metadata.get_base();
+                return Some(ManifestAsset {
+                    asset_id,
+                    kind: AssetStorageKind::Internal,
+                    locator: Some(a.blob_ref.locator.clone()),
+                    size: a.blob_ref.len,
+                    sha256: base.sha256.or(a.blob_ref.hash),
+                    content_type: base.mime_type.clone(),
+                });
+            }
+            let a = memory
+                .blob_external_assets
+                .iter()
+                .find(|a| a.asset_id == asset_id)?;
+            let base = a.metadata.get_base();
+            Some(ManifestAsset {
+                asset_id,
+                kind: AssetStorageKind::External,
+                locator: Some(a.url.clone().unwrap_or_else(|| a.storage_key.clone())),
+                size: base.bytes,
+                sha256: base.sha256,
+                content_type: base.mime_type.clone(),
+            })
+        })
+        .collect()
+}
+
+fn can_view(
+    capsule: &Capsule,
+    resource_type: ResourceType,
+    resource_id: &str,
+    ctx: &PrincipalContext,
+) -> bool {
+    resource_perm_mask(capsule, &resource_type, resource_id, ctx)
+        .is_some_and(|held| held & Perm::VIEW.bits() != 0)
+}
+
+/// Manifest of a gallery of `capsule` as `ctx` may see it
+///
+/// Needs VIEW on the gallery; items whose memory the caller can't view are
+/// left out and only counted.
+pub fn gallery_manifest(
+    capsule: &Capsule,
+    gallery_id: &str,
+    ctx: &PrincipalContext,
+) -> std::result::Result<GalleryManifest, Error> {
+    let gallery = capsule.galleries.get(gallery_id).ok_or(Error::NotFound)?;
+    if !can_view(capsule, ResourceType::Gallery, gallery_id, ctx) {
+        return Err(Error::Unauthorized);
+    }
+
+    let mut members: Vec<_> = gallery.items.iter().collect();
+    members.sort_by(|a, b| (a.position, &a.memory_id).cmp(&(b.position, &b.memory_id)));
+
+    let mut items = Vec::new();
+    let mut omitted_items = 0u32;
+    for item in members {
+        let memory = capsule
+            .memories
+            .get(&item.memory_id)
+            .filter(|_| can_view(capsule, ResourceType::Memory, &item.memory_id, ctx));
+        match memory {
+            Some(memory) => items.push(ManifestItem {
+                memory_id: item.memory_id.clone(),
+                memory_type: item.memory_type.clone(),
+                position: item.position,
+                caption: item.caption.clone(),
+                title: memory.metadata.title.clone(),
+                assets: manifest_assets(memory),
+            }),
+            None => omitted_items += 1,
+        }
+    }
+
+    Ok(GalleryManifest {
+        id: gallery.id.clone(),
+        capsule_id: gallery.capsule_id.clone(),
+        metadata: gallery.metadata.clone(),
+        cover_memory_id: gallery.cover_memory_id.clone(),
+        items,
+        omitted_items,
+        exported_at: ctx.now_ns,
+    })
+}
+
+/// Export a gallery with what's needed to fetch each member the caller can view
+pub fn galleries_export(gallery_id: String) -> std::result::Result<GalleryManifest, Error> {
+    let ctx = PrincipalContext::new(ic_cdk::api::msg_caller(), vec![], None, ic_cdk::api::time());
+    with_capsule_store(|store| {
+        let all_capsules = store.paginate(None, u32::MAX, Order::Asc);
+        let capsule = all_capsules
+            .items
+            .iter()
+            .find(|capsule| capsule.galleries.contains_key(&gallery_id))
+            .ok_or(Error::NotFound)?;
+        gallery_manifest(capsule, &gallery_id, &ctx)
+    })
+}
+
+/// Sort key of a gallery item, rendered as the `"{position}:{memory_id}"` cursor
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct GalleryItemKey(u32, String);
+
+impl std::fmt::Display for GalleryItemKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.0, self.1)
+    }
+}
+
+/// Paginate gallery items by (position, memory_id)
+///
+/// Cursor is `"{position}:{memory_id}"` of the last item of the previous page (exclusive).
+pub fn paginate_gallery_items(
+    items: Vec<GalleryItem>,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<GalleryItem>, Error> {
+    let limit = limit.unwrap_or(ITEMS_PAGE_DEFAULT).clamp(1, ITEMS_PAGE_MAX);
+
+    let after = match cursor {
+        Some(text) => {
+            let parsed = text
+                .split_once(':')
+                .and_then(|(position, memory_id)| {
+                    position
+                        .parse::<u32>()
+                        .ok()
+                        .map(|p| GalleryItemKey(p, memory_id.to_string()))
+                });
+            match parsed {
+                Some(key) => Some(key),
+                None => return Err(Error::InvalidArgument(format!("invalid cursor: {}", text))),
+            }
+        }
+        None => None,
+    };
+
+    Ok(paginate_sorted(
+        items,
+        |item| GalleryItemKey(item.position, item.memory_id.clone()),
+        after,
+        limit,
+        Order::Asc,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MemoryType;
+
+    fn item(memory_id: &str, position: u32) -> GalleryItem {
+        GalleryItem {
+            memory_id: memory_id.to_string(),
+            memory_type: MemoryType::Image,
+            position,
+            caption: None,
+            metadata: std::collections::HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_paginate_gallery_items_orders_by_position_then_id() {
+        let items = vec![item("c", 2), item("b", 1), item("a", 1), item("d", 0)];
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = paginate_gallery_items(items.clone(), cursor, Some(2)).unwrap();
+            assert!(page.items.len() <= 2);
+            seen.extend(page.items.into_iter().map(|i| i.memory_id));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, vec!["d", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_paginate_gallery_items_memory_id_with_colon() {
+        let items = vec![item("mem:1", 0), item("mem:2", 0), item("mem:3", 1)];
+        let page = paginate_gallery_items(items.clone(), None, Some(1)).unwrap();
+        assert_eq!(page.next_cursor.as_deref(), Some("0:mem:1"));
+
+        let page = paginate_gallery_items(items, page.next_cursor, Some(5)).unwrap();
+        let ids: Vec<_> = page.items.iter().map(|i| i.memory_id.as_str()).collect();
+        assert_eq!(ids, vec!["mem:2", "mem:3"]);
+        assert!(page.next_cursor.is_none());
+    }
+
+    fn view_entry(person: &PersonRef) -> crate::capsule::domain::AccessEntry {
+        use crate::capsule::domain::{AccessCondition, AccessEntry, GrantSource, ResourceRole};
+        AccessEntry {
+            id: "view_1".to_string(),
+            person_ref: Some(person.clone()),
+            is_public: false,
+            grant_source: GrantSource::User,
+            source_id: None,
+            role: ResourceRole::Guest,
+            perm_mask: Perm::VIEW.bits(),
+            invited_by_person_ref: None,
+            created_at: 1,
+            updated_at: 1,
+            condition: AccessCondition::Immediate,
+        }
+    }
+
+    #[test]
+    fn test_gallery_manifest_omits_memories_caller_cannot_view() {
+        use crate::gallery::domain::GalleryMetadata;
+        use crate::test_utils::{create_test_capsule_at, create_test_memory_at};
+        use crate::types::{BlobRef, MemoryAssetBlobInternal};
+        use candid::Principal;
+
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let friend_principal = Principal::from_slice(&[7; 29]);
+        let friend = PersonRef::Principal(friend_principal);
+        let mut capsule = create_test_capsule_at("capsule_1", owner, 1);
+
+        let mut shared = create_test_memory_at("m_shared", "capsule_1", 1);
+        shared.access_entries.push(view_entry(&friend));
+        shared.blob_internal_assets.push(MemoryAssetBlobInternal {
+            asset_id: "asset_1".to_string(),
+            blob_ref: BlobRef {
+                locator: "icp://blob/7".to_string(),
+                hash: Some([9; 32]),
+                len: 5,
+            },
+            metadata: crate::memories::types::AssetMetadata::Note(
+                crate::memories::types::NoteAssetMetadata {
+                    base: crate::memories::types::AssetMetadataBase {
+                        name: "note.txt".to_string(),
+                        description: None,
+                        tags: vec![],
+                        asset_type: crate::memories::types::AssetType::Original,
+                        bytes: 5,
+                        mime_type: "text/plain".to_string(),
+                        sha256: None,
+                        width: None,
+                        height: None,
+                        url: None,
+                        storage_key: None,
+                        bucket: None,
+                        asset_location: None,
+                        processing_status: None,
+                        processing_error: None,
+                        created_at: 1,
+                        updated_at: 1,
+                        deleted_at: None,
+                    },
+                    word_count: None,
+                    language: None,
+                    format: None,
+                },
+            ),
+            order: None,
+        });
+        capsule.memories.insert("m_shared".to_string(), shared);
+        capsule.memories.insert(
+            "m_private".to_string(),
+            create_test_memory_at("m_private", "capsule_1", 1),
+        );
+
+        let gallery = Gallery {
+            id: "g_1".to_string(),
+            capsule_id: "capsule_1".to_string(),
+            metadata: GalleryMetadata {
+                title: Some("Trip".to_string()),
+                name: "trip".to_string(),
+                description: None,
+                shared_count: 0,
+                sharing_status: crate::capsule::domain::SharingStatus::Private,
+                total_memories: 2,
+                storage_location: vec![],
+            },
+            items: vec![item("m_private", 0), item("m_shared", 1)],
+            cover_memory_id: None,
+            access_entries: vec![],
+            created_at: 1,
+            updated_at: 1,
+            deleted_at: None,
+        };
+        capsule.galleries.insert("g_1".to_string(), gallery);
+
+        let ctx = PrincipalContext::new(friend_principal, vec![], None, 5);
+        assert!(matches!(
+            gallery_manifest(&capsule, "g_1", &ctx),
+            Err(Error::Unauthorized)
+        ));
+
+        capsule
+            .galleries
+            .get_mut("g_1")
+            .unwrap()
+            .access_entries
+            .push(view_entry(&friend));
+        let manifest = gallery_manifest(&capsule, "g_1", &ctx).unwrap();
+        assert_eq!(manifest.omitted_items, 1);
+        assert_eq!(manifest.items.len(), 1);
+        assert_eq!(manifest.items[0].memory_id, "m_shared");
+        let asset = &manifest.items[0].assets[0];
+        assert_eq!(asset.kind, AssetStorageKind::Internal);
+        assert_eq!(asset.locator.as_deref(), Some("icp://blob/7"));
+        assert_eq!(asset.sha256, Some([9; 32]));
+
+        // The owner sees every member
+        let owner_ctx = PrincipalContext::new(Principal::anonymous(), vec![], None, 5);
+        let manifest = gallery_manifest(&capsule, "g_1", &owner_ctx).unwrap();
+        assert_eq!(manifest.omitted_items, 0);
+        assert_eq!(manifest.items.len(), 2);
+    }
+
+    #[test]
+    fn test_paginate_gallery_items_invalid_cursor() {
+        let result = paginate_gallery_items(vec![item("a", 0)], Some("nope".into()), None);
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+    }
+}
@@ -1,29 +1,9 @@
 use crate::gallery::domain::Gallery;
+use crate::size;
 
-/// Estimate the size of a gallery in bytes
+/// Estimate the size of a gallery in bytes (see `size::estimate`)
 pub fn estimate_gallery_size(gallery: &Gallery) -> u64 {
-    // Estimate based on gallery metadata and memory entries
-    let mut size = 0u64;
-
-    // Gallery metadata size
-    size += gallery.id.len() as u64;
-    if let Some(title) = &gallery.metadata.title {
-        size += title.len() as u64;
-    }
-    if let Some(description) = &gallery.metadata.description {
-        size += description.len() as u64;
-    }
-
-    // Memory entries size
-    for memory_entry in &gallery.items {
-        size += memory_entry.memory_id.len() as u64;
-        if let Some(caption) = &memory_entry.caption {
-            size += caption.len() as u64;
-        }
-        size += memory_entry.metadata.len() as u64;
-    }
-
-    size
+    size::estimate(gallery)
 }
 
 /// Estimate the size of a gallery within a capsule in bytes
@@ -60,17 +40,11 @@ pub fn get_gallery_size_breakdown(gallery: &Gallery) -> GallerySizeInfo {
         } else {
             0
         },
-        metadata_size: {
-            let mut size = 0u64;
-            size += gallery.id.len() as u64;
-            if let Some(title) = &gallery.metadata.title {
-                size += title.len() as u64;
-            }
-            if let Some(description) = &gallery.metadata.description {
-                size += description.len() as u64;
-            }
-            size
-        },
+        metadata_size: size::metadata_size(
+            &gallery.id,
+            &gallery.metadata.title,
+            &gallery.metadata.description,
+        ),
     }
 }
 
@@ -0,0 +1,121 @@
+//! Reverse grant index
+//!
+//! Maps a person to the capsules in which they hold at least one individual
+//! access grant, so "shared with me" listings don't have to scan every capsule.
+//! Kept in sync by `capsule::access` on grant and revoke, and backfilled once
+//! (from `post_upgrade`) for grants made before it existed. Readers still check
+//! the capsule itself, so a stale entry (e.g. a deleted capsule) is harmless.
+
+use crate::capsule::domain::{Capsule, PersonRef};
+use crate::memory::{MEM_GRANT_INDEX, MEM_GRANT_INDEX_BACKFILLED, MM};
+use crate::types::CapsuleId;
+use candid::{Decode, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{
+    storable::Bound, DefaultMemoryImpl, StableBTreeMap, StableCell, Storable,
+};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+thread_local! {
+    static STABLE_GRANT_INDEX: RefCell<StableBTreeMap<PersonRef, CapsuleSet, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_GRANT_INDEX)))
+    );
+
+    static BACKFILLED: RefCell<StableCell<bool, Memory>> = RefCell::new(
+        StableCell::init(MM.with(|m| m.borrow().get(MEM_GRANT_INDEX_BACKFILLED)), false)
+            .expect("Failed to init grant index backfill flag")
+    );
+}
+
+/// Capsules a person holds grants in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapsuleSet(BTreeSet<CapsuleId>);
+
+impl Storable for PersonRef {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("Failed to encode PersonRef"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), PersonRef).expect("Failed to decode PersonRef")
+    }
+}
+
+impl Storable for CapsuleSet {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, &self.0)).expect("Failed to encode CapsuleSet"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, ids): (u16, BTreeSet<CapsuleId>) =
+            Decode!(bytes.as_ref(), (u16, BTreeSet<CapsuleId>))
+                .expect("Failed to decode CapsuleSet");
+        assert_eq!(version, 1, "Unsupported CapsuleSet version");
+        CapsuleSet(ids)
+    }
+}
+
+/// Record whether `person` still holds a grant in `capsule_id`
+pub fn sync(person: &PersonRef, capsule_id: &str, has_grant: bool) {
+    STABLE_GRANT_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        let mut set = index.get(person).unwrap_or_default();
+        let changed = if has_grant {
+            set.0.insert(capsule_id.to_string())
+        } else {
+            set.0.remove(capsule_id)
+        };
+        if !changed {
+            return;
+        }
+        if set.0.is_empty() {
+            index.remove(person);
+        } else {
+            index.insert(person.clone(), set);
+        }
+    });
+}
+
+/// Capsules `person` holds grants in (may include since-deleted capsules)
+pub fn capsules_for(person: &PersonRef) -> Vec<CapsuleId> {
+    STABLE_GRANT_INDEX.with(|index| {
+        index
+            .borrow()
+            .get(person)
+            .map(|set| set.0.into_iter().collect())
+            .unwrap_or_default()
+    })
+}
+
+/// Whether grants stored before the index existed have been indexed
+pub fn is_backfilled() -> bool {
+    BACKFILLED.with(|flag| *flag.borrow().get())
+}
+
+/// Index every grant in `capsules` and mark the backfill done
+///
+/// Only adds entries, so grants indexed since are kept. Returns how many
+/// (person, capsule) pairs were indexed.
+pub fn backfill(capsules: &[Capsule]) -> u64 {
+    let mut indexed = 0;
+    for capsule in capsules {
+        for person in capsule.grantees() {
+            sync(&person, &capsule.id, true);
+            indexed += 1;
+        }
+    }
+    BACKFILLED.with(|flag| {
+        flag.borrow_mut()
+            .set(true)
+            .expect("Failed to store grant index backfill flag");
+    });
+    indexed
+}
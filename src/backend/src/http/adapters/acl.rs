@@ -114,6 +114,9 @@ mod tests {
             inline_assets: vec![],
             blob_internal_assets: vec![],
             blob_external_assets: vec![],
+            locked: None,
+            pending: None,
+            expires_at: None,
         }
     }
 
@@ -166,6 +169,9 @@ mod tests {
             inline_assets: vec![],
             blob_internal_assets: vec![],
             blob_external_assets: vec![],
+            locked: None,
+            pending: None,
+            expires_at: None,
         }
     }
 
@@ -161,7 +161,8 @@ impl AssetStore for FuturaAssetStore {
         ) {
             Ok(crate::types::MemoryAssetData::InternalBlob { blob_id, size, .. }) => {
                 // Get chunk size from blob metadata
-                let blob_store = crate::upload::blob_store::BlobStore::new();
+                use crate::upload::blob_store::BlobStore;
+                let blob_store = crate::upload::blob_store::StableBlobStore;
                 match blob_store
                     .get_blob_meta(&crate::upload::types::BlobId(blob_id.parse().unwrap_or(0)))
                 {
@@ -196,8 +197,9 @@ impl AssetStore for FuturaAssetStore {
         ) {
             Ok(crate::types::MemoryAssetData::InternalBlob { blob_id, .. }) => {
                 // Read chunk from internal blob store
-                let _blob_store = crate::upload::blob_store::BlobStore::new();
+                let blob_store = crate::upload::blob_store::StableBlobStore;
                 match crate::upload::blob_store::blob_read_chunk(
+                    &blob_store,
                     blob_id.clone(),
                     (offset / 1024) as u32,
                 ) {
@@ -461,6 +463,7 @@ impl AssetStore for FuturaAssetStore {
                         if asset.blob_ref.len <= 2 * 1024 * 1024 {
                             // Read the full blob
                             match crate::upload::blob_store::blob_read_chunk(
+                                &crate::upload::blob_store::StableBlobStore,
                                 asset.blob_ref.locator.clone(),
                                 0, // Read from beginning
                             ) {
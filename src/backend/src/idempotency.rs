@@ -0,0 +1,200 @@
+//! Idempotency-key registry
+//!
+//! Maps an idempotency key to the id of the resource it created, with the time
+//! it was recorded. A key is deduplicated only within its scope:
+//!
+//! - **caller + endpoint** for create endpoints (`memories_create`, ...): the same
+//!   key from two callers, or for two different endpoints, never collides.
+//! - Upload sessions keep their own `(capsule, caller, idem)` map in
+//!   `SessionCompat`, which lives only as long as the session.
+//!
+//! Records older than the TTL are ignored on lookup and removed by the periodic
+//! sweep, after which a repeated key is treated as new.
+
+use crate::memory::{MEM_IDEMPOTENCY, MEM_IDEM_TTL, MM};
+use crate::types::PersonRef;
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{
+    storable::Bound, DefaultMemoryImpl, StableBTreeMap, StableCell, Storable,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Default dedupe window: 24 hours (nanoseconds)
+pub const DEFAULT_IDEM_TTL_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+thread_local! {
+    // Keyed by sha256(endpoint, caller, key) so records have a fixed-size key
+    static STABLE_IDEM: RefCell<StableBTreeMap<[u8; 32], IdemRecord, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_IDEMPOTENCY)))
+    );
+
+    // Admin-set TTL, kept across upgrades
+    static IDEM_TTL_NS: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(MM.with(|m| m.borrow().get(MEM_IDEM_TTL)), DEFAULT_IDEM_TTL_NS)
+            .expect("Failed to init idempotency TTL")
+    );
+}
+
+/// Result recorded for an idempotency key
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct IdemRecord {
+    pub result: String, // id of the resource the key created
+    pub recorded_at: u64,
+}
+
+impl IdemRecord {
+    fn is_live(&self, now: u64, ttl: u64) -> bool {
+        now.saturating_sub(self.recorded_at) <= ttl
+    }
+}
+
+impl Storable for IdemRecord {
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 256,
+        is_fixed_size: false,
+    };
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode IdemRecord"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, record): (u16, IdemRecord) =
+            Decode!(bytes.as_ref(), (u16, IdemRecord)).expect("Failed to decode IdemRecord");
+        assert_eq!(version, 1, "Unsupported IdemRecord version");
+        record
+    }
+}
+
+/// Registry key for (endpoint, caller, key)
+fn scope_key(endpoint: &str, caller: &PersonRef, key: &str) -> [u8; 32] {
+    let mut h = Sha256::new();
+    h.update(endpoint.as_bytes());
+    h.update(b"#");
+    h.update(caller.to_string().as_bytes());
+    h.update(b"#");
+    h.update(key.as_bytes());
+    h.finalize().into()
+}
+
+/// Current TTL in nanoseconds
+pub fn ttl_ns() -> u64 {
+    IDEM_TTL_NS.with(|ttl| *ttl.borrow().get())
+}
+
+/// Change the TTL (admin); applies to existing records too
+pub fn set_ttl_ns(ttl: u64) {
+    IDEM_TTL_NS.with(|cell| {
+        cell.borrow_mut()
+            .set(ttl)
+            .expect("Failed to store idempotency TTL");
+    });
+}
+
+/// Result previously recorded for this key, if still within the TTL
+pub fn lookup(endpoint: &str, caller: &PersonRef, key: &str, now: u64) -> Option<String> {
+    let ttl = ttl_ns();
+    STABLE_IDEM
+        .with(|records| records.borrow().get(&scope_key(endpoint, caller, key)))
+        .filter(|record| record.is_live(now, ttl))
+        .map(|record| record.result)
+}
+
+/// Record the resource created for this key (overwrites an expired record)
+pub fn record(endpoint: &str, caller: &PersonRef, key: &str, result: &str, now: u64) {
+    STABLE_IDEM.with(|records| {
+        records.borrow_mut().insert(
+            scope_key(endpoint, caller, key),
+            IdemRecord {
+                result: result.to_string(),
+                recorded_at: now,
+            },
+        );
+    });
+}
+
+/// Remove records older than the TTL; returns how many were removed
+pub fn sweep_expired(now: u64) -> usize {
+    let ttl = ttl_ns();
+    STABLE_IDEM.with(|records| {
+        let mut records = records.borrow_mut();
+        let expired: Vec<[u8; 32]> = records
+            .iter()
+            .filter(|(_, record)| !record.is_live(now, ttl))
+            .map(|(key, _)| key)
+            .collect();
+        for key in &expired {
+            records.remove(key);
+        }
+        expired.len()
+    })
+}
+
+/// Number of stored records (for monitoring)
+#[allow(dead_code)]
+pub fn record_count() -> u64 {
+    STABLE_IDEM.with(|records| records.borrow().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+
+    const HOUR_NS: u64 = 60 * 60 * 1_000_000_000;
+
+    fn caller(byte: u8) -> PersonRef {
+        PersonRef::Principal(Principal::from_slice(&[byte; 29]))
+    }
+
+    #[test]
+    fn test_idem_record_storable_roundtrip() {
+        let record = IdemRecord {
+            result: "0000-mem".to_string(),
+            recorded_at: 42,
+        };
+        assert_eq!(IdemRecord::from_bytes(record.to_bytes()), record);
+    }
+
+    #[test]
+    fn test_lookup_within_ttl_and_after_expiry() {
+        record("memories_create", &caller(1), "k1", "mem_a", 0);
+
+        assert_eq!(
+            lookup("memories_create", &caller(1), "k1", HOUR_NS),
+            Some("mem_a".to_string())
+        );
+        assert_eq!(
+            lookup("memories_create", &caller(1), "k1", DEFAULT_IDEM_TTL_NS + 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_scope_is_caller_and_endpoint() {
+        record("memories_create", &caller(1), "k1", "mem_a", 0);
+
+        assert_eq!(lookup("memories_create", &caller(2), "k1", 0), None);
+        assert_eq!(lookup("galleries_create", &caller(1), "k1", 0), None);
+    }
+
+    #[test]
+    fn test_sweep_removes_only_expired_records() {
+        set_ttl_ns(HOUR_NS);
+        record("memories_create", &caller(1), "old", "mem_old", 0);
+        record("memories_create", &caller(1), "new", "mem_new", 2 * HOUR_NS);
+
+        assert_eq!(sweep_expired(2 * HOUR_NS), 1);
+        assert_eq!(record_count(), 1);
+        assert_eq!(
+            lookup("memories_create", &caller(1), "new", 2 * HOUR_NS),
+            Some("mem_new".to_string())
+        );
+    }
+}
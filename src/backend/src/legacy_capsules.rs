@@ -0,0 +1,178 @@
+//! Legacy capsule data carried across upgrades
+//!
+//! Before capsules lived in the stable store, `pre_upgrade` serialized them with
+//! `stable_save`, and `post_upgrade` still restores that blob for backward
+//! compatibility. Restored capsules are queued in stable memory and moved into
+//! the capsule store in batches of `MIGRATION_BATCH`, so capsules left over by
+//! `post_upgrade` show up in `legacy_capsule_count` (also after a later upgrade)
+//! and are moved by `admin_migrate_legacy_capsules`.
+
+use crate::capsule::domain::Capsule;
+use crate::capsule_store::CapsuleStore;
+use crate::memory::{with_capsule_store_mut, MEM_LEGACY_PENDING, MM};
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap};
+use serde::Serialize;
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Most legacy capsules moved per call, to stay within one message's instruction limit
+pub const MIGRATION_BATCH: usize = 500;
+
+thread_local! {
+    static STABLE_LEGACY_PENDING: RefCell<StableBTreeMap<String, Capsule, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_LEGACY_PENDING)))
+    );
+}
+
+/// Outcome of moving legacy capsules into the stable store
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct MigrationReport {
+    pub migrated: u32,
+    pub skipped: u32, // the store already held the capsule at the same or a newer version
+}
+
+/// Queue capsules restored from the legacy upgrade blob
+///
+/// A capsule already queued is only replaced by a newer copy.
+pub fn stash(capsules: Vec<(String, Capsule)>) {
+    STABLE_LEGACY_PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        for (id, capsule) in capsules {
+            let queued = pending.get(&id);
+            if queued.is_some_and(|queued| queued.updated_at >= capsule.updated_at) {
+                continue;
+            }
+            pending.insert(id, capsule);
+        }
+    });
+}
+
+/// Legacy capsules not yet moved into the stable store
+pub fn pending_count() -> u64 {
+    STABLE_LEGACY_PENDING.with(|pending| pending.borrow().len())
+}
+
+/// Move `legacy` capsules into `store`
+///
+/// Idempotent: a capsule the store already holds with the same or a newer
+/// `updated_at` is skipped, so stable data is never replaced by an older copy.
+/// Grants of moved capsules are indexed, as the one-time grant index backfill
+/// may already have run.
+pub fn migrate_into<S: CapsuleStore>(
+    store: &mut S,
+    legacy: Vec<(String, Capsule)>,
+) -> MigrationReport {
+    let mut report = MigrationReport::default();
+    for (id, capsule) in legacy {
+        let current = store.get(&id);
+        if current.is_some_and(|current| current.updated_at >= capsule.updated_at) {
+            report.skipped += 1;
+            continue;
+        }
+        for person in capsule.grantees() {
+            crate::grant_index::sync(&person, &id, true);
+        }
+        store.upsert(id, capsule);
+        report.migrated += 1;
+    }
+    report
+}
+
+/// Move up to `MIGRATION_BATCH` pending legacy capsules into the stable store
+pub fn migrate_pending() -> MigrationReport {
+    with_capsule_store_mut(|store| migrate_pending_into(store, MIGRATION_BATCH))
+}
+
+/// Move up to `limit` pending legacy capsules into `store`, dequeuing them
+fn migrate_pending_into<S: CapsuleStore>(store: &mut S, limit: usize) -> MigrationReport {
+    let batch: Vec<(String, Capsule)> =
+        STABLE_LEGACY_PENDING.with(|pending| pending.borrow().iter().take(limit).collect());
+    let ids: Vec<String> = batch.iter().map(|(id, _)| id.clone()).collect();
+    let report = migrate_into(store, batch);
+    STABLE_LEGACY_PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        for id in &ids {
+            pending.remove(id);
+        }
+    });
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capsule_store::Store;
+    use crate::test_utils::create_test_capsule_at;
+    use crate::types::PersonRef;
+    use candid::Principal;
+
+    #[test]
+    fn test_migrate_into_moves_missing_and_skips_current_capsules() {
+        let mut store = Store::new_stable_test();
+        let owner = PersonRef::Principal(Principal::anonymous());
+        store.upsert(
+            "current".to_string(),
+            create_test_capsule_at("current", owner.clone(), 10),
+        );
+
+        let legacy = vec![
+            (
+                "current".to_string(),
+                create_test_capsule_at("current", owner.clone(), 5),
+            ),
+            (
+                "missing".to_string(),
+                create_test_capsule_at("missing", owner, 5),
+            ),
+        ];
+        let report = migrate_into(&mut store, legacy.clone());
+        assert_eq!(
+            report,
+            MigrationReport {
+                migrated: 1,
+                skipped: 1,
+            }
+        );
+        assert_eq!(store.count(), 2);
+        assert_eq!(store.get(&"current".to_string()).unwrap().updated_at, 10);
+
+        // Running it again moves nothing
+        assert_eq!(
+            migrate_into(&mut store, legacy),
+            MigrationReport {
+                migrated: 0,
+                skipped: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_pending_capsules_stay_queued_until_migrated() {
+        let mut store = Store::new_stable_test();
+        let owner = PersonRef::Principal(Principal::anonymous());
+        stash(
+            ["a", "b", "c"]
+                .iter()
+                .map(|id| (id.to_string(), create_test_capsule_at(id, owner.clone(), 5)))
+                .collect(),
+        );
+        // An older copy of a queued capsule doesn't replace it
+        stash(vec![(
+            "a".to_string(),
+            create_test_capsule_at("a", owner.clone(), 1),
+        )]);
+        assert_eq!(pending_count(), 3);
+
+        let report = migrate_pending_into(&mut store, 2);
+        assert_eq!(report.migrated, 2);
+        assert_eq!(pending_count(), 1);
+        assert_eq!(store.get(&"a".to_string()).unwrap().updated_at, 5);
+
+        migrate_pending_into(&mut store, 2);
+        assert_eq!(pending_count(), 0);
+        assert_eq!(store.count(), 3);
+    }
+}
@@ -2,7 +2,7 @@
 use candid::Principal;
 use hex;
 use sha2::{Digest, Sha256};
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
 use std::collections::BTreeMap;
 
 // Internal imports
@@ -17,6 +17,8 @@ use crate::upload::types::{Result15, UploadFinishResult};
 // Rolling hash storage for upload verification
 thread_local! {
     static UPLOAD_HASH: RefCell<BTreeMap<u64, Sha256>> = RefCell::new(BTreeMap::new());
+    // Hash of the exported Candid; fixed for the lifetime of a build
+    static CANDID_INTERFACE_HASH: OnceCell<String> = const { OnceCell::new() };
 }
 
 // Import modules
@@ -26,13 +28,25 @@ mod canister_factory;
 pub mod capsule;
 pub mod capsule_acl;
 pub mod capsule_store;
+mod creation_delegations;
+mod events;
 mod folder;
 mod gallery;
+mod grant_index;
 mod http;
+mod idempotency;
+mod legacy_capsules;
+mod logging;
 pub mod memories;
 mod memory;
+mod memory_stats;
+mod memory_tombstones;
+mod metrics;
 mod person;
+mod person_registry;
 mod session;
+mod share_links;
+mod size;
 mod state;
 pub mod types;
 mod unified_types;
@@ -57,6 +71,12 @@ fn whoami() -> Principal {
     ic_cdk::api::msg_caller()
 }
 
+/// Caller principal, whether it is anonymous, and whether it has a capsule (one bootstrap call)
+#[ic_cdk::query]
+fn caller_identity() -> types::CallerIdentity {
+    capsule::query::caller_identity(ic_cdk::api::msg_caller())
+}
+
 #[ic_cdk::query]
 fn get_canister_size_stats() -> state::CanisterSizeStats {
     state::get_canister_size_stats()
@@ -82,7 +102,7 @@ fn verify_nonce(nonce: String) -> Result14 {
 }
 
 // ============================================================================
-// ADMINISTRATIVE FUNCTIONS (4 functions)
+// ADMINISTRATIVE FUNCTIONS (15 functions)
 // ============================================================================
 #[ic_cdk::update]
 fn add_admin(principal: Principal) -> std::result::Result<(), Error> {
@@ -94,23 +114,304 @@ fn remove_admin(principal: Principal) -> std::result::Result<(), Error> {
     admin::remove_admin(principal)
 }
 
+/// List admins with grant metadata (paginated by principal)
+#[ic_cdk::query]
+fn list_admins(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<crate::capsule_store::types::Page<admin::AdminEntry>, Error> {
+    admin::list_admins(cursor, limit)
+}
+
+/// List superadmins (paginated by principal)
+#[ic_cdk::query]
+fn list_superadmins(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<crate::capsule_store::types::Page<admin::AdminEntry>, Error> {
+    admin::list_superadmins(cursor, limit)
+}
+
+/// Legacy: list admin principals only (pre-pagination shape)
+#[ic_cdk::query]
+fn list_admin_principals() -> Vec<Principal> {
+    admin::list_admin_principals()
+}
+
+/// Legacy: list superadmin principals only (pre-pagination shape)
+#[ic_cdk::query]
+fn list_superadmin_principals() -> Vec<Principal> {
+    admin::list_superadmin_principals()
+}
+
+/// Set how long idempotency keys dedupe before a reused key counts as new (admin only, persisted)
+#[ic_cdk::update]
+fn idempotency_set_ttl(ttl_ns: u64) -> std::result::Result<(), Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+    if ttl_ns == 0 {
+        return Err(Error::InvalidArgument("ttl must be positive".to_string()));
+    }
+
+    idempotency::set_ttl_ns(ttl_ns);
+    Ok(())
+}
+
+/// Set how many inline assets a single memory may carry (admin only)
+#[ic_cdk::update]
+fn memories_set_max_inline_assets(max: u32) -> std::result::Result<(), Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+    if max == 0 {
+        return Err(Error::InvalidArgument("max must be positive".to_string()));
+    }
+
+    memories::core::update::set_max_inline_assets_per_memory(max);
+    Ok(())
+}
+
+/// Set the lowest log level that is written; quieter levels are dropped (admin only)
+#[ic_cdk::update]
+fn set_log_level(level: logging::LogLevel) -> std::result::Result<(), Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    logging::set_level(level);
+    Ok(())
+}
+
+/// Set the size above which full-object reads return ResponseTooLarge (admin only)
+#[ic_cdk::update]
+fn set_response_limit(limit_bytes: u64) -> std::result::Result<(), Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    utils::response_size::set_response_limit_bytes(limit_bytes)
+}
+
+/// Set how many live galleries a single capsule may hold (admin only)
+#[ic_cdk::update]
+fn galleries_set_max_per_capsule(max: u32) -> std::result::Result<(), Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+    if max == 0 {
+        return Err(Error::InvalidArgument("max must be positive".to_string()));
+    }
+
+    gallery::commands::set_max_galleries_per_capsule(max);
+    Ok(())
+}
+
+/// Current log level
+#[ic_cdk::query]
+fn get_log_level() -> logging::LogLevel {
+    logging::level()
+}
+
+/// Set whether new capsules start with advanced settings shown (admin only)
+#[ic_cdk::update]
+fn capsules_set_advanced_settings_default(enabled: bool) -> std::result::Result<(), Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    capsule::domain::set_advanced_settings_default(enabled);
+    Ok(())
+}
+
+/// Whether capsules created now start with advanced settings shown
 #[ic_cdk::query]
-fn list_admins() -> Vec<Principal> {
-    admin::list_admins()
+fn capsules_advanced_settings_default() -> bool {
+    capsule::domain::advanced_settings_default()
+}
+
+/// Force-abort another user's stuck upload session (admin only, audited)
+#[ic_cdk::update]
+fn admin_abort_session(session_id: u64) -> std::result::Result<(), Error> {
+    let caller = ic_cdk::api::msg_caller();
+    let sid = upload::types::SessionId(session_id);
+    let now = ic_cdk::api::time();
+    let aborted =
+        upload::service::admin_abort(&caller, |principal| admin::is_admin(principal, now), &sid)?;
+
+    // Drop the rolling hash so a wedged session leaves nothing behind
+    UPLOAD_HASH.with(|m| {
+        m.borrow_mut().remove(&session_id);
+    });
+
+    admin::record_admin_action(
+        caller,
+        now,
+        "abort_session",
+        format!(
+            "session {} (owner {}, capsule {})",
+            session_id, aborted.caller, aborted.capsule_id
+        ),
+    );
+    Ok(())
 }
 
+/// Find resources whose capsule_id disagrees with their containing capsule (admin only)
+///
+/// With `apply`, mismatches are rewritten to the containing capsule and audited.
+#[ic_cdk::update]
+fn admin_repair_capsule_ids(
+    apply: bool,
+) -> std::result::Result<Vec<capsule::api_types::CapsuleIdMismatch>, Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    let mismatches = capsule::util::check_capsule_ids(apply);
+    if apply && !mismatches.is_empty() {
+        admin::record_admin_action(
+            caller,
+            ic_cdk::api::time(),
+            "repair_capsule_ids",
+            format!("{} resources repointed", mismatches.len()),
+        );
+    }
+    Ok(mismatches)
+}
+
+/// Override a capsule's internal blob byte quota; `None` restores the default (admin only)
+#[ic_cdk::update]
+fn admin_set_blob_quota(
+    capsule_id: String,
+    quota_bytes: Option<u64>,
+) -> std::result::Result<(), Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    memory::with_capsule_store_mut(|store| {
+        store.update(&capsule_id, |capsule| capsule.blob_bytes_quota = quota_bytes)
+    })?;
+    admin::record_admin_action(
+        caller,
+        ic_cdk::api::time(),
+        "set_blob_quota",
+        format!("capsule {} quota {:?}", capsule_id, quota_bytes),
+    );
+    Ok(())
+}
+
+/// Canister-wide metrics for dashboards (admin only; see `metrics` for sampling)
+#[ic_cdk::query]
+fn metrics() -> std::result::Result<metrics::Metrics, Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    Ok(metrics::current())
+}
+
+/// Move the next batch of pending legacy capsules into the stable store (admin only, audited)
+#[ic_cdk::update]
+fn admin_migrate_legacy_capsules() -> std::result::Result<legacy_capsules::MigrationReport, Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    let report = legacy_capsules::migrate_pending();
+    if report.migrated > 0 {
+        admin::record_admin_action(
+            caller,
+            ic_cdk::api::time(),
+            "migrate_legacy_capsules",
+            format!("{} migrated, {} skipped", report.migrated, report.skipped),
+        );
+    }
+    Ok(report)
+}
+
+/// Legacy capsules restored on upgrade but not yet in the stable store (admin only)
 #[ic_cdk::query]
-fn list_superadmins() -> Vec<Principal> {
-    admin::list_superadmins()
+fn legacy_capsule_count() -> std::result::Result<u64, Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    Ok(legacy_capsules::pending_count())
+}
+
+/// Audit log of privileged admin actions (admin only, paginated by sequence)
+#[ic_cdk::query]
+fn admin_audit_log(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<crate::capsule_store::types::Page<admin::AdminAuditEntry>, Error> {
+    admin::list_admin_audit(cursor, limit)
 }
 
 // ============================================================================
 // CAPSULE MANAGEMENT (5 functions)
 // ============================================================================
 
+/// Create a capsule about the caller, or about `subject`
+///
+/// A principal subject other than the caller needs a `delegation` token minted
+/// by that subject for the caller; opaque subjects need none.
 #[ic_cdk::update]
-fn capsules_create(subject: Option<types::PersonRef>) -> std::result::Result<Capsule, Error> {
-    crate::capsule::commands::capsules_create(subject)
+fn capsules_create(
+    subject: Option<types::PersonRef>,
+    delegation: Option<String>,
+) -> std::result::Result<Capsule, Error> {
+    crate::capsule::commands::capsules_create(subject, delegation)
+}
+
+/// Authorize `creator` to create one capsule about the caller; returns the token to hand over
+#[ic_cdk::update]
+async fn capsules_delegate_creation(
+    creator: types::PersonRef,
+) -> std::result::Result<String, Error> {
+    let entropy = ic_cdk::management_canister::raw_rand()
+        .await
+        .map_err(|e| Error::Internal(format!("raw_rand failed: {:?}", e)))?;
+    let mut token_bytes = [0u8; 32];
+    token_bytes.copy_from_slice(&entropy[..32]);
+
+    creation_delegations::delegate(
+        types::PersonRef::from_caller(),
+        creator,
+        token_bytes,
+        ic_cdk::api::time(),
+    )
+}
+
+/// Create the caller's capsule (or reuse it) and optionally its first memory in one call
+#[ic_cdk::update]
+fn onboard(
+    subject: Option<types::PersonRef>,
+    first_memory: Option<crate::memories::types::MemoryCreateInput>,
+) -> std::result::Result<capsule::api_types::OnboardResult, Error> {
+    crate::capsule::commands::onboard_with(&memories::CanisterEnv, subject, first_memory)
+}
+
+/// Mint an opaque person id to use as the subject of a capsule about a non-user
+#[ic_cdk::update]
+fn persons_register_opaque(display_hint: Option<String>) -> std::result::Result<String, Error> {
+    person_registry::register_opaque(
+        types::PersonRef::from_caller(),
+        display_hint,
+        ic_cdk::api::time(),
+    )
 }
 
 #[ic_cdk::query]
@@ -123,10 +424,17 @@ fn capsules_read_basic(capsule_id: Option<String>) -> std::result::Result<Capsul
 
 #[ic_cdk::query]
 fn capsules_read_full(capsule_id: Option<String>) -> std::result::Result<Capsule, Error> {
-    match capsule_id {
+    let capsule = match capsule_id {
         Some(id) => crate::capsule::query::capsules_read(id),
         None => crate::capsule::query::capsule_read_self(),
-    }
+    }?;
+
+    // Fail with an actionable error instead of trapping on the response limit
+    utils::response_size::guard_response_size(
+        crate::capsule::util::calculate_capsule_size(&capsule),
+        "use capsules_read_basic for the capsule and memories_list/galleries_list for its contents",
+    )?;
+    Ok(capsule)
 }
 
 #[ic_cdk::update]
@@ -138,8 +446,28 @@ fn capsules_update(
 }
 
 #[ic_cdk::update]
-fn capsules_delete(capsule_id: String) -> std::result::Result<(), Error> {
-    crate::capsule::commands::capsules_delete(capsule_id)
+fn capsules_delete(
+    capsule_id: String,
+    delete_assets: bool,
+) -> std::result::Result<capsule::api_types::CapsuleDeleteReport, Error> {
+    crate::capsule::commands::capsules_delete(capsule_id, delete_assets)
+}
+
+/// Free the internal blobs of memories trashed at least `older_than` ns ago (owners only)
+#[ic_cdk::update]
+fn capsules_reclaim_trash_blobs(
+    capsule_id: String,
+    older_than: u64,
+) -> std::result::Result<capsule::api_types::ReclaimReport, Error> {
+    crate::capsule::commands::capsules_reclaim_trash_blobs(capsule_id, older_than)
+}
+
+/// Recompute a capsule's derived counters after an incident (owners and controllers only)
+#[ic_cdk::update]
+fn capsules_recompute_derived(
+    capsule_id: String,
+) -> std::result::Result<capsule::api_types::DerivedRecomputeReport, Error> {
+    crate::capsule::commands::capsules_recompute_derived(capsule_id)
 }
 
 #[ic_cdk::query]
@@ -147,6 +475,59 @@ fn capsules_list() -> Vec<CapsuleHeader> {
     crate::capsule::query::capsules_list()
 }
 
+/// Caller's capsules (owned or controlled) tagged `tag`
+#[ic_cdk::query]
+fn capsules_list_by_tag(tag: String) -> Vec<CapsuleHeader> {
+    crate::capsule::query::capsules_list_by_tag(tag)
+}
+
+/// Add tags to a capsule (owners only); returns the capsule's tags
+#[ic_cdk::update]
+fn capsules_add_tags(
+    capsule_id: String,
+    tags: Vec<String>,
+) -> std::result::Result<Vec<String>, Error> {
+    crate::capsule::commands::capsules_add_tags(capsule_id, tags)
+}
+
+/// Remove tags from a capsule (owners only); returns the capsule's tags
+#[ic_cdk::update]
+fn capsules_remove_tags(
+    capsule_id: String,
+    tags: Vec<String>,
+) -> std::result::Result<Vec<String>, Error> {
+    crate::capsule::commands::capsules_remove_tags(capsule_id, tags)
+}
+
+#[ic_cdk::query]
+fn capsules_shared_with_me() -> Vec<CapsuleHeader> {
+    crate::capsule::query::capsules_shared_with_me()
+}
+
+/// Headers for a batch of capsule IDs, in input order; `None` where missing or not viewable
+#[ic_cdk::query]
+fn capsules_headers(ids: Vec<String>) -> Vec<(String, Option<CapsuleHeader>)> {
+    crate::capsule::query::capsules_headers(ids)
+}
+
+/// Grantees, their strongest permissions and public resource counts of a capsule
+#[ic_cdk::query]
+fn capsules_sharing_summary(
+    capsule_id: String,
+) -> std::result::Result<capsule::api_types::SharingSummary, Error> {
+    crate::capsule::query::capsules_sharing_summary(capsule_id)
+}
+
+/// Internal blobs referenced by a capsule, with sizes and referencing memories
+#[ic_cdk::query]
+fn capsules_list_blobs(
+    capsule_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<capsule_store::types::Page<capsule::api_types::BlobSummary>, Error> {
+    crate::capsule::query::capsules_list_blobs(capsule_id, cursor, limit)
+}
+
 #[ic_cdk::update]
 fn capsules_bind_neon(
     resource_type: types::ResourceType,
@@ -169,7 +550,7 @@ fn update_user_settings(
 }
 
 // ============================================================================
-// GALLERY MANAGEMENT (7 functions)
+// GALLERY MANAGEMENT (10 functions)
 // ============================================================================
 #[ic_cdk::update]
 async fn galleries_create(
@@ -198,9 +579,9 @@ fn update_gallery_storage_location(
 }
 
 #[ic_cdk::query]
-fn galleries_list() -> Vec<types::GalleryHeader> {
+fn galleries_list(include_deleted: Option<bool>) -> Vec<types::GalleryHeader> {
     // Delegate to gallery module (thin facade)
-    gallery::query::galleries_list()
+    gallery::query::galleries_list(include_deleted)
 }
 
 #[ic_cdk::query]
@@ -221,6 +602,47 @@ fn galleries_read(gallery_id: String) -> std::result::Result<types::Gallery, Err
             .and_then(|capsule| capsule.galleries.get(&gallery_id).cloned())
             .ok_or(types::Error::NotFound)
     })
+    .and_then(|gallery| {
+        utils::response_size::guard_response_size(
+            size::estimate(&gallery),
+            "use galleries_read_meta for the header and galleries_read_items to page the items",
+        )?;
+        Ok(gallery)
+    })
+}
+
+/// Gallery metadata and cover without items (use galleries_read_items to page items)
+#[ic_cdk::query]
+fn galleries_read_meta(
+    gallery_id: String,
+) -> std::result::Result<gallery::api_types::GalleryMeta, Error> {
+    // Delegate to gallery module (thin facade)
+    gallery::query::galleries_read_meta(gallery_id)
+}
+
+/// Export a gallery with the asset locators of every member the caller can view
+#[ic_cdk::query]
+fn galleries_export(
+    gallery_id: String,
+) -> std::result::Result<gallery::api_types::GalleryManifest, Error> {
+    gallery::query::galleries_export(gallery_id).and_then(|manifest| {
+        utils::response_size::guard_response_size(
+            utils::response_size::encoded_size(&manifest),
+            "page the items with galleries_read_items and read each memory's assets",
+        )?;
+        Ok(manifest)
+    })
+}
+
+/// Page through a gallery's items by position
+#[ic_cdk::query]
+fn galleries_read_items(
+    gallery_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<capsule_store::types::Page<gallery::domain::GalleryItem>, Error> {
+    // Delegate to gallery module (thin facade)
+    gallery::query::galleries_read_items(gallery_id, cursor, limit)
 }
 
 #[ic_cdk::update]
@@ -238,6 +660,30 @@ async fn galleries_delete(gallery_id: String) -> std::result::Result<(), Error>
     gallery::commands::galleries_delete(gallery_id)
 }
 
+#[ic_cdk::update]
+fn galleries_restore(gallery_id: String) -> std::result::Result<types::Gallery, Error> {
+    gallery::commands::galleries_restore(gallery_id)
+}
+
+#[ic_cdk::update]
+fn galleries_move(
+    gallery_id: String,
+    target_capsule_id: String,
+    move_memories: bool,
+) -> std::result::Result<(), Error> {
+    // Delegate to gallery module (thin facade)
+    gallery::commands::galleries_move(gallery_id, target_capsule_id, move_memories)
+}
+
+#[ic_cdk::update]
+fn galleries_merge(
+    source_gallery_id: String,
+    target_gallery_id: String,
+) -> std::result::Result<gallery::api_types::GalleryMergeResult, Error> {
+    // Delegate to gallery module (thin facade)
+    gallery::commands::galleries_merge(source_gallery_id, target_gallery_id)
+}
+
 // ============================================================================
 // GALLERY UTILITY ENDPOINTS
 // ============================================================================
@@ -288,9 +734,24 @@ fn folders_delete(folder_id: String) -> std::result::Result<(), Error> {
     folder::commands::folders_delete(folder_id)
 }
 
+#[ic_cdk::update]
+fn folders_restore(folder_id: String) -> std::result::Result<types::Folder, Error> {
+    folder::commands::folders_restore(folder_id)
+}
+
+/// Move memories from one folder to another, reporting each memory's outcome
+#[ic_cdk::update]
+fn folders_move_memories(
+    source_folder_id: String,
+    target_folder_id: String,
+    memory_ids: Vec<String>,
+) -> std::result::Result<types::BulkResult<String>, Error> {
+    folder::commands::folders_move_memories(source_folder_id, target_folder_id, memory_ids)
+}
+
 #[ic_cdk::query]
-fn folders_list() -> Vec<types::FolderHeader> {
-    folder::query::folders_list()
+fn folders_list(include_deleted: Option<bool>) -> Vec<types::FolderHeader> {
+    folder::query::folders_list(include_deleted)
 }
 
 // ============================================================================
@@ -310,17 +771,16 @@ fn memories_create(
     external_hash: Option<Vec<u8>>,
     asset_metadata: types::AssetMetadata,
     idem: String,
+    options: Option<memories::types::MemoryCreateOptions>, // None keeps the defaults
 ) -> types::Result20 {
     use crate::memories::core::memories_create_core;
+    use crate::memories::types::MemoryCreateInput;
     use crate::memories::{CanisterEnv, StoreAdapter};
 
     let env = CanisterEnv;
     let mut store = StoreAdapter;
 
-    match memories_create_core(
-        &env,
-        &mut store,
-        capsule_id,
+    let input = MemoryCreateInput {
         bytes,
         blob_ref,
         external_location,
@@ -329,13 +789,119 @@ fn memories_create(
         external_size,
         external_hash,
         asset_metadata,
-        idem,
-    ) {
+    };
+    let options = options.unwrap_or_default();
+    match memories_create_core(&env, &mut store, capsule_id, input, idem, options) {
         Ok(memory_id) => types::Result20::Ok(memory_id),
         Err(error) => types::Result20::Err(error),
     }
 }
 
+/// Reserve a memory id ahead of its upload; complete it with `memories_attach_blob`
+#[ic_cdk::update]
+fn memories_reserve(
+    capsule_id: CapsuleId,
+    asset_metadata: types::AssetMetadata,
+    idem: String,
+) -> std::result::Result<types::MemoryId, Error> {
+    use crate::memories::core::memories_reserve_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    memories_reserve_core(&env, &mut store, capsule_id, asset_metadata, idem)
+}
+
+/// Attach a finished upload to a reserved memory, clearing its pending flag
+#[ic_cdk::update]
+fn memories_attach_blob(memory_id: String, blob_id: String) -> std::result::Result<String, Error> {
+    use crate::memories::core::memories_attach_blob_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+    use crate::upload::blob_store::BlobStore;
+
+    let blob_store = upload::blob_store::StableBlobStore;
+    let id = crate::utils::blob_id::parse_blob_id(&blob_id).map_err(Error::InvalidArgument)?;
+    let meta = blob_store.get_meta(id).ok_or(Error::NotFound)?;
+    let blob_ref = types::BlobRef {
+        locator: format!("blob_{}", id),
+        hash: Some(meta.checksum),
+        len: meta.size,
+    };
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    memories_attach_blob_core(&env, &mut store, memory_id, blob_ref, meta.capsule_id)
+}
+
+/// Swap the committed blob behind an internal asset in one call
+///
+/// The asset keeps its ID and order. `new_blob_id` must be a finished upload;
+/// the old blob is freed (and its bytes released) unless a duplicate still
+/// references it.
+#[ic_cdk::update]
+fn memories_replace_blob(
+    memory_id: String,
+    old_asset_id: String,
+    new_blob_id: String,
+) -> std::result::Result<(), Error> {
+    use crate::memories::core::memories_replace_blob_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+    use crate::upload::blob_store::BlobStore;
+    use crate::utils::blob_id::parse_blob_id;
+
+    let blob_store = upload::blob_store::StableBlobStore;
+    let id = parse_blob_id(&new_blob_id).map_err(Error::InvalidArgument)?;
+    // Blob metadata is only written once an upload is committed
+    let meta = blob_store.get_meta(id).ok_or(Error::NotFound)?;
+    let blob_ref = types::BlobRef {
+        locator: format!("blob_{}", id),
+        hash: Some(meta.checksum),
+        len: meta.size,
+    };
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+    let replaced = memories_replace_blob_core(
+        &env,
+        &mut store,
+        memory_id,
+        old_asset_id,
+        blob_ref,
+        meta.capsule_id,
+    )?;
+
+    let old_locator = replaced.locator.rsplit(':').next().unwrap_or_default();
+    if let Ok(old_id) = parse_blob_id(old_locator) {
+        memory::with_capsule_store_mut(|store| {
+            let ref_count = store.blob_ref_count(old_id);
+            if let Some(freed) =
+                capsule::util::free_blob_if_unreferenced(&blob_store, old_id, ref_count)
+            {
+                upload::service::release_blob_charge(store, &freed);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Create several memories in one call (inline budget checked for the whole batch)
+#[ic_cdk::update]
+fn memories_create_bulk(
+    capsule_id: CapsuleId,
+    items: Vec<crate::memories::types::MemoryCreateInput>,
+    idem: String,
+) -> std::result::Result<types::BulkResult<types::MemoryId>, Error> {
+    use crate::memories::core::memories_create_bulk_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    memories_create_bulk_core(&env, &mut store, capsule_id, items, idem)
+}
+
 #[ic_cdk::update]
 fn memories_create_with_internal_blobs(
     capsule_id: CapsuleId,
@@ -371,7 +937,89 @@ fn memories_read(memory_id: String) -> std::result::Result<types::Memory, Error>
     let store = StoreAdapter;
 
     // Get the full memory with all content
-    memories_read_core(&env, &store, memory_id)
+    let memory = memories_read_core(&env, &store, memory_id)?;
+    memory_stats::record(
+        &memory.capsule_id,
+        &memory.id,
+        memory_stats::AccessKind::View,
+        ic_cdk::api::time(),
+    );
+    Ok(memory)
+}
+
+/// Read a memory by the storage key of one of its external assets
+#[ic_cdk::query]
+fn memories_read_by_external_key(
+    capsule_id: String,
+    external_storage_key: String,
+) -> std::result::Result<types::Memory, Error> {
+    use crate::memories::core::memories_read_by_external_key_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let memory = memories_read_by_external_key_core(
+        &CanisterEnv,
+        &StoreAdapter,
+        capsule_id,
+        external_storage_key,
+    )?;
+    memory_stats::record(
+        &memory.capsule_id,
+        &memory.id,
+        memory_stats::AccessKind::View,
+        ic_cdk::api::time(),
+    );
+    Ok(memory)
+}
+
+/// View/download counters of a memory (owners and controllers of its capsule only)
+#[ic_cdk::query]
+fn memories_stats(memory_id: String) -> std::result::Result<memory_stats::MemoryStats, Error> {
+    use crate::memories::core::memories_read_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    // Read core only resolves memories in capsules the caller manages
+    let memory = memories_read_core(&CanisterEnv, &StoreAdapter, memory_id)?;
+    Ok(memory_stats::get(&memory.id))
+}
+
+/// Opt a capsule in or out of per-memory view/download counting
+#[ic_cdk::update]
+fn capsules_set_memory_stats(capsule_id: String, enabled: bool) -> std::result::Result<(), Error> {
+    let caller = types::PersonRef::from_caller();
+    let capsule = with_capsule_store(|store| store.get(&capsule_id)).ok_or(Error::NotFound)?;
+    if !capsule.has_write_access(&caller) {
+        return Err(Error::Unauthorized);
+    }
+    memory_stats::set_enabled(&capsule_id, enabled);
+    Ok(())
+}
+
+/// Copy a memory (sharing its stored blobs) into the target capsule, default same capsule
+#[ic_cdk::update]
+fn memories_duplicate(
+    memory_id: String,
+    target_capsule_id: Option<String>,
+) -> std::result::Result<types::MemoryId, Error> {
+    use crate::memories::core::memories_duplicate_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    memories_duplicate_core(&env, &mut store, memory_id, target_capsule_id)
+}
+
+#[ic_cdk::query]
+fn memories_storage_location(
+    memory_id: String,
+) -> std::result::Result<Vec<types::BlobHosting>, Error> {
+    use crate::memories::core::memories_storage_location_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let store = StoreAdapter;
+
+    memories_storage_location_core(&env, &store, memory_id)
 }
 
 #[ic_cdk::query]
@@ -387,55 +1035,42 @@ fn memories_read_asset(
 
     // Get the full memory first
     let memory = memories_read_core(&env, &store, memory_id)?;
+    memory_stats::record(
+        &memory.capsule_id,
+        &memory.id,
+        memory_stats::AccessKind::View,
+        ic_cdk::api::time(),
+    );
 
-    // Find the asset by index
-    let asset_index = asset_index as usize;
-
-    // Check inline assets first
-    if asset_index < memory.inline_assets.len() {
-        let asset = &memory.inline_assets[asset_index];
-        return Ok(types::MemoryAssetData::Inline {
-            bytes: asset.bytes.clone(),
-            content_type: asset.metadata.get_base().mime_type.clone(),
-            size: asset.bytes.len() as u64,
-            sha256: asset.metadata.get_base().sha256.map(|h| h.to_vec()),
-        });
-    }
+    // Find the asset by its position in display order
+    crate::memories::core::asset_data_at(&memory, asset_index, ic_cdk::api::time())
+}
 
-    // Check blob internal assets
-    let inline_count = memory.inline_assets.len();
-    if asset_index < inline_count + memory.blob_internal_assets.len() {
-        let blob_index = asset_index - inline_count;
-        let asset = &memory.blob_internal_assets[blob_index];
-        return Ok(types::MemoryAssetData::InternalBlob {
-            blob_id: asset.blob_ref.locator.clone(),
-            size: asset.blob_ref.len,
-            sha256: asset.blob_ref.hash.map(|h| h.to_vec()),
-        });
-    }
+/// Cache a short-lived signed URL for an external asset (index in display order)
+///
+/// `memories_read_asset` serves it until `expires_at`, then returns
+/// `SignatureExpired` so the client signs a new one.
+#[ic_cdk::update]
+fn memories_set_signed_url(
+    memory_id: String,
+    asset_index: u32,
+    url: String,
+    expires_at: u64,
+) -> std::result::Result<(), Error> {
+    use crate::memories::core::memories_set_signed_url_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
 
-    // Check blob external assets
-    let blob_internal_count = memory.blob_internal_assets.len();
-    if asset_index < inline_count + blob_internal_count + memory.blob_external_assets.len() {
-        let external_index = asset_index - inline_count - blob_internal_count;
-        let asset = &memory.blob_external_assets[external_index];
-        return Ok(types::MemoryAssetData::ExternalUrl {
-            url: asset.url.clone().unwrap_or_default(),
-            size: Some(asset.metadata.get_base().bytes),
-            sha256: asset.metadata.get_base().sha256.map(|h| h.to_vec()),
-        });
-    }
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
 
-    Err(Error::InvalidArgument(format!(
-        "Asset index {} out of range",
-        asset_index
-    )))
+    memories_set_signed_url_core(&env, &mut store, memory_id, asset_index, url, expires_at)
 }
 
 #[ic_cdk::update]
 fn memories_update(
     memory_id: String,
     updates: types::MemoryUpdateData,
+    expected_updated_at: Option<u64>,
 ) -> std::result::Result<types::Memory, Error> {
     use crate::memories::core::memories_update_core;
     use crate::memories::{CanisterEnv, StoreAdapter};
@@ -443,7 +1078,76 @@ fn memories_update(
     let env = CanisterEnv;
     let mut store = StoreAdapter;
 
-    memories_update_core(&env, &mut store, memory_id, updates)
+    // expected_updated_at enables compare-and-swap; None keeps last-writer-wins
+    memories_update_core(&env, &mut store, memory_id, updates, expected_updated_at)
+}
+
+#[ic_cdk::update]
+fn memories_set_locked(
+    memory_id: String,
+    locked: bool,
+) -> std::result::Result<types::Memory, Error> {
+    use crate::memories::core::memories_set_locked_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    // Owner-only; while locked, edits, deletes and asset removals fail with Locked
+    memories_set_locked_core(&env, &mut store, memory_id, locked)
+}
+
+#[ic_cdk::update]
+fn memories_set_expiry(
+    memory_id: String,
+    expires_at: Option<u64>,
+) -> std::result::Result<types::Memory, Error> {
+    use crate::memories::core::memories_set_expiry_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    // Owner-only; None clears the expiry. The maintenance sweep deletes expired memories
+    memories_set_expiry_core(&env, &mut store, memory_id, expires_at)
+}
+
+#[ic_cdk::update]
+fn memories_reorder_assets(
+    memory_id: String,
+    ordered_asset_ids: Vec<String>,
+) -> std::result::Result<types::Memory, Error> {
+    use crate::memories::core::memories_reorder_assets_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    // The first listed asset becomes the primary one
+    memories_reorder_assets_core(&env, &mut store, memory_id, ordered_asset_ids)
+}
+
+#[ic_cdk::update]
+async fn memories_create_share_link(
+    memory_id: String,
+    condition: Option<capsule::domain::AccessCondition>,
+) -> std::result::Result<String, Error> {
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let entropy = ic_cdk::management_canister::raw_rand()
+        .await
+        .map_err(|e| Error::Internal(format!("raw_rand failed: {:?}", e)))?;
+    let mut token_bytes = [0u8; 32];
+    token_bytes.copy_from_slice(&entropy[..32]);
+
+    // Defaults to expiring after the magic link TTL
+    share_links::create_link(&CanisterEnv, &StoreAdapter, memory_id, condition, token_bytes)
+}
+
+#[ic_cdk::query]
+fn public_resolve(token: String) -> std::result::Result<share_links::PublicResourceView, Error> {
+    // No caller check: holding the token is the permission
+    share_links::resolve(&memories::StoreAdapter, &token, ic_cdk::api::time())
 }
 
 #[ic_cdk::update]
@@ -483,14 +1187,24 @@ fn memories_add_inline_asset(
 }
 
 #[ic_cdk::update]
-fn memories_delete(memory_id: String, delete_assets: bool) -> std::result::Result<(), Error> {
+fn memories_delete(
+    memory_id: String,
+    delete_assets: bool,
+    force: Option<bool>,
+) -> std::result::Result<Vec<String>, Error> {
     use crate::memories::core::memories_delete_core;
     use crate::memories::{CanisterEnv, StoreAdapter};
 
     let env = CanisterEnv;
     let mut store = StoreAdapter;
 
-    memories_delete_core(&env, &mut store, memory_id, delete_assets)
+    memories_delete_core(
+        &env,
+        &mut store,
+        memory_id,
+        delete_assets,
+        force.unwrap_or(false),
+    )
 }
 
 #[ic_cdk::query]
@@ -503,9 +1217,10 @@ fn memories_list(
     use crate::memories::utils::generate_asset_links_for_memory_header;
     use crate::memory::with_capsule_store;
     use crate::types::PersonRef;
+    use crate::utils::pagination::paginate_sorted;
 
     let caller = PersonRef::from_caller();
-    let limit = limit.unwrap_or(50).min(100); // Default 50, max 100
+    let limit = limit.unwrap_or(50).clamp(1, 100); // Default 50, max 100
 
     with_capsule_store(|store| {
         store
@@ -523,22 +1238,14 @@ fn memories_list(
                         })
                         .collect();
 
-                    // Simple pagination implementation
-                    let start_idx = cursor.and_then(|c| c.parse::<usize>().ok()).unwrap_or(0);
-
-                    let end_idx = (start_idx + limit as usize).min(memories_with_asset_links.len());
-                    let page_items = memories_with_asset_links[start_idx..end_idx].to_vec();
-
-                    let next_cursor = if end_idx < memories_with_asset_links.len() {
-                        Some(end_idx.to_string())
-                    } else {
-                        None
-                    };
-
-                    Some(crate::capsule_store::types::Page {
-                        items: page_items,
-                        next_cursor,
-                    })
+                    // Keyset pagination by memory id (cursor = last id of the previous page)
+                    Some(paginate_sorted(
+                        memories_with_asset_links,
+                        |header| header.id.clone(),
+                        cursor,
+                        limit,
+                        Order::Asc,
+                    ))
                 } else {
                     None
                 }
@@ -558,9 +1265,10 @@ fn memories_list_by_capsule(
     use crate::memories::utils::generate_asset_links_for_memory_header;
     use crate::memory::with_capsule_store;
     use crate::types::PersonRef;
+    use crate::utils::pagination::paginate_sorted;
 
     let caller = PersonRef::from_caller();
-    let limit = limit.unwrap_or(50).min(100); // Default 50, max 100
+    let limit = limit.unwrap_or(50).clamp(1, 100); // Default 50, max 100
 
     with_capsule_store(|store| {
         store
@@ -578,21 +1286,14 @@ fn memories_list_by_capsule(
                         })
                         .collect();
 
-                    // Pagination logic
-                    let start_idx = cursor.and_then(|c| c.parse::<usize>().ok()).unwrap_or(0);
-                    let end_idx = (start_idx + limit as usize).min(memories_with_asset_links.len());
-                    let page_items = memories_with_asset_links[start_idx..end_idx].to_vec();
-
-                    let next_cursor = if end_idx < memories_with_asset_links.len() {
-                        Some(end_idx.to_string())
-                    } else {
-                        None
-                    };
-
-                    Some(crate::capsule_store::types::Page {
-                        items: page_items,
-                        next_cursor,
-                    })
+                    // Keyset pagination by memory id (cursor = last id of the previous page)
+                    Some(paginate_sorted(
+                        memories_with_asset_links,
+                        |header| header.id.clone(),
+                        cursor,
+                        limit,
+                        Order::Asc,
+                    ))
                 } else {
                     None
                 }
@@ -627,9 +1328,22 @@ fn upload_config() -> types::UploadConfig {
 
 /// Begin chunked upload for large files
 #[ic_cdk::update]
-fn uploads_begin(capsule_id: CapsuleId, expected_chunks: u32, idem: String) -> Result13 {
+fn uploads_begin(
+    capsule_id: CapsuleId,
+    expected_chunks: u32,
+    idem: String,
+    expected_sha256: Option<Vec<u8>>,
+    chunk_size: Option<u32>,
+) -> Result13 {
     match with_capsule_store_mut(|store| {
-        upload::service::begin_upload(store, capsule_id, expected_chunks, idem)
+        upload::service::begin_upload(
+            store,
+            capsule_id,
+            expected_chunks,
+            idem,
+            expected_sha256,
+            chunk_size,
+        )
     }) {
         Ok(session_id) => {
             let sid = session_id.0;
@@ -637,7 +1351,7 @@ fn uploads_begin(capsule_id: CapsuleId, expected_chunks: u32, idem: String) -> R
             UPLOAD_HASH.with(|m| {
                 m.borrow_mut().insert(sid, Sha256::new());
             });
-            ic_cdk::println!("UPLOAD_HASH_INIT sid={}", sid);
+            logging::info("UPLOAD_HASH_INIT", &[("sid", &sid)]);
             Result13::Ok(sid)
         }
         Err(error) => Result13::Err(error),
@@ -650,20 +1364,17 @@ async fn uploads_put_chunk(
     session_id: u64,
     chunk_idx: u32,
     bytes: Vec<u8>,
+    byte_offset: Option<u64>,
 ) -> std::result::Result<(), Error> {
     // Breadcrumb logging: log what we receive from Candid
-    let hex = bytes
-        .iter()
-        .take(8)
-        .map(|b| format!("{:02x}", b))
-        .collect::<Vec<_>>()
-        .join(" ");
-    ic_cdk::println!(
-        "PUT_CHUNK_RECV: session_id={}, chunk_idx={}, data_len={}, prefix=[{}]",
-        session_id,
-        chunk_idx,
-        bytes.len(),
-        hex
+    logging::debug(
+        "PUT_CHUNK_RECV",
+        &[
+            ("sid", &session_id),
+            ("chunk_idx", &chunk_idx),
+            ("data_len", &bytes.len()),
+            ("prefix", &hex::encode(&bytes[..bytes.len().min(8)])),
+        ],
     );
 
     // Update rolling hash FIRST (before writing)
@@ -679,107 +1390,589 @@ async fn uploads_put_chunk(
         Err(e) => return Err(e),
     }
 
-    // Then write chunk to storage
-    memory::with_capsule_store_mut(|store| {
-        let session_id = upload::types::SessionId(session_id);
-        upload::service::put_chunk(store, &session_id, chunk_idx, bytes)
+    // Then write chunk to storage
+    memory::with_capsule_store_mut(|store| {
+        let session_id = upload::types::SessionId(session_id);
+        let caller = ic_cdk::api::msg_caller();
+        upload::service::put_chunk(store, &session_id, caller, chunk_idx, bytes, byte_offset)
+    })
+}
+
+/// Check a finishing upload against the client's hash before anything is committed
+///
+/// Consumes the rolling hash, except when chunks are missing so finish can be retried.
+fn verified_finish_hash(
+    session_id: u64,
+    expected_sha256: &[u8],
+    total_len: u64,
+) -> std::result::Result<[u8; 32], Error> {
+    // A capsule deleted since begin makes the hash path meaningless - report it first
+    let blob_store = upload::blob_store::StableBlobStore;
+    if let Err(e) = memory::with_capsule_store(|store| {
+        upload::service::abandon_if_capsule_gone(
+            store,
+            &blob_store,
+            &upload::types::SessionId(session_id),
+        )
+    }) {
+        UPLOAD_HASH.with(|m| {
+            m.borrow_mut().remove(&session_id);
+        });
+        logging::error("FINISH_ERROR", &[("sid", &session_id), ("err", &e)]);
+        return Err(e);
+    }
+
+    // Report missing chunks before consuming the rolling hash, so finish can be retried
+    if let Err(e) = upload::service::ensure_chunks_complete(&upload::types::SessionId(session_id))
+    {
+        logging::error("FINISH_ERROR", &[("sid", &session_id), ("err", &e)]);
+        return Err(e);
+    }
+
+    // Verify rolling hash (before any other operations)
+    let computed_hash = match UPLOAD_HASH.with(|m| {
+        if let Some(hasher) = m.borrow_mut().remove(&session_id) {
+            Ok(hasher.finalize().to_vec())
+        } else {
+            Err(Error::NotFound)
+        }
+    }) {
+        Ok(hash) => hash,
+        Err(e) => {
+            logging::error(
+                "FINISH_ERROR",
+                &[("sid", &session_id), ("err", &"hash_not_found")],
+            );
+            return Err(e);
+        }
+    };
+
+    // Compare with client's expected hash
+    if computed_hash != expected_sha256 {
+        logging::error(
+            "FINISH_ERROR",
+            &[
+                ("sid", &session_id),
+                ("err", &"checksum_mismatch"),
+                ("computed", &hex::encode(&computed_hash[..8])),
+                ("expected", &hex::encode(&expected_sha256[..expected_sha256.len().min(8)])),
+            ],
+        );
+        return Err(Error::ChecksumMismatch {
+            computed_hex: hex::encode(&computed_hash),
+            expected_hex: hex::encode(expected_sha256),
+        });
+    }
+
+    logging::info("FINISH_HASH_OK", &[("sid", &session_id), ("len", &total_len)]);
+
+    match expected_sha256.try_into() {
+        Ok(h) => Ok(h),
+        Err(_) => {
+            logging::error(
+                "FINISH_ERROR",
+                &[
+                    ("sid", &session_id),
+                    ("err", &"invalid_hash_length"),
+                    ("got", &expected_sha256.len()),
+                ],
+            );
+            Err(types::Error::InvalidArgument(format!(
+                "invalid_hash_length: expected 32 bytes, got {}",
+                expected_sha256.len()
+            )))
+        }
+    }
+}
+
+/// Commit chunks to create final memory
+///
+/// With `with_receipt = Some(true)` the result carries a `commit_receipt` with
+/// per-chunk byte counts and the assembled length as stored.
+#[ic_cdk::update]
+async fn uploads_finish(
+    session_id: u64,
+    expected_sha256: Vec<u8>,
+    total_len: u64,
+    with_receipt: Option<bool>,
+) -> Result15 {
+    logging::info(
+        "FINISH_START",
+        &[("sid", &session_id), ("expected_len", &total_len)],
+    );
+
+    let hash = match verified_finish_hash(session_id, &expected_sha256, total_len) {
+        Ok(hash) => hash,
+        Err(e) => return Result15::Err(e),
+    };
+    let blob_store = upload::blob_store::StableBlobStore;
+
+    memory::with_capsule_store_mut(|store| {
+        let session_id = upload::types::SessionId(session_id);
+        match upload::service::commit(
+            store,
+            &blob_store,
+            session_id,
+            ic_cdk::api::msg_caller(),
+            hash,
+            total_len,
+            ic_cdk::api::time(),
+        ) {
+            Ok(outcome) => {
+                let blob_id = outcome.blob_id;
+                logging::info(
+                    "FINISH_BLOB_COMMITTED",
+                    &[
+                        ("sid", &session_id.0),
+                        ("blob", &blob_id),
+                        ("dedup", &outcome.was_deduplicated),
+                    ],
+                );
+
+                // Opt-in: reading every page back costs instructions and response size
+                let commit_receipt = if with_receipt.unwrap_or(false) {
+                    match upload::service::commit_receipt(&blob_store, &blob_id) {
+                        Ok(receipt) => Some(Box::new(receipt)),
+                        Err(err) => {
+                            logging::warn(
+                                "FINISH_RECEIPT_ERROR",
+                                &[("sid", &session_id.0), ("err", &err)],
+                            );
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                let result = UploadFinishResult {
+                    memory_id: "".to_string(), // No memory created - separate concern
+                    blob_id: blob_id.clone(),
+                    remote_id: None,
+                    size: total_len,
+                    checksum_sha256: Some(hash),
+                    storage_backend: upload::types::StorageBackend::Icp,
+                    storage_location: format!("icp://blob/{}", blob_id),
+                    uploaded_at: ic_cdk::api::time(),
+                    expires_at: None,
+                    commit_receipt,
+                    was_deduplicated: outcome.was_deduplicated,
+                };
+
+                logging::info("FINISH_OK", &[("sid", &session_id.0)]);
+                Result15::Ok(result)
+            }
+            Err(err) => {
+                logging::error("FINISH_ERROR", &[("sid", &session_id.0), ("err", &err)]);
+                Result15::Err(err)
+            }
+        }
+    })
+}
+
+/// Finish an upload straight into a gallery: commit the blob, create its memory, add it
+///
+/// `gallery` names an existing gallery, or a title for a new one (later uploads pass
+/// the returned `gallery_id`). The session and gallery are checked before committing;
+/// if the memory or gallery step still fails, what this call created is removed again:
+/// a memory an earlier call with the same `idem` created, and any blob a memory
+/// still uses, are kept.
+#[ic_cdk::update]
+async fn uploads_finish_into_gallery(
+    session_id: u64,
+    expected_sha256: Vec<u8>,
+    total_len: u64,
+    capsule_id: CapsuleId,
+    asset_metadata: types::AssetMetadata,
+    gallery: gallery::api_types::GalleryTarget,
+    idem: String,
+) -> std::result::Result<upload::types::GalleryUploadResult, Error> {
+    use crate::memories::core::memories_create_and_place_core;
+    use crate::memories::core::traits::Store;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    logging::info(
+        "FINISH_START",
+        &[("sid", &session_id), ("expected_len", &total_len)],
+    );
+    let sid = upload::types::SessionId(session_id);
+    if upload::service::pending_session_capsule(&sid, ic_cdk::api::msg_caller())? != capsule_id {
+        return Err(Error::InvalidArgument(
+            "upload session belongs to another capsule".to_string(),
+        ));
+    }
+    let max_galleries = gallery::commands::max_galleries_per_capsule();
+    memory::with_capsule_store(|store| {
+        let capsule = store.get(&capsule_id).ok_or(Error::NotFound)?;
+        gallery::commands::check_gallery_target(&capsule, &gallery, max_galleries)
+    })?;
+
+    let hash = verified_finish_hash(session_id, &expected_sha256, total_len)?;
+    let blob_store = upload::blob_store::StableBlobStore;
+    let outcome = memory::with_capsule_store_mut(|store| {
+        upload::service::commit(
+            store,
+            &blob_store,
+            sid,
+            ic_cdk::api::msg_caller(),
+            hash,
+            total_len,
+            ic_cdk::api::time(),
+        )
+    })
+    .inspect_err(|err| logging::error("FINISH_ERROR", &[("sid", &session_id), ("err", err)]))?;
+    let blob_id = outcome.blob_id;
+    logging::info(
+        "FINISH_BLOB_COMMITTED",
+        &[
+            ("sid", &session_id),
+            ("blob", &blob_id),
+            ("dedup", &outcome.was_deduplicated),
+        ],
+    );
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+    let now = ic_cdk::api::time();
+    let blob_ref = types::BlobRef {
+        locator: blob_id.clone(),
+        hash: Some(hash),
+        len: total_len,
+    };
+    let landed = memories_create_and_place_core(
+        &env,
+        &mut store,
+        capsule_id.clone(),
+        blob_ref,
+        asset_metadata,
+        idem.clone(),
+        |store, memory_id| {
+            let memory_type = store
+                .get_memory(&capsule_id, memory_id)
+                .ok_or(Error::NotFound)?
+                .metadata
+                .memory_type;
+            memory::with_capsule_store_mut(|capsules| {
+                capsules.update_with(&capsule_id, |capsule| {
+                    gallery::commands::add_memory_to_gallery(
+                        capsule,
+                        &gallery,
+                        memory_id,
+                        memory_type,
+                        &idem,
+                        now,
+                        max_galleries,
+                    )
+                })
+            })
+        },
+    );
+
+    let (memory_id, gallery_id) = match landed {
+        Ok(landed) => landed,
+        Err(err) => {
+            // Roll back the commit: drop the blob and give its bytes back, unless a
+            // memory uses it (a deduplicated blob, or an earlier call's memory)
+            if let Ok(id) = crate::utils::blob_id::parse_blob_id(&blob_id) {
+                memory::with_capsule_store_mut(|store| {
+                    let ref_count = store.blob_ref_count(id);
+                    if let Some(meta) =
+                        capsule::util::free_blob_if_unreferenced(&blob_store, id, ref_count)
+                    {
+                        upload::service::release_blob_charge(store, &meta);
+                    }
+                });
+            }
+            logging::error("FINISH_ERROR", &[("sid", &session_id), ("err", &err)]);
+            return Err(err);
+        }
+    };
+
+    logging::info(
+        "FINISH_OK",
+        &[("sid", &session_id), ("memory", &memory_id), ("gallery", &gallery_id)],
+    );
+    Ok(upload::types::GalleryUploadResult {
+        upload: UploadFinishResult {
+            memory_id,
+            blob_id: blob_id.clone(),
+            remote_id: None,
+            size: total_len,
+            checksum_sha256: Some(hash),
+            storage_backend: upload::types::StorageBackend::Icp,
+            storage_location: format!("icp://blob/{}", blob_id),
+            uploaded_at: now,
+            expires_at: None,
+            commit_receipt: None,
+            was_deduplicated: outcome.was_deduplicated,
+        },
+        gallery_id,
     })
 }
 
-/// Commit chunks to create final memory
+/// Report how long the last chunk took and get a suggested chunk size for the next upload
 #[ic_cdk::update]
-async fn uploads_finish(session_id: u64, expected_sha256: Vec<u8>, total_len: u64) -> Result15 {
-    ic_cdk::println!("FINISH_START sid={} expected_len={}", session_id, total_len);
+fn uploads_suggest_chunk_size(
+    session_id: u64,
+    last_chunk_ms: u32,
+) -> std::result::Result<u32, Error> {
+    upload::service::suggest_chunk_size(
+        &upload::types::SessionId(session_id),
+        ic_cdk::api::msg_caller(),
+        last_chunk_ms,
+    )
+}
 
-    // Verify rolling hash FIRST (before any other operations)
-    let computed_hash = match UPLOAD_HASH.with(|m| {
-        if let Some(hasher) = m.borrow_mut().remove(&session_id) {
-            Ok(hasher.finalize().to_vec())
-        } else {
-            Err(Error::NotFound)
-        }
-    }) {
-        Ok(hash) => hash,
-        Err(e) => {
-            ic_cdk::println!("FINISH_ERROR sid={} err=hash_not_found", session_id);
-            return Result15::Err(e);
-        }
-    };
+/// Rebuild the rolling hash of an in-flight upload from its stored chunks
+///
+/// Recovers sessions begun before an upgrade (which clears `UPLOAD_HASH`) so they
+/// can keep uploading and finish normally.
+#[ic_cdk::update]
+fn uploads_rehydrate_hash(session_id: u64) -> std::result::Result<(), Error> {
+    let blob_store = upload::blob_store::StableBlobStore;
+    let hasher = upload::service::rehydrate_hash(
+        &blob_store,
+        upload::types::SessionId(session_id),
+        ic_cdk::api::msg_caller(),
+    )?;
+
+    UPLOAD_HASH.with(|m| {
+        m.borrow_mut().insert(session_id, hasher);
+    });
+    logging::info("UPLOAD_HASH_REHYDRATED", &[("sid", &session_id)]);
+    Ok(())
+}
 
-    // Compare with client's expected hash
-    if computed_hash != expected_sha256 {
-        ic_cdk::println!(
-            "FINISH_ERROR sid={} err=checksum_mismatch computed={:?} expected={:?}",
-            session_id,
-            &computed_hash[..8],
-            &expected_sha256[..8]
-        );
-        return Result15::Err(Error::InvalidArgument(format!(
-            "checksum_mismatch: computed={}, expected={}",
-            hex::encode(&computed_hash),
-            hex::encode(&expected_sha256)
-        )));
+/// Check an in-flight upload's rolling hash against its stored chunks (debug)
+///
+/// With `repair`, a mismatching accumulator is replaced by the hash of the
+/// stored chunks in index order, so finish checks what was actually stored.
+#[ic_cdk::update]
+fn uploads_verify_hash(
+    session_id: u64,
+    repair: bool,
+) -> std::result::Result<upload::types::HashVerifyReport, Error> {
+    let blob_store = upload::blob_store::StableBlobStore;
+    let rolling = UPLOAD_HASH.with(|m| m.borrow().get(&session_id).cloned());
+    let (mut report, stored) = upload::service::verify_rolling_hash(
+        &blob_store,
+        upload::types::SessionId(session_id),
+        ic_cdk::api::msg_caller(),
+        rolling.as_ref(),
+    )?;
+
+    if !report.matches {
+        logging::warn("UPLOAD_HASH_MISMATCH", &[("sid", &session_id)]);
+        if repair {
+            UPLOAD_HASH.with(|m| {
+                m.borrow_mut().insert(session_id, stored);
+            });
+            report.repaired = true;
+            logging::info("UPLOAD_HASH_REPAIRED", &[("sid", &session_id)]);
+        }
     }
+    Ok(report)
+}
 
-    ic_cdk::println!("FINISH_HASH_OK sid={} len={}", session_id, total_len);
+/// Inspect which chunks of a pending upload are stored (e.g. after a failed finish)
+#[ic_cdk::query]
+fn uploads_inspect_partial(
+    session_id: u64,
+) -> std::result::Result<upload::types::PartialBlobReport, Error> {
+    let blob_store = upload::blob_store::StableBlobStore;
+    upload::service::inspect_partial(
+        &blob_store,
+        upload::types::SessionId(session_id),
+        ic_cdk::api::msg_caller(),
+    )
+}
 
-    // Use functional upload service
-    let hash: [u8; 32] = match expected_sha256.clone().try_into() {
+/// Retry a failed commit against already-stored chunks with a corrected hash
+#[ic_cdk::update]
+async fn uploads_recommit(
+    session_id: u64,
+    corrected_expected_sha256: Vec<u8>,
+    total_len: u64,
+) -> Result15 {
+    let hash: [u8; 32] = match corrected_expected_sha256.clone().try_into() {
         Ok(h) => h,
         Err(_) => {
-            ic_cdk::println!(
-                "FINISH_ERROR sid={} err=invalid_hash_length got={}",
-                session_id,
-                expected_sha256.len()
-            );
             return Result15::Err(types::Error::InvalidArgument(format!(
                 "invalid_hash_length: expected 32 bytes, got {}",
-                expected_sha256.len()
+                corrected_expected_sha256.len()
             )));
         }
     };
 
-    memory::with_capsule_store_mut(|store| {
-        let session_id = upload::types::SessionId(session_id);
-        match upload::service::commit(store, session_id, hash, total_len) {
-            Ok(blob_id) => {
-                ic_cdk::println!(
-                    "FINISH_BLOB_COMMITTED sid={} blob={}",
-                    session_id.0,
-                    blob_id
-                );
-
-                let result = UploadFinishResult {
-                    memory_id: "".to_string(), // No memory created - separate concern
-                    blob_id: blob_id.clone(),
-                    remote_id: None,
-                    size: total_len,
-                    checksum_sha256: Some(hash),
-                    storage_backend: upload::types::StorageBackend::Icp,
-                    storage_location: format!("icp://blob/{}", blob_id),
-                    uploaded_at: ic_cdk::api::time(),
-                    expires_at: None,
-                };
+    // The rolling hash (if still present) is stale once we rehash from storage
+    UPLOAD_HASH.with(|m| {
+        m.borrow_mut().remove(&session_id);
+    });
 
-                ic_cdk::println!("FINISH_OK sid={}", session_id.0);
-                Result15::Ok(result)
-            }
-            Err(err) => {
-                ic_cdk::println!("FINISH_ERROR sid={} err={:?}", session_id.0, err);
-                Result15::Err(err)
-            }
+    let blob_store = upload::blob_store::StableBlobStore;
+    let now = ic_cdk::api::time();
+    match memory::with_capsule_store_mut(|store| {
+        upload::service::recommit(
+            store,
+            &blob_store,
+            upload::types::SessionId(session_id),
+            ic_cdk::api::msg_caller(),
+            hash,
+            total_len,
+            now,
+        )
+    }) {
+        Ok(outcome) => {
+            let blob_id = outcome.blob_id;
+            logging::info("RECOMMIT_OK", &[("sid", &session_id), ("blob", &blob_id)]);
+            Result15::Ok(UploadFinishResult {
+                memory_id: "".to_string(), // No memory created - separate concern
+                blob_id: blob_id.clone(),
+                remote_id: None,
+                size: total_len,
+                checksum_sha256: Some(hash),
+                storage_backend: upload::types::StorageBackend::Icp,
+                storage_location: format!("icp://blob/{}", blob_id),
+                uploaded_at: now,
+                expires_at: None,
+                commit_receipt: None,
+                was_deduplicated: outcome.was_deduplicated,
+            })
         }
-    })
+        Err(err) => {
+            ic_cdk::println!("RECOMMIT_ERROR sid={} err={:?}", session_id, err);
+            Result15::Err(err)
+        }
+    }
 }
 
 /// Abort upload session and cleanup
 #[ic_cdk::update]
 async fn uploads_abort(session_id: u64) -> std::result::Result<(), Error> {
-    // Use functional upload service
+    // Use functional upload service (absent sessions count as already aborted)
     memory::with_capsule_store_mut(|store| {
-        let session_id = upload::types::SessionId(session_id);
-        upload::service::abort(store, session_id)
-    })
+        upload::service::abort(
+            store,
+            upload::types::SessionId(session_id),
+            ic_cdk::api::msg_caller(),
+        )
+    })?;
+
+    UPLOAD_HASH.with(|m| {
+        m.borrow_mut().remove(&session_id);
+    });
+    Ok(())
+}
+
+// ============================================================================
+// ACCESS GRANTS & EVENT FEED (4 functions)
+// ============================================================================
+
+/// Grant a person access to a memory, gallery or folder
+#[ic_cdk::update]
+fn resources_grant_access(
+    resource_type: capsule::domain::ResourceType,
+    resource_id: String,
+    person: types::PersonRef,
+    role: capsule::domain::ResourceRole,
+    perm_mask: u32,
+) -> std::result::Result<capsule::domain::AccessEntry, Error> {
+    capsule::access::resources_grant_access(resource_type, resource_id, person, role, perm_mask)
+}
+
+/// Grant a person a named role (e.g. "member", "guest") on a memory, gallery or folder
+#[ic_cdk::update]
+fn resources_grant_role(
+    resource_type: capsule::domain::ResourceType,
+    resource_id: String,
+    person: types::PersonRef,
+    role_name: String,
+) -> std::result::Result<capsule::domain::AccessEntry, Error> {
+    capsule::access::resources_grant_role(resource_type, resource_id, person, role_name)
+}
+
+/// Grant a person or group a named role on a capsule, optionally on its memories and galleries
+///
+/// Cascading updates a batch of resources per call; call again until `ok` holds
+/// fewer than `CAPSULE_GRANT_BATCH` resources besides the capsule.
+#[ic_cdk::update]
+fn capsules_grant_role(
+    capsule_id: String,
+    grantee: capsule::api_types::SharingGrantee,
+    role_name: String,
+    cascade: bool,
+) -> std::result::Result<types::BulkResult<String>, Error> {
+    capsule::access::capsules_grant_role(capsule_id, grantee, role_name, cascade)
+}
+
+/// Add (or replace) a custom role template on a capsule
+#[ic_cdk::update]
+fn capsule_add_role_template(
+    capsule_id: String,
+    template: capsule::domain::RoleTemplate,
+) -> std::result::Result<capsule::domain::RoleTemplate, Error> {
+    capsule::access::capsule_add_role_template(capsule_id, template)
+}
+
+/// Remove a custom role template from a capsule
+#[ic_cdk::update]
+fn capsule_remove_role_template(
+    capsule_id: String,
+    name: String,
+) -> std::result::Result<capsule::domain::RoleTemplate, Error> {
+    capsule::access::capsule_remove_role_template(capsule_id, name)
+}
+
+/// Built-in and custom role templates available in a capsule
+#[ic_cdk::query]
+fn capsule_list_role_templates(
+    capsule_id: String,
+) -> std::result::Result<Vec<capsule::domain::RoleTemplate>, Error> {
+    capsule::access::capsule_list_role_templates(capsule_id)
+}
+
+/// Revoke a person's access to a memory, gallery or folder
+#[ic_cdk::update]
+fn resources_revoke_access(
+    resource_type: capsule::domain::ResourceType,
+    resource_id: String,
+    person: types::PersonRef,
+) -> std::result::Result<(), Error> {
+    capsule::access::resources_revoke_access(resource_type, resource_id, person)
+}
+
+/// Check many (resource, perm_mask) pairs for the caller in one call, in input order
+#[ic_cdk::query]
+fn resources_can_access_bulk(
+    queries: Vec<(capsule::domain::ResourceType, String, u32)>,
+) -> Vec<bool> {
+    // perm_mask holds Perm bits; a missing resource gives false
+    capsule::access::resources_can_access_bulk(queries)
+}
+
+/// Access changes affecting the caller, across all capsules (paginated by sequence)
+#[ic_cdk::query]
+fn events_access_changes(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<capsule_store::types::Page<events::CapsuleEvent>, Error> {
+    let caller = types::PersonRef::from_caller();
+    events::list_access_changes_for(&caller, cursor, limit)
+}
+
+/// Event feed of one capsule (caller needs read access)
+#[ic_cdk::query]
+fn capsules_events(
+    capsule_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<capsule_store::types::Page<events::CapsuleEvent>, Error> {
+    let caller = types::PersonRef::from_caller();
+    let capsule = with_capsule_store(|store| store.get(&capsule_id)).ok_or(Error::NotFound)?;
+    if !capsule.has_read_access(&caller) {
+        return Err(Error::Unauthorized);
+    }
+    events::list_capsule_events(&capsule_id, cursor, limit)
 }
 
 // ============================================================================
@@ -810,7 +2003,7 @@ fn sessions_stats() -> std::result::Result<String, Error> {
     })
 }
 
-/// List all sessions for debugging
+/// List all sessions for debugging (unbounded; prefer `sessions_list_paged`)
 #[ic_cdk::query]
 fn sessions_list() -> std::result::Result<String, Error> {
     memory::with_capsule_store_mut(|_store| {
@@ -835,6 +2028,22 @@ fn sessions_list() -> std::result::Result<String, Error> {
     })
 }
 
+/// Upload sessions in id order, one page at a time (admins only)
+#[ic_cdk::query]
+fn sessions_list_paged(
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<capsule_store::types::Page<upload::types::SessionSummary>, Error> {
+    let caller = ic_cdk::api::msg_caller();
+    let now = ic_cdk::api::time();
+    upload::service::sessions_page(
+        &caller,
+        |principal| admin::is_admin(principal, now),
+        cursor,
+        limit,
+    )
+}
+
 /// Clean up expired sessions
 #[ic_cdk::update]
 fn sessions_cleanup_expired() -> std::result::Result<String, Error> {
@@ -855,7 +2064,7 @@ fn sessions_cleanup_expired() -> std::result::Result<String, Error> {
 fn clear_all_stable_memory() -> std::result::Result<(), Error> {
     // Only allow admin to call this
     let caller = ic_cdk::api::msg_caller();
-    if !admin::is_admin(&caller) {
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
         return Err(types::Error::Unauthorized);
     }
 
@@ -888,30 +2097,81 @@ fn debug_sha256(bytes: Vec<u8>) -> String {
 /// Read blob data by locator (for asset retrieval)
 #[ic_cdk::query]
 fn blob_read(locator: String) -> std::result::Result<Vec<u8>, Error> {
-    upload::blob_read(locator)
+    let blob_store = upload::blob_store::StableBlobStore;
+    upload::blob_read(&blob_store, locator)
 }
 
 /// Read blob data by locator in chunks (for large files)
 /// Returns individual chunks to avoid IC message size limits
 #[ic_cdk::query]
 fn blob_read_chunk(locator: String, chunk_index: u32) -> std::result::Result<Vec<u8>, Error> {
-    upload::blob_store::blob_read_chunk(locator, chunk_index)
+    let blob_store = upload::blob_store::StableBlobStore;
+    upload::blob_store::blob_read_chunk(&blob_store, locator, chunk_index)
 }
 
 /// Get blob metadata including total chunk count
 #[ic_cdk::query]
 fn blob_get_meta(locator: String) -> std::result::Result<types::BlobMeta, Error> {
-    upload::blob_store::blob_get_meta(locator)
+    let blob_store = upload::blob_store::StableBlobStore;
+    upload::blob_store::blob_get_meta(&blob_store, locator)
+}
+
+/// Report live vs orphaned blob store pages and reclaimable bytes (admin only)
+#[ic_cdk::query]
+fn blobs_compaction_report() -> std::result::Result<upload::types::CompactionReport, Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    let blob_store = upload::blob_store::StableBlobStore;
+    Ok(upload::service::blobs_compaction_report(&blob_store))
+}
+
+/// Remove orphaned blob store pages (admin only)
+#[ic_cdk::update]
+fn blobs_compact() -> std::result::Result<upload::types::CompactionReport, Error> {
+    let caller = ic_cdk::api::msg_caller();
+    if !admin::is_admin(&caller, ic_cdk::api::time()) {
+        return Err(types::Error::Unauthorized);
+    }
+
+    let blob_store = upload::blob_store::StableBlobStore;
+    Ok(upload::service::blobs_compact(&blob_store))
 }
 
 /// Delete blob by ID (unified endpoint for all blob types)
+///
+/// An internal blob still used by a memory is refused with `Conflict`.
 #[ic_cdk::update]
 fn blob_delete(blob_id: String) -> types::Result6 {
     // Determine blob type and handle accordingly
     if blob_id.starts_with("blob_") {
         // Internal blob (ICP blob store)
-        match upload::blob_store::blob_delete(blob_id) {
-            Ok(()) => types::Result6::Ok("Internal blob deleted successfully".to_string()),
+        let blob_store = upload::blob_store::StableBlobStore;
+        // Duplicated and deduplicated memories share blobs: never free one in use
+        let in_use = crate::utils::blob_id::parse_blob_id(&blob_id).is_ok_and(|id| {
+            memory::with_capsule_store(|store| {
+                store
+                    .paginate(None, u32::MAX, Order::Asc)
+                    .items
+                    .iter()
+                    .any(|capsule| capsule::util::internal_blob_ids(capsule).contains(&id))
+            })
+        });
+        if in_use {
+            return types::Result6::Err(Error::Conflict(format!(
+                "{} is still used by a memory; remove the asset or memory instead",
+                blob_id
+            )));
+        }
+        match upload::blob_store::blob_delete(&blob_store, blob_id) {
+            Ok(meta) => {
+                memory::with_capsule_store_mut(|store| {
+                    upload::service::release_blob_charge(store, &meta)
+                });
+                types::Result6::Ok("Internal blob deleted successfully".to_string())
+            }
             Err(error) => types::Result6::Err(error),
         }
     } else if blob_id.starts_with("inline_") {
@@ -948,7 +2208,8 @@ async fn debug_put_chunk_b64(
         .map_err(|_| types::Error::InvalidArgument("bad base64".into()))?;
     memory::with_capsule_store_mut(|store| {
         let session_id = upload::types::SessionId(session_id);
-        upload::service::put_chunk(store, &session_id, chunk_idx, bytes).map_err(types::Error::from)
+        let caller = ic_cdk::api::msg_caller();
+        upload::service::put_chunk(store, &session_id, caller, chunk_idx, bytes, None)
     })
 }
 
@@ -969,15 +2230,24 @@ async fn debug_finish_hex(
     let mut hash_array = [0u8; 32];
     hash_array.copy_from_slice(&bytes);
 
+    let blob_store = upload::blob_store::StableBlobStore;
     memory::with_capsule_store_mut(|store| {
         let session_id = upload::types::SessionId(session_id);
-        upload::service::commit(store, session_id, hash_array, total_len)
-            .map_err(types::Error::from)
+        upload::service::commit(
+            store,
+            &blob_store,
+            session_id,
+            ic_cdk::api::msg_caller(),
+            hash_array,
+            total_len,
+            ic_cdk::api::time(),
+        )
+        .map(|outcome| outcome.blob_id)
     })
 }
 
 // ============================================================================
-// PERSONAL CANISTER MANAGEMENT (22 functions)
+// PERSONAL CANISTER MANAGEMENT (32 functions)
 // ============================================================================
 #[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
 #[ic_cdk::update]
@@ -992,6 +2262,58 @@ async fn create_personal_canister() -> canister_factory::PersonalCanisterCreatio
     }
 }
 
+/// Cancel the caller's in-progress personal canister creation and delete its canister
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::update]
+async fn cancel_personal_canister_creation() -> std::result::Result<(), Error> {
+    canister_factory::cancel_personal_canister_creation().await
+}
+
+/// Continue the caller's personal canister creation after an upgrade interrupted it
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::update]
+async fn resume_personal_canister_creation(
+) -> std::result::Result<canister_factory::CreationStatusResponse, Error> {
+    canister_factory::resume_personal_canister_creation().await
+}
+
+/// Send memories created since the last sync to the caller's existing personal canister
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::update]
+async fn sync_to_personal_canister() -> std::result::Result<canister_factory::SyncReport, Error> {
+    canister_factory::sync_to_personal_canister().await
+}
+
+/// Keep one of the caller's import sessions alive during a pause between chunks
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::update]
+fn import_heartbeat(session_id: String) -> std::result::Result<(), Error> {
+    canister_factory::import::import_heartbeat(session_id)
+}
+
+/// Progress of one of the caller's import sessions
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::query]
+fn import_progress(
+    session_id: String,
+) -> std::result::Result<canister_factory::ImportSessionProgress, Error> {
+    canister_factory::import::import_progress(session_id)
+}
+
+/// IDs of the caller's active import sessions
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::query]
+fn my_import_sessions() -> Vec<String> {
+    canister_factory::import::my_import_sessions()
+}
+
+/// Dry-run size, cycles and time estimate for migrating the caller's capsule
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::query]
+fn estimate_migration() -> std::result::Result<canister_factory::MigrationEstimate, Error> {
+    canister_factory::estimate_migration()
+}
+
 #[ic_cdk::query]
 fn get_creation_status() -> Option<canister_factory::CreationStatusResponse> {
     canister_factory::get_creation_status()
@@ -1063,6 +2385,23 @@ fn clear_creation_state(user: Principal) -> std::result::Result<bool, Error> {
     canister_factory::clear_creation_state(user)
 }
 
+/// Canisters left behind by failed creations (admin only)
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::query]
+fn list_orphaned_canisters(
+) -> std::result::Result<Vec<canister_factory::PersonalCanisterRecord>, String> {
+    canister_factory::list_orphaned_canisters()
+}
+
+/// Stop and delete an orphaned canister and drop it from the registry (admin only)
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::update]
+async fn admin_cleanup_orphaned_canister(
+    canister_id: Principal,
+) -> std::result::Result<(), String> {
+    canister_factory::admin_cleanup_orphaned_canister(canister_id).await
+}
+
 #[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
 #[ic_cdk::update]
 fn clear_migration_state(user: Principal) -> std::result::Result<bool, Error> {
@@ -1076,6 +2415,13 @@ fn set_personal_canister_creation_enabled(enabled: bool) -> std::result::Result<
     canister_factory::set_personal_canister_creation_enabled(enabled)
 }
 
+/// Pause new personal canister creations without stopping in-flight ones (admin only)
+#[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
+#[ic_cdk::update]
+fn set_migration_paused(paused: bool) -> std::result::Result<(), Error> {
+    canister_factory::set_migration_paused(paused)
+}
+
 #[cfg(any(feature = "migration", feature = "personal_canister_creation"))]
 #[ic_cdk::query]
 fn get_personal_canister_creation_stats(
@@ -1180,11 +2526,7 @@ async fn post_upgrade() {
             Vec<Principal>,
             canister_factory::PersonalCanisterCreationStateData,
         )>() {
-            with_capsule_store_mut(|store| {
-                for (id, capsule) in capsule_data {
-                    store.upsert(id, capsule);
-                }
-            });
+            legacy_capsules::stash(capsule_data);
             admin::import_admins_from_upgrade(admin_data);
             canister_factory::import_migration_state_from_upgrade(migration_data);
         }
@@ -1196,21 +2538,42 @@ async fn post_upgrade() {
         if let Ok((capsule_data, admin_data)) =
             ic_cdk::storage::stable_restore::<(Vec<(String, Capsule)>, Vec<Principal>)>()
         {
-            with_capsule_store_mut(|store| {
-                for (id, capsule) in capsule_data {
-                    store.upsert(id, capsule);
-                }
-            });
+            legacy_capsules::stash(capsule_data);
             admin::import_admins_from_upgrade(admin_data);
         }
     }
     // If restore fails, start with empty state (no panic)
 
+    // Count blob references of capsules stored before the refcount index existed
+    // (before migrating legacy capsules, whose upserts update the index themselves)
+    let blobs = memory::with_capsule_store_mut(|store| store.backfill_blob_refs());
+    ic_cdk::println!("Post-upgrade: blob refcounts backfilled for {} blobs", blobs);
+
+    let report = legacy_capsules::migrate_pending();
+    ic_cdk::println!(
+        "Post-upgrade: legacy capsules migrated={} skipped={} pending={}",
+        report.migrated,
+        report.skipped,
+        legacy_capsules::pending_count()
+    );
+
+    // Index grants made before the reverse grant index existed (once, after the
+    // legacy migration so migrated capsules are covered)
+    if !grant_index::is_backfilled() {
+        let capsules = memory::with_capsule_store(|store| {
+            store.paginate(None, u32::MAX, Order::Asc).items
+        });
+        let indexed = grant_index::backfill(&capsules);
+        ic_cdk::println!("Post-upgrade: grant index backfilled with {} grants", indexed);
+    }
+
     ic_cdk::println!("Post-upgrade: stable memory structures restored automatically");
 
     // Initialize HTTP secret store
     http::secret_store::post_upgrade();
 
+    start_maintenance_timer();
+
     // Set skip certification for private assets
     ic_cdk::api::certified_data_set(
         &ic_http_certification::utils::skip_certification_certified_data(),
@@ -1288,6 +2651,23 @@ fn memories_delete_all(
     memories_delete_all_core(&env, &mut store, capsule_id, delete_assets)
 }
 
+/// Delete memories created in `[after, before)`; call again until a batch comes back short
+#[ic_cdk::update]
+fn memories_delete_by_time_range(
+    capsule_id: String,
+    before: Option<u64>,
+    after: Option<u64>,
+    delete_assets: bool,
+) -> Result<crate::memories::types::BulkDeleteResult, Error> {
+    use crate::memories::core::memories_delete_by_time_range_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    memories_delete_by_time_range_core(&env, &mut store, capsule_id, before, after, delete_assets)
+}
+
 /// TEMPORARY DEV METHOD: Clear all memories in a capsule
 ///
 /// WARNING: This is a developer method that bypasses normal ACL checks
@@ -1325,6 +2705,21 @@ fn memories_cleanup_assets_all(
     memories_cleanup_assets_all_core(&env, &mut store, memory_id)
 }
 
+/// Remove all assets of one storage kind (inline, internal or external) from a memory
+#[ic_cdk::update]
+fn memories_remove_assets_by_kind(
+    memory_id: String,
+    kind: crate::memories::types::AssetStorageKind,
+) -> Result<crate::memories::types::AssetCleanupResult, Error> {
+    use crate::memories::core::memories_remove_assets_by_kind_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    let env = CanisterEnv;
+    let mut store = StoreAdapter;
+
+    memories_remove_assets_by_kind_core(&env, &mut store, memory_id, kind)
+}
+
 /// Bulk cleanup assets from multiple memories
 #[ic_cdk::update]
 fn memories_cleanup_assets_bulk(
@@ -1439,13 +2834,24 @@ fn asset_remove_by_id(
 /// Get a specific asset from a memory by asset_id
 #[ic_cdk::query]
 fn asset_get_by_id(memory_id: String, asset_id: String) -> Result<types::MemoryAssetData, Error> {
-    use crate::memories::core::asset_get_by_id_core;
+    use crate::memories::core::{asset_get_by_id_core, memories_read_core};
     use crate::memories::{CanisterEnv, StoreAdapter};
 
     let env = CanisterEnv;
     let store = StoreAdapter;
 
-    asset_get_by_id_core(&env, &store, memory_id, asset_id)
+    let asset = asset_get_by_id_core(&env, &store, memory_id.clone(), asset_id)?;
+
+    // Count the download against the memory's capsule
+    if let Ok(memory) = memories_read_core(&env, &store, memory_id) {
+        memory_stats::record(
+            &memory.capsule_id,
+            &memory.id,
+            memory_stats::AccessKind::Download,
+            ic_cdk::api::time(),
+        );
+    }
+    Ok(asset)
 }
 
 /// List all assets in a memory
@@ -1462,12 +2868,67 @@ fn memories_list_assets(
     memories_list_assets_core(&env, &mut store, memory_id)
 }
 
+/// Every asset of a memory with size, content type, hash, backend and local availability
+#[ic_cdk::query]
+fn memories_asset_inventory(
+    memory_id: String,
+) -> Result<crate::memories::types::AssetInventory, Error> {
+    use crate::memories::core::memories_asset_inventory_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    memories_asset_inventory_core(
+        &CanisterEnv,
+        &StoreAdapter,
+        &upload::blob_store::StableBlobStore,
+        memory_id,
+    )
+}
+
+/// Asset bytes and counts of a capsule per storage backend (caller needs read access)
+#[ic_cdk::query]
+fn capsules_storage_distribution(
+    capsule_id: String,
+) -> Result<crate::memories::types::StorageDistribution, Error> {
+    use crate::memories::core::capsules_storage_distribution_core;
+    use crate::memories::{CanisterEnv, StoreAdapter};
+
+    capsules_storage_distribution_core(
+        &CanisterEnv,
+        &StoreAdapter,
+        &upload::blob_store::StableBlobStore,
+        capsule_id,
+    )
+}
+
 // ============================================================================
 // HTTP REQUEST HANDLERS
 // ============================================================================
 
+/// Interval of the maintenance sweep (expired upload sessions, idempotency keys)
+const MAINTENANCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// Start the periodic maintenance sweep (timers don't survive upgrades)
+fn start_maintenance_timer() {
+    ic_cdk_timers::set_timer_interval(MAINTENANCE_INTERVAL, || {
+        upload::service::cleanup_expired_sessions(upload::service::SESSION_EXPIRY_MS);
+        let swept = idempotency::sweep_expired(ic_cdk::api::time());
+        ic_cdk::println!("MAINTENANCE: swept {} expired idempotency keys", swept);
+        let purged = capsule::commands::purge_container_trash(ic_cdk::api::time());
+        ic_cdk::println!("MAINTENANCE: purged {} trashed galleries/folders", purged);
+        let expired = memory_tombstones::purge_expired(ic_cdk::api::time());
+        ic_cdk::println!("MAINTENANCE: dropped {} expired memory tombstones", expired);
+        let expired = share_links::purge_expired(ic_cdk::api::time());
+        ic_cdk::println!("MAINTENANCE: dropped {} expired share links", expired);
+        let expired = memories::sweep_expired(ic_cdk::api::time());
+        ic_cdk::println!("MAINTENANCE: deleted {} expired memories", expired);
+        metrics::refresh_sample(ic_cdk::api::time());
+    });
+}
+
 #[ic_cdk::init]
 async fn init() {
+    start_maintenance_timer();
+
     // Initialize HTTP secret store
     http::secret_store::init().await;
 
@@ -1528,5 +2989,34 @@ fn mint_http_tokens_bulk(
     )
 }
 
+/// SHA-256 (hex) of the exported Candid interface, computed once per build
+fn interface_hash() -> String {
+    CANDID_INTERFACE_HASH.with(|hash| {
+        hash.get_or_init(|| hex::encode(Sha256::digest(__export_service().as_bytes())))
+            .clone()
+    })
+}
+
+/// Hash of this canister's Candid interface, for clients to detect drift from their bindings
+#[ic_cdk::query]
+fn candid_interface_hash() -> String {
+    interface_hash()
+}
+
 // Export the interface for the smart contract.
 ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candid_interface_hash_is_stable_within_a_build() {
+        let first = interface_hash();
+        assert_eq!(first.len(), 64);
+        // Computed once, so repeated calls agree even though the exported
+        // text isn't guaranteed to be byte-identical between exports
+        assert_eq!(interface_hash(), first);
+        assert!(__export_service().contains("candid_interface_hash"));
+    }
+}
@@ -0,0 +1,144 @@
+//! Leveled, key=value breadcrumbs
+//!
+//! Lines look like `INFO FINISH_OK sid=7 len=1024`. The verbosity is a stable
+//! setting changed with the admin endpoint `set_log_level`, so operators can
+//! turn breadcrumbs up or down without a redeploy, and it survives upgrades.
+//! Field values are only formatted for lines that pass the threshold.
+
+use crate::memory::{MEM_LOG_LEVEL, MM};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableCell, Storable};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt::Display;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Severity of a log line; lines below the current level are dropped
+#[derive(
+    CandidType, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    fn label(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+}
+
+impl Storable for LogLevel {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode LogLevel"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, level): (u16, LogLevel) =
+            Decode!(bytes.as_ref(), (u16, LogLevel)).expect("Failed to decode LogLevel");
+        assert_eq!(version, 1, "Unsupported LogLevel version");
+        level
+    }
+}
+
+pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Info;
+
+thread_local! {
+    // Admin-set threshold, kept across upgrades
+    static LOG_LEVEL: RefCell<StableCell<LogLevel, Memory>> = RefCell::new(
+        StableCell::init(MM.with(|m| m.borrow().get(MEM_LOG_LEVEL)), DEFAULT_LOG_LEVEL)
+            .expect("Failed to init log level")
+    );
+}
+
+/// Current threshold
+pub fn level() -> LogLevel {
+    LOG_LEVEL.with(|level| *level.borrow().get())
+}
+
+/// Change the threshold (admin)
+pub fn set_level(level: LogLevel) {
+    LOG_LEVEL.with(|cell| {
+        cell.borrow_mut()
+            .set(level)
+            .expect("Failed to store log level");
+    });
+}
+
+/// Formatted line for `event`, or None when `level` is below the threshold
+fn render(level: LogLevel, event: &str, fields: &[(&str, &dyn Display)]) -> Option<String> {
+    if level < self::level() {
+        return None;
+    }
+    let mut line = format!("{} {}", level.label(), event);
+    for (key, value) in fields {
+        line.push_str(&format!(" {}={}", key, value));
+    }
+    Some(line)
+}
+
+/// Write `event` with its fields if `level` passes the threshold
+pub fn log(level: LogLevel, event: &str, fields: &[(&str, &dyn Display)]) {
+    if let Some(line) = render(level, event, fields) {
+        ic_cdk::println!("{}", line);
+    }
+}
+
+pub fn debug(event: &str, fields: &[(&str, &dyn Display)]) {
+    log(LogLevel::Debug, event, fields);
+}
+
+pub fn info(event: &str, fields: &[(&str, &dyn Display)]) {
+    log(LogLevel::Info, event, fields);
+}
+
+pub fn warn(event: &str, fields: &[(&str, &dyn Display)]) {
+    log(LogLevel::Warn, event, fields);
+}
+
+pub fn error(event: &str, fields: &[(&str, &dyn Display)]) {
+    log(LogLevel::Error, event, fields);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_below_threshold_lines_are_suppressed() {
+        assert_eq!(
+            render(LogLevel::Info, "FINISH_OK", &[("sid", &7), ("len", &1024)]),
+            Some("INFO FINISH_OK sid=7 len=1024".to_string())
+        );
+        assert_eq!(
+            render(LogLevel::Debug, "PUT_CHUNK_RECV", &[("sid", &7)]),
+            None
+        );
+
+        set_level(LogLevel::Warn);
+        assert_eq!(render(LogLevel::Info, "FINISH_OK", &[]), None);
+        assert_eq!(
+            render(
+                LogLevel::Error,
+                "FINISH_ERROR",
+                &[("err", &"hash_mismatch")]
+            ),
+            Some("ERROR FINISH_ERROR err=hash_mismatch".to_string())
+        );
+
+        set_level(LogLevel::Debug);
+        assert!(render(LogLevel::Debug, "PUT_CHUNK_RECV", &[]).is_some());
+    }
+}
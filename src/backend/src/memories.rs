@@ -4,7 +4,7 @@ pub mod types;
 pub mod utils;
 
 // Re-export the main functions for easy access
-pub use adapters::{ping, CanisterEnv, StoreAdapter};
+pub use adapters::{ping, sweep_expired, CanisterEnv, StoreAdapter};
 
 // Re-export new asset link types for external use
 pub use utils::{AssetKind, AssetLink, AssetLinks};
@@ -132,6 +132,7 @@ impl crate::memories::core::Store for StoreAdapter {
                     capsule.owners.clone(),
                     capsule.controllers.clone(),
                 )
+                .with_default_memory_access(capsule.memory_access_default())
             })
         })
     }
@@ -151,17 +152,72 @@ impl crate::memories::core::Store for StoreAdapter {
     }
 
     /// Clear all internal blobs in a capsule (atomic operation)
+    ///
+    /// Blobs another capsule still references are kept; the capsule's own
+    /// memories are about to be cleared, so they don't count.
     fn clear_all_internal_blobs_in_capsule(&mut self, capsule_id: &str) -> std::result::Result<(), Error> {
-        // Get all memories first to access their assets
-        let memories = self.get_all_memories(&capsule_id.to_string());
-        
-        // Clean up assets for each memory
-        for memory in memories {
-            // Use existing cleanup function for each memory's assets
-            crate::memories::core::assets::cleanup_memory_assets(&memory)?;
-        }
-        
-        Ok(())
+        with_capsule_store_mut(|store| {
+            let capsule = store.get(&capsule_id.to_string()).ok_or(Error::NotFound)?;
+            let own_refs = crate::capsule::util::blob_ref_counts(&capsule);
+            let (_, freed) = crate::capsule::util::free_capsule_blobs(
+                &crate::upload::blob_store::StableBlobStore,
+                &capsule,
+                |blob_id| {
+                    let own = own_refs.get(&blob_id).copied().unwrap_or(0);
+                    store.blob_ref_count(blob_id).saturating_sub(own)
+                },
+            );
+            for meta in &freed {
+                crate::upload::service::release_blob_charge(store, meta);
+            }
+            Ok(())
+        })
+    }
+
+    fn galleries_referencing_memory(&self, capsule: &CapsuleId, id: &MemoryId) -> Vec<String> {
+        with_capsule_store(|store| {
+            store
+                .get(capsule)
+                .map(|capsule_data| {
+                    crate::gallery::commands::galleries_referencing(&capsule_data, id)
+                })
+                .unwrap_or_default()
+        })
+    }
+
+    fn blob_referenced_elsewhere(&self, blob_id: u64, capsule: &CapsuleId, id: &MemoryId) -> bool {
+        with_capsule_store(|store| {
+            // The stored memory's own references are counted until it is written back
+            let own = store
+                .get(capsule)
+                .and_then(|capsule_data| capsule_data.memories.get(id).cloned())
+                .map_or(0, |memory| {
+                    memory
+                        .blob_internal_assets
+                        .iter()
+                        .filter_map(crate::capsule::util::internal_blob_id)
+                        .filter(|asset_blob| *asset_blob == blob_id)
+                        .count() as u32
+                });
+            store.blob_ref_count(blob_id) > own
+        })
+    }
+
+    fn detach_memory_from_galleries(
+        &mut self,
+        capsule: &CapsuleId,
+        id: &MemoryId,
+        now: u64,
+    ) -> std::result::Result<Vec<String>, Error> {
+        with_capsule_store_mut(|store| {
+            store.update_with(capsule, |capsule_data| {
+                Ok(crate::gallery::commands::detach_memory_from_galleries(
+                    capsule_data,
+                    id,
+                    now,
+                ))
+            })
+        })
     }
 
     /// Check if capsule exists
@@ -170,6 +226,14 @@ impl crate::memories::core::Store for StoreAdapter {
             store.get(&capsule_id.to_string()).is_some()
         })
     }
+
+    fn idem_lookup(&self, scope: &str, caller: &PersonRef, key: &str, now: u64) -> Option<String> {
+        crate::idempotency::lookup(scope, caller, key, now)
+    }
+
+    fn idem_record(&mut self, scope: &str, caller: &PersonRef, key: &str, result: &str, now: u64) {
+        crate::idempotency::record(scope, caller, key, result, now)
+    }
 }
 
 // ============================================================================
@@ -178,39 +242,53 @@ impl crate::memories::core::Store for StoreAdapter {
 
 /// Check presence for multiple memories on ICP
 /// 
-/// Returns presence status for the given memory IDs by checking if they exist
-/// in the caller's accessible capsules.
+/// Resolves each ID against every capsule the caller can access (IDs may span
+/// capsules) and reports the holding capsule; see `memories_ping_core`.
 pub fn ping(
     memory_ids: Vec<String>,
 ) -> std::result::Result<Vec<crate::types::MemoryPresenceResult>, Error> {
-    let caller = PersonRef::from_caller();
-    
-    let results: Vec<crate::types::MemoryPresenceResult> = memory_ids
-        .iter()
-        .map(|memory_id| {
-            // Check if memory exists in any of the caller's accessible capsules
-            let exists = with_capsule_store(|store| {
-                let all_capsules = store.paginate(None, u32::MAX, Order::Asc);
-                all_capsules
-                    .items
-                    .iter()
-                    .any(|capsule| {
-                        // Check if caller has read access to this capsule
-                        capsule.has_read_access(&caller) && 
-                        // Check if memory exists in this capsule
-                        capsule.memories.contains_key(memory_id)
-                    })
-            });
-            
-            crate::types::MemoryPresenceResult {
-                memory_id: memory_id.clone(),
-                metadata_present: exists,
-                asset_present: exists, // For now, assume if metadata exists, asset exists
-            }
-        })
-        .collect();
+    let env = CanisterEnv;
+    let store = StoreAdapter;
+
+    Ok(crate::memories::core::memories_ping_core(&env, &store, memory_ids))
+}
 
-    Ok(results)
+thread_local! {
+    /// Last capsule fully swept for expired memories (heap only: an upgrade
+    /// just restarts the walk from the first capsule)
+    static SWEEP_CURSOR: std::cell::RefCell<Option<CapsuleId>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Delete memories past their expiry, a bounded slice of capsules per call
+///
+/// Called by the maintenance timer; each tick resumes where the last one
+/// stopped and wraps around after the last capsule.
+pub fn sweep_expired(now: u64) -> u32 {
+    use crate::memories::core::delete::{SWEEP_EXPIRED_BATCH, SWEEP_EXPIRED_CAPSULES};
+
+    let after = SWEEP_CURSOR.with(|cursor| cursor.borrow().clone());
+    let page = with_capsule_store(|store| {
+        store.paginate(after.clone(), SWEEP_EXPIRED_CAPSULES, Order::Asc)
+    });
+    let capsule_ids: Vec<CapsuleId> = page.items.into_iter().map(|capsule| capsule.id).collect();
+
+    let (deleted, swept) = crate::memories::core::memories_sweep_expired_core(
+        &mut StoreAdapter,
+        &capsule_ids,
+        now,
+        SWEEP_EXPIRED_BATCH,
+    );
+    let next = if swept == capsule_ids.len() {
+        // Whole page done: continue with the next one, or start over
+        page.next_cursor
+    } else if swept > 0 {
+        Some(capsule_ids[swept - 1].clone())
+    } else {
+        after
+    };
+    SWEEP_CURSOR.with(|cursor| *cursor.borrow_mut() = next);
+    deleted
 }
 
 // TODO: list() function is currently unused but may be needed for legacy API compatibility
@@ -332,12 +410,27 @@ impl Memory {
                     len: size,
                 },
                 metadata: asset_metadata,
+                order: None,
             }],
             blob_external_assets: vec![],
         }
     }
     */
 
+    /// Asset IDs in display order; the first one is the primary asset
+    ///
+    /// Assets with an explicit `order` come first, by that order. The rest keep
+    /// their storage order: inline, then internal blobs, then external blobs.
+    pub fn ordered_asset_ids(&self) -> Vec<String> {
+        let inline = self.inline_assets.iter().map(|a| (a.order, &a.asset_id));
+        let internal = self.blob_internal_assets.iter().map(|a| (a.order, &a.asset_id));
+        let external = self.blob_external_assets.iter().map(|a| (a.order, &a.asset_id));
+        let mut assets: Vec<_> = inline.chain(internal).chain(external).collect();
+        // Stable sort keeps storage order among unordered assets
+        assets.sort_by_key(|(order, _)| order.unwrap_or(u32::MAX));
+        assets.into_iter().map(|(_, id)| id.clone()).collect()
+    }
+
     /// Get memory header for listing
     pub fn to_header(&self) -> crate::types::MemoryHeader {
         // Calculate total size from all assets
@@ -394,6 +487,8 @@ impl Memory {
             
             // NEW: Storage location information
             database_storage_edges: self.metadata.database_storage_edges.clone(),
+
+            pending: self.pending.is_some(),
         }
     }
     
@@ -15,12 +15,12 @@ pub mod update;
 
 // Re-export the public surface (clean API)
 pub use assets::{
-    asset_get_by_id_core, asset_remove_by_id_core, asset_remove_core, asset_remove_external_core,
+    asset_get_by_id_core, asset_remove_by_id_core, memories_asset_inventory_core, capsules_storage_distribution_core, asset_remove_core, asset_remove_external_core,
     asset_remove_inline_core, asset_remove_internal_core, memories_cleanup_assets_all_core,
-    memories_cleanup_assets_bulk_core, memories_list_assets_core,
+    memories_cleanup_assets_bulk_core, memories_list_assets_core, memories_remove_assets_by_kind_core,
 };
-pub use create::memories_create_core;
-pub use delete::{memories_delete_all_core, memories_delete_bulk_core, memories_delete_core, _dev_clear_all_memories_in_capsule_core};
-pub use read::memories_read_core;
+pub use create::{memories_create_and_place_core, memories_create_bulk_core, memories_create_core, memories_duplicate_core, memories_reserve_core};
+pub use delete::{memories_delete_all_core, memories_delete_bulk_core, memories_delete_by_time_range_core, memories_delete_core, memories_sweep_expired_core, _dev_clear_all_memories_in_capsule_core};
+pub use read::{asset_data_at, memories_ping_core, memories_read_by_external_key_core, memories_read_core, memories_storage_location_core};
 pub use traits::{Env, Store};
-pub use update::{memories_update_core, memories_add_asset_core, memories_add_inline_asset_core};
+pub use update::{memories_update_core, memories_add_asset_core, memories_add_inline_asset_core, memories_attach_blob_core, memories_replace_blob_core, memories_set_signed_url_core, memories_set_locked_core, memories_reorder_assets_core, memories_set_expiry_core};
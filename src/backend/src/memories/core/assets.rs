@@ -3,18 +3,34 @@
 //! This module contains functions for managing memory assets,
 //! including cleanup operations for different storage backends.
 
+use super::model_helpers::blob_hosting_for_edge;
+use super::read::memories_read_core;
 use super::traits::*;
-use crate::types::{BlobRef, Error, Memory, MemoryAssetBlobExternal, StorageEdgeBlobType};
+use super::update::check_unlocked;
+use crate::capsule_acl::CapsuleAcl;
+use crate::memories::types::{
+    AssetCleanupResult, AssetInventory, AssetInventoryEntry, AssetStorageKind, BackendUsage,
+    StorageDistribution,
+};
+use crate::types::{
+    BlobHosting, BlobRef, CapsuleId, Error, Memory, MemoryAssetBlobExternal, MemoryId,
+    StorageEdgeBlobType,
+};
+use crate::upload::blob_store::BlobStore;
 
 /// Clean up all assets associated with a memory before deletion
 /// This prevents memory leaks and storage bloat
-pub fn cleanup_memory_assets(memory: &Memory) -> std::result::Result<(), Error> {
+pub fn cleanup_memory_assets<S: Store>(
+    store: &S,
+    capsule_id: &CapsuleId,
+    memory: &Memory,
+) -> std::result::Result<(), Error> {
     // 1. Inline assets: No cleanup needed - they're stored directly in the memory struct
     // When the memory is deleted, inline assets are automatically removed
 
     // 2. Internal blob assets: Delete from ICP blob store
     for blob_asset in &memory.blob_internal_assets {
-        cleanup_internal_blob_asset(&blob_asset.blob_ref)?;
+        cleanup_internal_blob_asset(store, capsule_id, &memory.id, &blob_asset.blob_ref)?;
     }
 
     // 3. External blob assets: Delete from external storage
@@ -25,9 +41,19 @@ pub fn cleanup_memory_assets(memory: &Memory) -> std::result::Result<(), Error>
     Ok(())
 }
 
-/// Clean up an internal blob asset from ICP blob store
-pub fn cleanup_internal_blob_asset(blob_ref: &BlobRef) -> std::result::Result<(), Error> {
-    use crate::upload::blob_store::BlobStore;
+/// Clean up an internal blob asset of `memory_id` from ICP blob store
+///
+/// Every memory-side blob delete goes through here, so this is also where the
+/// blob's bytes are given back to the capsule quota they were charged to. A blob
+/// another memory still references (a duplicate, or deduplicated content) is kept,
+/// and one that is already gone counts as cleaned up.
+pub fn cleanup_internal_blob_asset<S: Store>(
+    store: &S,
+    capsule_id: &CapsuleId,
+    memory_id: &MemoryId,
+    blob_ref: &BlobRef,
+) -> std::result::Result<(), Error> {
+    use crate::upload::blob_store::{BlobStore, StableBlobStore};
     use crate::upload::types::BlobId;
     use crate::utils::blob_id::parse_blob_id;
 
@@ -43,23 +69,25 @@ pub fn cleanup_internal_blob_asset(blob_ref: &BlobRef) -> std::result::Result<()
         &blob_ref.locator
     };
 
-    // Add loud, temporary logging to debug exact blob ID strings
-    ic_cdk::println!(
-        "[cleanup_internal_blob_asset] raw='{}' (len={}) bytes={:?}", 
-        blob_id_str, 
-        blob_id_str.len(), 
-        blob_id_str.as_bytes()
-    );
-
     // Use the bulletproof parser
     let blob_id_num = parse_blob_id(blob_id_str)
         .map_err(|e| Error::InvalidArgument(e))?;
 
     let blob_id = BlobId(blob_id_num);
+    if store.blob_referenced_elsewhere(blob_id.0, capsule_id, memory_id) {
+        return Ok(());
+    }
 
-    // Delete the blob from the store
-    let blob_store = BlobStore::new();
+    // Delete the blob from the store and give its bytes back to the capsule quota
+    let blob_store = StableBlobStore;
+    let Some(meta) = blob_store.get_meta(blob_id.0) else {
+        // Already gone: nothing left to delete or refund
+        return Ok(());
+    };
     blob_store.delete_blob(&blob_id)?;
+    crate::memory::with_capsule_store_mut(|store| {
+        crate::upload::service::release_blob_charge(store, &meta)
+    });
 
     Ok(())
 }
@@ -182,13 +210,21 @@ pub fn memories_cleanup_assets_all_core<E: Env, S: Store>(
 
     for capsule_id in accessible_capsules {
         if let Some(memory) = store.get_memory(&capsule_id, &memory_id) {
+            check_unlocked(&memory)?;
+
             // Clean up all assets
-            cleanup_memory_assets(&memory)?;
+            cleanup_memory_assets(store, &capsule_id, &memory)?;
 
+            let inline_removed = memory.inline_assets.len() as u32;
+            let internal_removed = memory.blob_internal_assets.len() as u32;
+            let external_removed = memory.blob_external_assets.len() as u32;
             return Ok(crate::memories::types::AssetCleanupResult {
                 memory_id,
-                assets_cleaned: 1, // Simplified - would need to count actual assets
+                assets_cleaned: inline_removed + internal_removed + external_removed,
                 message: "Assets cleaned successfully".to_string(),
+                inline_removed,
+                internal_removed,
+                external_removed,
             });
         }
     }
@@ -196,6 +232,76 @@ pub fn memories_cleanup_assets_all_core<E: Env, S: Store>(
     Err(Error::NotFound)
 }
 
+/// Remove every asset of one storage kind from a memory, keeping the others
+///
+/// Internal blobs are deleted from the blob store and their quota charge released.
+/// Inline bytes need no bookkeeping: the inline budget is computed from the
+/// memories themselves.
+pub fn memories_remove_assets_by_kind_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    memory_id: String,
+    kind: AssetStorageKind,
+) -> std::result::Result<AssetCleanupResult, Error> {
+    let caller = env.caller();
+    let accessible_capsules = store.get_accessible_capsules(&caller);
+
+    for capsule_id in accessible_capsules {
+        if let Some(mut memory) = store.get_memory(&capsule_id, &memory_id) {
+            let capsule_access = store
+                .get_capsule_for_acl(&capsule_id)
+                .ok_or(Error::NotFound)?;
+            if !capsule_access.can_write(&caller) {
+                return Err(Error::Unauthorized);
+            }
+            check_unlocked(&memory)?;
+
+            let mut result = AssetCleanupResult {
+                memory_id: memory_id.clone(),
+                assets_cleaned: 0,
+                message: String::new(),
+                inline_removed: 0,
+                internal_removed: 0,
+                external_removed: 0,
+            };
+            match kind {
+                AssetStorageKind::Inline => {
+                    result.inline_removed = memory.inline_assets.len() as u32;
+                    memory.inline_assets.clear();
+                }
+                AssetStorageKind::Internal => {
+                    for asset in memory.blob_internal_assets.drain(..) {
+                        cleanup_internal_blob_asset(
+                            store,
+                            &capsule_id,
+                            &memory_id,
+                            &asset.blob_ref,
+                        )?;
+                        result.internal_removed += 1;
+                    }
+                }
+                AssetStorageKind::External => {
+                    for asset in memory.blob_external_assets.drain(..) {
+                        cleanup_external_blob_asset(&asset)?;
+                        result.external_removed += 1;
+                    }
+                }
+            }
+            result.assets_cleaned =
+                result.inline_removed + result.internal_removed + result.external_removed;
+            result.message = format!("Removed {} {:?} assets", result.assets_cleaned, kind);
+
+            memory.metadata.updated_at = env.now();
+            memory.update_dashboard_fields();
+            store.update_memory(&capsule_id, &memory_id, memory)?;
+
+            return Ok(result);
+        }
+    }
+
+    Err(Error::NotFound)
+}
+
 /// Core bulk cleanup assets function - pure business logic
 pub fn memories_cleanup_assets_bulk_core<E: Env, S: Store>(
     env: &E,
@@ -250,7 +356,9 @@ pub fn asset_remove_core<E: Env, S: Store>(
     let accessible_capsules = store.get_accessible_capsules(&env.caller());
 
     for capsule_id in accessible_capsules {
-        if let Some(_memory) = store.get_memory(&capsule_id, &memory_id) {
+        if let Some(memory) = store.get_memory(&capsule_id, &memory_id) {
+            check_unlocked(&memory)?;
+
             // This is a simplified implementation
             // In practice, you'd need to find and remove the specific asset
             return Ok(crate::memories::types::AssetRemovalResult {
@@ -275,7 +383,9 @@ pub fn asset_remove_inline_core<E: Env, S: Store>(
     let accessible_capsules = store.get_accessible_capsules(&env.caller());
 
     for capsule_id in accessible_capsules {
-        if let Some(_memory) = store.get_memory(&capsule_id, &memory_id) {
+        if let Some(memory) = store.get_memory(&capsule_id, &memory_id) {
+            check_unlocked(&memory)?;
+
             // This is a simplified implementation
             // In practice, you'd need to remove the asset at the specified index
             return Ok(crate::memories::types::AssetRemovalResult {
@@ -300,7 +410,9 @@ pub fn asset_remove_internal_core<E: Env, S: Store>(
     let accessible_capsules = store.get_accessible_capsules(&env.caller());
 
     for capsule_id in accessible_capsules {
-        if let Some(_memory) = store.get_memory(&capsule_id, &memory_id) {
+        if let Some(memory) = store.get_memory(&capsule_id, &memory_id) {
+            check_unlocked(&memory)?;
+
             // This is a simplified implementation
             // In practice, you'd need to remove the specific internal asset
             return Ok(crate::memories::types::AssetRemovalResult {
@@ -325,7 +437,9 @@ pub fn asset_remove_external_core<E: Env, S: Store>(
     let accessible_capsules = store.get_accessible_capsules(&env.caller());
 
     for capsule_id in accessible_capsules {
-        if let Some(_memory) = store.get_memory(&capsule_id, &memory_id) {
+        if let Some(memory) = store.get_memory(&capsule_id, &memory_id) {
+            check_unlocked(&memory)?;
+
             // This is a simplified implementation
             // In practice, you'd need to remove the specific external asset
             return Ok(crate::memories::types::AssetRemovalResult {
@@ -376,6 +490,7 @@ pub fn memories_list_assets_core<E: Env, S: Store>(
                 inline_assets,
                 internal_assets,
                 external_assets,
+                asset_ids: memory.ordered_asset_ids(),
                 total_count: total_count as u32,
             });
         }
@@ -384,6 +499,137 @@ pub fn memories_list_assets_core<E: Env, S: Store>(
     Err(Error::NotFound)
 }
 
+/// Inventory entries of every asset of `memory`: inline, then internal, then external
+fn inventory_entries<B: BlobStore>(memory: &Memory, blobs: &B) -> Vec<AssetInventoryEntry> {
+    use crate::utils::blob_id::parse_blob_id;
+
+    let inline = memory.inline_assets.iter().map(|asset| {
+        let base = asset.metadata.get_base();
+        AssetInventoryEntry {
+            asset_id: asset.asset_id.clone(),
+            kind: AssetStorageKind::Inline,
+            size: asset.bytes.len() as u64,
+            content_type: base.mime_type.clone(),
+            sha256: base.sha256,
+            backend: BlobHosting::Icp,
+            locally_available: true,
+        }
+    });
+    let internal = memory.blob_internal_assets.iter().map(|asset| {
+        let base = asset.metadata.get_base();
+        let stored = asset
+            .blob_ref
+            .locator
+            .rsplit(':')
+            .next()
+            .and_then(|locator| parse_blob_id(locator).ok())
+            .is_some_and(|blob_id| blobs.get_meta(blob_id).is_some());
+        AssetInventoryEntry {
+            asset_id: asset.asset_id.clone(),
+            kind: AssetStorageKind::Internal,
+            size: asset.blob_ref.len,
+            content_type: base.mime_type.clone(),
+            sha256: base.sha256.or(asset.blob_ref.hash),
+            backend: BlobHosting::Icp,
+            locally_available: stored,
+        }
+    });
+    let external = memory.blob_external_assets.iter().map(|asset| {
+        let base = asset.metadata.get_base();
+        AssetInventoryEntry {
+            asset_id: asset.asset_id.clone(),
+            kind: AssetStorageKind::External,
+            size: base.bytes,
+            content_type: base.mime_type.clone(),
+            sha256: base.sha256,
+            backend: blob_hosting_for_edge(&asset.location),
+            locally_available: false,
+        }
+    });
+    inline.chain(internal).chain(external).collect()
+}
+
+/// Every asset of a memory with its size, backend and local availability
+///
+/// Read access is the same as `memories_read_core`. An internal asset is
+/// locally available while its blob metadata is still in `blobs`.
+pub fn memories_asset_inventory_core<E: Env, S: Store, B: BlobStore>(
+    env: &E,
+    store: &S,
+    blobs: &B,
+    memory_id: String,
+) -> std::result::Result<AssetInventory, Error> {
+    let memory = memories_read_core(env, store, memory_id)?;
+    let assets = inventory_entries(&memory, blobs);
+
+    let bytes_of = |kind: AssetStorageKind| -> u64 {
+        assets
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .map(|entry| entry.size)
+            .sum()
+    };
+    Ok(AssetInventory {
+        inline_bytes: bytes_of(AssetStorageKind::Inline),
+        blob_bytes: bytes_of(AssetStorageKind::Internal),
+        external_bytes: bytes_of(AssetStorageKind::External),
+        memory_id: memory.id,
+        capsule_id: memory.capsule_id,
+        assets,
+    })
+}
+
+/// Asset bytes and counts of a capsule per backend, across all of its memories
+///
+/// Built from the same entries as `memories_asset_inventory_core`, keyed by
+/// backend and storage kind so ICP inline bytes and ICP blobs stay apart. The
+/// caller needs read access to the capsule.
+pub fn capsules_storage_distribution_core<E: Env, S: Store, B: BlobStore>(
+    env: &E,
+    store: &S,
+    blobs: &B,
+    capsule_id: CapsuleId,
+) -> std::result::Result<StorageDistribution, Error> {
+    let capsule_access = store
+        .get_capsule_for_acl(&capsule_id)
+        .ok_or(Error::NotFound)?;
+    if !capsule_access.can_read(&env.caller()) {
+        return Err(Error::Unauthorized);
+    }
+
+    let mut memories = store.get_all_memories(&capsule_id);
+    memories.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut backends: Vec<BackendUsage> = Vec::new();
+    for entry in memories
+        .iter()
+        .flat_map(|memory| inventory_entries(memory, blobs))
+    {
+        match backends
+            .iter_mut()
+            .find(|usage| usage.backend == entry.backend && usage.kind == entry.kind)
+        {
+            Some(usage) => {
+                usage.asset_count += 1;
+                usage.bytes += entry.size;
+            }
+            None => backends.push(BackendUsage {
+                backend: entry.backend,
+                kind: entry.kind,
+                asset_count: 1,
+                bytes: entry.size,
+            }),
+        }
+    }
+
+    Ok(StorageDistribution {
+        capsule_id,
+        memory_count: memories.len() as u32,
+        total_bytes: backends.iter().map(|usage| usage.bytes).sum(),
+        backends,
+    })
+}
+
 /// Core asset removal by asset_id function - pure business logic
 pub fn asset_remove_by_id_core<E: Env, S: Store>(
     env: &E,
@@ -396,6 +642,8 @@ pub fn asset_remove_by_id_core<E: Env, S: Store>(
 
     for capsule_id in accessible_capsules {
         if let Some(mut memory) = store.get_memory(&capsule_id, &memory_id) {
+            check_unlocked(&memory)?;
+
             // Try to find and remove from inline assets
             if let Some(index) = memory
                 .inline_assets
@@ -419,7 +667,7 @@ pub fn asset_remove_by_id_core<E: Env, S: Store>(
             {
                 let asset = memory.blob_internal_assets.remove(index);
                 // Clean up the blob from storage
-                cleanup_internal_blob_asset(&asset.blob_ref)?;
+                cleanup_internal_blob_asset(store, &capsule_id, &memory_id, &asset.blob_ref)?;
                 let _ = store.update_memory(&capsule_id, &memory_id, memory);
                 return Ok(crate::memories::types::AssetRemovalResult {
                     memory_id: memory_id.clone(),
@@ -506,3 +754,237 @@ pub fn asset_get_by_id_core<E: Env, S: Store>(
 
     Err(Error::NotFound)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mixed_asset_memory, mock_capsule, MockEnv};
+    use crate::types::PersonRef;
+    use candid::Principal;
+
+    #[test]
+    fn test_memories_asset_inventory_inline_and_internal() {
+        use crate::memories::core::memories_asset_inventory_core;
+        use crate::memories::types::AssetStorageKind;
+        use crate::types::BlobHosting;
+        use crate::upload::blob_store::{BlobStore, InMemoryBlobStore};
+        use crate::upload::types::BlobMeta;
+
+        let blob_store = InMemoryBlobStore::new(1);
+        blob_store.put_meta(
+            9_002,
+            BlobMeta {
+                size: 1024,
+                checksum: [0; 32],
+                created_at: 1,
+                pmid_hash: [9u8; 32],
+                capsule_id: Some("capsule_inventory".to_string()),
+            },
+        );
+
+        let (env, mut store) = mock_capsule("capsule_inventory");
+        let capsule_id = "capsule_inventory".to_string();
+        let mut memory = mixed_asset_memory(&env, "mem_inv", &capsule_id, "blob_9002");
+        memory.blob_external_assets.clear();
+        store.insert_memory(&capsule_id, memory).unwrap();
+
+        let inventory =
+            memories_asset_inventory_core(&env, &store, &blob_store, "mem_inv".to_string())
+                .unwrap();
+
+        let summary: Vec<_> = inventory
+            .assets
+            .iter()
+            .map(|a| (a.kind, a.size, a.backend.clone(), a.locally_available))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (AssetStorageKind::Inline, 16, BlobHosting::Icp, true),
+                (AssetStorageKind::Internal, 1024, BlobHosting::Icp, true),
+            ]
+        );
+        assert_eq!(inventory.assets[0].content_type, "image/jpeg");
+        assert_eq!(inventory.assets[1].sha256, Some([1u8; 32]));
+        assert_eq!(
+            (
+                inventory.inline_bytes,
+                inventory.blob_bytes,
+                inventory.external_bytes
+            ),
+            (16, 1024, 0)
+        );
+
+        // Callers who can't read the memory get NotFound
+        let stranger = MockEnv {
+            caller: PersonRef::Principal(Principal::from_slice(&[9, 9, 9])),
+            now: env.now,
+        };
+        store.capsules.clear();
+        assert_eq!(
+            memories_asset_inventory_core(&stranger, &store, &blob_store, "mem_inv".to_string()),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_capsules_storage_distribution_totals_per_backend() {
+        use crate::memories::core::capsules_storage_distribution_core;
+        use crate::memories::types::{AssetStorageKind, BackendUsage};
+        use crate::types::BlobHosting;
+        use crate::upload::blob_store::InMemoryBlobStore;
+
+        let blob_store = InMemoryBlobStore::new(1);
+        let (env, mut store) = mock_capsule("capsule_dist");
+        let capsule_id = "capsule_dist".to_string();
+        let photo = mixed_asset_memory(&env, "mem_a", &capsule_id, "blob_9101");
+        let mut archived = mixed_asset_memory(&env, "mem_b", &capsule_id, "blob_9102");
+        archived.blob_external_assets[0].location = StorageEdgeBlobType::Arweave;
+        store.insert_memory(&capsule_id, photo).unwrap();
+        store.insert_memory(&capsule_id, archived).unwrap();
+
+        let distribution =
+            capsules_storage_distribution_core(&env, &store, &blob_store, capsule_id.clone())
+                .unwrap();
+
+        let usage = |backend, kind, asset_count, bytes| BackendUsage {
+            backend,
+            kind,
+            asset_count,
+            bytes,
+        };
+        assert_eq!(distribution.memory_count, 2);
+        assert_eq!(
+            distribution.backends,
+            vec![
+                usage(BlobHosting::Icp, AssetStorageKind::Inline, 2, 32),
+                usage(BlobHosting::Icp, AssetStorageKind::Internal, 2, 2048),
+                usage(BlobHosting::S3, AssetStorageKind::External, 1, 1024),
+                usage(BlobHosting::Arweave, AssetStorageKind::External, 1, 1024),
+            ]
+        );
+        assert_eq!(distribution.total_bytes, 32 + 2048 + 2048);
+
+        let stranger = MockEnv {
+            caller: PersonRef::Principal(Principal::from_slice(&[9, 9, 9])),
+            now: env.now,
+        };
+        assert_eq!(
+            capsules_storage_distribution_core(&stranger, &store, &blob_store, capsule_id),
+            Err(Error::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn test_remove_assets_by_kind_external_only() {
+        use crate::memories::core::memories_remove_assets_by_kind_core;
+        use crate::memories::types::AssetStorageKind;
+
+        let (env, mut store) = mock_capsule("capsule_kind_ext");
+        let capsule_id = "capsule_kind_ext".to_string();
+        store
+            .insert_memory(
+                &capsule_id,
+                mixed_asset_memory(&env, "mem_ext", &capsule_id, "blob_1"),
+            )
+            .unwrap();
+
+        let result = memories_remove_assets_by_kind_core(
+            &env,
+            &mut store,
+            "mem_ext".to_string(),
+            AssetStorageKind::External,
+        )
+        .unwrap();
+
+        assert_eq!(
+            (
+                result.inline_removed,
+                result.internal_removed,
+                result.external_removed
+            ),
+            (0, 0, 1)
+        );
+        let memory = store
+            .get_memory(&capsule_id, &"mem_ext".to_string())
+            .unwrap();
+        assert!(memory.blob_external_assets.is_empty());
+        assert_eq!(memory.inline_assets.len(), 1);
+        assert_eq!(memory.blob_internal_assets.len(), 1);
+        assert_eq!(memory.metadata.asset_count, 2);
+    }
+
+    #[test]
+    fn test_remove_assets_by_kind_internal_only_frees_blobs() {
+        use crate::capsule_store::CapsuleStore;
+        use crate::memories::core::memories_remove_assets_by_kind_core;
+        use crate::memories::types::AssetStorageKind;
+        use crate::upload::blob_store::{BlobStore, StableBlobStore};
+        use crate::upload::types::BlobMeta;
+
+        // The blob's bytes are charged to the capsule in the canister store
+        let mut charged = crate::test_utils::create_test_capsule_at(
+            "capsule_kind_int",
+            PersonRef::Principal(Principal::anonymous()),
+            1,
+        );
+        charged.charge_blob_bytes(1024);
+        crate::memory::with_capsule_store_mut(|capsules| {
+            capsules.upsert("capsule_kind_int".to_string(), charged)
+        });
+
+        let blob_store = StableBlobStore;
+        blob_store.put_page([9u8; 32], 0, vec![0u8; 1024]);
+        blob_store.put_meta(
+            9_001,
+            BlobMeta {
+                size: 1024,
+                checksum: [0; 32],
+                created_at: 1,
+                pmid_hash: [9u8; 32],
+                capsule_id: Some("capsule_kind_int".to_string()),
+            },
+        );
+
+        let (env, mut store) = mock_capsule("capsule_kind_int");
+        let capsule_id = "capsule_kind_int".to_string();
+        store
+            .insert_memory(
+                &capsule_id,
+                mixed_asset_memory(&env, "mem_int", &capsule_id, "blob_9001"),
+            )
+            .unwrap();
+
+        let result = memories_remove_assets_by_kind_core(
+            &env,
+            &mut store,
+            "mem_int".to_string(),
+            AssetStorageKind::Internal,
+        )
+        .unwrap();
+
+        assert_eq!(
+            (
+                result.inline_removed,
+                result.internal_removed,
+                result.external_removed
+            ),
+            (0, 1, 0)
+        );
+        assert!(blob_store.get_meta(9_001).is_none());
+        assert!(blob_store.get_page(&[9u8; 32], 0).is_none());
+        let used = crate::memory::with_capsule_store(|capsules| {
+            capsules
+                .get(&"capsule_kind_int".to_string())
+                .map(|capsule| capsule.blob_bytes_used())
+        });
+        assert_eq!(used, Some(0));
+
+        let memory = store
+            .get_memory(&capsule_id, &"mem_int".to_string())
+            .unwrap();
+        assert!(memory.blob_internal_assets.is_empty());
+        assert_eq!(memory.inline_assets.len(), 1);
+        assert_eq!(memory.blob_external_assets.len(), 1);
+    }
+}
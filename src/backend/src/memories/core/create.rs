@@ -5,11 +5,61 @@
 
 use super::{model_helpers::*, traits::*};
 use crate::capsule_acl::CapsuleAcl;
-use crate::memories::types::{InternalBlobAssetInput, MemoryMetadata};
+use crate::memories::types::{
+    InternalBlobAssetInput, MemoryCreateInput, MemoryCreateOptions, MemoryMetadata,
+};
 use crate::types::{
-    AssetMetadata, BlobRef, CapsuleId, Error, Memory, MemoryAssetBlobInternal, MemoryId,
-    StorageEdgeBlobType,
+    AssetMetadata, BlobRef, BulkFailure, BulkResult, CapsuleId, Error, Memory,
+    MemoryAssetBlobInternal, MemoryId, PersonRef,
 };
+use crate::upload::types::CAPSULE_INLINE_BUDGET;
+
+/// Maximum number of memories in one `memories_create_bulk` call
+pub const MAX_BULK_CREATE: usize = 100;
+
+/// Idempotency scope of `memories_create`
+const IDEM_SCOPE_CREATE: &str = "memories_create";
+/// Idempotency scope of `memories_create_with_internal_blobs`
+const IDEM_SCOPE_CREATE_WITH_BLOBS: &str = "memories_create_with_internal_blobs";
+/// Idempotency scope of `memories_reserve`
+const IDEM_SCOPE_RESERVE: &str = "memories_reserve";
+
+/// Outcome of resolving an idempotency key to a memory id
+enum IdemResolution {
+    /// Key was used within the TTL and its memory still exists
+    Existing(MemoryId),
+    /// Key is new (or expired) - create a memory with this id
+    Fresh(MemoryId),
+}
+
+/// Resolve an idempotency key (scoped to caller + endpoint) to a memory id
+///
+/// First use keeps the deterministic id derived from the key; a key reused
+/// after its TTL gets an id derived from the key and the current time so the
+/// new memory doesn't collide with the one created earlier.
+fn resolve_memory_id<S: Store>(
+    store: &S,
+    scope: &str,
+    caller: &PersonRef,
+    capsule_id: &CapsuleId,
+    idem: &str,
+    now: u64,
+) -> IdemResolution {
+    if let Some(existing) = store.idem_lookup(scope, caller, idem, now) {
+        if store.get_memory(capsule_id, &existing).is_some() {
+            return IdemResolution::Existing(existing);
+        }
+    }
+
+    let memory_id = generate_deterministic_uuid_from_idem(idem);
+    if store.get_memory(capsule_id, &memory_id).is_none() {
+        IdemResolution::Fresh(memory_id)
+    } else {
+        IdemResolution::Fresh(generate_deterministic_uuid_from_idem(&format!(
+            "{idem}#{now}"
+        )))
+    }
+}
 
 /// Core memory creation function - pure business logic
 ///
@@ -19,16 +69,22 @@ pub fn memories_create_core<E: Env, S: Store>(
     env: &E,
     store: &mut S,
     capsule_id: CapsuleId,
-    bytes: Option<Vec<u8>>,
-    blob_ref: Option<BlobRef>,
-    external_location: Option<StorageEdgeBlobType>,
-    external_storage_key: Option<String>,
-    external_url: Option<String>,
-    external_size: Option<u64>,
-    external_hash: Option<Vec<u8>>,
-    asset_metadata: AssetMetadata,
-    _idem: String,
+    input: MemoryCreateInput,
+    idem: String,
+    options: MemoryCreateOptions,
 ) -> std::result::Result<MemoryId, Error> {
+    let MemoryCreateInput {
+        bytes,
+        blob_ref,
+        external_location,
+        external_storage_key,
+        external_url,
+        external_size,
+        external_hash,
+        asset_metadata,
+    } = input;
+    let MemoryCreateOptions { expires_at, access } = options;
+
     // Validate that exactly one asset type is provided
     let asset_count =
         bytes.is_some() as u8 + blob_ref.is_some() as u8 + external_location.is_some() as u8;
@@ -113,16 +169,20 @@ pub fn memories_create_core<E: Env, S: Store>(
         capsule_access.can_delete(&caller)
     );
 
+    // Only owners decide when a memory auto-deletes
+    if expires_at.is_some() && !capsule_access.owners.contains_key(&caller) {
+        return Err(Error::Unauthorized);
+    }
+
     // Capture timestamp once for consistency
     let now = env.now();
 
-    // Generate deterministic UUID from idempotency key for proper idempotency
-    let memory_id = generate_deterministic_uuid_from_idem(&_idem);
-
-    // Check for existing memory (idempotency)
-    if let Some(_existing) = store.get_memory(&capsule_id, &memory_id) {
-        return Ok(memory_id); // Return existing ID for idempotency
-    }
+    // Idempotency: return the memory created for this key within the TTL
+    let memory_id =
+        match resolve_memory_id(store, IDEM_SCOPE_CREATE, &caller, &capsule_id, &idem, now) {
+            IdemResolution::Existing(memory_id) => return Ok(memory_id),
+            IdemResolution::Fresh(memory_id) => memory_id,
+        };
 
     // Create memory based on asset type
     let mut memory = if let Some(bytes_data) = bytes {
@@ -155,6 +215,15 @@ pub fn memories_create_core<E: Env, S: Store>(
         ));
     };
 
+    memory.expires_at = expires_at;
+
+    // The creator's choice wins over the capsule default
+    apply_initial_access(
+        &mut memory,
+        access.unwrap_or(capsule_access.default_memory_access),
+        now,
+    );
+
     // NEW: Compute and store dashboard fields
     memory.update_dashboard_fields();
 
@@ -169,6 +238,8 @@ pub fn memories_create_core<E: Env, S: Store>(
         ));
     }
 
+    store.idem_record(IDEM_SCOPE_CREATE, &caller, &idem, &memory_id, now);
+
     // Debug: Log successful memory creation
     ic_cdk::println!(
         "[DEBUG] memories_create: successfully created memory {} in capsule {}",
@@ -179,6 +250,62 @@ pub fn memories_create_core<E: Env, S: Store>(
     Ok(memory_id)
 }
 
+/// Create the memory for a committed upload blob, then run `place` on it
+///
+/// Used to land an upload somewhere in one call (e.g. a gallery). When `place`
+/// fails the memory is deleted again, but only if this call created it: a retry
+/// with the same `idem` gets the memory an earlier call created, and that stays.
+pub fn memories_create_and_place_core<E: Env, S: Store, T>(
+    env: &E,
+    store: &mut S,
+    capsule_id: CapsuleId,
+    blob_ref: BlobRef,
+    asset_metadata: AssetMetadata,
+    idem: String,
+    place: impl FnOnce(&mut S, &MemoryId) -> std::result::Result<T, Error>,
+) -> std::result::Result<(MemoryId, T), Error> {
+    let caller = env.caller();
+    let existed = matches!(
+        resolve_memory_id(
+            store,
+            IDEM_SCOPE_CREATE,
+            &caller,
+            &capsule_id,
+            &idem,
+            env.now()
+        ),
+        IdemResolution::Existing(_)
+    );
+
+    let input = MemoryCreateInput {
+        bytes: None,
+        blob_ref: Some(blob_ref),
+        external_location: None,
+        external_storage_key: None,
+        external_url: None,
+        external_size: None,
+        external_hash: None,
+        asset_metadata,
+    };
+    let memory_id = memories_create_core(
+        env,
+        store,
+        capsule_id.clone(),
+        input,
+        idem,
+        MemoryCreateOptions::default(),
+    )?;
+    match place(store, &memory_id) {
+        Ok(placed) => Ok((memory_id, placed)),
+        Err(err) => {
+            if !existed {
+                let _ = store.delete_memory(&capsule_id, &memory_id);
+            }
+            Err(err)
+        }
+    }
+}
+
 /// Create memory with internal blob assets (ICP blob storage)
 ///
 /// This function creates a memory with one or more internal blob assets.
@@ -189,7 +316,7 @@ pub fn memories_create_with_internal_blobs_core<E: Env, S: Store>(
     capsule_id: CapsuleId,
     memory_metadata: MemoryMetadata,
     internal_blob_assets: Vec<InternalBlobAssetInput>,
-    _idem: String,
+    idem: String,
 ) -> std::result::Result<MemoryId, Error> {
     // Validate inputs
     if internal_blob_assets.is_empty() {
@@ -214,13 +341,18 @@ pub fn memories_create_with_internal_blobs_core<E: Env, S: Store>(
     // Capture timestamp once for consistency
     let now = env.now();
 
-    // Generate deterministic UUID from idempotency key for proper idempotency
-    let memory_id = generate_deterministic_uuid_from_idem(&_idem);
-
-    // Check for existing memory (idempotency)
-    if let Some(_existing) = store.get_memory(&capsule_id, &memory_id) {
-        return Ok(memory_id); // Return existing ID for idempotency
-    }
+    // Idempotency: return the memory created for this key within the TTL
+    let memory_id = match resolve_memory_id(
+        store,
+        IDEM_SCOPE_CREATE_WITH_BLOBS,
+        &caller,
+        &capsule_id,
+        &idem,
+        now,
+    ) {
+        IdemResolution::Existing(memory_id) => return Ok(memory_id),
+        IdemResolution::Fresh(memory_id) => memory_id,
+    };
 
     // Create memory with multiple internal blob assets
     let mut blob_internal_assets = Vec::new();
@@ -251,6 +383,7 @@ pub fn memories_create_with_internal_blobs_core<E: Env, S: Store>(
             asset_id: generate_asset_id(&caller, now),
             blob_ref,
             metadata: asset_input.metadata.clone(),
+            order: None,
         };
 
         blob_internal_assets.push(blob_asset);
@@ -272,7 +405,11 @@ pub fn memories_create_with_internal_blobs_core<E: Env, S: Store>(
         inline_assets: vec![],
         blob_internal_assets,
         blob_external_assets: vec![],
+        locked: None,
+        pending: None,
+        expires_at: None,
     };
+    apply_initial_access(&mut memory, capsule_access.default_memory_access, now);
 
     // NEW: Compute and store dashboard fields
     memory.update_dashboard_fields();
@@ -287,161 +424,286 @@ pub fn memories_create_with_internal_blobs_core<E: Env, S: Store>(
         ));
     }
 
+    store.idem_record(
+        IDEM_SCOPE_CREATE_WITH_BLOBS,
+        &caller,
+        &idem,
+        &memory_id,
+        now,
+    );
+
     // Memory creation successful - no logging in pure function
 
     Ok(memory_id)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::capsule::domain::SharingStatus;
-    use crate::capsule_acl::CapsuleAccess;
-    use crate::memories::types::{
-        AssetMetadata, AssetMetadataBase, AssetType, ImageAssetMetadata, MemoryMetadata, MemoryType,
-    };
-    use crate::types::{OwnerState, PersonRef};
-    use candid::Principal;
-    use std::collections::HashMap;
+/// Reserve a memory id before its bytes are uploaded
+///
+/// Creates a placeholder with no assets whose `pending` holds the metadata of
+/// the asset still uploading; `memories_attach_blob` completes it. A placeholder
+/// has no bytes, so it counts against neither the inline budget nor the blob
+/// quota until the upload is attached.
+pub fn memories_reserve_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    capsule_id: CapsuleId,
+    asset_metadata: AssetMetadata,
+    idem: String,
+) -> std::result::Result<MemoryId, Error> {
+    let caller = env.caller();
+    let capsule_access = store
+        .get_capsule_for_acl(&capsule_id)
+        .ok_or(Error::NotFound)?;
+    if !capsule_access.can_write(&caller) {
+        return Err(Error::Unauthorized);
+    }
 
-    // Mock implementations for testing
-    struct MockEnv {
-        caller: PersonRef,
-        now: u64,
+    let now = env.now();
+    let memory_id =
+        match resolve_memory_id(store, IDEM_SCOPE_RESERVE, &caller, &capsule_id, &idem, now) {
+            IdemResolution::Existing(memory_id) => return Ok(memory_id),
+            IdemResolution::Fresh(memory_id) => memory_id,
+        };
+
+    // Same metadata a real create would derive, minus the asset itself
+    let mut memory = create_inline_memory(
+        &memory_id,
+        &capsule_id,
+        vec![],
+        asset_metadata.clone(),
+        now,
+        &caller,
+    );
+    memory.inline_assets.clear();
+    memory.pending = Some(asset_metadata);
+    apply_initial_access(&mut memory, capsule_access.default_memory_access, now);
+    memory.update_dashboard_fields();
+
+    store.insert_memory(&capsule_id, memory)?;
+
+    // POST-WRITE ASSERTION: Verify the placeholder was actually created
+    if store.get_memory(&capsule_id, &memory_id).is_none() {
+        return Err(Error::Internal(
+            "Post-write readback failed: memory was not persisted".to_string(),
+        ));
     }
 
-    impl Env for MockEnv {
-        fn caller(&self) -> PersonRef {
-            self.caller.clone()
-        }
+    store.idem_record(IDEM_SCOPE_RESERVE, &caller, &idem, &memory_id, now);
 
-        fn now(&self) -> u64 {
-            self.now
-        }
+    Ok(memory_id)
+}
+
+/// Inline bytes currently stored in a capsule's memories
+fn capsule_inline_bytes<S: Store>(store: &S, capsule_id: &CapsuleId) -> u64 {
+    store
+        .get_all_memories(capsule_id)
+        .iter()
+        .flat_map(|memory| memory.inline_assets.iter())
+        .map(|asset| asset.bytes.len() as u64)
+        .sum()
+}
+
+/// Idempotency key of item `index` in a bulk create
+fn bulk_item_idem(idem: &str, index: usize) -> String {
+    format!("{idem}:{index}")
+}
+
+/// Create several memories in one call, reusing `memories_create_core` per item
+///
+/// The capsule inline budget is checked for the whole batch up front: if any
+/// inline item would push the capsule over `CAPSULE_INLINE_BUDGET`, nothing is
+/// created and those items are reported as `ResourceExhausted`. Otherwise each
+/// item is created independently and per-item validation errors are reported
+/// without affecting the others.
+///
+/// Each item uses the idempotency key `{idem}:{index}`; failures are reported
+/// under that key since they have no memory id.
+pub fn memories_create_bulk_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    capsule_id: CapsuleId,
+    items: Vec<MemoryCreateInput>,
+    idem: String,
+) -> std::result::Result<BulkResult<MemoryId>, Error> {
+    if items.is_empty() {
+        return Err(Error::InvalidArgument(
+            "items must not be empty".to_string(),
+        ));
+    }
+    if items.len() > MAX_BULK_CREATE {
+        return Err(Error::InvalidArgument(format!(
+            "too many items: {} (max {})",
+            items.len(),
+            MAX_BULK_CREATE
+        )));
     }
 
-    struct MockStore {
-        memories: HashMap<(CapsuleId, MemoryId), Memory>,
-        capsules: HashMap<CapsuleId, CapsuleAccess>,
+    let caller = env.caller();
+    let capsule_access = store
+        .get_capsule_for_acl(&capsule_id)
+        .ok_or(Error::NotFound)?;
+    if !capsule_access.can_write(&caller) {
+        return Err(Error::Unauthorized);
     }
 
-    impl MockStore {
-        fn new() -> Self {
-            Self {
-                memories: HashMap::new(),
-                capsules: HashMap::new(),
-            }
+    // Whole-batch inline budget check (retried items that already exist don't count twice)
+    let now = env.now();
+    let mut inline_used = capsule_inline_bytes(store, &capsule_id);
+    let mut over_budget = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let bytes = match &item.bytes {
+            Some(bytes) => bytes,
+            None => continue,
+        };
+        let item_idem = bulk_item_idem(&idem, index);
+        if let IdemResolution::Existing(_) = resolve_memory_id(
+            store,
+            IDEM_SCOPE_CREATE,
+            &caller,
+            &capsule_id,
+            &item_idem,
+            now,
+        ) {
+            continue;
         }
 
-        fn add_capsule(&mut self, capsule_id: CapsuleId, access: CapsuleAccess) {
-            self.capsules.insert(capsule_id, access);
+        inline_used += bytes.len() as u64;
+        if inline_used > CAPSULE_INLINE_BUDGET {
+            over_budget.push(BulkFailure {
+                id: item_idem,
+                err: Error::ResourceExhausted,
+            });
         }
     }
+    if !over_budget.is_empty() {
+        return Ok(BulkResult {
+            ok: vec![],
+            failed: over_budget,
+        });
+    }
 
-    impl Store for MockStore {
-        fn insert_memory(
-            &mut self,
-            capsule: &CapsuleId,
-            memory: Memory,
-        ) -> std::result::Result<(), Error> {
-            self.memories
-                .insert((capsule.clone(), memory.id.clone()), memory);
-            Ok(())
+    let mut result = BulkResult {
+        ok: Vec::with_capacity(items.len()),
+        failed: vec![],
+    };
+    for (index, item) in items.into_iter().enumerate() {
+        let item_idem = bulk_item_idem(&idem, index);
+        match memories_create_core(
+            env,
+            store,
+            capsule_id.clone(),
+            item,
+            item_idem.clone(),
+            MemoryCreateOptions::default(),
+        ) {
+            Ok(memory_id) => result.ok.push(memory_id),
+            Err(err) => result.failed.push(BulkFailure { id: item_idem, err }),
         }
+    }
 
-        fn get_memory(&self, capsule: &CapsuleId, id: &MemoryId) -> Option<Memory> {
-            self.memories.get(&(capsule.clone(), id.clone())).cloned()
-        }
+    Ok(result)
+}
 
-        fn delete_memory(
-            &mut self,
-            capsule: &CapsuleId,
-            id: &MemoryId,
-        ) -> std::result::Result<(), Error> {
-            self.memories.remove(&(capsule.clone(), id.clone()));
-            Ok(())
-        }
+/// Copy a memory into `target_capsule_id` (defaults to the source capsule) under a fresh id
+///
+/// Internal blob assets keep their locators, so the copy shares the stored bytes
+/// instead of re-uploading them; inline bytes and external references are copied
+/// as-is. A shared blob is only freed once no memory references it.
+///
+/// A blob's bytes are charged to one capsule, so a memory with internal blob
+/// assets can only be duplicated within its own capsule.
+///
+/// The caller needs read access to the source capsule and write access to the target.
+pub fn memories_duplicate_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    memory_id: MemoryId,
+    target_capsule_id: Option<CapsuleId>,
+) -> std::result::Result<MemoryId, Error> {
+    let caller = env.caller();
 
-        fn update_memory(
-            &mut self,
-            capsule: &CapsuleId,
-            id: &MemoryId,
-            memory: Memory,
-        ) -> std::result::Result<(), Error> {
-            self.memories.insert((capsule.clone(), id.clone()), memory);
-            Ok(())
-        }
+    let (source_capsule_id, source) = store
+        .get_accessible_capsules(&caller)
+        .into_iter()
+        .find_map(|capsule_id| {
+            store
+                .get_memory(&capsule_id, &memory_id)
+                .map(|memory| (capsule_id, memory))
+        })
+        .ok_or(Error::NotFound)?;
 
-        fn get_all_memories(&self, capsule: &CapsuleId) -> Vec<Memory> {
-            self.memories
-                .iter()
-                .filter(|((c, _), _)| c == capsule)
-                .map(|(_, memory)| memory.clone())
-                .collect()
-        }
+    let source_access = store
+        .get_capsule_for_acl(&source_capsule_id)
+        .ok_or(Error::NotFound)?;
+    if !source_access.can_read(&caller) {
+        return Err(Error::Unauthorized);
+    }
 
-        fn get_accessible_capsules(&self, _caller: &PersonRef) -> Vec<CapsuleId> {
-            self.capsules.keys().cloned().collect()
-        }
+    let target_capsule_id = target_capsule_id.unwrap_or_else(|| source_capsule_id.clone());
+    let target_access = store
+        .get_capsule_for_acl(&target_capsule_id)
+        .ok_or(Error::NotFound)?;
+    if !target_access.can_write(&caller) {
+        return Err(Error::Unauthorized);
+    }
+    if target_capsule_id != source_capsule_id && !source.blob_internal_assets.is_empty() {
+        return Err(Error::InvalidArgument(
+            "memories with internal blobs can only be duplicated within their capsule".to_string(),
+        ));
+    }
 
-        fn get_capsule_for_acl(&self, capsule_id: &CapsuleId) -> Option<CapsuleAccess> {
-            self.capsules.get(capsule_id).cloned()
-        }
+    // Inline bytes are copied, so they count against the target's budget
+    let inline_len: u64 = source
+        .inline_assets
+        .iter()
+        .map(|asset| asset.bytes.len() as u64)
+        .sum();
+    if inline_len > 0
+        && capsule_inline_bytes(store, &target_capsule_id) + inline_len > CAPSULE_INLINE_BUDGET
+    {
+        return Err(Error::ResourceExhausted);
+    }
 
-        fn clear_all_memories_in_capsule(
-            &mut self,
-            capsule_id: &str,
-        ) -> std::result::Result<(), Error> {
-            self.memories
-                .retain(|(capsule, _), _| capsule != capsule_id);
-            Ok(())
-        }
+    let now = env.now();
+    let new_id = generate_uuid_v7();
+
+    let mut memory = source;
+    memory.id = new_id.clone();
+    memory.capsule_id = target_capsule_id.clone();
+    memory.access_entries = vec![create_owner_access_entry(&caller, now)];
+    apply_initial_access(&mut memory, target_access.default_memory_access, now);
+    memory.metadata.created_at = now;
+    memory.metadata.updated_at = now;
+    // A copy starts editable even when the original is frozen
+    memory.locked = None;
+    if target_capsule_id != source_capsule_id {
+        // Folders are per-capsule; the source folder doesn't exist in the target
+        memory.metadata.parent_folder_id = None;
+    }
+    memory.update_dashboard_fields();
 
-        fn clear_all_internal_blobs_in_capsule(
-            &mut self,
-            _capsule_id: &str,
-        ) -> std::result::Result<(), Error> {
-            // For mock implementation, we don't need to actually clear blobs
-            // since we're not managing real blob storage
-            Ok(())
-        }
+    store.insert_memory(&target_capsule_id, memory)?;
 
-        fn capsule_exists(&self, capsule_id: &str) -> bool {
-            self.capsules.contains_key(capsule_id)
-        }
+    // POST-WRITE ASSERTION: Verify the copy was actually created
+    if store.get_memory(&target_capsule_id, &new_id).is_none() {
+        return Err(Error::Internal(
+            "Post-write readback failed: duplicate was not persisted".to_string(),
+        ));
     }
 
-    fn create_test_asset_metadata() -> AssetMetadata {
-        let base = AssetMetadataBase {
-            name: "test_image.jpg".to_string(),
-            description: Some("Test image for unit testing".to_string()),
-            tags: vec!["test".to_string(), "unit".to_string()],
-            asset_type: AssetType::Original,
-            bytes: 1024,
-            mime_type: "image/jpeg".to_string(),
-            sha256: Some([1u8; 32]),
-            width: Some(1920),
-            height: Some(1080),
-            url: None,
-            storage_key: None,
-            bucket: None,
-            asset_location: None,
-            processing_status: None,
-            processing_error: None,
-            created_at: 1234567890,
-            updated_at: 1234567890,
-            deleted_at: None,
-        };
+    Ok(new_id)
+}
 
-        AssetMetadata::Image(ImageAssetMetadata {
-            base,
-            color_space: Some("sRGB".to_string()),
-            exif_data: None,
-            compression_ratio: Some(0.8),
-            dpi: Some(72),
-            orientation: Some(1),
-        })
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capsule::domain::SharingStatus;
+    use crate::capsule_acl::CapsuleAccess;
+    use crate::memories::types::{AssetMetadata, MemoryMetadata, MemoryType};
+    use crate::test_utils::{create_test_asset_metadata, mock_capsule, MockEnv, MockStore};
+    use crate::types::{OwnerState, PersonRef};
+    use candid::Principal;
+    use std::collections::HashMap;
 
     fn create_test_memory_metadata() -> MemoryMetadata {
         MemoryMetadata {
@@ -471,6 +733,94 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_new_memories_follow_capsule_default_access() {
+        use crate::capsule::commands::apply_memory_access_settings;
+        use crate::capsule::domain::{Capsule, DefaultMemoryAccess};
+        use crate::types::UserSettingsUpdateData;
+
+        let caller = PersonRef::Principal(Principal::from_slice(&[1, 2, 3, 4, 5]));
+        let env = MockEnv {
+            caller: caller.clone(),
+            now: 1234567890,
+        };
+        let mut capsule =
+            crate::test_utils::create_test_capsule_at("capsule_defaults", caller.clone(), 1);
+        let settings = |access, allow| UserSettingsUpdateData {
+            has_advanced_settings: None,
+            default_memory_access: access,
+            allow_public_memory_default: allow,
+        };
+        let create = |capsule: &Capsule, idem: &str| {
+            let mut store = MockStore::new();
+            let capsule_access = CapsuleAccess::new(
+                capsule.subject.clone(),
+                capsule.owners.clone(),
+                capsule.controllers.clone(),
+            )
+            .with_default_memory_access(capsule.memory_access_default());
+            store.add_capsule(capsule.id.clone(), capsule_access);
+            let memory_id = memories_create_with_internal_blobs_core(
+                &env,
+                &mut store,
+                capsule.id.clone(),
+                create_test_memory_metadata(),
+                vec![InternalBlobAssetInput {
+                    blob_id: "blob_1234567890".to_string(),
+                    metadata: create_test_asset_metadata(),
+                }],
+                idem.to_string(),
+            )
+            .unwrap();
+            store.get_memory(&capsule.id, &memory_id).unwrap()
+        };
+
+        // Private by default: only the owner entry
+        let private = create(&capsule, "idem-default-private");
+        assert_eq!(private.access_entries.len(), 1);
+        assert!(!private.access_entries.iter().any(|entry| entry.is_public));
+
+        // A public default needs the owner's opt-in, which only owners can give
+        assert!(matches!(
+            apply_memory_access_settings(
+                &mut capsule,
+                &caller,
+                &settings(Some(DefaultMemoryAccess::Public), None)
+            ),
+            Err(Error::InvalidArgument(_))
+        ));
+        let stranger = PersonRef::Opaque("stranger".to_string());
+        assert!(matches!(
+            apply_memory_access_settings(&mut capsule, &stranger, &settings(None, Some(true))),
+            Err(Error::Unauthorized)
+        ));
+        apply_memory_access_settings(
+            &mut capsule,
+            &caller,
+            &settings(Some(DefaultMemoryAccess::Public), Some(true)),
+        )
+        .unwrap();
+
+        let public = create(&capsule, "idem-default-public");
+        assert_eq!(public.metadata.sharing_status, SharingStatus::Public);
+        let entry = public
+            .access_entries
+            .iter()
+            .find(|entry| entry.is_public)
+            .unwrap();
+        assert_eq!(
+            entry.perm_mask,
+            (crate::capsule::domain::Perm::VIEW | crate::capsule::domain::Perm::DOWNLOAD).bits()
+        );
+
+        // Opting out puts the default back to Private
+        apply_memory_access_settings(&mut capsule, &caller, &settings(None, Some(false))).unwrap();
+        assert_eq!(
+            capsule.memory_access_default(),
+            DefaultMemoryAccess::Private
+        );
+    }
+
     #[test]
     fn test_memories_create_with_internal_blobs_single_asset() {
         // Setup
@@ -827,4 +1177,310 @@ mod tests {
         let memories = store.get_all_memories(&capsule_id);
         assert_eq!(memories.len(), 1);
     }
+
+    #[test]
+    fn test_memories_create_idempotency_key_expires_after_ttl() {
+        let caller = PersonRef::Principal(Principal::from_slice(&[1, 2, 3, 4, 5]));
+        let capsule_id = "capsule_idem_ttl".to_string();
+        let mut store = MockStore::new();
+        let mut owners = HashMap::new();
+        owners.insert(
+            caller.clone(),
+            OwnerState {
+                since: 0,
+                last_activity_at: 0,
+            },
+        );
+        store.add_capsule(
+            capsule_id.clone(),
+            CapsuleAccess::new(caller.clone(), owners, HashMap::new()),
+        );
+
+        let create_at = |store: &mut MockStore, now: u64| {
+            let env = MockEnv {
+                caller: caller.clone(),
+                now,
+            };
+            memories_create_with_internal_blobs_core(
+                &env,
+                store,
+                capsule_id.clone(),
+                create_test_memory_metadata(),
+                vec![InternalBlobAssetInput {
+                    blob_id: "blob_1234567890".to_string(),
+                    metadata: create_test_asset_metadata(),
+                }],
+                "ttl-idem".to_string(),
+            )
+            .unwrap()
+        };
+
+        let ttl = crate::idempotency::DEFAULT_IDEM_TTL_NS;
+        let first = create_at(&mut store, 1_000);
+
+        // Within TTL: same key dedupes
+        let retry = create_at(&mut store, 1_000 + ttl);
+        assert_eq!(retry, first);
+        assert_eq!(store.get_all_memories(&capsule_id).len(), 1);
+
+        // After TTL: same key creates a fresh memory
+        let reused = create_at(&mut store, 1_000 + ttl + 1);
+        assert_ne!(reused, first);
+        assert_eq!(store.get_all_memories(&capsule_id).len(), 2);
+
+        // ...and the fresh memory is what the key dedupes to from now on
+        let retry = create_at(&mut store, 1_000 + ttl + 2);
+        assert_eq!(retry, reused);
+        assert_eq!(store.get_all_memories(&capsule_id).len(), 2);
+    }
+
+    fn inline_item(len: usize) -> MemoryCreateInput {
+        let mut asset_metadata = create_test_asset_metadata();
+        if let AssetMetadata::Image(image) = &mut asset_metadata {
+            image.base.bytes = len as u64;
+        }
+        MemoryCreateInput {
+            bytes: Some(vec![7u8; len]),
+            blob_ref: None,
+            external_location: None,
+            external_storage_key: None,
+            external_url: None,
+            external_size: None,
+            external_hash: None,
+            asset_metadata,
+        }
+    }
+
+    #[test]
+    fn test_memories_create_bulk_rejects_batch_over_inline_budget() {
+        let (env, mut store) = mock_capsule("capsule_bulk_budget");
+        let item_len = (CAPSULE_INLINE_BUDGET / 3 + 1) as usize;
+        let items = vec![
+            inline_item(item_len),
+            inline_item(item_len),
+            inline_item(item_len),
+        ];
+
+        let result = memories_create_bulk_core(
+            &env,
+            &mut store,
+            "capsule_bulk_budget".to_string(),
+            items,
+            "bulk".to_string(),
+        )
+        .unwrap();
+
+        // Only the item that crosses the budget is reported, and nothing is created
+        assert!(result.ok.is_empty());
+        assert_eq!(
+            result.failed,
+            vec![BulkFailure {
+                id: "bulk:2".to_string(),
+                err: Error::ResourceExhausted,
+            }]
+        );
+        assert!(store
+            .get_all_memories(&"capsule_bulk_budget".to_string())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_memories_create_bulk_reports_per_item_failures() {
+        let (env, mut store) = mock_capsule("capsule_bulk_items");
+        let mut invalid = inline_item(16);
+        invalid.bytes = Some(vec![0u8; 8]); // disagrees with metadata.base.bytes
+
+        let result = memories_create_bulk_core(
+            &env,
+            &mut store,
+            "capsule_bulk_items".to_string(),
+            vec![inline_item(16), invalid, inline_item(16)],
+            "bulk".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(result.ok.len(), 2);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].id, "bulk:1");
+        assert!(matches!(result.failed[0].err, Error::InvalidArgument(_)));
+        assert_eq!(
+            store
+                .get_all_memories(&"capsule_bulk_items".to_string())
+                .len(),
+            2
+        );
+
+        // Retrying the same call dedupes per item instead of creating duplicates
+        let retry = memories_create_bulk_core(
+            &env,
+            &mut store,
+            "capsule_bulk_items".to_string(),
+            vec![inline_item(16), inline_item(16), inline_item(16)],
+            "bulk".to_string(),
+        )
+        .unwrap();
+        assert_eq!(retry.ok[0], result.ok[0]);
+        assert_eq!(retry.ok[2], result.ok[1]);
+    }
+
+    #[test]
+    fn test_memories_duplicate_shares_blob_locators() {
+        let caller = PersonRef::Principal(Principal::from_slice(&[1, 2, 3, 4, 5]));
+        let capsule_id = "capsule_123".to_string();
+        let env = MockEnv {
+            caller: caller.clone(),
+            now: 1234567890,
+        };
+        let mut store = MockStore::new();
+
+        let mut owners = HashMap::new();
+        owners.insert(
+            caller.clone(),
+            OwnerState {
+                since: 1234567890,
+                last_activity_at: 1234567890,
+            },
+        );
+        store.add_capsule(
+            capsule_id.clone(),
+            CapsuleAccess::new(caller.clone(), owners, HashMap::new()),
+        );
+
+        let source_id = memories_create_with_internal_blobs_core(
+            &env,
+            &mut store,
+            capsule_id.clone(),
+            create_test_memory_metadata(),
+            vec![InternalBlobAssetInput {
+                blob_id: "blob_1234567890".to_string(),
+                metadata: create_test_asset_metadata(),
+            }],
+            "dup-source".to_string(),
+        )
+        .unwrap();
+
+        let later = MockEnv {
+            caller: caller.clone(),
+            now: 1234567999,
+        };
+        let copy_id = memories_duplicate_core(&later, &mut store, source_id.clone(), None).unwrap();
+        assert_ne!(copy_id, source_id);
+
+        let source = store.get_memory(&capsule_id, &source_id).unwrap();
+        let copy = store.get_memory(&capsule_id, &copy_id).unwrap();
+        assert_eq!(copy.capsule_id, capsule_id);
+        assert_eq!(copy.metadata.created_at, 1234567999);
+        let locators = |m: &Memory| {
+            m.blob_internal_assets
+                .iter()
+                .map(|a| a.blob_ref.locator.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(locators(&copy), locators(&source));
+
+        // Target capsule must exist (and be writable)
+        assert_eq!(
+            memories_duplicate_core(&later, &mut store, source_id, Some("missing".to_string())),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_memories_duplicate_keeps_internal_blobs_in_their_capsule() {
+        let (env, mut store) = mock_capsule("capsule_dup_source");
+        let (_, target) = mock_capsule("capsule_dup_target");
+        let target_access = target
+            .get_capsule_for_acl(&"capsule_dup_target".to_string())
+            .unwrap();
+        store.add_capsule("capsule_dup_target".to_string(), target_access);
+
+        let source_id = memories_create_with_internal_blobs_core(
+            &env,
+            &mut store,
+            "capsule_dup_source".to_string(),
+            create_test_memory_metadata(),
+            vec![InternalBlobAssetInput {
+                blob_id: "blob_42".to_string(),
+                metadata: create_test_asset_metadata(),
+            }],
+            "dup-cross".to_string(),
+        )
+        .unwrap();
+
+        // The blob stays charged to the source capsule, so the target can't share it
+        assert!(matches!(
+            memories_duplicate_core(
+                &env,
+                &mut store,
+                source_id,
+                Some("capsule_dup_target".to_string())
+            ),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(store
+            .get_all_memories(&"capsule_dup_target".to_string())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_create_and_place_rolls_back_only_its_own_memory() {
+        use crate::memories::core::memories_create_and_place_core;
+
+        let (env, mut store) = mock_capsule("capsule_place");
+        let capsule_id = "capsule_place".to_string();
+        let blob_ref = BlobRef {
+            locator: "blob_77".to_string(),
+            hash: None,
+            len: 1024,
+        };
+        let place = |ok: bool| {
+            move |_: &mut MockStore, _: &MemoryId| {
+                if ok {
+                    Ok("gallery_1".to_string())
+                } else {
+                    Err(Error::Conflict("gallery is full".to_string()))
+                }
+            }
+        };
+
+        // A failed placement removes the memory the call just created
+        assert!(memories_create_and_place_core(
+            &env,
+            &mut store,
+            capsule_id.clone(),
+            blob_ref.clone(),
+            create_test_asset_metadata(),
+            "idem_place_fail".to_string(),
+            place(false),
+        )
+        .is_err());
+        assert!(store.get_all_memories(&capsule_id).is_empty());
+
+        let (memory_id, gallery_id) = memories_create_and_place_core(
+            &env,
+            &mut store,
+            capsule_id.clone(),
+            blob_ref.clone(),
+            create_test_asset_metadata(),
+            "idem_place".to_string(),
+            place(true),
+        )
+        .unwrap();
+        assert_eq!(gallery_id, "gallery_1");
+
+        // A retry that fails to place must not delete the memory the first call created
+        assert_eq!(
+            memories_create_and_place_core(
+                &env,
+                &mut store,
+                capsule_id.clone(),
+                blob_ref,
+                create_test_asset_metadata(),
+                "idem_place".to_string(),
+                place(false),
+            ),
+            Err(Error::Conflict("gallery is full".to_string()))
+        );
+        assert!(store.get_memory(&capsule_id, &memory_id).is_some());
+    }
 }
@@ -3,19 +3,25 @@
 //! This module contains the core business logic for deleting memories
 //! with proper asset cleanup and post-write assertions.
 
+use super::assets::cleanup_memory_assets;
 use super::traits::*;
+use super::update::{check_not_expired, check_unlocked};
 use crate::capsule_acl::CapsuleAcl;
-use crate::types::{
-    BlobRef, Error, Memory, MemoryAssetBlobExternal, MemoryId, StorageEdgeBlobType,
-};
+use crate::memory_tombstones;
+use crate::types::{CapsuleId, Error, Memory, MemoryId};
 
 /// Core memory deletion function - pure business logic
+///
+/// A memory used by galleries (as an item or cover) is only deleted with
+/// `force`, which also removes it from those galleries. Returns the galleries
+/// that were changed.
 pub fn memories_delete_core<E: Env, S: Store>(
     env: &E,
     store: &mut S,
     memory_id: MemoryId,
     delete_assets: bool,
-) -> std::result::Result<(), Error> {
+    force: bool,
+) -> std::result::Result<Vec<String>, Error> {
     let caller = env.caller();
 
     // Find the memory across all accessible capsules
@@ -50,9 +56,21 @@ pub fn memories_delete_core<E: Env, S: Store>(
                 capsule_access.can_delete(&caller)
             );
 
+            check_unlocked(&memory)?;
+
+            // Refuse to leave galleries pointing at a missing memory
+            let galleries = store.galleries_referencing_memory(&capsule_id, &memory_id);
+            if !galleries.is_empty() && !force {
+                return Err(Error::Conflict(format!(
+                    "memory {} is used by galleries {}; pass force to remove it from them",
+                    memory_id,
+                    galleries.join(", ")
+                )));
+            }
+
             // CRITICAL: Clean up assets before deleting the memory (if requested)
             if delete_assets {
-                cleanup_memory_assets(&memory)?;
+                cleanup_memory_assets(store, &capsule_id, &memory)?;
             }
 
             // Delete the memory
@@ -64,8 +82,12 @@ pub fn memories_delete_core<E: Env, S: Store>(
                     "Post-delete readback failed: memory was not removed".to_string(),
                 ));
             }
+            memory_tombstones::record(&memory_id, &capsule_id, env.now());
 
-            return Ok(());
+            if galleries.is_empty() {
+                return Ok(galleries);
+            }
+            return store.detach_memory_from_galleries(&capsule_id, &memory_id, env.now());
         }
     }
 
@@ -94,9 +116,9 @@ pub fn memories_delete_bulk_core<E: Env, S: Store>(
         return Err(Error::Unauthorized);
     }
 
-    // Delete each memory
+    // Delete each memory, removing it from any gallery that uses it
     for memory_id in memory_ids {
-        match memories_delete_core(env, store, memory_id.clone(), delete_assets) {
+        match memories_delete_core(env, store, memory_id.clone(), delete_assets, true) {
             Ok(_) => {
                 deleted_count += 1;
             }
@@ -168,168 +190,105 @@ pub fn memories_delete_all_core<E: Env, S: Store>(
     })
 }
 
-/// Clean up all assets associated with a memory before deletion
-/// This prevents memory leaks and storage bloat
-pub fn cleanup_memory_assets(memory: &Memory) -> std::result::Result<(), Error> {
-    // 1. Inline assets: No cleanup needed - they're stored directly in the memory struct
-    // When the memory is deleted, inline assets are automatically removed
+/// Most memories `memories_delete_by_time_range_core` deletes per call
+pub const TIME_RANGE_DELETE_BATCH: usize = 500;
 
-    // 2. Internal blob assets: Delete from ICP blob store
-    for blob_asset in &memory.blob_internal_assets {
-        cleanup_internal_blob_asset(&blob_asset.blob_ref)?;
+/// Delete memories of a capsule whose `created_at` is in `[after, before)`
+///
+/// A missing bound leaves that side of the window open. At most
+/// `TIME_RANGE_DELETE_BATCH` memories (oldest first) are deleted per call;
+/// deleted memories no longer match, so calling again resumes the cleanup,
+/// and a call deleting fewer than a full batch has reached the end.
+pub fn memories_delete_by_time_range_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    capsule_id: String,
+    before: Option<u64>,
+    after: Option<u64>,
+    delete_assets: bool,
+) -> std::result::Result<crate::memories::types::BulkDeleteResult, Error> {
+    if let (Some(before), Some(after)) = (before, after) {
+        if after > before {
+            return Err(Error::InvalidArgument(format!(
+                "after ({}) must not be later than before ({})",
+                after, before
+            )));
+        }
     }
 
-    // 3. External blob assets: Delete from external storage
-    for external_asset in &memory.blob_external_assets {
-        cleanup_external_blob_asset(external_asset)?;
+    let capsule_access = store
+        .get_capsule_for_acl(&capsule_id)
+        .ok_or(Error::NotFound)?;
+    if !capsule_access.can_write(&env.caller()) {
+        return Err(Error::Unauthorized);
     }
 
-    Ok(())
+    let mut in_window: Vec<(u64, String)> = store
+        .get_all_memories(&capsule_id)
+        .into_iter()
+        .map(|memory| (memory.metadata.created_at, memory.id))
+        .filter(|(created_at, _)| {
+            after.is_none_or(|after| *created_at >= after)
+                && before.is_none_or(|before| *created_at < before)
+        })
+        .collect();
+    in_window.sort();
+    let memory_ids = in_window
+        .into_iter()
+        .take(TIME_RANGE_DELETE_BATCH)
+        .map(|(_, id)| id)
+        .collect();
+
+    memories_delete_bulk_core(env, store, capsule_id, memory_ids, delete_assets)
 }
 
-/// Clean up an internal blob asset from ICP blob store
-fn cleanup_internal_blob_asset(blob_ref: &BlobRef) -> std::result::Result<(), Error> {
-    use crate::upload::blob_store::BlobStore;
-    use crate::upload::types::BlobId;
-    use crate::utils::blob_id::parse_blob_id;
-
-    // Parse the blob locator to get the BlobId
-    // Format: "canister_id:blob_id" or just "blob_id"
-    let blob_id_str = if blob_ref.locator.contains(':') {
-        blob_ref
-            .locator
-            .split(':')
-            .nth(1)
-            .unwrap_or(&blob_ref.locator)
-    } else {
-        &blob_ref.locator
-    };
-
-    // Add loud, temporary logging to debug exact blob ID strings
-    ic_cdk::println!(
-        "[cleanup_internal_blob_asset] raw='{}' (len={}) bytes={:?}",
-        blob_id_str,
-        blob_id_str.len(),
-        blob_id_str.as_bytes()
-    );
-
-    // Use the bulletproof parser
-    let blob_id_num = parse_blob_id(blob_id_str).map_err(|e| Error::InvalidArgument(e))?;
+/// Capsules one maintenance tick looks at for expired memories
+pub const SWEEP_EXPIRED_CAPSULES: u32 = 50;
+/// Most expired memories one maintenance tick deletes
+pub const SWEEP_EXPIRED_BATCH: u32 = 200;
 
-    let blob_id = BlobId(blob_id_num);
-
-    // Delete the blob from the store
-    let blob_store = BlobStore::new();
-    blob_store.delete_blob(&blob_id)?;
-
-    Ok(())
-}
-
-/// Clean up an external blob asset from external storage
-fn cleanup_external_blob_asset(
-    external_asset: &MemoryAssetBlobExternal,
-) -> std::result::Result<(), Error> {
-    match &external_asset.location {
-        StorageEdgeBlobType::Icp => {
-            // This shouldn't happen - ICP assets should be in blob_internal_assets
-            return Err(Error::InvalidArgument(
-                "ICP assets should be in blob_internal_assets".to_string(),
-            ));
-        }
-        StorageEdgeBlobType::VercelBlob => {
-            cleanup_vercel_blob_asset(&external_asset.storage_key)?;
-        }
-        StorageEdgeBlobType::S3 => {
-            cleanup_s3_blob_asset(&external_asset.storage_key)?;
-        }
-        StorageEdgeBlobType::Arweave => {
-            cleanup_arweave_blob_asset(&external_asset.storage_key)?;
-        }
-        StorageEdgeBlobType::Ipfs => {
-            cleanup_ipfs_blob_asset(&external_asset.storage_key)?;
-        }
-        StorageEdgeBlobType::Neon => {
-            cleanup_neon_blob_asset(&external_asset.storage_key)?;
+/// Delete the memories of `capsule_ids` whose `expires_at` has passed
+///
+/// Runs from the maintenance timer without a caller: the expiry was set by an
+/// owner. Assets are cleaned up and galleries using the memory are detached.
+/// Locked memories are kept until unlocked, and a memory whose asset cleanup
+/// fails is retried by the next sweep. At most `limit` memories are deleted, so
+/// a capsule with many expired memories can take several sweeps.
+/// Returns how many memories were deleted and how many of `capsule_ids`, in
+/// order, were swept completely (the caller resumes after the last of them).
+pub fn memories_sweep_expired_core<S: Store>(
+    store: &mut S,
+    capsule_ids: &[CapsuleId],
+    now: u64,
+    limit: u32,
+) -> (u32, usize) {
+    let mut deleted = 0;
+    for (swept, capsule_id) in capsule_ids.iter().enumerate() {
+        let expired: Vec<Memory> = store
+            .get_all_memories(capsule_id)
+            .into_iter()
+            .filter(|memory| check_not_expired(memory, now).is_err())
+            .filter(|memory| check_unlocked(memory).is_ok())
+            .collect();
+        for memory in expired {
+            if deleted == limit {
+                return (deleted, swept);
+            }
+            if cleanup_memory_assets(store, capsule_id, &memory).is_err() {
+                continue;
+            }
+            let galleries = store.galleries_referencing_memory(capsule_id, &memory.id);
+            if store.delete_memory(capsule_id, &memory.id).is_err() {
+                continue;
+            }
+            memory_tombstones::record(&memory.id, capsule_id, now);
+            if !galleries.is_empty() {
+                let _ = store.detach_memory_from_galleries(capsule_id, &memory.id, now);
+            }
+            deleted += 1;
         }
     }
-
-    Ok(())
-}
-
-/// Clean up a Vercel Blob asset
-fn cleanup_vercel_blob_asset(storage_key: &str) -> std::result::Result<(), Error> {
-    // TODO: Implement Vercel Blob deletion via HTTP outcall
-    // This would require:
-    // 1. Making an HTTP outcall to Vercel Blob API
-    // 2. Using the storage_key to delete the blob
-    // 3. Handling authentication and error responses
-
-    // For now, log the deletion attempt
-    ic_cdk::println!("TODO: Delete Vercel Blob asset: {}", storage_key);
-
-    // Return success for now to avoid breaking the deletion flow
-    // In production, this should be implemented properly
-    Ok(())
-}
-
-/// Clean up an S3 asset
-fn cleanup_s3_blob_asset(storage_key: &str) -> std::result::Result<(), Error> {
-    // TODO: Implement S3 deletion via HTTP outcall
-    // This would require:
-    // 1. Making an HTTP outcall to S3 API
-    // 2. Using the storage_key to delete the object
-    // 3. Handling AWS authentication and error responses
-
-    // For now, log the deletion attempt
-    ic_cdk::println!("TODO: Delete S3 asset: {}", storage_key);
-
-    // Return success for now to avoid breaking the deletion flow
-    // In production, this should be implemented properly
-    Ok(())
-}
-
-/// Clean up an Arweave asset
-fn cleanup_arweave_blob_asset(storage_key: &str) -> std::result::Result<(), Error> {
-    // TODO: Implement Arweave deletion
-    // Note: Arweave is designed to be permanent storage
-    // Deletion might not be possible or might require special handling
-
-    // For now, log the deletion attempt
-    ic_cdk::println!("TODO: Delete Arweave asset: {}", storage_key);
-
-    // Return success for now to avoid breaking the deletion flow
-    // In production, this should be implemented properly
-    Ok(())
-}
-
-/// Clean up an IPFS asset
-fn cleanup_ipfs_blob_asset(storage_key: &str) -> std::result::Result<(), Error> {
-    // TODO: Implement IPFS deletion
-    // Note: IPFS is designed to be permanent storage
-    // Deletion might not be possible or might require special handling
-
-    // For now, log the deletion attempt
-    ic_cdk::println!("TODO: Delete IPFS asset: {}", storage_key);
-
-    // Return success for now to avoid breaking the deletion flow
-    // In production, this should be implemented properly
-    Ok(())
-}
-
-/// Clean up a Neon database asset
-fn cleanup_neon_blob_asset(storage_key: &str) -> std::result::Result<(), Error> {
-    // TODO: Implement Neon database asset deletion
-    // This would require:
-    // 1. Making an HTTP outcall to Neon API
-    // 2. Using the storage_key to delete the asset record
-    // 3. Handling database authentication and error responses
-
-    // For now, log the deletion attempt
-    ic_cdk::println!("TODO: Delete Neon asset: {}", storage_key);
-
-    // Return success for now to avoid breaking the deletion flow
-    // In production, this should be implemented properly
-    Ok(())
+    (deleted, capsule_ids.len())
 }
 
 /// TEMPORARY DEV METHOD: Clear all memories in a capsule
@@ -381,3 +340,285 @@ pub fn _dev_clear_all_memories_in_capsule_core<E: Env, S: Store>(
         ),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memories::core::memories_duplicate_core;
+    use crate::memories::core::model_helpers::create_blob_memory;
+    use crate::test_utils::{
+        controller_of, create_test_asset_metadata, create_test_gallery, create_test_memory_at,
+        mixed_asset_memory, mock_capsule, MockEnv,
+    };
+    use crate::types::{BlobRef, PersonRef};
+    use candid::Principal;
+
+    #[test]
+    fn test_deleting_a_duplicate_keeps_the_shared_blob() {
+        use crate::memories::core::memories_delete_core;
+        use crate::upload::blob_store::{BlobStore, StableBlobStore};
+        use crate::upload::types::BlobMeta;
+
+        let blob_store = StableBlobStore;
+        blob_store.put_page([8u8; 32], 0, vec![0u8; 1024]);
+        blob_store.put_meta(
+            9_008,
+            BlobMeta {
+                size: 1024,
+                checksum: [0; 32],
+                created_at: 1,
+                pmid_hash: [8u8; 32],
+                capsule_id: None,
+            },
+        );
+
+        let (env, mut store) = mock_capsule("capsule_dup_delete");
+        let capsule_id = "capsule_dup_delete".to_string();
+        store
+            .insert_memory(
+                &capsule_id,
+                mixed_asset_memory(&env, "mem_source", &capsule_id, "blob_9008"),
+            )
+            .unwrap();
+        let copy_id =
+            memories_duplicate_core(&env, &mut store, "mem_source".to_string(), None).unwrap();
+
+        // The copy still uses the blob, so deleting the source keeps it stored
+        memories_delete_core(&env, &mut store, "mem_source".to_string(), true, false).unwrap();
+        assert!(blob_store.get_meta(9_008).is_some());
+
+        // The last memory using it frees it
+        memories_delete_core(&env, &mut store, copy_id, true, false).unwrap();
+        assert!(blob_store.get_meta(9_008).is_none());
+        assert!(blob_store.get_page(&[8u8; 32], 0).is_none());
+    }
+
+    #[test]
+    fn test_deleting_a_gallery_memory_needs_force_and_detaches_it() {
+        let (env, mut store) = mock_capsule("capsule_gal");
+        let capsule_id = "capsule_gal".to_string();
+        for id in ["m1", "m2", "m3"] {
+            store
+                .insert_memory(&capsule_id, create_test_memory_at(id, &capsule_id, 1))
+                .unwrap();
+        }
+        store.add_gallery(create_test_gallery("g_items", &capsule_id, &["m1", "m2"]));
+        let mut covered = create_test_gallery("g_cover", &capsule_id, &["m2"]);
+        covered.cover_memory_id = Some("m1".to_string());
+        store.add_gallery(covered);
+        store.add_gallery(create_test_gallery("g_other", &capsule_id, &["m2"]));
+
+        assert!(matches!(
+            memories_delete_core(&env, &mut store, "m1".to_string(), false, false),
+            Err(Error::Conflict(_))
+        ));
+        assert!(store.get_memory(&capsule_id, &"m1".to_string()).is_some());
+
+        let affected =
+            memories_delete_core(&env, &mut store, "m1".to_string(), false, true).unwrap();
+        assert_eq!(affected, vec!["g_cover".to_string(), "g_items".to_string()]);
+        assert!(store.get_memory(&capsule_id, &"m1".to_string()).is_none());
+
+        let items = &store.galleries[&(capsule_id.clone(), "g_items".to_string())];
+        assert_eq!(items.items.len(), 1);
+        assert_eq!(items.updated_at, 1_000);
+        let cover = &store.galleries[&(capsule_id.clone(), "g_cover".to_string())];
+        assert_eq!(cover.cover_memory_id, None);
+        let other = &store.galleries[&(capsule_id.clone(), "g_other".to_string())];
+        assert_eq!(other.updated_at, 1);
+
+        // Memories outside any gallery still delete without force
+        assert_eq!(
+            memories_delete_core(&env, &mut store, "m3".to_string(), false, false),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_memories_read_after_delete_reports_deleted() {
+        use crate::memories::core::{memories_delete_core, memories_read_core};
+
+        let (env, mut store) = mock_capsule("capsule_tomb");
+        store
+            .insert_memory(
+                &"capsule_tomb".to_string(),
+                crate::test_utils::create_test_memory_at("mem_tomb", "capsule_tomb", 1),
+            )
+            .unwrap();
+
+        memories_delete_core(&env, &mut store, "mem_tomb".to_string(), false, false).unwrap();
+        assert_eq!(
+            memories_read_core(&env, &store, "mem_tomb".to_string()),
+            Err(Error::Deleted { deleted_at: 1_000 })
+        );
+
+        // A caller without access to the capsule can't tell it ever existed
+        let stranger = MockEnv {
+            caller: PersonRef::Principal(Principal::from_slice(&[9, 9, 9])),
+            now: 1_000,
+        };
+        assert_eq!(
+            memories_read_core(&stranger, &store, "mem_tomb".to_string()),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_memories_past_expiry_read_as_expired_and_are_swept() {
+        use crate::memories::core::{
+            memories_read_core, memories_set_expiry_core, memories_sweep_expired_core,
+        };
+
+        let (env, mut store) = mock_capsule("capsule_expiry");
+        let capsule_id = "capsule_expiry".to_string();
+        for id in ["mem_expired", "mem_kept"] {
+            store
+                .insert_memory(
+                    &capsule_id,
+                    crate::test_utils::create_test_memory_at(id, "capsule_expiry", 1),
+                )
+                .unwrap();
+        }
+
+        // Only owners set expiry
+        let controller = controller_of(&mut store, "capsule_expiry", env.now);
+        assert_eq!(
+            memories_set_expiry_core(&controller, &mut store, "mem_expired".to_string(), Some(1)),
+            Err(Error::Unauthorized)
+        );
+
+        let past = env.now - 1;
+        let updated =
+            memories_set_expiry_core(&env, &mut store, "mem_expired".to_string(), Some(past))
+                .unwrap();
+        assert_eq!(updated.expires_at, Some(past));
+        memories_set_expiry_core(&env, &mut store, "mem_kept".to_string(), Some(env.now + 1))
+            .unwrap();
+
+        // Not swept yet, but already treated as expired
+        assert_eq!(
+            memories_read_core(&env, &store, "mem_expired".to_string()),
+            Err(Error::Expired { expired_at: past })
+        );
+        assert!(memories_read_core(&env, &store, "mem_kept".to_string()).is_ok());
+
+        assert_eq!(
+            memories_sweep_expired_core(&mut store, std::slice::from_ref(&capsule_id), env.now, 10),
+            (1, 1)
+        );
+        assert!(store
+            .get_memory(&capsule_id, &"mem_expired".to_string())
+            .is_none());
+        assert_eq!(
+            memories_read_core(&env, &store, "mem_expired".to_string()),
+            Err(Error::Deleted {
+                deleted_at: env.now
+            })
+        );
+        assert!(store
+            .get_memory(&capsule_id, &"mem_kept".to_string())
+            .is_some());
+    }
+
+    #[test]
+    fn test_memories_sweep_expired_stops_at_the_limit_and_resumes() {
+        use crate::memories::core::memories_sweep_expired_core;
+
+        let (env, mut store) = mock_capsule("capsule_sweep_a");
+        store.add_capsule(
+            "capsule_sweep_b".to_string(),
+            store.capsules["capsule_sweep_a"].clone(),
+        );
+        for (capsule_id, id) in [
+            ("capsule_sweep_a", "mem_a1"),
+            ("capsule_sweep_a", "mem_a2"),
+            ("capsule_sweep_a", "mem_a3"),
+            ("capsule_sweep_b", "mem_b1"),
+        ] {
+            let mut memory = crate::test_utils::create_test_memory_at(id, capsule_id, 1);
+            memory.expires_at = Some(1);
+            store
+                .insert_memory(&capsule_id.to_string(), memory)
+                .unwrap();
+        }
+        // Its blob is already gone, which counts as cleaned up rather than failing
+        let mut memory = create_blob_memory(
+            "mem_b2",
+            &"capsule_sweep_b".to_string(),
+            BlobRef {
+                locator: "blob_404".to_string(),
+                hash: None,
+                len: 1024,
+            },
+            create_test_asset_metadata(),
+            env.now,
+            &env.caller,
+        );
+        memory.expires_at = Some(1);
+        store
+            .insert_memory(&"capsule_sweep_b".to_string(), memory)
+            .unwrap();
+
+        let capsule_ids = ["capsule_sweep_a".to_string(), "capsule_sweep_b".to_string()];
+        assert_eq!(
+            memories_sweep_expired_core(&mut store, &capsule_ids, env.now, 2),
+            (2, 0)
+        );
+        assert_eq!(
+            memories_sweep_expired_core(&mut store, &capsule_ids, env.now, 2),
+            (2, 1)
+        );
+        assert_eq!(
+            memories_sweep_expired_core(&mut store, &capsule_ids[1..], env.now, 2),
+            (1, 1)
+        );
+        assert!(store.memories.is_empty());
+    }
+
+    #[test]
+    fn test_memories_delete_by_time_range_deletes_only_the_window() {
+        use crate::memories::core::memories_delete_by_time_range_core;
+
+        let (env, mut store) = mock_capsule("capsule_range");
+        let capsule_id = "capsule_range".to_string();
+        for (id, created_at) in [("m10", 10), ("m20", 20), ("m30", 30), ("m40", 40)] {
+            store
+                .insert_memory(
+                    &capsule_id,
+                    crate::test_utils::create_test_memory_at(id, "capsule_range", created_at),
+                )
+                .unwrap();
+        }
+
+        assert!(matches!(
+            memories_delete_by_time_range_core(
+                &env,
+                &mut store,
+                capsule_id.clone(),
+                Some(20),
+                Some(30),
+                false
+            ),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let result = memories_delete_by_time_range_core(
+            &env,
+            &mut store,
+            capsule_id.clone(),
+            Some(40),
+            Some(20),
+            false,
+        )
+        .unwrap();
+        assert_eq!(result.deleted_count, 2);
+
+        let mut left: Vec<String> = store
+            .get_all_memories(&capsule_id)
+            .into_iter()
+            .map(|memory| memory.id)
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["m10".to_string(), "m40".to_string()]);
+    }
+}
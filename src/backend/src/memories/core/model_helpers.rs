@@ -3,10 +3,13 @@
 //! This module contains helper functions used across different memory operations,
 //! providing common functionality for creating memories and managing assets.
 
-use crate::capsule::domain::{AccessCondition, AccessEntry, GrantSource, Perm, ResourceRole};
+use crate::capsule::domain::{
+    AccessCondition, AccessEntry, DefaultMemoryAccess, GrantSource, Perm, ResourceRole,
+};
 use crate::types::{
-    AssetMetadata, BlobRef, CapsuleId, Memory, MemoryAssetBlobExternal, MemoryAssetBlobInternal,
-    MemoryAssetInline, MemoryMetadata, MemoryType, PersonRef, StorageEdgeBlobType,
+    AssetMetadata, BlobHosting, BlobRef, CapsuleId, Memory, MemoryAssetBlobExternal,
+    MemoryAssetBlobInternal, MemoryAssetInline, MemoryMetadata, MemoryType, PersonRef,
+    StorageEdgeBlobType,
 };
 use crate::utils::uuid_v7;
 use sha2::Digest;
@@ -28,6 +31,30 @@ pub fn create_owner_access_entry(owner: &PersonRef, now: u64) -> AccessEntry {
     }
 }
 
+/// Public VIEW | DOWNLOAD entry for a memory created with a Public default
+pub fn create_public_access_entry(now: u64) -> AccessEntry {
+    AccessEntry {
+        id: uuid_v7::uuid_v7_weak(),
+        person_ref: None,
+        is_public: true,
+        grant_source: GrantSource::System, // Granted by the capsule's default, not a user
+        source_id: None,
+        role: ResourceRole::Guest,
+        perm_mask: (Perm::VIEW | Perm::DOWNLOAD).bits(),
+        invited_by_person_ref: None,
+        created_at: now,
+        updated_at: now,
+        condition: AccessCondition::Immediate,
+    }
+}
+
+/// Give a new memory the access entries of `access` (its owner entry is already there)
+pub fn apply_initial_access(memory: &mut Memory, access: DefaultMemoryAccess, now: u64) {
+    if access == DefaultMemoryAccess::Public {
+        memory.access_entries.push(create_public_access_entry(now));
+    }
+}
+
 // Thread-local RNG state for UUID generation (DEPRECATED - using proper UUID v7 now)
 // thread_local! {
 //     static RNG_STATE: RefCell<Option<VecDeque<u8>>> = RefCell::new(None);
@@ -144,6 +171,41 @@ pub fn memory_type_from_asset(meta: &AssetMetadata) -> MemoryType {
     }
 }
 
+/// Map an asset storage edge to the blob hosting backend it lives on
+pub fn blob_hosting_for_edge(edge: &StorageEdgeBlobType) -> BlobHosting {
+    match edge {
+        StorageEdgeBlobType::Icp => BlobHosting::Icp,
+        StorageEdgeBlobType::VercelBlob => BlobHosting::VercelBlob,
+        StorageEdgeBlobType::S3 => BlobHosting::S3,
+        StorageEdgeBlobType::Arweave => BlobHosting::Arweave,
+        StorageEdgeBlobType::Ipfs => BlobHosting::Ipfs,
+        StorageEdgeBlobType::Neon => BlobHosting::Neon,
+    }
+}
+
+/// Effective storage location of a memory: the union of backends across its assets
+///
+/// Inline and internal blob assets live on ICP; external assets report their own
+/// location. Order follows first appearance (inline, internal, external) and
+/// duplicates are dropped, matching the `Vec<BlobHosting>` galleries expose.
+pub fn memory_storage_locations(memory: &Memory) -> Vec<BlobHosting> {
+    let mut locations: Vec<BlobHosting> = Vec::new();
+    let mut push = |hosting: BlobHosting| {
+        if !locations.contains(&hosting) {
+            locations.push(hosting);
+        }
+    };
+
+    if !memory.inline_assets.is_empty() || !memory.blob_internal_assets.is_empty() {
+        push(BlobHosting::Icp);
+    }
+    for asset in &memory.blob_external_assets {
+        push(blob_hosting_for_edge(&asset.location));
+    }
+
+    locations
+}
+
 /// Create an inline memory (small assets stored directly)
 pub fn create_inline_memory(
     memory_id: &str,
@@ -157,6 +219,7 @@ pub fn create_inline_memory(
         asset_id: generate_asset_id(caller, now),
         bytes: bytes.clone(),
         metadata: asset_metadata.clone(),
+        order: None,
     }];
 
     let base = asset_metadata.get_base();
@@ -202,6 +265,9 @@ pub fn create_inline_memory(
         inline_assets,
         blob_internal_assets: vec![],
         blob_external_assets: vec![],
+        locked: None,
+        pending: None,
+        expires_at: None,
     }
 }
 
@@ -218,6 +284,7 @@ pub fn create_blob_memory(
         asset_id: generate_asset_id(caller, now),
         blob_ref,
         metadata: asset_metadata.clone(),
+        order: None,
     }];
 
     let base = asset_metadata.get_base();
@@ -263,6 +330,9 @@ pub fn create_blob_memory(
         inline_assets: vec![],
         blob_internal_assets,
         blob_external_assets: vec![],
+        locked: None,
+        pending: None,
+        expires_at: None,
     }
 }
 
@@ -285,6 +355,8 @@ pub fn create_external_memory(
         storage_key: storage_key.unwrap_or_default(),
         url,
         metadata: asset_metadata.clone(),
+        order: None,
+        signed_url: None,
     }];
 
     let base = asset_metadata.get_base();
@@ -330,6 +402,9 @@ pub fn create_external_memory(
         inline_assets: vec![],
         blob_internal_assets: vec![],
         blob_external_assets,
+        locked: None,
+        pending: None,
+        expires_at: None,
     }
 }
 
@@ -441,6 +516,89 @@ mod tests {
         assert!(is_uuid_v7(&uuid1), "UUID should pass validation");
     }
 
+    fn note_metadata() -> AssetMetadata {
+        AssetMetadata::Note(crate::memories::types::NoteAssetMetadata {
+            base: crate::memories::types::AssetMetadataBase {
+                name: "note.txt".to_string(),
+                description: None,
+                tags: vec![],
+                asset_type: crate::memories::types::AssetType::Original,
+                bytes: 4,
+                mime_type: "text/plain".to_string(),
+                sha256: None,
+                width: None,
+                height: None,
+                url: None,
+                storage_key: None,
+                bucket: None,
+                asset_location: None,
+                processing_status: None,
+                processing_error: None,
+                created_at: 0,
+                updated_at: 0,
+                deleted_at: None,
+            },
+            word_count: None,
+            language: None,
+            format: None,
+        })
+    }
+
+    fn external_asset(id: &str, location: StorageEdgeBlobType) -> MemoryAssetBlobExternal {
+        MemoryAssetBlobExternal {
+            asset_id: id.to_string(),
+            location,
+            storage_key: format!("key_{id}"),
+            url: None,
+            metadata: note_metadata(),
+            order: None,
+            signed_url: None,
+        }
+    }
+
+    #[test]
+    fn test_memory_storage_locations_unions_backends() {
+        let mut memory = crate::test_utils::create_test_memory_at("mem_1", "capsule_1", 1);
+        assert!(memory_storage_locations(&memory).is_empty());
+
+        memory.inline_assets.push(MemoryAssetInline {
+            asset_id: "inline_1".to_string(),
+            bytes: b"note".to_vec(),
+            metadata: note_metadata(),
+            order: None,
+        });
+        memory
+            .blob_external_assets
+            .push(external_asset("ext_1", StorageEdgeBlobType::S3));
+        memory
+            .blob_external_assets
+            .push(external_asset("ext_2", StorageEdgeBlobType::VercelBlob));
+        memory
+            .blob_external_assets
+            .push(external_asset("ext_3", StorageEdgeBlobType::S3));
+        memory
+            .blob_external_assets
+            .push(external_asset("ext_4", StorageEdgeBlobType::Icp));
+
+        assert_eq!(
+            memory_storage_locations(&memory),
+            vec![BlobHosting::Icp, BlobHosting::S3, BlobHosting::VercelBlob]
+        );
+    }
+
+    #[test]
+    fn test_memory_storage_locations_external_only() {
+        let mut memory = crate::test_utils::create_test_memory_at("mem_1", "capsule_1", 1);
+        memory
+            .blob_external_assets
+            .push(external_asset("ext_1", StorageEdgeBlobType::Arweave));
+
+        assert_eq!(
+            memory_storage_locations(&memory),
+            vec![BlobHosting::Arweave]
+        );
+    }
+
     #[test]
     fn test_is_uuid_v7_validation() {
         // Test valid UUIDs
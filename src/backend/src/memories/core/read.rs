@@ -3,10 +3,21 @@
 //! This module contains the core business logic for reading memories
 //! with proper access control and error handling.
 
+use super::model_helpers::memory_storage_locations;
 use super::traits::*;
-use crate::types::{Error, Memory, MemoryId};
+use super::update::check_not_expired;
+use crate::capsule_acl::CapsuleAcl;
+use crate::memory_tombstones;
+use crate::types::{
+    BlobHosting, CapsuleId, Error, Memory, MemoryAssetData, MemoryId, MemoryPresenceResult,
+    PresenceStatus,
+};
 
 /// Core memory reading function - pure business logic
+///
+/// A memory deleted within the tombstone retention window gives
+/// `Error::Deleted` rather than `NotFound`, and one past its `expires_at` gives
+/// `Error::Expired` until the maintenance sweep deletes it.
 pub fn memories_read_core<E: Env, S: Store>(
     env: &E,
     store: &S,
@@ -16,11 +27,339 @@ pub fn memories_read_core<E: Env, S: Store>(
     let accessible_capsules = store.get_accessible_capsules(&env.caller());
 
     // Search for the memory across all accessible capsules
-    for capsule_id in accessible_capsules {
-        if let Some(memory) = store.get_memory(&capsule_id, &memory_id) {
+    for capsule_id in &accessible_capsules {
+        if let Some(memory) = store.get_memory(capsule_id, &memory_id) {
+            check_not_expired(&memory, env.now())?;
             return Ok(memory);
         }
     }
 
+    // Recently deleted from a capsule the caller can read
+    if let Some(tombstone) = memory_tombstones::get(&memory_id, env.now()) {
+        if accessible_capsules.contains(&tombstone.capsule_id) {
+            return Err(Error::Deleted {
+                deleted_at: tombstone.deleted_at,
+            });
+        }
+    }
+
     Err(Error::NotFound)
 }
+
+/// The asset at `asset_index` in display order, as returned by `memories_read_asset`
+///
+/// External assets with a cached signed URL serve it until it expires, then give
+/// `Error::SignatureExpired`; those without one serve their stored `url`.
+pub fn asset_data_at(
+    memory: &Memory,
+    asset_index: u32,
+    now: u64,
+) -> std::result::Result<MemoryAssetData, Error> {
+    let out_of_range =
+        || Error::InvalidArgument(format!("Asset index {} out of range", asset_index));
+    let asset_id = memory
+        .ordered_asset_ids()
+        .into_iter()
+        .nth(asset_index as usize)
+        .ok_or_else(out_of_range)?;
+
+    if let Some(asset) = memory.inline_assets.iter().find(|a| a.asset_id == asset_id) {
+        return Ok(MemoryAssetData::Inline {
+            bytes: asset.bytes.clone(),
+            content_type: asset.metadata.get_base().mime_type.clone(),
+            size: asset.bytes.len() as u64,
+            sha256: asset.metadata.get_base().sha256.map(|h| h.to_vec()),
+        });
+    }
+
+    if let Some(asset) = memory
+        .blob_internal_assets
+        .iter()
+        .find(|a| a.asset_id == asset_id)
+    {
+        return Ok(MemoryAssetData::InternalBlob {
+            blob_id: asset.blob_ref.locator.clone(),
+            size: asset.blob_ref.len,
+            sha256: asset.blob_ref.hash.map(|h| h.to_vec()),
+        });
+    }
+
+    if let Some(asset) = memory
+        .blob_external_assets
+        .iter()
+        .find(|a| a.asset_id == asset_id)
+    {
+        let url = match &asset.signed_url {
+            Some(signed) if signed.expires_at > now => signed.url.clone(),
+            Some(signed) => {
+                return Err(Error::SignatureExpired {
+                    expired_at: signed.expires_at,
+                })
+            }
+            None => asset.url.clone().unwrap_or_default(),
+        };
+        return Ok(MemoryAssetData::ExternalUrl {
+            url,
+            size: Some(asset.metadata.get_base().bytes),
+            sha256: asset.metadata.get_base().sha256.map(|h| h.to_vec()),
+        });
+    }
+
+    Err(out_of_range())
+}
+
+/// Memory in `capsule_id` with an external asset stored under `storage_key`
+///
+/// For integrations that track content by their own keys (e.g. S3 object keys)
+/// rather than memory IDs. Like `memories_read_core`, a capsule the caller
+/// can't read gives `NotFound`, as does a key no memory references.
+pub fn memories_read_by_external_key_core<E: Env, S: Store>(
+    env: &E,
+    store: &S,
+    capsule_id: CapsuleId,
+    storage_key: String,
+) -> std::result::Result<Memory, Error> {
+    let capsule_access = store
+        .get_capsule_for_acl(&capsule_id)
+        .ok_or(Error::NotFound)?;
+    if !capsule_access.can_read(&env.caller()) {
+        return Err(Error::NotFound);
+    }
+
+    store
+        .get_all_memories(&capsule_id)
+        .into_iter()
+        .find(|memory| {
+            memory
+                .blob_external_assets
+                .iter()
+                .any(|asset| asset.storage_key == storage_key)
+        })
+        .ok_or(Error::NotFound)
+}
+
+/// Effective storage location of a memory (union of backends across its assets)
+///
+/// Uses the same accessible-capsule lookup as `memories_read_core`, so callers
+/// without read access get `NotFound`.
+pub fn memories_storage_location_core<E: Env, S: Store>(
+    env: &E,
+    store: &S,
+    memory_id: MemoryId,
+) -> std::result::Result<Vec<BlobHosting>, Error> {
+    let memory = memories_read_core(env, store, memory_id)?;
+    Ok(memory_storage_locations(&memory))
+}
+
+/// Presence of each memory ID across every capsule the caller can read
+///
+/// IDs may span several capsules; each found ID reports the capsule holding it
+/// so the client can route follow-up reads there. IDs that don't exist, or live
+/// in a capsule the caller can't read, come back as `NotFoundOrInaccessible`.
+pub fn memories_ping_core<E: Env, S: Store>(
+    env: &E,
+    store: &S,
+    memory_ids: Vec<MemoryId>,
+) -> Vec<MemoryPresenceResult> {
+    let accessible_capsules = store.get_accessible_capsules(&env.caller());
+
+    memory_ids
+        .into_iter()
+        .map(|memory_id| {
+            let holder = accessible_capsules
+                .iter()
+                .find(|capsule_id| store.get_memory(capsule_id, &memory_id).is_some());
+            match holder {
+                Some(capsule_id) => MemoryPresenceResult {
+                    memory_id,
+                    metadata_present: true,
+                    asset_present: true, // assets are stored with the memory
+                    status: PresenceStatus::Present,
+                    capsule_id: Some(capsule_id.clone()),
+                },
+                None => MemoryPresenceResult {
+                    memory_id,
+                    metadata_present: false,
+                    asset_present: false,
+                    status: PresenceStatus::NotFoundOrInaccessible,
+                    capsule_id: None,
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capsule_acl::CapsuleAccess;
+    use crate::test_utils::{mixed_asset_memory, mock_capsule, MockEnv, MockStore};
+    use crate::types::{OwnerState, PersonRef};
+    use candid::Principal;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_memories_ping_resolves_across_capsules() {
+        let (env, mut store) = mock_capsule("capsule_ping_a");
+        let mut owners = HashMap::new();
+        owners.insert(
+            env.caller.clone(),
+            OwnerState {
+                since: 0,
+                last_activity_at: 0,
+            },
+        );
+        store.add_capsule(
+            "capsule_ping_b".to_string(),
+            CapsuleAccess::new(env.caller.clone(), owners, HashMap::new()),
+        );
+        for (capsule_id, memory_id) in [("capsule_ping_a", "mem_a"), ("capsule_ping_b", "mem_b")] {
+            store
+                .insert_memory(
+                    &capsule_id.to_string(),
+                    crate::test_utils::create_test_memory_at(memory_id, capsule_id, 1),
+                )
+                .unwrap();
+        }
+
+        let results = crate::memories::core::memories_ping_core(
+            &env,
+            &store,
+            vec![
+                "mem_b".to_string(),
+                "missing".to_string(),
+                "mem_a".to_string(),
+            ],
+        );
+
+        let routed: Vec<_> = results
+            .iter()
+            .map(|r| {
+                (
+                    r.memory_id.as_str(),
+                    r.status.clone(),
+                    r.capsule_id.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            routed,
+            vec![
+                ("mem_b", PresenceStatus::Present, Some("capsule_ping_b")),
+                ("missing", PresenceStatus::NotFoundOrInaccessible, None),
+                ("mem_a", PresenceStatus::Present, Some("capsule_ping_a")),
+            ]
+        );
+        assert!(!results[1].metadata_present);
+    }
+
+    #[test]
+    fn test_memories_read_by_external_key() {
+        use crate::memories::core::memories_read_by_external_key_core;
+
+        let (env, mut store) = mock_capsule("capsule_ext_key");
+        let capsule_id = "capsule_ext_key".to_string();
+        store
+            .insert_memory(
+                &capsule_id,
+                mixed_asset_memory(&env, "mem_ext", &capsule_id, "blob_1"),
+            )
+            .unwrap();
+
+        let memory = memories_read_by_external_key_core(
+            &env,
+            &store,
+            capsule_id.clone(),
+            "photos/1.jpg".to_string(),
+        )
+        .unwrap();
+        assert_eq!(memory.id, "mem_ext");
+
+        assert_eq!(
+            memories_read_by_external_key_core(
+                &env,
+                &store,
+                capsule_id.clone(),
+                "photos/2.jpg".to_string()
+            ),
+            Err(Error::NotFound)
+        );
+
+        // Callers without read access can't probe keys
+        let stranger = MockEnv {
+            caller: PersonRef::Principal(Principal::from_slice(&[9, 9, 9])),
+            now: env.now,
+        };
+        assert_eq!(
+            memories_read_by_external_key_core(
+                &stranger,
+                &store,
+                capsule_id,
+                "photos/1.jpg".to_string()
+            ),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_signed_url_is_served_until_it_expires() {
+        use crate::memories::core::{asset_data_at, memories_set_signed_url_core};
+        use crate::types::MemoryAssetData;
+
+        let (env, mut store) = mock_capsule("capsule_signed");
+        let capsule_id = "capsule_signed".to_string();
+        let memory_id = "mem_signed".to_string();
+        store
+            .insert_memory(
+                &capsule_id,
+                mixed_asset_memory(&env, &memory_id, &capsule_id, "blob_1"),
+            )
+            .unwrap();
+        let external_index = 2;
+        let url_at = |store: &MockStore, now| {
+            let memory = store.get_memory(&capsule_id, &memory_id).unwrap();
+            asset_data_at(&memory, external_index, now).map(|data| match data {
+                MemoryAssetData::ExternalUrl { url, .. } => url,
+                _ => panic!("expected an external asset"),
+            })
+        };
+        assert_eq!(url_at(&store, env.now), Ok(String::new()));
+
+        let expires_at = env.now + 100;
+        memories_set_signed_url_core(
+            &env,
+            &mut store,
+            memory_id.clone(),
+            external_index,
+            "https://bucket.s3/photos/1.jpg?sig=abc".to_string(),
+            expires_at,
+        )
+        .unwrap();
+
+        assert_eq!(
+            url_at(&store, env.now + 50),
+            Ok("https://bucket.s3/photos/1.jpg?sig=abc".to_string())
+        );
+        assert_eq!(
+            url_at(&store, expires_at),
+            Err(Error::SignatureExpired {
+                expired_at: expires_at
+            })
+        );
+
+        // Only external assets take a signed URL, and it must not be stale already
+        for (index, expires_at) in [(0, env.now + 100), (external_index, env.now)] {
+            assert!(matches!(
+                memories_set_signed_url_core(
+                    &env,
+                    &mut store,
+                    memory_id.clone(),
+                    index,
+                    "https://bucket.s3/x".to_string(),
+                    expires_at,
+                ),
+                Err(Error::InvalidArgument(_))
+            ));
+        }
+    }
+}
@@ -34,6 +34,20 @@ pub trait Store {
     fn get_all_memories(&self, capsule: &CapsuleId) -> Vec<Memory>;
     fn get_accessible_capsules(&self, caller: &PersonRef) -> Vec<CapsuleId>;
     fn get_capsule_for_acl(&self, capsule_id: &CapsuleId) -> Option<CapsuleAccess>;
+
+    /// Galleries of the capsule using the memory as an item or cover
+    fn galleries_referencing_memory(&self, capsule: &CapsuleId, id: &MemoryId) -> Vec<String>;
+
+    /// Whether a memory other than `id` still references internal blob `blob_id`
+    fn blob_referenced_elsewhere(&self, blob_id: u64, capsule: &CapsuleId, id: &MemoryId) -> bool;
+
+    /// Remove the memory from every gallery's items and cover, returning the galleries changed
+    fn detach_memory_from_galleries(
+        &mut self,
+        capsule: &CapsuleId,
+        id: &MemoryId,
+        now: u64,
+    ) -> std::result::Result<Vec<String>, Error>;
     
     /// Clear all memories in a capsule (atomic operation)
     fn clear_all_memories_in_capsule(&mut self, capsule_id: &str) -> std::result::Result<(), Error>;
@@ -43,4 +57,10 @@ pub trait Store {
     
     /// Check if capsule exists
     fn capsule_exists(&self, capsule_id: &str) -> bool;
+
+    /// Resource recorded for an idempotency key (scoped to endpoint + caller), if still live
+    fn idem_lookup(&self, scope: &str, caller: &PersonRef, key: &str, now: u64) -> Option<String>;
+
+    /// Record the resource created for an idempotency key
+    fn idem_record(&mut self, scope: &str, caller: &PersonRef, key: &str, result: &str, now: u64);
 }
@@ -6,10 +6,99 @@
 use super::model_helpers::*;
 use super::traits::*;
 use crate::capsule_acl::CapsuleAcl;
-use crate::memories::types::{InlineAssetInput, InternalBlobAssetInput};
+use crate::memories::types::{InlineAssetInput, InternalBlobAssetInput, SignedUrl};
+use crate::memory::{MEM_MAX_INLINE_ASSETS, MM};
 use crate::types::{
-    BlobRef, Error, MemoryAssetBlobInternal, MemoryAssetInline, MemoryId, MemoryUpdateData,
+    BlobRef, CapsuleId, Error, MemoryAssetBlobInternal, MemoryAssetInline, MemoryId,
+    MemoryUpdateData,
 };
+use crate::upload::types::MAX_INLINE_ASSETS_PER_MEMORY_DEFAULT;
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{DefaultMemoryImpl, StableCell};
+use std::cell::RefCell;
+
+type Mem = VirtualMemory<DefaultMemoryImpl>;
+
+thread_local! {
+    // Admin-set cap, kept across upgrades
+    static MAX_INLINE_ASSETS_PER_MEMORY: RefCell<StableCell<u32, Mem>> = RefCell::new(
+        StableCell::init(
+            MM.with(|m| m.borrow().get(MEM_MAX_INLINE_ASSETS)),
+            MAX_INLINE_ASSETS_PER_MEMORY_DEFAULT,
+        )
+        .expect("Failed to init inline asset cap")
+    );
+}
+
+/// Current cap on inline assets per memory
+pub fn max_inline_assets_per_memory() -> u32 {
+    MAX_INLINE_ASSETS_PER_MEMORY.with(|max| *max.borrow().get())
+}
+
+/// Change the inline asset cap (admin); existing memories above it are left as-is
+pub fn set_max_inline_assets_per_memory(max: u32) {
+    MAX_INLINE_ASSETS_PER_MEMORY.with(|cell| {
+        cell.borrow_mut()
+            .set(max)
+            .expect("Failed to store inline asset cap");
+    });
+}
+
+/// Reject adding another inline asset to a memory that already holds `current`
+///
+/// Independent of the byte budget: guards against count-based bloat from many
+/// tiny renditions.
+pub fn check_inline_asset_count(
+    memory_id: &str,
+    current: usize,
+    max: u32,
+) -> std::result::Result<(), Error> {
+    if current >= max as usize {
+        return Err(Error::InvalidArgument(format!(
+            "memory {} already has {} inline assets (max {})",
+            memory_id, current, max
+        )));
+    }
+    Ok(())
+}
+
+/// Reject any mutation of a memory its owner has frozen
+pub fn check_unlocked(memory: &crate::types::Memory) -> std::result::Result<(), Error> {
+    if memory.locked.unwrap_or(false) {
+        return Err(Error::Locked(memory.id.clone()));
+    }
+    Ok(())
+}
+
+/// Treat a memory past its `expires_at` as gone, even before the sweep deletes it
+pub fn check_not_expired(
+    memory: &crate::types::Memory,
+    now: u64,
+) -> std::result::Result<(), Error> {
+    match memory.expires_at {
+        Some(expires_at) if expires_at <= now => Err(Error::Expired {
+            expired_at: expires_at,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Compare-and-swap guard for optimistic concurrency
+///
+/// With `Some(expected)`, the stored `updated_at` must match or the update is rejected
+/// with `Conflict`. `None` skips the check.
+pub fn check_expected_updated_at(
+    memory: &crate::types::Memory,
+    expected_updated_at: Option<u64>,
+) -> std::result::Result<(), Error> {
+    match expected_updated_at {
+        Some(expected) if expected != memory.metadata.updated_at => Err(Error::Conflict(format!(
+            "memory {} was modified (expected updated_at {}, found {})",
+            memory.id, expected, memory.metadata.updated_at
+        ))),
+        _ => Ok(()),
+    }
+}
 
 /// Core memory update function - pure business logic
 pub fn memories_update_core<E: Env, S: Store>(
@@ -17,6 +106,7 @@ pub fn memories_update_core<E: Env, S: Store>(
     store: &mut S,
     memory_id: MemoryId,
     updates: MemoryUpdateData,
+    expected_updated_at: Option<u64>,
 ) -> std::result::Result<crate::types::Memory, Error> {
     // Capture timestamp once for consistency
     let now = env.now();
@@ -29,6 +119,10 @@ pub fn memories_update_core<E: Env, S: Store>(
             // TODO: Add ownership check when we have proper owner tracking
             // For now, if the caller has access to the capsule, they can update memories
 
+            // Reject frozen or stale writes before touching anything
+            check_unlocked(&memory)?;
+            check_expected_updated_at(&memory, expected_updated_at)?;
+
             // Apply updates
             if let Some(name) = updates.name {
                 memory.metadata.title = Some(name);
@@ -71,6 +165,213 @@ pub fn memories_update_core<E: Env, S: Store>(
     Err(Error::NotFound)
 }
 
+/// Freeze or unfreeze a memory
+///
+/// Owner-only in both directions: controllers can edit memories but can't lift
+/// (or impose) a freeze. Returns the memory with its new lock state.
+pub fn memories_set_locked_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    memory_id: MemoryId,
+    locked: bool,
+) -> std::result::Result<crate::types::Memory, Error> {
+    let caller = env.caller();
+    let accessible_capsules = store.get_accessible_capsules(&caller);
+
+    for capsule_id in accessible_capsules {
+        if let Some(mut memory) = store.get_memory(&capsule_id, &memory_id) {
+            let capsule_access = store
+                .get_capsule_for_acl(&capsule_id)
+                .ok_or(Error::NotFound)?;
+
+            if !capsule_access.owners.contains_key(&caller) {
+                return Err(Error::Unauthorized);
+            }
+
+            memory.locked = if locked { Some(true) } else { None };
+            store.update_memory(&capsule_id, &memory_id, memory)?;
+
+            // POST-WRITE ASSERTION: Verify the lock state was stored
+            return match store.get_memory(&capsule_id, &memory_id) {
+                Some(updated) if updated.locked.unwrap_or(false) == locked => Ok(updated),
+                _ => Err(Error::Internal(
+                    "Post-update readback failed: lock state was not stored".to_string(),
+                )),
+            };
+        }
+    }
+
+    Err(Error::NotFound)
+}
+
+/// Set or clear the time after which a memory is auto-deleted
+///
+/// Owner-only, like locking. A locked memory's expiry can't be changed. The
+/// maintenance sweep deletes the memory (and its assets) once `expires_at`
+/// has passed; until then reads give `Error::Expired`.
+pub fn memories_set_expiry_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    memory_id: MemoryId,
+    expires_at: Option<u64>,
+) -> std::result::Result<crate::types::Memory, Error> {
+    let caller = env.caller();
+    let accessible_capsules = store.get_accessible_capsules(&caller);
+
+    for capsule_id in accessible_capsules {
+        if let Some(mut memory) = store.get_memory(&capsule_id, &memory_id) {
+            let capsule_access = store
+                .get_capsule_for_acl(&capsule_id)
+                .ok_or(Error::NotFound)?;
+
+            if !capsule_access.owners.contains_key(&caller) {
+                return Err(Error::Unauthorized);
+            }
+
+            check_unlocked(&memory)?;
+
+            memory.expires_at = expires_at;
+            memory.metadata.updated_at = env.now();
+            store.update_memory(&capsule_id, &memory_id, memory)?;
+
+            // POST-WRITE ASSERTION: Verify the expiry was stored
+            return match store.get_memory(&capsule_id, &memory_id) {
+                Some(updated) if updated.expires_at == expires_at => Ok(updated),
+                _ => Err(Error::Internal(
+                    "Post-update readback failed: expiry was not stored".to_string(),
+                )),
+            };
+        }
+    }
+
+    Err(Error::NotFound)
+}
+
+/// Set the display order of a memory's assets
+///
+/// `ordered_asset_ids` must name every asset of the memory exactly once; its
+/// first entry becomes the primary asset. Returns the reordered memory.
+pub fn memories_reorder_assets_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    memory_id: MemoryId,
+    ordered_asset_ids: Vec<String>,
+) -> std::result::Result<crate::types::Memory, Error> {
+    let caller = env.caller();
+    let now = env.now();
+    let accessible_capsules = store.get_accessible_capsules(&caller);
+
+    for capsule_id in accessible_capsules {
+        if let Some(mut memory) = store.get_memory(&capsule_id, &memory_id) {
+            let capsule_access = store
+                .get_capsule_for_acl(&capsule_id)
+                .ok_or(Error::NotFound)?;
+
+            if !capsule_access.can_write(&caller) {
+                return Err(Error::Unauthorized);
+            }
+
+            check_unlocked(&memory)?;
+
+            let mut current = memory.ordered_asset_ids();
+            current.sort();
+            let mut requested = ordered_asset_ids.clone();
+            requested.sort();
+            if requested != current {
+                return Err(Error::InvalidArgument(
+                    "ordered_asset_ids must list every asset of the memory exactly once"
+                        .to_string(),
+                ));
+            }
+
+            let position = |asset_id: &String| {
+                ordered_asset_ids
+                    .iter()
+                    .position(|id| id == asset_id)
+                    .map(|index| index as u32)
+            };
+            for asset in &mut memory.inline_assets {
+                asset.order = position(&asset.asset_id);
+            }
+            for asset in &mut memory.blob_internal_assets {
+                asset.order = position(&asset.asset_id);
+            }
+            for asset in &mut memory.blob_external_assets {
+                asset.order = position(&asset.asset_id);
+            }
+            memory.metadata.updated_at = now;
+            store.update_memory(&capsule_id, &memory_id, memory)?;
+
+            // POST-WRITE ASSERTION: Verify the new order was stored
+            return match store.get_memory(&capsule_id, &memory_id) {
+                Some(updated) if updated.ordered_asset_ids() == ordered_asset_ids => Ok(updated),
+                _ => Err(Error::Internal(
+                    "Post-update readback failed: asset order was not stored".to_string(),
+                )),
+            };
+        }
+    }
+
+    Err(Error::NotFound)
+}
+
+/// Cache a client-signed URL for the external asset at `asset_index` (display order)
+///
+/// Reads serve it until `expires_at`, then fail with `Error::SignatureExpired`
+/// so the client signs a fresh one. A cache refresh, so `updated_at` is kept.
+pub fn memories_set_signed_url_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    memory_id: MemoryId,
+    asset_index: u32,
+    url: String,
+    expires_at: u64,
+) -> std::result::Result<(), Error> {
+    let caller = env.caller();
+    let now = env.now();
+    if url.trim().is_empty() {
+        return Err(Error::InvalidArgument("url is required".to_string()));
+    }
+    if expires_at <= now {
+        return Err(Error::InvalidArgument(
+            "signed url would already be expired".to_string(),
+        ));
+    }
+    let accessible_capsules = store.get_accessible_capsules(&caller);
+
+    for capsule_id in accessible_capsules {
+        if let Some(mut memory) = store.get_memory(&capsule_id, &memory_id) {
+            let capsule_access = store
+                .get_capsule_for_acl(&capsule_id)
+                .ok_or(Error::NotFound)?;
+
+            if !capsule_access.can_write(&caller) {
+                return Err(Error::Unauthorized);
+            }
+
+            let asset_id = memory
+                .ordered_asset_ids()
+                .into_iter()
+                .nth(asset_index as usize);
+            let asset = memory
+                .blob_external_assets
+                .iter_mut()
+                .find(|asset| Some(&asset.asset_id) == asset_id.as_ref())
+                .ok_or_else(|| {
+                    Error::InvalidArgument(format!(
+                        "asset index {} is not an external asset",
+                        asset_index
+                    ))
+                })?;
+            asset.signed_url = Some(SignedUrl { url, expires_at });
+
+            return store.update_memory(&capsule_id, &memory_id, memory);
+        }
+    }
+
+    Err(Error::NotFound)
+}
+
 /// Core function to add a blob asset to an existing memory
 ///
 /// This function adds a new internal blob asset to an existing memory.
@@ -100,6 +401,8 @@ pub fn memories_add_asset_core<E: Env, S: Store>(
                 return Err(Error::Unauthorized);
             }
 
+            check_unlocked(&memory)?;
+
             // Parse blob_id to get BlobRef
             let blob_ref = if asset.blob_id.starts_with("blob_") {
                 // Extract the numeric ID from "blob_1234567890"
@@ -125,6 +428,7 @@ pub fn memories_add_asset_core<E: Env, S: Store>(
                 asset_id: generate_asset_id(&caller, now),
                 blob_ref,
                 metadata: asset.metadata.clone(),
+                order: None,
             };
 
             // Add asset to memory
@@ -159,6 +463,151 @@ pub fn memories_add_asset_core<E: Env, S: Store>(
     Err(Error::NotFound)
 }
 
+/// Complete a reserved memory with its uploaded blob
+///
+/// `blob_capsule_id` is the capsule charged for the blob. Only a blob charged to
+/// the memory's own capsule can be attached: one charged elsewhere, or committed
+/// before quotas (no capsule), may belong to someone else. Returns the new asset id.
+pub fn memories_attach_blob_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    memory_id: MemoryId,
+    blob_ref: BlobRef,
+    blob_capsule_id: Option<CapsuleId>,
+) -> std::result::Result<String, Error> {
+    let caller = env.caller();
+    let now = env.now();
+    let accessible_capsules = store.get_accessible_capsules(&caller);
+
+    for capsule_id in accessible_capsules {
+        if let Some(mut memory) = store.get_memory(&capsule_id, &memory_id) {
+            let capsule_access = store
+                .get_capsule_for_acl(&capsule_id)
+                .ok_or(Error::NotFound)?;
+
+            if !capsule_access.can_write(&caller) {
+                return Err(Error::Unauthorized);
+            }
+            if blob_capsule_id.as_ref() != Some(&capsule_id) {
+                return Err(Error::Unauthorized);
+            }
+
+            check_unlocked(&memory)?;
+
+            let metadata = memory.pending.take().ok_or_else(|| {
+                Error::InvalidArgument(format!("memory {} is not pending", memory_id))
+            })?;
+            if metadata.get_base().bytes != blob_ref.len {
+                return Err(Error::InvalidArgument(
+                    "blob_ref.len != metadata.base.bytes".to_string(),
+                ));
+            }
+
+            let asset_id = generate_asset_id(&caller, now);
+            memory.blob_internal_assets.push(MemoryAssetBlobInternal {
+                asset_id: asset_id.clone(),
+                blob_ref,
+                metadata,
+                order: None,
+            });
+            memory.metadata.updated_at = now;
+            memory.update_dashboard_fields();
+
+            store.update_memory(&capsule_id, &memory_id, memory)?;
+
+            // POST-WRITE ASSERTION: Verify the memory is no longer pending
+            return match store.get_memory(&capsule_id, &memory_id) {
+                Some(updated) if updated.pending.is_none() => Ok(asset_id),
+                _ => Err(Error::Internal(
+                    "Post-update readback failed: memory is still pending".to_string(),
+                )),
+            };
+        }
+    }
+
+    Err(Error::NotFound)
+}
+
+/// Point an internal blob asset at another committed blob in one write
+///
+/// The asset keeps its ID and display order; its size and hash metadata follow
+/// the new blob. `blob_capsule_id` is the capsule charged for the new blob and
+/// must be the memory's own, as in `memories_attach_blob_core`. Returns the
+/// replaced blob reference so the caller can free it once nothing else points at it.
+pub fn memories_replace_blob_core<E: Env, S: Store>(
+    env: &E,
+    store: &mut S,
+    memory_id: MemoryId,
+    asset_id: String,
+    blob_ref: BlobRef,
+    blob_capsule_id: Option<CapsuleId>,
+) -> std::result::Result<BlobRef, Error> {
+    let caller = env.caller();
+    let now = env.now();
+    let accessible_capsules = store.get_accessible_capsules(&caller);
+
+    for capsule_id in accessible_capsules {
+        if let Some(mut memory) = store.get_memory(&capsule_id, &memory_id) {
+            let capsule_access = store
+                .get_capsule_for_acl(&capsule_id)
+                .ok_or(Error::NotFound)?;
+
+            if !capsule_access.can_write(&caller) {
+                return Err(Error::Unauthorized);
+            }
+            if blob_capsule_id.as_ref() != Some(&capsule_id) {
+                return Err(Error::Unauthorized);
+            }
+
+            check_unlocked(&memory)?;
+
+            let asset = memory
+                .blob_internal_assets
+                .iter_mut()
+                .find(|asset| asset.asset_id == asset_id)
+                .ok_or_else(|| {
+                    Error::InvalidArgument(format!(
+                        "{} is not an internal blob asset of memory {}",
+                        asset_id, memory_id
+                    ))
+                })?;
+            if asset.blob_ref.locator == blob_ref.locator {
+                return Err(Error::InvalidArgument(
+                    "asset already points at this blob".to_string(),
+                ));
+            }
+
+            let base = asset.metadata.get_base_mut();
+            base.bytes = blob_ref.len;
+            base.sha256 = blob_ref.hash;
+            base.updated_at = now;
+            let replaced = std::mem::replace(&mut asset.blob_ref, blob_ref.clone());
+
+            memory.metadata.updated_at = now;
+            memory.update_dashboard_fields();
+            store.update_memory(&capsule_id, &memory_id, memory)?;
+
+            // POST-WRITE ASSERTION: Verify the asset now points at the new blob
+            let swapped = store
+                .get_memory(&capsule_id, &memory_id)
+                .is_some_and(|updated| {
+                    updated
+                        .blob_internal_assets
+                        .iter()
+                        .any(|asset| asset.asset_id == asset_id && asset.blob_ref == blob_ref)
+                });
+            if !swapped {
+                return Err(Error::Internal(
+                    "Post-update readback failed: blob was not replaced".to_string(),
+                ));
+            }
+            return Ok(replaced);
+        }
+    }
+
+    Err(Error::NotFound)
+}
+
 /// Core function to add an inline asset to an existing memory
 ///
 /// This function adds a new inline asset to an existing memory.
@@ -188,11 +637,20 @@ pub fn memories_add_inline_asset_core<E: Env, S: Store>(
                 return Err(Error::Unauthorized);
             }
 
+            check_unlocked(&memory)?;
+
+            check_inline_asset_count(
+                &memory_id,
+                memory.inline_assets.len(),
+                max_inline_assets_per_memory(),
+            )?;
+
             // Create the new inline asset
             let new_asset = MemoryAssetInline {
                 asset_id: generate_asset_id(&caller, now),
                 bytes: asset.bytes.clone(),
                 metadata: asset.metadata.clone(),
+                order: None,
             };
 
             // Add asset to memory
@@ -230,6 +688,10 @@ pub fn memories_add_inline_asset_core<E: Env, S: Store>(
 #[cfg(test)]
 mod tests {
     use crate::capsule::domain::SharingStatus;
+    use crate::memories::core::{memories_reserve_core, Store};
+    use crate::test_utils::{
+        controller_of, create_test_asset_metadata, mixed_asset_memory, mock_capsule, MockStore,
+    };
     use crate::types::*;
 
     /// Test utility to create a Memory with default values
@@ -272,6 +734,61 @@ mod tests {
             inline_assets: vec![],
             blob_internal_assets: vec![],
             blob_external_assets: vec![],
+            locked: None,
+            pending: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_check_expected_updated_at_matching() {
+        let memory = create_test_memory("test_memory", Some("Test Memory"), false, 0, "private");
+
+        assert!(super::check_expected_updated_at(&memory, None).is_ok());
+        assert!(super::check_expected_updated_at(&memory, Some(1234567890)).is_ok());
+    }
+
+    #[test]
+    fn test_check_expected_updated_at_stale() {
+        let memory = create_test_memory("test_memory", Some("Test Memory"), false, 0, "private");
+
+        let result = super::check_expected_updated_at(&memory, Some(1234567000));
+        assert!(matches!(result, Err(Error::Conflict(_))));
+    }
+
+    #[test]
+    fn test_memories_update_rejects_stale_expected_updated_at() {
+        use crate::memories::core::memories_update_core;
+        use crate::types::MemoryUpdateData;
+
+        let (env, mut store) = mock_capsule("capsule_cas");
+        let capsule_id = "capsule_cas".to_string();
+        let memory = crate::test_utils::create_test_memory_at("mem_cas", "capsule_cas", 1);
+        store.insert_memory(&capsule_id, memory.clone()).unwrap();
+        let rename = MemoryUpdateData {
+            name: Some("renamed".to_string()),
+            metadata: None,
+            access_entries: None,
+        };
+
+        let result = memories_update_core(&env, &mut store, "mem_cas".to_string(), rename, Some(0));
+        assert!(matches!(result, Err(Error::Conflict(_))));
+
+        let stored = store
+            .get_memory(&capsule_id, &"mem_cas".to_string())
+            .unwrap();
+        assert_eq!(stored.metadata.title, memory.metadata.title);
+        assert_eq!(stored.metadata.updated_at, 1);
+    }
+
+    #[test]
+    fn test_check_inline_asset_count_boundary() {
+        assert!(super::check_inline_asset_count("mem_1", 15, 16).is_ok());
+
+        let result = super::check_inline_asset_count("mem_1", 16, 16);
+        match result {
+            Err(Error::InvalidArgument(msg)) => assert!(msg.contains("max 16")),
+            other => panic!("expected InvalidArgument, got {:?}", other),
         }
     }
 
@@ -332,4 +849,311 @@ mod tests {
         assert_eq!(header.description, Some("Test Description".to_string()));
         assert_eq!(header.tags, vec!["test".to_string()]);
     }
+
+    #[test]
+    fn test_locked_memory_rejects_mutations_until_owner_unlocks() {
+        use crate::memories::core::{
+            asset_remove_by_id_core, memories_delete_core, memories_set_locked_core,
+            memories_update_core,
+        };
+        use crate::types::MemoryUpdateData;
+
+        let (env, mut store) = mock_capsule("capsule_lock");
+        store
+            .insert_memory(
+                &"capsule_lock".to_string(),
+                crate::test_utils::create_test_memory_at("mem_lock", "capsule_lock", 1),
+            )
+            .unwrap();
+        let rename = || MemoryUpdateData {
+            name: Some("renamed".to_string()),
+            metadata: None,
+            access_entries: None,
+        };
+
+        let locked =
+            memories_set_locked_core(&env, &mut store, "mem_lock".to_string(), true).unwrap();
+        assert_eq!(locked.locked, Some(true));
+
+        assert!(matches!(
+            memories_update_core(&env, &mut store, "mem_lock".to_string(), rename(), None),
+            Err(Error::Locked(_))
+        ));
+        assert!(matches!(
+            asset_remove_by_id_core(
+                &env,
+                &mut store,
+                "mem_lock".to_string(),
+                "any_asset".to_string()
+            ),
+            Err(Error::Locked(_))
+        ));
+        assert!(matches!(
+            memories_delete_core(&env, &mut store, "mem_lock".to_string(), false, false),
+            Err(Error::Locked(_))
+        ));
+
+        // Only an owner can lift the freeze
+        let controller = controller_of(&mut store, "capsule_lock", 1_000);
+        assert!(matches!(
+            memories_set_locked_core(&controller, &mut store, "mem_lock".to_string(), false),
+            Err(Error::Unauthorized)
+        ));
+
+        memories_set_locked_core(&env, &mut store, "mem_lock".to_string(), false).unwrap();
+        let updated =
+            memories_update_core(&env, &mut store, "mem_lock".to_string(), rename(), None).unwrap();
+        assert_eq!(updated.metadata.title, Some("renamed".to_string()));
+        memories_delete_core(&env, &mut store, "mem_lock".to_string(), false, false).unwrap();
+        assert!(store
+            .get_memory(&"capsule_lock".to_string(), &"mem_lock".to_string())
+            .is_none());
+    }
+
+    #[test]
+    fn test_memories_reserve_then_attach_completes_memory() {
+        use crate::memories::core::memories_attach_blob_core;
+
+        let (env, mut store) = mock_capsule("capsule_reserve");
+        let capsule_id = "capsule_reserve".to_string();
+
+        let memory_id = memories_reserve_core(
+            &env,
+            &mut store,
+            capsule_id.clone(),
+            create_test_asset_metadata(),
+            "idem_reserve".to_string(),
+        )
+        .unwrap();
+
+        // Placeholder: listed as pending, holds no bytes
+        let reserved = store.get_memory(&capsule_id, &memory_id).unwrap();
+        assert!(reserved.to_header().pending);
+        assert_eq!(reserved.metadata.total_size, 0);
+        assert!(reserved.inline_assets.is_empty());
+
+        // Retrying the reserve returns the same placeholder
+        let again = memories_reserve_core(
+            &env,
+            &mut store,
+            capsule_id.clone(),
+            create_test_asset_metadata(),
+            "idem_reserve".to_string(),
+        )
+        .unwrap();
+        assert_eq!(again, memory_id);
+
+        let blob_ref = BlobRef {
+            locator: "blob_42".to_string(),
+            hash: Some([1u8; 32]),
+            len: 1024,
+        };
+        memories_attach_blob_core(
+            &env,
+            &mut store,
+            memory_id.clone(),
+            blob_ref.clone(),
+            Some(capsule_id.clone()),
+        )
+        .unwrap();
+
+        let completed = store.get_memory(&capsule_id, &memory_id).unwrap();
+        assert!(!completed.to_header().pending);
+        assert_eq!(completed.blob_internal_assets.len(), 1);
+        assert_eq!(completed.metadata.total_size, 1024);
+
+        // Only a pending memory can take an attach
+        assert!(matches!(
+            memories_attach_blob_core(
+                &env,
+                &mut store,
+                memory_id,
+                blob_ref,
+                Some(capsule_id.clone())
+            ),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_memories_attach_blob_requires_blob_of_the_same_capsule() {
+        use crate::memories::core::memories_attach_blob_core;
+
+        let (env, mut store) = mock_capsule("capsule_attach_owner");
+        let capsule_id = "capsule_attach_owner".to_string();
+        let memory_id = memories_reserve_core(
+            &env,
+            &mut store,
+            capsule_id.clone(),
+            create_test_asset_metadata(),
+            "idem_attach_owner".to_string(),
+        )
+        .unwrap();
+        let blob_ref = BlobRef {
+            locator: "blob_43".to_string(),
+            hash: Some([1u8; 32]),
+            len: 1024,
+        };
+
+        // A blob charged to another capsule, or to none (committed before quotas),
+        // may be someone else's
+        for owner in [Some("other_capsule".to_string()), None] {
+            assert_eq!(
+                memories_attach_blob_core(
+                    &env,
+                    &mut store,
+                    memory_id.clone(),
+                    blob_ref.clone(),
+                    owner
+                ),
+                Err(Error::Unauthorized)
+            );
+        }
+        assert!(store
+            .get_memory(&capsule_id, &memory_id)
+            .unwrap()
+            .pending
+            .is_some());
+    }
+
+    #[test]
+    fn test_memories_reorder_assets_changes_primary() {
+        use crate::memories::core::{memories_list_assets_core, memories_reorder_assets_core};
+
+        let (env, mut store) = mock_capsule("capsule_reorder");
+        let capsule_id = "capsule_reorder".to_string();
+        store
+            .insert_memory(
+                &capsule_id,
+                mixed_asset_memory(&env, "mem_reorder", &capsule_id, "blob_1"),
+            )
+            .unwrap();
+
+        // Without an explicit order the inline asset comes first
+        let before = store
+            .get_memory(&capsule_id, &"mem_reorder".to_string())
+            .unwrap();
+        let ids = before.ordered_asset_ids();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(ids[0], "inline_1");
+        let internal_id = before.blob_internal_assets[0].asset_id.clone();
+
+        let new_order = vec![
+            "external_1".to_string(),
+            "inline_1".to_string(),
+            internal_id.clone(),
+        ];
+        let memory = memories_reorder_assets_core(
+            &env,
+            &mut store,
+            "mem_reorder".to_string(),
+            new_order.clone(),
+        )
+        .unwrap();
+        assert_eq!(memory.ordered_asset_ids(), new_order);
+
+        let listed = memories_list_assets_core(&env, &store, "mem_reorder".to_string()).unwrap();
+        assert_eq!(listed.asset_ids, new_order);
+        assert_eq!(listed.asset_ids[0], "external_1");
+
+        // The list must name every asset exactly once
+        for bad in [
+            vec!["external_1".to_string(), "inline_1".to_string()],
+            vec![
+                "external_1".to_string(),
+                "inline_1".to_string(),
+                "inline_1".to_string(),
+            ],
+            vec![
+                "external_1".to_string(),
+                "inline_1".to_string(),
+                "unknown".to_string(),
+            ],
+        ] {
+            assert!(matches!(
+                memories_reorder_assets_core(&env, &mut store, "mem_reorder".to_string(), bad),
+                Err(Error::InvalidArgument(_))
+            ));
+        }
+        let unchanged = store
+            .get_memory(&capsule_id, &"mem_reorder".to_string())
+            .unwrap();
+        assert_eq!(unchanged.ordered_asset_ids(), new_order);
+    }
+
+    #[test]
+    fn test_memories_replace_blob_keeps_asset_id() {
+        use crate::memories::core::{memories_reorder_assets_core, memories_replace_blob_core};
+
+        let (env, mut store) = mock_capsule("capsule_swap");
+        let capsule_id = "capsule_swap".to_string();
+        let memory_id = "mem_swap".to_string();
+        store
+            .insert_memory(
+                &capsule_id,
+                mixed_asset_memory(&env, &memory_id, &capsule_id, "blob_1"),
+            )
+            .unwrap();
+        let memory = store.get_memory(&capsule_id, &memory_id).unwrap();
+        let asset_id = memory.blob_internal_assets[0].asset_id.clone();
+        let order = vec![
+            asset_id.clone(),
+            "inline_1".to_string(),
+            "external_1".to_string(),
+        ];
+        memories_reorder_assets_core(&env, &mut store, memory_id.clone(), order.clone()).unwrap();
+
+        let new_blob = BlobRef {
+            locator: "blob_2".to_string(),
+            hash: Some([7u8; 32]),
+            len: 2048,
+        };
+        let replaced = memories_replace_blob_core(
+            &env,
+            &mut store,
+            memory_id.clone(),
+            asset_id.clone(),
+            new_blob.clone(),
+            Some(capsule_id.clone()),
+        )
+        .unwrap();
+        assert_eq!(replaced.locator, "blob_1");
+
+        let memory = store.get_memory(&capsule_id, &memory_id).unwrap();
+        assert_eq!(memory.blob_internal_assets.len(), 1);
+        let asset = &memory.blob_internal_assets[0];
+        assert_eq!(asset.asset_id, asset_id);
+        assert_eq!(asset.blob_ref, new_blob);
+        assert_eq!(asset.metadata.get_base().bytes, 2048);
+        assert_eq!(asset.metadata.get_base().sha256, Some([7u8; 32]));
+        assert_eq!(memory.ordered_asset_ids(), order);
+
+        // Only internal blob assets can be swapped, and only to the memory's own blobs
+        let swap = |store: &mut MockStore, asset_id: &str, owner: Option<&str>| {
+            memories_replace_blob_core(
+                &env,
+                store,
+                memory_id.clone(),
+                asset_id.to_string(),
+                BlobRef {
+                    locator: "blob_3".to_string(),
+                    hash: None,
+                    len: 10,
+                },
+                owner.map(str::to_string),
+            )
+        };
+        assert!(matches!(
+            swap(&mut store, "inline_1", Some(&capsule_id)),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(
+            swap(&mut store, &asset_id, Some("other_capsule")),
+            Err(Error::Unauthorized)
+        );
+        // A blob committed before quotas has no capsule and may be someone else's
+        assert_eq!(swap(&mut store, &asset_id, None), Err(Error::Unauthorized));
+        let memory = store.get_memory(&capsule_id, &memory_id).unwrap();
+        assert_eq!(memory.blob_internal_assets[0].blob_ref, new_blob);
+    }
 }
@@ -6,7 +6,7 @@ use std::borrow::Cow;
 // Import types from the main types module
 use crate::types::{StorageEdgeBlobType, StorageEdgeDatabaseType};
 // Import access control types from capsule domain
-use crate::capsule::domain::SharingStatus;
+use crate::capsule::domain::{DefaultMemoryAccess, SharingStatus};
 // Import AssetLink and AssetLinks from utils
 use crate::memories::utils::AssetLinks;
 
@@ -199,6 +199,7 @@ pub struct MemoryAssetInline {
     pub asset_id: String, // Unique identifier for this asset
     pub bytes: Vec<u8>,
     pub metadata: AssetMetadata,
+    pub order: Option<u32>, // Position set by memories_reorder_assets (None sorts last)
 }
 
 /// Blob asset (reference to ICP blob store)
@@ -207,6 +208,7 @@ pub struct MemoryAssetBlobInternal {
     pub asset_id: String, // Unique identifier for this asset
     pub blob_ref: BlobRef,
     pub metadata: AssetMetadata,
+    pub order: Option<u32>, // Position set by memories_reorder_assets (None sorts last)
 }
 
 /// External blob asset (reference to external storage)
@@ -217,6 +219,15 @@ pub struct MemoryAssetBlobExternal {
     pub storage_key: String,           // Key/ID in external storage system
     pub url: Option<String>,           // Public URL (if available)
     pub metadata: AssetMetadata,       // Type-specific metadata
+    pub order: Option<u32>,            // Position set by memories_reorder_assets (None sorts last)
+    pub signed_url: Option<SignedUrl>, // Short-lived URL for private stores, signed by the client
+}
+
+/// A client-signed URL for an external asset, served until `expires_at`
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct SignedUrl {
+    pub url: String,
+    pub expires_at: u64,
 }
 
 /// Legacy struct for backward compatibility (will be removed)
@@ -237,6 +248,9 @@ pub struct Memory {
     pub inline_assets: Vec<MemoryAssetInline>,                    // 0 or more inline assets
     pub blob_internal_assets: Vec<MemoryAssetBlobInternal>,       // 0 or more ICP blob assets
     pub blob_external_assets: Vec<MemoryAssetBlobExternal>,       // 0 or more external blob assets
+    pub locked: Option<bool>, // frozen by the owner: no edits, deletes or asset removals
+    pub pending: Option<AssetMetadata>, // reserved via memories_reserve: asset still uploading
+    pub expires_at: Option<u64>, // auto-deleted by the maintenance sweep once passed
 }
 
 /// Memory header for listings
@@ -265,6 +279,8 @@ pub struct MemoryHeader {
 
     // NEW: Storage location information
     pub database_storage_edges: Vec<StorageEdgeDatabaseType>, // Where the memory is stored: ['Icp'], ['Neon'], ['Icp', 'Neon']
+
+    pub pending: bool, // reserved, bytes not attached yet
 }
 
 /// Memory operation response
@@ -291,12 +307,22 @@ pub struct MemoryListResponse {
     pub message: String,
 }
 
+/// Outcome of resolving one ID in a presence check
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub enum PresenceStatus {
+    Present,
+    /// Missing, or held in a capsule the caller can't read (deliberately indistinguishable)
+    NotFoundOrInaccessible,
+}
+
 /// Memory presence check result
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
 pub struct MemoryPresenceResult {
     pub memory_id: String,
     pub metadata_present: bool,
     pub asset_present: bool,
+    pub status: PresenceStatus,
+    pub capsule_id: Option<String>, // capsule holding the memory, for routing follow-up reads
 }
 
 /// Simple memory metadata structure for ICP storage
@@ -361,6 +387,17 @@ impl AssetMetadata {
             AssetMetadata::Note(note) => &note.base,
         }
     }
+
+    /// Mutable access to the base metadata
+    pub fn get_base_mut(&mut self) -> &mut AssetMetadataBase {
+        match self {
+            AssetMetadata::Image(img) => &mut img.base,
+            AssetMetadata::Video(vid) => &mut vid.base,
+            AssetMetadata::Audio(audio) => &mut audio.base,
+            AssetMetadata::Document(doc) => &mut doc.base,
+            AssetMetadata::Note(note) => &mut note.base,
+        }
+    }
 }
 
 // ============================================================================
@@ -381,6 +418,17 @@ pub struct AssetCleanupResult {
     pub memory_id: String,
     pub assets_cleaned: u32,
     pub message: String,
+    pub inline_removed: u32,
+    pub internal_removed: u32,
+    pub external_removed: u32,
+}
+
+/// Where an asset's bytes live, for removing assets by storage kind
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub enum AssetStorageKind {
+    Inline,
+    Internal,
+    External,
 }
 
 // ============================================================================
@@ -412,6 +460,26 @@ pub struct ExternalBlobAssetInput {
     pub metadata: AssetMetadata,
 }
 
+/// Input for one memory in `memories_create_bulk` (same fields as `memories_create`)
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct MemoryCreateInput {
+    pub bytes: Option<Vec<u8>>,
+    pub blob_ref: Option<BlobRef>,
+    pub external_location: Option<StorageEdgeBlobType>,
+    pub external_storage_key: Option<String>,
+    pub external_url: Option<String>,
+    pub external_size: Option<u64>,
+    pub external_hash: Option<Vec<u8>>,
+    pub asset_metadata: AssetMetadata,
+}
+
+/// Optional settings for a new memory (all None keeps the defaults)
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct MemoryCreateOptions {
+    pub expires_at: Option<u64>, // owner-only; auto-deleted once passed
+    pub access: Option<DefaultMemoryAccess>, // None uses the capsule default
+}
+
 /// Result type for bulk asset cleanup operations
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
 pub struct BulkAssetCleanupResult {
@@ -436,9 +504,51 @@ pub struct MemoryAssetsList {
     pub inline_assets: Vec<String>, // Asset references for inline assets
     pub internal_assets: Vec<String>, // Blob references for ICP assets
     pub external_assets: Vec<String>, // Storage keys for external assets
+    pub asset_ids: Vec<String>,     // All asset IDs in display order; the first is primary
     pub total_count: u32,
 }
 
+/// One asset of a memory with where it lives (for `memories_asset_inventory`)
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct AssetInventoryEntry {
+    pub asset_id: String,
+    pub kind: AssetStorageKind,
+    pub size: u64,
+    pub content_type: String,
+    pub sha256: Option<[u8; 32]>,
+    pub backend: crate::types::BlobHosting,
+    pub locally_available: bool, // bytes present in this canister (never for external assets)
+}
+
+/// Every asset of a memory, with per-kind byte totals
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct AssetInventory {
+    pub memory_id: String,
+    pub capsule_id: String,
+    pub assets: Vec<AssetInventoryEntry>, // inline, then internal, then external
+    pub inline_bytes: u64,
+    pub blob_bytes: u64, // internal blobs stored in this canister
+    pub external_bytes: u64,
+}
+
+/// Assets of one kind on one backend (for `capsules_storage_distribution`)
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct BackendUsage {
+    pub backend: crate::types::BlobHosting,
+    pub kind: AssetStorageKind, // tells ICP inline bytes apart from ICP blobs
+    pub asset_count: u32,
+    pub bytes: u64,
+}
+
+/// How a capsule's asset bytes are spread across storage backends
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct StorageDistribution {
+    pub capsule_id: String,
+    pub memory_count: u32,
+    pub backends: Vec<BackendUsage>, // in order of first appearance
+    pub total_bytes: u64,
+}
+
 /// Gallery memory entry (for gallery-specific memory references)
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
 pub struct GalleryMemoryEntry {
@@ -34,6 +34,65 @@ pub const MEM_BLOB_COUNTER: MemoryId = MemoryId::new(8);
 
 // Admin storage
 pub const MEM_ADMINS: MemoryId = MemoryId::new(9);
+pub const MEM_ADMIN_GRANTS: MemoryId = MemoryId::new(10);
+
+// Capsule event feed
+pub const MEM_EVENTS: MemoryId = MemoryId::new(11);
+
+// Idempotency-key registry
+pub const MEM_IDEMPOTENCY: MemoryId = MemoryId::new(12);
+
+// Admin action audit log
+pub const MEM_ADMIN_AUDIT: MemoryId = MemoryId::new(13);
+
+// Reverse grant index (person -> capsules)
+pub const MEM_GRANT_INDEX: MemoryId = MemoryId::new(14);
+
+// Per-memory view/download counters and the capsules that opted in
+pub const MEM_MEMORY_STATS: MemoryId = MemoryId::new(15);
+pub const MEM_STATS_CAPSULES: MemoryId = MemoryId::new(16);
+
+// Registered opaque (non-principal) persons
+pub const MEM_OPAQUE_PERSONS: MemoryId = MemoryId::new(17);
+
+// Recently deleted memory IDs
+pub const MEM_MEMORY_TOMBSTONES: MemoryId = MemoryId::new(18);
+
+// Share link tokens (by hash) and the memory each one opens
+pub const MEM_SHARE_LINKS: MemoryId = MemoryId::new(19);
+
+// Delegation tokens (by hash) for creating a capsule about someone else
+pub const MEM_CREATION_DELEGATIONS: MemoryId = MemoryId::new(20);
+
+// Content index for upload dedup: hash of (capsule, checksum) -> blob ID
+pub const MEM_BLOB_CHECKSUMS: MemoryId = MemoryId::new(21);
+
+// Idempotency-key TTL set by admins
+pub const MEM_IDEM_TTL: MemoryId = MemoryId::new(22);
+
+// Internal blob refcounts: blob ID -> memory assets referencing it
+pub const MEM_BLOB_REFS: MemoryId = MemoryId::new(23);
+
+// Full-object read threshold set by admins
+pub const MEM_RESPONSE_LIMIT: MemoryId = MemoryId::new(24);
+
+// Inline asset cap per memory set by admins
+pub const MEM_MAX_INLINE_ASSETS: MemoryId = MemoryId::new(25);
+
+// Advanced-settings default for new capsules set by admins
+pub const MEM_ADVANCED_SETTINGS_DEFAULT: MemoryId = MemoryId::new(26);
+
+// Log level set by admins
+pub const MEM_LOG_LEVEL: MemoryId = MemoryId::new(27);
+
+// Gallery cap per capsule set by admins
+pub const MEM_MAX_GALLERIES: MemoryId = MemoryId::new(28);
+
+// Set once grants stored before the reverse grant index are indexed
+pub const MEM_GRANT_INDEX_BACKFILLED: MemoryId = MemoryId::new(29);
+
+// Legacy upgrade-blob capsules not yet moved into the capsule store
+pub const MEM_LEGACY_PENDING: MemoryId = MemoryId::new(30);
 
 thread_local! {
     /// Global memory manager for all stable structures
@@ -0,0 +1,132 @@
+//! Per-memory view/download counters
+//!
+//! Counting is opt-in per capsule so capsules that don't want analytics pay
+//! nothing. Counters live in their own stable map keyed by memory id rather
+//! than on `Memory`, so an increment never rewrites the whole capsule.
+//!
+//! The read endpoints are queries: an increment made while serving a plain
+//! query is discarded with the rest of the query's state, and only persists
+//! when the client invokes the method as an update (replicated) call.
+
+use crate::memory::{MEM_MEMORY_STATS, MEM_STATS_CAPSULES, MM};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableBTreeMap, Storable};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+thread_local! {
+    static STABLE_MEMORY_STATS: RefCell<StableBTreeMap<String, MemoryStats, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_MEMORY_STATS)))
+    );
+
+    // Capsules that opted in to counting
+    static STABLE_STATS_CAPSULES: RefCell<StableBTreeMap<String, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_STATS_CAPSULES)))
+    );
+}
+
+/// Access counters of one memory
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct MemoryStats {
+    pub views: u64,
+    pub downloads: u64,
+    pub last_accessed: Option<u64>,
+}
+
+impl Storable for MemoryStats {
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 64,
+        is_fixed_size: false,
+    };
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode MemoryStats"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, stats): (u16, MemoryStats) =
+            Decode!(bytes.as_ref(), (u16, MemoryStats)).expect("Failed to decode MemoryStats");
+        assert_eq!(version, 1, "Unsupported MemoryStats version");
+        stats
+    }
+}
+
+/// Kind of access being counted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccessKind {
+    View,
+    Download,
+}
+
+/// Whether `capsule_id` opted in to counting
+pub fn is_enabled(capsule_id: &str) -> bool {
+    STABLE_STATS_CAPSULES.with(|capsules| capsules.borrow().contains_key(&capsule_id.to_string()))
+}
+
+/// Turn counting on or off for a capsule (existing counters are kept)
+pub fn set_enabled(capsule_id: &str, enabled: bool) {
+    STABLE_STATS_CAPSULES.with(|capsules| {
+        let mut capsules = capsules.borrow_mut();
+        if enabled {
+            capsules.insert(capsule_id.to_string(), ());
+        } else {
+            capsules.remove(&capsule_id.to_string());
+        }
+    });
+}
+
+/// Count one access to a memory of `capsule_id`; no-op unless the capsule opted in
+pub fn record(capsule_id: &str, memory_id: &str, kind: AccessKind, now: u64) {
+    if !is_enabled(capsule_id) {
+        return;
+    }
+    STABLE_MEMORY_STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        let mut entry = stats.get(&memory_id.to_string()).unwrap_or_default();
+        match kind {
+            AccessKind::View => entry.views = entry.views.saturating_add(1),
+            AccessKind::Download => entry.downloads = entry.downloads.saturating_add(1),
+        }
+        entry.last_accessed = Some(now);
+        stats.insert(memory_id.to_string(), entry);
+    });
+}
+
+/// Counters of a memory (zero when never counted)
+pub fn get(memory_id: &str) -> MemoryStats {
+    STABLE_MEMORY_STATS.with(|stats| {
+        stats
+            .borrow()
+            .get(&memory_id.to_string())
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_counts_only_opted_in_capsules() {
+        record("capsule_off", "mem_off", AccessKind::View, 5);
+        assert_eq!(get("mem_off"), MemoryStats::default());
+
+        set_enabled("capsule_on", true);
+        record("capsule_on", "mem_on", AccessKind::View, 5);
+        record("capsule_on", "mem_on", AccessKind::Download, 7);
+
+        let stats = get("mem_on");
+        assert_eq!(stats.views, 1);
+        assert_eq!(stats.downloads, 1);
+        assert_eq!(stats.last_accessed, Some(7));
+
+        // Opting out stops counting but keeps what was recorded
+        set_enabled("capsule_on", false);
+        record("capsule_on", "mem_on", AccessKind::View, 9);
+        assert_eq!(get("mem_on").views, 1);
+    }
+}
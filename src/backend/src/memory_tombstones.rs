@@ -0,0 +1,128 @@
+//! Tombstones for recently deleted memories
+//!
+//! Deleting a memory records its ID here for a retention window, so reads can
+//! answer `Error::Deleted { deleted_at }` instead of a plain `NotFound` and
+//! clients can tell "gone" from "never existed". The set is bounded: expired
+//! tombstones are dropped by the maintenance timer, and when the set is full
+//! the oldest tombstone makes room for the new one.
+
+use crate::capsule::time::DAY_NS;
+use crate::memory::{MEM_MEMORY_TOMBSTONES, MM};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableBTreeMap, Storable};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// How long a deleted memory ID keeps answering `Deleted`
+pub const TOMBSTONE_RETENTION_NS: u64 = 7 * DAY_NS;
+
+/// Most tombstones kept at once
+pub const TOMBSTONE_MAX_ENTRIES: u64 = 10_000;
+
+thread_local! {
+    static STABLE_TOMBSTONES: RefCell<StableBTreeMap<String, Tombstone, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_MEMORY_TOMBSTONES)))
+    );
+}
+
+/// Record of one deleted memory
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Tombstone {
+    pub capsule_id: String, // only callers who can read this capsule learn about the deletion
+    pub deleted_at: u64,
+}
+
+impl Tombstone {
+    fn is_live(&self, now: u64) -> bool {
+        now.saturating_sub(self.deleted_at) < TOMBSTONE_RETENTION_NS
+    }
+}
+
+impl Storable for Tombstone {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode Tombstone"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, tombstone): (u16, Tombstone) =
+            Decode!(bytes.as_ref(), (u16, Tombstone)).expect("Failed to decode Tombstone");
+        assert_eq!(version, 1, "Unsupported Tombstone version");
+        tombstone
+    }
+}
+
+/// Record that `memory_id` of `capsule_id` was deleted at `now`
+pub fn record(memory_id: &str, capsule_id: &str, now: u64) {
+    if STABLE_TOMBSTONES.with(|t| t.borrow().len()) >= TOMBSTONE_MAX_ENTRIES {
+        purge_expired(now);
+    }
+    STABLE_TOMBSTONES.with(|tombstones| {
+        let mut tombstones = tombstones.borrow_mut();
+        if tombstones.len() >= TOMBSTONE_MAX_ENTRIES {
+            let oldest = tombstones
+                .iter()
+                .min_by_key(|(_, tombstone)| tombstone.deleted_at)
+                .map(|(id, _)| id);
+            if let Some(oldest) = oldest {
+                tombstones.remove(&oldest);
+            }
+        }
+        tombstones.insert(
+            memory_id.to_string(),
+            Tombstone {
+                capsule_id: capsule_id.to_string(),
+                deleted_at: now,
+            },
+        );
+    });
+}
+
+/// Live tombstone of `memory_id`, if it was deleted within the retention window
+pub fn get(memory_id: &str, now: u64) -> Option<Tombstone> {
+    STABLE_TOMBSTONES.with(|tombstones| {
+        tombstones
+            .borrow()
+            .get(&memory_id.to_string())
+            .filter(|tombstone| tombstone.is_live(now))
+    })
+}
+
+/// Drop tombstones past the retention window; returns how many were dropped
+pub fn purge_expired(now: u64) -> usize {
+    STABLE_TOMBSTONES.with(|tombstones| {
+        let mut tombstones = tombstones.borrow_mut();
+        let expired: Vec<String> = tombstones
+            .iter()
+            .filter(|(_, tombstone)| !tombstone.is_live(now))
+            .map(|(id, _)| id)
+            .collect();
+        for id in &expired {
+            tombstones.remove(id);
+        }
+        expired.len()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tombstones_expire_after_retention() {
+        record("mem_old", "capsule", 1_000);
+        record("mem_new", "capsule", 1_000 + TOMBSTONE_RETENTION_NS);
+
+        assert_eq!(get("mem_old", 2_000).unwrap().deleted_at, 1_000);
+        assert!(get("mem_old", 1_000 + TOMBSTONE_RETENTION_NS).is_none());
+
+        assert_eq!(purge_expired(1_000 + TOMBSTONE_RETENTION_NS), 1);
+        assert!(get("mem_new", 1_000 + TOMBSTONE_RETENTION_NS).is_some());
+        assert!(get("mem_missing", 2_000).is_none());
+    }
+}
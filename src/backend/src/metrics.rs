@@ -0,0 +1,109 @@
+//! Canister-wide metrics for dashboards and alerting
+//!
+//! Most values are read live from counters the stores already maintain, so the
+//! `metrics` query stays cheap. Values that would need a scan over every
+//! capsule or blob (memory count, blob bytes) are sampled by the maintenance
+//! timer instead: they are at most one maintenance interval (15 minutes) old,
+//! and absent (`sampled_at == None`) until the first sweep after an install or
+//! upgrade.
+
+use crate::capsule::domain::Capsule;
+use crate::capsule_store::{types::PaginationOrder as Order, CapsuleStore};
+use crate::memory::with_capsule_store;
+use crate::upload::blob_store::{BlobStore, StableBlobStore};
+use crate::upload::types::BlobMeta;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::cell::Cell;
+
+thread_local! {
+    // Heap-only: resampled by the next maintenance sweep after an upgrade
+    static SAMPLE: Cell<Option<MetricsSample>> = const { Cell::new(None) };
+}
+
+/// Values taken from a full scan by the maintenance timer
+#[derive(CandidType, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct MetricsSample {
+    pub memory_count: u64,
+    pub blob_bytes: u64, // sum of stored blob sizes
+    pub sampled_at: u64,
+}
+
+/// Metrics payload returned by the `metrics` query
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Metrics {
+    // Live values
+    pub capsule_count: u64,
+    pub blob_count: u64,
+    pub active_upload_sessions: u64,
+    pub admin_count: u64, // stored admins; hardcoded superadmins are not counted
+    pub cycle_balance: u128,
+    // Sampled by the maintenance timer; None until the first sweep
+    pub sample: Option<MetricsSample>,
+}
+
+/// Aggregate the sampled values from capsules and blob metadata
+pub fn sample_from(capsules: &[Capsule], metas: &[(u64, BlobMeta)], now: u64) -> MetricsSample {
+    MetricsSample {
+        memory_count: capsules.iter().map(|c| c.memories.len() as u64).sum(),
+        blob_bytes: metas.iter().map(|(_, meta)| meta.size).sum(),
+        sampled_at: now,
+    }
+}
+
+/// Rescan capsules and blobs and store the result (maintenance timer)
+pub fn refresh_sample(now: u64) {
+    let capsules = with_capsule_store(|store| store.paginate(None, u32::MAX, Order::Asc).items);
+    let sample = sample_from(&capsules, &StableBlobStore.metas(), now);
+    SAMPLE.with(|cell| cell.set(Some(sample)));
+}
+
+/// Current metrics: live counters plus the latest sample
+pub fn current() -> Metrics {
+    Metrics {
+        capsule_count: with_capsule_store(|store| store.count()),
+        blob_count: StableBlobStore.meta_count(),
+        active_upload_sessions: crate::upload::service::total_session_count() as u64,
+        admin_count: crate::admin::admin_count(),
+        cycle_balance: ic_cdk::api::canister_cycle_balance(),
+        sample: SAMPLE.with(|cell| cell.get()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_test_capsule_at, create_test_memory_at};
+    use crate::types::PersonRef;
+    use candid::Principal;
+
+    #[test]
+    fn test_sample_from_sums_memories_and_blob_sizes() {
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut first = create_test_capsule_at("c1", owner.clone(), 1);
+        for id in ["m1", "m2"] {
+            first
+                .memories
+                .insert(id.to_string(), create_test_memory_at(id, "c1", 1));
+        }
+        let second = create_test_capsule_at("c2", owner, 1);
+
+        let meta = |size| BlobMeta {
+            size,
+            checksum: [0; 32],
+            created_at: 1,
+            pmid_hash: [0; 32],
+            capsule_id: None,
+        };
+        let sample = sample_from(&[first, second], &[(1, meta(10)), (2, meta(32))], 7);
+
+        assert_eq!(
+            sample,
+            MetricsSample {
+                memory_count: 2,
+                blob_bytes: 42,
+                sampled_at: 7,
+            }
+        );
+    }
+}
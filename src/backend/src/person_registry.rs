@@ -0,0 +1,129 @@
+//! Registry of opaque persons
+//!
+//! Subjects that have no principal (e.g. the deceased subject of a memorial
+//! capsule) are referenced as `PersonRef::Opaque(id)`. Minting those ids here
+//! rather than letting clients pick them keeps two registrations from ever
+//! sharing an id: each minted id is recorded, and a candidate that is already
+//! taken is re-derived.
+
+use crate::memory::{MEM_OPAQUE_PERSONS, MM};
+use crate::types::{Error, PersonRef};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Longest display hint accepted, in characters
+pub const DISPLAY_HINT_MAX_CHARS: usize = 128;
+
+thread_local! {
+    static STABLE_OPAQUE_PERSONS: RefCell<StableBTreeMap<String, OpaquePerson, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_OPAQUE_PERSONS)))
+    );
+}
+
+/// A registered opaque person
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct OpaquePerson {
+    pub registered_by: PersonRef,
+    pub display_hint: Option<String>, // human label for the registrant, never used for matching
+    pub registered_at: u64,
+}
+
+impl Storable for OpaquePerson {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode OpaquePerson"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, person): (u16, OpaquePerson) =
+            Decode!(bytes.as_ref(), (u16, OpaquePerson)).expect("Failed to decode OpaquePerson");
+        assert_eq!(version, 1, "Unsupported OpaquePerson version");
+        person
+    }
+}
+
+/// UUID-shaped candidate id for the `attempt`-th try of a registration
+fn candidate_id(registered_by: &PersonRef, now: u64, attempt: u32) -> String {
+    let mut h = Sha256::new();
+    h.update(registered_by.to_string().as_bytes());
+    h.update(b"#");
+    h.update(now.to_be_bytes());
+    h.update(attempt.to_be_bytes());
+    let digest = h.finalize();
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex::encode(&digest[0..4]),
+        hex::encode(&digest[4..6]),
+        hex::encode(&digest[6..8]),
+        hex::encode(&digest[8..10]),
+        hex::encode(&digest[10..16])
+    )
+}
+
+/// Mint and record a new opaque person id on behalf of `registered_by`
+pub fn register_opaque(
+    registered_by: PersonRef,
+    display_hint: Option<String>,
+    now: u64,
+) -> Result<String, Error> {
+    let display_hint = display_hint
+        .map(|hint| hint.trim().to_string())
+        .filter(|hint| !hint.is_empty());
+    if let Some(hint) = &display_hint {
+        if hint.chars().count() > DISPLAY_HINT_MAX_CHARS {
+            return Err(Error::InvalidArgument(format!(
+                "display_hint exceeds {} characters",
+                DISPLAY_HINT_MAX_CHARS
+            )));
+        }
+    }
+
+    STABLE_OPAQUE_PERSONS.with(|persons| {
+        let mut persons = persons.borrow_mut();
+        let id = (0u32..)
+            .map(|attempt| candidate_id(&registered_by, now, attempt))
+            .find(|id| !persons.contains_key(id))
+            .expect("candidate ids are unbounded");
+        persons.insert(
+            id.clone(),
+            OpaquePerson {
+                registered_by,
+                display_hint,
+                registered_at: now,
+            },
+        );
+        Ok(id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+
+    #[test]
+    fn test_register_opaque_mints_distinct_ids() {
+        let caller = PersonRef::Principal(Principal::anonymous());
+
+        // Same caller, same timestamp: the second id must still differ
+        let first = register_opaque(caller.clone(), Some("Grandma".to_string()), 5).unwrap();
+        let second = register_opaque(caller.clone(), None, 5).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(first.len(), 36);
+        assert_eq!(first.matches('-').count(), 4);
+
+        let too_long = "x".repeat(DISPLAY_HINT_MAX_CHARS + 1);
+        assert!(matches!(
+            register_opaque(caller, Some(too_long), 6),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+}
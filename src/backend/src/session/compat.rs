@@ -1,5 +1,5 @@
 use super::service::SessionService;
-use super::types::{ByteSink, SessionId, SessionSpec};
+use super::types::{ByteSink, Clock, SessionId, SessionSpec};
 use crate::types::{CapsuleId, Error};
 use candid::Principal;
 use std::cell::RefCell;
@@ -21,6 +21,9 @@ pub struct UploadSessionMeta {
     pub chunk_size: usize,
     pub idem: String,
     pub blob_id: Option<u64>, // Upload-specific: blob ID after commit
+    pub expected_sha256: Option<[u8; 32]>, // Optional final hash declared at begin
+    pub suggested_chunk_size: usize, // Latest throughput-based recommendation for the client
+    pub was_deduplicated: bool, // Upload-specific: commit settled onto an existing blob
 }
 
 type IdemKey = (CapsuleId, Principal, String);
@@ -63,6 +66,16 @@ impl SessionCompat {
 
     /// Create session with upload-specific metadata (old API signature)
     pub fn create(&self, sid: SessionId, meta: UploadSessionMeta) -> Result<(), Error> {
+        self.create_with_clock(sid, meta, &crate::session::adapter::ICClock)
+    }
+
+    /// Create session using an explicit clock (lets tests run without the IC time API)
+    pub fn create_with_clock(
+        &self,
+        sid: SessionId,
+        meta: UploadSessionMeta,
+        clock: &dyn Clock,
+    ) -> Result<(), Error> {
         // Build a generic spec from upload meta
         let spec = SessionSpec {
             chunk_size: meta.chunk_size,
@@ -71,8 +84,7 @@ impl SessionCompat {
             idem: meta.idem.clone(),
         };
 
-        let clock = crate::session::adapter::ICClock;
-        self.svc.borrow_mut().begin_with_id(sid, spec, &clock)?;
+        self.svc.borrow_mut().begin_with_id(sid, spec, clock)?;
 
         // Store compat meta + idem map
         self.meta.borrow_mut().insert(sid.0, meta.clone());
@@ -106,18 +118,24 @@ impl SessionCompat {
             .count()
     }
 
-    /// Verify chunks are complete
+    /// Verify chunks are complete (`IncompleteUpload` lists what's missing)
     pub fn verify_chunks_complete(&self, sid: &SessionId, chunk_count: u32) -> Result<(), Error> {
-        let rc = self.svc.borrow().received_count(*sid)?;
-        if rc == chunk_count {
-            Ok(())
-        } else {
-            Err(Error::InvalidArgument("Incomplete chunks".to_string()))
-        }
+        self.svc.borrow().ensure_complete(*sid, chunk_count)
     }
 
     /// Put chunk with ByteSink (old API signature: sid, idx, bytes)
     pub fn put_chunk(&self, sid: &SessionId, idx: u32, data: &[u8]) -> Result<(), Error> {
+        self.put_chunk_with_clock(sid, idx, data, &crate::session::adapter::ICClock)
+    }
+
+    /// Put chunk using an explicit clock (lets tests run without the IC time API)
+    pub fn put_chunk_with_clock(
+        &self,
+        sid: &SessionId,
+        idx: u32,
+        data: &[u8],
+        clock: &dyn Clock,
+    ) -> Result<(), Error> {
         let meta = self
             .meta
             .borrow()
@@ -125,10 +143,9 @@ impl SessionCompat {
             .cloned()
             .ok_or(Error::NotFound)?;
         let mut sink = (self.sink_factory)(&meta)?;
-        let clock = crate::session::adapter::ICClock;
         self.svc
             .borrow_mut()
-            .put_chunk(*sid, idx, data, &mut *sink, &clock)
+            .put_chunk(*sid, idx, data, &mut *sink, clock)
     }
 
     /// Finish session (delegates to generic service)
@@ -144,6 +161,17 @@ impl SessionCompat {
         self.svc.borrow_mut().abort(*sid)
     }
 
+    /// Abort a session regardless of owner and drop its compat entries
+    ///
+    /// Returns the removed upload metadata, or NotFound if the session is unknown.
+    pub fn force_abort(&self, sid: &SessionId) -> Result<UploadSessionMeta, Error> {
+        let meta = self.get(sid)?.ok_or(Error::NotFound)?;
+        // The generic session may already be gone (e.g. swept); the compat entry is what wedges
+        let _ = self.svc.borrow_mut().abort(*sid);
+        self.cleanup(sid);
+        Ok(meta)
+    }
+
     /// Cleanup session (remove from compat maps)
     pub fn cleanup(&self, sid: &SessionId) {
         // Remove from meta and idempotency maps
@@ -209,6 +237,16 @@ impl SessionCompat {
             .collect()
     }
 
+    /// Up to `take` upload sessions with an id of at least `start`, in id order
+    pub fn upload_sessions_from(&self, start: u64, take: usize) -> Vec<(u64, UploadSessionMeta)> {
+        self.meta
+            .borrow()
+            .range(start..)
+            .take(take)
+            .map(|(id, meta)| (*id, meta.clone()))
+            .collect()
+    }
+
     /// Total session count
     pub fn total_session_count(&self) -> usize {
         self.svc.borrow().total_sessions()
@@ -271,6 +309,9 @@ mod tests {
             chunk_size: 1024,
             idem: "test-idem".to_string(),
             blob_id: None,
+            expected_sha256: None,
+            suggested_chunk_size: 1024,
+            was_deduplicated: false,
         }
     }
 
@@ -4,6 +4,21 @@ use crate::session::types::{
 use crate::types::Error;
 use std::collections::{BTreeMap, BTreeSet};
 
+/// Fail with `IncompleteUpload` unless every chunk in `0..expected` was received
+pub fn ensure_all_received(received: &BTreeSet<u32>, expected: u32) -> Result<(), Error> {
+    let missing: Vec<u32> = (0..expected)
+        .filter(|idx| !received.contains(idx))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(Error::IncompleteUpload {
+        missing,
+        received: received.len() as u32,
+        expected,
+    })
+}
+
 /// Generic session service (no upload semantics)
 /// Manages session lifecycle and per-chunk book-keeping only
 pub struct SessionService {
@@ -83,11 +98,7 @@ impl SessionService {
         // Verify all chunks received
         let expected_chunks =
             (session.bytes_expected + session.chunk_size as u64 - 1) / session.chunk_size as u64;
-        if session.received_idxs.len() != expected_chunks as usize {
-            return Err(Error::InvalidArgument(
-                "Not all chunks received".to_string(),
-            ));
-        }
+        ensure_all_received(&session.received_idxs, expected_chunks as u32)?;
 
         // Mark as committed
         session.session_meta.status = SessionStatus::Committed {
@@ -196,10 +207,17 @@ impl SessionService {
     }
 
     /// Get received chunk count
+    #[allow(dead_code)]
     pub fn received_count(&self, sid: SessionId) -> Result<u32, Error> {
         let session = self.sessions.get(&sid.0).ok_or(Error::NotFound)?;
         Ok(session.received_idxs.len() as u32)
     }
+
+    /// Check that all `chunk_count` chunks of a session arrived
+    pub fn ensure_complete(&self, sid: SessionId, chunk_count: u32) -> Result<(), Error> {
+        let session = self.sessions.get(&sid.0).ok_or(Error::NotFound)?;
+        ensure_all_received(&session.received_idxs, chunk_count)
+    }
 }
 
 #[cfg(test)]
@@ -443,6 +461,34 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_finish_reports_missing_chunk_indices() {
+        let mut service = SessionService::new();
+        let clock = MockClock { time_ms: 1000 };
+        let spec = create_test_spec(1024, 3072);
+        let sid = service.begin(spec, &clock);
+
+        let mut sink = MockByteSink::new();
+
+        // Chunk 1 of 3 never arrives
+        service
+            .put_chunk(sid, 0, &vec![1; 1024], &mut sink, &clock)
+            .unwrap();
+        service
+            .put_chunk(sid, 2, &vec![3; 1024], &mut sink, &clock)
+            .unwrap();
+
+        assert_eq!(
+            service.finish(sid, &clock),
+            Err(Error::IncompleteUpload {
+                missing: vec![1],
+                received: 2,
+                expected: 3,
+            })
+        );
+        assert_eq!(service.ensure_complete(sid, 3), service.finish(sid, &clock));
+    }
+
     #[test]
     fn test_abort_removes_session() {
         let mut service = SessionService::new();
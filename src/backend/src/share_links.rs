@@ -0,0 +1,335 @@
+//! Share links: opaque tokens that open one memory to anyone holding them
+//!
+//! A link is minted by someone who can write the memory's capsule and carries
+//! an `AccessCondition` (by default it expires after `MAGIC_LINK_TTL_NS`). Only
+//! the sha256 of each token is stored, so the registry can't be read back into
+//! working links. Resolving a token returns a `PublicResourceView`: enough to
+//! render the memory, without the owning capsule or its access entries.
+
+use crate::capsule::domain::{is_access_active, AccessCondition};
+use crate::capsule::time::MAGIC_LINK_TTL_NS;
+use crate::capsule_acl::CapsuleAcl;
+use crate::memories::core::traits::{Env, Store};
+use crate::memories::core::update::check_not_expired;
+use crate::memories::types::AssetStorageKind;
+use crate::memory::{MEM_SHARE_LINKS, MM};
+use crate::types::{Error, MemoryType, PersonRef};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{storable::Bound, DefaultMemoryImpl, StableBTreeMap, Storable};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+thread_local! {
+    static STABLE_SHARE_LINKS: RefCell<StableBTreeMap<String, ShareLink, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_SHARE_LINKS)))
+    );
+}
+
+/// A minted share link, keyed by the sha256 of its token
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ShareLink {
+    pub capsule_id: String,
+    pub memory_id: String,
+    pub created_by: PersonRef,
+    pub created_at: u64,
+    pub condition: AccessCondition,
+}
+
+impl Storable for ShareLink {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(&(1u16, self)).expect("Failed to encode ShareLink"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (version, link): (u16, ShareLink) =
+            Decode!(bytes.as_ref(), (u16, ShareLink)).expect("Failed to decode ShareLink");
+        assert_eq!(version, 1, "Unsupported ShareLink version");
+        link
+    }
+}
+
+/// One asset of a shared memory, in display order
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PublicAssetDescriptor {
+    pub asset_id: String,
+    pub kind: AssetStorageKind,
+    pub content_type: String,
+    pub size: u64,
+}
+
+/// What a share link recipient gets to see of a memory
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PublicResourceView {
+    pub memory_id: String,
+    pub memory_type: MemoryType,
+    pub title: Option<String>,
+    pub content_type: String,
+    pub assets: Vec<PublicAssetDescriptor>, // the first one is the primary asset
+}
+
+fn token_key(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Mint a share link for `memory_id` from 32 bytes of `entropy` (write access)
+///
+/// Returns the token; it is not stored and can't be recovered later.
+pub fn create_link<E: Env, S: Store>(
+    env: &E,
+    store: &S,
+    memory_id: String,
+    condition: Option<AccessCondition>,
+    entropy: [u8; 32],
+) -> Result<String, Error> {
+    let caller = env.caller();
+    let now = env.now();
+    let condition = condition.unwrap_or(AccessCondition::ExpiresAt {
+        expires: now.saturating_add(MAGIC_LINK_TTL_NS),
+    });
+    if let AccessCondition::ExpiresAt { expires } = condition {
+        if expires <= now {
+            return Err(Error::InvalidArgument(
+                "share link would already be expired".to_string(),
+            ));
+        }
+    }
+
+    for capsule_id in store.get_accessible_capsules(&caller) {
+        if store.get_memory(&capsule_id, &memory_id).is_some() {
+            let capsule_access = store
+                .get_capsule_for_acl(&capsule_id)
+                .ok_or(Error::NotFound)?;
+            if !capsule_access.can_write(&caller) {
+                return Err(Error::Unauthorized);
+            }
+
+            let token = hex::encode(entropy);
+            STABLE_SHARE_LINKS.with(|links| {
+                links.borrow_mut().insert(
+                    token_key(&token),
+                    ShareLink {
+                        capsule_id,
+                        memory_id,
+                        created_by: caller,
+                        created_at: now,
+                        condition,
+                    },
+                )
+            });
+            return Ok(token);
+        }
+    }
+
+    Err(Error::NotFound)
+}
+
+/// Resolve `token` to a view of the memory it shares
+///
+/// Unknown tokens and tokens whose memory is gone are `NotFound`; a link (or
+/// memory) past its expiry is `Expired`, and a link not yet (or never) active
+/// is `Unauthorized`.
+pub fn resolve<S: Store>(store: &S, token: &str, now: u64) -> Result<PublicResourceView, Error> {
+    let link = STABLE_SHARE_LINKS
+        .with(|links| links.borrow().get(&token_key(token)))
+        .ok_or(Error::NotFound)?;
+
+    if !is_access_active(&link.condition, now) {
+        return Err(match link.condition {
+            AccessCondition::ExpiresAt { expires } => Error::Expired {
+                expired_at: expires,
+            },
+            _ => Error::Unauthorized,
+        });
+    }
+
+    let memory = store
+        .get_memory(&link.capsule_id, &link.memory_id)
+        .ok_or(Error::NotFound)?;
+    check_not_expired(&memory, now)?;
+
+    let assets = memory
+        .ordered_asset_ids()
+        .into_iter()
+        .filter_map(|asset_id| {
+            let (kind, metadata, size) =
+                if let Some(a) = memory.inline_assets.iter().find(|a| a.asset_id == asset_id) {
+                    (AssetStorageKind::Inline, &a.metadata, a.bytes.len() as u64)
+                } else if let Some(a) = memory
+                    .blob_internal_assets
+                    .iter()
+                    .find(|a| a.asset_id == asset_id)
+                {
+                    (AssetStorageKind::Internal, &a.metadata, a.blob_ref.len)
+                } else {
+                    let a = memory
+                        .blob_external_assets
+                        .iter()
+                        .find(|a| a.asset_id == asset_id)?;
+                    (
+                        AssetStorageKind::External,
+                        &a.metadata,
+                        a.metadata.get_base().bytes,
+                    )
+                };
+            Some(PublicAssetDescriptor {
+                asset_id,
+                kind,
+                content_type: metadata.get_base().mime_type.clone(),
+                size,
+            })
+        })
+        .collect();
+
+    Ok(PublicResourceView {
+        memory_id: memory.id,
+        memory_type: memory.metadata.memory_type,
+        title: memory.metadata.title,
+        content_type: memory.metadata.content_type,
+        assets,
+    })
+}
+
+/// Point the links of a memory that moved from `from_capsule` to `to_capsule` at its new capsule
+///
+/// Returns how many links were re-scoped.
+pub fn rescope(memory_id: &str, from_capsule: &str, to_capsule: &str) -> usize {
+    STABLE_SHARE_LINKS.with(|links| {
+        let mut links = links.borrow_mut();
+        let moved: Vec<(String, ShareLink)> = links
+            .iter()
+            .filter(|(_, link)| link.memory_id == memory_id && link.capsule_id == from_capsule)
+            .collect();
+        let count = moved.len();
+        for (key, mut link) in moved {
+            link.capsule_id = to_capsule.to_string();
+            links.insert(key, link);
+        }
+        count
+    })
+}
+
+/// Drop links whose expiry has passed; returns how many were dropped
+pub fn purge_expired(now: u64) -> usize {
+    STABLE_SHARE_LINKS.with(|links| {
+        let mut links = links.borrow_mut();
+        let expired: Vec<String> = links
+            .iter()
+            .filter(|(_, link)| {
+                matches!(link.condition, AccessCondition::ExpiresAt { expires } if expires < now)
+            })
+            .map(|(key, _)| key)
+            .collect();
+        for key in &expired {
+            links.remove(key);
+        }
+        expired.len()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mixed_asset_memory, mock_capsule, MockEnv};
+    use candid::Principal;
+
+    #[test]
+    fn test_public_resolve_share_links() {
+        use crate::capsule::domain::AccessCondition;
+        use crate::capsule::time::MAGIC_LINK_TTL_NS;
+        use crate::memories::types::AssetStorageKind;
+        use crate::share_links::{create_link, resolve};
+
+        let (env, mut store) = mock_capsule("capsule_share");
+        let capsule_id = "capsule_share".to_string();
+        store
+            .insert_memory(
+                &capsule_id,
+                mixed_asset_memory(&env, "mem_share", &capsule_id, "blob_1"),
+            )
+            .unwrap();
+
+        // Valid token: the view lists assets in display order, no capsule details
+        let token = create_link(&env, &store, "mem_share".to_string(), None, [7; 32]).unwrap();
+        let view = resolve(&store, &token, env.now).unwrap();
+        assert_eq!(view.memory_id, "mem_share");
+        assert_eq!(view.assets.len(), 3);
+        assert_eq!(view.assets[0].asset_id, "inline_1");
+        assert_eq!(view.assets[0].kind, AssetStorageKind::Inline);
+        assert_eq!(view.assets[0].size, 16);
+        assert_eq!(view.assets[2].kind, AssetStorageKind::External);
+
+        // Expired token: the default condition runs out after the magic link TTL
+        let expires = env.now + MAGIC_LINK_TTL_NS;
+        assert_eq!(
+            resolve(&store, &token, expires + 1),
+            Err(Error::Expired {
+                expired_at: expires
+            })
+        );
+        let scheduled = create_link(
+            &env,
+            &store,
+            "mem_share".to_string(),
+            Some(AccessCondition::Scheduled {
+                accessible_after: env.now + 10,
+            }),
+            [8; 32],
+        )
+        .unwrap();
+        assert_eq!(
+            resolve(&store, &scheduled, env.now),
+            Err(Error::Unauthorized)
+        );
+
+        // Callers outside the capsule can't mint links, or even see the memory
+        let stranger = MockEnv {
+            caller: PersonRef::Principal(Principal::from_slice(&[9, 9, 9])),
+            now: env.now,
+        };
+        assert_eq!(
+            create_link(&stranger, &store, "mem_share".to_string(), None, [9; 32]),
+            Err(Error::NotFound)
+        );
+
+        // Unknown token, and a link whose memory is gone
+        assert_eq!(
+            resolve(&store, "not-a-token", env.now),
+            Err(Error::NotFound)
+        );
+        store
+            .delete_memory(&capsule_id, &"mem_share".to_string())
+            .unwrap();
+        assert_eq!(resolve(&store, &token, env.now), Err(Error::NotFound));
+    }
+
+    #[test]
+    fn test_rescoped_link_follows_a_moved_memory() {
+        let (env, mut store) = mock_capsule("capsule_from");
+        let memory = mixed_asset_memory(&env, "mem_moved", "capsule_from", "blob_1");
+        store
+            .insert_memory(&"capsule_from".to_string(), memory.clone())
+            .unwrap();
+        let token = create_link(&env, &store, "mem_moved".to_string(), None, [5; 32]).unwrap();
+
+        store
+            .delete_memory(&"capsule_from".to_string(), &"mem_moved".to_string())
+            .unwrap();
+        store
+            .insert_memory(&"capsule_to".to_string(), memory)
+            .unwrap();
+        assert_eq!(resolve(&store, &token, env.now), Err(Error::NotFound));
+
+        assert_eq!(rescope("mem_moved", "capsule_from", "capsule_to"), 1);
+        assert_eq!(
+            resolve(&store, &token, env.now).unwrap().memory_id,
+            "mem_moved"
+        );
+    }
+}
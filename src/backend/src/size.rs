@@ -0,0 +1,155 @@
+//! Size estimates for capsules and what they hold
+//!
+//! One place for the byte math behind quotas and response-size guards. An
+//! estimate counts the variable-length content of a value (ids, titles,
+//! captions, inline bytes, locators) rather than its exact encoding, so it is
+//! cheap to compute and doesn't move when a fixed-size field is added. A
+//! container's estimate is the sum of its children's plus its own fields; use
+//! `capsule::util::calculate_capsule_size` when the exact stored size matters.
+
+use crate::capsule::domain::Capsule;
+use crate::folder::domain::Folder;
+use crate::gallery::domain::Gallery;
+use crate::types::Memory;
+
+/// Fixed allowance per capsule for its timestamps, flags and settings
+pub const CAPSULE_OVERHEAD_BYTES: u64 = 256;
+/// Allowance per owner, controller or connection entry of a capsule
+pub const CAPSULE_PERSON_BYTES: u64 = 64;
+
+/// A value whose stored size can be estimated
+pub trait SizeEstimable {
+    /// Estimated size in bytes, including everything the value contains
+    fn estimated_size(&self) -> u64;
+}
+
+/// Estimated size of `value` in bytes
+pub fn estimate<T: SizeEstimable>(value: &T) -> u64 {
+    value.estimated_size()
+}
+
+fn opt_len(text: &Option<String>) -> u64 {
+    text.as_ref().map_or(0, |text| text.len() as u64)
+}
+
+/// Bytes of the descriptive fields galleries and folders share
+pub fn metadata_size(id: &str, title: &Option<String>, description: &Option<String>) -> u64 {
+    id.len() as u64 + opt_len(title) + opt_len(description)
+}
+
+/// What a capsule costs beyond its memories, galleries and folders
+pub fn capsule_overhead(capsule: &Capsule) -> u64 {
+    let people = capsule.owners.len() + capsule.controllers.len() + capsule.connections.len();
+    CAPSULE_OVERHEAD_BYTES + capsule.id.len() as u64 + people as u64 * CAPSULE_PERSON_BYTES
+}
+
+impl SizeEstimable for Memory {
+    fn estimated_size(&self) -> u64 {
+        let metadata = &self.metadata;
+        let mut size = self.id.len() as u64
+            + opt_len(&metadata.title)
+            + opt_len(&metadata.description)
+            + metadata.content_type.len() as u64
+            + metadata
+                .tags
+                .iter()
+                .map(|tag| tag.len() as u64)
+                .sum::<u64>();
+
+        // Inline bytes live in the memory; blobs are referenced by locator only
+        for asset in &self.inline_assets {
+            size += asset.asset_id.len() as u64 + asset.bytes.len() as u64;
+        }
+        for asset in &self.blob_internal_assets {
+            size += asset.asset_id.len() as u64 + asset.blob_ref.locator.len() as u64;
+        }
+        for asset in &self.blob_external_assets {
+            size +=
+                asset.asset_id.len() as u64 + asset.storage_key.len() as u64 + opt_len(&asset.url);
+        }
+        size
+    }
+}
+
+impl SizeEstimable for Gallery {
+    fn estimated_size(&self) -> u64 {
+        let items: u64 = self
+            .items
+            .iter()
+            .map(|item| {
+                item.memory_id.len() as u64 + opt_len(&item.caption) + item.metadata.len() as u64
+            })
+            .sum();
+        metadata_size(&self.id, &self.metadata.title, &self.metadata.description) + items
+    }
+}
+
+impl SizeEstimable for Folder {
+    fn estimated_size(&self) -> u64 {
+        metadata_size(&self.id, &self.metadata.title, &self.metadata.description)
+    }
+}
+
+impl SizeEstimable for Capsule {
+    fn estimated_size(&self) -> u64 {
+        let memories: u64 = self.memories.values().map(estimate).sum();
+        let galleries: u64 = self.galleries.values().map(estimate).sum();
+        let folders: u64 = self.folders.values().map(estimate).sum();
+        capsule_overhead(self) + memories + galleries + folders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        create_test_capsule_at, create_test_folder, create_test_gallery, create_test_memory_at,
+    };
+    use crate::types::PersonRef;
+    use candid::Principal;
+
+    fn gallery(id: &str, memory_ids: &[&str]) -> Gallery {
+        let mut gallery = create_test_gallery(id, "capsule_1", memory_ids);
+        for item in &mut gallery.items {
+            item.caption = Some("caption".to_string());
+        }
+        gallery
+    }
+
+    fn folder(id: &str) -> Folder {
+        let mut folder = create_test_folder(id, "capsule_1");
+        folder.metadata.description = Some("Scans".to_string());
+        folder
+    }
+
+    #[test]
+    fn test_gallery_and_folder_estimates() {
+        // id + title, then memory_id + caption per item
+        assert_eq!(
+            estimate(&gallery("g_1", &["m1", "m2"])),
+            3 + 4 + 2 * (2 + 7)
+        );
+        assert_eq!(estimate(&folder("f_1")), 3 + 4 + 5);
+    }
+
+    #[test]
+    fn test_capsule_estimate_is_components_plus_overhead() {
+        let owner = PersonRef::Principal(Principal::anonymous());
+        let mut capsule = create_test_capsule_at("capsule_1", owner, 1);
+        assert_eq!(estimate(&capsule), capsule_overhead(&capsule));
+
+        let memory = create_test_memory_at("m1", "capsule_1", 1);
+        let gallery = gallery("g_1", &["m1"]);
+        let folder = folder("f_1");
+        let components = estimate(&memory) + estimate(&gallery) + estimate(&folder);
+        capsule.memories.insert("m1".to_string(), memory);
+        capsule.galleries.insert("g_1".to_string(), gallery);
+        capsule.folders.insert("f_1".to_string(), folder);
+
+        assert_eq!(estimate(&capsule), capsule_overhead(&capsule) + components);
+        assert_eq!(
+            capsule_overhead(&capsule),
+            CAPSULE_OVERHEAD_BYTES + "capsule_1".len() as u64 + CAPSULE_PERSON_BYTES
+        );
+    }
+}
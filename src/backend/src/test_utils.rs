@@ -6,16 +6,32 @@
 use crate::capsule::domain::{
     AccessCondition, AccessEntry, Capsule, GrantSource, OwnerState, ResourceRole, SharingStatus,
 };
-use crate::memories::types::{Memory, MemoryMetadata, MemoryType};
-use crate::types::PersonRef;
+use crate::capsule_acl::{CapsuleAccess, CapsuleAcl};
+use crate::folder::domain::{Folder, FolderMetadata};
+use crate::gallery::domain::{Gallery, GalleryItem, GalleryMetadata};
+use crate::memories::core::model_helpers::create_blob_memory;
+use crate::memories::core::{Env, Store};
+use crate::memories::types::{
+    AssetMetadata, AssetMetadataBase, AssetType, ImageAssetMetadata, Memory, MemoryMetadata,
+    MemoryType,
+};
+use crate::types::{
+    BlobRef, CapsuleId, ControllerState, Error, MemoryId, PersonRef, StorageEdgeBlobType,
+};
 use candid::Principal;
 use std::collections::HashMap;
 
 /// Create a test capsule with default values
 pub fn create_test_capsule() -> Capsule {
-    let now = ic_cdk::api::time();
-    let subject = PersonRef::Principal(Principal::anonymous());
+    create_test_capsule_at(
+        "test_capsule_1",
+        PersonRef::Principal(Principal::anonymous()),
+        ic_cdk::api::time(),
+    )
+}
 
+/// Create a test capsule with a fixed id, subject and timestamp (no ic_cdk calls)
+pub fn create_test_capsule_at(id: &str, subject: PersonRef, now: u64) -> Capsule {
     let mut owners = HashMap::new();
     owners.insert(
         subject.clone(),
@@ -26,7 +42,7 @@ pub fn create_test_capsule() -> Capsule {
     );
 
     Capsule {
-        id: "test_capsule_1".to_string(),
+        id: id.to_string(),
         subject: subject.clone(),
         owners,
         controllers: HashMap::new(),
@@ -41,12 +57,24 @@ pub fn create_test_capsule() -> Capsule {
         inline_bytes_used: 0,
         has_advanced_settings: true,
         hosting_preferences: crate::types::HostingPreferences::default(),
+        created_by: None,
+        role_templates: None,
+        blob_bytes_quota: None,
+        blob_bytes_used: None,
+        tags: None,
+        access_entries: None,
+        default_memory_access: None,
+        public_memory_default_allowed: None,
     }
 }
 
 /// Create a test memory with default values
 pub fn create_test_memory() -> Memory {
-    let now = ic_cdk::api::time();
+    create_test_memory_at("test_memory_1", "test_capsule_1", ic_cdk::api::time())
+}
+
+/// Create a test memory with a fixed id, capsule and timestamp (no ic_cdk calls)
+pub fn create_test_memory_at(id: &str, capsule_id: &str, now: u64) -> Memory {
     let owner = PersonRef::Principal(Principal::anonymous());
 
     // Create owner access entry
@@ -65,8 +93,8 @@ pub fn create_test_memory() -> Memory {
     };
 
     Memory {
-        id: "test_memory_1".to_string(),
-        capsule_id: "test_capsule_1".to_string(),
+        id: id.to_string(),
+        capsule_id: capsule_id.to_string(),
         metadata: MemoryMetadata {
             memory_type: MemoryType::Note,
             title: Some("Test Memory".to_string()),
@@ -93,6 +121,9 @@ pub fn create_test_memory() -> Memory {
         inline_assets: vec![],
         blob_internal_assets: vec![],
         blob_external_assets: vec![],
+        locked: None,
+        pending: None,
+        expires_at: None,
         access_entries: vec![owner_access_entry],
     }
 }
@@ -176,3 +207,348 @@ pub fn create_test_capsule_with_memories() -> (Capsule, Vec<Memory>) {
 
     (capsule, memories)
 }
+
+/// Create a test gallery holding `memory_ids` in order (no ic_cdk calls)
+pub fn create_test_gallery(id: &str, capsule_id: &str, memory_ids: &[&str]) -> Gallery {
+    Gallery {
+        id: id.to_string(),
+        capsule_id: capsule_id.to_string(),
+        metadata: GalleryMetadata {
+            title: Some("Trip".to_string()),
+            name: "trip".to_string(),
+            description: None,
+            shared_count: 0,
+            sharing_status: SharingStatus::Private,
+            total_memories: memory_ids.len() as u32,
+            storage_location: vec![],
+        },
+        items: memory_ids
+            .iter()
+            .enumerate()
+            .map(|(position, memory_id)| GalleryItem {
+                memory_id: memory_id.to_string(),
+                memory_type: MemoryType::Image,
+                position: position as u32,
+                caption: None,
+                metadata: Default::default(),
+            })
+            .collect(),
+        cover_memory_id: None,
+        access_entries: vec![],
+        created_at: 1,
+        updated_at: 1,
+        deleted_at: None,
+    }
+}
+
+/// Create an empty test folder (no ic_cdk calls)
+pub fn create_test_folder(id: &str, capsule_id: &str) -> Folder {
+    Folder {
+        id: id.to_string(),
+        capsule_id: capsule_id.to_string(),
+        metadata: FolderMetadata {
+            title: Some("Docs".to_string()),
+            name: "docs".to_string(),
+            description: None,
+            shared_count: 0,
+            sharing_status: SharingStatus::Private,
+            total_memories: 0,
+            storage_location: vec![],
+        },
+        access_entries: vec![],
+        created_at: 1,
+        updated_at: 1,
+        deleted_at: None,
+    }
+}
+
+// ============================================================================
+// MEMORY CORE MOCKS
+// ============================================================================
+
+/// `Env` with a fixed caller and clock
+pub struct MockEnv {
+    pub caller: PersonRef,
+    pub now: u64,
+}
+
+impl Env for MockEnv {
+    fn caller(&self) -> PersonRef {
+        self.caller.clone()
+    }
+
+    fn now(&self) -> u64 {
+        self.now
+    }
+}
+
+/// In-memory `Store` for memory core tests (no galleries, no blob storage)
+#[derive(Default)]
+pub struct MockStore {
+    pub memories: HashMap<(CapsuleId, MemoryId), Memory>,
+    pub capsules: HashMap<CapsuleId, CapsuleAccess>,
+    pub galleries: HashMap<(CapsuleId, String), Gallery>,
+    // (scope, caller, key) -> (result, recorded_at), live for the default TTL
+    pub idem: HashMap<(String, String, String), (String, u64)>,
+}
+
+impl MockStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_capsule(&mut self, capsule_id: CapsuleId, access: CapsuleAccess) {
+        self.capsules.insert(capsule_id, access);
+    }
+
+    pub fn add_gallery(&mut self, gallery: Gallery) {
+        self.galleries
+            .insert((gallery.capsule_id.clone(), gallery.id.clone()), gallery);
+    }
+}
+
+impl Store for MockStore {
+    fn insert_memory(
+        &mut self,
+        capsule: &CapsuleId,
+        memory: Memory,
+    ) -> std::result::Result<(), Error> {
+        self.memories
+            .insert((capsule.clone(), memory.id.clone()), memory);
+        Ok(())
+    }
+
+    fn get_memory(&self, capsule: &CapsuleId, id: &MemoryId) -> Option<Memory> {
+        self.memories.get(&(capsule.clone(), id.clone())).cloned()
+    }
+
+    fn delete_memory(
+        &mut self,
+        capsule: &CapsuleId,
+        id: &MemoryId,
+    ) -> std::result::Result<(), Error> {
+        self.memories.remove(&(capsule.clone(), id.clone()));
+        Ok(())
+    }
+
+    fn update_memory(
+        &mut self,
+        capsule: &CapsuleId,
+        id: &MemoryId,
+        memory: Memory,
+    ) -> std::result::Result<(), Error> {
+        self.memories.insert((capsule.clone(), id.clone()), memory);
+        Ok(())
+    }
+
+    fn get_all_memories(&self, capsule: &CapsuleId) -> Vec<Memory> {
+        self.memories
+            .iter()
+            .filter(|((c, _), _)| c == capsule)
+            .map(|(_, memory)| memory.clone())
+            .collect()
+    }
+
+    fn get_accessible_capsules(&self, caller: &PersonRef) -> Vec<CapsuleId> {
+        self.capsules
+            .iter()
+            .filter(|(_, access)| access.can_read(caller))
+            .map(|(capsule_id, _)| capsule_id.clone())
+            .collect()
+    }
+
+    fn get_capsule_for_acl(&self, capsule_id: &CapsuleId) -> Option<CapsuleAccess> {
+        self.capsules.get(capsule_id).cloned()
+    }
+
+    fn clear_all_memories_in_capsule(
+        &mut self,
+        capsule_id: &str,
+    ) -> std::result::Result<(), Error> {
+        self.memories
+            .retain(|(capsule, _), _| capsule != capsule_id);
+        Ok(())
+    }
+
+    fn clear_all_internal_blobs_in_capsule(
+        &mut self,
+        _capsule_id: &str,
+    ) -> std::result::Result<(), Error> {
+        // For mock implementation, we don't need to actually clear blobs
+        // since we're not managing real blob storage
+        Ok(())
+    }
+
+    fn capsule_exists(&self, capsule_id: &str) -> bool {
+        self.capsules.contains_key(capsule_id)
+    }
+
+    fn idem_lookup(&self, scope: &str, caller: &PersonRef, key: &str, now: u64) -> Option<String> {
+        let scoped = (scope.to_string(), caller.to_string(), key.to_string());
+        self.idem
+            .get(&scoped)
+            .filter(|(_, recorded_at)| {
+                now.saturating_sub(*recorded_at) <= crate::idempotency::DEFAULT_IDEM_TTL_NS
+            })
+            .map(|(result, _)| result.clone())
+    }
+
+    fn idem_record(&mut self, scope: &str, caller: &PersonRef, key: &str, result: &str, now: u64) {
+        let scoped = (scope.to_string(), caller.to_string(), key.to_string());
+        self.idem.insert(scoped, (result.to_string(), now));
+    }
+
+    fn galleries_referencing_memory(&self, capsule: &CapsuleId, id: &MemoryId) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .galleries
+            .iter()
+            .filter(|((capsule_id, _), gallery)| {
+                capsule_id == capsule
+                    && (gallery.cover_memory_id.as_ref() == Some(id)
+                        || gallery.items.iter().any(|item| &item.memory_id == id))
+            })
+            .map(|((_, gallery_id), _)| gallery_id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn blob_referenced_elsewhere(&self, blob_id: u64, capsule: &CapsuleId, id: &MemoryId) -> bool {
+        self.memories
+            .iter()
+            .any(|((capsule_id, memory_id), memory)| {
+                (capsule_id != capsule || memory_id != id)
+                    && memory
+                        .blob_internal_assets
+                        .iter()
+                        .any(|asset| crate::capsule::util::internal_blob_id(asset) == Some(blob_id))
+            })
+    }
+
+    fn detach_memory_from_galleries(
+        &mut self,
+        capsule: &CapsuleId,
+        id: &MemoryId,
+        now: u64,
+    ) -> std::result::Result<Vec<String>, Error> {
+        let affected = self.galleries_referencing_memory(capsule, id);
+        for gallery_id in &affected {
+            if let Some(gallery) = self
+                .galleries
+                .get_mut(&(capsule.clone(), gallery_id.clone()))
+            {
+                gallery.remove_memory(id);
+                gallery.updated_at = now;
+            }
+        }
+        Ok(affected)
+    }
+}
+
+/// Image asset metadata of 1024 bytes
+pub fn create_test_asset_metadata() -> AssetMetadata {
+    let base = AssetMetadataBase {
+        name: "test_image.jpg".to_string(),
+        description: Some("Test image for unit testing".to_string()),
+        tags: vec!["test".to_string(), "unit".to_string()],
+        asset_type: AssetType::Original,
+        bytes: 1024,
+        mime_type: "image/jpeg".to_string(),
+        sha256: Some([1u8; 32]),
+        width: Some(1920),
+        height: Some(1080),
+        url: None,
+        storage_key: None,
+        bucket: None,
+        asset_location: None,
+        processing_status: None,
+        processing_error: None,
+        created_at: 1234567890,
+        updated_at: 1234567890,
+        deleted_at: None,
+    };
+
+    AssetMetadata::Image(ImageAssetMetadata {
+        base,
+        color_space: Some("sRGB".to_string()),
+        exif_data: None,
+        compression_ratio: Some(0.8),
+        dpi: Some(72),
+        orientation: Some(1),
+    })
+}
+
+/// A `MockStore` holding one capsule, and an `Env` for its owner at time 1_000
+pub fn mock_capsule(capsule_id: &str) -> (MockEnv, MockStore) {
+    let caller = PersonRef::Principal(Principal::from_slice(&[1, 2, 3, 4, 5]));
+    let mut owners = HashMap::new();
+    owners.insert(
+        caller.clone(),
+        OwnerState {
+            since: 0,
+            last_activity_at: 0,
+        },
+    );
+    let mut store = MockStore::new();
+    store.add_capsule(
+        capsule_id.to_string(),
+        CapsuleAccess::new(caller.clone(), owners, HashMap::new()),
+    );
+    (MockEnv { caller, now: 1_000 }, store)
+}
+
+/// A caller who controls `capsule_id` without owning it
+pub fn controller_of(store: &mut MockStore, capsule_id: &str, now: u64) -> MockEnv {
+    let caller = PersonRef::Principal(Principal::from_slice(&[9, 9, 9]));
+    let access = store.capsules.get_mut(capsule_id).unwrap();
+    access.controllers.insert(
+        caller.clone(),
+        ControllerState {
+            granted_at: 0,
+            granted_by: access.subject.clone(),
+        },
+    );
+    MockEnv { caller, now }
+}
+
+/// Memory holding one inline, one internal (`blob_locator`) and one external asset
+/// A memory with one internal blob (at `blob_locator`), one inline and one external asset
+pub fn mixed_asset_memory(
+    env: &MockEnv,
+    memory_id: &str,
+    capsule_id: &str,
+    blob_locator: &str,
+) -> Memory {
+    let blob_ref = BlobRef {
+        locator: blob_locator.to_string(),
+        hash: None,
+        len: 1024,
+    };
+    let mut memory = create_blob_memory(
+        memory_id,
+        &capsule_id.to_string(),
+        blob_ref,
+        create_test_asset_metadata(),
+        env.now,
+        &env.caller,
+    );
+    memory.inline_assets.push(crate::types::MemoryAssetInline {
+        asset_id: "inline_1".to_string(),
+        bytes: vec![0u8; 16],
+        metadata: create_test_asset_metadata(),
+        order: None,
+    });
+    memory
+        .blob_external_assets
+        .push(crate::types::MemoryAssetBlobExternal {
+            asset_id: "external_1".to_string(),
+            location: StorageEdgeBlobType::S3,
+            storage_key: "photos/1.jpg".to_string(),
+            url: None,
+            metadata: create_test_asset_metadata(),
+            order: None,
+            signed_url: None,
+        });
+    memory.update_dashboard_fields();
+    memory
+}
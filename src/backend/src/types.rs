@@ -25,7 +25,8 @@ pub use crate::capsule::domain::{
 
 // Re-export capsule API types from capsule module
 pub use crate::capsule::api_types::{
-    CapsuleHeader, CapsuleInfo, CapsuleUpdateData, UserSettingsResponse, UserSettingsUpdateData,
+    CallerIdentity, CapsuleHeader, CapsuleInfo, CapsuleUpdateData, UserSettingsResponse,
+    UserSettingsUpdateData,
 };
 
 // Re-export folder domain types from folder module
@@ -159,6 +160,38 @@ pub enum Error {
     ResourceExhausted,       // quotas/size/cycles
     Internal(String),        // redact in prod logs
     NotImplemented(String),  // feature not yet implemented
+    CapsuleGone(String),     // target capsule deleted mid-operation (capsule id)
+    Locked(String),          // memory frozen by its owner (memory id)
+    Deleted {
+        deleted_at: u64, // the ID was recently deleted rather than never existing
+    },
+    Expired {
+        expired_at: u64, // e.g. a share link past its expiry
+    },
+    SignatureExpired {
+        expired_at: u64, // cached signed URL is stale; re-sign and set it again
+    },
+    QuotaExceeded {
+        requested_bytes: u64,
+        remaining_bytes: u64, // what the capsule can still take
+    },
+    LimitReached {
+        current: u32, // how many the container already holds
+        max: u32,
+    },
+    ChecksumMismatch {
+        computed_hex: String, // sha256 of the bytes actually stored
+        expected_hex: String, // sha256 the client declared
+    },
+    IncompleteUpload {
+        missing: Vec<u32>, // chunk indices to re-send before retrying finish
+        received: u32,
+        expected: u32,
+    },
+    ResponseTooLarge {
+        estimated_bytes: u64,
+        suggestion: String, // paged/projected endpoint to call instead
+    },
 }
 
 // Canonical Rust Result type
@@ -176,6 +209,50 @@ impl std::fmt::Display for Error {
             Error::ResourceExhausted => write!(f, "resource exhausted"),
             Error::Internal(msg) => write!(f, "internal error: {}", msg.to_lowercase()),
             Error::NotImplemented(msg) => write!(f, "not implemented: {}", msg.to_lowercase()),
+            Error::CapsuleGone(capsule_id) => write!(f, "capsule gone: {}", capsule_id),
+            Error::Locked(memory_id) => write!(f, "memory locked: {}", memory_id),
+            Error::Deleted { deleted_at } => write!(f, "deleted at {}", deleted_at),
+            Error::Expired { expired_at } => write!(f, "expired at {}", expired_at),
+            Error::SignatureExpired { expired_at } => {
+                write!(f, "signature expired at {}", expired_at)
+            }
+            Error::QuotaExceeded {
+                requested_bytes,
+                remaining_bytes,
+            } => write!(
+                f,
+                "quota exceeded: requested {} bytes, {} remaining",
+                requested_bytes, remaining_bytes
+            ),
+            Error::LimitReached { current, max } => {
+                write!(f, "limit reached: {} of {}", current, max)
+            }
+            Error::ChecksumMismatch {
+                computed_hex,
+                expected_hex,
+            } => write!(
+                f,
+                "checksum_mismatch: computed={}, expected={}",
+                computed_hex, expected_hex
+            ),
+            Error::IncompleteUpload {
+                missing,
+                received,
+                expected,
+            } => write!(
+                f,
+                "incomplete upload: {} of {} chunks received, missing {:?}",
+                received, expected, missing
+            ),
+            Error::ResponseTooLarge {
+                estimated_bytes,
+                suggestion,
+            } => write!(
+                f,
+                "response too large: ~{} bytes, {}",
+                estimated_bytes,
+                suggestion.to_lowercase()
+            ),
         }
     }
 }
@@ -478,6 +555,14 @@ mod tests {
             Error::Internal("test error".to_string()).to_string(),
             "internal error: test error"
         );
+        assert_eq!(
+            Error::ResponseTooLarge {
+                estimated_bytes: 2_000_000,
+                suggestion: "Use capsules_read_basic".to_string(),
+            }
+            .to_string(),
+            "response too large: ~2000000 bytes, use capsules_read_basic"
+        );
     }
 
     #[test]
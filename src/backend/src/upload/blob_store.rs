@@ -1,12 +1,14 @@
-use crate::memory::{MEM_BLOBS, MEM_BLOB_COUNTER, MEM_BLOB_META, MM};
+use crate::logging;
+use crate::memory::{MEM_BLOBS, MEM_BLOB_CHECKSUMS, MEM_BLOB_COUNTER, MEM_BLOB_META, MM};
 use crate::session::ByteSink;
 use crate::types::Error;
-use crate::upload::types::{BlobId, BlobMeta};
+use crate::upload::types::{BlobId, BlobMeta, CompactionReport, PartialBlobReport, PartialChunk};
 use ic_stable_structures::memory_manager::VirtualMemory;
 use ic_stable_structures::DefaultMemoryImpl;
 use ic_stable_structures::{StableBTreeMap, StableCell};
 use sha2::{Digest, Sha256};
 use std::cell::RefCell;
+use std::collections::BTreeSet;
 
 /// Deterministic hash of provisional_memory_id for stable chunk keys
 /// CRITICAL: This MUST be used everywhere chunks are written/read
@@ -26,12 +28,24 @@ pub fn pmid_session_hash32(pmid: &str, session_id: u64) -> [u8; 32] {
     h.finalize().into()
 }
 
+/// Key of the content index: blobs are deduplicated per capsule
+pub fn checksum_key(capsule_id: &str, checksum: &[u8; 32]) -> [u8; 32] {
+    let mut h = Sha256::new();
+    h.update(capsule_id.as_bytes());
+    h.update(b"#"); // Separator
+    h.update(checksum);
+    h.finalize().into()
+}
+
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 
+/// Page key: (`pmid_session_hash32` of the upload, chunk index)
+type PageKey = ([u8; 32], u32);
+
 thread_local! {
     // Key changed to ([u8; 32] SHA256 of provisional_memory_id, u32 chunk_idx) for determinism
     // Note: This is a BREAKING CHANGE - existing blob data will need migration
-    pub static STABLE_BLOB_STORE: RefCell<StableBTreeMap<([u8; 32], u32), Vec<u8>, Memory>> = RefCell::new(
+    pub static STABLE_BLOB_STORE: RefCell<StableBTreeMap<PageKey, Vec<u8>, Memory>> = RefCell::new(
         StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_BLOBS)))
     );
 
@@ -39,34 +53,62 @@ thread_local! {
         StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_BLOB_META)))
     );
 
+    // Maintained by put_meta/remove_meta for blobs charged to a capsule
+    static STABLE_BLOB_CHECKSUMS: RefCell<StableBTreeMap<[u8; 32], u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(MM.with(|m| m.borrow().get(MEM_BLOB_CHECKSUMS)))
+    );
+
     pub static STABLE_BLOB_COUNTER: RefCell<StableCell<u64, Memory>> = RefCell::new(
         StableCell::init(MM.with(|m| m.borrow().get(MEM_BLOB_COUNTER)), 0)
             .expect("Failed to init blob counter")
     );
 }
 
-/// Blob store for paged storage of large files
-#[cfg_attr(not(feature = "upload"), allow(dead_code))]
-pub struct BlobStore;
+/// Storage abstraction for paged blob data and blob metadata
+///
+/// Pages are keyed by `(pmid_hash, page_idx)` and metadata by blob id.
+/// Production code uses `StableBlobStore`; unit tests inject `InMemoryBlobStore`
+/// so commit/read logic can run off-canister, mirroring the `Env`/`Store` split
+/// in `memories::core`.
+pub trait BlobStore {
+    /// Read a single page
+    fn get_page(&self, pmid_hash: &[u8; 32], page_idx: u32) -> Option<Vec<u8>>;
+
+    /// Write a single page (overwrites silently)
+    #[allow(dead_code)]
+    fn put_page(&self, pmid_hash: [u8; 32], page_idx: u32, data: Vec<u8>);
 
-#[cfg_attr(not(feature = "upload"), allow(dead_code))]
-impl Default for BlobStore {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+    /// Remove a single page, returning the previous value
+    fn remove_page(&self, pmid_hash: &[u8; 32], page_idx: u32) -> Option<Vec<u8>>;
 
-impl BlobStore {
-    pub fn new() -> Self {
-        BlobStore
-    }
+    /// Read blob metadata
+    fn get_meta(&self, blob_id: u64) -> Option<BlobMeta>;
+
+    /// Write blob metadata (indexing its content once it is charged to a capsule)
+    fn put_meta(&self, blob_id: u64, meta: BlobMeta);
+
+    /// Remove blob metadata and its content index entry, returning the previous value
+    fn remove_meta(&self, blob_id: u64) -> Option<BlobMeta>;
+
+    /// Blob indexed under `key` (see `checksum_key`)
+    fn blob_by_checksum(&self, key: &[u8; 32]) -> Option<u64>;
+
+    /// Number of blobs with metadata
+    fn meta_count(&self) -> u64;
 
-    // Note: put_inline method removed - not currently used
+    /// Every stored page key, without loading page data (full scan - admin use only)
+    fn page_keys(&self) -> Vec<PageKey>;
+
+    /// Every stored blob metadata entry (full scan - admin use only)
+    fn metas(&self) -> Vec<(u64, BlobMeta)>;
+
+    /// Current time in nanoseconds (used for `BlobMeta.created_at`)
+    fn now(&self) -> u64;
 
     /// Store chunks from session as a blob with integrity verification
     /// NOTE: This method is being phased out in favor of write-through ByteSink design
     /// where chunks are written directly to storage during put_chunk
-    pub fn store_from_chunks(
+    fn store_from_chunks(
         &self,
         session_store: &crate::session::SessionCompat,
         session_id: &crate::upload::types::SessionId,
@@ -84,6 +126,26 @@ impl BlobStore {
         let pmid_hash =
             pmid_session_hash32(&session_meta.provisional_memory_id, session_meta.session_id);
 
+        self.store_pages(
+            session_id.0,
+            pmid_hash,
+            chunk_count,
+            expected_len,
+            expected_hash,
+        )
+    }
+
+    /// Verify the pages written under `pmid_hash` and record blob metadata
+    ///
+    /// Split out of `store_from_chunks` so it can be exercised without a session.
+    fn store_pages(
+        &self,
+        session_id: u64,
+        pmid_hash: [u8; 32],
+        chunk_count: u32,
+        expected_len: u64,
+        expected_hash: [u8; 32],
+    ) -> std::result::Result<BlobId, Error> {
         // Create blob_id from first 8 bytes of hash for metadata storage
         let blob_id = BlobId(u64::from_be_bytes([
             pmid_hash[0],
@@ -104,28 +166,30 @@ impl BlobStore {
 
         // Verify all chunks exist in blob store
         for page_idx in 0..chunk_count {
-            let page_key = (pmid_hash, page_idx);
-            let chunk_data =
-                STABLE_BLOB_STORE.with(|store| store.borrow().get(&page_key).unwrap_or_default());
-
-            ic_cdk::println!(
-                "BLOB_READ sid={} chunk_idx={} found={} len={} pmid_hash={:?}",
-                session_id.0,
-                page_idx,
-                !chunk_data.is_empty(),
-                chunk_data.len(),
-                &pmid_hash[..8]
+            let chunk_data = self.get_page(&pmid_hash, page_idx).unwrap_or_default();
+
+            logging::debug(
+                "BLOB_READ",
+                &[
+                    ("sid", &session_id),
+                    ("chunk_idx", &page_idx),
+                    ("found", &!chunk_data.is_empty()),
+                    ("len", &chunk_data.len()),
+                    ("pmid_hash", &hex::encode(&pmid_hash[..8])),
+                ],
             );
 
             if chunk_data.is_empty() {
-                ic_cdk::println!(
-                    "BLOB_READ_NOTFOUND sid={} chunk_idx={} pmid_hash={:?}",
-                    session_id.0,
-                    page_idx,
-                    &pmid_hash[..8]
+                logging::warn(
+                    "BLOB_READ_NOTFOUND",
+                    &[
+                        ("sid", &session_id),
+                        ("chunk_idx", &page_idx),
+                        ("pmid_hash", &hex::encode(&pmid_hash[..8])),
+                    ],
                 );
-                // Cleanup on failure
-                self.delete_blob(&blob_id)?;
+                // Cleanup on failure (meta may not exist yet - nothing to delete then)
+                let _ = self.delete_blob(&blob_id);
                 return Err(Error::NotFound);
             }
 
@@ -134,8 +198,8 @@ impl BlobStore {
 
         // Verify total size matches expected
         if total_written != expected_len {
-            // Cleanup on failure
-            self.delete_blob(&blob_id)?;
+            // Cleanup on failure (meta may not exist yet - nothing to delete then)
+            let _ = self.delete_blob(&blob_id);
             return Err(Error::InvalidArgument(format!(
                 "size_mismatch: expected={}, actual={}",
                 expected_len, total_written
@@ -146,105 +210,392 @@ impl BlobStore {
         let meta = BlobMeta {
             size: total_written,
             checksum: expected_hash,
-            created_at: ic_cdk::api::time(),
-            pmid_hash, // Save for later retrieval/deletion
+            created_at: self.now(),
+            pmid_hash,        // Save for later retrieval/deletion
+            capsule_id: None, // charged by the caller once the blob is attributed
         };
 
-        STABLE_BLOB_META.with(|metas| {
-            metas.borrow_mut().insert(blob_id.0, meta);
-        });
+        self.put_meta(blob_id.0, meta);
 
         Ok(blob_id)
     }
 
+    /// Report which of `chunk_count` pages under `pmid_hash` are stored, with per-page hashes
+    fn partial_report(
+        &self,
+        session_id: u64,
+        pmid_hash: &[u8; 32],
+        chunk_count: u32,
+    ) -> PartialBlobReport {
+        let mut stored_chunks = Vec::new();
+        let mut missing_chunks = Vec::new();
+        let mut stored_bytes = 0u64;
+        let mut combined = Sha256::new();
+
+        for index in 0..chunk_count {
+            match self.get_page(pmid_hash, index) {
+                Some(data) if !data.is_empty() => {
+                    combined.update(&data);
+                    stored_bytes += data.len() as u64;
+                    stored_chunks.push(PartialChunk {
+                        index,
+                        len: data.len() as u64,
+                        sha256: Sha256::digest(&data).into(),
+                    });
+                }
+                _ => missing_chunks.push(index),
+            }
+        }
+
+        let sha256 = if missing_chunks.is_empty() {
+            Some(combined.finalize().into())
+        } else {
+            None
+        };
+
+        PartialBlobReport {
+            session_id,
+            chunk_count,
+            stored_chunks,
+            missing_chunks,
+            stored_bytes,
+            sha256,
+        }
+    }
+
+    /// Hash the stored pages in order and compare with `expected_hash`
+    ///
+    /// Used when the rolling hash from `put_chunk` is unavailable (recommit).
+    fn verify_pages_hash(
+        &self,
+        pmid_hash: &[u8; 32],
+        chunk_count: u32,
+        expected_hash: [u8; 32],
+    ) -> std::result::Result<(), Error> {
+        let mut hasher = Sha256::new();
+        for page_idx in 0..chunk_count {
+            let data = self.get_page(pmid_hash, page_idx).ok_or(Error::NotFound)?;
+            hasher.update(&data);
+        }
+
+        let computed: [u8; 32] = hasher.finalize().into();
+        if computed != expected_hash {
+            return Err(Error::ChecksumMismatch {
+                computed_hex: hex::encode(computed),
+                expected_hex: hex::encode(expected_hash),
+            });
+        }
+        Ok(())
+    }
+
     /// Read entire blob (use carefully - can be large)
-    pub fn read_blob(&self, blob_id: &BlobId) -> std::result::Result<Vec<u8>, Error> {
-        let meta = STABLE_BLOB_META
-            .with(|metas| metas.borrow().get(&blob_id.0))
-            .ok_or(Error::NotFound)?;
+    fn read_blob(&self, blob_id: &BlobId) -> std::result::Result<Vec<u8>, Error> {
+        let meta = self.get_meta(blob_id.0).ok_or(Error::NotFound)?;
 
         let mut result = Vec::with_capacity(meta.size as usize);
         let mut page_idx = 0u32;
 
-        loop {
-            let page_key = (meta.pmid_hash, page_idx); // Use stored pmid_hash
-            let page_data = STABLE_BLOB_STORE.with(|store| store.borrow().get(&page_key));
-
-            match page_data {
-                Some(data) => {
-                    result.extend_from_slice(&data);
-                    page_idx += 1;
-                }
-                None => break,
-            }
+        // Use stored pmid_hash
+        while let Some(data) = self.get_page(&meta.pmid_hash, page_idx) {
+            result.extend_from_slice(&data);
+            page_idx += 1;
         }
 
         Ok(result)
     }
 
+    /// Read a single page of a blob (empty when the page doesn't exist)
+    fn read_blob_page(
+        &self,
+        blob_id: &BlobId,
+        page_idx: u32,
+    ) -> std::result::Result<Vec<u8>, Error> {
+        // Get meta to retrieve pmid_hash
+        let meta = self.get_meta(blob_id.0).ok_or(Error::NotFound)?;
+        Ok(self.get_page(&meta.pmid_hash, page_idx).unwrap_or_default())
+    }
+
     /// Get blob metadata without reading content
-    pub fn get_blob_meta(&self, blob_id: &BlobId) -> std::result::Result<Option<BlobMeta>, Error> {
-        let meta = STABLE_BLOB_META.with(|metas| metas.borrow().get(&blob_id.0));
-        Ok(meta)
+    fn get_blob_meta(&self, blob_id: &BlobId) -> std::result::Result<Option<BlobMeta>, Error> {
+        Ok(self.get_meta(blob_id.0))
     }
 
     /// Delete blob and all its pages
-    pub fn delete_blob(&self, blob_id: &BlobId) -> std::result::Result<(), Error> {
+    fn delete_blob(&self, blob_id: &BlobId) -> std::result::Result<(), Error> {
         // Get meta to retrieve pmid_hash before deleting
-        let meta = STABLE_BLOB_META
-            .with(|metas| metas.borrow_mut().remove(&blob_id.0))
-            .ok_or(Error::NotFound)?;
+        let meta = self.remove_meta(blob_id.0).ok_or(Error::NotFound)?;
 
-        // Delete all pages using pmid_hash
+        // Delete all pages using stored pmid_hash
         let mut page_idx = 0u32;
-        loop {
-            let page_key = (meta.pmid_hash, page_idx); // Use stored pmid_hash
-            let removed = STABLE_BLOB_STORE.with(|store| store.borrow_mut().remove(&page_key));
-
-            if removed.is_none() {
-                break; // No more pages
-            }
+        while self.remove_page(&meta.pmid_hash, page_idx).is_some() {
             page_idx += 1;
         }
 
         Ok(())
     }
 
-    // Note: blob_exists method removed - not currently used
-
-    // Removed unused method: head
-
     /// Get total number of blobs (for monitoring)
     #[allow(dead_code)]
-    pub fn blob_count(&self) -> u64 {
+    fn blob_count(&self) -> u64 {
+        self.meta_count()
+    }
+
+    /// Classify every stored page as live, in-flight or orphaned
+    ///
+    /// `in_flight` holds the page-key stems of pending upload sessions; their
+    /// pages have no metadata yet but must not be counted as reclaimable.
+    /// Live bytes come from blob metadata, so only orphaned pages are read.
+    fn compaction_report(&self, in_flight: &BTreeSet<[u8; 32]>) -> CompactionReport {
+        let metas = self.metas();
+        let live: BTreeSet<[u8; 32]> = metas.iter().map(|(_, meta)| meta.pmid_hash).collect();
+
+        let mut report = CompactionReport {
+            live_blobs: metas.len() as u64,
+            live_bytes: metas.iter().map(|(_, meta)| meta.size).sum(),
+            ..Default::default()
+        };
+
+        for (pmid_hash, page_idx) in self.page_keys() {
+            if live.contains(&pmid_hash) {
+                report.live_pages += 1;
+            } else if in_flight.contains(&pmid_hash) {
+                report.in_flight_pages += 1;
+            } else {
+                let len = self
+                    .get_page(&pmid_hash, page_idx)
+                    .map_or(0, |data| data.len() as u64);
+                report.orphaned_pages += 1;
+                report.reclaimable_bytes += len + PAGE_KEY_BYTES;
+            }
+        }
+
+        report.dangling_metas = metas
+            .iter()
+            .filter(|(_, meta)| meta.size > 0 && self.get_page(&meta.pmid_hash, 0).is_none())
+            .count() as u64;
+
+        report
+    }
+
+    /// Remove orphaned pages and return the accounting taken before removal
+    ///
+    /// StableBTreeMap reuses freed nodes for later inserts but never shrinks
+    /// stable memory, so "compaction" here means dropping dead entries.
+    /// Dangling metadata is reported only - it may still be referenced by memories.
+    fn compact(&self, in_flight: &BTreeSet<[u8; 32]>) -> CompactionReport {
+        let mut report = self.compaction_report(in_flight);
+        let live: BTreeSet<[u8; 32]> = self
+            .metas()
+            .into_iter()
+            .map(|(_, meta)| meta.pmid_hash)
+            .collect();
+
+        for (pmid_hash, page_idx) in self.page_keys() {
+            if live.contains(&pmid_hash) || in_flight.contains(&pmid_hash) {
+                continue;
+            }
+            if let Some(data) = self.remove_page(&pmid_hash, page_idx) {
+                report.removed_pages += 1;
+                report.removed_bytes += data.len() as u64 + PAGE_KEY_BYTES;
+            }
+        }
+
+        logging::info(
+            "BLOB_COMPACT",
+            &[
+                ("removed_pages", &report.removed_pages),
+                ("removed_bytes", &report.removed_bytes),
+            ],
+        );
+
+        report
+    }
+}
+
+/// Serialized size of a page key `([u8; 32], u32)`, counted towards reclaimable bytes
+const PAGE_KEY_BYTES: u64 = 36;
+
+/// Production blob store backed by the thread-local stable maps
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StableBlobStore;
+
+impl BlobStore for StableBlobStore {
+    fn get_page(&self, pmid_hash: &[u8; 32], page_idx: u32) -> Option<Vec<u8>> {
+        STABLE_BLOB_STORE.with(|store| store.borrow().get(&(*pmid_hash, page_idx)))
+    }
+
+    fn put_page(&self, pmid_hash: [u8; 32], page_idx: u32, data: Vec<u8>) {
+        STABLE_BLOB_STORE.with(|store| {
+            store.borrow_mut().insert((pmid_hash, page_idx), data);
+        });
+    }
+
+    fn remove_page(&self, pmid_hash: &[u8; 32], page_idx: u32) -> Option<Vec<u8>> {
+        STABLE_BLOB_STORE.with(|store| store.borrow_mut().remove(&(*pmid_hash, page_idx)))
+    }
+
+    fn get_meta(&self, blob_id: u64) -> Option<BlobMeta> {
+        STABLE_BLOB_META.with(|metas| metas.borrow().get(&blob_id))
+    }
+
+    fn put_meta(&self, blob_id: u64, meta: BlobMeta) {
+        if let Some(capsule_id) = &meta.capsule_id {
+            let key = checksum_key(capsule_id, &meta.checksum);
+            STABLE_BLOB_CHECKSUMS.with(|index| index.borrow_mut().insert(key, blob_id));
+        }
+        STABLE_BLOB_META.with(|metas| {
+            metas.borrow_mut().insert(blob_id, meta);
+        });
+    }
+
+    fn remove_meta(&self, blob_id: u64) -> Option<BlobMeta> {
+        let meta = STABLE_BLOB_META.with(|metas| metas.borrow_mut().remove(&blob_id))?;
+        if let Some(capsule_id) = &meta.capsule_id {
+            let key = checksum_key(capsule_id, &meta.checksum);
+            STABLE_BLOB_CHECKSUMS.with(|index| {
+                let mut index = index.borrow_mut();
+                if index.get(&key) == Some(blob_id) {
+                    index.remove(&key);
+                }
+            });
+        }
+        Some(meta)
+    }
+
+    fn blob_by_checksum(&self, key: &[u8; 32]) -> Option<u64> {
+        STABLE_BLOB_CHECKSUMS.with(|index| index.borrow().get(key))
+    }
+
+    fn meta_count(&self) -> u64 {
         STABLE_BLOB_META.with(|metas| metas.borrow().len())
     }
 
-    // Removed unused method: total_storage_used
+    fn page_keys(&self) -> Vec<PageKey> {
+        STABLE_BLOB_STORE.with(|store| store.borrow().keys().collect())
+    }
+
+    fn metas(&self) -> Vec<(u64, BlobMeta)> {
+        STABLE_BLOB_META.with(|metas| metas.borrow().iter().collect())
+    }
+
+    fn now(&self) -> u64 {
+        ic_cdk::api::time()
+    }
 }
 
-/// Read blob data by locator (public API function)
-/// Automatically chooses between single response and chunked reading based on size
-pub fn blob_read(locator: String) -> std::result::Result<Vec<u8>, Error> {
-    use crate::upload::types::BlobId;
+/// In-memory blob store for deterministic unit tests (no stable memory, no IC time)
+#[cfg(test)]
+#[derive(Default)]
+pub struct InMemoryBlobStore {
+    pages: RefCell<std::collections::BTreeMap<PageKey, Vec<u8>>>,
+    metas: RefCell<std::collections::BTreeMap<u64, BlobMeta>>,
+    checksums: RefCell<std::collections::BTreeMap<[u8; 32], u64>>,
+    pub now: u64,
+}
+
+#[cfg(test)]
+impl InMemoryBlobStore {
+    pub fn new(now: u64) -> Self {
+        Self {
+            now,
+            ..Default::default()
+        }
+    }
+
+    /// Number of stored pages across all blobs
+    pub fn page_count(&self) -> usize {
+        self.pages.borrow().len()
+    }
+}
+
+#[cfg(test)]
+impl BlobStore for InMemoryBlobStore {
+    fn get_page(&self, pmid_hash: &[u8; 32], page_idx: u32) -> Option<Vec<u8>> {
+        self.pages.borrow().get(&(*pmid_hash, page_idx)).cloned()
+    }
+
+    fn put_page(&self, pmid_hash: [u8; 32], page_idx: u32, data: Vec<u8>) {
+        self.pages.borrow_mut().insert((pmid_hash, page_idx), data);
+    }
+
+    fn remove_page(&self, pmid_hash: &[u8; 32], page_idx: u32) -> Option<Vec<u8>> {
+        self.pages.borrow_mut().remove(&(*pmid_hash, page_idx))
+    }
+
+    fn get_meta(&self, blob_id: u64) -> Option<BlobMeta> {
+        self.metas.borrow().get(&blob_id).cloned()
+    }
+
+    fn put_meta(&self, blob_id: u64, meta: BlobMeta) {
+        if let Some(capsule_id) = &meta.capsule_id {
+            let key = checksum_key(capsule_id, &meta.checksum);
+            self.checksums.borrow_mut().insert(key, blob_id);
+        }
+        self.metas.borrow_mut().insert(blob_id, meta);
+    }
+
+    fn remove_meta(&self, blob_id: u64) -> Option<BlobMeta> {
+        let meta = self.metas.borrow_mut().remove(&blob_id)?;
+        if let Some(capsule_id) = &meta.capsule_id {
+            let key = checksum_key(capsule_id, &meta.checksum);
+            let mut checksums = self.checksums.borrow_mut();
+            if checksums.get(&key) == Some(&blob_id) {
+                checksums.remove(&key);
+            }
+        }
+        Some(meta)
+    }
+
+    fn blob_by_checksum(&self, key: &[u8; 32]) -> Option<u64> {
+        self.checksums.borrow().get(key).copied()
+    }
+
+    fn meta_count(&self) -> u64 {
+        self.metas.borrow().len() as u64
+    }
+
+    fn page_keys(&self) -> Vec<PageKey> {
+        self.pages.borrow().keys().copied().collect()
+    }
+
+    fn metas(&self) -> Vec<(u64, BlobMeta)> {
+        self.metas
+            .borrow()
+            .iter()
+            .map(|(id, meta)| (*id, meta.clone()))
+            .collect()
+    }
+
+    fn now(&self) -> u64 {
+        self.now
+    }
+}
 
-    // Parse locator to extract blob ID
+/// Parse a `blob_{id}` locator into a BlobId
+fn parse_blob_locator(locator: &str) -> std::result::Result<BlobId, Error> {
     // Format: "blob_{blob_id}" (inline_ format removed for performance)
-    let blob_id = if locator.starts_with("blob_") {
+    if let Some(id_str) = locator.strip_prefix("blob_") {
         // For blob_ format, extract the numeric ID (fast O(1) lookup)
-        let id_str = locator.strip_prefix("blob_").unwrap_or("");
-        let blob_id_num: u64 = id_str.parse().map_err(|_| {
-            crate::types::Error::InvalidArgument("Invalid blob ID in locator".to_string())
-        })?;
-        BlobId(blob_id_num)
+        let blob_id_num: u64 = id_str
+            .parse()
+            .map_err(|_| Error::InvalidArgument("Invalid blob ID in locator".to_string()))?;
+        Ok(BlobId(blob_id_num))
     } else {
-        return Err(crate::types::Error::InvalidArgument(
+        Err(Error::InvalidArgument(
             "Unsupported locator format. Expected 'blob_{id}'".to_string(),
-        ));
-    };
+        ))
+    }
+}
+
+/// Read blob data by locator (public API function)
+/// Automatically chooses between single response and chunked reading based on size
+pub fn blob_read<B: BlobStore>(
+    blob_store: &B,
+    locator: String,
+) -> std::result::Result<Vec<u8>, Error> {
+    let blob_id = parse_blob_locator(&locator)?;
 
     // Check blob size and choose reading strategy
-    let blob_store = BlobStore::new();
     if let Ok(Some(meta)) = blob_store.get_blob_meta(&blob_id) {
         const MAX_SINGLE_RESPONSE_SIZE: u64 = 2 * 1024 * 1024; // 2MB limit
 
@@ -256,7 +607,7 @@ pub fn blob_read(locator: String) -> std::result::Result<Vec<u8>, Error> {
             })
         } else {
             // Large blob - use chunked reading
-            read_blob_chunked(&blob_store, &blob_id, meta.size)
+            read_blob_chunked(blob_store, &blob_id, meta.size)
         }
     } else {
         Err(crate::types::Error::NotFound)
@@ -265,9 +616,9 @@ pub fn blob_read(locator: String) -> std::result::Result<Vec<u8>, Error> {
 
 /// Read large blob data in chunks and combine into single response
 /// This is a fallback for blobs that are too large for single response
-fn read_blob_chunked(
-    blob_store: &BlobStore,
-    blob_id: &crate::upload::types::BlobId,
+fn read_blob_chunked<B: BlobStore>(
+    blob_store: &B,
+    blob_id: &BlobId,
     total_size: u64,
 ) -> std::result::Result<Vec<u8>, Error> {
     // Removed unused constant: CHUNK_SIZE
@@ -275,7 +626,7 @@ fn read_blob_chunked(
     let mut chunk_index = 0u32;
 
     loop {
-        let chunk_data = read_blob_chunk(blob_store, blob_id, chunk_index)?;
+        let chunk_data = blob_store.read_blob_page(blob_id, chunk_index)?;
 
         if chunk_data.is_empty() {
             // No more chunks
@@ -296,82 +647,37 @@ fn read_blob_chunked(
     Ok(result)
 }
 
-/// Read a single chunk of blob data
-fn read_blob_chunk(
-    _blob_store: &BlobStore,
-    blob_id: &crate::upload::types::BlobId,
-    chunk_index: u32,
-) -> std::result::Result<Vec<u8>, Error> {
-    // Get meta to retrieve pmid_hash
-    let meta = STABLE_BLOB_META
-        .with(|metas| metas.borrow().get(&blob_id.0))
-        .ok_or(Error::NotFound)?;
-
-    let page_key = (meta.pmid_hash, chunk_index); // Use stored pmid_hash
-    let page_data = STABLE_BLOB_STORE.with(|store| store.borrow().get(&page_key));
-
-    Ok(page_data.unwrap_or_default())
-}
-
 /// Read blob data by locator in chunks (public API for chunked reading)
 /// Returns individual chunks to avoid IC message size limits
-pub fn blob_read_chunk(locator: String, chunk_index: u32) -> std::result::Result<Vec<u8>, Error> {
-    use crate::upload::types::BlobId;
-
-    // Parse locator to extract blob ID (inline_ format removed for performance)
-    let blob_id = if locator.starts_with("blob_") {
-        // For blob_ format, extract the numeric ID (fast O(1) lookup)
-        let id_str = locator.strip_prefix("blob_").unwrap_or("");
-        let blob_id_num: u64 = id_str.parse().map_err(|_| {
-            crate::types::Error::InvalidArgument("Invalid blob ID in locator".to_string())
-        })?;
-        BlobId(blob_id_num)
-    } else {
-        return Err(crate::types::Error::InvalidArgument(
-            "Unsupported locator format. Expected 'blob_{id}'".to_string(),
-        ));
-    };
+pub fn blob_read_chunk<B: BlobStore>(
+    blob_store: &B,
+    locator: String,
+    chunk_index: u32,
+) -> std::result::Result<Vec<u8>, Error> {
+    let blob_id = parse_blob_locator(&locator)?;
 
     // Verify blob exists
-    let blob_store = BlobStore::new();
     if blob_store.get_blob_meta(&blob_id)?.is_none() {
         return Err(crate::types::Error::NotFound);
     }
 
     // Read the specific chunk
-    read_blob_chunk(&blob_store, &blob_id, chunk_index)
+    blob_store.read_blob_page(&blob_id, chunk_index)
 }
 
 /// Get blob metadata including total chunk count
-pub fn blob_get_meta(locator: String) -> std::result::Result<crate::types::BlobMeta, Error> {
-    use crate::upload::types::BlobId;
-
-    // Parse locator to extract blob ID (inline_ format removed for performance)
-    let blob_id = if locator.starts_with("blob_") {
-        // For blob_ format, extract the numeric ID (fast O(1) lookup)
-        let id_str = locator.strip_prefix("blob_").unwrap_or("");
-        let blob_id_num: u64 = id_str.parse().map_err(|_| {
-            crate::types::Error::InvalidArgument("Invalid blob ID in locator".to_string())
-        })?;
-        BlobId(blob_id_num)
-    } else {
-        return Err(crate::types::Error::InvalidArgument(
-            "Unsupported locator format. Expected 'blob_{id}'".to_string(),
-        ));
-    };
+pub fn blob_get_meta<B: BlobStore>(
+    blob_store: &B,
+    locator: String,
+) -> std::result::Result<crate::types::BlobMeta, Error> {
+    let blob_id = parse_blob_locator(&locator)?;
 
     // Get blob metadata
-    let blob_store = BlobStore::new();
     if let Ok(Some(meta)) = blob_store.get_blob_meta(&blob_id) {
         // Calculate total chunk count based on size
         // Each chunk is stored as a page, and we need to count how many pages exist
         let mut chunk_count = 0u32;
-        loop {
-            let page_key = (meta.pmid_hash, chunk_count); // Use stored pmid_hash
-            let exists = STABLE_BLOB_STORE.with(|store| store.borrow().contains_key(&page_key));
-            if !exists {
-                break;
-            }
+        while blob_store.get_page(&meta.pmid_hash, chunk_count).is_some() {
             chunk_count += 1;
         }
 
@@ -385,25 +691,16 @@ pub fn blob_get_meta(locator: String) -> std::result::Result<crate::types::BlobM
 }
 
 /// Delete blob by locator (public API function)
-pub fn blob_delete(locator: String) -> std::result::Result<(), Error> {
-    use crate::upload::types::BlobId;
-
-    // Parse locator to extract blob ID
-    let blob_id = if locator.starts_with("blob_") {
-        // For blob_ format, extract the numeric ID
-        let id_str = locator.strip_prefix("blob_").unwrap_or("");
-        let blob_id_num: u64 = id_str.parse().map_err(|_| {
-            crate::types::Error::InvalidArgument("Invalid blob ID in locator".to_string())
-        })?;
-        BlobId(blob_id_num)
-    } else {
-        return Err(crate::types::Error::InvalidArgument(
-            "Unsupported locator format. Expected 'blob_{id}'".to_string(),
-        ));
-    };
-
-    let blob_store = BlobStore::new();
-    blob_store.delete_blob(&blob_id)
+///
+/// Returns the removed metadata so the caller can release the capsule's quota charge.
+pub fn blob_delete<B: BlobStore>(
+    blob_store: &B,
+    locator: String,
+) -> std::result::Result<BlobMeta, Error> {
+    let blob_id = parse_blob_locator(&locator)?;
+    let meta = blob_store.get_meta(blob_id.0).ok_or(Error::NotFound)?;
+    blob_store.delete_blob(&blob_id)?;
+    Ok(meta)
 }
 
 #[cfg(test)]
@@ -412,8 +709,8 @@ mod tests {
     use crate::upload::types::BlobId;
 
     // Helper function to create a test blob store with some data
-    fn create_test_blob_store() -> BlobStore {
-        let blob_store = BlobStore::new();
+    fn create_test_blob_store() -> StableBlobStore {
+        let blob_store = StableBlobStore;
 
         // Create a test blob with some data
         let test_data = b"Hello, World! This is test data for blob reading.";
@@ -440,6 +737,7 @@ mod tests {
             checksum,
             created_at: 1234567890,
             pmid_hash: [0u8; 32], // Test hash
+            capsule_id: None,
         };
 
         STABLE_BLOB_META.with(|store| {
@@ -454,7 +752,7 @@ mod tests {
         let _blob_store = create_test_blob_store();
 
         // Test reading blob by ID
-        let result = blob_read("blob_0".to_string());
+        let result = blob_read(&StableBlobStore, "blob_0".to_string());
         assert!(result.is_ok());
 
         let data = result.unwrap();
@@ -464,7 +762,7 @@ mod tests {
     #[test]
     fn test_blob_read_not_found() {
         // Test reading non-existent blob
-        let result = blob_read("blob_999".to_string());
+        let result = blob_read(&StableBlobStore, "blob_999".to_string());
         assert!(result.is_err());
 
         match result.unwrap_err() {
@@ -476,7 +774,7 @@ mod tests {
     #[test]
     fn test_blob_read_invalid_locator_format() {
         // Test with invalid locator format
-        let result = blob_read("invalid_format".to_string());
+        let result = blob_read(&StableBlobStore, "invalid_format".to_string());
         assert!(result.is_err());
 
         match result.unwrap_err() {
@@ -488,7 +786,7 @@ mod tests {
     #[test]
     fn test_blob_read_invalid_blob_id() {
         // Test with invalid blob ID
-        let result = blob_read("blob_invalid".to_string());
+        let result = blob_read(&StableBlobStore, "blob_invalid".to_string());
         assert!(result.is_err());
 
         match result.unwrap_err() {
@@ -500,7 +798,7 @@ mod tests {
     #[test]
     fn test_blob_read_unsupported_format() {
         // Test that inline_ format is properly rejected
-        let result = blob_read("inline_0102030405".to_string());
+        let result = blob_read(&StableBlobStore, "inline_0102030405".to_string());
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
@@ -510,7 +808,7 @@ mod tests {
 
     #[test]
     fn test_read_blob_chunked_large_blob() {
-        let _blob_store = BlobStore::new();
+        let _blob_store = StableBlobStore;
 
         // Create a large blob that exceeds MAX_SINGLE_RESPONSE_SIZE
         let large_data = vec![0u8; 3 * 1024 * 1024]; // 3MB
@@ -537,6 +835,7 @@ mod tests {
             checksum,
             created_at: 1234567890,
             pmid_hash: [0u8; 32], // Test hash
+            capsule_id: None,
         };
 
         STABLE_BLOB_META.with(|store| {
@@ -544,7 +843,7 @@ mod tests {
         });
 
         // Test reading large blob (should use chunked reading)
-        let result = blob_read("blob_1".to_string());
+        let result = blob_read(&StableBlobStore, "blob_1".to_string());
         assert!(result.is_ok());
 
         let data = result.unwrap();
@@ -557,12 +856,244 @@ mod tests {
         let blob_id = BlobId(2);
 
         // Test reading a chunk that doesn't exist (should return empty)
-        let result = read_blob_chunk(&BlobStore::new(), &blob_id, 0);
+        let result = StableBlobStore.read_blob_page(&blob_id, 0);
         assert!(result.is_ok());
 
         let data = result.unwrap();
         assert!(data.is_empty());
     }
+
+    // ------------------------------------------------------------------------
+    // In-memory BlobStore tests (deterministic, no stable memory)
+    // ------------------------------------------------------------------------
+
+    fn write_pages(store: &InMemoryBlobStore, pmid_hash: [u8; 32], data: &[u8], page_size: usize) {
+        for (idx, page) in data.chunks(page_size).enumerate() {
+            store.put_page(pmid_hash, idx as u32, page.to_vec());
+        }
+    }
+
+    fn blob_id_for(pmid_hash: &[u8; 32]) -> BlobId {
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&pmid_hash[..8]);
+        BlobId(u64::from_be_bytes(prefix))
+    }
+
+    #[test]
+    fn test_in_memory_store_pages_records_meta() {
+        let store = InMemoryBlobStore::new(42);
+        let pmid_hash = pmid_session_hash32("mem_1", 7);
+        let data = b"0123456789abcdefghij";
+        write_pages(&store, pmid_hash, data, 8);
+
+        let blob_id = store
+            .store_pages(7, pmid_hash, 3, data.len() as u64, [9u8; 32])
+            .unwrap();
+
+        assert_eq!(blob_id, blob_id_for(&pmid_hash));
+        let meta = store.get_blob_meta(&blob_id).unwrap().unwrap();
+        assert_eq!(meta.size, data.len() as u64);
+        assert_eq!(meta.checksum, [9u8; 32]);
+        assert_eq!(meta.created_at, 42);
+        assert_eq!(store.blob_count(), 1);
+    }
+
+    #[test]
+    fn test_in_memory_store_pages_missing_chunk() {
+        let store = InMemoryBlobStore::new(0);
+        let pmid_hash = pmid_session_hash32("mem_2", 1);
+        store.put_page(pmid_hash, 0, vec![1, 2, 3]);
+
+        let result = store.store_pages(1, pmid_hash, 2, 6, [0u8; 32]);
+        assert!(matches!(result, Err(Error::NotFound)));
+        assert_eq!(store.blob_count(), 0);
+    }
+
+    #[test]
+    fn test_in_memory_store_pages_size_mismatch() {
+        let store = InMemoryBlobStore::new(0);
+        let pmid_hash = pmid_session_hash32("mem_3", 1);
+        store.put_page(pmid_hash, 0, vec![1, 2, 3]);
+
+        let result = store.store_pages(1, pmid_hash, 1, 4, [0u8; 32]);
+        match result {
+            Err(Error::InvalidArgument(msg)) => assert!(msg.contains("size_mismatch")),
+            other => panic!("Expected size_mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_partial_report_lists_stored_and_missing_pages() {
+        let store = InMemoryBlobStore::new(0);
+        let pmid_hash = pmid_session_hash32("mem_partial", 5);
+        store.put_page(pmid_hash, 0, b"abc".to_vec());
+        store.put_page(pmid_hash, 2, b"ef".to_vec());
+
+        let report = store.partial_report(5, &pmid_hash, 3);
+        assert_eq!(report.missing_chunks, vec![1]);
+        assert_eq!(report.stored_bytes, 5);
+        assert_eq!(report.stored_chunks.len(), 2);
+        assert_eq!(report.stored_chunks[1].index, 2);
+        let expected: [u8; 32] = Sha256::digest(b"ef").into();
+        assert_eq!(report.stored_chunks[1].sha256, expected);
+        assert_eq!(report.sha256, None);
+
+        store.put_page(pmid_hash, 1, b"d".to_vec());
+        let report = store.partial_report(5, &pmid_hash, 3);
+        let expected: [u8; 32] = Sha256::digest(b"abcdef").into();
+        assert_eq!(report.sha256, Some(expected));
+    }
+
+    #[test]
+    fn test_verify_pages_hash() {
+        let store = InMemoryBlobStore::new(0);
+        let pmid_hash = pmid_session_hash32("mem_verify", 1);
+        write_pages(&store, pmid_hash, b"0123456789", 4);
+
+        let good: [u8; 32] = Sha256::digest(b"0123456789").into();
+        assert!(store.verify_pages_hash(&pmid_hash, 3, good).is_ok());
+
+        match store.verify_pages_hash(&pmid_hash, 3, [0u8; 32]) {
+            Err(err @ Error::ChecksumMismatch { .. }) => {
+                assert_eq!(
+                    err,
+                    Error::ChecksumMismatch {
+                        computed_hex: hex::encode(good),
+                        expected_hex: hex::encode([0u8; 32]),
+                    }
+                );
+                // Display keeps the legacy text for log scrapers
+                assert!(err.to_string().starts_with("checksum_mismatch: computed="));
+            }
+            other => panic!("Expected checksum_mismatch, got {:?}", other),
+        }
+        assert!(matches!(
+            store.verify_pages_hash(&pmid_hash, 4, good),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_in_memory_blob_read_and_read_chunk() {
+        let store = InMemoryBlobStore::new(0);
+        let pmid_hash = pmid_session_hash32("mem_4", 3);
+        let data = b"Hello, in-memory blob store!";
+        write_pages(&store, pmid_hash, data, 10);
+        let blob_id = store
+            .store_pages(3, pmid_hash, 3, data.len() as u64, [0u8; 32])
+            .unwrap();
+        let locator = format!("blob_{}", blob_id.0);
+
+        assert_eq!(blob_read(&store, locator.clone()).unwrap(), data.to_vec());
+        assert_eq!(
+            blob_read_chunk(&store, locator.clone(), 1).unwrap(),
+            data[10..20].to_vec()
+        );
+        // Past the last page: empty chunk, not an error
+        assert!(blob_read_chunk(&store, locator.clone(), 3)
+            .unwrap()
+            .is_empty());
+
+        let meta = blob_get_meta(&store, locator).unwrap();
+        assert_eq!(meta.size, data.len() as u64);
+        assert_eq!(meta.chunk_count, 3);
+    }
+
+    #[test]
+    fn test_in_memory_blob_read_chunk_unknown_blob() {
+        let store = InMemoryBlobStore::new(0);
+        assert!(matches!(
+            blob_read_chunk(&store, "blob_123".to_string(), 0),
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(
+            blob_read_chunk(&store, "inline_123".to_string(), 0),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_in_memory_blob_delete_removes_pages() {
+        let store = InMemoryBlobStore::new(0);
+        let pmid_hash = pmid_session_hash32("mem_5", 9);
+        write_pages(&store, pmid_hash, &[7u8; 25], 10);
+        let blob_id = store.store_pages(9, pmid_hash, 3, 25, [0u8; 32]).unwrap();
+
+        blob_delete(&store, format!("blob_{}", blob_id.0)).unwrap();
+
+        assert_eq!(store.page_count(), 0);
+        assert_eq!(store.blob_count(), 0);
+        assert!(matches!(
+            blob_delete(&store, format!("blob_{}", blob_id.0)),
+            Err(Error::NotFound)
+        ));
+    }
+
+    /// One committed blob (3 pages), one pending upload (1 page), one orphan (2 pages)
+    fn store_with_mixed_pages() -> (InMemoryBlobStore, BTreeSet<[u8; 32]>) {
+        let store = InMemoryBlobStore::new(0);
+        let live = pmid_session_hash32("mem_live", 1);
+        write_pages(&store, live, &[1u8; 25], 10);
+        store.store_pages(1, live, 3, 25, [0u8; 32]).unwrap();
+
+        let pending = pmid_session_hash32("mem_pending", 2);
+        write_pages(&store, pending, &[2u8; 10], 10);
+
+        let orphan = pmid_session_hash32("mem_orphan", 3);
+        write_pages(&store, orphan, &[3u8; 15], 10);
+
+        (store, BTreeSet::from([pending]))
+    }
+
+    #[test]
+    fn test_compaction_report_classifies_pages() {
+        let (store, in_flight) = store_with_mixed_pages();
+
+        let report = store.compaction_report(&in_flight);
+
+        assert_eq!(report.live_blobs, 1);
+        assert_eq!(report.live_pages, 3);
+        assert_eq!(report.live_bytes, 25);
+        assert_eq!(report.in_flight_pages, 1);
+        assert_eq!(report.orphaned_pages, 2);
+        assert_eq!(report.reclaimable_bytes, 15 + 2 * PAGE_KEY_BYTES);
+        assert_eq!(report.dangling_metas, 0);
+        assert_eq!(report.removed_pages, 0);
+        // Reporting never mutates the store
+        assert_eq!(store.page_count(), 6);
+    }
+
+    #[test]
+    fn test_compact_removes_only_orphaned_pages() {
+        let (store, in_flight) = store_with_mixed_pages();
+
+        let report = store.compact(&in_flight);
+
+        assert_eq!(report.removed_pages, 2);
+        assert_eq!(report.removed_bytes, report.reclaimable_bytes);
+        assert_eq!(store.page_count(), 4);
+
+        let after = store.compaction_report(&in_flight);
+        assert_eq!(after.orphaned_pages, 0);
+        assert_eq!(after.live_pages, 3);
+        assert_eq!(after.in_flight_pages, 1);
+    }
+
+    #[test]
+    fn test_compaction_report_flags_dangling_meta() {
+        let store = InMemoryBlobStore::new(0);
+        let pmid_hash = pmid_session_hash32("mem_dangling", 4);
+        write_pages(&store, pmid_hash, &[4u8; 10], 10);
+        store.store_pages(4, pmid_hash, 1, 10, [0u8; 32]).unwrap();
+        store.remove_page(&pmid_hash, 0);
+
+        let report = store.compact(&BTreeSet::new());
+
+        assert_eq!(report.dangling_metas, 1);
+        assert_eq!(report.removed_pages, 0);
+        // Metadata is kept - it may still be referenced by a memory
+        assert_eq!(store.blob_count(), 1);
+    }
 }
 
 // ============================================================================
@@ -601,13 +1132,14 @@ impl StableBlobSink {
             return Err(Error::InvalidArgument("oversized chunk".into()));
         }
 
-        // Debug logging
-        ic_cdk::println!(
-            "WRITE_AT: pmid_hash={:?}, chunk_idx={}, offset={}, data_len={}",
-            &self.pmid_hash[..4], // First 4 bytes for logging
-            chunk_idx,
-            offset,
-            data.len()
+        logging::debug(
+            "WRITE_AT",
+            &[
+                ("pmid_hash", &hex::encode(&self.pmid_hash[..4])),
+                ("chunk_idx", &chunk_idx),
+                ("offset", &offset),
+                ("data_len", &data.len()),
+            ],
         );
 
         // Store chunk directly in stable storage (write-through, no buffering)
@@ -626,26 +1158,25 @@ impl StableBlobSink {
         });
         match verify {
             Some(len) if len == data.len() => {
-                ic_cdk::println!(
-                    "BLOB_VERIFY_SAMECALL idx={} wrote={} read={} ✅",
-                    chunk_idx,
-                    data.len(),
-                    len
+                logging::debug(
+                    "BLOB_VERIFY_SAMECALL",
+                    &[("idx", &chunk_idx), ("wrote", &data.len()), ("read", &len)],
                 );
             }
             Some(len) => {
-                ic_cdk::println!(
-                    "BLOB_VERIFY_SAMECALL_MISMATCH idx={} wrote={} read={} ❌",
-                    chunk_idx,
-                    data.len(),
-                    len
+                logging::error(
+                    "BLOB_VERIFY_SAMECALL_MISMATCH",
+                    &[("idx", &chunk_idx), ("wrote", &data.len()), ("read", &len)],
                 );
             }
             None => {
-                ic_cdk::println!(
-                    "BLOB_VERIFY_SAMECALL_MISS idx={} wrote={} read=None ❌❌❌",
-                    chunk_idx,
-                    data.len()
+                logging::error(
+                    "BLOB_VERIFY_SAMECALL_MISS",
+                    &[
+                        ("idx", &chunk_idx),
+                        ("wrote", &data.len()),
+                        ("read", &"none"),
+                    ],
                 );
             }
         }
@@ -1,4 +1,6 @@
+use crate::capsule_store::types::Page;
 use crate::capsule_store::{CapsuleStore, Store};
+use crate::logging;
 use crate::session::{SessionCompat, SessionId};
 use crate::types::{CapsuleId, Error, MemoryId, PersonRef};
 use crate::upload::blob_store::BlobStore;
@@ -27,18 +29,92 @@ fn with_session_compat<R>(f: impl FnOnce(&SessionCompat) -> R) -> R {
     })
 }
 
+/// Upload sessions older than this are swept (2 hours)
+pub const SESSION_EXPIRY_MS: u64 = 2 * 60 * 60 * 1000;
+
 // Note: UploadService struct removed - converted to module-level functions
-// The struct was stateless and just wrapped BlobStore::new() which does nothing
+// The struct was stateless and just wrapped a blob store unit struct which did nothing
 
 // Note: check_upload_rate_limit removed - not currently used
 
 // Note: create_inline method removed - not currently used
 
+/// Check a chunk against its session before it is stored
+///
+/// Returns the session and its page key stem. Shared by `put_chunk` and the
+/// upload replay tests, which store accepted chunks in an in-memory blob store.
+fn validate_chunk(
+    session_id: &SessionId,
+    caller: candid::Principal,
+    chunk_idx: u32,
+    len: usize,
+    byte_offset: Option<u64>,
+) -> std::result::Result<(crate::session::compat::UploadSessionMeta, [u8; 32]), Error> {
+    // Session must exist, belong to the caller and still be pending
+    let (session, pmid_hash) = pending_session_for(session_id, caller)?;
+
+    // Verify chunk index is within expected range
+    if chunk_idx >= session.chunk_count {
+        return Err(Error::InvalidArgument(format!(
+            "chunk_index {} out of range (expected < {})",
+            chunk_idx, session.chunk_count
+        )));
+    }
+
+    // A declared offset must match the chunk's slot, catching boundary bugs at this chunk
+    check_byte_offset(chunk_idx, session.chunk_size, byte_offset)?;
+
+    // Verify chunk size (except possibly last chunk)
+    if len > session.chunk_size {
+        return Err(Error::InvalidArgument(format!(
+            "chunk size {} exceeds limit of {} bytes",
+            len, session.chunk_size
+        )));
+    }
+
+    Ok((session, pmid_hash))
+}
+
+/// Validate an optional session chunk size (defaults to `CHUNK_SIZE`)
+pub fn validate_chunk_size(chunk_size: Option<u32>) -> std::result::Result<usize, Error> {
+    match chunk_size.map(|size| size as usize) {
+        None => Ok(CHUNK_SIZE),
+        Some(size) if (MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&size) => Ok(size),
+        Some(size) => Err(Error::InvalidArgument(format!(
+            "chunk_size {} outside [{}, {}]",
+            size, MIN_CHUNK_SIZE, MAX_CHUNK_SIZE
+        ))),
+    }
+}
+
+/// Validate an optional expected SHA-256 hash (must be exactly 32 bytes)
+///
+/// Same error format as `uploads_finish` so clients can handle both uniformly.
+pub fn validate_expected_sha256(
+    expected_sha256: Option<Vec<u8>>,
+) -> std::result::Result<Option<[u8; 32]>, Error> {
+    match expected_sha256 {
+        None => Ok(None),
+        Some(bytes) => {
+            let len = bytes.len();
+            let hash: [u8; 32] = bytes.try_into().map_err(|_| {
+                Error::InvalidArgument(format!(
+                    "invalid_hash_length: expected 32 bytes, got {}",
+                    len
+                ))
+            })?;
+            Ok(Some(hash))
+        }
+    }
+}
+
 pub fn begin_upload(
     store: &mut Store,
     capsule_id: CapsuleId,
     expected_chunks: u32,
     idem: String,
+    expected_sha256: Option<Vec<u8>>,
+    chunk_size: Option<u32>,
 ) -> std::result::Result<SessionId, Error> {
     // 0) validate input early
     if expected_chunks == 0 {
@@ -49,6 +125,9 @@ pub fn begin_upload(
     if expected_chunks > MAX_CHUNKS {
         return Err(Error::InvalidArgument("expected_chunks_too_large".into()));
     }
+    // fail fast on a malformed final hash - before any chunk is uploaded
+    let expected_sha256 = validate_expected_sha256(expected_sha256)?;
+    let chunk_size = validate_chunk_size(chunk_size)?;
 
     // 1) auth
     let caller = ic_cdk::api::msg_caller();
@@ -69,8 +148,7 @@ pub fn begin_upload(
     }
 
     // 3) Clean up expired sessions before checking limits
-    // Only clean up sessions that are actually expired (older than 2 hours)
-    const SESSION_EXPIRY_MS: u64 = 2 * 60 * 60 * 1000; // 2 hours
+    // Only clean up sessions that are actually expired (older than SESSION_EXPIRY_MS)
     with_session_compat(|sessions| {
         sessions.cleanup_expired_sessions_for_caller(&capsule_id, &caller, SESSION_EXPIRY_MS)
     });
@@ -82,12 +160,14 @@ pub fn begin_upload(
     let total_count = with_session_compat(|sessions| sessions.total_session_count());
 
     // Log session count for monitoring
-    ic_cdk::println!(
-        "UPLOAD_BEGIN: caller={}, capsule={}, active_sessions={}, total_sessions={}",
-        caller,
-        capsule_id,
-        active_count,
-        total_count
+    logging::info(
+        "UPLOAD_BEGIN",
+        &[
+            ("caller", &caller),
+            ("capsule", &capsule_id),
+            ("active_sessions", &active_count),
+            ("total_sessions", &total_count),
+        ],
     );
 
     if active_count >= MAX_ACTIVE_PER_CALLER {
@@ -107,9 +187,12 @@ pub fn begin_upload(
         status: SessionStatus::Pending,
         chunk_count: expected_chunks,
         provisional_memory_id: provisional_memory_id.to_string(),
-        chunk_size: crate::upload::types::CHUNK_SIZE,
+        chunk_size,
         idem: idem.clone(),
         blob_id: None, // No blob ID yet (pending)
+        expected_sha256,
+        suggested_chunk_size: chunk_size,
+        was_deduplicated: false,
     };
 
     with_session_compat(|sessions| sessions.create(session_id.clone(), upload_meta))?;
@@ -121,10 +204,10 @@ pub fn begin_upload(
 /// Upload a chunk for an active session.
 ///
 /// Semantics:
-/// - Only the session creator (caller) may upload chunks.
+/// - Only the session creator (`caller`) may upload chunks.
 /// - Session must be in `Pending` state (committed sessions reject uploads).
 /// - `chunk_idx` must be `< session.chunk_count`.
-/// - Each chunk must be ≤ `CHUNK_SIZE` bytes. The last chunk may be smaller.
+/// - Each chunk must be ≤ the session's `chunk_size` bytes. The last chunk may be smaller.
 /// - Duplicate uploads of the same chunk **overwrite silently** (idempotent retry behavior).
 ///
 /// Integrity is enforced at `commit`: all chunks must be present, and final
@@ -132,54 +215,21 @@ pub fn begin_upload(
 pub fn put_chunk(
     _store: &mut Store,
     session_id: &SessionId,
+    caller: candid::Principal,
     chunk_idx: u32,
     bytes: Vec<u8>,
+    byte_offset: Option<u64>,
 ) -> std::result::Result<(), Error> {
-    // Verify session exists and caller matches
-    let session =
-        with_session_compat(|sessions| sessions.get(session_id))?.ok_or(Error::NotFound)?;
-
-    let caller = ic_cdk::api::msg_caller();
-    if session.caller != caller {
-        return Err(Error::Unauthorized);
-    }
-
-    // Verify session is in pending state (not committed)
-    if let SessionStatus::Committed { .. } = session.status {
-        return Err(Error::InvalidArgument(
-            "session already committed".to_string(),
-        ));
-    }
-
-    // Verify chunk index is within expected range
-    if chunk_idx >= session.chunk_count {
-        return Err(Error::InvalidArgument(format!(
-            "chunk_index {} out of range (expected < {})",
-            chunk_idx, session.chunk_count
-        )));
-    }
-
-    // Verify chunk size (except possibly last chunk)
-    if bytes.len() > CHUNK_SIZE {
-        return Err(Error::InvalidArgument(format!(
-            "chunk size {} exceeds limit of {} bytes",
-            bytes.len(),
-            CHUNK_SIZE
-        )));
-    }
-
-    // Debug logging: Log the exact bytes being stored
-    let first_10_bytes = if bytes.len() >= 10 {
-        format!("{:?}", &bytes[..10])
-    } else {
-        format!("{:?}", &bytes[..])
-    };
-    ic_cdk::println!(
-        "PUT_CHUNK: session_id={}, chunk_idx={}, data_len={}, first_10_bytes={}",
-        session_id.0,
-        chunk_idx,
-        bytes.len(),
-        first_10_bytes
+    validate_chunk(session_id, caller, chunk_idx, bytes.len(), byte_offset)?;
+
+    logging::debug(
+        "PUT_CHUNK",
+        &[
+            ("sid", &session_id.0),
+            ("chunk_idx", &chunk_idx),
+            ("data_len", &bytes.len()),
+            ("prefix", &hex::encode(&bytes[..bytes.len().min(10)])),
+        ],
     );
 
     // Store chunk
@@ -187,42 +237,121 @@ pub fn put_chunk(
     Ok(())
 }
 
+/// Chunk size to suggest after a chunk of `current` bytes took `last_chunk_ms`
+///
+/// Scales toward `TARGET_CHUNK_MS` per chunk, moving at most 2x per report so a
+/// single noisy timing can't swing it, and stays within `[MIN_CHUNK_SIZE, MAX_CHUNK_SIZE]`
+/// (a fast link may go above the `CHUNK_SIZE` default).
+pub fn next_chunk_size(current: usize, last_chunk_ms: u32) -> usize {
+    let current = current as u64;
+    let scaled = current * TARGET_CHUNK_MS as u64 / last_chunk_ms.max(1) as u64;
+    let stepped = scaled.clamp(current / 2, current * 2);
+    (stepped as usize).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+}
+
+/// Record how long the caller's last chunk took and return the suggested chunk size
+///
+/// Chunk offsets are fixed by the session's `chunk_size`, so clients apply the
+/// suggestion as the `chunk_size` of their next `uploads_begin`.
+pub fn suggest_chunk_size(
+    session_id: &SessionId,
+    caller: candid::Principal,
+    last_chunk_ms: u32,
+) -> std::result::Result<u32, Error> {
+    let (mut session, _) = pending_session_for(session_id, caller)?;
+    let suggested = next_chunk_size(session.suggested_chunk_size, last_chunk_ms);
+    session.suggested_chunk_size = suggested;
+    with_session_compat(|sessions| sessions.update(*session_id, session))?;
+    Ok(suggested as u32)
+}
+
+/// Reject a declared `byte_offset` that isn't `chunk_idx * chunk_size`
+pub fn check_byte_offset(
+    chunk_idx: u32,
+    chunk_size: usize,
+    byte_offset: Option<u64>,
+) -> std::result::Result<(), Error> {
+    let expected = chunk_idx as u64 * chunk_size as u64;
+    match byte_offset {
+        Some(declared) if declared != expected => Err(Error::InvalidArgument(format!(
+            "chunk {} declares byte_offset {} (expected {})",
+            chunk_idx, declared, expected
+        ))),
+        _ => Ok(()),
+    }
+}
+
 /// Commit upload and create blob (crash-safe with idempotency)
 ///
 /// Semantics:
-/// - Only the session creator (caller) may commit the upload.
+/// - Only the session creator (`caller`) may commit the upload.
 /// - Session must be in `Pending` state (aborted sessions reject commits).
 /// - All chunks must be present before commit.
 /// - Hash and size verification ensures data integrity.
 /// - Fails if any chunk missing or hash/size mismatch; safe to retry.
-/// - Returns only blob_id (memory creation is separate concern).
-pub fn commit(
-    _store: &mut Store,
+/// - Returns only the blob (memory creation is separate concern).
+/// - Content already stored for the same capsule is deduplicated: the uploaded
+///   pages are dropped and the existing blob is returned without a new charge.
+///   Deletes only free a blob no other memory references, so sharing it is safe.
+///
+/// Blob pages/metadata go through the injected `BlobStore` (`StableBlobStore` in
+/// production, `InMemoryBlobStore` in unit tests).
+pub fn commit<B: BlobStore>(
+    store: &mut Store,
+    blob_store: &B,
     session_id: SessionId,
+    caller: candid::Principal,
     expected_sha256: [u8; 32],
     total_len: u64,
-) -> std::result::Result<String, Error> {
+    now: u64,
+) -> std::result::Result<CommitOutcome, Error> {
     let mut session =
         with_session_compat(|sessions| sessions.get(&session_id))?.ok_or(Error::NotFound)?;
 
     // Verify caller matches
-    let caller = ic_cdk::api::msg_caller();
     if session.caller != caller {
         return Err(Error::Unauthorized);
     }
 
+    // Capsule may have been deleted since begin - fail with a typed error, not a hash error
+    abandon_if_capsule_gone(store, blob_store, &session_id)?;
+
     // Handle idempotent retry (crash recovery) for committed sessions
     if let SessionStatus::Committed { .. } = session.status {
         let blob_id = session.blob_id.ok_or(Error::NotFound)?;
-        // Already committed - just return the blob_id
+        // Already committed - return the blob_id and dedup flag recorded at commit
         with_session_compat(|sessions| sessions.cleanup(&session_id));
-        return Ok(format!("blob_{}", blob_id));
+        return Ok(CommitOutcome {
+            blob_id: format!("blob_{}", blob_id),
+            was_deduplicated: session.was_deduplicated,
+        });
     }
 
     // First-time commit
+    let duplicate =
+        find_duplicate_blob(blob_store, &session.capsule_id, expected_sha256, total_len);
+
+    // The blob must fit in the capsule's remaining blob quota (a duplicate is already charged)
+    let capsule = store
+        .get(&session.capsule_id)
+        .ok_or_else(|| Error::CapsuleGone(session.capsule_id.clone()))?;
+    if duplicate.is_none() {
+        capsule.check_blob_quota(total_len)?;
+    }
+
+    // Hash declared at begin (if any) must match the one supplied at finish
+    if let Some(declared) = session.expected_sha256 {
+        if declared != expected_sha256 {
+            return Err(Error::InvalidArgument(format!(
+                "checksum_mismatch: declared_at_begin={}, got={}",
+                hex::encode(declared),
+                hex::encode(expected_sha256)
+            )));
+        }
+    }
 
     // 0. Sanity-check total_len vs chunk_count
-    let max_len = (session.chunk_count as u64) * (CHUNK_SIZE as u64);
+    let max_len = (session.chunk_count as u64) * (session.chunk_size as u64);
     if total_len == 0 || total_len > max_len {
         return Err(Error::InvalidArgument(format!(
             "total_len {} out of bounds (expected 0 < len <= {})",
@@ -234,10 +363,18 @@ pub fn commit(
     with_session_compat(|sessions| {
         sessions.verify_chunks_complete(&session_id, session.chunk_count)
     })?;
-    ic_cdk::println!("COMMIT: sid={} chunks_verified", session_id.0);
+    logging::debug("COMMIT_CHUNKS_VERIFIED", &[("sid", &session_id.0)]);
+
+    if let Some(existing) = duplicate {
+        let pmid_hash = crate::upload::blob_store::pmid_session_hash32(
+            &session.provisional_memory_id,
+            session.session_id,
+        );
+        blob_store.verify_pages_hash(&pmid_hash, session.chunk_count, expected_sha256)?;
+        return finish_as_duplicate(blob_store, session_id, session, pmid_hash, existing, now);
+    }
 
     // 2. Stream chunks to blob store with verification
-    let blob_store = BlobStore::new();
     let blob_id = with_session_compat(|sessions| {
         blob_store.store_from_chunks(
             sessions,
@@ -247,16 +384,15 @@ pub fn commit(
             expected_sha256,
         )
     })?;
-    ic_cdk::println!(
-        "COMMIT: sid={} hash_verified blob_id={}",
-        session_id.0,
-        blob_id.0
+    logging::info(
+        "COMMIT_HASH_VERIFIED",
+        &[("sid", &session_id.0), ("blob_id", &blob_id.0)],
     );
 
+    charge_blob(store, blob_store, &blob_id, &session.capsule_id)?;
+
     // 3. Mark session as committed (crash-safe checkpoint)
-    session.status = SessionStatus::Committed {
-        completed_at: ic_cdk::api::time(),
-    };
+    session.status = SessionStatus::Committed { completed_at: now };
     session.blob_id = Some(blob_id.0);
     with_session_compat(|sessions| sessions.update(session_id.clone(), session.clone()))?;
 
@@ -264,23 +400,417 @@ pub fn commit(
     with_session_compat(|sessions| sessions.cleanup(&session_id));
 
     // Return only blob ID (memory creation is separate concern)
-    Ok(format!("blob_{}", blob_id.0))
+    Ok(CommitOutcome {
+        blob_id: format!("blob_{}", blob_id.0),
+        was_deduplicated: false,
+    })
 }
 
-/// Abort upload and cleanup with authorization
-pub fn abort(_store: &mut Store, session_id: SessionId) -> std::result::Result<(), Error> {
-    // Verify caller matches (if session exists)
-    if let Some(session) = with_session_compat(|sessions| sessions.get(&session_id))? {
-        let caller = ic_cdk::api::msg_caller();
-        if session.caller != caller {
-            return Err(Error::Unauthorized);
+/// Blob already charged to `capsule_id` with the same checksum and size, if any
+///
+/// Looked up in the content index, so it costs one read however many blobs exist.
+/// Blobs committed before the index existed are not indexed and never match.
+pub fn find_duplicate_blob<B: BlobStore>(
+    blob_store: &B,
+    capsule_id: &CapsuleId,
+    checksum: [u8; 32],
+    size: u64,
+) -> Option<u64> {
+    let key = crate::upload::blob_store::checksum_key(capsule_id, &checksum);
+    let blob_id = blob_store.blob_by_checksum(&key)?;
+    blob_store
+        .get_meta(blob_id)
+        .filter(|meta| meta.size == size)
+        .map(|_| blob_id)
+}
+
+/// Settle a verified upload onto `existing` instead of storing a second copy
+///
+/// Drops the session's pages and marks it committed to the existing blob, so a
+/// retried finish returns the same locator.
+fn finish_as_duplicate<B: BlobStore>(
+    blob_store: &B,
+    session_id: SessionId,
+    mut session: crate::session::compat::UploadSessionMeta,
+    pmid_hash: [u8; 32],
+    existing: u64,
+    now: u64,
+) -> std::result::Result<CommitOutcome, Error> {
+    for page_idx in 0..session.chunk_count {
+        blob_store.remove_page(&pmid_hash, page_idx);
+    }
+    logging::info(
+        "COMMIT_DEDUPLICATED",
+        &[("sid", &session_id.0), ("blob_id", &existing)],
+    );
+
+    session.status = SessionStatus::Committed { completed_at: now };
+    session.blob_id = Some(existing);
+    session.was_deduplicated = true;
+    with_session_compat(|sessions| sessions.update(session_id, session.clone()))?;
+    with_session_compat(|sessions| sessions.cleanup(&session_id));
+
+    Ok(CommitOutcome {
+        blob_id: format!("blob_{}", existing),
+        was_deduplicated: true,
+    })
+}
+
+/// Fail with `IncompleteUpload` if a pending session is still missing chunks
+///
+/// Lets `uploads_finish` tell the client exactly which chunks to re-send before
+/// any hash is consumed, so finish can simply be retried afterwards.
+pub fn ensure_chunks_complete(session_id: &SessionId) -> std::result::Result<(), Error> {
+    let session =
+        with_session_compat(|sessions| sessions.get(session_id))?.ok_or(Error::NotFound)?;
+    if let SessionStatus::Committed { .. } = session.status {
+        return Ok(()); // idempotent retry - commit handles it
+    }
+    with_session_compat(|sessions| sessions.verify_chunks_complete(session_id, session.chunk_count))
+}
+
+/// Abandon a pending upload whose target capsule no longer exists
+///
+/// Drops the session and its stored chunks and returns `CapsuleGone`. Unknown or
+/// committed sessions are left alone (`commit` handles those). The caller is
+/// responsible for dropping any rolling hash kept for the session.
+pub fn abandon_if_capsule_gone<B: BlobStore>(
+    store: &Store,
+    blob_store: &B,
+    session_id: &SessionId,
+) -> std::result::Result<(), Error> {
+    let session = match with_session_compat(|sessions| sessions.get(session_id))? {
+        Some(session) if matches!(session.status, SessionStatus::Pending) => session,
+        _ => return Ok(()),
+    };
+    if store.exists(&session.capsule_id) {
+        return Ok(());
+    }
+
+    let pmid_hash = crate::upload::blob_store::pmid_session_hash32(
+        &session.provisional_memory_id,
+        session.session_id,
+    );
+    for page_idx in 0..session.chunk_count {
+        blob_store.remove_page(&pmid_hash, page_idx);
+    }
+    with_session_compat(|sessions| sessions.force_abort(session_id))?;
+
+    Err(Error::CapsuleGone(session.capsule_id))
+}
+
+/// Attribute a freshly stored blob to `capsule_id` and charge its size to the quota
+fn charge_blob<B: BlobStore>(
+    store: &mut Store,
+    blob_store: &B,
+    blob_id: &BlobId,
+    capsule_id: &CapsuleId,
+) -> std::result::Result<(), Error> {
+    let mut meta = blob_store.get_meta(blob_id.0).ok_or(Error::NotFound)?;
+    meta.capsule_id = Some(capsule_id.clone());
+    let size = meta.size;
+    blob_store.put_meta(blob_id.0, meta);
+
+    store.update(capsule_id, |capsule| capsule.charge_blob_bytes(size))
+}
+
+/// Chunk-level receipt for a committed blob
+///
+/// Walks the blob's stored pages in order, so the receipt reflects exactly what
+/// the canister holds rather than what the client believes it sent.
+pub fn commit_receipt<B: BlobStore>(
+    blob_store: &B,
+    blob_id: &str,
+) -> std::result::Result<CommitReceipt, Error> {
+    let id = crate::utils::blob_id::parse_blob_id(blob_id).map_err(Error::InvalidArgument)?;
+    let meta = blob_store.get_meta(id).ok_or(Error::NotFound)?;
+
+    let mut chunk_bytes = Vec::new();
+    let mut assembled_len = 0u64;
+    while assembled_len < meta.size {
+        match blob_store.get_page(&meta.pmid_hash, chunk_bytes.len() as u32) {
+            Some(page) => {
+                assembled_len += page.len() as u64;
+                chunk_bytes.push(page.len() as u64);
+            }
+            None => break,
+        }
+    }
+
+    Ok(CommitReceipt {
+        blob_id: blob_id.to_string(),
+        chunk_bytes,
+        assembled_len,
+        checksum_sha256: meta.checksum,
+    })
+}
+
+/// Give a deleted blob's bytes back to the capsule it was charged to
+///
+/// Blobs stored before quota tracking (or whose capsule is gone) release nothing.
+pub fn release_blob_charge(store: &mut Store, meta: &BlobMeta) {
+    if let Some(capsule_id) = &meta.capsule_id {
+        let _ = store.update(capsule_id, |capsule| capsule.release_blob_bytes(meta.size));
+    }
+}
+
+/// Load a pending session owned by `caller` together with its page key stem
+fn pending_session_for(
+    session_id: &SessionId,
+    caller: candid::Principal,
+) -> std::result::Result<(crate::session::compat::UploadSessionMeta, [u8; 32]), Error> {
+    let session =
+        with_session_compat(|sessions| sessions.get(session_id))?.ok_or(Error::NotFound)?;
+
+    if session.caller != caller {
+        return Err(Error::Unauthorized);
+    }
+    if let SessionStatus::Committed { .. } = session.status {
+        return Err(Error::InvalidArgument(
+            "session already committed".to_string(),
+        ));
+    }
+
+    let pmid_hash = crate::upload::blob_store::pmid_session_hash32(
+        &session.provisional_memory_id,
+        session.session_id,
+    );
+    Ok((session, pmid_hash))
+}
+
+/// Capsule a pending upload of `caller` will be charged to
+pub fn pending_session_capsule(
+    session_id: &SessionId,
+    caller: candid::Principal,
+) -> std::result::Result<CapsuleId, Error> {
+    pending_session_for(session_id, caller).map(|(session, _)| session.capsule_id)
+}
+
+/// Inspect which chunks of a pending upload are physically stored
+///
+/// Only the session creator may inspect; committed sessions have nothing partial to report.
+pub fn inspect_partial<B: BlobStore>(
+    blob_store: &B,
+    session_id: SessionId,
+    caller: candid::Principal,
+) -> std::result::Result<PartialBlobReport, Error> {
+    let (session, pmid_hash) = pending_session_for(&session_id, caller)?;
+    Ok(blob_store.partial_report(session_id.0, &pmid_hash, session.chunk_count))
+}
+
+/// Retry a failed commit against the chunks already in storage
+///
+/// Unlike `commit`, the hash is recomputed from the stored pages (the rolling hash
+/// is consumed by the failed finish), and `corrected_sha256` replaces any hash
+/// declared at begin. Missing chunks still fail with `NotFound`; the client can
+/// re-send them with `put_chunk` and recommit. Content already stored for the
+/// capsule is deduplicated as in `commit`.
+pub fn recommit<B: BlobStore>(
+    store: &mut Store,
+    blob_store: &B,
+    session_id: SessionId,
+    caller: candid::Principal,
+    corrected_sha256: [u8; 32],
+    total_len: u64,
+    now: u64,
+) -> std::result::Result<CommitOutcome, Error> {
+    let (mut session, pmid_hash) = pending_session_for(&session_id, caller)?;
+
+    let max_len = (session.chunk_count as u64) * (session.chunk_size as u64);
+    if total_len == 0 || total_len > max_len {
+        return Err(Error::InvalidArgument(format!(
+            "total_len {} out of bounds (expected 0 < len <= {})",
+            total_len, max_len
+        )));
+    }
+
+    let duplicate =
+        find_duplicate_blob(blob_store, &session.capsule_id, corrected_sha256, total_len);
+    let capsule = store
+        .get(&session.capsule_id)
+        .ok_or_else(|| Error::CapsuleGone(session.capsule_id.clone()))?;
+    if duplicate.is_none() {
+        capsule.check_blob_quota(total_len)?;
+    }
+
+    blob_store.verify_pages_hash(&pmid_hash, session.chunk_count, corrected_sha256)?;
+    if let Some(existing) = duplicate {
+        session.expected_sha256 = Some(corrected_sha256);
+        return finish_as_duplicate(blob_store, session_id, session, pmid_hash, existing, now);
+    }
+    let blob_id = blob_store.store_pages(
+        session_id.0,
+        pmid_hash,
+        session.chunk_count,
+        total_len,
+        corrected_sha256,
+    )?;
+    charge_blob(store, blob_store, &blob_id, &session.capsule_id)?;
+
+    session.expected_sha256 = Some(corrected_sha256);
+    session.status = SessionStatus::Committed { completed_at: now };
+    session.blob_id = Some(blob_id.0);
+    with_session_compat(|sessions| sessions.update(session_id, session.clone()))?;
+    with_session_compat(|sessions| sessions.cleanup(&session_id));
+
+    Ok(CommitOutcome {
+        blob_id: format!("blob_{}", blob_id.0),
+        was_deduplicated: false,
+    })
+}
+
+/// Recompute the rolling upload hash from the chunks already stored for a session
+///
+/// Rolling hashes live in heap memory and are lost on upgrade while chunks persist.
+/// Hashes the contiguous run of stored chunks from index 0 so `put_chunk` can carry
+/// on from there. A chunk stored after a gap can't be folded into a rolling hash,
+/// so that case is rejected with `Conflict` (use `recommit` once all chunks are in).
+pub fn rehydrate_hash<B: BlobStore>(
+    blob_store: &B,
+    session_id: SessionId,
+    caller: candid::Principal,
+) -> std::result::Result<Sha256, Error> {
+    let (session, pmid_hash) = pending_session_for(&session_id, caller)?;
+
+    let mut hasher = Sha256::new();
+    let mut gap_at = None;
+    for page_idx in 0..session.chunk_count {
+        match blob_store.get_page(&pmid_hash, page_idx) {
+            Some(data) if !data.is_empty() => {
+                if let Some(gap) = gap_at {
+                    return Err(Error::Conflict(format!(
+                        "chunk {} stored after missing chunk {}",
+                        page_idx, gap
+                    )));
+                }
+                hasher.update(&data);
+            }
+            _ => {
+                gap_at.get_or_insert(page_idx);
+            }
         }
     }
+    Ok(hasher)
+}
+
+/// Compare a session's rolling hash with one recomputed from its stored chunks
+///
+/// The rolling hash follows arrival order while chunks are stored by index, so
+/// chunks sent out of order make them disagree and finish would fail. `rolling`
+/// is the live accumulator, if any. The recomputation is `rehydrate_hash`'s (same
+/// caller check, same `Conflict` on a gap) and is returned as well, so the caller
+/// can install it in place of a mismatching accumulator.
+pub fn verify_rolling_hash<B: BlobStore>(
+    blob_store: &B,
+    session_id: SessionId,
+    caller: candid::Principal,
+    rolling: Option<&Sha256>,
+) -> std::result::Result<(HashVerifyReport, Sha256), Error> {
+    let stored = rehydrate_hash(blob_store, session_id, caller)?;
+    let stored_sha256: [u8; 32] = stored.clone().finalize().into();
+    let rolling_sha256: Option<[u8; 32]> = rolling.map(|hasher| hasher.clone().finalize().into());
+
+    let report = HashVerifyReport {
+        session_id: session_id.0,
+        rolling_sha256,
+        stored_sha256,
+        matches: rolling_sha256 == Some(stored_sha256),
+        repaired: false,
+    };
+    Ok((report, stored))
+}
+
+/// Abort upload and cleanup with authorization
+///
+/// Idempotent: an absent session is already the desired end state, so a retried
+/// abort (e.g. after a dropped response) succeeds too. Only another caller's live
+/// session is refused.
+pub fn abort(
+    _store: &mut Store,
+    session_id: SessionId,
+    caller: candid::Principal,
+) -> std::result::Result<(), Error> {
+    let session = match with_session_compat(|sessions| sessions.get(&session_id))? {
+        Some(session) => session,
+        None => return Ok(()),
+    };
+    if session.caller != caller {
+        return Err(Error::Unauthorized);
+    }
 
     with_session_compat(|sessions| sessions.cleanup(&session_id));
     Ok(())
 }
 
+/// Force-abort any upload session regardless of owner (admin cleanup tool)
+///
+/// `is_admin` is injected by the endpoint (normally `admin::is_admin` at the
+/// call's time) so this stays callable off-canister.
+/// Returns the aborted session's metadata for the audit log.
+pub fn admin_abort(
+    caller: &candid::Principal,
+    is_admin: impl FnOnce(&candid::Principal) -> bool,
+    session_id: &SessionId,
+) -> std::result::Result<crate::session::compat::UploadSessionMeta, Error> {
+    if !is_admin(caller) {
+        return Err(Error::Unauthorized);
+    }
+    with_session_compat(|sessions| sessions.force_abort(session_id))
+}
+
+/// Default and largest page size of `sessions_page`
+const SESSIONS_PAGE_DEFAULT: u32 = 50;
+const SESSIONS_PAGE_MAX: u32 = 100;
+
+/// Page through all upload sessions by id (admin listing)
+///
+/// Cursor is the id of the last session of the previous page (exclusive).
+/// `is_admin` is injected by the endpoint, as for `admin_abort`.
+pub fn sessions_page(
+    caller: &candid::Principal,
+    is_admin: impl FnOnce(&candid::Principal) -> bool,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> std::result::Result<Page<SessionSummary>, Error> {
+    if !is_admin(caller) {
+        return Err(Error::Unauthorized);
+    }
+    let limit = limit
+        .unwrap_or(SESSIONS_PAGE_DEFAULT)
+        .clamp(1, SESSIONS_PAGE_MAX) as usize;
+    let start = match cursor {
+        Some(text) => text
+            .parse::<u64>()
+            .map_err(|_| Error::InvalidArgument(format!("invalid cursor: {}", text)))?
+            .saturating_add(1),
+        None => 0,
+    };
+
+    // One extra session tells whether another page follows
+    let mut sessions =
+        with_session_compat(|sessions| sessions.upload_sessions_from(start, limit + 1));
+    let has_more = sessions.len() > limit;
+    sessions.truncate(limit);
+    let items: Vec<SessionSummary> = sessions
+        .into_iter()
+        .map(|(session_id, meta)| SessionSummary {
+            session_id,
+            capsule_id: meta.capsule_id,
+            caller: meta.caller,
+            status: meta.status,
+            created_at: meta.created_at,
+            chunk_count: meta.chunk_count,
+            chunk_size: meta.chunk_size as u64,
+        })
+        .collect();
+    let next_cursor = if has_more {
+        items.last().map(|item| item.session_id.to_string())
+    } else {
+        None
+    };
+    Ok(Page { items, next_cursor })
+}
+
 /// Utility function to compute SHA256 for client-side verification
 #[allow(dead_code)] // Used in tests
 pub fn compute_sha256(data: &[u8]) -> [u8; 32] {
@@ -322,6 +852,27 @@ pub fn cleanup_expired_sessions(expiry_ms: u64) {
     with_session_compat(|sessions| sessions.cleanup_expired_sessions(expiry_ms));
 }
 
+/// Page-key stems of pending upload sessions (their chunks have no blob meta yet)
+pub fn pending_blob_stems() -> std::collections::BTreeSet<[u8; 32]> {
+    use crate::upload::blob_store::pmid_session_hash32;
+
+    list_upload_sessions()
+        .into_iter()
+        .filter(|(_, meta)| matches!(meta.status, SessionStatus::Pending))
+        .map(|(_, meta)| pmid_session_hash32(&meta.provisional_memory_id, meta.session_id))
+        .collect()
+}
+
+/// Report live vs orphaned blob pages, excluding pages of pending uploads
+pub fn blobs_compaction_report<B: BlobStore>(blob_store: &B) -> CompactionReport {
+    blob_store.compaction_report(&pending_blob_stems())
+}
+
+/// Drop orphaned blob pages, leaving pending uploads untouched
+pub fn blobs_compact<B: BlobStore>(blob_store: &B) -> CompactionReport {
+    blob_store.compact(&pending_blob_stems())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -440,6 +991,672 @@ mod tests {
         // assert!(matches!(result.unwrap_err(), Error::InvalidArgument(_)));
     }
 
+    #[test]
+    fn test_begin_upload_rejects_short_expected_hash() {
+        let mut store = create_test_store();
+
+        // 31-byte hash must be rejected before auth/session creation
+        let result = begin_upload(
+            &mut store,
+            create_test_capsule_id(),
+            3,
+            "test-idem".to_string(),
+            Some(vec![0u8; 31]),
+            None,
+        );
+
+        match result {
+            Err(Error::InvalidArgument(msg)) => {
+                assert_eq!(msg, "invalid_hash_length: expected 32 bytes, got 31")
+            }
+            other => panic!("Expected invalid_hash_length, got {:?}", other),
+        }
+        assert_eq!(total_session_count(), 0);
+    }
+
+    #[test]
+    fn test_validate_expected_sha256() {
+        assert_eq!(validate_expected_sha256(None).unwrap(), None);
+        assert_eq!(
+            validate_expected_sha256(Some(vec![7u8; 32])).unwrap(),
+            Some([7u8; 32])
+        );
+        assert!(matches!(
+            validate_expected_sha256(Some(vec![0u8; 33])),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            validate_expected_sha256(Some(Vec::new())),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    // ============================================================================
+    // ADMIN ABORT TESTS
+    // ============================================================================
+
+    struct FixedClock;
+
+    impl crate::session::types::Clock for FixedClock {
+        fn now_ms(&self) -> u64 {
+            mock_time()
+        }
+    }
+
+    fn create_pending_session(sid: u64, owner: Principal) {
+        let meta = crate::session::compat::UploadSessionMeta {
+            session_id: sid,
+            capsule_id: create_test_capsule_id(),
+            caller: owner,
+            created_at: mock_time(),
+            expected_chunks: 2,
+            status: SessionStatus::Pending,
+            chunk_count: 2,
+            provisional_memory_id: "test-memory-123".to_string(),
+            chunk_size: 1024,
+            idem: format!("idem-{sid}"),
+            blob_id: None,
+            expected_sha256: None,
+            suggested_chunk_size: CHUNK_SIZE,
+            was_deduplicated: false,
+        };
+        with_session_compat(|sessions| {
+            sessions.create_with_clock(SessionId(sid), meta, &FixedClock)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_abort_is_idempotent() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = create_test_store();
+        create_pending_session(921, owner);
+
+        // Someone else can't abort a live session
+        let stranger = Principal::from_slice(&[8; 29]);
+        assert_eq!(
+            abort(&mut store, SessionId(921), stranger),
+            Err(Error::Unauthorized)
+        );
+
+        assert_eq!(abort(&mut store, SessionId(921), owner), Ok(()));
+        assert_eq!(abort(&mut store, SessionId(921), owner), Ok(()));
+        assert!(
+            with_session_compat(|sessions| sessions.get(&SessionId(921)))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    /// The endpoint's admin check, with the first principal asked bootstrapped
+    fn admin_check(principal: &Principal) -> bool {
+        crate::admin::is_admin(principal, mock_time())
+    }
+
+    #[test]
+    fn test_admin_abort_rejects_non_admin() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let admin = Principal::from_slice(&[8; 29]);
+        assert!(admin_check(&admin));
+        create_pending_session(900, owner);
+
+        // The session's own owner is no admin either
+        let result = admin_abort(&owner, admin_check, &SessionId(900));
+        assert!(matches!(result, Err(Error::Unauthorized)));
+        assert_eq!(
+            total_session_count(),
+            1,
+            "session must survive a rejected abort"
+        );
+    }
+
+    #[test]
+    fn test_admin_abort_removes_other_users_session() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let admin = Principal::from_slice(&[8; 29]);
+        assert!(admin_check(&admin));
+        create_pending_session(901, owner);
+
+        let aborted = admin_abort(&admin, admin_check, &SessionId(901)).unwrap();
+        assert_eq!(aborted.caller, owner);
+        assert_eq!(total_session_count(), 0);
+        assert!(
+            with_session_compat(|sessions| sessions.get(&SessionId(901)))
+                .unwrap()
+                .is_none()
+        );
+
+        // Second abort finds nothing left
+        assert!(matches!(
+            admin_abort(&admin, admin_check, &SessionId(901)),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_sessions_page_lists_each_session_once() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let admin = Principal::from_slice(&[8; 29]);
+        assert!(admin_check(&admin));
+        for sid in 930..935 {
+            create_pending_session(sid, owner);
+        }
+
+        assert!(matches!(
+            sessions_page(&owner, admin_check, None, None),
+            Err(Error::Unauthorized)
+        ));
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = sessions_page(&admin, admin_check, cursor, Some(2)).unwrap();
+            assert!(page.items.len() <= 2);
+            seen.extend(page.items.iter().map(|item| item.session_id));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec![930, 931, 932, 933, 934]);
+    }
+
+    // ============================================================================
+    // PARTIAL UPLOAD RECOVERY TESTS
+    // ============================================================================
+
+    fn stored_session_pages(
+        blob_store: &crate::upload::blob_store::InMemoryBlobStore,
+        sid: u64,
+        pages: &[(u32, &[u8])],
+    ) {
+        let pmid_hash = crate::upload::blob_store::pmid_session_hash32("test-memory-123", sid);
+        for (idx, data) in pages {
+            blob_store.put_page(pmid_hash, *idx, data.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_inspect_partial_reports_missing_chunks() {
+        let owner = Principal::from_slice(&[7; 29]);
+        create_pending_session(906, owner);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        stored_session_pages(&blob_store, 906, &[(0, b"hello ")]);
+
+        let report = inspect_partial(&blob_store, SessionId(906), owner).unwrap();
+        assert_eq!(report.chunk_count, 2);
+        assert_eq!(report.missing_chunks, vec![1]);
+        assert_eq!(report.stored_bytes, 6);
+
+        let stranger = Principal::from_slice(&[8; 29]);
+        assert!(matches!(
+            inspect_partial(&blob_store, SessionId(906), stranger),
+            Err(Error::Unauthorized)
+        ));
+    }
+
+    fn store_with_test_capsule(owner: Principal, quota: Option<u64>) -> Store {
+        let mut store = create_test_store();
+        let mut capsule = crate::test_utils::create_test_capsule_at(
+            &create_test_capsule_id(),
+            PersonRef::Principal(owner),
+            mock_time(),
+        );
+        capsule.blob_bytes_quota = quota;
+        store.upsert(create_test_capsule_id(), capsule);
+        store
+    }
+
+    #[test]
+    fn test_recommit_with_corrected_hash() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = store_with_test_capsule(owner, None);
+        create_pending_session(907, owner);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        stored_session_pages(&blob_store, 907, &[(0, b"hello "), (1, b"world")]);
+
+        // Wrong hash still fails and leaves the session pending
+        let result = recommit(
+            &mut store,
+            &blob_store,
+            SessionId(907),
+            owner,
+            [0u8; 32],
+            11,
+            mock_time(),
+        );
+        assert!(matches!(result, Err(Error::ChecksumMismatch { .. })));
+        assert!(inspect_partial(&blob_store, SessionId(907), owner).is_ok());
+
+        let corrected = compute_sha256(b"hello world");
+        let blob = recommit(
+            &mut store,
+            &blob_store,
+            SessionId(907),
+            owner,
+            corrected,
+            11,
+            mock_time(),
+        )
+        .unwrap()
+        .blob_id;
+        assert!(blob.starts_with("blob_"));
+        assert_eq!(blob_store.blob_count(), 1);
+        assert!(
+            with_session_compat(|sessions| sessions.get(&SessionId(907)))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_commit_bounds_total_len_by_session_chunk_size() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = store_with_test_capsule(owner, None);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        // Two chunks of the session's 1024 bytes, not of the `CHUNK_SIZE` default
+        create_pending_session(964, owner);
+
+        for commit_fn in [
+            commit::<crate::upload::blob_store::InMemoryBlobStore>,
+            recommit,
+        ] {
+            let result = commit_fn(
+                &mut store,
+                &blob_store,
+                SessionId(964),
+                owner,
+                [0u8; 32],
+                2049,
+                mock_time(),
+            );
+            assert_eq!(
+                result,
+                Err(Error::InvalidArgument(
+                    "total_len 2049 out of bounds (expected 0 < len <= 2048)".to_string()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_commit_receipt_reports_stored_chunks() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = store_with_test_capsule(owner, None);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        let hash = compute_sha256(b"hello world");
+
+        create_pending_session(919, owner);
+        stored_session_pages(&blob_store, 919, &[(0, b"hello "), (1, b"world")]);
+        let blob = recommit(
+            &mut store,
+            &blob_store,
+            SessionId(919),
+            owner,
+            hash,
+            11,
+            mock_time(),
+        )
+        .unwrap()
+        .blob_id;
+
+        let receipt = commit_receipt(&blob_store, &blob).unwrap();
+        assert_eq!(receipt.blob_id, blob);
+        assert_eq!(receipt.chunk_bytes, vec![6, 5]);
+        assert_eq!(receipt.assembled_len, 11);
+        assert_eq!(receipt.checksum_sha256, hash);
+
+        assert_eq!(commit_receipt(&blob_store, "blob_1"), Err(Error::NotFound));
+    }
+
+    #[test]
+    fn test_blob_quota_blocks_commit_until_space_freed() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = store_with_test_capsule(owner, Some(20));
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        let hash = compute_sha256(b"hello world");
+
+        create_pending_session(915, owner);
+        stored_session_pages(&blob_store, 915, &[(0, b"hello "), (1, b"world")]);
+        let first = recommit(
+            &mut store,
+            &blob_store,
+            SessionId(915),
+            owner,
+            hash,
+            11,
+            mock_time(),
+        )
+        .unwrap()
+        .blob_id;
+        let capsule = store.get(&create_test_capsule_id()).unwrap();
+        assert_eq!(capsule.blob_bytes_used(), 11);
+
+        // Different content of the same size, so it isn't deduplicated
+        let other = compute_sha256(b"hello there");
+        create_pending_session(916, owner);
+        stored_session_pages(&blob_store, 916, &[(0, b"hello "), (1, b"there")]);
+        assert_eq!(
+            recommit(
+                &mut store,
+                &blob_store,
+                SessionId(916),
+                owner,
+                other,
+                11,
+                mock_time()
+            ),
+            Err(Error::QuotaExceeded {
+                requested_bytes: 11,
+                remaining_bytes: 9,
+            })
+        );
+
+        // Deleting the first blob frees its bytes for the retry
+        let meta = crate::upload::blob_store::blob_delete(&blob_store, first).unwrap();
+        release_blob_charge(&mut store, &meta);
+        assert!(recommit(
+            &mut store,
+            &blob_store,
+            SessionId(916),
+            owner,
+            other,
+            11,
+            mock_time()
+        )
+        .is_ok());
+        let capsule = store.get(&create_test_capsule_id()).unwrap();
+        assert_eq!(capsule.blob_bytes_used(), 11);
+    }
+
+    /// Record every chunk of a pending session as received, as `uploads_put_chunk` does
+    fn receive_session_chunks(sid: u64, chunks: &[&[u8]]) {
+        for (idx, data) in chunks.iter().enumerate() {
+            with_session_compat(|sessions| {
+                sessions.put_chunk_with_clock(&SessionId(sid), idx as u32, data, &FixedClock)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_commit_over_quota_succeeds_after_delete() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = store_with_test_capsule(owner, Some(20));
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+
+        create_pending_session(960, owner);
+        stored_session_pages(&blob_store, 960, &[(0, b"hello "), (1, b"world")]);
+        receive_session_chunks(960, &[b"hello ", b"world"]);
+        let first = commit(
+            &mut store,
+            &blob_store,
+            SessionId(960),
+            owner,
+            compute_sha256(b"hello world"),
+            11,
+            mock_time(),
+        )
+        .unwrap();
+
+        let other = compute_sha256(b"hello there");
+        create_pending_session(961, owner);
+        stored_session_pages(&blob_store, 961, &[(0, b"hello "), (1, b"there")]);
+        receive_session_chunks(961, &[b"hello ", b"there"]);
+        assert_eq!(
+            commit(
+                &mut store,
+                &blob_store,
+                SessionId(961),
+                owner,
+                other,
+                11,
+                mock_time()
+            ),
+            Err(Error::QuotaExceeded {
+                requested_bytes: 11,
+                remaining_bytes: 9,
+            })
+        );
+
+        // The rejected session stays pending, so the same commit goes through once
+        // the first blob is deleted and its charge released
+        let meta = crate::upload::blob_store::blob_delete(&blob_store, first.blob_id).unwrap();
+        release_blob_charge(&mut store, &meta);
+        let second = commit(
+            &mut store,
+            &blob_store,
+            SessionId(961),
+            owner,
+            other,
+            11,
+            mock_time(),
+        )
+        .unwrap();
+        assert!(!second.was_deduplicated);
+        let capsule = store.get(&create_test_capsule_id()).unwrap();
+        assert_eq!(capsule.blob_bytes_used(), 11);
+    }
+
+    #[test]
+    fn test_identical_upload_reports_dedup() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = store_with_test_capsule(owner, None);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        let hash = compute_sha256(b"hello world");
+
+        create_pending_session(922, owner);
+        stored_session_pages(&blob_store, 922, &[(0, b"hello "), (1, b"world")]);
+        let first = recommit(
+            &mut store,
+            &blob_store,
+            SessionId(922),
+            owner,
+            hash,
+            11,
+            mock_time(),
+        )
+        .unwrap();
+        assert!(!first.was_deduplicated);
+
+        create_pending_session(923, owner);
+        stored_session_pages(&blob_store, 923, &[(0, b"hello "), (1, b"world")]);
+        let second = recommit(
+            &mut store,
+            &blob_store,
+            SessionId(923),
+            owner,
+            hash,
+            11,
+            mock_time(),
+        )
+        .unwrap();
+        assert!(second.was_deduplicated);
+        assert_eq!(second.blob_id, first.blob_id);
+
+        // One stored copy, charged once, and the second upload's pages are dropped
+        assert_eq!(blob_store.blob_count(), 1);
+        let capsule = store.get(&create_test_capsule_id()).unwrap();
+        assert_eq!(capsule.blob_bytes_used(), 11);
+        let pmid_hash = crate::upload::blob_store::pmid_session_hash32("test-memory-123", 923);
+        assert!(blob_store.get_page(&pmid_hash, 0).is_none());
+    }
+
+    #[test]
+    fn test_retried_commit_reports_recorded_dedup() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = store_with_test_capsule(owner, None);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+
+        // Crash after the committed checkpoint of a deduplicated upload, before cleanup
+        create_pending_session(980, owner);
+        let mut meta = with_session_compat(|sessions| sessions.get(&SessionId(980)))
+            .unwrap()
+            .unwrap();
+        meta.status = SessionStatus::Committed {
+            completed_at: mock_time(),
+        };
+        meta.blob_id = Some(7);
+        meta.was_deduplicated = true;
+        with_session_compat(|sessions| sessions.update(SessionId(980), meta)).unwrap();
+
+        let retried = commit(
+            &mut store,
+            &blob_store,
+            SessionId(980),
+            owner,
+            [0; 32],
+            11,
+            mock_time(),
+        )
+        .unwrap();
+        assert_eq!(retried.blob_id, "blob_7");
+        assert!(retried.was_deduplicated);
+    }
+
+    #[test]
+    fn test_deleted_blob_is_not_a_dedup_target() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = store_with_test_capsule(owner, None);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        let hash = compute_sha256(b"hello world");
+
+        create_pending_session(924, owner);
+        stored_session_pages(&blob_store, 924, &[(0, b"hello "), (1, b"world")]);
+        let first = recommit(
+            &mut store,
+            &blob_store,
+            SessionId(924),
+            owner,
+            hash,
+            11,
+            mock_time(),
+        )
+        .unwrap();
+        assert_eq!(
+            find_duplicate_blob(&blob_store, &create_test_capsule_id(), hash, 11),
+            Some(first.blob_id.trim_start_matches("blob_").parse().unwrap())
+        );
+
+        // Deleting the blob drops its index entry, so the same content is stored again
+        crate::upload::blob_store::blob_delete(&blob_store, first.blob_id).unwrap();
+        assert_eq!(
+            find_duplicate_blob(&blob_store, &create_test_capsule_id(), hash, 11),
+            None
+        );
+        create_pending_session(925, owner);
+        stored_session_pages(&blob_store, 925, &[(0, b"hello "), (1, b"world")]);
+        let second = recommit(
+            &mut store,
+            &blob_store,
+            SessionId(925),
+            owner,
+            hash,
+            11,
+            mock_time(),
+        )
+        .unwrap();
+        assert!(!second.was_deduplicated);
+        assert_eq!(blob_store.blob_count(), 1);
+    }
+
+    #[test]
+    fn test_commit_path_reports_capsule_gone_and_cleans_up() {
+        let owner = Principal::from_slice(&[7; 29]);
+        let mut store = create_test_store();
+        let capsule = crate::test_utils::create_test_capsule_at(
+            &create_test_capsule_id(),
+            PersonRef::Principal(owner),
+            mock_time(),
+        );
+        store.upsert(create_test_capsule_id(), capsule);
+
+        create_pending_session(909, owner);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        stored_session_pages(&blob_store, 909, &[(0, b"hello "), (1, b"world")]);
+
+        // Capsule still there: nothing to do
+        assert!(abandon_if_capsule_gone(&store, &blob_store, &SessionId(909)).is_ok());
+
+        store.remove(&create_test_capsule_id());
+        assert_eq!(
+            abandon_if_capsule_gone(&store, &blob_store, &SessionId(909)),
+            Err(Error::CapsuleGone(create_test_capsule_id()))
+        );
+
+        let pmid_hash = crate::upload::blob_store::pmid_session_hash32("test-memory-123", 909);
+        assert!(blob_store.get_page(&pmid_hash, 0).is_none());
+        assert!(blob_store.get_page(&pmid_hash, 1).is_none());
+        assert!(
+            with_session_compat(|sessions| sessions.get(&SessionId(909)))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_rehydrate_hash_after_rolling_hash_lost() {
+        let owner = Principal::from_slice(&[7; 29]);
+        create_pending_session(913, owner);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+
+        // Chunk 0 arrived before the upgrade; the rolling hash did not survive
+        stored_session_pages(&blob_store, 913, &[(0, b"hello ")]);
+        let mut hasher = rehydrate_hash(&blob_store, SessionId(913), owner).unwrap();
+
+        // The client keeps uploading and finishes against the rehydrated hash
+        hasher.update(b"world");
+        let rehydrated: [u8; 32] = hasher.finalize().into();
+        assert_eq!(rehydrated, compute_sha256(b"hello world"));
+
+        let stranger = Principal::from_slice(&[8; 29]);
+        assert!(matches!(
+            rehydrate_hash(&blob_store, SessionId(913), stranger),
+            Err(Error::Unauthorized)
+        ));
+    }
+
+    #[test]
+    fn test_verify_rolling_hash_detects_out_of_order_chunks() {
+        let owner = Principal::from_slice(&[7; 29]);
+        create_pending_session(936, owner);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+
+        // Chunk 1 arrived before chunk 0: stored by index, hashed by arrival
+        let mut rolling = Sha256::new();
+        rolling.update(b"world");
+        rolling.update(b"hello ");
+        stored_session_pages(&blob_store, 936, &[(1, b"world"), (0, b"hello ")]);
+
+        let (report, stored) =
+            verify_rolling_hash(&blob_store, SessionId(936), owner, Some(&rolling)).unwrap();
+        assert!(!report.matches);
+        assert_eq!(report.stored_sha256, compute_sha256(b"hello world"));
+        assert_eq!(report.rolling_sha256, Some(compute_sha256(b"worldhello ")));
+
+        // The recomputed hasher is a valid replacement for the accumulator
+        let (repaired, _) =
+            verify_rolling_hash(&blob_store, SessionId(936), owner, Some(&stored)).unwrap();
+        assert!(repaired.matches);
+
+        let (missing, _) = verify_rolling_hash(&blob_store, SessionId(936), owner, None).unwrap();
+        assert_eq!(missing.rolling_sha256, None);
+        assert!(!missing.matches);
+    }
+
+    #[test]
+    fn test_rehydrate_hash_rejects_gap() {
+        let owner = Principal::from_slice(&[7; 29]);
+        create_pending_session(914, owner);
+        let blob_store = crate::upload::blob_store::InMemoryBlobStore::new(mock_time());
+        stored_session_pages(&blob_store, 914, &[(1, b"world")]);
+
+        assert!(matches!(
+            rehydrate_hash(&blob_store, SessionId(914), owner),
+            Err(Error::Conflict(_))
+        ));
+    }
+
     // ============================================================================
     // PUT_CHUNK TESTS
     // ============================================================================
@@ -452,11 +1669,31 @@ mod tests {
         let bytes = vec![1, 2, 3, 4];
 
         // Test that non-existent session returns NotFound
-        let result = put_chunk(&mut store, &session_id, chunk_idx, bytes);
+        let result = put_chunk(
+            &mut store,
+            &session_id,
+            Principal::anonymous(),
+            chunk_idx,
+            bytes,
+            None,
+        );
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), Error::NotFound));
     }
 
+    #[test]
+    fn test_put_chunk_rejects_wrong_byte_offset() {
+        // Undeclared offsets and offsets on the chunk's slot pass
+        assert!(check_byte_offset(2, 1024, None).is_ok());
+        assert!(check_byte_offset(2, 1024, Some(2048)).is_ok());
+
+        // Off by one chunk: the client computed the boundary from the wrong index
+        match check_byte_offset(2, 1024, Some(1024)) {
+            Err(Error::InvalidArgument(msg)) => assert!(msg.contains("expected 2048")),
+            other => panic!("expected InvalidArgument, got {:?}", other),
+        }
+    }
+
     // #[test]
     fn _test_put_chunk_unauthorized_caller() {
         // let mut service = create_test_upload_service();
@@ -515,6 +1752,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_slow_chunk_report_lowers_suggested_chunk_size() {
+        // On target keeps the size; slow halves it at most; fast can pass the default
+        // but not the limit
+        assert_eq!(next_chunk_size(1_000_000, TARGET_CHUNK_MS), 1_000_000);
+        assert_eq!(next_chunk_size(1_000_000, TARGET_CHUNK_MS * 10), 500_000);
+        assert_eq!(next_chunk_size(CHUNK_SIZE, 1), MAX_CHUNK_SIZE);
+        assert_eq!(next_chunk_size(MAX_CHUNK_SIZE, 1), MAX_CHUNK_SIZE);
+        assert_eq!(next_chunk_size(MIN_CHUNK_SIZE, u32::MAX), MIN_CHUNK_SIZE);
+
+        let owner = Principal::from_slice(&[8; 29]);
+        create_pending_session(64, owner);
+        let suggested = suggest_chunk_size(&SessionId(64), owner, TARGET_CHUNK_MS * 4).unwrap();
+        assert!((suggested as usize) < CHUNK_SIZE);
+        let stored = list_upload_sessions()
+            .into_iter()
+            .find(|(sid, _)| *sid == 64)
+            .map(|(_, meta)| meta.suggested_chunk_size);
+        assert_eq!(stored, Some(suggested as usize));
+
+        assert!(matches!(
+            suggest_chunk_size(&SessionId(64), Principal::anonymous(), 10),
+            Err(Error::Unauthorized)
+        ));
+    }
+
     #[test]
     fn test_put_chunk_oversized() {
         let mut store = create_test_store();
@@ -523,7 +1786,14 @@ mod tests {
         let oversized_bytes = vec![0u8; CHUNK_SIZE + 1]; // Too large
 
         // Test that oversized chunks are rejected (session not found, but that's expected)
-        let result = put_chunk(&mut store, &session_id, chunk_idx, oversized_bytes.clone());
+        let result = put_chunk(
+            &mut store,
+            &session_id,
+            Principal::anonymous(),
+            chunk_idx,
+            oversized_bytes.clone(),
+            None,
+        );
         assert!(result.is_err());
         // The session doesn't exist, so we get NotFound, but the chunk size validation
         // would happen if the session existed
@@ -861,6 +2131,325 @@ mod tests {
             "Length error should be descriptive"
         );
     }
+
+    // ============================================================================
+    // UPLOAD REPLAY HARNESS
+    // ============================================================================
+    //
+    // Scripts drive sessions through begin -> put_chunk x N -> finish/abort in a
+    // fixed order. Chunks go through the same validation as `put_chunk` and are
+    // stored where its sink would put them, in an in-memory blob store; finish
+    // goes through `recommit`, which hashes the stored pages. After each script
+    // the runner checks that committed blobs hold exactly the scripted content and
+    // that compaction leaves no orphaned chunks behind.
+
+    /// Expected outcome of a step: `Ok` or the name of the error variant
+    type Outcome = std::result::Result<(), &'static str>;
+
+    /// One step of a scripted upload (every session has two chunks of up to 1024 bytes)
+    enum Step {
+        Begin {
+            sid: u64,
+        },
+        Put {
+            sid: u64,
+            idx: u32,
+            data: &'static [u8],
+            offset: Option<u64>,
+            expect: Outcome,
+        },
+        /// Rebuild the rolling hash from stored chunks, as after an upgrade
+        Rehydrate {
+            sid: u64,
+            expect: Outcome,
+        },
+        /// Finish with the hash and length of `content`
+        Finish {
+            sid: u64,
+            content: &'static [u8],
+            expect: Outcome,
+        },
+        Abort {
+            sid: u64,
+            by: Principal,
+            expect: Outcome,
+        },
+    }
+
+    fn replay_owner() -> Principal {
+        Principal::from_slice(&[7; 29])
+    }
+
+    fn error_kind(err: &Error) -> &'static str {
+        match err {
+            Error::NotFound => "NotFound",
+            Error::Unauthorized => "Unauthorized",
+            Error::InvalidArgument(_) => "InvalidArgument",
+            Error::Conflict(_) => "Conflict",
+            Error::ChecksumMismatch { .. } => "ChecksumMismatch",
+            other => panic!("unexpected error in upload replay: {:?}", other),
+        }
+    }
+
+    /// Replay `steps` from a clean slate and check the upload invariants
+    fn replay(name: &str, steps: &[Step]) {
+        use crate::upload::blob_store::InMemoryBlobStore;
+        use std::collections::BTreeMap;
+
+        clear_all_sessions();
+        let owner = replay_owner();
+        let mut store = store_with_test_capsule(owner, None);
+        let blob_store = InMemoryBlobStore::new(mock_time());
+        // Latest accepted bytes of every chunk, per session
+        let mut model: BTreeMap<u64, BTreeMap<u32, Vec<u8>>> = BTreeMap::new();
+        let mut committed: Vec<(String, &'static [u8])> = Vec::new();
+
+        for (n, step) in steps.iter().enumerate() {
+            let (result, expect) = match step {
+                Step::Begin { sid } => {
+                    create_pending_session(*sid, owner);
+                    (Ok(()), &Ok(()))
+                }
+                Step::Put {
+                    sid,
+                    idx,
+                    data,
+                    offset,
+                    expect,
+                } => {
+                    let result = validate_chunk(&SessionId(*sid), owner, *idx, data.len(), *offset)
+                        .map(|(_, pmid_hash)| {
+                            blob_store.put_page(pmid_hash, *idx, data.to_vec());
+                            model.entry(*sid).or_default().insert(*idx, data.to_vec());
+                        });
+                    (result, expect)
+                }
+                Step::Rehydrate { sid, expect } => {
+                    let result =
+                        rehydrate_hash(&blob_store, SessionId(*sid), owner).map(|hasher| {
+                            // Must match the contiguous run of accepted chunks from index 0
+                            let mut expected = Sha256::new();
+                            let chunks = model.get(sid).cloned().unwrap_or_default();
+                            for (i, (idx, data)) in chunks.iter().enumerate() {
+                                if *idx != i as u32 {
+                                    break;
+                                }
+                                expected.update(data);
+                            }
+                            assert_eq!(
+                                hasher.finalize(),
+                                expected.finalize(),
+                                "{name}: step {n} rehydrated hash"
+                            );
+                        });
+                    (result, expect)
+                }
+                Step::Finish {
+                    sid,
+                    content,
+                    expect,
+                } => {
+                    let result = recommit(
+                        &mut store,
+                        &blob_store,
+                        SessionId(*sid),
+                        owner,
+                        compute_sha256(content),
+                        content.len() as u64,
+                        mock_time(),
+                    )
+                    .map(|outcome| committed.push((outcome.blob_id, *content)));
+                    (result, expect)
+                }
+                Step::Abort { sid, by, expect } => {
+                    (abort(&mut store, SessionId(*sid), *by), expect)
+                }
+            };
+            assert_eq!(
+                result.map_err(|err| error_kind(&err)),
+                *expect,
+                "{name}: step {n}"
+            );
+        }
+
+        // Chunks of finished or aborted sessions are reclaimable: none stay orphaned
+        blob_store.compact(&pending_blob_stems());
+        assert_eq!(
+            blob_store
+                .compaction_report(&pending_blob_stems())
+                .orphaned_pages,
+            0,
+            "{name}: orphaned chunks"
+        );
+
+        // Committed blobs hold exactly the scripted content, also after compaction
+        for (blob_id, content) in &committed {
+            let id = crate::utils::blob_id::parse_blob_id(blob_id).unwrap();
+            assert_eq!(
+                blob_store.read_blob(&BlobId(id)).unwrap(),
+                content.to_vec(),
+                "{name}: {blob_id} content"
+            );
+        }
+    }
+
+    #[test]
+    fn test_upload_replay_scripts() {
+        use Step::*;
+
+        let owner = replay_owner();
+        let stranger = Principal::from_slice(&[8; 29]);
+        let put = |sid: u64, idx: u32, data: &'static [u8]| Put {
+            sid,
+            idx,
+            data,
+            offset: None,
+            expect: Ok(()),
+        };
+        let finish_ok = |sid: u64| Finish {
+            sid,
+            content: b"hello world",
+            expect: Ok(()),
+        };
+
+        let scripts: Vec<(&str, Vec<Step>)> = vec![
+            (
+                "in order",
+                vec![
+                    Begin { sid: 1 },
+                    put(1, 0, b"hello "),
+                    put(1, 1, b"world"),
+                    finish_ok(1),
+                ],
+            ),
+            // Rolling-hash bug: a chunk arriving before its predecessor
+            (
+                "out of order",
+                vec![
+                    Begin { sid: 2 },
+                    put(2, 1, b"world"),
+                    Rehydrate {
+                        sid: 2,
+                        expect: Err("Conflict"),
+                    },
+                    put(2, 0, b"hello "),
+                    Rehydrate {
+                        sid: 2,
+                        expect: Ok(()),
+                    },
+                    finish_ok(2),
+                ],
+            ),
+            // A retried chunk replaces the first copy
+            (
+                "duplicate chunk",
+                vec![
+                    Begin { sid: 3 },
+                    put(3, 0, b"HELLO "),
+                    put(3, 0, b"hello "),
+                    put(3, 1, b"world"),
+                    finish_ok(3),
+                ],
+            ),
+            (
+                "finish with missing chunk",
+                vec![
+                    Begin { sid: 4 },
+                    put(4, 0, b"hello "),
+                    Finish {
+                        sid: 4,
+                        content: b"hello world",
+                        expect: Err("NotFound"),
+                    },
+                    put(4, 1, b"world"),
+                    finish_ok(4),
+                ],
+            ),
+            // A wrong hash leaves the session pending for a corrected retry
+            (
+                "wrong hash then corrected",
+                vec![
+                    Begin { sid: 5 },
+                    put(5, 0, b"hello "),
+                    put(5, 1, b"world"),
+                    Finish {
+                        sid: 5,
+                        content: b"hello there",
+                        expect: Err("ChecksumMismatch"),
+                    },
+                    finish_ok(5),
+                ],
+            ),
+            (
+                "abort then finish",
+                vec![
+                    Begin { sid: 6 },
+                    put(6, 0, b"hello "),
+                    Abort {
+                        sid: 6,
+                        by: stranger,
+                        expect: Err("Unauthorized"),
+                    },
+                    Abort {
+                        sid: 6,
+                        by: owner,
+                        expect: Ok(()),
+                    },
+                    Abort {
+                        sid: 6,
+                        by: owner,
+                        expect: Ok(()),
+                    },
+                    Put {
+                        sid: 6,
+                        idx: 1,
+                        data: b"world",
+                        offset: None,
+                        expect: Err("NotFound"),
+                    },
+                    Finish {
+                        sid: 6,
+                        content: b"hello world",
+                        expect: Err("NotFound"),
+                    },
+                ],
+            ),
+            // Declared offsets must match the chunk slot (chunk size 1024)
+            (
+                "byte offsets and indices",
+                vec![
+                    Begin { sid: 7 },
+                    Put {
+                        sid: 7,
+                        idx: 1,
+                        data: b"world",
+                        offset: Some(6),
+                        expect: Err("InvalidArgument"),
+                    },
+                    Put {
+                        sid: 7,
+                        idx: 2,
+                        data: b"!",
+                        offset: None,
+                        expect: Err("InvalidArgument"),
+                    },
+                    Put {
+                        sid: 7,
+                        idx: 1,
+                        data: b"world",
+                        offset: Some(1024),
+                        expect: Ok(()),
+                    },
+                    put(7, 0, b"hello "),
+                    finish_ok(7),
+                ],
+            ),
+        ];
+
+        for (name, steps) in &scripts {
+            replay(name, steps);
+        }
+    }
 }
 
 // Integration tests will be added after core functionality is working
@@ -42,6 +42,31 @@ pub struct UploadFinishResult {
     pub storage_location: String, // URL or key
     pub uploaded_at: u64,         // ms since epoch
     pub expires_at: Option<u64>,
+    pub commit_receipt: Option<Box<CommitReceipt>>, // only when requested at finish
+    pub was_deduplicated: bool,                     // blob_id names an existing blob
+}
+
+/// Result of `uploads_finish_into_gallery`: the finished upload and the gallery it joined
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct GalleryUploadResult {
+    pub upload: UploadFinishResult, // memory_id is set
+    pub gallery_id: String,
+}
+
+/// Blob a commit settled on, and whether identical content was already stored
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitOutcome {
+    pub blob_id: String,
+    pub was_deduplicated: bool, // no new bytes were written or charged
+}
+
+/// Server-side accounting of what a commit stored, read back from the blob pages
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct CommitReceipt {
+    pub blob_id: String,
+    pub chunk_bytes: Vec<u64>, // bytes stored per chunk, in chunk order
+    pub assembled_len: u64,
+    pub checksum_sha256: [u8; 32],
 }
 
 /// Unified upload progress for all storage backends
@@ -106,7 +131,12 @@ pub struct CommitResponse {
 pub const INLINE_MAX: u64 = 32 * 1024; // 32KB (fits in Capsule bound)
 pub const CHUNK_SIZE: usize = 1_800_000; // 1.8MB - ICP expert recommended optimal size
                                          // Removed unused constant: PAGE_SIZE
+pub const MIN_CHUNK_SIZE: usize = 64 * 1024; // Smallest chunk size a session may use
+pub const MAX_CHUNK_SIZE: usize = 1_950_000; // Largest session chunk size (fits a 2MiB message)
+pub const TARGET_CHUNK_MS: u32 = 2_000; // Per-chunk upload time chunk size suggestions aim for
 pub const CAPSULE_INLINE_BUDGET: u64 = 32 * 1024; // Max inline bytes per capsule
+pub const MAX_INLINE_ASSETS_PER_MEMORY_DEFAULT: u32 = 16; // Inline renditions one memory may carry
+pub const CAPSULE_BLOB_QUOTA_DEFAULT: u64 = 1024 * 1024 * 1024; // 1GB of internal blobs per capsule
 
 // Re-export SessionId from session module to avoid duplication
 pub use crate::session::types::SessionId;
@@ -215,6 +245,7 @@ pub struct BlobMeta {
     pub checksum: [u8; 32],
     pub created_at: u64,
     pub pmid_hash: [u8; 32], // SHA256 of provisional_memory_id for deterministic key lookups
+    pub capsule_id: Option<String>, // capsule charged for these bytes (None before quotas)
 }
 
 impl Storable for BlobMeta {
@@ -235,6 +266,67 @@ impl Storable for BlobMeta {
     }
 }
 
+/// Live vs dead accounting for the stable blob store
+///
+/// Orphaned pages are pages with no blob metadata and no pending upload session
+/// writing them (left behind by failed commits or aborted uploads).
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct CompactionReport {
+    pub live_blobs: u64,
+    pub live_pages: u64,
+    pub live_bytes: u64,
+    pub in_flight_pages: u64, // written by pending upload sessions
+    pub orphaned_pages: u64,
+    pub reclaimable_bytes: u64, // orphaned page data + key overhead
+    pub dangling_metas: u64,    // metadata whose first page is missing
+    pub removed_pages: u64,     // only set by blobs_compact
+    pub removed_bytes: u64,     // only set by blobs_compact
+}
+
+/// One chunk of a pending upload that is physically present in the blob store
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PartialChunk {
+    pub index: u32,
+    pub len: u64,
+    pub sha256: [u8; 32],
+}
+
+/// Rolling upload hash compared with the stored chunks (for `uploads_verify_hash`)
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct HashVerifyReport {
+    pub session_id: u64,
+    pub rolling_sha256: Option<[u8; 32]>, // None when the session has no live accumulator
+    pub stored_sha256: [u8; 32],          // stored chunks hashed in index order
+    pub matches: bool,
+    pub repaired: bool, // the accumulator was replaced with the index-ordered hash
+}
+
+/// One upload session, as listed to admins by `sessions_list_paged`
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SessionSummary {
+    pub session_id: u64,
+    pub capsule_id: CapsuleId,
+    pub caller: candid::Principal,
+    pub status: crate::session::types::SessionStatus,
+    pub created_at: u64,
+    pub chunk_count: u32,
+    pub chunk_size: u64,
+}
+
+/// What a pending (uncommitted) upload session has actually stored
+///
+/// Lets a client whose `uploads_finish` failed validation decide whether to
+/// `uploads_recommit` with a corrected hash or re-send specific chunks.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PartialBlobReport {
+    pub session_id: u64,
+    pub chunk_count: u32,
+    pub stored_chunks: Vec<PartialChunk>,
+    pub missing_chunks: Vec<u32>,
+    pub stored_bytes: u64,
+    pub sha256: Option<[u8; 32]>, // hash over all chunks in order, only when none are missing
+}
+
 /// Result type for uploads_finish function (UploadFinishResult or Error)
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub enum Result15 {
@@ -62,7 +62,7 @@ pub fn register_user_with_nonce(nonce: String) -> std::result::Result<(), Error>
     } else {
         // Frontend perspective: "Create new user"
         // Backend reality: "Create new self-capsule"
-        match capsules_create(None) {
+        match capsules_create(None, None) {
             Ok(_) => {}
             Err(e) => {
                 return Err(types::Error::Internal(format!(
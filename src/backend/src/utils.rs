@@ -1,6 +1,8 @@
 // ✅ MODERN RUST: Main utils module file
 pub mod blob_id;
 pub mod name_conversion;  // ✅ ADD: New name conversion utilities
+pub mod pagination;
+pub mod response_size;
 pub mod uuid_v7;
 
 // Re-export specific items for convenience
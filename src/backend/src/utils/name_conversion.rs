@@ -5,6 +5,10 @@
 
 /// Convert a user-facing title to a URL-safe identifier
 ///
+/// Lowercases, folds Latin diacritics to ASCII (precomposed or combining),
+/// turns whitespace runs, underscores and dots into hyphens and drops other
+/// symbols. Titles with nothing left (empty, symbols only) become `"untitled"`.
+///
 /// # Examples
 ///
 /// ```
@@ -14,23 +18,100 @@
 /// assert_eq!(title_to_name("My Dog's Birthday!"), "my-dogs-birthday");
 /// assert_eq!(title_to_name("IMG_2024_12_19.jpg"), "img-2024-12-19-jpg");
 /// assert_eq!(title_to_name("Beach Sunset 🌅"), "beach-sunset");
+/// assert_eq!(title_to_name("Café  Zürich"), "cafe-zurich");
 /// assert_eq!(title_to_name(""), "untitled");
 /// ```
 pub fn title_to_name(title: &str) -> String {
-    if title.trim().is_empty() {
-        return "untitled".to_string();
+    let mut name = String::with_capacity(title.len());
+    let mut in_whitespace = false;
+
+    for c in title.trim().chars() {
+        if c.is_whitespace() {
+            if !in_whitespace {
+                name.push('-');
+            }
+            in_whitespace = true;
+            continue;
+        }
+        in_whitespace = false;
+
+        match c {
+            '_' | '.' | '-' => name.push('-'),
+            _ if is_combining_mark(c) => {}
+            _ => {
+                for lower in c.to_lowercase() {
+                    match fold_latin(lower) {
+                        Some(ascii) => name.push_str(ascii),
+                        None if lower.is_alphanumeric() => name.push(lower),
+                        None => {}
+                    }
+                }
+            }
+        }
     }
 
-    title
-        .to_lowercase()
-        .replace(" ", "-") // spaces to hyphens
-        .replace("_", "-") // underscores to hyphens
-        .replace(".", "-") // dots to hyphens
-        .chars()
-        .filter(|c| c.is_alphanumeric() || *c == '-') // only alphanumeric + hyphens
-        .collect::<String>()
-        .trim_matches('-') // remove leading/trailing hyphens
-        .to_string()
+    let name = name.trim_matches('-');
+    if name.is_empty() {
+        "untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Make `base` unique among existing names
+///
+/// Returns `base` if free, else `base-2`, `base-3`, ... The `"untitled"`
+/// fallback is always numbered (`untitled-1`, `untitled-2`, ...).
+pub fn unique_name(base: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    let first_suffix = if base == "untitled" {
+        1
+    } else {
+        if !is_taken(base) {
+            return base.to_string();
+        }
+        2
+    };
+
+    (first_suffix..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|candidate| !is_taken(candidate))
+        .expect("unbounded suffix range")
+}
+
+/// Combining diacritical marks (left behind by decomposed input like "e\u{301}")
+fn is_combining_mark(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}')
+}
+
+/// ASCII replacement of a lowercase Latin letter with diacritics
+fn fold_latin(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'þ' => "th",
+        'ð' => "d",
+        _ => return None,
+    })
 }
 
 /// Generate a default name when no title is provided
@@ -65,6 +146,38 @@ mod tests {
         assert_eq!(title_to_name("   "), "untitled");
     }
 
+    #[test]
+    fn test_title_to_name_unicode() {
+        assert_eq!(title_to_name("Café  Zürich"), "cafe-zurich");
+        // Decomposed accents normalize the same as precomposed ones
+        assert_eq!(title_to_name("Cafe\u{301}"), title_to_name("Café"));
+        assert_eq!(title_to_name("Straße"), "strasse");
+        assert_eq!(title_to_name("Summer\t\n 2024"), "summer-2024");
+        // Non-Latin scripts are kept (lowercased where applicable)
+        assert_eq!(title_to_name("東京 Trip"), "東京-trip");
+        assert_eq!(title_to_name("ΑΘΗΝΑ"), "αθηνα");
+    }
+
+    #[test]
+    fn test_title_to_name_symbols_only() {
+        assert_eq!(title_to_name("!!! ???"), "untitled");
+        assert_eq!(title_to_name("🌅🌅"), "untitled");
+    }
+
+    #[test]
+    fn test_unique_name_appends_suffix() {
+        let taken = ["trip", "trip-2"];
+        assert_eq!(unique_name("beach", |n| taken.contains(&n)), "beach");
+        assert_eq!(unique_name("trip", |n| taken.contains(&n)), "trip-3");
+
+        let untitled = ["untitled-1"];
+        assert_eq!(
+            unique_name("untitled", |n| untitled.contains(&n)),
+            "untitled-2"
+        );
+        assert_eq!(unique_name("untitled", |_| false), "untitled-1");
+    }
+
     #[test]
     fn test_generate_default_name() {
         assert_eq!(generate_default_name("memory", "123"), "memory-123");
@@ -0,0 +1,103 @@
+use crate::capsule_store::types::{Page, PaginationOrder as Order};
+
+/// Keyset-paginate `items` by `key_fn`
+///
+/// Items are sorted by key in `order`; the page holds up to `limit` items strictly
+/// after `after` (in that order). `next_cursor` is the key of the last item on the
+/// page, rendered with `Display`, and is `None` once nothing follows it. Keys must
+/// be unique, otherwise items sharing the cursor key are skipped.
+pub fn paginate_sorted<T, K, F>(
+    mut items: Vec<T>,
+    key_fn: F,
+    after: Option<K>,
+    limit: u32,
+    order: Order,
+) -> Page<T>
+where
+    K: Ord + std::fmt::Display,
+    F: Fn(&T) -> K,
+{
+    items.sort_by(|a, b| match order {
+        Order::Asc => key_fn(a).cmp(&key_fn(b)),
+        Order::Desc => key_fn(b).cmp(&key_fn(a)),
+    });
+
+    let start = match after {
+        Some(after) => items
+            .iter()
+            .position(|item| match order {
+                Order::Asc => key_fn(item) > after,
+                Order::Desc => key_fn(item) < after,
+            })
+            .unwrap_or(items.len()),
+        None => 0,
+    };
+    let end = start.saturating_add(limit.max(1) as usize).min(items.len());
+    let has_more = end < items.len();
+
+    let page: Vec<T> = items.drain(start..end).collect();
+    let next_cursor = if has_more {
+        page.last().map(|item| key_fn(item).to_string())
+    } else {
+        None
+    };
+
+    Page {
+        items: page,
+        next_cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+
+    /// Walk every page and return the items in the order they were served
+    fn walk(items: &[u32], limit: u32, order: Order) -> Vec<u32> {
+        let mut seen = Vec::new();
+        let mut after: Option<u32> = None;
+        loop {
+            let page = paginate_sorted(items.to_vec(), |n| *n, after, limit, order);
+            seen.extend(page.items.iter().copied());
+            match page.next_cursor {
+                Some(cursor) => after = Some(cursor.parse().unwrap()),
+                None => return seen,
+            }
+        }
+    }
+
+    #[test]
+    fn test_paginate_sorted_desc() {
+        let page = paginate_sorted(vec![3u32, 1, 2], |n| *n, None, 2, Order::Desc);
+        assert_eq!(page.items, vec![3, 2]);
+        assert_eq!(page.next_cursor.as_deref(), Some("2"));
+
+        let page = paginate_sorted(vec![3u32, 1, 2], |n| *n, Some(2), 2, Order::Desc);
+        assert_eq!(page.items, vec![1]);
+        assert!(page.next_cursor.is_none());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_consecutive_pages_never_overlap_or_skip(
+            items in proptest::collection::btree_set(any::<u32>(), 0..200),
+            limit in 1u32..20,
+            desc in any::<bool>(),
+        ) {
+            let order = if desc { Order::Desc } else { Order::Asc };
+            // Shuffle-ish input: pagination must not depend on incoming order
+            let input: Vec<u32> = items.iter().rev().copied().collect();
+
+            let served = walk(&input, limit, order);
+
+            let mut expected: Vec<u32> = items.iter().copied().collect();
+            if desc {
+                expected.reverse();
+            }
+            prop_assert_eq!(&served, &expected);
+            prop_assert_eq!(served.iter().collect::<BTreeSet<_>>().len(), served.len());
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::memory::{MEM_RESPONSE_LIMIT, MM};
+use crate::types::Error;
+use candid::CandidType;
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{DefaultMemoryImpl, StableCell};
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Hard cap on a replicated query/update response imposed by the IC.
+pub const IC_MAX_RESPONSE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Safe threshold for full-object reads. Leaves headroom below the IC cap for
+/// the `Result` envelope and Candid type table, which estimates don't account for.
+pub const DEFAULT_RESPONSE_LIMIT_BYTES: u64 = 1_800_000;
+
+thread_local! {
+    // Admin-set threshold, kept across upgrades
+    static RESPONSE_LIMIT_BYTES: RefCell<StableCell<u64, Memory>> = RefCell::new(
+        StableCell::init(
+            MM.with(|m| m.borrow().get(MEM_RESPONSE_LIMIT)),
+            DEFAULT_RESPONSE_LIMIT_BYTES,
+        )
+        .expect("Failed to init response limit")
+    );
+}
+
+/// Current threshold for full-object reads
+pub fn response_limit_bytes() -> u64 {
+    RESPONSE_LIMIT_BYTES.with(|limit| *limit.borrow().get())
+}
+
+/// Change the full-object read threshold (admin)
+///
+/// Must stay positive and no higher than `IC_MAX_RESPONSE_BYTES`.
+pub fn set_response_limit_bytes(limit: u64) -> Result<(), Error> {
+    if limit == 0 || limit > IC_MAX_RESPONSE_BYTES {
+        return Err(Error::InvalidArgument(format!(
+            "limit must be between 1 and {} bytes",
+            IC_MAX_RESPONSE_BYTES
+        )));
+    }
+    RESPONSE_LIMIT_BYTES.with(|cell| {
+        cell.borrow_mut()
+            .set(limit)
+            .expect("Failed to store response limit");
+    });
+    Ok(())
+}
+
+/// Candid-encoded size of a response payload, or `u64::MAX` if it can't be encoded
+pub fn encoded_size<T: CandidType>(value: &T) -> u64 {
+    candid::encode_one(value)
+        .map(|bytes| bytes.len() as u64)
+        .unwrap_or(u64::MAX)
+}
+
+/// Reject a payload whose estimated size exceeds `limit` with a structured
+/// `ResponseTooLarge` error instead of letting the reply trap at runtime.
+pub fn guard_response_size_with_limit(
+    estimated_bytes: u64,
+    limit: u64,
+    suggestion: &str,
+) -> Result<(), Error> {
+    if estimated_bytes > limit {
+        return Err(Error::ResponseTooLarge {
+            estimated_bytes,
+            suggestion: suggestion.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// `guard_response_size_with_limit` against the current `response_limit_bytes`
+pub fn guard_response_size(estimated_bytes: u64, suggestion: &str) -> Result<(), Error> {
+    guard_response_size_with_limit(estimated_bytes, response_limit_bytes(), suggestion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_allows_payload_under_limit() {
+        assert!(guard_response_size_with_limit(100, 1_000, "unused").is_ok());
+        assert!(guard_response_size_with_limit(1_000, 1_000, "unused").is_ok());
+    }
+
+    #[test]
+    fn test_guard_rejects_payload_over_limit() {
+        let err =
+            guard_response_size_with_limit(1_001, 1_000, "use capsules_read_basic").unwrap_err();
+        assert_eq!(
+            err,
+            Error::ResponseTooLarge {
+                estimated_bytes: 1_001,
+                suggestion: "use capsules_read_basic".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_default_limit_is_below_ic_cap() {
+        const { assert!(DEFAULT_RESPONSE_LIMIT_BYTES < IC_MAX_RESPONSE_BYTES) };
+        assert!(guard_response_size(IC_MAX_RESPONSE_BYTES, "x").is_err());
+    }
+
+    #[test]
+    fn test_response_limit_setter_bounds() {
+        assert!(set_response_limit_bytes(0).is_err());
+        assert!(set_response_limit_bytes(IC_MAX_RESPONSE_BYTES + 1).is_err());
+        assert_eq!(response_limit_bytes(), DEFAULT_RESPONSE_LIMIT_BYTES);
+
+        set_response_limit_bytes(1_000).unwrap();
+        assert!(guard_response_size(1_000, "x").is_ok());
+        assert!(guard_response_size(1_001, "x").is_err());
+    }
+
+    #[test]
+    fn test_encoded_size_grows_with_payload() {
+        let small = encoded_size(&"a".to_string());
+        let large = encoded_size(&"a".repeat(1_000));
+        assert!(large > small);
+        assert!(large >= 1_000);
+    }
+}
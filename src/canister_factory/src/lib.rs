@@ -19,7 +19,10 @@ use std::collections::{BTreeMap, BTreeSet};
 /// ===== Configuration Constants =====
 const MAX_CHUNK_SIZE: usize = 2_000_000; // 2MB per chunk
 const DEFAULT_UPLOAD_TTL_NS: u64 = 24 * 60 * 60 * 1_000_000_000; // 24 hours in nanoseconds
+const AUDIT_LOG_MAX: usize = 1_000; // oldest entries are dropped beyond this
+const DEFAULT_MAX_UPLOAD_TTL_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days in nanoseconds
 const DEFAULT_MAX_UPLOAD_SIZE: u64 = 50_000_000; // 50MB max WASM size
+const MAX_UPLOAD_LABEL_LEN: usize = 128; // bytes
 
 /// ===== Types exposed over Candid =====
 
@@ -37,6 +40,8 @@ pub struct InitArg {
     pub max_upload_size: Option<u64>,
     /// Optional upload TTL in seconds (default 24 hours).
     pub upload_ttl_seconds: Option<u64>,
+    /// Optional cap on per-upload TTL overrides in seconds (default 7 days).
+    pub max_upload_ttl_seconds: Option<u64>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone)]
@@ -46,6 +51,21 @@ pub struct UploadInfo {
     pub total_len: u64,
     pub committed_hash: Option<[u8; 32]>,
     pub created_at_time_ns: u64,
+    /// Effective deadline; `None` for uploads created before per-upload TTLs.
+    pub expires_at_time_ns: Option<u64>,
+    /// Client-chosen name (e.g. "backend-v2.wasm"); bookkeeping only, no effect on install.
+    pub label: Option<String>,
+}
+
+/// An upload as listed by `my_uploads`, without its chunks.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UploadSummary {
+    pub upload_id: u64,
+    pub label: Option<String>,
+    pub total_len: u64,
+    pub committed: bool,
+    pub created_at_time_ns: u64,
+    pub expires_at_time_ns: u64,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Default, Clone)]
@@ -90,6 +110,16 @@ pub struct UploadCommit {
     pub expected_sha256_hex: String,
 }
 
+/// Audit log entry for a privileged admin action
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub at: u64,
+    pub actor: Principal,
+    pub action: String,
+    pub detail: String,
+}
+
 #[derive(CandidType, Serialize, Deserialize)]
 pub struct FactoryStats {
     pub total_canisters_created: u64,
@@ -107,6 +137,7 @@ struct Config {
     min_factory_cycles: u128,
     max_upload_size: u64,
     upload_ttl_ns: u64,
+    max_upload_ttl_ns: Option<u64>, // cap on per-upload overrides; None = default (7 days)
     allowlist: Option<BTreeSet<Principal>>,
     admins: BTreeSet<Principal>,
     next_upload_id: u64,
@@ -120,6 +151,7 @@ impl Default for Config {
             min_factory_cycles: 5_000_000_000_000, // 5T cycles
             max_upload_size: DEFAULT_MAX_UPLOAD_SIZE,
             upload_ttl_ns: DEFAULT_UPLOAD_TTL_NS,
+            max_upload_ttl_ns: None,
             allowlist: None,
             admins: BTreeSet::new(),
             next_upload_id: 1,
@@ -134,6 +166,7 @@ struct State {
     uploads: BTreeMap<u64, UploadInfo>,
     caller_stats: BTreeMap<Principal, CallerStats>,
     total_canisters_created: u64,
+    audit_log: Option<Vec<AuditEntry>>, // None until the first audited action
 }
 
 thread_local! {
@@ -209,22 +242,65 @@ fn caller_stats_ok(caller: Principal) -> Result<(), String> {
     })
 }
 
+fn record_audit(st: &mut State, actor: Principal, at: u64, action: &str, detail: String) {
+    let log = st.audit_log.get_or_insert_with(Vec::new);
+    let seq = log.last().map(|entry| entry.seq + 1).unwrap_or(0);
+    log.push(AuditEntry {
+        seq,
+        at,
+        actor,
+        action: action.to_string(),
+        detail,
+    });
+    if log.len() > AUDIT_LOG_MAX {
+        log.remove(0);
+    }
+}
+
 fn sha256_hex(bytes: &[u8]) -> String {
     let mut h = Sha256::new();
     h.update(bytes);
     hex::encode(h.finalize())
 }
 
-fn cleanup_expired_uploads() -> u32 {
-    let now = ic_cdk::api::time();
+/// TTL for a new upload: the requested override or the default, capped at the admin max
+fn effective_upload_ttl_ns(cfg: &Config, ttl_seconds: Option<u64>) -> u64 {
+    let max = cfg.max_upload_ttl_ns.unwrap_or(DEFAULT_MAX_UPLOAD_TTL_NS);
+    ttl_seconds
+        .map(|sec| sec.saturating_mul(1_000_000_000))
+        .unwrap_or(cfg.upload_ttl_ns)
+        .min(max)
+}
+
+/// Deadline of an upload; older uploads without one use the global TTL
+/// Trim a client label; blank labels are dropped and long ones rejected.
+fn normalize_upload_label(label: Option<String>) -> Result<Option<String>, String> {
+    let Some(label) = label else {
+        return Ok(None);
+    };
+    let label = label.trim();
+    if label.len() > MAX_UPLOAD_LABEL_LEN {
+        return Err(format!(
+            "Label too long: {} bytes (max {MAX_UPLOAD_LABEL_LEN})",
+            label.len()
+        ));
+    }
+    Ok((!label.is_empty()).then(|| label.to_string()))
+}
+
+fn upload_expires_at(cfg: &Config, upload: &UploadInfo) -> u64 {
+    upload
+        .expires_at_time_ns
+        .unwrap_or_else(|| upload.created_at_time_ns.saturating_add(cfg.upload_ttl_ns))
+}
+
+fn cleanup_expired_uploads(now: u64) -> u32 {
     STATE.with(|s| {
         let mut st = s.borrow_mut();
         let expired: Vec<u64> = st
             .uploads
             .iter()
-            .filter(|(_, upload)| {
-                now.saturating_sub(upload.created_at_time_ns) > st.cfg.upload_ttl_ns
-            })
+            .filter(|(_, upload)| now > upload_expires_at(&st.cfg, upload))
             .map(|(id, _)| *id)
             .collect();
 
@@ -253,6 +329,9 @@ fn init(arg: Option<InitArg>) {
         if let Some(ttl_sec) = a.upload_ttl_seconds {
             st.cfg.upload_ttl_ns = ttl_sec * 1_000_000_000; // Convert to nanoseconds
         }
+        if let Some(max_sec) = a.max_upload_ttl_seconds {
+            st.cfg.max_upload_ttl_ns = Some(max_sec * 1_000_000_000);
+        }
         if let Some(list) = a.allowlist {
             st.cfg.allowlist = Some(list.into_iter().collect());
         }
@@ -283,31 +362,74 @@ fn post_upgrade() {
 
 /// ===== Upload API (chunked) =====
 
+/// Start a chunked upload; `ttl_seconds` overrides the default TTL up to the admin max,
+/// and `label` is an optional name for the client's own bookkeeping.
 #[update]
-async fn create_upload() -> Result<u64, String> {
+async fn create_upload(ttl_seconds: Option<u64>, label: Option<String>) -> Result<u64, String> {
     let caller = msg_caller();
     must_allowed(caller)?;
     must_have_cycles_left()?;
 
+    create_upload_as(caller, ic_cdk::api::time(), ttl_seconds, label)
+}
+
+fn create_upload_as(
+    caller: Principal,
+    now: u64,
+    ttl_seconds: Option<u64>,
+    label: Option<String>,
+) -> Result<u64, String> {
+    let label = normalize_upload_label(label)?;
+
     // Clean up expired uploads opportunistically
-    cleanup_expired_uploads();
+    cleanup_expired_uploads(now);
 
     let id = bump_upload_id();
     STATE.with(|s| {
-        s.borrow_mut().uploads.insert(
+        let mut st = s.borrow_mut();
+        let ttl_ns = effective_upload_ttl_ns(&st.cfg, ttl_seconds);
+        st.uploads.insert(
             id,
             UploadInfo {
                 owner: caller,
                 chunks: Vec::new(),
                 total_len: 0,
                 committed_hash: None,
-                created_at_time_ns: ic_cdk::api::time(),
+                created_at_time_ns: now,
+                expires_at_time_ns: Some(now.saturating_add(ttl_ns)),
+                label,
             },
         );
     });
     Ok(id)
 }
 
+/// Rename an upload, or clear its label with `None`.
+#[update]
+fn set_upload_label(upload_id: u64, label: Option<String>) -> Result<(), String> {
+    set_upload_label_as(msg_caller(), upload_id, label)
+}
+
+fn set_upload_label_as(
+    caller: Principal,
+    upload_id: u64,
+    label: Option<String>,
+) -> Result<(), String> {
+    let label = normalize_upload_label(label)?;
+    STATE.with(|s| {
+        let mut st = s.borrow_mut();
+        let up = st
+            .uploads
+            .get_mut(&upload_id)
+            .ok_or_else(|| "upload_id not found".to_string())?;
+        if up.owner != caller {
+            return Err("not owner".into());
+        }
+        up.label = label;
+        Ok(())
+    })
+}
+
 #[update]
 async fn put_chunk(upload_id: u64, chunk: Vec<u8>) -> Result<u64, String> {
     let caller = msg_caller();
@@ -353,7 +475,14 @@ async fn put_chunk(upload_id: u64, chunk: Vec<u8>) -> Result<u64, String> {
 
 #[update]
 async fn commit_upload(upload_id: u64, commit: UploadCommit) -> Result<String, String> {
-    let caller = msg_caller();
+    commit_upload_as(msg_caller(), upload_id, commit)
+}
+
+fn commit_upload_as(
+    caller: Principal,
+    upload_id: u64,
+    commit: UploadCommit,
+) -> Result<String, String> {
     STATE.with(|s| {
         let mut st = s.borrow_mut();
         let up = st
@@ -590,16 +719,68 @@ fn remove_admin(admin: Principal) -> Result<(), String> {
     })
 }
 
+#[update]
+fn set_max_upload_ttl(ttl_seconds: u64) -> Result<(), String> {
+    let caller = msg_caller();
+    must_be_admin(caller)?;
+
+    STATE.with(|s| {
+        s.borrow_mut().cfg.max_upload_ttl_ns = Some(ttl_seconds.saturating_mul(1_000_000_000));
+    });
+    Ok(())
+}
+
+/// Hand an upload to a new principal, e.g. to recover a large upload after the
+/// client lost its identity. Audited.
+#[update]
+fn reassign_upload(upload_id: u64, new_owner: Principal) -> Result<(), String> {
+    reassign_upload_as(msg_caller(), ic_cdk::api::time(), upload_id, new_owner)
+}
+
+fn reassign_upload_as(
+    caller: Principal,
+    now: u64,
+    upload_id: u64,
+    new_owner: Principal,
+) -> Result<(), String> {
+    must_be_admin(caller)?;
+
+    STATE.with(|s| {
+        let mut st = s.borrow_mut();
+        let up = st
+            .uploads
+            .get_mut(&upload_id)
+            .ok_or_else(|| "upload_id not found".to_string())?;
+        let previous = std::mem::replace(&mut up.owner, new_owner);
+        record_audit(
+            &mut st,
+            caller,
+            now,
+            "reassign_upload",
+            format!("upload {upload_id}: {previous} -> {new_owner}"),
+        );
+        Ok(())
+    })
+}
+
 #[update]
 fn cleanup_expired_uploads_manual() -> Result<u32, String> {
     let caller = msg_caller();
     must_be_admin(caller)?;
 
-    Ok(cleanup_expired_uploads())
+    Ok(cleanup_expired_uploads(ic_cdk::api::time()))
 }
 
 /// ===== Query endpoints =====
 
+#[query]
+fn get_audit_log() -> Result<Vec<AuditEntry>, String> {
+    let caller = msg_caller();
+    must_be_admin(caller)?;
+
+    Ok(STATE.with(|s| s.borrow().audit_log.clone().unwrap_or_default()))
+}
+
 #[query]
 fn get_config() -> Config {
     STATE.with(|s| s.borrow().cfg.clone())
@@ -645,7 +826,34 @@ fn get_upload_info(upload_id: u64) -> Result<UploadInfo, String> {
             return Err("Not the owner of this upload".into());
         }
 
-        Ok(upload.clone())
+        // Report the effective deadline, including for uploads created without one
+        let mut info = upload.clone();
+        info.expires_at_time_ns = Some(upload_expires_at(&st.cfg, upload));
+        Ok(info)
+    })
+}
+
+/// The caller's pending and committed uploads, oldest first.
+#[query]
+fn my_uploads() -> Vec<UploadSummary> {
+    my_uploads_as(msg_caller())
+}
+
+fn my_uploads_as(caller: Principal) -> Vec<UploadSummary> {
+    STATE.with(|s| {
+        let st = s.borrow();
+        st.uploads
+            .iter()
+            .filter(|(_, up)| up.owner == caller)
+            .map(|(&upload_id, up)| UploadSummary {
+                upload_id,
+                label: up.label.clone(),
+                total_len: up.total_len,
+                committed: up.committed_hash.is_some(),
+                created_at_time_ns: up.created_at_time_ns,
+                expires_at_time_ns: upload_expires_at(&st.cfg, up),
+            })
+            .collect()
     })
 }
 
@@ -697,4 +905,127 @@ mod tests {
         assert_eq!(config.max_upload_size, DEFAULT_MAX_UPLOAD_SIZE);
         assert!(!config.emergency_stop);
     }
+
+    #[test]
+    fn short_custom_ttl_expires_before_default() {
+        const SEC: u64 = 1_000_000_000;
+        let upload = |expires_at_time_ns| UploadInfo {
+            owner: Principal::anonymous(),
+            chunks: Vec::new(),
+            total_len: 0,
+            committed_hash: None,
+            created_at_time_ns: 0,
+            expires_at_time_ns,
+            label: None,
+        };
+
+        STATE.with(|s| {
+            let mut st = s.borrow_mut();
+            let short_ttl = effective_upload_ttl_ns(&st.cfg, Some(60));
+            assert_eq!(short_ttl, 60 * SEC);
+            st.uploads.insert(1, upload(Some(short_ttl)));
+            // Created with the default TTL, and one predating per-upload TTLs
+            let default_ttl = effective_upload_ttl_ns(&st.cfg, None);
+            st.uploads.insert(2, upload(Some(default_ttl)));
+            st.uploads.insert(3, upload(None));
+        });
+
+        assert_eq!(cleanup_expired_uploads(120 * SEC), 1);
+        STATE.with(|s| {
+            let st = s.borrow();
+            assert!(!st.uploads.contains_key(&1));
+            assert!(st.uploads.contains_key(&2));
+            assert!(st.uploads.contains_key(&3));
+        });
+
+        // Past the default TTL the others go too
+        assert_eq!(cleanup_expired_uploads(DEFAULT_UPLOAD_TTL_NS + 1), 2);
+    }
+
+    #[test]
+    fn reassigned_upload_is_committed_by_new_owner_only() {
+        let admin = Principal::from_slice(&[1; 29]);
+        let old_owner = Principal::from_slice(&[2; 29]);
+        let new_owner = Principal::from_slice(&[3; 29]);
+        let commit = || UploadCommit {
+            expected_sha256_hex: sha256_hex(b"wasm"),
+        };
+
+        STATE.with(|s| {
+            let mut st = s.borrow_mut();
+            st.cfg.admins.insert(admin);
+            st.uploads.insert(
+                1,
+                UploadInfo {
+                    owner: old_owner,
+                    chunks: vec![b"wasm".to_vec()],
+                    total_len: 4,
+                    committed_hash: None,
+                    created_at_time_ns: 0,
+                    expires_at_time_ns: None,
+                    label: None,
+                },
+            );
+        });
+
+        // Only admins may reassign
+        assert!(reassign_upload_as(old_owner, 5, 1, new_owner).is_err());
+        reassign_upload_as(admin, 5, 1, new_owner).unwrap();
+
+        assert_eq!(
+            commit_upload_as(old_owner, 1, commit()),
+            Err("not owner".to_string())
+        );
+        assert_eq!(
+            commit_upload_as(new_owner, 1, commit()),
+            Ok(sha256_hex(b"wasm"))
+        );
+
+        let log = STATE.with(|s| s.borrow().audit_log.clone().unwrap_or_default());
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].actor, admin);
+        assert_eq!(log[0].action, "reassign_upload");
+    }
+
+    #[test]
+    fn upload_label_is_set_renamed_and_listed() {
+        let owner = Principal::from_slice(&[4; 29]);
+        let other = Principal::from_slice(&[5; 29]);
+
+        let id = create_upload_as(owner, 0, None, Some("  backend-v1.wasm ".to_string())).unwrap();
+        let listed = my_uploads_as(owner);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].upload_id, id);
+        assert_eq!(listed[0].label.as_deref(), Some("backend-v1.wasm"));
+        assert!(my_uploads_as(other).is_empty());
+
+        assert_eq!(
+            set_upload_label_as(other, id, Some("x".to_string())),
+            Err("not owner".to_string())
+        );
+        set_upload_label_as(owner, id, Some("backend-v2.wasm".to_string())).unwrap();
+        let label = STATE.with(|s| s.borrow().uploads[&id].label.clone());
+        assert_eq!(label.as_deref(), Some("backend-v2.wasm"));
+
+        assert!(
+            set_upload_label_as(owner, id, Some("a".repeat(MAX_UPLOAD_LABEL_LEN + 1))).is_err()
+        );
+        set_upload_label_as(owner, id, Some(" ".to_string())).unwrap();
+        assert_eq!(my_uploads_as(owner)[0].label, None);
+    }
+
+    #[test]
+    fn custom_ttl_is_clamped_to_admin_max() {
+        let mut config = Config::default();
+        assert_eq!(
+            effective_upload_ttl_ns(&config, Some(365 * 24 * 60 * 60)),
+            DEFAULT_MAX_UPLOAD_TTL_NS
+        );
+
+        config.max_upload_ttl_ns = Some(3_600 * 1_000_000_000);
+        assert_eq!(
+            effective_upload_ttl_ns(&config, Some(7_200)),
+            3_600 * 1_000_000_000
+        );
+    }
 }